@@ -0,0 +1,180 @@
+//! Reporting of hardware-accelerated code paths
+//!
+//! The AES, ChaCha20 and SHA-2 implementations detect CPU support at runtime
+//! and select accelerated code paths where available, falling back to portable
+//! implementations otherwise. The functions in this module mirror that selection
+//! so that the active backend can be audited.
+//!
+//! The `force_soft` feature restricts SHA-2 to the portable implementation. The
+//! `aes` and `chacha20` crates only expose their portable implementations
+//! through configuration flags rather than Cargo features, so AES and ChaCha20
+//! are restricted by building with
+//! `RUSTFLAGS="--cfg aes_force_soft --cfg chacha20_force_soft"`.
+
+use core::fmt::{self, Display, Formatter};
+
+/// An implementation backend for a cryptographic primitive
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// Portable software implementation
+    Soft,
+    /// x86 AES-NI instructions
+    AesNi,
+    /// x86 SHA extensions
+    ShaNi,
+    /// x86 SSE2 instructions
+    Sse2,
+    /// x86 AVX2 instructions
+    Avx2,
+    /// ARMv8 cryptography extensions
+    Armv8,
+    /// ARM NEON instructions
+    Neon,
+}
+
+impl Backend {
+    /// Get a reference to a string representing the backend
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Soft => "soft",
+            Self::AesNi => "aesni",
+            Self::ShaNi => "shani",
+            Self::Sse2 => "sse2",
+            Self::Avx2 => "avx2",
+            Self::Armv8 => "armv8",
+            Self::Neon => "neon",
+        }
+    }
+
+    /// Check whether the backend uses hardware acceleration
+    pub fn is_accelerated(&self) -> bool {
+        *self != Self::Soft
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(aes_ni_cpuid, "aes", "sse2");
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(avx2_cpuid, "avx2");
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(sse2_cpuid, "sse2");
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(sha_ni_cpuid, "sha", "sse2", "ssse3", "sse4.1");
+
+#[cfg(target_arch = "aarch64")]
+cpufeatures::new!(aes_hwcap, "aes");
+#[cfg(all(feature = "sha2_asm", target_arch = "aarch64"))]
+cpufeatures::new!(sha2_hwcap, "sha2");
+#[cfg(all(feature = "sha2_asm", target_arch = "aarch64"))]
+cpufeatures::new!(sha3_hwcap, "sha3");
+
+/// Determine the backend used for AES block encryption
+pub fn aes_backend() -> Backend {
+    #[cfg(all(not(aes_force_soft), any(target_arch = "x86", target_arch = "x86_64")))]
+    if aes_ni_cpuid::get() {
+        return Backend::AesNi;
+    }
+    #[cfg(all(not(aes_force_soft), target_arch = "aarch64"))]
+    if aes_hwcap::get() {
+        return Backend::Armv8;
+    }
+    Backend::Soft
+}
+
+/// Determine the backend used for the ChaCha20 stream cipher
+pub fn chacha20_backend() -> Backend {
+    #[cfg(all(
+        not(chacha20_force_soft),
+        any(target_arch = "x86", target_arch = "x86_64")
+    ))]
+    {
+        if avx2_cpuid::get() {
+            return Backend::Avx2;
+        }
+        if sse2_cpuid::get() {
+            return Backend::Sse2;
+        }
+    }
+    if cfg!(all(
+        not(chacha20_force_soft),
+        chacha20_force_neon,
+        target_arch = "aarch64"
+    )) {
+        return Backend::Neon;
+    }
+    Backend::Soft
+}
+
+/// Determine the backend used for SHA-224 and SHA-256 hashing
+///
+/// On ARMv8 the accelerated implementation is only available with the
+/// `sha2_asm` feature.
+pub fn sha256_backend() -> Backend {
+    #[cfg(all(
+        not(feature = "force_soft"),
+        any(target_arch = "x86", target_arch = "x86_64")
+    ))]
+    if sha_ni_cpuid::get() {
+        return Backend::ShaNi;
+    }
+    #[cfg(all(
+        not(feature = "force_soft"),
+        feature = "sha2_asm",
+        target_arch = "aarch64"
+    ))]
+    if sha2_hwcap::get() {
+        return Backend::Armv8;
+    }
+    Backend::Soft
+}
+
+/// Determine the backend used for SHA-384 and SHA-512 hashing
+///
+/// On ARMv8 the accelerated implementation is only available with the
+/// `sha2_asm` feature.
+pub fn sha512_backend() -> Backend {
+    #[cfg(all(
+        not(feature = "force_soft"),
+        any(target_arch = "x86", target_arch = "x86_64")
+    ))]
+    if avx2_cpuid::get() {
+        return Backend::Avx2;
+    }
+    #[cfg(all(
+        not(feature = "force_soft"),
+        feature = "sha2_asm",
+        target_arch = "aarch64"
+    ))]
+    if sha3_hwcap::get() {
+        return Backend::Armv8;
+    }
+    Backend::Soft
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backend_report() {
+        for backend in [
+            aes_backend(),
+            chacha20_backend(),
+            sha256_backend(),
+            sha512_backend(),
+        ] {
+            assert_eq!(backend.is_accelerated(), backend.as_str() != "soft");
+        }
+        #[cfg(feature = "force_soft")]
+        {
+            assert_eq!(sha256_backend(), Backend::Soft);
+            assert_eq!(sha512_backend(), Backend::Soft);
+        }
+    }
+}
@@ -78,6 +78,24 @@ where
         buffer: &mut dyn ResizeBuffer,
         nonce: &[u8],
         aad: &[u8],
+    ) -> Result<usize, Error> {
+        self.encrypt_in_place_chunked(buffer, nonce, &[aad])
+    }
+
+    fn decrypt_in_place(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Error> {
+        self.decrypt_in_place_chunked(buffer, nonce, &[aad])
+    }
+
+    fn encrypt_in_place_chunked(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        nonce: &[u8],
+        aad: &[&[u8]],
     ) -> Result<usize, Error> {
         if nonce.len() != NonceSize::<Self>::USIZE {
             return Err(err_msg!(InvalidNonce));
@@ -89,7 +107,8 @@ where
                 "AES-CBC-HMAC tag size exceeds maximum supported"
             ));
         }
-        if aad.len() as u64 > u64::MAX / 8 {
+        let aad_len = aad.iter().map(|chunk| chunk.len() as u64).sum::<u64>();
+        if aad_len > u64::MAX / 8 {
             return Err(err_msg!(
                 Encryption,
                 "AES-CBC-HMAC AAD size exceeds maximum supported"
@@ -107,10 +126,12 @@ where
 
         let mut hmac = <SimpleHmac<D> as Mac>::new_from_slice(&self.0[..C::KeySize::USIZE])
             .expect("Incompatible HMAC key length");
-        hmac.update(aad);
+        for chunk in aad {
+            hmac.update(chunk);
+        }
         hmac.update(nonce.as_ref());
         hmac.update(&buffer.as_ref()[..ctext_end]);
-        hmac.update(&((aad.len() as u64) * 8).to_be_bytes());
+        hmac.update(&(aad_len * 8).to_be_bytes());
         let mac = hmac.finalize().into_bytes();
         buffer.as_mut()[ctext_end..(ctext_end + TagSize::<Self>::USIZE)]
             .copy_from_slice(&mac[..TagSize::<Self>::USIZE]);
@@ -118,16 +139,17 @@ where
         Ok(ctext_end)
     }
 
-    fn decrypt_in_place(
+    fn decrypt_in_place_chunked(
         &self,
         buffer: &mut dyn ResizeBuffer,
         nonce: &[u8],
-        aad: &[u8],
+        aad: &[&[u8]],
     ) -> Result<(), Error> {
         if nonce.len() != NonceSize::<Self>::USIZE {
             return Err(err_msg!(InvalidNonce));
         }
-        if aad.len() as u64 > u64::MAX / 8 {
+        let aad_len = aad.iter().map(|chunk| chunk.len() as u64).sum::<u64>();
+        if aad_len > u64::MAX / 8 {
             return Err(err_msg!(
                 Encryption,
                 "AES-CBC-HMAC AAD size exceeds maximum supported"
@@ -142,10 +164,12 @@ where
 
         let mut hmac = <SimpleHmac<D> as Mac>::new_from_slice(&self.0[..C::KeySize::USIZE])
             .expect("Incompatible HMAC key length");
-        hmac.update(aad);
+        for chunk in aad {
+            hmac.update(chunk);
+        }
         hmac.update(nonce.as_ref());
         hmac.update(&buffer.as_ref()[..ctext_end]);
-        hmac.update(&((aad.len() as u64) * 8).to_be_bytes());
+        hmac.update(&(aad_len * 8).to_be_bytes());
         let mac = hmac.finalize().into_bytes();
         let tag_match = tag.as_ref().ct_eq(&mac[..TagSize::<Self>::USIZE]);
 
@@ -264,4 +288,23 @@ mod tests {
             .unwrap();
         assert_eq!(buffer, &input[..]);
     }
+
+    #[test]
+    fn encrypt_chunked_aad() {
+        let key_data = &hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+        let input = b"Three is a magic number.";
+        let nonce = &hex!("000102030405060708090a0b0c0d0e0f");
+        let aad = b"The second principle of Auguste Kerckhoffs";
+        let key = AesKey::<A128CbcHs256>::from_secret_bytes(key_data).unwrap();
+        let mut expected = SecretBytes::from_slice(input);
+        key.encrypt_in_place(&mut expected, &nonce[..], &aad[..])
+            .unwrap();
+        let mut buffer = SecretBytes::from_slice(input);
+        key.encrypt_in_place_chunked(&mut buffer, &nonce[..], &[&aad[..11], &[], &aad[11..]])
+            .unwrap();
+        assert_eq!(buffer, expected);
+        key.decrypt_in_place_chunked(&mut buffer, &nonce[..], &[&aad[..20], &aad[20..]])
+            .unwrap();
+        assert_eq!(buffer, &input[..]);
+    }
 }
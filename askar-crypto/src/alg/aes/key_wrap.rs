@@ -4,7 +4,7 @@ use core::marker::PhantomData;
 
 use aes_core::{
     cipher::{BlockCipher, BlockDecrypt, BlockEncrypt, KeyInit, KeySizeUser},
-    Aes128, Aes256,
+    Aes128, Aes192, Aes256,
 };
 use subtle::ConstantTimeEq;
 
@@ -31,6 +31,15 @@ impl AesType for A128Kw {
     const JWK_ALG: &'static str = "A128KW";
 }
 
+/// 192 bit AES Key Wrap
+pub type A192Kw = AesKeyWrap<Aes192>;
+
+impl AesType for A192Kw {
+    type KeySize = <Aes192 as KeySizeUser>::KeySize;
+    const ALG_TYPE: AesTypes = AesTypes::A192Kw;
+    const JWK_ALG: &'static str = "A192KW";
+}
+
 /// 256 bit AES Key Wrap
 pub type A256Kw = AesKeyWrap<Aes256>;
 
@@ -95,7 +104,7 @@ where
                 aes.encrypt_block(&mut block);
                 let t = (((blocks * j) + i + 1) as u64).to_be_bytes();
                 iv.copy_from_slice(&block[0..8]);
-                for (a, t) in iv.as_mut().iter_mut().zip(&t[..]) {
+                for (a, t) in iv.iter_mut().zip(&t[..]) {
                     *a ^= t;
                 }
                 chunk.copy_from_slice(&block[8..16]);
@@ -186,6 +195,24 @@ mod tests {
         assert_eq!(buffer, &input[..]);
     }
 
+    #[test]
+    // from RFC 3394 test vectors
+    fn key_wrap_192_expected() {
+        let key = AesKey::<A192Kw>::from_secret_bytes(&hex!(
+            "000102030405060708090A0B0C0D0E0F1011121314151617"
+        ))
+        .unwrap();
+        let input = &hex!("00112233445566778899aabbccddeeff");
+        let mut buffer = SecretBytes::from_slice(input);
+        key.encrypt_in_place(&mut buffer, &[], &[]).unwrap();
+        assert_eq!(
+            buffer.as_hex().to_string(),
+            "96778b25ae6ca435f92b5b97c050aed2468ab8a17ad84e5d"
+        );
+        key.decrypt_in_place(&mut buffer, &[], &[]).unwrap();
+        assert_eq!(buffer, &input[..]);
+    }
+
     #[test]
     // from RFC 3394 test vectors
     fn key_wrap_256_expected() {
@@ -4,13 +4,15 @@ use core::fmt::{self, Debug, Formatter};
 
 use aead::{generic_array::ArrayLength, AeadCore, AeadInPlace, KeyInit, KeySizeUser};
 use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use aes_gcm_siv::{Aes128GcmSiv, Aes256GcmSiv};
+use aes_siv::{siv, Aes128SivAead, Aes256SivAead};
 use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
 use super::{AesTypes, HasKeyAlg, HasKeyBackend, KeyAlg};
 use crate::{
     buffer::{ArrayKey, ResizeBuffer, Writer},
-    encrypt::{KeyAeadInPlace, KeyAeadMeta, KeyAeadParams},
+    encrypt::{KeyAeadDeterministic, KeyAeadInPlace, KeyAeadMeta, KeyAeadParams},
     error::Error,
     generic_array::{typenum::Unsigned, GenericArray},
     jwk::{JwkEncoder, ToJwk},
@@ -19,11 +21,20 @@ use crate::{
     repr::{KeyGen, KeyMeta, KeySecretBytes},
 };
 
+#[cfg(feature = "mac")]
+use crate::{
+    buffer::WriteBuffer,
+    mac::{self, KeyMac, MacType},
+};
+
+#[cfg(feature = "unauthenticated")]
+use crate::unauthenticated::{self, KeyUnauthenticatedCipher};
+
 mod cbc_hmac;
 pub use cbc_hmac::{A128CbcHs256, A256CbcHs512};
 
 mod key_wrap;
-pub use key_wrap::{A128Kw, A256Kw};
+pub use key_wrap::{A128Kw, A192Kw, A256Kw};
 
 /// The 'kty' value of a symmetric key JWK
 pub static JWK_KEY_TYPE: &str = "oct";
@@ -175,7 +186,50 @@ impl AesType for A256Gcm {
     const JWK_ALG: &'static str = "A256GCM";
 }
 
-// generic implementation applying to AesGcm
+/// 128 bit AES-GCM-SIV
+pub type A128GcmSiv = Aes128GcmSiv;
+
+impl AesType for A128GcmSiv {
+    type KeySize = <Self as KeySizeUser>::KeySize;
+
+    const ALG_TYPE: AesTypes = AesTypes::A128GcmSiv;
+    const JWK_ALG: &'static str = "A128GCM-SIV";
+}
+
+/// 256 bit AES-GCM-SIV
+pub type A256GcmSiv = Aes256GcmSiv;
+
+impl AesType for A256GcmSiv {
+    type KeySize = <Self as KeySizeUser>::KeySize;
+
+    const ALG_TYPE: AesTypes = AesTypes::A256GcmSiv;
+    const JWK_ALG: &'static str = "A256GCM-SIV";
+}
+
+/// 128 bit AES-SIV (AES-SIV-CMAC-256)
+pub type A128Siv = Aes128SivAead;
+
+impl AesType for A128Siv {
+    type KeySize = <Self as KeySizeUser>::KeySize;
+
+    const ALG_TYPE: AesTypes = AesTypes::A128Siv;
+    const JWK_ALG: &'static str = "A128SIV";
+}
+
+/// 256 bit AES-SIV (AES-SIV-CMAC-512)
+pub type A256Siv = Aes256SivAead;
+
+impl AesType for A256Siv {
+    type KeySize = <Self as KeySizeUser>::KeySize;
+
+    const ALG_TYPE: AesTypes = AesTypes::A256Siv;
+    const JWK_ALG: &'static str = "A256SIV";
+}
+
+/// The length of the synthetic IV prepended by deterministic AES-SIV encryption
+pub const SIV_TAG_LENGTH: usize = 16;
+
+// generic implementation applying to AesGcm, AesGcmSiv and AesSiv
 impl<T: AeadCore + AesType> KeyAeadMeta for AesKey<T> {
     type NonceSize = <T as AeadCore>::NonceSize;
     type TagSize = <T as AeadCore>::TagSize;
@@ -234,6 +288,50 @@ where
         Ok(())
     }
 
+    fn encrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        nonce: &[u8],
+        aad: &[u8],
+        tag: &mut [u8],
+    ) -> Result<(), Error> {
+        if nonce.len() != T::NonceSize::USIZE {
+            return Err(err_msg!(InvalidNonce));
+        }
+        if tag.len() != T::TagSize::USIZE {
+            return Err(err_msg!(Usage, "Invalid size for verification tag"));
+        }
+        let enc = <T as KeyInit>::new(self.0.as_ref());
+        let result = enc
+            .encrypt_in_place_detached(GenericArray::from_slice(nonce), aad, buffer)
+            .map_err(|_| err_msg!(Encryption, "AEAD encryption error"))?;
+        tag.copy_from_slice(&result[..]);
+        Ok(())
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        nonce: &[u8],
+        aad: &[u8],
+        tag: &[u8],
+    ) -> Result<(), Error> {
+        if nonce.len() != T::NonceSize::USIZE {
+            return Err(err_msg!(InvalidNonce));
+        }
+        if tag.len() != T::TagSize::USIZE {
+            return Err(err_msg!(Encryption, "Invalid size for verification tag"));
+        }
+        let enc = <T as KeyInit>::new(self.0.as_ref());
+        enc.decrypt_in_place_detached(
+            GenericArray::from_slice(nonce),
+            aad,
+            buffer,
+            GenericArray::from_slice(tag),
+        )
+        .map_err(|_| err_msg!(Encryption, "AEAD decryption error"))
+    }
+
     fn aead_params(&self) -> KeyAeadParams {
         KeyAeadParams {
             nonce_length: T::NonceSize::USIZE,
@@ -242,6 +340,148 @@ where
     }
 }
 
+macro_rules! with_aes_siv {
+    ($alg:expr, $siv:ident => $body:expr) => {
+        match $alg {
+            AesTypes::A128Siv => {
+                type $siv = siv::Aes128Siv;
+                $body
+            }
+            AesTypes::A256Siv => {
+                type $siv = siv::Aes256Siv;
+                $body
+            }
+            _ => Err(err_msg!(
+                Unsupported,
+                "Deterministic encryption not supported for this key type"
+            )),
+        }
+    };
+}
+
+impl<T: AesType> KeyAeadDeterministic for AesKey<T> {
+    fn encrypt_deterministic(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[&[u8]],
+    ) -> Result<(), Error> {
+        with_aes_siv!(T::ALG_TYPE, S => {
+            let mut enc = <S as KeyInit>::new_from_slice(self.0.as_ref())
+                .map_err(|_| err_msg!(InvalidKeyData))?;
+            let tag = enc
+                .encrypt_in_place_detached(aad, buffer.as_mut())
+                .map_err(|_| err_msg!(Encryption, "AES-SIV encryption error"))?;
+            buffer.buffer_insert(0, &tag[..])
+        })
+    }
+
+    fn decrypt_deterministic(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[&[u8]],
+    ) -> Result<(), Error> {
+        with_aes_siv!(T::ALG_TYPE, S => {
+            let buf_len = buffer.as_ref().len();
+            if buf_len < SIV_TAG_LENGTH {
+                return Err(err_msg!(Encryption, "Invalid size for encrypted data"));
+            }
+            let tag = aes_siv::Tag::clone_from_slice(&buffer.as_ref()[..SIV_TAG_LENGTH]);
+            let mut enc = <S as KeyInit>::new_from_slice(self.0.as_ref())
+                .map_err(|_| err_msg!(InvalidKeyData))?;
+            enc.decrypt_in_place_detached(aad, &mut buffer.as_mut()[SIV_TAG_LENGTH..], &tag)
+                .map_err(|_| err_msg!(Encryption, "AES-SIV decryption error"))?;
+            buffer.buffer_remove(0..SIV_TAG_LENGTH)
+        })
+    }
+}
+
+#[cfg(feature = "mac")]
+impl<T: AesType> KeyMac for AesKey<T> {
+    fn write_mac(
+        &self,
+        message: &[u8],
+        mac_type: MacType,
+        customization: &[u8],
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let key = self.0.as_ref();
+        match mac_type {
+            MacType::Cmac => {
+                if !customization.is_empty() {
+                    return Err(err_msg!(Usage, "Customization not supported for CMAC"));
+                }
+                match T::ALG_TYPE {
+                    AesTypes::A128Gcm | AesTypes::A128GcmSiv | AesTypes::A128Kw => {
+                        mac::cmac::<aes_core::Aes128>(key, message, out)
+                    }
+                    AesTypes::A192Kw => mac::cmac::<aes_core::Aes192>(key, message, out),
+                    AesTypes::A256Gcm | AesTypes::A256GcmSiv | AesTypes::A256Kw => {
+                        mac::cmac::<aes_core::Aes256>(key, message, out)
+                    }
+                    _ => Err(err_msg!(
+                        Unsupported,
+                        "CMAC not supported for this key type"
+                    )),
+                }
+            }
+            _ => mac::keyed_mac(mac_type, key, message, customization, out),
+        }
+    }
+}
+
+#[cfg(feature = "unauthenticated")]
+macro_rules! with_aes_cipher {
+    ($alg:expr, $cipher:ident => $body:expr) => {
+        match $alg {
+            AesTypes::A128Gcm | AesTypes::A128GcmSiv | AesTypes::A128Kw => {
+                type $cipher = aes_core::Aes128;
+                $body
+            }
+            AesTypes::A192Kw => {
+                type $cipher = aes_core::Aes192;
+                $body
+            }
+            AesTypes::A256Gcm | AesTypes::A256GcmSiv | AesTypes::A256Kw => {
+                type $cipher = aes_core::Aes256;
+                $body
+            }
+            _ => Err(err_msg!(
+                Unsupported,
+                "Unauthenticated encryption not supported for this key type"
+            )),
+        }
+    };
+}
+
+#[cfg(feature = "unauthenticated")]
+impl<T: AesType> KeyUnauthenticatedCipher for AesKey<T> {
+    fn apply_ctr_unauthenticated(&self, buffer: &mut [u8], iv: &[u8]) -> Result<(), Error> {
+        with_aes_cipher!(T::ALG_TYPE, C => {
+            unauthenticated::ctr_apply::<C>(self.0.as_ref(), iv, buffer)
+        })
+    }
+
+    fn encrypt_cbc_unauthenticated(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        iv: &[u8],
+    ) -> Result<(), Error> {
+        with_aes_cipher!(T::ALG_TYPE, C => {
+            unauthenticated::cbc_encrypt::<C>(self.0.as_ref(), iv, buffer)
+        })
+    }
+
+    fn decrypt_cbc_unauthenticated(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        iv: &[u8],
+    ) -> Result<(), Error> {
+        with_aes_cipher!(T::ALG_TYPE, C => {
+            unauthenticated::cbc_decrypt::<C>(self.0.as_ref(), iv, buffer)
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -278,6 +518,10 @@ mod tests {
         test_encrypt::<A256Gcm>();
         test_encrypt::<A128CbcHs256>();
         test_encrypt::<A256CbcHs512>();
+        test_encrypt::<A128GcmSiv>();
+        test_encrypt::<A256GcmSiv>();
+        test_encrypt::<A128Siv>();
+        test_encrypt::<A256Siv>();
     }
 
     #[test]
@@ -306,6 +550,113 @@ mod tests {
         test_serialize::<A128CbcHs256>();
         test_serialize::<A256CbcHs512>();
         test_serialize::<A128Kw>();
+        test_serialize::<A192Kw>();
         test_serialize::<A256Kw>();
+        test_serialize::<A128GcmSiv>();
+        test_serialize::<A256GcmSiv>();
+        test_serialize::<A128Siv>();
+        test_serialize::<A256Siv>();
+    }
+
+    #[test]
+    fn gcm_siv_expected() {
+        // from RFC 8452 Appendix C.1
+        let key =
+            AesKey::<A128GcmSiv>::from_secret_bytes(&hex!("01000000000000000000000000000000"))
+                .unwrap();
+        let nonce = hex!("030000000000000000000000");
+        let mut buffer = SecretBytes::from_slice(&hex!("0100000000000000"));
+        key.encrypt_in_place(&mut buffer, &nonce, &[]).unwrap();
+        assert_eq!(
+            &buffer[..],
+            &hex!("b5d839330ac7b786578782fff6013b815b287c22493a364c")[..]
+        );
+    }
+
+    #[test]
+    fn siv_expected() {
+        // from RFC 5297 Appendix A.1
+        let key = AesKey::<A128Siv>::from_secret_bytes(&hex!(
+            "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff"
+        ))
+        .unwrap();
+        let aad = hex!("101112131415161718191a1b1c1d1e1f2021222324252627");
+        let input = hex!("112233445566778899aabbccddee");
+        let mut buffer = SecretBytes::from_slice(&input);
+        key.encrypt_deterministic(&mut buffer, &[&aad]).unwrap();
+        assert_eq!(
+            &buffer[..],
+            &hex!("85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c")[..]
+        );
+        let mut dec = buffer.clone();
+        key.decrypt_deterministic(&mut dec, &[&aad]).unwrap();
+        assert_eq!(&dec[..], &input[..]);
+        buffer.as_mut()[0] ^= 1;
+        assert!(key.decrypt_deterministic(&mut buffer, &[&aad]).is_err());
+
+        let key = AesKey::<A256Gcm>::random().unwrap();
+        assert!(key
+            .encrypt_deterministic(&mut SecretBytes::from_slice(&input), &[])
+            .is_err());
+    }
+
+    #[cfg(feature = "mac")]
+    #[test]
+    // from RFC 4493 section 4, example 2
+    fn cmac_expected() {
+        let key =
+            AesKey::<A128Kw>::from_secret_bytes(&hex!("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        let message = hex!("6bc1bee22e409f96e93d7e117393172a");
+        let tag = key.create_mac(&message, MacType::Cmac, b"").unwrap();
+        assert_eq!(&tag[..], &hex!("070a16b46b4d4144f79bdd9dd04a287c"));
+        assert!(key
+            .verify_mac(&message, &tag[..], MacType::Cmac, b"")
+            .unwrap());
+        assert!(!key
+            .verify_mac(b"other", &tag[..], MacType::Cmac, b"")
+            .unwrap());
+        assert!(key.create_mac(&message, MacType::Cmac, b"custom").is_err());
+
+        let kmac = key
+            .create_mac(&message, MacType::Kmac128, b"custom")
+            .unwrap();
+        assert_eq!(kmac.len(), 32);
+
+        let cbc_key = AesKey::<A128CbcHs256>::random().unwrap();
+        assert!(cbc_key.create_mac(&message, MacType::Cmac, b"").is_err());
+    }
+
+    #[cfg(feature = "unauthenticated")]
+    #[test]
+    // from NIST SP 800-38A, sections F.2.1 and F.5.1
+    fn unauthenticated_expected() {
+        let key =
+            AesKey::<A128Kw>::from_secret_bytes(&hex!("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        let message = hex!("6bc1bee22e409f96e93d7e117393172a");
+
+        let iv = hex!("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let mut buffer = message;
+        key.apply_ctr_unauthenticated(&mut buffer, &iv).unwrap();
+        assert_eq!(buffer, hex!("874d6191b620e3261bef6864990db6ce"));
+        key.apply_ctr_unauthenticated(&mut buffer, &iv).unwrap();
+        assert_eq!(buffer, message);
+
+        let iv = hex!("000102030405060708090a0b0c0d0e0f");
+        let mut buffer = SecretBytes::from_slice(&message);
+        key.encrypt_cbc_unauthenticated(&mut buffer, &iv).unwrap();
+        assert_eq!(
+            &buffer[..],
+            &hex!("7649abac8119b246cee98e9b12e9197d8964e0b149c10b7b682e6e39aaeb731c")
+        );
+        key.decrypt_cbc_unauthenticated(&mut buffer, &iv).unwrap();
+        assert_eq!(&buffer[..], &message);
+        assert!(key
+            .decrypt_cbc_unauthenticated(&mut buffer, &iv[..8])
+            .is_err());
+
+        let cbc_key = AesKey::<A128CbcHs256>::random().unwrap();
+        assert!(cbc_key
+            .apply_ctr_unauthenticated(buffer.as_mut(), &iv)
+            .is_err());
     }
 }
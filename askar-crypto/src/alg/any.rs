@@ -7,7 +7,10 @@ use core::{
 
 #[cfg(feature = "aes")]
 use super::{
-    aes::{A128CbcHs256, A128Gcm, A128Kw, A256CbcHs512, A256Gcm, A256Kw, AesKey},
+    aes::{
+        A128CbcHs256, A128Gcm, A128GcmSiv, A128Kw, A128Siv, A192Kw, A256CbcHs512, A256Gcm,
+        A256GcmSiv, A256Kw, A256Siv, AesKey,
+    },
     AesTypes,
 };
 
@@ -27,6 +30,19 @@ use super::{
 use super::ed25519::{self, Ed25519KeyPair};
 #[cfg(feature = "ed25519")]
 use super::x25519::{self, X25519KeyPair};
+#[cfg(feature = "hmac")]
+use super::{
+    hmac::{self, HmacKey, HS256, HS384, HS512},
+    HmacTypes,
+};
+
+#[cfg(feature = "ml_kem")]
+use super::x25519_ml_kem::X25519MlKem768KeyPair;
+
+#[cfg(feature = "ed448")]
+use super::ed448::{self, Ed448KeyPair};
+#[cfg(feature = "ed448")]
+use super::x448::{self, X448KeyPair};
 
 #[cfg(feature = "k256")]
 use super::k256::{self, K256KeyPair};
@@ -40,17 +56,32 @@ use super::p384::{self, P384KeyPair};
 #[cfg(feature = "p256_hardware")]
 use super::p256_hardware::P256HardwareKeyPair;
 
+#[cfg(feature = "ml_dsa")]
+use super::{
+    ml_dsa::{self, MlDsaKeyPair},
+    MlDsaTypes,
+};
+
+#[cfg(feature = "rsa")]
+use super::{
+    rsa::{self, RsaKeyPair},
+    RsaTypes,
+};
+
+#[cfg(feature = "slh_dsa")]
+use super::slh_dsa::{self, SlhDsaKeyPair};
+
 use super::{HasKeyAlg, HasKeyBackend, KeyAlg};
 use crate::{
     backend::KeyBackend,
     buffer::{ResizeBuffer, SecretBytes, WriteBuffer},
-    encrypt::{KeyAeadInPlace, KeyAeadParams},
+    encrypt::{KeyAeadDeterministic, KeyAeadInPlace, KeyAeadParams},
     error::Error,
     jwk::{FromJwk, JwkEncoder, JwkParts, ToJwk},
     kdf::{KeyDerivation, KeyExchange},
     random::KeyMaterial,
     repr::{KeyGen, KeyPublicBytes, KeySecretBytes, ToPublicBytes, ToSecretBytes},
-    sign::{KeySigVerify, KeySign, SignatureType},
+    sign::{KeySigVerify, KeySign, SignatureFormat, SignatureType},
 };
 
 #[cfg(any(
@@ -61,12 +92,55 @@ use crate::{
 ))]
 use super::EcCurves;
 
+#[cfg(any(feature = "aes", feature = "chacha", feature = "hmac"))]
+use crate::kdf::FromKeyDerivation;
 #[cfg(any(feature = "aes", feature = "chacha"))]
-use crate::kdf::{FromKeyDerivation, FromKeyExchange};
+use crate::kdf::FromKeyExchange;
+
+#[cfg(feature = "cose")]
+use crate::cose::{CoseKeyEncoder, CoseKeyParts, FromCoseKey, ToCoseKey};
+
+#[cfg(feature = "mac")]
+use crate::mac::{KeyMac, MacType};
+
+#[cfg(feature = "unauthenticated")]
+use crate::unauthenticated::KeyUnauthenticatedCipher;
+
+#[cfg(feature = "multikey")]
+use crate::multikey::{decode_multikey, encode_multikey, FromMultikey, ToMultikey};
+#[cfg(feature = "multikey")]
+use alloc::string::String;
+
+#[cfg(feature = "pkcs8")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "pkcs8")]
+use crate::pkcs8::{pkcs8_key_alg, public_key_alg, FromPkcs8, ToPkcs8};
 
 #[derive(Debug)]
 pub struct KeyT<T: AnyKeyAlg + Send + Sync + RefUnwindSafe + UnwindSafe + ?Sized>(T);
 
+#[cfg(feature = "mlock")]
+impl<T: AnyKeyAlg + Send + Sync + RefUnwindSafe + UnwindSafe + ?Sized> KeyT<T> {
+    /// Lock the pages holding the key, which must not be moved afterwards
+    fn protect(&self) {
+        crate::buffer::protect::protect_region(
+            self as *const Self as *const u8,
+            core::mem::size_of_val(self),
+        );
+    }
+}
+
+#[cfg(feature = "mlock")]
+impl<T: AnyKeyAlg + Send + Sync + RefUnwindSafe + UnwindSafe + ?Sized> Drop for KeyT<T> {
+    fn drop(&mut self) {
+        crate::buffer::protect::unprotect_region(
+            self as *const Self as *const u8,
+            core::mem::size_of_val(self),
+        );
+    }
+}
+
 /// The type-erased representation for a concrete key instance
 pub type AnyKey = KeyT<dyn AnyKeyAlg + Send + Sync + RefUnwindSafe + UnwindSafe>;
 
@@ -111,9 +185,9 @@ pub trait AnyKeyCreate: Sized {
     fn get_with_id(alg: KeyAlg, id: &str) -> Result<Self, Error>;
 
     /// Generate a new random key for the given key algorithm.
-    #[cfg(feature = "getrandom")]
+    #[cfg(feature = "default_rng")]
     fn random(alg: KeyAlg) -> Result<Self, Error> {
-        Self::generate_with_rng(alg, crate::random::default_rng())
+        Self::generate_with_rng(alg, crate::random::try_default_rng()?)
     }
 
     /// Generate a new random key for the given key algorithm.
@@ -172,7 +246,10 @@ impl AnyKeyCreate for Box<AnyKey> {
     >(
         key: K,
     ) -> Self {
-        Box::new(KeyT(key))
+        let key = Box::new(KeyT(key));
+        #[cfg(feature = "mlock")]
+        key.protect();
+        key
     }
 
     fn from_key_exchange<Sk, Pk>(alg: KeyAlg, secret: &Sk, public: &Pk) -> Result<Self, Error>
@@ -219,7 +296,10 @@ impl AnyKeyCreate for Arc<AnyKey> {
     >(
         key: K,
     ) -> Self {
-        Arc::new(KeyT(key))
+        let key = Arc::new(KeyT(key));
+        #[cfg(feature = "mlock")]
+        key.protect();
+        key
     }
 
     fn from_key_exchange<Sk, Pk>(alg: KeyAlg, secret: &Sk, public: &Pk) -> Result<Self, Error>
@@ -257,7 +337,17 @@ fn generate_any_with_rng<R: AllocKey>(alg: KeyAlg, rng: impl KeyMaterial) -> Res
         #[cfg(feature = "aes")]
         KeyAlg::Aes(AesTypes::A128Kw) => AesKey::<A128Kw>::generate(rng).map(R::alloc_key),
         #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A192Kw) => AesKey::<A192Kw>::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "aes")]
         KeyAlg::Aes(AesTypes::A256Kw) => AesKey::<A256Kw>::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A128GcmSiv) => AesKey::<A128GcmSiv>::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A256GcmSiv) => AesKey::<A256GcmSiv>::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A128Siv) => AesKey::<A128Siv>::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A256Siv) => AesKey::<A256Siv>::generate(rng).map(R::alloc_key),
         #[cfg(feature = "bls")]
         KeyAlg::Bls12_381(BlsCurves::G1) => BlsKeyPair::<G1>::generate(rng).map(R::alloc_key),
         #[cfg(feature = "bls")]
@@ -274,14 +364,32 @@ fn generate_any_with_rng<R: AllocKey>(alg: KeyAlg, rng: impl KeyMaterial) -> Res
         }
         #[cfg(feature = "ed25519")]
         KeyAlg::Ed25519 => Ed25519KeyPair::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "hmac")]
+        KeyAlg::Hmac(HmacTypes::HS256) => HmacKey::<HS256>::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "hmac")]
+        KeyAlg::Hmac(HmacTypes::HS384) => HmacKey::<HS384>::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "hmac")]
+        KeyAlg::Hmac(HmacTypes::HS512) => HmacKey::<HS512>::generate(rng).map(R::alloc_key),
         #[cfg(feature = "ed25519")]
         KeyAlg::X25519 => X25519KeyPair::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "ml_kem")]
+        KeyAlg::X25519MlKem768 => X25519MlKem768KeyPair::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        KeyAlg::Ed448 => Ed448KeyPair::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        KeyAlg::X448 => X448KeyPair::generate(rng).map(R::alloc_key),
         #[cfg(feature = "k256")]
         KeyAlg::EcCurve(EcCurves::Secp256k1) => K256KeyPair::generate(rng).map(R::alloc_key),
         #[cfg(feature = "p256")]
         KeyAlg::EcCurve(EcCurves::Secp256r1) => P256KeyPair::generate(rng).map(R::alloc_key),
         #[cfg(feature = "p384")]
         KeyAlg::EcCurve(EcCurves::Secp384r1) => P384KeyPair::generate(rng).map(R::alloc_key),
+        #[cfg(feature = "rsa")]
+        KeyAlg::Rsa(size) => RsaKeyPair::generate(size, rng).map(R::alloc_key),
+        #[cfg(feature = "ml_dsa")]
+        KeyAlg::MlDsa(size) => MlDsaKeyPair::generate(size, rng).map(R::alloc_key),
+        #[cfg(feature = "slh_dsa")]
+        KeyAlg::SlhDsa(size) => SlhDsaKeyPair::generate(size, rng).map(R::alloc_key),
         #[allow(unreachable_patterns)]
         _ => Err(err_msg!(
             Unsupported,
@@ -340,6 +448,14 @@ fn from_public_bytes_any<R: AllocKey>(alg: KeyAlg, public: &[u8]) -> Result<R, E
         KeyAlg::Ed25519 => Ed25519KeyPair::from_public_bytes(public).map(R::alloc_key),
         #[cfg(feature = "ed25519")]
         KeyAlg::X25519 => X25519KeyPair::from_public_bytes(public).map(R::alloc_key),
+        #[cfg(feature = "ml_kem")]
+        KeyAlg::X25519MlKem768 => {
+            X25519MlKem768KeyPair::from_public_bytes(public).map(R::alloc_key)
+        }
+        #[cfg(feature = "ed448")]
+        KeyAlg::Ed448 => Ed448KeyPair::from_public_bytes(public).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        KeyAlg::X448 => X448KeyPair::from_public_bytes(public).map(R::alloc_key),
         #[cfg(feature = "k256")]
         KeyAlg::EcCurve(EcCurves::Secp256k1) => {
             K256KeyPair::from_public_bytes(public).map(R::alloc_key)
@@ -352,6 +468,16 @@ fn from_public_bytes_any<R: AllocKey>(alg: KeyAlg, public: &[u8]) -> Result<R, E
         KeyAlg::EcCurve(EcCurves::Secp384r1) => {
             P384KeyPair::from_public_bytes(public).map(R::alloc_key)
         }
+        #[cfg(feature = "rsa")]
+        KeyAlg::Rsa(size) => RsaKeyPair::from_public_bytes(public)
+            .and_then(|k| check_rsa_size(k, size))
+            .map(R::alloc_key),
+        #[cfg(feature = "ml_dsa")]
+        KeyAlg::MlDsa(size) => MlDsaKeyPair::from_public_bytes(public)
+            .and_then(|k| check_ml_dsa_size(k, size))
+            .map(R::alloc_key),
+        #[cfg(feature = "slh_dsa")]
+        KeyAlg::SlhDsa(size) => SlhDsaKeyPair::from_public_bytes(size, public).map(R::alloc_key),
         #[allow(unreachable_patterns)]
         _ => Err(err_msg!(
             Unsupported,
@@ -384,9 +510,29 @@ fn from_secret_bytes_any<R: AllocKey>(alg: KeyAlg, secret: &[u8]) -> Result<R, E
             AesKey::<A128Kw>::from_secret_bytes(secret).map(R::alloc_key)
         }
         #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A192Kw) => {
+            AesKey::<A192Kw>::from_secret_bytes(secret).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
         KeyAlg::Aes(AesTypes::A256Kw) => {
             AesKey::<A256Kw>::from_secret_bytes(secret).map(R::alloc_key)
         }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A128GcmSiv) => {
+            AesKey::<A128GcmSiv>::from_secret_bytes(secret).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A256GcmSiv) => {
+            AesKey::<A256GcmSiv>::from_secret_bytes(secret).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A128Siv) => {
+            AesKey::<A128Siv>::from_secret_bytes(secret).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A256Siv) => {
+            AesKey::<A256Siv>::from_secret_bytes(secret).map(R::alloc_key)
+        }
         #[cfg(feature = "bls")]
         KeyAlg::Bls12_381(BlsCurves::G1) => {
             BlsKeyPair::<G1>::from_secret_bytes(secret).map(R::alloc_key)
@@ -409,8 +555,28 @@ fn from_secret_bytes_any<R: AllocKey>(alg: KeyAlg, secret: &[u8]) -> Result<R, E
         }
         #[cfg(feature = "ed25519")]
         KeyAlg::Ed25519 => Ed25519KeyPair::from_secret_bytes(secret).map(R::alloc_key),
+        #[cfg(feature = "hmac")]
+        KeyAlg::Hmac(HmacTypes::HS256) => {
+            HmacKey::<HS256>::from_secret_bytes(secret).map(R::alloc_key)
+        }
+        #[cfg(feature = "hmac")]
+        KeyAlg::Hmac(HmacTypes::HS384) => {
+            HmacKey::<HS384>::from_secret_bytes(secret).map(R::alloc_key)
+        }
+        #[cfg(feature = "hmac")]
+        KeyAlg::Hmac(HmacTypes::HS512) => {
+            HmacKey::<HS512>::from_secret_bytes(secret).map(R::alloc_key)
+        }
         #[cfg(feature = "ed25519")]
         KeyAlg::X25519 => X25519KeyPair::from_secret_bytes(secret).map(R::alloc_key),
+        #[cfg(feature = "ml_kem")]
+        KeyAlg::X25519MlKem768 => {
+            X25519MlKem768KeyPair::from_secret_bytes(secret).map(R::alloc_key)
+        }
+        #[cfg(feature = "ed448")]
+        KeyAlg::Ed448 => Ed448KeyPair::from_secret_bytes(secret).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        KeyAlg::X448 => X448KeyPair::from_secret_bytes(secret).map(R::alloc_key),
         #[cfg(feature = "k256")]
         KeyAlg::EcCurve(EcCurves::Secp256k1) => {
             K256KeyPair::from_secret_bytes(secret).map(R::alloc_key)
@@ -423,6 +589,14 @@ fn from_secret_bytes_any<R: AllocKey>(alg: KeyAlg, secret: &[u8]) -> Result<R, E
         KeyAlg::EcCurve(EcCurves::Secp384r1) => {
             P384KeyPair::from_secret_bytes(secret).map(R::alloc_key)
         }
+        #[cfg(feature = "rsa")]
+        KeyAlg::Rsa(size) => RsaKeyPair::from_secret_bytes(secret)
+            .and_then(|k| check_rsa_size(k, size))
+            .map(R::alloc_key),
+        #[cfg(feature = "ml_dsa")]
+        KeyAlg::MlDsa(size) => MlDsaKeyPair::from_secret_bytes(size, secret).map(R::alloc_key),
+        #[cfg(feature = "slh_dsa")]
+        KeyAlg::SlhDsa(size) => SlhDsaKeyPair::from_secret_bytes(size, secret).map(R::alloc_key),
         #[allow(unreachable_patterns)]
         _ => Err(err_msg!(
             Unsupported,
@@ -431,6 +605,26 @@ fn from_secret_bytes_any<R: AllocKey>(alg: KeyAlg, secret: &[u8]) -> Result<R, E
     }
 }
 
+#[cfg(feature = "rsa")]
+#[inline]
+fn check_rsa_size(key: RsaKeyPair, size: RsaTypes) -> Result<RsaKeyPair, Error> {
+    if key.key_size() == size {
+        Ok(key)
+    } else {
+        Err(err_msg!(InvalidKeyData, "RSA key size mismatch"))
+    }
+}
+
+#[cfg(feature = "ml_dsa")]
+#[inline]
+fn check_ml_dsa_size(key: MlDsaKeyPair, size: MlDsaTypes) -> Result<MlDsaKeyPair, Error> {
+    if key.key_size() == size {
+        Ok(key)
+    } else {
+        Err(err_msg!(InvalidKeyData, "ML-DSA key size mismatch"))
+    }
+}
+
 #[cfg(any(feature = "aes", feature = "chacha"))]
 #[inline]
 fn from_key_exchange_any<R, Sk, Pk>(alg: KeyAlg, secret: &Sk, public: &Pk) -> Result<R, Error>
@@ -461,9 +655,29 @@ where
             AesKey::<A128Kw>::from_key_exchange(secret, public).map(R::alloc_key)
         }
         #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A192Kw) => {
+            AesKey::<A192Kw>::from_key_exchange(secret, public).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
         KeyAlg::Aes(AesTypes::A256Kw) => {
             AesKey::<A256Kw>::from_key_exchange(secret, public).map(R::alloc_key)
         }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A128GcmSiv) => {
+            AesKey::<A128GcmSiv>::from_key_exchange(secret, public).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A256GcmSiv) => {
+            AesKey::<A256GcmSiv>::from_key_exchange(secret, public).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A128Siv) => {
+            AesKey::<A128Siv>::from_key_exchange(secret, public).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A256Siv) => {
+            AesKey::<A256Siv>::from_key_exchange(secret, public).map(R::alloc_key)
+        }
         #[cfg(feature = "chacha")]
         KeyAlg::Chacha20(Chacha20Types::C20P) => {
             Chacha20Key::<C20P>::from_key_exchange(secret, public).map(R::alloc_key)
@@ -493,7 +707,7 @@ fn from_key_exchange_any<R, Sk: ?Sized, Pk: ?Sized>(
     ));
 }
 
-#[cfg(any(feature = "aes", feature = "chacha"))]
+#[cfg(any(feature = "aes", feature = "chacha", feature = "hmac"))]
 #[inline]
 fn from_key_derivation_any<R: AllocKey>(
     alg: KeyAlg,
@@ -521,9 +735,29 @@ fn from_key_derivation_any<R: AllocKey>(
             AesKey::<A128Kw>::from_key_derivation(derive).map(R::alloc_key)
         }
         #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A192Kw) => {
+            AesKey::<A192Kw>::from_key_derivation(derive).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
         KeyAlg::Aes(AesTypes::A256Kw) => {
             AesKey::<A256Kw>::from_key_derivation(derive).map(R::alloc_key)
         }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A128GcmSiv) => {
+            AesKey::<A128GcmSiv>::from_key_derivation(derive).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A256GcmSiv) => {
+            AesKey::<A256GcmSiv>::from_key_derivation(derive).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A128Siv) => {
+            AesKey::<A128Siv>::from_key_derivation(derive).map(R::alloc_key)
+        }
+        #[cfg(feature = "aes")]
+        KeyAlg::Aes(AesTypes::A256Siv) => {
+            AesKey::<A256Siv>::from_key_derivation(derive).map(R::alloc_key)
+        }
         #[cfg(feature = "chacha")]
         KeyAlg::Chacha20(Chacha20Types::C20P) => {
             Chacha20Key::<C20P>::from_key_derivation(derive).map(R::alloc_key)
@@ -532,6 +766,18 @@ fn from_key_derivation_any<R: AllocKey>(
         KeyAlg::Chacha20(Chacha20Types::XC20P) => {
             Chacha20Key::<XC20P>::from_key_derivation(derive).map(R::alloc_key)
         }
+        #[cfg(feature = "hmac")]
+        KeyAlg::Hmac(HmacTypes::HS256) => {
+            HmacKey::<HS256>::from_key_derivation(derive).map(R::alloc_key)
+        }
+        #[cfg(feature = "hmac")]
+        KeyAlg::Hmac(HmacTypes::HS384) => {
+            HmacKey::<HS384>::from_key_derivation(derive).map(R::alloc_key)
+        }
+        #[cfg(feature = "hmac")]
+        KeyAlg::Hmac(HmacTypes::HS512) => {
+            HmacKey::<HS512>::from_key_derivation(derive).map(R::alloc_key)
+        }
         #[allow(unreachable_patterns)]
         _ => Err(err_msg!(
             Unsupported,
@@ -540,7 +786,7 @@ fn from_key_derivation_any<R: AllocKey>(
     }
 }
 
-#[cfg(not(any(feature = "aes", feature = "chacha")))]
+#[cfg(not(any(feature = "aes", feature = "chacha", feature = "hmac")))]
 fn from_key_derivation_any<R: AllocKey>(
     _alg: KeyAlg,
     _derive: impl KeyDerivation,
@@ -599,6 +845,146 @@ impl FromJwk for Arc<AnyKey> {
     }
 }
 
+#[cfg(feature = "cose")]
+impl FromCoseKey for Box<AnyKey> {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        from_cose_key_any(cose_key)
+    }
+}
+
+#[cfg(feature = "cose")]
+impl FromCoseKey for Arc<AnyKey> {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        from_cose_key_any(cose_key)
+    }
+}
+
+#[cfg(feature = "cose")]
+#[inline]
+fn from_cose_key_any<R: AllocKey>(cose_key: CoseKeyParts<'_>) -> Result<R, Error> {
+    match cose_key.key_alg()? {
+        KeyAlg::Aes(AesTypes::A128Gcm) => {
+            AesKey::<A128Gcm>::from_cose_key_parts(cose_key).map(R::alloc_key)
+        }
+        KeyAlg::Aes(AesTypes::A256Gcm) => {
+            AesKey::<A256Gcm>::from_cose_key_parts(cose_key).map(R::alloc_key)
+        }
+        KeyAlg::Aes(AesTypes::A128Kw) => {
+            AesKey::<A128Kw>::from_cose_key_parts(cose_key).map(R::alloc_key)
+        }
+        KeyAlg::Aes(AesTypes::A192Kw) => {
+            AesKey::<A192Kw>::from_cose_key_parts(cose_key).map(R::alloc_key)
+        }
+        KeyAlg::Aes(AesTypes::A256Kw) => {
+            AesKey::<A256Kw>::from_cose_key_parts(cose_key).map(R::alloc_key)
+        }
+        KeyAlg::Chacha20(Chacha20Types::C20P) => {
+            Chacha20Key::<C20P>::from_cose_key_parts(cose_key).map(R::alloc_key)
+        }
+        KeyAlg::Ed25519 => Ed25519KeyPair::from_cose_key_parts(cose_key).map(R::alloc_key),
+        KeyAlg::X25519 => X25519KeyPair::from_cose_key_parts(cose_key).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        KeyAlg::Ed448 => Ed448KeyPair::from_cose_key_parts(cose_key).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        KeyAlg::X448 => X448KeyPair::from_cose_key_parts(cose_key).map(R::alloc_key),
+        KeyAlg::EcCurve(EcCurves::Secp256k1) => {
+            K256KeyPair::from_cose_key_parts(cose_key).map(R::alloc_key)
+        }
+        KeyAlg::EcCurve(EcCurves::Secp256r1) => {
+            P256KeyPair::from_cose_key_parts(cose_key).map(R::alloc_key)
+        }
+        KeyAlg::EcCurve(EcCurves::Secp384r1) => {
+            P384KeyPair::from_cose_key_parts(cose_key).map(R::alloc_key)
+        }
+        _ => Err(err_msg!(Unsupported, "Unsupported COSE_Key for key import")),
+    }
+}
+
+#[cfg(feature = "multikey")]
+impl FromMultikey for Box<AnyKey> {
+    fn from_multikey(multikey: &str) -> Result<Self, Error> {
+        let (alg, public) = decode_multikey(multikey)?;
+        from_public_bytes_any(alg, &public)
+    }
+}
+
+#[cfg(feature = "multikey")]
+impl FromMultikey for Arc<AnyKey> {
+    fn from_multikey(multikey: &str) -> Result<Self, Error> {
+        let (alg, public) = decode_multikey(multikey)?;
+        from_public_bytes_any(alg, &public)
+    }
+}
+
+#[cfg(feature = "pkcs8")]
+impl FromPkcs8 for Box<AnyKey> {
+    fn from_pkcs8_der(der: &[u8]) -> Result<Self, Error> {
+        from_pkcs8_any(der)
+    }
+
+    fn from_public_key_der(der: &[u8]) -> Result<Self, Error> {
+        from_public_key_der_any(der)
+    }
+}
+
+#[cfg(feature = "pkcs8")]
+impl FromPkcs8 for Arc<AnyKey> {
+    fn from_pkcs8_der(der: &[u8]) -> Result<Self, Error> {
+        from_pkcs8_any(der)
+    }
+
+    fn from_public_key_der(der: &[u8]) -> Result<Self, Error> {
+        from_public_key_der_any(der)
+    }
+}
+
+#[cfg(feature = "pkcs8")]
+#[inline]
+fn from_pkcs8_any<R: AllocKey>(der: &[u8]) -> Result<R, Error> {
+    match pkcs8_key_alg(der)? {
+        KeyAlg::Ed25519 => Ed25519KeyPair::from_pkcs8_der(der).map(R::alloc_key),
+        KeyAlg::X25519 => X25519KeyPair::from_pkcs8_der(der).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        KeyAlg::Ed448 => Ed448KeyPair::from_pkcs8_der(der).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        KeyAlg::X448 => X448KeyPair::from_pkcs8_der(der).map(R::alloc_key),
+        KeyAlg::EcCurve(EcCurves::Secp256k1) => K256KeyPair::from_pkcs8_der(der).map(R::alloc_key),
+        KeyAlg::EcCurve(EcCurves::Secp256r1) => P256KeyPair::from_pkcs8_der(der).map(R::alloc_key),
+        KeyAlg::EcCurve(EcCurves::Secp384r1) => P384KeyPair::from_pkcs8_der(der).map(R::alloc_key),
+        #[cfg(feature = "rsa")]
+        KeyAlg::Rsa(_) => RsaKeyPair::from_pkcs8_der(der).map(R::alloc_key),
+        _ => Err(err_msg!(Unsupported, "Unsupported key for PKCS#8 import")),
+    }
+}
+
+#[cfg(feature = "pkcs8")]
+#[inline]
+fn from_public_key_der_any<R: AllocKey>(der: &[u8]) -> Result<R, Error> {
+    match public_key_alg(der)? {
+        KeyAlg::Ed25519 => Ed25519KeyPair::from_public_key_der(der).map(R::alloc_key),
+        KeyAlg::X25519 => X25519KeyPair::from_public_key_der(der).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        KeyAlg::Ed448 => Ed448KeyPair::from_public_key_der(der).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        KeyAlg::X448 => X448KeyPair::from_public_key_der(der).map(R::alloc_key),
+        KeyAlg::EcCurve(EcCurves::Secp256k1) => {
+            K256KeyPair::from_public_key_der(der).map(R::alloc_key)
+        }
+        KeyAlg::EcCurve(EcCurves::Secp256r1) => {
+            P256KeyPair::from_public_key_der(der).map(R::alloc_key)
+        }
+        KeyAlg::EcCurve(EcCurves::Secp384r1) => {
+            P384KeyPair::from_public_key_der(der).map(R::alloc_key)
+        }
+        #[cfg(feature = "rsa")]
+        KeyAlg::Rsa(_) => RsaKeyPair::from_public_key_der(der).map(R::alloc_key),
+        _ => Err(err_msg!(
+            Unsupported,
+            "Unsupported key for public key import"
+        )),
+    }
+}
+
 #[inline]
 fn from_jwk_any<R: AllocKey>(jwk: JwkParts<'_>) -> Result<R, Error> {
     match (jwk.kty, jwk.crv.as_ref()) {
@@ -610,6 +996,10 @@ fn from_jwk_any<R: AllocKey>(jwk: JwkParts<'_>) -> Result<R, Error> {
         ("OKP", c) if c == x25519::JWK_CURVE => {
             X25519KeyPair::from_jwk_parts(jwk).map(R::alloc_key)
         }
+        #[cfg(feature = "ed448")]
+        ("OKP", c) if c == ed448::JWK_CURVE => Ed448KeyPair::from_jwk_parts(jwk).map(R::alloc_key),
+        #[cfg(feature = "ed448")]
+        ("OKP", c) if c == x448::JWK_CURVE => X448KeyPair::from_jwk_parts(jwk).map(R::alloc_key),
         #[cfg(feature = "bls")]
         ("OKP" | "EC", c) if c == G1::JWK_CURVE => {
             BlsKeyPair::<G1>::from_jwk_parts(jwk).map(R::alloc_key)
@@ -628,7 +1018,29 @@ fn from_jwk_any<R: AllocKey>(jwk: JwkParts<'_>) -> Result<R, Error> {
         ("EC", c) if c == p256::JWK_CURVE => P256KeyPair::from_jwk_parts(jwk).map(R::alloc_key),
         #[cfg(feature = "p384")]
         ("EC", c) if c == p384::JWK_CURVE => P384KeyPair::from_jwk_parts(jwk).map(R::alloc_key),
-        // FIXME implement symmetric keys?
+        #[cfg(feature = "rsa")]
+        (kty, _) if kty == rsa::JWK_KEY_TYPE => RsaKeyPair::from_jwk_parts(jwk).map(R::alloc_key),
+        #[cfg(feature = "ml_dsa")]
+        (kty, _) if kty == ml_dsa::JWK_KEY_TYPE && jwk.alg.as_ref().starts_with("ML-DSA") => {
+            MlDsaKeyPair::from_jwk_parts(jwk).map(R::alloc_key)
+        }
+        #[cfg(feature = "slh_dsa")]
+        (kty, _) if kty == slh_dsa::JWK_KEY_TYPE && jwk.alg.as_ref().starts_with("SLH-DSA") => {
+            SlhDsaKeyPair::from_jwk_parts(jwk).map(R::alloc_key)
+        }
+        #[cfg(feature = "hmac")]
+        (kty, _) if kty == hmac::JWK_KEY_TYPE && jwk.alg == "HS256" => {
+            HmacKey::<HS256>::from_jwk_parts(jwk).map(R::alloc_key)
+        }
+        #[cfg(feature = "hmac")]
+        (kty, _) if kty == hmac::JWK_KEY_TYPE && jwk.alg == "HS384" => {
+            HmacKey::<HS384>::from_jwk_parts(jwk).map(R::alloc_key)
+        }
+        #[cfg(feature = "hmac")]
+        (kty, _) if kty == hmac::JWK_KEY_TYPE && jwk.alg == "HS512" => {
+            HmacKey::<HS512>::from_jwk_parts(jwk).map(R::alloc_key)
+        }
+        // FIXME implement symmetric encryption keys?
         _ => Err(err_msg!(Unsupported, "Unsupported JWK for key import")),
     }
 }
@@ -666,9 +1078,29 @@ macro_rules! match_key_alg {
             return Ok($key.assume::<AesKey<A128Kw>>());
         }
         #[cfg(feature = "aes")]
+        if $alg == KeyAlg::Aes(AesTypes::A192Kw) {
+            return Ok($key.assume::<AesKey<A192Kw>>());
+        }
+        #[cfg(feature = "aes")]
         if $alg == KeyAlg::Aes(AesTypes::A256Kw) {
             return Ok($key.assume::<AesKey<A256Kw>>());
         }
+        #[cfg(feature = "aes")]
+        if $alg == KeyAlg::Aes(AesTypes::A128GcmSiv) {
+            return Ok($key.assume::<AesKey<A128GcmSiv>>());
+        }
+        #[cfg(feature = "aes")]
+        if $alg == KeyAlg::Aes(AesTypes::A256GcmSiv) {
+            return Ok($key.assume::<AesKey<A256GcmSiv>>());
+        }
+        #[cfg(feature = "aes")]
+        if $alg == KeyAlg::Aes(AesTypes::A128Siv) {
+            return Ok($key.assume::<AesKey<A128Siv>>());
+        }
+        #[cfg(feature = "aes")]
+        if $alg == KeyAlg::Aes(AesTypes::A256Siv) {
+            return Ok($key.assume::<AesKey<A256Siv>>());
+        }
         match_key_alg!(@ $($rest)*; $key, $alg)
     }};
     (@ Bls $($rest:ident)*; $key:ident, $alg:ident) => {{
@@ -686,6 +1118,17 @@ macro_rules! match_key_alg {
         }
         match_key_alg!(@ $($rest)*; $key, $alg)
     }};
+    (@ BlsSig $($rest:ident)*; $key:ident, $alg:ident) => {{
+        #[cfg(feature = "bls")]
+        if $alg == KeyAlg::Bls12_381(BlsCurves::G1) {
+            return Ok($key.assume::<BlsKeyPair<G1>>());
+        }
+        #[cfg(feature = "bls")]
+        if $alg == KeyAlg::Bls12_381(BlsCurves::G2) {
+            return Ok($key.assume::<BlsKeyPair<G2>>());
+        }
+        match_key_alg!(@ $($rest)*; $key, $alg)
+    }};
     (@ Chacha $($rest:ident)*; $key:ident, $alg:ident) => {{
         #[cfg(feature = "chacha")]
         if $alg == KeyAlg::Chacha20(Chacha20Types::C20P) {
@@ -711,6 +1154,42 @@ macro_rules! match_key_alg {
         }
         match_key_alg!(@ $($rest)*; $key, $alg)
     }};
+    (@ X25519MlKem768 $($rest:ident)*; $key:ident, $alg:ident) => {{
+        #[cfg(feature = "ml_kem")]
+        if $alg == KeyAlg::X25519MlKem768 {
+            return Ok($key.assume::<X25519MlKem768KeyPair>())
+        }
+        match_key_alg!(@ $($rest)*; $key, $alg)
+    }};
+    (@ Hmac $($rest:ident)*; $key:ident, $alg:ident) => {{
+        #[cfg(feature = "hmac")]
+        if $alg == KeyAlg::Hmac(HmacTypes::HS256) {
+            return Ok($key.assume::<HmacKey<HS256>>());
+        }
+        #[cfg(feature = "hmac")]
+        if $alg == KeyAlg::Hmac(HmacTypes::HS384) {
+            return Ok($key.assume::<HmacKey<HS384>>());
+        }
+        #[cfg(feature = "hmac")]
+        if $alg == KeyAlg::Hmac(HmacTypes::HS512) {
+            return Ok($key.assume::<HmacKey<HS512>>());
+        }
+        match_key_alg!(@ $($rest)*; $key, $alg)
+    }};
+    (@ Ed448 $($rest:ident)*; $key:ident, $alg:ident) => {{
+        #[cfg(feature = "ed448")]
+        if $alg == KeyAlg::Ed448 {
+            return Ok($key.assume::<Ed448KeyPair>())
+        }
+        match_key_alg!(@ $($rest)*; $key, $alg)
+    }};
+    (@ X448 $($rest:ident)*; $key:ident, $alg:ident) => {{
+        #[cfg(feature = "ed448")]
+        if $alg == KeyAlg::X448 {
+            return Ok($key.assume::<X448KeyPair>())
+        }
+        match_key_alg!(@ $($rest)*; $key, $alg)
+    }};
     (@ K256 $($rest:ident)*; $key:ident, $alg:ident) => {{
         #[cfg(feature = "k256")]
         if $alg == KeyAlg::EcCurve(EcCurves::Secp256k1) {
@@ -739,6 +1218,27 @@ macro_rules! match_key_alg {
         }
         match_key_alg!(@ $($rest)*; $key, $alg)
     }};
+    (@ MlDsa $($rest:ident)*; $key:ident, $alg:ident) => {{
+        #[cfg(feature = "ml_dsa")]
+        if let KeyAlg::MlDsa(_) = $alg {
+            return Ok($key.assume::<MlDsaKeyPair>())
+        }
+        match_key_alg!(@ $($rest)*; $key, $alg)
+    }};
+    (@ Rsa $($rest:ident)*; $key:ident, $alg:ident) => {{
+        #[cfg(feature = "rsa")]
+        if let KeyAlg::Rsa(_) = $alg {
+            return Ok($key.assume::<RsaKeyPair>())
+        }
+        match_key_alg!(@ $($rest)*; $key, $alg)
+    }};
+    (@ SlhDsa $($rest:ident)*; $key:ident, $alg:ident) => {{
+        #[cfg(feature = "slh_dsa")]
+        if let KeyAlg::SlhDsa(_) = $alg {
+            return Ok($key.assume::<SlhDsaKeyPair>())
+        }
+        match_key_alg!(@ $($rest)*; $key, $alg)
+    }};
 }
 
 impl AnyKey {
@@ -752,6 +1252,26 @@ impl AnyKey {
         }
     }
 
+    fn key_as_deterministic(&self) -> Result<&dyn KeyAeadDeterministic, Error> {
+        match_key_alg! {
+            self,
+            &dyn KeyAeadDeterministic,
+            Aes,
+            "Deterministic encryption is not supported for this key type"
+        }
+    }
+
+    #[cfg(feature = "unauthenticated")]
+    fn key_as_unauthenticated(&self) -> Result<&dyn KeyUnauthenticatedCipher, Error> {
+        match_key_alg! {
+            self,
+            &dyn KeyUnauthenticatedCipher,
+            Aes,
+            Chacha,
+            "Unauthenticated encryption is not supported for this key type"
+        }
+    }
+
     fn key_to_secret(&self) -> Result<&dyn ToSecretBytes, Error> {
         match_key_alg! {
             self,
@@ -760,10 +1280,17 @@ impl AnyKey {
             Bls,
             Chacha,
             Ed25519,
+            Ed448,
+            Hmac,
             K256,
+            MlDsa,
             P256,
             P384,
+            Rsa,
+            SlhDsa,
             X25519,
+            X25519MlKem768,
+            X448,
             "Secret key export is not supported for this key type"
         }
     }
@@ -774,11 +1301,17 @@ impl AnyKey {
             &dyn ToPublicBytes,
             Bls,
             Ed25519,
+            Ed448,
             K256,
+            MlDsa,
             P256,
             P256Hardware,
             P384,
+            Rsa,
+            SlhDsa,
             X25519,
+            X25519MlKem768,
+            X448,
             "Public key export is not supported for this key type"
         }
     }
@@ -794,6 +1327,13 @@ impl ToPublicBytes for AnyKey {
     }
 }
 
+#[cfg(feature = "multikey")]
+impl ToMultikey for AnyKey {
+    fn to_multikey(&self) -> Result<String, Error> {
+        encode_multikey(self.algorithm(), &self.to_public_bytes()?)
+    }
+}
+
 impl ToSecretBytes for AnyKey {
     fn secret_bytes_length(&self) -> Result<usize, Error> {
         self.key_to_secret()?.secret_bytes_length()
@@ -814,6 +1354,10 @@ impl KeyExchange for AnyKey {
             KeyAlg::X25519 => Ok(self
                 .assume::<X25519KeyPair>()
                 .write_key_exchange(other.assume::<X25519KeyPair>(), out)?),
+            #[cfg(feature = "ed448")]
+            KeyAlg::X448 => Ok(self
+                .assume::<X448KeyPair>()
+                .write_key_exchange(other.assume::<X448KeyPair>(), out)?),
             #[cfg(feature = "k256")]
             KeyAlg::EcCurve(EcCurves::Secp256k1) => Ok(self
                 .assume::<K256KeyPair>()
@@ -854,6 +1398,48 @@ impl KeyAeadInPlace for AnyKey {
         self.key_as_aead()?.decrypt_in_place(buffer, nonce, aad)
     }
 
+    fn encrypt_in_place_chunked(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        nonce: &[u8],
+        aad: &[&[u8]],
+    ) -> Result<usize, Error> {
+        self.key_as_aead()?
+            .encrypt_in_place_chunked(buffer, nonce, aad)
+    }
+
+    fn decrypt_in_place_chunked(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        nonce: &[u8],
+        aad: &[&[u8]],
+    ) -> Result<(), Error> {
+        self.key_as_aead()?
+            .decrypt_in_place_chunked(buffer, nonce, aad)
+    }
+
+    fn encrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        nonce: &[u8],
+        aad: &[u8],
+        tag: &mut [u8],
+    ) -> Result<(), Error> {
+        self.key_as_aead()?
+            .encrypt_in_place_detached(buffer, nonce, aad, tag)
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        nonce: &[u8],
+        aad: &[u8],
+        tag: &[u8],
+    ) -> Result<(), Error> {
+        self.key_as_aead()?
+            .decrypt_in_place_detached(buffer, nonce, aad, tag)
+    }
+
     fn aead_params(&self) -> KeyAeadParams {
         if let Ok(key) = self.key_as_aead() {
             key.aead_params()
@@ -871,6 +1457,82 @@ impl KeyAeadInPlace for AnyKey {
     }
 }
 
+#[cfg(feature = "mac")]
+impl KeyMac for AnyKey {
+    fn write_mac(
+        &self,
+        message: &[u8],
+        mac_type: MacType,
+        customization: &[u8],
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let key = match_key_alg! {
+            self,
+            &dyn KeyMac,
+            Aes,
+            Chacha,
+            "MAC is not supported for this key type"
+        }?;
+        key.write_mac(message, mac_type, customization, out)
+    }
+}
+
+impl KeyAeadDeterministic for AnyKey {
+    fn encrypt_deterministic(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[&[u8]],
+    ) -> Result<(), Error> {
+        self.key_as_deterministic()?
+            .encrypt_deterministic(buffer, aad)
+    }
+
+    fn decrypt_deterministic(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[&[u8]],
+    ) -> Result<(), Error> {
+        self.key_as_deterministic()?
+            .decrypt_deterministic(buffer, aad)
+    }
+}
+
+#[cfg(feature = "unauthenticated")]
+impl KeyUnauthenticatedCipher for AnyKey {
+    fn apply_ctr_unauthenticated(&self, buffer: &mut [u8], iv: &[u8]) -> Result<(), Error> {
+        self.key_as_unauthenticated()?
+            .apply_ctr_unauthenticated(buffer, iv)
+    }
+
+    fn encrypt_cbc_unauthenticated(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        iv: &[u8],
+    ) -> Result<(), Error> {
+        self.key_as_unauthenticated()?
+            .encrypt_cbc_unauthenticated(buffer, iv)
+    }
+
+    fn decrypt_cbc_unauthenticated(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        iv: &[u8],
+    ) -> Result<(), Error> {
+        self.key_as_unauthenticated()?
+            .decrypt_cbc_unauthenticated(buffer, iv)
+    }
+
+    fn apply_chacha20_unauthenticated(
+        &self,
+        buffer: &mut [u8],
+        nonce: &[u8],
+        counter: u32,
+    ) -> Result<(), Error> {
+        self.key_as_unauthenticated()?
+            .apply_chacha20_unauthenticated(buffer, nonce, counter)
+    }
+}
+
 impl ToJwk for AnyKey {
     fn encode_jwk(&self, enc: &mut dyn JwkEncoder) -> Result<(), Error> {
         let key = match_key_alg! {
@@ -880,17 +1542,87 @@ impl ToJwk for AnyKey {
             Bls,
             Chacha,
             Ed25519,
+            Ed448,
+            Hmac,
             K256,
+            MlDsa,
             P256,
             P256Hardware,
             P384,
+            Rsa,
+            SlhDsa,
             X25519,
+            X448,
             "JWK export is not supported for this key type"
         }?;
         key.encode_jwk(enc)
     }
 }
 
+#[cfg(feature = "cose")]
+impl ToCoseKey for AnyKey {
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error> {
+        let key = match_key_alg! {
+            self,
+            &dyn ToCoseKey,
+            Aes,
+            Chacha,
+            Ed25519,
+            Ed448,
+            K256,
+            P256,
+            P384,
+            X25519,
+            X448,
+            "COSE_Key export is not supported for this key type"
+        }?;
+        key.encode_cose_key(enc)
+    }
+}
+
+#[cfg(feature = "pkcs8")]
+impl ToPkcs8 for AnyKey {
+    fn to_pkcs8_der(&self) -> Result<SecretBytes, Error> {
+        let key = match_key_alg! {
+            self,
+            &dyn ToPkcs8,
+            Ed25519,
+            Ed448,
+            K256,
+            P256,
+            P384,
+            Rsa,
+            X25519,
+            X448,
+            "PKCS#8 export is not supported for this key type"
+        }?;
+        key.to_pkcs8_der()
+    }
+
+    fn to_public_key_der(&self) -> Result<Vec<u8>, Error> {
+        let key = match_key_alg! {
+            self,
+            &dyn ToPkcs8,
+            Ed25519,
+            Ed448,
+            K256,
+            P256,
+            P384,
+            Rsa,
+            X25519,
+            X448,
+            "Public key export is not supported for this key type"
+        }?;
+        key.to_public_key_der()
+    }
+}
+
+impl HasKeyAlg for AnyKey {
+    fn algorithm(&self) -> KeyAlg {
+        self.0.algorithm()
+    }
+}
+
 impl KeySign for AnyKey {
     fn write_signature(
         &self,
@@ -901,15 +1633,71 @@ impl KeySign for AnyKey {
         let key = match_key_alg! {
             self,
             &dyn KeySign,
+            BlsSig,
             Ed25519,
+            Ed448,
+            Hmac,
             K256,
+            MlDsa,
             P256,
             P256Hardware,
             P384,
+            Rsa,
+            SlhDsa,
             "Signing is not supported for this key type"
         }?;
         key.write_signature(message, sig_type, out)
     }
+
+    fn write_signature_format(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        format: SignatureFormat,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let key = match_key_alg! {
+            self,
+            &dyn KeySign,
+            BlsSig,
+            Ed25519,
+            Ed448,
+            Hmac,
+            K256,
+            MlDsa,
+            P256,
+            P256Hardware,
+            P384,
+            Rsa,
+            SlhDsa,
+            "Signing is not supported for this key type"
+        }?;
+        key.write_signature_format(message, sig_type, format, out)
+    }
+
+    fn write_signature_prehashed(
+        &self,
+        digest: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let key = match_key_alg! {
+            self,
+            &dyn KeySign,
+            BlsSig,
+            Ed25519,
+            Ed448,
+            K256,
+            MlDsa,
+            P256,
+            P256Hardware,
+            P384,
+            Rsa,
+            SlhDsa,
+            "Signing is not supported for this key type"
+        }?;
+        key.write_signature_prehashed(digest, sig_type, out)
+    }
 }
 
 impl KeySigVerify for AnyKey {
@@ -922,15 +1710,71 @@ impl KeySigVerify for AnyKey {
         let key = match_key_alg! {
             self,
             &dyn KeySigVerify,
+            BlsSig,
             Ed25519,
+            Ed448,
+            Hmac,
             K256,
+            MlDsa,
             P256,
             P256Hardware,
             P384,
+            Rsa,
+            SlhDsa,
             "Signature verification is not supported for this key type"
         }?;
         key.verify_signature(message, signature, sig_type)
     }
+
+    fn verify_signature_format(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+        format: SignatureFormat,
+    ) -> Result<bool, Error> {
+        let key = match_key_alg! {
+            self,
+            &dyn KeySigVerify,
+            BlsSig,
+            Ed25519,
+            Ed448,
+            Hmac,
+            K256,
+            MlDsa,
+            P256,
+            P256Hardware,
+            P384,
+            Rsa,
+            SlhDsa,
+            "Signature verification is not supported for this key type"
+        }?;
+        key.verify_signature_format(message, signature, sig_type, format)
+    }
+
+    fn verify_signature_prehashed(
+        &self,
+        digest: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        let key = match_key_alg! {
+            self,
+            &dyn KeySigVerify,
+            BlsSig,
+            Ed25519,
+            Ed448,
+            K256,
+            MlDsa,
+            P256,
+            P256Hardware,
+            P384,
+            Rsa,
+            SlhDsa,
+            "Signature verification is not supported for this key type"
+        }?;
+        key.verify_signature_prehashed(digest, signature, sig_type)
+    }
 }
 
 // may want to implement in-place initialization to avoid copies
@@ -967,6 +1811,8 @@ impl<K: HasKeyAlg + HasKeyBackend + Sized + 'static> AnyKeyAlg for K {
 mod tests {
     #[allow(unused_imports)]
     use super::*;
+    #[cfg(feature = "slh_dsa")]
+    use crate::alg::SlhDsaTypes;
 
     // FIXME - add a custom key type for testing, to allow feature independence
 
@@ -979,6 +1825,145 @@ mod tests {
         let _ = key.to_jwk_public(None).unwrap();
     }
 
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn hmac_jwk_any() {
+        let key = Box::<AnyKey>::random(KeyAlg::Hmac(HmacTypes::HS512)).unwrap();
+        let sig = key.create_signature(b"message", None).unwrap();
+        let loaded =
+            Box::<AnyKey>::from_jwk_slice(key.to_jwk_secret(None).unwrap().as_ref()).unwrap();
+        assert_eq!(loaded.algorithm(), KeyAlg::Hmac(HmacTypes::HS512));
+        assert!(loaded
+            .verify_signature(b"message", &sig[..], Some(SignatureType::HS512))
+            .unwrap());
+    }
+
+    #[cfg(feature = "cose")]
+    #[test]
+    fn cose_key_any() {
+        let key = Box::<AnyKey>::random(KeyAlg::EcCurve(EcCurves::Secp256k1)).unwrap();
+        let secret = key.to_cose_key_secret().unwrap();
+        let loaded = Box::<AnyKey>::from_cose_key(&secret).unwrap();
+        assert_eq!(loaded.algorithm(), key.algorithm());
+        assert_eq!(
+            loaded.to_public_bytes().unwrap(),
+            key.to_public_bytes().unwrap()
+        );
+
+        let key = Box::<AnyKey>::random(KeyAlg::Chacha20(Chacha20Types::C20P)).unwrap();
+        assert!(key.to_cose_key_public().is_err());
+        let loaded = Box::<AnyKey>::from_cose_key(&key.to_cose_key_secret().unwrap()).unwrap();
+        assert_eq!(loaded.algorithm(), key.algorithm());
+    }
+
+    #[cfg(feature = "pkcs8")]
+    #[test]
+    fn pkcs8_any() {
+        let key = Box::<AnyKey>::random(KeyAlg::EcCurve(EcCurves::Secp384r1)).unwrap();
+        let loaded = Box::<AnyKey>::from_pkcs8_der(&key.to_pkcs8_der().unwrap()).unwrap();
+        assert_eq!(loaded.algorithm(), key.algorithm());
+        assert_eq!(
+            loaded.to_secret_bytes().unwrap(),
+            key.to_secret_bytes().unwrap()
+        );
+        let public = Box::<AnyKey>::from_public_key_pem(&key.to_public_key_pem().unwrap()).unwrap();
+        assert_eq!(public.algorithm(), key.algorithm());
+        assert_eq!(
+            public.to_public_bytes().unwrap(),
+            key.to_public_bytes().unwrap()
+        );
+
+        let key = Box::<AnyKey>::random(KeyAlg::Chacha20(Chacha20Types::C20P)).unwrap();
+        assert!(key.to_pkcs8_der().is_err());
+    }
+
+    #[cfg(all(feature = "multikey", feature = "all_keys"))]
+    #[test]
+    fn multikey_any() {
+        for alg in [
+            KeyAlg::Ed25519,
+            KeyAlg::X25519,
+            KeyAlg::EcCurve(EcCurves::Secp256r1),
+            KeyAlg::Bls12_381(BlsCurves::G1G2),
+        ] {
+            let key = Box::<AnyKey>::random(alg).unwrap();
+            let loaded = Box::<AnyKey>::from_multikey(&key.to_multikey().unwrap()).unwrap();
+            assert_eq!(loaded.algorithm(), alg);
+            assert_eq!(
+                loaded.to_public_bytes().unwrap(),
+                key.to_public_bytes().unwrap()
+            );
+        }
+
+        let key = Box::<AnyKey>::random(KeyAlg::Chacha20(Chacha20Types::C20P)).unwrap();
+        assert!(key.to_multikey().is_err());
+    }
+
+    #[cfg(feature = "ml_dsa")]
+    #[test]
+    fn ml_dsa_sign_any() {
+        let key = Box::<AnyKey>::random(KeyAlg::MlDsa(MlDsaTypes::MlDsa44)).unwrap();
+        let sig = key.create_signature(b"message", None).unwrap();
+        assert!(key
+            .verify_signature(b"message", &sig, Some(SignatureType::MlDsa44))
+            .unwrap());
+        let public = Box::<AnyKey>::from_jwk(&key.to_jwk_public(None).unwrap()).unwrap();
+        assert_eq!(public.algorithm(), key.algorithm());
+        assert!(public.verify_signature(b"message", &sig, None).unwrap());
+        assert!(Box::<AnyKey>::from_public_bytes(
+            KeyAlg::MlDsa(MlDsaTypes::MlDsa65),
+            &key.to_public_bytes().unwrap()
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "slh_dsa")]
+    #[test]
+    fn slh_dsa_sign_any() {
+        let key = Box::<AnyKey>::random(KeyAlg::SlhDsa(SlhDsaTypes::Sha2_128f)).unwrap();
+        let sig = key.create_signature(b"message", None).unwrap();
+        assert!(key
+            .verify_signature(b"message", &sig, Some(SignatureType::SlhDsaSha2_128f))
+            .unwrap());
+        let public = Box::<AnyKey>::from_jwk(&key.to_jwk_public(None).unwrap()).unwrap();
+        assert_eq!(public.algorithm(), key.algorithm());
+        assert!(public.verify_signature(b"message", &sig, None).unwrap());
+        assert!(!public
+            .verify_signature(b"other message", &sig, None)
+            .unwrap());
+    }
+
+    #[cfg(feature = "ml_kem")]
+    #[test]
+    fn x25519_ml_kem_any() {
+        let key = Box::<AnyKey>::random(KeyAlg::X25519MlKem768).unwrap();
+        let loaded = Box::<AnyKey>::from_secret_bytes(
+            KeyAlg::X25519MlKem768,
+            &key.to_secret_bytes().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            loaded.to_public_bytes().unwrap(),
+            key.to_public_bytes().unwrap()
+        );
+        let public = Box::<AnyKey>::from_public_bytes(
+            KeyAlg::X25519MlKem768,
+            &key.to_public_bytes().unwrap(),
+        )
+        .unwrap();
+        let (secret, ciphertext) = public
+            .downcast_ref::<X25519MlKem768KeyPair>()
+            .unwrap()
+            .encapsulate()
+            .unwrap();
+        let recv = loaded
+            .downcast_ref::<X25519MlKem768KeyPair>()
+            .unwrap()
+            .decapsulate(&ciphertext)
+            .unwrap();
+        assert_eq!(recv, secret);
+    }
+
     #[cfg(feature = "aes")]
     #[test]
     fn key_exchange_any() {
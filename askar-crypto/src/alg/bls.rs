@@ -5,9 +5,15 @@ use core::{
     ops::Add,
 };
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
 use aead::generic_array::GenericArray;
 use blake2::Digest;
-use bls12_381::{G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+use bls12_381::{
+    hash_to_curve::{ExpandMsgXmd, HashToCurve},
+    pairing, G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar,
+};
 use group::GroupEncoding;
 use sha2::Sha256;
 use subtle::ConstantTimeEq;
@@ -20,13 +26,20 @@ use crate::generic_array::{
 
 use super::{BlsCurves, HasKeyAlg, HasKeyBackend, KeyAlg};
 use crate::{
-    buffer::ArrayKey,
+    buffer::{ArrayKey, WriteBuffer},
     error::Error,
     jwk::{FromJwk, JwkEncoder, JwkParts, ToJwk},
     random::KeyMaterial,
     repr::{KeyGen, KeyMeta, KeyPublicBytes, KeySecretBytes, KeypairMeta},
+    sign::{KeySigVerify, KeySign, SignatureType},
 };
 
+/// The message expansion used when hashing to the curve
+///
+/// The hash-to-curve implementation of `bls12_381` depends on `digest` 0.9,
+/// and so requires the matching release of `sha2`.
+pub(crate) type ExpandMsgSha256 = ExpandMsgXmd<sha2_legacy::Sha256>;
+
 /// The 'kty' value of a BLS key JWK
 pub const JWK_KEY_TYPE: &str = "OKP";
 
@@ -446,10 +459,319 @@ impl From<&BlsKeyPair<G1G2>> for BlsKeyPair<G2> {
 /// A utility struct combining G1 and G2 public keys
 pub struct G1G2Pair(G1Affine, G2Affine);
 
+/// Trait implemented by BLS public key types supporting signatures
+pub trait BlsSigType: BlsPublicKeyType {
+    /// The length of a serialized signature
+    const SIGNATURE_LENGTH: usize;
+    /// The domain separation tag for the basic scheme
+    const DST_BASIC: &'static [u8];
+    /// The domain separation tag for the message augmentation scheme
+    const DST_AUG: &'static [u8];
+    /// The domain separation tag for the proof-of-possession scheme
+    const DST_POP: &'static [u8];
+    /// The domain separation tag for proofs of possession
+    const DST_POP_PROVE: &'static [u8];
+
+    /// Sign a message with the secret scalar
+    fn sign_message(
+        secret: &Scalar,
+        message: &[u8],
+        dst: &[u8],
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error>;
+
+    /// Verify a signature over a sequence of public key and message pairs
+    fn verify_messages<'m>(
+        pairs: &mut dyn Iterator<Item = (&'m Self::Buffer, &'m [u8])>,
+        dst: &[u8],
+        signature: &[u8],
+    ) -> bool;
+
+    /// Combine a sequence of signatures into an aggregate signature
+    fn aggregate_signatures(
+        signatures: &mut dyn Iterator<Item = &[u8]>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error>;
+
+    /// Combine a sequence of public keys into an aggregate public key
+    fn aggregate_public_keys(keys: &mut dyn Iterator<Item = &Self::Buffer>) -> Self::Buffer;
+
+    /// Get the domain separation tag for a signature type
+    fn signature_dst(sig_type: Option<SignatureType>) -> Result<&'static [u8], Error> {
+        match sig_type {
+            None | Some(SignatureType::BlsBasic) => Ok(Self::DST_BASIC),
+            Some(SignatureType::BlsAug) => Ok(Self::DST_AUG),
+            Some(SignatureType::BlsPop) => Ok(Self::DST_POP),
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+}
+
+impl BlsSigType for G1 {
+    const SIGNATURE_LENGTH: usize = 96;
+    const DST_BASIC: &'static [u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+    const DST_AUG: &'static [u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_AUG_";
+    const DST_POP: &'static [u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+    const DST_POP_PROVE: &'static [u8] = b"BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+    fn sign_message(
+        secret: &Scalar,
+        message: &[u8],
+        dst: &[u8],
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let h = <G2Projective as HashToCurve<ExpandMsgSha256>>::hash_to_curve(message, dst);
+        out.buffer_write(&G2Affine::from(h * secret).to_compressed())
+    }
+
+    fn verify_messages<'m>(
+        pairs: &mut dyn Iterator<Item = (&'m Self::Buffer, &'m [u8])>,
+        dst: &[u8],
+        signature: &[u8],
+    ) -> bool {
+        let Some(sig) = parse_g2_signature(signature) else {
+            return false;
+        };
+        let mut acc = Gt::identity();
+        for (pk, message) in pairs {
+            if bool::from(pk.is_identity()) {
+                return false;
+            }
+            let h = <G2Projective as HashToCurve<ExpandMsgSha256>>::hash_to_curve(message, dst);
+            acc += pairing(pk, &G2Affine::from(h));
+        }
+        acc == pairing(&G1Affine::generator(), &sig)
+    }
+
+    fn aggregate_signatures(
+        signatures: &mut dyn Iterator<Item = &[u8]>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let mut acc = G2Projective::identity();
+        for sig in signatures {
+            acc += parse_g2_signature(sig).ok_or_else(|| err_msg!(Invalid, "Invalid signature"))?;
+        }
+        out.buffer_write(&G2Affine::from(acc).to_compressed())
+    }
+
+    fn aggregate_public_keys(keys: &mut dyn Iterator<Item = &Self::Buffer>) -> Self::Buffer {
+        G1Affine::from(keys.fold(G1Projective::identity(), |acc, pk| acc + pk))
+    }
+}
+
+impl BlsSigType for G2 {
+    const SIGNATURE_LENGTH: usize = 48;
+    const DST_BASIC: &'static [u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_";
+    const DST_AUG: &'static [u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_AUG_";
+    const DST_POP: &'static [u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_POP_";
+    const DST_POP_PROVE: &'static [u8] = b"BLS_POP_BLS12381G1_XMD:SHA-256_SSWU_RO_POP_";
+
+    fn sign_message(
+        secret: &Scalar,
+        message: &[u8],
+        dst: &[u8],
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let h = <G1Projective as HashToCurve<ExpandMsgSha256>>::hash_to_curve(message, dst);
+        out.buffer_write(&G1Affine::from(h * secret).to_compressed())
+    }
+
+    fn verify_messages<'m>(
+        pairs: &mut dyn Iterator<Item = (&'m Self::Buffer, &'m [u8])>,
+        dst: &[u8],
+        signature: &[u8],
+    ) -> bool {
+        let Some(sig) = parse_g1_signature(signature) else {
+            return false;
+        };
+        let mut acc = Gt::identity();
+        for (pk, message) in pairs {
+            if bool::from(pk.is_identity()) {
+                return false;
+            }
+            let h = <G1Projective as HashToCurve<ExpandMsgSha256>>::hash_to_curve(message, dst);
+            acc += pairing(&G1Affine::from(h), pk);
+        }
+        acc == pairing(&sig, &G2Affine::generator())
+    }
+
+    fn aggregate_signatures(
+        signatures: &mut dyn Iterator<Item = &[u8]>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let mut acc = G1Projective::identity();
+        for sig in signatures {
+            acc += parse_g1_signature(sig).ok_or_else(|| err_msg!(Invalid, "Invalid signature"))?;
+        }
+        out.buffer_write(&G1Affine::from(acc).to_compressed())
+    }
+
+    fn aggregate_public_keys(keys: &mut dyn Iterator<Item = &Self::Buffer>) -> Self::Buffer {
+        G2Affine::from(keys.fold(G2Projective::identity(), |acc, pk| acc + pk))
+    }
+}
+
+fn parse_g1_signature(sig: &[u8]) -> Option<G1Affine> {
+    let sig: &[u8; 48] = sig.try_into().ok()?;
+    Option::from(G1Affine::from_compressed(sig))
+}
+
+fn parse_g2_signature(sig: &[u8]) -> Option<G2Affine> {
+    let sig: &[u8; 96] = sig.try_into().ok()?;
+    Option::from(G2Affine::from_compressed(sig))
+}
+
+impl<Pk: BlsSigType> BlsKeyPair<Pk> {
+    /// Apply the message augmentation for the signature type, if any
+    fn with_message<O>(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        f: impl FnOnce(&[u8]) -> O,
+    ) -> Result<O, Error> {
+        if sig_type == Some(SignatureType::BlsAug) {
+            #[cfg(feature = "alloc")]
+            {
+                let mut msg = Vec::with_capacity(Pk::BufferSize::USIZE + message.len());
+                Pk::with_bytes(&self.public, None, |pk| msg.extend_from_slice(pk));
+                msg.extend_from_slice(message);
+                return Ok(f(&msg));
+            }
+            #[cfg(not(feature = "alloc"))]
+            return Err(err_msg!(
+                Unsupported,
+                "Message augmentation requires the alloc feature"
+            ));
+        }
+        Ok(f(message))
+    }
+
+    /// Create a proof of possession of the secret key
+    pub fn prove_possession(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        let sk = self
+            .secret
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        Pk::with_bytes(&self.public, None, |pk| {
+            Pk::sign_message(&sk.0, pk, Pk::DST_POP_PROVE, out)
+        })
+    }
+
+    /// Verify a proof of possession of the secret key
+    pub fn verify_possession(&self, proof: &[u8]) -> bool {
+        Pk::with_bytes(&self.public, None, |pk| {
+            Pk::verify_messages(
+                &mut [(&self.public, pk)].into_iter(),
+                Pk::DST_POP_PROVE,
+                proof,
+            )
+        })
+    }
+
+    /// Combine multiple signatures into a single aggregate signature
+    pub fn aggregate_signatures<'s>(
+        signatures: impl IntoIterator<Item = &'s [u8]>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        Pk::aggregate_signatures(&mut signatures.into_iter(), out)
+    }
+
+    /// Verify an aggregate signature over a set of key and message pairs
+    pub fn aggregate_verify(
+        pairs: &[(&Self, &[u8])],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        let dst = Pk::signature_dst(sig_type)?;
+        if pairs.is_empty() {
+            return Ok(false);
+        }
+        match sig_type {
+            None | Some(SignatureType::BlsBasic) => {
+                // the basic scheme requires all messages to be distinct
+                for (idx, (_, message)) in pairs.iter().enumerate() {
+                    if pairs[..idx].iter().any(|(_, prev)| prev == message) {
+                        return Ok(false);
+                    }
+                }
+                Ok(Pk::verify_messages(
+                    &mut pairs.iter().map(|(kp, message)| (&kp.public, *message)),
+                    dst,
+                    signature,
+                ))
+            }
+            #[cfg(feature = "alloc")]
+            Some(SignatureType::BlsAug) => {
+                let messages = pairs
+                    .iter()
+                    .map(|(kp, message)| kp.with_message(message, sig_type, |m| m.to_vec()))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok(Pk::verify_messages(
+                    &mut pairs
+                        .iter()
+                        .zip(messages.iter())
+                        .map(|((kp, _), message)| (&kp.public, message.as_slice())),
+                    dst,
+                    signature,
+                ))
+            }
+            Some(SignatureType::BlsPop) => Ok(Pk::verify_messages(
+                &mut pairs.iter().map(|(kp, message)| (&kp.public, *message)),
+                dst,
+                signature,
+            )),
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+
+    /// Verify an aggregate signature by multiple keys over a single message.
+    /// The proof of possession for each key must be verified in advance.
+    pub fn fast_aggregate_verify(keys: &[&Self], message: &[u8], signature: &[u8]) -> bool {
+        if keys.is_empty() {
+            return false;
+        }
+        let agg_pk = Pk::aggregate_public_keys(&mut keys.iter().map(|kp| &kp.public));
+        let mut pairs = [(&agg_pk, message)].into_iter();
+        Pk::verify_messages(&mut pairs, Pk::DST_POP, signature)
+    }
+}
+
+impl<Pk: BlsSigType> KeySign for BlsKeyPair<Pk> {
+    fn write_signature(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let sk = self
+            .secret
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        let dst = Pk::signature_dst(sig_type)?;
+        self.with_message(message, sig_type, |msg| {
+            Pk::sign_message(&sk.0, msg, dst, out)
+        })?
+    }
+}
+
+impl<Pk: BlsSigType> KeySigVerify for BlsKeyPair<Pk> {
+    fn verify_signature(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        let dst = Pk::signature_dst(sig_type)?;
+        self.with_message(message, sig_type, |msg| {
+            Pk::verify_messages(&mut [(&self.public, msg)].into_iter(), dst, signature)
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use base64::Engine;
-    use std::string::ToString;
+    use std::{string::ToString, vec::Vec};
 
     use super::*;
     use crate::repr::{ToPublicBytes, ToSecretBytes};
@@ -567,6 +889,106 @@ mod tests {
         // );
     }
 
+    fn sign_verify_schemes<Pk: BlsSigType>() {
+        let kp = BlsKeyPair::<Pk>::random().unwrap();
+        let message = b"test message";
+        for sig_type in [
+            None,
+            Some(SignatureType::BlsBasic),
+            Some(SignatureType::BlsAug),
+            Some(SignatureType::BlsPop),
+        ] {
+            let sig = kp.create_signature(message, sig_type).unwrap();
+            assert_eq!(sig.len(), Pk::SIGNATURE_LENGTH);
+            assert!(kp.verify_signature(message, &sig, sig_type).unwrap());
+            assert!(!kp
+                .verify_signature(b"other message", &sig, sig_type)
+                .unwrap());
+        }
+        let sig = kp
+            .create_signature(message, Some(SignatureType::BlsBasic))
+            .unwrap();
+        assert!(!kp
+            .verify_signature(message, &sig, Some(SignatureType::BlsPop))
+            .unwrap());
+        assert!(kp
+            .create_signature(message, Some(SignatureType::EdDSA))
+            .is_err());
+    }
+
+    #[test]
+    fn g1_sign_verify() {
+        sign_verify_schemes::<G1>();
+    }
+
+    #[test]
+    fn g2_sign_verify() {
+        sign_verify_schemes::<G2>();
+    }
+
+    fn aggregate_schemes<Pk: BlsSigType>() {
+        let kp1 = BlsKeyPair::<Pk>::random().unwrap();
+        let kp2 = BlsKeyPair::<Pk>::random().unwrap();
+        let (msg1, msg2) = (&b"message one"[..], &b"message two"[..]);
+
+        for sig_type in [
+            Some(SignatureType::BlsBasic),
+            Some(SignatureType::BlsAug),
+            Some(SignatureType::BlsPop),
+        ] {
+            let sig1 = kp1.create_signature(msg1, sig_type).unwrap();
+            let sig2 = kp2.create_signature(msg2, sig_type).unwrap();
+            let mut agg = Vec::new();
+            BlsKeyPair::<Pk>::aggregate_signatures([sig1.as_ref(), sig2.as_ref()], &mut agg)
+                .unwrap();
+            assert!(
+                BlsKeyPair::aggregate_verify(&[(&kp1, msg1), (&kp2, msg2)], &agg, sig_type)
+                    .unwrap()
+            );
+            assert!(
+                !BlsKeyPair::aggregate_verify(&[(&kp1, msg2), (&kp2, msg1)], &agg, sig_type)
+                    .unwrap()
+            );
+        }
+
+        // the basic scheme rejects duplicate messages
+        let sig1 = kp1.create_signature(msg1, None).unwrap();
+        let sig2 = kp2.create_signature(msg1, None).unwrap();
+        let mut agg = Vec::new();
+        BlsKeyPair::<Pk>::aggregate_signatures([sig1.as_ref(), sig2.as_ref()], &mut agg).unwrap();
+        assert!(!BlsKeyPair::aggregate_verify(&[(&kp1, msg1), (&kp2, msg1)], &agg, None).unwrap());
+
+        // proof of possession and fast aggregate verification
+        let mut pop = Vec::new();
+        kp1.prove_possession(&mut pop).unwrap();
+        assert!(kp1.verify_possession(&pop));
+        assert!(!kp2.verify_possession(&pop));
+        let sig1 = kp1
+            .create_signature(msg1, Some(SignatureType::BlsPop))
+            .unwrap();
+        let sig2 = kp2
+            .create_signature(msg1, Some(SignatureType::BlsPop))
+            .unwrap();
+        let mut agg = Vec::new();
+        BlsKeyPair::<Pk>::aggregate_signatures([sig1.as_ref(), sig2.as_ref()], &mut agg).unwrap();
+        assert!(BlsKeyPair::fast_aggregate_verify(&[&kp1, &kp2], msg1, &agg));
+        assert!(!BlsKeyPair::fast_aggregate_verify(
+            &[&kp1, &kp2],
+            msg2,
+            &agg
+        ));
+    }
+
+    #[test]
+    fn g1_aggregate() {
+        aggregate_schemes::<G1>();
+    }
+
+    #[test]
+    fn g2_aggregate() {
+        aggregate_schemes::<G2>();
+    }
+
     #[cfg(feature = "any_key")]
     #[test]
     // test loading of a key with the EC key type
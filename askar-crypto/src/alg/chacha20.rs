@@ -19,6 +19,15 @@ use crate::{
     repr::{KeyGen, KeyMeta, KeySecretBytes},
 };
 
+#[cfg(feature = "mac")]
+use crate::{
+    buffer::WriteBuffer,
+    mac::{self, KeyMac, MacType},
+};
+
+#[cfg(feature = "unauthenticated")]
+use crate::unauthenticated::{self, KeyUnauthenticatedCipher};
+
 /// The 'kty' value of a symmetric key JWK
 pub static JWK_KEY_TYPE: &str = "oct";
 
@@ -199,6 +208,48 @@ impl<T: Chacha20Type> KeyAeadInPlace for Chacha20Key<T> {
         Ok(())
     }
 
+    fn encrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        nonce: &[u8],
+        aad: &[u8],
+        tag: &mut [u8],
+    ) -> Result<(), Error> {
+        if nonce.len() != NonceSize::<T>::USIZE {
+            return Err(err_msg!(InvalidNonce));
+        }
+        if tag.len() != TagSize::<T>::USIZE {
+            return Err(err_msg!(Usage, "Invalid size for verification tag"));
+        }
+        let nonce = GenericArray::from_slice(nonce);
+        let chacha = T::Aead::new(self.0.as_ref());
+        let result = chacha
+            .encrypt_in_place_detached(nonce, aad, buffer)
+            .map_err(|_| err_msg!(Encryption, "AEAD encryption error"))?;
+        tag.copy_from_slice(&result[..]);
+        Ok(())
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        nonce: &[u8],
+        aad: &[u8],
+        tag: &[u8],
+    ) -> Result<(), Error> {
+        if nonce.len() != NonceSize::<T>::USIZE {
+            return Err(err_msg!(InvalidNonce));
+        }
+        if tag.len() != TagSize::<T>::USIZE {
+            return Err(err_msg!(Invalid, "Invalid size for verification tag"));
+        }
+        let nonce = GenericArray::from_slice(nonce);
+        let chacha = T::Aead::new(self.0.as_ref());
+        chacha
+            .decrypt_in_place_detached(nonce, aad, buffer, GenericArray::from_slice(tag))
+            .map_err(|_| err_msg!(Encryption, "AEAD decryption error"))
+    }
+
     fn aead_params(&self) -> KeyAeadParams {
         KeyAeadParams {
             nonce_length: NonceSize::<T>::USIZE,
@@ -240,12 +291,59 @@ where
     }
 }
 
+#[cfg(feature = "mac")]
+impl<T: Chacha20Type> KeyMac for Chacha20Key<T> {
+    fn write_mac(
+        &self,
+        message: &[u8],
+        mac_type: MacType,
+        customization: &[u8],
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        mac::keyed_mac(mac_type, self.0.as_ref(), message, customization, out)
+    }
+}
+
+#[cfg(feature = "unauthenticated")]
+impl<T: Chacha20Type> KeyUnauthenticatedCipher for Chacha20Key<T> {
+    fn apply_chacha20_unauthenticated(
+        &self,
+        buffer: &mut [u8],
+        nonce: &[u8],
+        counter: u32,
+    ) -> Result<(), Error> {
+        let key = self.0.as_ref();
+        match T::ALG_TYPE {
+            Chacha20Types::C20P => {
+                unauthenticated::stream_apply::<chacha20::ChaCha20>(key, nonce, counter, buffer)
+            }
+            Chacha20Types::XC20P => {
+                unauthenticated::stream_apply::<chacha20::XChaCha20>(key, nonce, counter, buffer)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::buffer::SecretBytes;
     use crate::repr::ToSecretBytes;
 
+    #[test]
+    fn generate_deterministic() {
+        use crate::random::RandomDet;
+        let seed = b"testseed000000000000000000000001";
+        let key = Chacha20Key::<XC20P>::generate(RandomDet::new(seed)).unwrap();
+        let check = Chacha20Key::<XC20P>::generate(RandomDet::new(seed)).unwrap();
+        assert_eq!(key, check);
+        let nonce = Chacha20Key::<XC20P>::generate_nonce(RandomDet::new(seed));
+        assert_eq!(
+            nonce,
+            Chacha20Key::<XC20P>::generate_nonce(RandomDet::new(seed))
+        );
+    }
+
     #[test]
     fn encrypt_round_trip() {
         fn test_encrypt<T: Chacha20Type>() {
@@ -263,6 +361,75 @@ mod tests {
         test_encrypt::<XC20P>();
     }
 
+    #[test]
+    fn encrypt_chunked_aad() {
+        let input = b"hello";
+        let key = Chacha20Key::<C20P>::random().unwrap();
+        let nonce = Chacha20Key::<C20P>::random_nonce();
+        let mut expected = SecretBytes::from_slice(input);
+        key.encrypt_in_place(&mut expected, &nonce, b"header.aad")
+            .unwrap();
+        let mut buffer = SecretBytes::from_slice(input);
+        key.encrypt_in_place_chunked(&mut buffer, &nonce, &[b"header", b".", b"aad"])
+            .unwrap();
+        assert_eq!(buffer, expected);
+        key.decrypt_in_place_chunked(&mut buffer, &nonce, &[b"header.", b"aad"])
+            .unwrap();
+        assert_eq!(&buffer[..], input);
+    }
+
+    #[test]
+    fn encrypt_detached() {
+        use crate::encrypt::AeadLayout;
+        let input = b"hello";
+        let key = Chacha20Key::<C20P>::random().unwrap();
+        let nonce = Chacha20Key::<C20P>::random_nonce();
+        let mut combined = SecretBytes::from_slice(input);
+        key.encrypt_in_place(&mut combined, &nonce, b"aad").unwrap();
+
+        let mut buffer = *input;
+        let mut tag = [0u8; 16];
+        key.encrypt_in_place_detached(&mut buffer, &nonce, b"aad", &mut tag)
+            .unwrap();
+        assert_eq!(&combined[..5], &buffer[..]);
+        assert_eq!(&combined[5..], &tag[..]);
+        key.decrypt_in_place_detached(&mut buffer, &nonce, b"aad", &tag)
+            .unwrap();
+        assert_eq!(&buffer, input);
+
+        // [tag | nonce | message]
+        let layout = AeadLayout {
+            nonce_offset: 16,
+            message: 28..33,
+            tag_offset: 0,
+        };
+        let mut envelope = [0u8; 33];
+        envelope[16..28].copy_from_slice(&nonce);
+        envelope[28..].copy_from_slice(input);
+        key.encrypt_in_place_at(&mut envelope, &layout, b"aad")
+            .unwrap();
+        assert_eq!(&envelope[..16], &tag[..]);
+        assert_eq!(&envelope[28..], &combined[..5]);
+        let mut tampered = envelope;
+        tampered[0] ^= 1;
+        assert!(key
+            .decrypt_in_place_at(&mut tampered, &layout, b"aad")
+            .is_err());
+        key.decrypt_in_place_at(&mut envelope, &layout, b"aad")
+            .unwrap();
+        assert_eq!(&envelope[28..], input);
+
+        // overlapping regions
+        let layout = AeadLayout {
+            nonce_offset: 0,
+            message: 8..13,
+            tag_offset: 13,
+        };
+        assert!(key
+            .encrypt_in_place_at(&mut [0u8; 29], &layout, b"")
+            .is_err());
+    }
+
     #[test]
     fn serialize_round_trip() {
         fn test_serialize<T: Chacha20Type>() {
@@ -275,4 +442,34 @@ mod tests {
         test_serialize::<C20P>();
         test_serialize::<XC20P>();
     }
+
+    #[cfg(feature = "unauthenticated")]
+    #[test]
+    // from RFC 8439 section 2.4.2
+    fn keystream_expected() {
+        let key = Chacha20Key::<C20P>::from_secret_bytes(&hex!(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        ))
+        .unwrap();
+        let nonce = hex!("000000000000004a00000000");
+        let input = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        let mut buffer = *input;
+        key.apply_chacha20_unauthenticated(&mut buffer, &nonce, 1)
+            .unwrap();
+        assert_eq!(
+            buffer[..],
+            hex!(
+                "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b
+                f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8
+                07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736
+                5af90bbf74a35be6b40b8eedf2785e42874d"
+            )[..]
+        );
+        key.apply_chacha20_unauthenticated(&mut buffer, &nonce, 1)
+            .unwrap();
+        assert_eq!(&buffer, input);
+        assert!(key
+            .apply_chacha20_unauthenticated(&mut buffer, &nonce[..8], 1)
+            .is_err());
+    }
 }
@@ -16,3 +16,21 @@ pub fn write_sk<C: Curve>(sk: &SecretKey<C>, out: &mut [u8]) {
         dst.copy_from_slice(&src.to_be_bytes());
     }
 }
+
+/// The maximum supported length of an encoded curve coordinate
+const MAX_COORD_LENGTH: usize = 48;
+
+/// Accept public key bytes in the compressed or uncompressed SEC1 encoding,
+/// or as the raw concatenated x and y coordinates, which are converted to the
+/// uncompressed SEC1 encoding
+pub fn with_sec1_public_bytes<O>(key: &[u8], coord_len: usize, f: impl FnOnce(&[u8]) -> O) -> O {
+    debug_assert!(coord_len <= MAX_COORD_LENGTH);
+    if key.len() == coord_len * 2 {
+        let mut buf = [0u8; 1 + MAX_COORD_LENGTH * 2];
+        buf[0] = 0x04;
+        buf[1..=key.len()].copy_from_slice(key);
+        f(&buf[..=key.len()])
+    } else {
+        f(key)
+    }
+}
@@ -1,11 +1,16 @@
 //! Ed25519 signature and verification key support
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 use core::{
     convert::{TryFrom, TryInto},
     fmt::{self, Debug, Formatter},
 };
 
-use curve25519_dalek::{edwards::CompressedEdwardsY, scalar::clamp_integer};
+use curve25519_dalek::{
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::{clamp_integer, Scalar},
+};
 use ed25519_dalek::{
     SecretKey, Signature, Signer, SigningKey, VerifyingKey, KEYPAIR_LENGTH, PUBLIC_KEY_LENGTH,
     SECRET_KEY_LENGTH, SIGNATURE_LENGTH as EDDSA_SIGNATURE_LENGTH,
@@ -26,6 +31,17 @@ use crate::{
     sign::{KeySigVerify, KeySign, SignatureType},
 };
 
+/// The length of an ECVRF proof
+pub const VRF_PROOF_LENGTH: usize = 80;
+/// The length of an ECVRF hash output
+pub const VRF_OUTPUT_LENGTH: usize = 64;
+
+/// The suite string of ECVRF-EDWARDS25519-SHA512-TAI
+const VRF_SUITE: u8 = 0x03;
+
+/// The dom2 prefix for Ed25519ph signatures with an empty context
+const ED25519PH_PREFIX: &[u8] = b"SigEd25519 no Ed25519 collisions\x01\x00";
+
 /// The 'kty' value of an Ed25519 JWK
 pub static JWK_KEY_TYPE: &str = "OKP";
 /// The 'crv' value of an Ed25519 JWK
@@ -65,6 +81,12 @@ impl Ed25519KeyPair {
     }
 
     /// Convert this keypair to an X25519 keypair
+    ///
+    /// A public key without a secret key is converted using the birational map from
+    /// the Edwards y-coordinate to the Montgomery u-coordinate, `u = (1 + y) / (1 - y)`.
+    /// The sign of the Edwards x-coordinate is discarded, as X25519 only uses the
+    /// u-coordinate, so the result matches the public key of the converted keypair
+    /// but cannot be converted back to an Ed25519 public key.
     pub fn to_x25519_keypair(&self) -> X25519KeyPair {
         if let Some(secret) = self.secret.as_ref() {
             let hash = sha2::Sha512::digest(secret);
@@ -74,10 +96,11 @@ impl Ed25519KeyPair {
             let public = XPublicKey::from(&secret);
             X25519KeyPair::new(Some(secret), public)
         } else {
+            // the public key is validated when the keypair is constructed
             let public = XPublicKey::from(
                 CompressedEdwardsY(self.public)
                     .decompress()
-                    .unwrap()
+                    .expect("Invalid ed25519 public key")
                     .to_montgomery()
                     .to_bytes(),
             );
@@ -99,6 +122,247 @@ impl Ed25519KeyPair {
             false
         }
     }
+
+    /// Verify a batch of signatures, each against the corresponding message and public key
+    ///
+    /// Returns `true` only if every signature is valid. As with `verify_signature`,
+    /// weak public keys and small-order signature commitments are rejected, but the
+    /// batch uses the cofactored verification equation. If the batch fails, the
+    /// signatures must be checked individually to identify the invalid entries.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn verify_batch(
+        messages: &[&[u8]],
+        signatures: &[&[u8]],
+        keys: &[Ed25519KeyPair],
+    ) -> Result<bool, Error> {
+        if messages.len() != signatures.len() || messages.len() != keys.len() {
+            return Err(err_msg!(Usage, "Mismatched batch lengths"));
+        }
+        let mut sigs = Vec::with_capacity(signatures.len());
+        for sig in signatures {
+            let Ok(sig) = Signature::try_from(*sig) else {
+                return Ok(false);
+            };
+            match CompressedEdwardsY(*sig.r_bytes()).decompress() {
+                Some(r) if !r.is_small_order() => sigs.push(sig),
+                _ => return Ok(false),
+            }
+        }
+        let mut vks = Vec::with_capacity(keys.len());
+        for key in keys {
+            // the public key is validated when the keypair is constructed
+            let vk = VerifyingKey::from_bytes(&key.public).unwrap();
+            if vk.is_weak() {
+                return Ok(false);
+            }
+            vks.push(vk);
+        }
+        Ok(ed25519_dalek::verify_batch(messages, &sigs, &vks).is_ok())
+    }
+
+    /// Sign a SHA-512 message digest with the secret key using Ed25519ph (RFC 8032)
+    pub fn sign_prehashed(&self, digest: &[u8]) -> Result<[u8; EDDSA_SIGNATURE_LENGTH], Error> {
+        if digest.len() != 64 {
+            return Err(err_msg!(Usage, "Invalid message digest length"));
+        }
+        let secret = self
+            .secret
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        let mut hash = [0u8; 64];
+        hash.copy_from_slice(&sha2::Sha512::digest(secret));
+        let mut s = Scalar::from_bytes_mod_order(clamp_integer(hash[..32].try_into().unwrap()));
+        let mut r_hash = sha2::Sha512::new()
+            .chain_update(ED25519PH_PREFIX)
+            .chain_update(&hash[32..])
+            .chain_update(digest)
+            .finalize();
+        hash.copy_from_slice(&r_hash);
+        let mut r = Scalar::from_bytes_mod_order_wide(&hash);
+        hash.zeroize();
+        r_hash.as_mut_slice().zeroize();
+
+        let big_r = EdwardsPoint::mul_base(&r).compress();
+        let k = ed25519ph_challenge(big_r.as_bytes(), &self.public, digest);
+        let sig_s = r + k * s;
+        s.zeroize();
+        r.zeroize();
+
+        let mut sig = [0u8; EDDSA_SIGNATURE_LENGTH];
+        sig[..32].copy_from_slice(big_r.as_bytes());
+        sig[32..].copy_from_slice(sig_s.as_bytes());
+        Ok(sig)
+    }
+
+    /// Verify an Ed25519ph signature over a SHA-512 message digest
+    pub fn verify_signature_prehashed(&self, digest: &[u8], signature: &[u8]) -> bool {
+        if digest.len() != 64 || signature.len() != EDDSA_SIGNATURE_LENGTH {
+            return false;
+        }
+        let (Some(a), Some(r)) = (
+            CompressedEdwardsY(self.public).decompress(),
+            CompressedEdwardsY(signature[..32].try_into().unwrap()).decompress(),
+        ) else {
+            return false;
+        };
+        if a.is_small_order() || r.is_small_order() {
+            return false;
+        }
+        let Some(s) = Option::<Scalar>::from(Scalar::from_canonical_bytes(
+            signature[32..].try_into().unwrap(),
+        )) else {
+            return false;
+        };
+        let k = ed25519ph_challenge(&signature[..32], &self.public, digest);
+        let check = EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &(-a), &s);
+        check.compress().as_bytes()[..] == signature[..32]
+    }
+
+    /// Produce an ECVRF-EDWARDS25519-SHA512-TAI proof (RFC 9381) for an input
+    ///
+    /// Returns the proof and the VRF hash output.
+    pub fn vrf_prove(
+        &self,
+        alpha: &[u8],
+    ) -> Result<([u8; VRF_PROOF_LENGTH], [u8; VRF_OUTPUT_LENGTH]), Error> {
+        let secret = self
+            .secret
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        let mut hash = [0u8; 64];
+        hash.copy_from_slice(&sha2::Sha512::digest(secret));
+        let mut x = Scalar::from_bytes_mod_order(clamp_integer(hash[..32].try_into().unwrap()));
+        let h = vrf_encode_to_curve(&self.public, alpha)?;
+        let h_string = h.compress().to_bytes();
+        let mut k_hash = sha2::Sha512::new()
+            .chain_update(&hash[32..])
+            .chain_update(h_string)
+            .finalize();
+        hash.copy_from_slice(&k_hash);
+        let mut k = Scalar::from_bytes_mod_order_wide(&hash);
+        hash.zeroize();
+        k_hash.as_mut_slice().zeroize();
+
+        let gamma = x * h;
+        let c = vrf_challenge([
+            &self.public,
+            &h_string,
+            &gamma.compress().to_bytes(),
+            &EdwardsPoint::mul_base(&k).compress().to_bytes(),
+            &(k * h).compress().to_bytes(),
+        ]);
+        let s = k + vrf_challenge_scalar(&c) * x;
+        x.zeroize();
+        k.zeroize();
+
+        let mut proof = [0u8; VRF_PROOF_LENGTH];
+        proof[..32].copy_from_slice(gamma.compress().as_bytes());
+        proof[32..48].copy_from_slice(&c);
+        proof[48..].copy_from_slice(s.as_bytes());
+        Ok((proof, vrf_proof_to_hash(&gamma)))
+    }
+
+    /// Verify an ECVRF-EDWARDS25519-SHA512-TAI proof (RFC 9381) for an input
+    ///
+    /// Returns the VRF hash output if the proof is valid.
+    pub fn vrf_verify(&self, alpha: &[u8], proof: &[u8]) -> Result<[u8; VRF_OUTPUT_LENGTH], Error> {
+        if proof.len() != VRF_PROOF_LENGTH {
+            return Err(err_msg!(Invalid, "Invalid VRF proof length"));
+        }
+        let y = CompressedEdwardsY(self.public)
+            .decompress()
+            .ok_or_else(|| err_msg!(InvalidKeyData))?;
+        if y.is_small_order() {
+            return Err(err_msg!(InvalidKeyData, "Invalid VRF public key"));
+        }
+        let gamma = CompressedEdwardsY(proof[..32].try_into().unwrap())
+            .decompress()
+            .ok_or_else(|| err_msg!(Invalid, "Invalid VRF proof"))?;
+        let c = vrf_challenge_scalar(proof[32..48].try_into().unwrap());
+        let s = Option::<Scalar>::from(Scalar::from_canonical_bytes(
+            proof[48..].try_into().unwrap(),
+        ))
+        .ok_or_else(|| err_msg!(Invalid, "Invalid VRF proof"))?;
+
+        let h = vrf_encode_to_curve(&self.public, alpha)?;
+        let u = EdwardsPoint::vartime_double_scalar_mul_basepoint(&(-c), &y, &s);
+        let v = s * h - c * gamma;
+        let check = vrf_challenge([
+            &self.public,
+            &h.compress().to_bytes(),
+            &gamma.compress().to_bytes(),
+            &u.compress().to_bytes(),
+            &v.compress().to_bytes(),
+        ]);
+        if check.ct_eq(&proof[32..48]).into() {
+            Ok(vrf_proof_to_hash(&gamma))
+        } else {
+            Err(err_msg!(Invalid, "Invalid VRF proof"))
+        }
+    }
+}
+
+fn ed25519ph_challenge(r: &[u8], public: &[u8; PUBLIC_KEY_LENGTH], digest: &[u8]) -> Scalar {
+    let mut hash = [0u8; 64];
+    hash.copy_from_slice(
+        &sha2::Sha512::new()
+            .chain_update(ED25519PH_PREFIX)
+            .chain_update(r)
+            .chain_update(public)
+            .chain_update(digest)
+            .finalize(),
+    );
+    Scalar::from_bytes_mod_order_wide(&hash)
+}
+
+/// Hash a VRF input to a curve point using the try-and-increment method
+fn vrf_encode_to_curve(
+    public: &[u8; PUBLIC_KEY_LENGTH],
+    alpha: &[u8],
+) -> Result<EdwardsPoint, Error> {
+    for ctr in 0..=255u8 {
+        let hash = sha2::Sha512::new()
+            .chain_update([VRF_SUITE, 0x01])
+            .chain_update(public)
+            .chain_update(alpha)
+            .chain_update([ctr, 0x00])
+            .finalize();
+        if let Some(pt) = CompressedEdwardsY(hash[..32].try_into().unwrap()).decompress() {
+            return Ok(pt.mul_by_cofactor());
+        }
+    }
+    Err(err_msg!(Unexpected, "Error encoding VRF input"))
+}
+
+fn vrf_challenge(points: [&[u8; 32]; 5]) -> [u8; 16] {
+    let mut hasher = sha2::Sha512::new();
+    hasher.update([VRF_SUITE, 0x02]);
+    for pt in points {
+        hasher.update(pt);
+    }
+    hasher.update([0x00]);
+    let mut c = [0u8; 16];
+    c.copy_from_slice(&hasher.finalize()[..16]);
+    c
+}
+
+fn vrf_challenge_scalar(c: &[u8; 16]) -> Scalar {
+    let mut bytes = [0u8; 32];
+    bytes[..16].copy_from_slice(c);
+    Scalar::from_bytes_mod_order(bytes)
+}
+
+fn vrf_proof_to_hash(gamma: &EdwardsPoint) -> [u8; VRF_OUTPUT_LENGTH] {
+    let mut output = [0u8; VRF_OUTPUT_LENGTH];
+    output.copy_from_slice(
+        &sha2::Sha512::new()
+            .chain_update([VRF_SUITE, 0x03])
+            .chain_update(gamma.mul_by_cofactor().compress().as_bytes())
+            .chain_update([0x00])
+            .finalize(),
+    );
+    output
 }
 
 impl Debug for Ed25519KeyPair {
@@ -211,10 +475,29 @@ impl KeySign for Ed25519KeyPair {
                     Err(err_msg!(MissingSecretKey))
                 }
             }
+            Some(SignatureType::Ed25519ph) => {
+                self.write_signature_prehashed(&sha2::Sha512::digest(message), sig_type, out)
+            }
             #[allow(unreachable_patterns)]
             _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
         }
     }
+
+    fn write_signature_prehashed(
+        &self,
+        digest: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        match sig_type {
+            None | Some(SignatureType::Ed25519ph) => {
+                let sig = self.sign_prehashed(digest)?;
+                out.buffer_write(&sig[..])?;
+                Ok(())
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
 }
 
 impl KeySigVerify for Ed25519KeyPair {
@@ -226,10 +509,27 @@ impl KeySigVerify for Ed25519KeyPair {
     ) -> Result<bool, Error> {
         match sig_type {
             None | Some(SignatureType::EdDSA) => Ok(self.verify_signature(message, signature)),
+            Some(SignatureType::Ed25519ph) => {
+                Ok(self.verify_signature_prehashed(&sha2::Sha512::digest(message), signature))
+            }
             #[allow(unreachable_patterns)]
             _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
         }
     }
+
+    fn verify_signature_prehashed(
+        &self,
+        digest: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        match sig_type {
+            None | Some(SignatureType::Ed25519ph) => {
+                Ok(self.verify_signature_prehashed(digest, signature))
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
 }
 
 impl ToJwk for Ed25519KeyPair {
@@ -322,6 +622,32 @@ mod tests {
         assert_eq!(cmp.to_keypair_bytes().unwrap(), &test_sk[..]);
     }
 
+    #[test]
+    fn verify_batch() {
+        let kps = [
+            Ed25519KeyPair::random().unwrap(),
+            Ed25519KeyPair::random().unwrap(),
+            Ed25519KeyPair::random().unwrap(),
+        ];
+        let messages: [&[u8]; 3] = [b"first", b"second", b"third"];
+        let sigs: Vec<[u8; EDDSA_SIGNATURE_LENGTH]> = kps
+            .iter()
+            .zip(messages)
+            .map(|(kp, msg)| kp.sign(msg).unwrap())
+            .collect();
+        let mut sig_refs: Vec<&[u8]> = sigs.iter().map(|s| &s[..]).collect();
+        assert!(Ed25519KeyPair::verify_batch(&messages, &sig_refs, &kps).unwrap());
+
+        let bad: [&[u8]; 3] = [b"first", b"other", b"third"];
+        assert!(!Ed25519KeyPair::verify_batch(&bad, &sig_refs, &kps).unwrap());
+
+        sig_refs.swap(0, 1);
+        assert!(!Ed25519KeyPair::verify_batch(&messages, &sig_refs, &kps).unwrap());
+        sig_refs[0] = &[0u8; 10];
+        assert!(!Ed25519KeyPair::verify_batch(&messages, &sig_refs, &kps).unwrap());
+        assert!(Ed25519KeyPair::verify_batch(&messages[..2], &sig_refs, &kps).is_err());
+    }
+
     #[test]
     fn ed25519_to_x25519() {
         let test_keypair = &hex!("1c1179a560d092b90458fe6ab8291215a427fcd6b3927cb240701778ef55201927c96646f2d4632d4fc241f84cbc427fbc3ecaa95becba55088d6c7b81fc5bbf");
@@ -334,6 +660,13 @@ mod tests {
             .unwrap();
         assert_eq!(&x_pair[..32], x_sk);
         assert_eq!(&x_pair[32..], x_pk);
+
+        // conversion of the public key alone produces the same result
+        let x_pub = Ed25519KeyPair::from_public_bytes(&test_keypair[32..])
+            .unwrap()
+            .to_x25519_keypair();
+        assert!(x_pub.secret.is_none());
+        assert_eq!(x_pub.to_public_bytes().unwrap(), &x_pk[..]);
     }
 
     #[test]
@@ -397,6 +730,64 @@ mod tests {
         assert!(!kp.verify_signature(test_msg, &[0u8; 64]));
     }
 
+    #[test]
+    fn sign_verify_prehashed_expected() {
+        // from RFC 8032 section 7.3, test 'abc'
+        let kp = Ed25519KeyPair::from_secret_bytes(&hex!(
+            "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42"
+        ))
+        .unwrap();
+        let test_sig = hex!(
+            "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae41
+            31f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406"
+        );
+        let digest = sha2::Sha512::digest(b"abc");
+        assert_eq!(kp.sign_prehashed(&digest).unwrap(), test_sig);
+        assert!(kp.verify_signature_prehashed(&digest, &test_sig));
+        assert!(!kp.verify_signature_prehashed(&sha2::Sha512::digest(b"abd"), &test_sig));
+        assert!(!kp.verify_signature(b"abc", &test_sig));
+
+        let sig = kp
+            .create_signature(b"abc", Some(SignatureType::Ed25519ph))
+            .unwrap();
+        assert_eq!(sig, &test_sig[..]);
+        assert!(KeySigVerify::verify_signature_prehashed(&kp, &digest, &sig, None).unwrap());
+    }
+
+    #[test]
+    fn vrf_expected() {
+        // from RFC 9381 Appendix B.3, example 16
+        let kp = Ed25519KeyPair::from_secret_bytes(&hex!(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"
+        ))
+        .unwrap();
+        let (proof, output) = kp.vrf_prove(b"").unwrap();
+        assert_eq!(
+            proof,
+            hex!(
+                "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f
+                26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab12
+                68a1b0db10836d9826a528ca76567805"
+            )
+        );
+        assert_eq!(
+            output,
+            hex!(
+                "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff
+                66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae"
+            )
+        );
+
+        let pk = Ed25519KeyPair::from_public_bytes(&kp.to_public_bytes().unwrap()).unwrap();
+        assert_eq!(pk.vrf_verify(b"", &proof).unwrap(), output);
+        assert!(pk.vrf_prove(b"").is_err());
+        assert!(pk.vrf_verify(b"input", &proof).is_err());
+        let mut modified = proof;
+        modified[40] ^= 1;
+        assert!(pk.vrf_verify(b"", &modified).is_err());
+        assert!(pk.vrf_verify(b"", &proof[..79]).is_err());
+    }
+
     #[test]
     fn round_trip_bytes() {
         let kp = Ed25519KeyPair::random().unwrap();
@@ -0,0 +1,602 @@
+//! Ed448 signature and verification key support
+
+use core::{
+    convert::TryInto,
+    fmt::{self, Debug, Formatter},
+};
+
+use ed448_goldilocks::{
+    curve::edwards::{CompressedEdwardsY, ExtendedPoint},
+    Scalar,
+};
+use sha3::{
+    digest::{ExtendableOutput, Update, XofReader},
+    Shake256,
+};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::{HasKeyAlg, HasKeyBackend, KeyAlg};
+use crate::{
+    buffer::{ArrayKey, WriteBuffer},
+    error::Error,
+    generic_array::typenum::{U114, U57},
+    jwk::{FromJwk, JwkEncoder, JwkParts, ToJwk},
+    random::KeyMaterial,
+    repr::{KeyGen, KeyMeta, KeyPublicBytes, KeySecretBytes, KeypairBytes, KeypairMeta},
+    sign::{KeySigVerify, KeySign, SignatureType},
+};
+
+/// The length of a public key in bytes
+pub const PUBLIC_KEY_LENGTH: usize = 57;
+/// The length of a secret key in bytes
+pub const SECRET_KEY_LENGTH: usize = 57;
+/// The length of a keypair in bytes
+pub const KEYPAIR_LENGTH: usize = SECRET_KEY_LENGTH + PUBLIC_KEY_LENGTH;
+/// The length of an Ed448 signature
+pub const SIGNATURE_LENGTH: usize = 114;
+
+/// The 'kty' value of an Ed448 JWK
+pub static JWK_KEY_TYPE: &str = "OKP";
+/// The 'crv' value of an Ed448 JWK
+pub static JWK_CURVE: &str = "Ed448";
+
+/// The value of 2^448 modulo the group order
+const SCALAR_2_448: [u8; 56] = [
+    0x34, 0xec, 0x9e, 0x52, 0xb5, 0xf5, 0x1c, 0x72, 0xab, 0xc2, 0xe9, 0xc8, 0x35, 0xf6, 0x4c, 0x7a,
+    0xbf, 0x25, 0xa7, 0x44, 0xd9, 0x92, 0xc4, 0xee, 0x58, 0x70, 0xd7, 0x0c, 0x02, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// The signature context prefix for pure Ed448 with an empty context
+const DOM4_PREFIX: &[u8] = b"SigEd448\x00\x00";
+
+/// An Ed448 public key or keypair
+#[derive(Clone)]
+pub struct Ed448KeyPair {
+    secret: Option<[u8; SECRET_KEY_LENGTH]>,
+    public: [u8; PUBLIC_KEY_LENGTH],
+}
+
+impl Ed448KeyPair {
+    #[inline]
+    pub(crate) fn from_secret_key(secret: &[u8; SECRET_KEY_LENGTH]) -> Self {
+        let mut expanded = expand_secret(secret);
+        let public =
+            encode_point(&ExtendedPoint::generator().scalar_mul(&secret_scalar(&expanded)));
+        expanded.zeroize();
+        Self {
+            secret: Some(*secret),
+            public,
+        }
+    }
+
+    pub(crate) fn check_public_bytes(&self, pk: &[u8]) -> Result<(), Error> {
+        if self.public.ct_eq(pk).into() {
+            Ok(())
+        } else {
+            Err(err_msg!(InvalidKeyData, "invalid ed448 keypair"))
+        }
+    }
+
+    /// Sign a message with the secret key
+    pub fn sign(&self, message: &[u8]) -> Option<[u8; SIGNATURE_LENGTH]> {
+        let secret = self.secret.as_ref()?;
+        let mut expanded = expand_secret(secret);
+        let s = secret_scalar(&expanded);
+        let r = hash_scalar(&[DOM4_PREFIX, &expanded[57..], message]);
+        expanded.zeroize();
+        let r_enc = encode_point(&ExtendedPoint::generator().scalar_mul(&r));
+        let h = hash_scalar(&[DOM4_PREFIX, &r_enc, &self.public, message]);
+
+        let mut sig = [0u8; SIGNATURE_LENGTH];
+        sig[..57].copy_from_slice(&r_enc);
+        sig[57..113].copy_from_slice(&(r + h * s).to_bytes());
+        Some(sig)
+    }
+
+    /// Verify a signature against the public key
+    pub fn verify_signature(&self, message: &[u8], signature: &[u8]) -> bool {
+        if signature.len() != SIGNATURE_LENGTH {
+            return false;
+        }
+        let (r_bytes, s_bytes) = signature.split_at(PUBLIC_KEY_LENGTH);
+        let (Some(a), Some(r)) = (decode_point(&self.public), decode_point(r_bytes)) else {
+            return false;
+        };
+        // the scalar S must be canonical
+        let mut s_arr = [0u8; 56];
+        s_arr.copy_from_slice(&s_bytes[..56]);
+        let s = Scalar::from_bytes(s_arr);
+        if s_bytes[56] != 0 || (s * Scalar::one()).to_bytes() != s_arr {
+            return false;
+        }
+        let h = hash_scalar(&[DOM4_PREFIX, r_bytes, &self.public, message]);
+
+        // check [4][S]B = [4]R + [4][h]A
+        let lhs = ExtendedPoint::generator().scalar_mul(&s).double().double();
+        let rhs = r.add(&a.scalar_mul(&h)).double().double();
+        lhs == rhs
+    }
+}
+
+/// Expand a secret key into the secret scalar and the nonce prefix
+fn expand_secret(secret: &[u8; SECRET_KEY_LENGTH]) -> [u8; 114] {
+    let mut expanded = [0u8; 114];
+    let mut xof = Shake256::default();
+    xof.update(secret);
+    xof.finalize_xof().read(&mut expanded);
+    expanded
+}
+
+/// Clamp the first half of an expanded secret key to produce the secret scalar
+fn secret_scalar(expanded: &[u8; 114]) -> Scalar {
+    let mut buf = [0u8; 56];
+    buf.copy_from_slice(&expanded[..56]);
+    buf[0] &= 0xfc;
+    buf[55] |= 0x80;
+    // the clamped value may exceed the group order, and is reduced by multiplication
+    let s = Scalar::from_bytes(buf) * Scalar::one();
+    buf.zeroize();
+    s
+}
+
+/// Hash the concatenated inputs with SHAKE256 and reduce the result to a scalar
+fn hash_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut hash = [0u8; 114];
+    let mut xof = Shake256::default();
+    for part in parts {
+        xof.update(part);
+    }
+    xof.finalize_xof().read(&mut hash);
+    reduce_scalar(&hash)
+}
+
+/// Encode a point, recording the sign of the x coordinate in the top bit of the final byte
+fn encode_point(point: &ExtendedPoint) -> [u8; PUBLIC_KEY_LENGTH] {
+    let mut encoded = point.compress().0;
+    encoded[56] <<= 7;
+    encoded
+}
+
+fn decode_point(encoded: &[u8]) -> Option<ExtendedPoint> {
+    let encoded: [u8; PUBLIC_KEY_LENGTH] = encoded.try_into().ok()?;
+    if encoded[56] & 0x7f != 0 {
+        return None;
+    }
+    let negative = encoded[56] != 0;
+    let point = CompressedEdwardsY(encoded).decompress()?;
+    // the sign of the recovered x coordinate is not normalized, and the
+    // compressed form only records the sign in the lowest bit of the last
+    // byte. Non-canonical encodings are rejected
+    [point, point.negate()].into_iter().find(|p| {
+        let check = p.compress().0;
+        check[..56] == encoded[..56] && (check[56] != 0) == negative
+    })
+}
+
+/// Reduce a 114 byte little-endian value modulo the group order
+fn reduce_scalar(wide: &[u8; 114]) -> Scalar {
+    let chunk = |range: core::ops::Range<usize>| {
+        let mut buf = [0u8; 56];
+        buf[..range.len()].copy_from_slice(&wide[range]);
+        // the chunk may exceed the group order, and is reduced by multiplication
+        Scalar::from_bytes(buf) * Scalar::one()
+    };
+    let k = Scalar::from_bytes(SCALAR_2_448);
+    chunk(0..56) + (chunk(56..112) + chunk(112..114) * k) * k
+}
+
+impl Debug for Ed448KeyPair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ed448KeyPair")
+            .field(
+                "secret",
+                if self.secret.is_some() {
+                    &"<secret>"
+                } else {
+                    &"None"
+                },
+            )
+            .field("public", &&self.public[..])
+            .finish()
+    }
+}
+
+impl KeyGen for Ed448KeyPair {
+    fn generate(rng: impl KeyMaterial) -> Result<Self, Error> {
+        let sk = ArrayKey::<U57>::generate(rng);
+        Ok(Self::from_secret_key((&*sk).try_into().unwrap()))
+    }
+}
+
+impl HasKeyBackend for Ed448KeyPair {}
+
+impl HasKeyAlg for Ed448KeyPair {
+    fn algorithm(&self) -> KeyAlg {
+        KeyAlg::Ed448
+    }
+}
+
+impl KeyMeta for Ed448KeyPair {
+    type KeySize = U57;
+}
+
+impl KeySecretBytes for Ed448KeyPair {
+    fn from_secret_bytes(key: &[u8]) -> Result<Self, Error> {
+        let sk: &[u8; SECRET_KEY_LENGTH] = key.try_into().map_err(|_| err_msg!(InvalidKeyData))?;
+        Ok(Self::from_secret_key(sk))
+    }
+
+    fn with_secret_bytes<O>(&self, f: impl FnOnce(Option<&[u8]>) -> O) -> O {
+        f(self.secret.as_ref().map(|sk| &sk[..]))
+    }
+}
+
+impl KeypairMeta for Ed448KeyPair {
+    type PublicKeySize = U57;
+    type KeypairSize = U114;
+}
+
+impl KeypairBytes for Ed448KeyPair {
+    fn from_keypair_bytes(kp: &[u8]) -> Result<Self, Error> {
+        if kp.len() != KEYPAIR_LENGTH {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        let result = Ed448KeyPair::from_secret_bytes(&kp[..SECRET_KEY_LENGTH])?;
+        result.check_public_bytes(&kp[SECRET_KEY_LENGTH..])?;
+        Ok(result)
+    }
+
+    fn with_keypair_bytes<O>(&self, f: impl FnOnce(Option<&[u8]>) -> O) -> O {
+        if let Some(secret) = self.secret.as_ref() {
+            ArrayKey::<<Self as KeypairMeta>::KeypairSize>::temp(|arr| {
+                arr[..SECRET_KEY_LENGTH].copy_from_slice(secret);
+                arr[SECRET_KEY_LENGTH..].copy_from_slice(&self.public[..]);
+                f(Some(&*arr))
+            })
+        } else {
+            f(None)
+        }
+    }
+}
+
+impl KeyPublicBytes for Ed448KeyPair {
+    fn from_public_bytes(key: &[u8]) -> Result<Self, Error> {
+        if decode_point(key).is_none() {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        Ok(Self {
+            secret: None,
+            public: key.try_into().unwrap(),
+        })
+    }
+
+    fn with_public_bytes<O>(&self, f: impl FnOnce(&[u8]) -> O) -> O {
+        f(&self.public[..])
+    }
+}
+
+impl KeySign for Ed448KeyPair {
+    fn write_signature(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        match sig_type {
+            None | Some(SignatureType::EdDSA) | Some(SignatureType::Ed448) => {
+                if self.secret.is_none() {
+                    return Err(err_msg!(MissingSecretKey));
+                }
+                if let Some(sig) = self.sign(message) {
+                    out.buffer_write(&sig[..])?;
+                    Ok(())
+                } else {
+                    Err(err_msg!(Unexpected, "Error creating ed448 signature"))
+                }
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+}
+
+impl KeySigVerify for Ed448KeyPair {
+    fn verify_signature(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        match sig_type {
+            None | Some(SignatureType::EdDSA) | Some(SignatureType::Ed448) => {
+                Ok(self.verify_signature(message, signature))
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+}
+
+impl ToJwk for Ed448KeyPair {
+    fn encode_jwk(&self, enc: &mut dyn JwkEncoder) -> Result<(), Error> {
+        enc.add_str("crv", JWK_CURVE)?;
+        enc.add_str("kty", JWK_KEY_TYPE)?;
+        self.with_public_bytes(|buf| enc.add_as_base64("x", buf))?;
+        if enc.is_secret() {
+            self.with_secret_bytes(|buf| {
+                if let Some(sk) = buf {
+                    enc.add_as_base64("d", sk)
+                } else {
+                    Ok(())
+                }
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl FromJwk for Ed448KeyPair {
+    fn from_jwk_parts(jwk: JwkParts<'_>) -> Result<Self, Error> {
+        if jwk.kty != JWK_KEY_TYPE {
+            return Err(err_msg!(InvalidKeyData, "Unsupported key type"));
+        }
+        if jwk.crv != JWK_CURVE {
+            return Err(err_msg!(InvalidKeyData, "Unsupported key algorithm"));
+        }
+        ArrayKey::<U57>::temp(|pk_arr| {
+            if jwk.x.decode_base64(pk_arr)? != pk_arr.len() {
+                Err(err_msg!(InvalidKeyData))
+            } else if jwk.d.is_some() {
+                ArrayKey::<U57>::temp(|sk_arr| {
+                    if jwk.d.decode_base64(sk_arr)? != sk_arr.len() {
+                        Err(err_msg!(InvalidKeyData))
+                    } else {
+                        let kp = Ed448KeyPair::from_secret_bytes(sk_arr)?;
+                        kp.check_public_bytes(pk_arr)?;
+                        Ok(kp)
+                    }
+                })
+            } else {
+                Ed448KeyPair::from_public_bytes(pk_arr)
+            }
+        })
+    }
+}
+
+impl Drop for Ed448KeyPair {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+        self.public.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Ed448KeyPair {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::{ToPublicBytes, ToSecretBytes};
+
+    /// The encoding of the group order
+    const ORDER: [u8; 56] = hex!(
+        "f34458ab92c27823558fc58d72c26c219036d6ae49db4ec4e923ca7cffffffff
+        ffffffffffffffffffffffffffffffffffffffffffffff3f"
+    );
+
+    #[test]
+    fn sign_verify_expected() {
+        // RFC 8032 section 7.4, 1 octet test vector
+        let test_sk = &hex!(
+            "c4eab05d357007c632f3dbb48489924d552b08fe0c353a0d4a1f00acda2c463a
+            fbea67c5e8d2877c5e3bc397a659949ef8021e954e0a12274e"
+        );
+        let test_pk = &hex!(
+            "43ba28f430cdff456ae531545f7ecd0ac834a55d9358c0372bfa0c6c6798c086
+            6aea01eb00742802b8438ea4cb82169c235160627b4c3a9480"
+        );
+        let test_msg = &hex!("03");
+        let test_sig = &hex!(
+            "26b8f91727bd62897af15e41eb43c377efb9c610d48f2335cb0bd0087810f435
+            2541b143c4b981b7e18f62de8ccdf633fc1bf037ab7cd779805e0dbcc0aae1cb
+            cee1afb2e027df36bc04dcecbf154336c19f0af7e0a6472905e799f1953d2a0f
+            f3348ab21aa4adafd1d234441cf807c03a00"
+        );
+        let kp = Ed448KeyPair::from_secret_bytes(test_sk).unwrap();
+        assert_eq!(kp.to_public_bytes().unwrap(), &test_pk[..]);
+        let sig = kp.sign(test_msg).unwrap();
+        assert_eq!(&sig[..], &test_sig[..]);
+        assert!(kp.verify_signature(test_msg, &sig[..]));
+        assert!(!kp.verify_signature(b"Not the message", &sig[..]));
+        assert!(!kp.verify_signature(test_msg, &[0u8; SIGNATURE_LENGTH]));
+
+        let pk = Ed448KeyPair::from_public_bytes(test_pk).unwrap();
+        assert!(pk.verify_signature(test_msg, &test_sig[..]));
+        assert!(!pk.verify_signature(b"Not the message", &test_sig[..]));
+    }
+
+    #[test]
+    fn rfc8032_vectors() {
+        // RFC 8032 section 7.4, pure Ed448 test vectors without a context
+        let vectors: &[[&[u8]; 4]] = &[
+            [
+                // blank
+                &hex!(
+                    "6c82a562cb808d10d632be89c8513ebf6c929f34ddfa8c9f63c9960ef6e348a3
+                    528c8a3fcc2f044e39a3fc5b94492f8f032e7549a20098f95b"
+                ),
+                &hex!(
+                    "5fd7449b59b461fd2ce787ec616ad46a1da1342485a70e1f8a0ea75d80e96778
+                    edf124769b46c7061bd6783df1e50f6cd1fa1abeafe8256180"
+                ),
+                b"",
+                &hex!(
+                    "533a37f6bbe457251f023c0d88f976ae2dfb504a843e34d2074fd823d41a591f
+                    2b233f034f628281f2fd7a22ddd47d7828c59bd0a21bfd3980ff0d2028d4b18a
+                    9df63e006c5d1c2d345b925d8dc00b4104852db99ac5c7cdda8530a113a0f4db
+                    b61149f05a7363268c71d95808ff2e652600"
+                ),
+            ],
+            [
+                // 11 octets
+                &hex!(
+                    "cd23d24f714274e744343237b93290f511f6425f98e64459ff203e8985083ffd
+                    f60500553abc0e05cd02184bdb89c4ccd67e187951267eb328"
+                ),
+                &hex!(
+                    "dcea9e78f35a1bf3499a831b10b86c90aac01cd84b67a0109b55a36e9328b1e3
+                    65fce161d71ce7131a543ea4cb5f7e9f1d8b00696447001400"
+                ),
+                &hex!("0c3e544074ec63b0265e0c"),
+                &hex!(
+                    "1f0a8888ce25e8d458a21130879b840a9089d999aaba039eaf3e3afa090a09d3
+                    89dba82c4ff2ae8ac5cdfb7c55e94d5d961a29fe0109941e00b8dbdeea6d3b05
+                    1068df7254c0cdc129cbe62db2dc957dbb47b51fd3f213fb8698f064774250a5
+                    028961c9bf8ffd973fe5d5c206492b140e00"
+                ),
+            ],
+            [
+                // 12 octets
+                &hex!(
+                    "258cdd4ada32ed9c9ff54e63756ae582fb8fab2ac721f2c8e676a72768513d93
+                    9f63dddb55609133f29adf86ec9929dccb52c1c5fd2ff7e21b"
+                ),
+                &hex!(
+                    "3ba16da0c6f2cc1f30187740756f5e798d6bc5fc015d7c63cc9510ee3fd44adc
+                    24d8e968b6e46e6f94d19b945361726bd75e149ef09817f580"
+                ),
+                &hex!("64a65f3cdedcdd66811e2915"),
+                &hex!(
+                    "7eeeab7c4e50fb799b418ee5e3197ff6bf15d43a14c34389b59dd1a7b1b85b4a
+                    e90438aca634bea45e3a2695f1270f07fdcdf7c62b8efeaf00b45c2c96ba457e
+                    b1a8bf075a3db28e5c24f6b923ed4ad747c3c9e03c7079efb87cb110d3a99861
+                    e72003cbae6d6b8b827e4e6c143064ff3c00"
+                ),
+            ],
+            [
+                // 13 octets
+                &hex!(
+                    "7ef4e84544236752fbb56b8f31a23a10e42814f5f55ca037cdcc11c64c9a3b29
+                    49c1bb60700314611732a6c2fea98eebc0266a11a93970100e"
+                ),
+                &hex!(
+                    "b3da079b0aa493a5772029f0467baebee5a8112d9d3a22532361da294f7bb381
+                    5c5dc59e176b4d9f381ca0938e13c6c07b174be65dfa578e80"
+                ),
+                &hex!("64a65f3cdedcdd66811e2915e7"),
+                &hex!(
+                    "6a12066f55331b6c22acd5d5bfc5d71228fbda80ae8dec26bdd306743c5027cb
+                    4890810c162c027468675ecf645a83176c0d7323a2ccde2d80efe5a1268e8aca
+                    1d6fbc194d3f77c44986eb4ab4177919ad8bec33eb47bbb5fc6e28196fd1caf5
+                    6b4e7e0ba5519234d047155ac727a1053100"
+                ),
+            ],
+            [
+                // 64 octets
+                &hex!(
+                    "d65df341ad13e008567688baedda8e9dcdc17dc024974ea5b4227b6530e339bf
+                    f21f99e68ca6968f3cca6dfe0fb9f4fab4fa135d5542ea3f01"
+                ),
+                &hex!(
+                    "df9705f58edbab802c7f8363cfe5560ab1c6132c20a9f1dd163483a26f8ac53a
+                    39d6808bf4a1dfbd261b099bb03b3fb50906cb28bd8a081f00"
+                ),
+                &hex!(
+                    "bd0f6a3747cd561bdddf4640a332461a4a30a12a434cd0bf40d766d9c6d458e5
+                    512204a30c17d1f50b5079631f64eb3112182da3005835461113718d1a5ef944"
+                ),
+                &hex!(
+                    "554bc2480860b49eab8532d2a533b7d578ef473eeb58c98bb2d0e1ce488a98b1
+                    8dfde9b9b90775e67f47d4a1c3482058efc9f40d2ca033a0801b63d45b3b722e
+                    f552bad3b4ccb667da350192b61c508cf7b6b5adadc2c8d9a446ef003fb05cba
+                    5f30e88e36ec2703b349ca229c2670833900"
+                ),
+            ],
+        ];
+        for &[sk, pk, msg, sig] in vectors {
+            let kp = Ed448KeyPair::from_secret_bytes(sk).unwrap();
+            assert_eq!(kp.to_public_bytes().unwrap(), pk);
+            assert_eq!(&kp.sign(msg).unwrap()[..], sig);
+            let pk = Ed448KeyPair::from_public_bytes(pk).unwrap();
+            assert!(pk.verify_signature(msg, sig));
+        }
+    }
+
+    #[test]
+    fn reject_non_canonical() {
+        let kp = Ed448KeyPair::random().unwrap();
+        let msg = b"message";
+        let sig = kp.sign(msg).unwrap();
+        assert!(kp.verify_signature(msg, &sig[..]));
+
+        // S + L encodes the same scalar, but is not reduced
+        let mut s_plus_l = sig;
+        let mut carry = 0u16;
+        for (b, o) in s_plus_l[57..113].iter_mut().zip(ORDER) {
+            let v = *b as u16 + o as u16 + carry;
+            *b = v as u8;
+            carry = v >> 8;
+        }
+        assert_eq!(carry, 0);
+        assert!(!kp.verify_signature(msg, &s_plus_l[..]));
+
+        // any set bits in the final octet of S are rejected
+        let mut high_s = sig;
+        high_s[113] = 1;
+        assert!(!kp.verify_signature(msg, &high_s[..]));
+
+        // the point (1, 0) is accepted with y = 0, but not with y encoded as p
+        let y_zero = [0u8; PUBLIC_KEY_LENGTH];
+        let y_field_order = hex!(
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffeffffff
+            ffffffffffffffffffffffffffffffffffffffffffffffff00"
+        );
+        assert!(Ed448KeyPair::from_public_bytes(&y_zero).is_ok());
+        assert!(Ed448KeyPair::from_public_bytes(&y_field_order).is_err());
+
+        // the same encoding is rejected for R
+        let mut bad_r = sig;
+        bad_r[..57].copy_from_slice(&y_field_order);
+        assert!(!kp.verify_signature(msg, &bad_r[..]));
+
+        // unused bits in the final octet of the public key are rejected
+        let mut pk = kp.to_public_bytes().unwrap().to_vec();
+        pk[56] |= 0x01;
+        assert!(Ed448KeyPair::from_public_bytes(&pk).is_err());
+    }
+
+    #[test]
+    fn jwk_round_trip() {
+        let kp = Ed448KeyPair::random().unwrap();
+        let jwk = kp
+            .to_jwk_public(None)
+            .expect("Error converting public key to JWK");
+        let jwk = JwkParts::try_from_str(&jwk).expect("Error parsing JWK output");
+        assert_eq!(jwk.kty, JWK_KEY_TYPE);
+        assert_eq!(jwk.crv, JWK_CURVE);
+        let pk_load = Ed448KeyPair::from_jwk_parts(jwk).unwrap();
+        assert_eq!(kp.to_public_bytes(), pk_load.to_public_bytes());
+
+        let jwk = kp
+            .to_jwk_secret(None)
+            .expect("Error converting private key to JWK");
+        let jwk = JwkParts::from_slice(&jwk).expect("Error parsing JWK output");
+        let sk_load = Ed448KeyPair::from_jwk_parts(jwk).unwrap();
+        assert_eq!(
+            kp.to_secret_bytes().unwrap(),
+            sk_load.to_secret_bytes().unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip_bytes() {
+        let kp = Ed448KeyPair::random().unwrap();
+        let cmp = Ed448KeyPair::from_keypair_bytes(&kp.to_keypair_bytes().unwrap()).unwrap();
+        assert_eq!(
+            kp.to_keypair_bytes().unwrap(),
+            cmp.to_keypair_bytes().unwrap()
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! HMAC-SHA2 key representations with signing support
+
+use core::fmt::{self, Debug, Formatter};
+
+use digest::{core_api::BlockSizeUser, Digest};
+use hmac::{Mac, SimpleHmac};
+use sha2::{Sha256, Sha384, Sha512};
+use zeroize::Zeroize;
+
+use super::{HasKeyAlg, HasKeyBackend, HmacTypes, KeyAlg};
+use crate::{
+    buffer::{ArrayKey, WriteBuffer},
+    error::Error,
+    generic_array::{
+        typenum::{Unsigned, U32, U48, U64},
+        ArrayLength,
+    },
+    jwk::{FromJwk, JwkEncoder, JwkParts, ToJwk},
+    kdf::{FromKeyDerivation, KeyDerivation},
+    random::KeyMaterial,
+    repr::{KeyGen, KeyMeta, KeySecretBytes},
+    sign::{KeySigVerify, KeySign, SignatureType},
+};
+
+/// The 'kty' value of a symmetric key JWK
+pub static JWK_KEY_TYPE: &str = "oct";
+
+/// Trait implemented by supported HMAC algorithms
+pub trait HmacType: 'static {
+    /// The hash function
+    type Hash: Digest + BlockSizeUser + Clone;
+    /// The size of the key secret bytes, equal to the hash output size
+    type KeySize: ArrayLength<u8>;
+
+    /// The associated algorithm type
+    const ALG_TYPE: HmacTypes;
+    /// The associated JWK algorithm name
+    const JWK_ALG: &'static str;
+    /// The associated signature type
+    const SIG_TYPE: SignatureType;
+}
+
+/// HMAC using SHA-256
+#[derive(Debug)]
+pub struct HS256;
+
+impl HmacType for HS256 {
+    type Hash = Sha256;
+    type KeySize = U32;
+
+    const ALG_TYPE: HmacTypes = HmacTypes::HS256;
+    const JWK_ALG: &'static str = "HS256";
+    const SIG_TYPE: SignatureType = SignatureType::HS256;
+}
+
+/// HMAC using SHA-384
+#[derive(Debug)]
+pub struct HS384;
+
+impl HmacType for HS384 {
+    type Hash = Sha384;
+    type KeySize = U48;
+
+    const ALG_TYPE: HmacTypes = HmacTypes::HS384;
+    const JWK_ALG: &'static str = "HS384";
+    const SIG_TYPE: SignatureType = SignatureType::HS384;
+}
+
+/// HMAC using SHA-512
+#[derive(Debug)]
+pub struct HS512;
+
+impl HmacType for HS512 {
+    type Hash = Sha512;
+    type KeySize = U64;
+
+    const ALG_TYPE: HmacTypes = HmacTypes::HS512;
+    const JWK_ALG: &'static str = "HS512";
+    const SIG_TYPE: SignatureType = SignatureType::HS512;
+}
+
+type KeyType<T> = ArrayKey<<T as HmacType>::KeySize>;
+
+/// An HMAC-SHA2 symmetric signing key
+#[derive(Zeroize)]
+// SECURITY: ArrayKey is zeroized on drop
+pub struct HmacKey<T: HmacType>(KeyType<T>);
+
+impl<T: HmacType> HmacKey<T> {
+    /// The length of the secret key in bytes
+    pub const KEY_LENGTH: usize = KeyType::<T>::SIZE;
+    /// The length of the HMAC output in bytes
+    pub const TAG_LENGTH: usize = T::KeySize::USIZE;
+
+    fn hmac(&self, message: &[u8]) -> SimpleHmac<T::Hash> {
+        let mut mac = <SimpleHmac<T::Hash> as Mac>::new_from_slice(self.0.as_ref())
+            .expect("HMAC accepts any key size");
+        mac.update(message);
+        mac
+    }
+
+    /// Compute the HMAC of a message
+    pub fn sign(&self, message: &[u8], out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        out.buffer_write(&self.hmac(message).finalize().into_bytes()[..])
+    }
+
+    /// Verify the HMAC of a message in constant time
+    pub fn verify_signature(&self, message: &[u8], signature: &[u8]) -> bool {
+        self.hmac(message).verify_slice(signature).is_ok()
+    }
+}
+
+impl<T: HmacType> Clone for HmacKey<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: HmacType> Debug for HmacKey<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmacKey")
+            .field("alg", &T::JWK_ALG)
+            .field("key", &self.0)
+            .finish()
+    }
+}
+
+impl<T: HmacType> PartialEq for HmacKey<T> {
+    fn eq(&self, other: &Self) -> bool {
+        other.0 == self.0
+    }
+}
+
+impl<T: HmacType> Eq for HmacKey<T> {}
+
+impl<T: HmacType> HasKeyBackend for HmacKey<T> {}
+
+impl<T: HmacType> HasKeyAlg for HmacKey<T> {
+    fn algorithm(&self) -> KeyAlg {
+        KeyAlg::Hmac(T::ALG_TYPE)
+    }
+}
+
+impl<T: HmacType> KeyMeta for HmacKey<T> {
+    type KeySize = T::KeySize;
+}
+
+impl<T: HmacType> KeyGen for HmacKey<T> {
+    fn generate(rng: impl KeyMaterial) -> Result<Self, Error> {
+        Ok(HmacKey(KeyType::<T>::generate(rng)))
+    }
+}
+
+impl<T: HmacType> KeySecretBytes for HmacKey<T> {
+    fn from_secret_bytes(key: &[u8]) -> Result<Self, Error> {
+        if key.len() != KeyType::<T>::SIZE {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        Ok(Self(KeyType::<T>::from_slice(key)))
+    }
+
+    fn with_secret_bytes<O>(&self, f: impl FnOnce(Option<&[u8]>) -> O) -> O {
+        f(Some(self.0.as_ref()))
+    }
+}
+
+impl<T: HmacType> FromKeyDerivation for HmacKey<T> {
+    fn from_key_derivation<D: KeyDerivation>(mut derive: D) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        Ok(Self(KeyType::<T>::try_new_with(|arr| {
+            derive.derive_key_bytes(arr)
+        })?))
+    }
+}
+
+impl<T: HmacType> KeySign for HmacKey<T> {
+    fn write_signature(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        match sig_type {
+            None => self.sign(message, out),
+            Some(s) if s == T::SIG_TYPE => self.sign(message, out),
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+}
+
+impl<T: HmacType> KeySigVerify for HmacKey<T> {
+    fn verify_signature(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        match sig_type {
+            None => Ok(self.verify_signature(message, signature)),
+            Some(s) if s == T::SIG_TYPE => Ok(self.verify_signature(message, signature)),
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+}
+
+impl<T: HmacType> ToJwk for HmacKey<T> {
+    fn encode_jwk(&self, enc: &mut dyn JwkEncoder) -> Result<(), Error> {
+        if enc.is_public() {
+            return Err(err_msg!(Unsupported, "Cannot export as a public key"));
+        }
+        if !enc.is_thumbprint() {
+            enc.add_str("alg", T::JWK_ALG)?;
+        }
+        enc.add_as_base64("k", self.0.as_ref())?;
+        enc.add_str("kty", JWK_KEY_TYPE)?;
+        Ok(())
+    }
+}
+
+impl<T: HmacType> FromJwk for HmacKey<T> {
+    fn from_jwk_parts(jwk: JwkParts<'_>) -> Result<Self, Error> {
+        if jwk.kty != JWK_KEY_TYPE {
+            return Err(err_msg!(InvalidKeyData, "Unsupported key type"));
+        }
+        if jwk.alg.is_some() && jwk.alg != T::JWK_ALG {
+            return Err(err_msg!(InvalidKeyData, "Unsupported key algorithm"));
+        }
+        Ok(Self(KeyType::<T>::try_new_with(|arr| {
+            if jwk.k.decode_base64(arr)? != arr.len() {
+                Err(err_msg!(InvalidKeyData))
+            } else {
+                Ok(())
+            }
+        })?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::ToSecretBytes;
+
+    #[test]
+    // from RFC 4231 test case 2, using a zero-padded key
+    fn sign_expected() {
+        let mut key = [0u8; 64];
+        key[..4].copy_from_slice(b"Jefe");
+        let message = b"what do ya want for nothing?";
+
+        let key256 = HmacKey::<HS256>::from_secret_bytes(&key[..32]).unwrap();
+        let sig = key256.create_signature(message, None).unwrap();
+        assert_eq!(
+            &sig[..],
+            &hex!("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+        assert!(key256.verify_signature(message, &sig));
+        assert!(!key256.verify_signature(b"other", &sig));
+        assert!(
+            KeySigVerify::verify_signature(&key256, message, &sig, Some(SignatureType::HS256))
+                .unwrap()
+        );
+        assert!(
+            KeySigVerify::verify_signature(&key256, message, &sig, Some(SignatureType::HS512))
+                .is_err()
+        );
+
+        let key512 = HmacKey::<HS512>::from_secret_bytes(&key).unwrap();
+        let sig = key512.create_signature(message, None).unwrap();
+        assert_eq!(
+            &sig[..],
+            &hex!(
+                "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554
+                9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+            )
+        );
+        assert_eq!(sig.len(), HmacKey::<HS512>::TAG_LENGTH);
+
+        let key384 = HmacKey::<HS384>::from_secret_bytes(&key[..48]).unwrap();
+        let sig = key384.create_signature(message, None).unwrap();
+        assert_eq!(
+            &sig[..],
+            &hex!(
+                "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e
+                8e2240ca5e69e2c78b3239ecfab21649"
+            )
+        );
+    }
+
+    #[test]
+    fn jwk_round_trip() {
+        let key = HmacKey::<HS384>::random().unwrap();
+        let jwk = key.to_jwk_secret(None).unwrap();
+        let parsed = JwkParts::from_slice(jwk.as_ref()).unwrap();
+        assert_eq!(parsed.kty, JWK_KEY_TYPE);
+        assert_eq!(parsed.alg, "HS384");
+        let loaded = HmacKey::<HS384>::from_jwk_parts(parsed).unwrap();
+        assert_eq!(
+            loaded.to_secret_bytes().unwrap(),
+            key.to_secret_bytes().unwrap()
+        );
+        assert!(HmacKey::<HS512>::from_jwk_slice(jwk.as_ref()).is_err());
+    }
+}
@@ -2,8 +2,11 @@
 
 use k256::{
     ecdsa::{
-        signature::{Signer, Verifier},
-        Signature, SigningKey, VerifyingKey,
+        signature::{
+            hazmat::{PrehashSigner, PrehashVerifier},
+            Signer, Verifier,
+        },
+        DerSignature, RecoveryId, Signature, SigningKey, VerifyingKey,
     },
     elliptic_curve::{
         self,
@@ -12,6 +15,7 @@ use k256::{
     },
     EncodedPoint, PublicKey, SecretKey,
 };
+use sha2::{Digest, Sha256};
 use subtle::ConstantTimeEq;
 
 use super::{ec_common, EcCurves, HasKeyAlg, HasKeyBackend, KeyAlg};
@@ -23,7 +27,7 @@ use crate::{
     kdf::KeyExchange,
     random::KeyMaterial,
     repr::{KeyGen, KeyMeta, KeyPublicBytes, KeySecretBytes, KeypairBytes, KeypairMeta},
-    sign::{KeySigVerify, KeySign, SignatureType},
+    sign::{KeySigVerify, KeySign, SignatureFormat, SignatureType},
 };
 
 // SECURITY: PublicKey contains a k256::AffinePoint, which is always checked
@@ -36,6 +40,8 @@ use crate::{
 
 /// The length of an ES256K signature
 pub const ES256K_SIGNATURE_LENGTH: usize = 64;
+/// The length of an ES256K-R recoverable signature
+pub const ES256KR_SIGNATURE_LENGTH: usize = 65;
 
 /// The length of a compressed public key in bytes
 pub const PUBLIC_KEY_LENGTH: usize = 33;
@@ -77,6 +83,11 @@ impl K256KeyPair {
         }
     }
 
+    /// Access a temporary slice of the public key bytes in the uncompressed SEC1 encoding
+    pub fn with_uncompressed_public_bytes<O>(&self, f: impl FnOnce(&[u8]) -> O) -> O {
+        f(self.public.to_encoded_point(false).as_bytes())
+    }
+
     pub(crate) fn to_signing_key(&self) -> Option<SigningKey> {
         self.secret.as_ref().map(SigningKey::from)
     }
@@ -92,6 +103,16 @@ impl K256KeyPair {
         }
     }
 
+    /// Sign a message with the secret key, appending the recovery ID
+    pub fn sign_recoverable(&self, message: &[u8]) -> Option<[u8; ES256KR_SIGNATURE_LENGTH]> {
+        let skey = self.to_signing_key()?;
+        let (sig, recid) = skey.sign_recoverable(message).ok()?;
+        let mut sigb = [0u8; ES256KR_SIGNATURE_LENGTH];
+        sigb[..ES256K_SIGNATURE_LENGTH].copy_from_slice(&sig.to_bytes());
+        sigb[ES256K_SIGNATURE_LENGTH] = recid.to_byte();
+        Some(sigb)
+    }
+
     /// Verify a signature with the public key
     pub fn verify_signature(&self, message: &[u8], signature: &[u8]) -> bool {
         if let Ok(sig) = Signature::try_from(signature) {
@@ -101,6 +122,102 @@ impl K256KeyPair {
             false
         }
     }
+
+    /// Sign a message digest computed by the caller with the secret key
+    pub fn sign_prehashed(&self, digest: &[u8]) -> Result<[u8; ES256K_SIGNATURE_LENGTH], Error> {
+        let skey = self
+            .to_signing_key()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        let sig: Signature = skey
+            .sign_prehash(digest)
+            .map_err(|_| err_msg!(Usage, "Invalid message digest"))?;
+        Ok(sig.to_bytes().into())
+    }
+
+    /// Verify a signature over a message digest computed by the caller
+    pub fn verify_signature_prehashed(&self, digest: &[u8], signature: &[u8]) -> bool {
+        if let Ok(sig) = Signature::try_from(signature) {
+            let vk = VerifyingKey::from(&self.public);
+            vk.verify_prehash(digest, &sig).is_ok()
+        } else {
+            false
+        }
+    }
+
+    /// Sign a message with the secret key, normalizing the signature to low-S form
+    pub fn sign_low_s(&self, message: &[u8]) -> Option<[u8; ES256K_SIGNATURE_LENGTH]> {
+        if let Some(skey) = self.to_signing_key() {
+            let sig: Signature = skey.sign(message);
+            let sig = sig.normalize_s().unwrap_or(sig);
+            let sigb: [u8; 64] = sig.to_bytes().into();
+            Some(sigb)
+        } else {
+            None
+        }
+    }
+
+    /// Verify a signature with the public key, rejecting high-S signatures
+    pub fn verify_signature_strict(&self, message: &[u8], signature: &[u8]) -> bool {
+        if let Ok(sig) = Signature::try_from(signature) {
+            if sig.normalize_s().is_some() {
+                // signature is not in low-S form
+                return false;
+            }
+            let vk = VerifyingKey::from(&self.public);
+            vk.verify(message, &sig).is_ok()
+        } else {
+            false
+        }
+    }
+
+    /// Sign a message with the secret key, producing a DER-encoded signature
+    pub fn sign_der(&self, message: &[u8]) -> Option<DerSignature> {
+        if let Some(skey) = self.to_signing_key() {
+            let sig: Signature = skey.sign(message);
+            Some(sig.to_der())
+        } else {
+            None
+        }
+    }
+
+    /// Verify a DER-encoded signature with the public key
+    pub fn verify_signature_der(&self, message: &[u8], signature: &[u8]) -> bool {
+        if let Ok(sig) = Signature::from_der(signature) {
+            let vk = VerifyingKey::from(&self.public);
+            vk.verify(message, &sig).is_ok()
+        } else {
+            false
+        }
+    }
+
+    /// Verify a recoverable signature with the public key
+    pub fn verify_signature_recoverable(&self, message: &[u8], signature: &[u8]) -> bool {
+        Self::recover_public_key(message, signature)
+            .map(|pk| pk.public == self.public)
+            .unwrap_or(false)
+    }
+
+    /// Recover the public key from a message and its recoverable signature
+    pub fn recover_public_key(message: &[u8], signature: &[u8]) -> Result<Self, Error> {
+        if signature.len() != ES256KR_SIGNATURE_LENGTH {
+            return Err(err_msg!(Invalid, "Invalid recoverable signature length"));
+        }
+        let sig = Signature::try_from(&signature[..ES256K_SIGNATURE_LENGTH])
+            .map_err(|_| err_msg!(Invalid, "Invalid signature"))?;
+        // accept Ethereum-style recovery values of 27 and 28
+        let mut v = signature[ES256K_SIGNATURE_LENGTH];
+        if v >= 27 {
+            v -= 27;
+        }
+        let recid =
+            RecoveryId::from_byte(v).ok_or_else(|| err_msg!(Invalid, "Invalid recovery ID"))?;
+        let vk = VerifyingKey::recover_from_msg(message, &sig, recid)
+            .map_err(|_| err_msg!(Invalid, "Error recovering public key"))?;
+        Ok(Self {
+            secret: None,
+            public: PublicKey::from(&vk),
+        })
+    }
 }
 
 impl HasKeyBackend for K256KeyPair {}
@@ -128,9 +245,8 @@ impl KeyGen for K256KeyPair {
 
 impl KeySecretBytes for K256KeyPair {
     fn from_secret_bytes(key: &[u8]) -> Result<Self, Error> {
-        #[allow(clippy::unnecessary_fallible_conversions)]
-        if let Ok(key) = key.try_into() {
-            if let Ok(sk) = SecretKey::from_bytes(key) {
+        if key.len() == SECRET_KEY_LENGTH {
+            if let Ok(sk) = SecretKey::from_bytes(key.into()) {
                 return Ok(Self::from_secret_key(sk));
             }
         }
@@ -181,7 +297,8 @@ impl KeypairBytes for K256KeyPair {
 
 impl KeyPublicBytes for K256KeyPair {
     fn from_public_bytes(key: &[u8]) -> Result<Self, Error> {
-        let pk = PublicKey::from_sec1_bytes(key).map_err(|_| err_msg!(InvalidKeyData))?;
+        let pk = ec_common::with_sec1_public_bytes(key, 32, PublicKey::from_sec1_bytes)
+            .map_err(|_| err_msg!(InvalidKeyData))?;
         Ok(Self {
             secret: None,
             public: pk,
@@ -209,10 +326,55 @@ impl KeySign for K256KeyPair {
                     Err(err_msg!(Unsupported, "Undefined secret key"))
                 }
             }
+            Some(SignatureType::ES256KR) => {
+                if let Some(sig) = self.sign_recoverable(message) {
+                    out.buffer_write(&sig[..])?;
+                    Ok(())
+                } else {
+                    Err(err_msg!(Unsupported, "Undefined secret key"))
+                }
+            }
             #[allow(unreachable_patterns)]
             _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
         }
     }
+
+    fn write_signature_format(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        format: SignatureFormat,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        match (format, sig_type) {
+            (SignatureFormat::Raw, _) => self.write_signature(message, sig_type, out),
+            (SignatureFormat::Der, None | Some(SignatureType::ES256K)) => {
+                if let Some(sig) = self.sign_der(message) {
+                    out.buffer_write(sig.as_bytes())?;
+                    Ok(())
+                } else {
+                    Err(err_msg!(Unsupported, "Undefined secret key"))
+                }
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+
+    fn write_signature_prehashed(
+        &self,
+        digest: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        match sig_type {
+            None | Some(SignatureType::ES256K) => {
+                let sig = self.sign_prehashed(digest)?;
+                out.buffer_write(&sig[..])?;
+                Ok(())
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
 }
 
 impl KeySigVerify for K256KeyPair {
@@ -224,10 +386,45 @@ impl KeySigVerify for K256KeyPair {
     ) -> Result<bool, Error> {
         match sig_type {
             None | Some(SignatureType::ES256K) => Ok(self.verify_signature(message, signature)),
+            Some(SignatureType::ES256KR) => {
+                Ok(self.verify_signature_recoverable(message, signature))
+            }
             #[allow(unreachable_patterns)]
             _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
         }
     }
+
+    fn verify_signature_format(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+        format: SignatureFormat,
+    ) -> Result<bool, Error> {
+        match (format, sig_type) {
+            (SignatureFormat::Raw, _) => {
+                KeySigVerify::verify_signature(self, message, signature, sig_type)
+            }
+            (SignatureFormat::Der, None | Some(SignatureType::ES256K)) => {
+                Ok(self.verify_signature_der(message, signature))
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+
+    fn verify_signature_prehashed(
+        &self,
+        digest: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        match sig_type {
+            None | Some(SignatureType::ES256K) => {
+                Ok(self.verify_signature_prehashed(digest, signature))
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
 }
 
 impl ToJwk for K256KeyPair {
@@ -322,12 +519,49 @@ impl KeyExchange for K256KeyPair {
     }
 }
 
+/// Output formats for a secp256k1 key exchange
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum K256EcdhOutput {
+    /// The x-coordinate of the shared point, as produced by `write_key_exchange`
+    #[default]
+    XCoordinate,
+    /// The shared point in the uncompressed SEC1 encoding
+    Uncompressed,
+    /// The SHA-256 hash of the shared point in the compressed SEC1 encoding,
+    /// matching the default output of libsecp256k1
+    Sha256Compressed,
+}
+
+impl K256KeyPair {
+    /// Perform a key exchange with another keypair, writing the shared secret
+    /// in the requested format
+    pub fn write_key_exchange_output(
+        &self,
+        other: &Self,
+        output: K256EcdhOutput,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let shared = || -> Result<_, Error> {
+            let sk = self.secret.as_ref().ok_or(err_msg!(MissingSecretKey))?;
+            Ok((other.public.to_projective() * *sk.to_nonzero_scalar()).to_affine())
+        };
+        match output {
+            K256EcdhOutput::XCoordinate => self.write_key_exchange(other, out),
+            K256EcdhOutput::Uncompressed => {
+                out.buffer_write(shared()?.to_encoded_point(false).as_bytes())
+            }
+            K256EcdhOutput::Sha256Compressed => out
+                .buffer_write(Sha256::digest(shared()?.to_encoded_point(true).as_bytes()).as_ref()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use base64::Engine;
 
     use super::*;
-    use crate::repr::ToPublicBytes;
+    use crate::{buffer::Writer, repr::ToPublicBytes};
 
     #[test]
     fn jwk_expected() {
@@ -392,6 +626,102 @@ mod tests {
         assert!(!kp.verify_signature(&test_msg[..], &[0u8; 64]));
     }
 
+    #[test]
+    fn sign_verify_prehashed() {
+        use sha2::Digest;
+
+        let test_msg = b"This is a dummy message for use with tests";
+        let test_sig = &hex!(
+            "a2a3affbe18cda8c5a7b6375f05b304c2303ab8beb21428709a43a519f8f946f
+            6ffa7966afdb337e9b1f70bb575282e71d4fe5bbe6bfa97b229d6bd7e97df1e5"
+        );
+        let test_pvt = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode("jv_VrhPomm6_WOzb74xF4eMI0hu9p0W1Zlxi0nz8AFs")
+            .unwrap();
+        let kp = K256KeyPair::from_secret_bytes(&test_pvt).unwrap();
+        let digest = sha2::Sha256::digest(&test_msg[..]);
+        let sig = kp.sign_prehashed(&digest).unwrap();
+        assert_eq!(sig, &test_sig[..]);
+        assert!(kp.verify_signature_prehashed(&digest, &sig[..]));
+        assert!(kp.verify_signature(&test_msg[..], &sig[..]));
+        assert!(!kp.verify_signature_prehashed(&test_msg[..8], &sig[..]));
+        assert!(!kp.verify_signature_prehashed(&digest, &[0u8; 64]));
+        assert!(KeySigVerify::verify_signature_prehashed(
+            &kp,
+            &digest,
+            &sig,
+            Some(SignatureType::ES256K)
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn sign_recover_expected() {
+        let test_msg = b"This is a dummy message for use with tests";
+        let test_pvt = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode("jv_VrhPomm6_WOzb74xF4eMI0hu9p0W1Zlxi0nz8AFs")
+            .unwrap();
+        let kp = K256KeyPair::from_secret_bytes(&test_pvt).unwrap();
+        let sig = kp.sign_recoverable(&test_msg[..]).unwrap();
+        assert_eq!(
+            &sig[..ES256K_SIGNATURE_LENGTH],
+            &kp.sign(&test_msg[..]).unwrap()[..]
+        );
+        assert!(kp.verify_signature_recoverable(&test_msg[..], &sig[..]));
+        assert!(!kp.verify_signature_recoverable(b"Not the message", &sig[..]));
+
+        let recovered = K256KeyPair::recover_public_key(&test_msg[..], &sig[..]).unwrap();
+        assert_eq!(recovered.to_public_bytes(), kp.to_public_bytes());
+        assert!(recovered.secret.is_none());
+
+        let mut eth_sig = sig;
+        eth_sig[ES256K_SIGNATURE_LENGTH] += 27;
+        let recovered = K256KeyPair::recover_public_key(&test_msg[..], &eth_sig[..]).unwrap();
+        assert_eq!(recovered.to_public_bytes(), kp.to_public_bytes());
+    }
+
+    #[test]
+    fn sign_verify_low_s() {
+        let test_msg = b"This is a dummy message for use with tests";
+        let kp = K256KeyPair::random().unwrap();
+        let sig = kp.sign_low_s(&test_msg[..]).unwrap();
+        assert!(kp.verify_signature_strict(&test_msg[..], &sig[..]));
+        assert!(!kp.verify_signature_strict(b"Not the message", &sig[..]));
+
+        let low = Signature::try_from(&sig[..]).unwrap();
+        let high = Signature::from_scalars(low.r().to_bytes(), (-*low.s()).to_bytes()).unwrap();
+        let high_sig: [u8; 64] = high.to_bytes().into();
+        assert!(!kp.verify_signature_strict(&test_msg[..], &high_sig[..]));
+        assert_eq!(
+            high.normalize_s().map(|s| s.to_bytes()),
+            Some(low.to_bytes())
+        );
+    }
+
+    #[test]
+    fn sign_verify_der() {
+        let test_msg = b"This is a dummy message for use with tests";
+        let kp = K256KeyPair::random().unwrap();
+        let mut buf = [0u8; 72];
+        let mut writer = Writer::from_slice(&mut buf[..]);
+        kp.write_signature_format(&test_msg[..], None, SignatureFormat::Der, &mut writer)
+            .unwrap();
+        let sig_len = writer.position();
+        let sig = &buf[..sig_len];
+        assert_eq!(sig[0], 0x30);
+        assert!(kp
+            .verify_signature_format(&test_msg[..], sig, None, SignatureFormat::Der)
+            .unwrap());
+        assert!(!kp
+            .verify_signature_format(b"Not the message", sig, None, SignatureFormat::Der)
+            .unwrap());
+        assert!(!KeySigVerify::verify_signature(&kp, &test_msg[..], sig, None).unwrap());
+        let raw = kp.sign(&test_msg[..]).unwrap();
+        assert!(!kp
+            .verify_signature_format(&test_msg[..], &raw, None, SignatureFormat::Der)
+            .unwrap());
+    }
+
     #[test]
     fn key_exchange_random() {
         let kp1 = K256KeyPair::random().unwrap();
@@ -407,6 +737,33 @@ mod tests {
         assert_eq!(xch1, xch2);
     }
 
+    #[test]
+    fn key_exchange_output() {
+        let kp1 = K256KeyPair::from_secret_bytes(&[1u8; 32]).unwrap();
+        let kp2 = K256KeyPair::from_secret_bytes(&[2u8; 32]).unwrap();
+        let mut buf = [0u8; 65];
+        let mut w = Writer::from_slice(&mut buf[..]);
+        kp1.write_key_exchange_output(&kp2, K256EcdhOutput::Uncompressed, &mut w)
+            .unwrap();
+        assert_eq!(
+            buf,
+            hex!(
+                "04d0158a38faf6118af133af12d9bfa388eab4a08d1a2088ea6e6ec1269e03567f
+                08b3ae6f6b1da2294a5feca5864bf5261262965286051238b5b44e3cb6a0fa99"
+            )
+        );
+        assert_eq!(&buf[1..33], &kp2.key_exchange_bytes(&kp1).unwrap()[..]);
+
+        let mut hashed = [0u8; 32];
+        let mut w = Writer::from_slice(&mut hashed[..]);
+        kp2.write_key_exchange_output(&kp1, K256EcdhOutput::Sha256Compressed, &mut w)
+            .unwrap();
+        assert_eq!(
+            hashed,
+            hex!("b7c99dee100e6844572a8d9ee91975af09e602491d4ba32f6781261cd9c99173")
+        );
+    }
+
     #[test]
     fn round_trip_bytes() {
         let kp = K256KeyPair::random().unwrap();
@@ -0,0 +1,394 @@
+//! ML-DSA (FIPS 204) signature and verification key support
+
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use base64::Engine;
+use ml_dsa::{
+    EncodedSignature, EncodedVerifyingKey, KeyGen as MlDsaKeyGen, MlDsa44, MlDsa65, MlDsa87,
+    MlDsaParams, Signature, VerifyingKey, B32,
+};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use super::{HasKeyAlg, HasKeyBackend, KeyAlg, MlDsaTypes};
+use crate::{
+    buffer::WriteBuffer,
+    error::Error,
+    jwk::{FromJwk, JwkEncoder, JwkParts, ToJwk},
+    random::{try_default_rng, KeyMaterial},
+    repr::{ToPublicBytes, ToSecretBytes},
+    sign::{KeySigVerify, KeySign, SignatureType},
+};
+
+/// The length of the secret key seed in bytes
+pub const SEED_LENGTH: usize = 32;
+
+/// The 'kty' value of an ML-DSA JWK
+pub static JWK_KEY_TYPE: &str = "AKP";
+
+impl MlDsaTypes {
+    /// Get the length of an encoded public key
+    pub const fn public_key_length(&self) -> usize {
+        match self {
+            Self::MlDsa44 => 1312,
+            Self::MlDsa65 => 1952,
+            Self::MlDsa87 => 2592,
+        }
+    }
+
+    /// Get the corresponding signature type
+    pub const fn signature_type(&self) -> SignatureType {
+        match self {
+            Self::MlDsa44 => SignatureType::MlDsa44,
+            Self::MlDsa65 => SignatureType::MlDsa65,
+            Self::MlDsa87 => SignatureType::MlDsa87,
+        }
+    }
+
+    /// Get the JOSE algorithm identifier for the parameter set
+    pub const fn jwk_alg(&self) -> &'static str {
+        match self {
+            Self::MlDsa44 => "ML-DSA-44",
+            Self::MlDsa65 => "ML-DSA-65",
+            Self::MlDsa87 => "ML-DSA-87",
+        }
+    }
+
+    /// Look up the parameter set for a JOSE algorithm identifier
+    pub fn from_jwk_alg(alg: &str) -> Result<Self, Error> {
+        match alg {
+            "ML-DSA-44" => Ok(Self::MlDsa44),
+            "ML-DSA-65" => Ok(Self::MlDsa65),
+            "ML-DSA-87" => Ok(Self::MlDsa87),
+            _ => Err(err_msg!(Unsupported, "Unsupported ML-DSA algorithm")),
+        }
+    }
+
+    /// Determine the parameter set from an encoded public key length
+    pub fn from_public_key_length(len: usize) -> Result<Self, Error> {
+        match len {
+            1312 => Ok(Self::MlDsa44),
+            1952 => Ok(Self::MlDsa65),
+            2592 => Ok(Self::MlDsa87),
+            _ => Err(err_msg!(InvalidKeyData, "Invalid ML-DSA public key length")),
+        }
+    }
+}
+
+/// An ML-DSA public key or keypair
+///
+/// The secret key is represented by the 32-byte seed from which the
+/// signing key is expanded (FIPS 204 `ML-DSA.KeyGen_internal`).
+#[derive(Clone)]
+pub struct MlDsaKeyPair {
+    secret: Option<Zeroizing<[u8; SEED_LENGTH]>>,
+    public: Vec<u8>,
+    size: MlDsaTypes,
+}
+
+impl MlDsaKeyPair {
+    #[inline]
+    pub(crate) fn from_seed(size: MlDsaTypes, seed: &[u8; SEED_LENGTH]) -> Self {
+        let xi = B32::from(*seed);
+        let public = match size {
+            MlDsaTypes::MlDsa44 => derive_public::<MlDsa44>(&xi),
+            MlDsaTypes::MlDsa65 => derive_public::<MlDsa65>(&xi),
+            MlDsaTypes::MlDsa87 => derive_public::<MlDsa87>(&xi),
+        };
+        Self {
+            secret: Some(Zeroizing::new(*seed)),
+            public,
+            size,
+        }
+    }
+
+    pub(crate) fn check_public_bytes(&self, pk: &[u8]) -> Result<(), Error> {
+        if self.public.ct_eq(pk).into() {
+            Ok(())
+        } else {
+            Err(err_msg!(InvalidKeyData, "invalid ML-DSA keypair"))
+        }
+    }
+
+    /// Generate a new keypair with the given parameter set from a key material generator
+    pub fn generate(size: MlDsaTypes, mut rng: impl KeyMaterial) -> Result<Self, Error> {
+        let mut seed = Zeroizing::new([0u8; SEED_LENGTH]);
+        rng.read_okm(&mut seed[..]);
+        Ok(Self::from_seed(size, &seed))
+    }
+
+    /// Generate a new random keypair with the given parameter set
+    pub fn random(size: MlDsaTypes) -> Result<Self, Error> {
+        Self::generate(size, try_default_rng()?)
+    }
+
+    /// Load a keypair from a 32-byte secret key seed
+    pub fn from_secret_bytes(size: MlDsaTypes, key: &[u8]) -> Result<Self, Error> {
+        let seed: &[u8; SEED_LENGTH] = key.try_into().map_err(|_| err_msg!(InvalidKeyData))?;
+        Ok(Self::from_seed(size, seed))
+    }
+
+    /// Load a public key from its encoded representation
+    pub fn from_public_bytes(key: &[u8]) -> Result<Self, Error> {
+        let size = MlDsaTypes::from_public_key_length(key.len())?;
+        Ok(Self {
+            secret: None,
+            public: key.to_vec(),
+            size,
+        })
+    }
+
+    /// Accessor for the parameter set
+    pub fn key_size(&self) -> MlDsaTypes {
+        self.size
+    }
+
+    /// Sign a message with the secret key, using an empty context string
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let seed = self
+            .secret
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        let xi = B32::from(**seed);
+        match self.size {
+            MlDsaTypes::MlDsa44 => sign_with::<MlDsa44>(&xi, message),
+            MlDsaTypes::MlDsa65 => sign_with::<MlDsa65>(&xi, message),
+            MlDsaTypes::MlDsa87 => sign_with::<MlDsa87>(&xi, message),
+        }
+    }
+
+    /// Verify a signature against the public key, using an empty context string
+    pub fn verify_signature(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self.size {
+            MlDsaTypes::MlDsa44 => verify_with::<MlDsa44>(&self.public, message, signature),
+            MlDsaTypes::MlDsa65 => verify_with::<MlDsa65>(&self.public, message, signature),
+            MlDsaTypes::MlDsa87 => verify_with::<MlDsa87>(&self.public, message, signature),
+        }
+    }
+
+    #[inline]
+    fn check_sig_type(&self, sig_type: Option<SignatureType>) -> Result<(), Error> {
+        match sig_type {
+            None => Ok(()),
+            Some(sig_type) if sig_type == self.size.signature_type() => Ok(()),
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+}
+
+fn derive_public<P: MlDsaParams>(xi: &B32) -> Vec<u8> {
+    P::key_gen_internal(xi).verifying_key().encode().to_vec()
+}
+
+fn sign_with<P: MlDsaParams>(xi: &B32, message: &[u8]) -> Result<Vec<u8>, Error> {
+    P::key_gen_internal(xi)
+        .signing_key()
+        .sign_deterministic(message, &[])
+        .map(|sig| sig.encode().to_vec())
+        .map_err(|_| err_msg!(Unexpected, "Error creating ML-DSA signature"))
+}
+
+fn verify_with<P: MlDsaParams>(public: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let (Ok(pk), Ok(sig)) = (
+        EncodedVerifyingKey::<P>::try_from(public),
+        EncodedSignature::<P>::try_from(signature),
+    ) else {
+        return false;
+    };
+    match Signature::<P>::decode(&sig) {
+        Some(sig) => VerifyingKey::<P>::decode(&pk).verify_with_context(message, &[], &sig),
+        None => false,
+    }
+}
+
+impl Debug for MlDsaKeyPair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MlDsaKeyPair")
+            .field(
+                "secret",
+                if self.secret.is_some() {
+                    &"<secret>"
+                } else {
+                    &"None"
+                },
+            )
+            .field("public", &&self.public[..])
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl HasKeyBackend for MlDsaKeyPair {}
+
+impl HasKeyAlg for MlDsaKeyPair {
+    fn algorithm(&self) -> KeyAlg {
+        KeyAlg::MlDsa(self.size)
+    }
+}
+
+impl ToSecretBytes for MlDsaKeyPair {
+    fn secret_bytes_length(&self) -> Result<usize, Error> {
+        Ok(SEED_LENGTH)
+    }
+
+    fn write_secret_bytes(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        let seed = self
+            .secret
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        out.buffer_write(&seed[..])
+    }
+}
+
+impl ToPublicBytes for MlDsaKeyPair {
+    fn public_bytes_length(&self) -> Result<usize, Error> {
+        Ok(self.public.len())
+    }
+
+    fn write_public_bytes(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        out.buffer_write(&self.public)
+    }
+}
+
+impl KeySign for MlDsaKeyPair {
+    fn write_signature(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        self.check_sig_type(sig_type)?;
+        let sig = self.sign(message)?;
+        out.buffer_write(&sig)
+    }
+}
+
+impl KeySigVerify for MlDsaKeyPair {
+    fn verify_signature(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        self.check_sig_type(sig_type)?;
+        Ok(self.verify_signature(message, signature))
+    }
+}
+
+impl ToJwk for MlDsaKeyPair {
+    fn encode_jwk(&self, enc: &mut dyn JwkEncoder) -> Result<(), Error> {
+        // members are written in lexicographic order for thumbprint generation
+        enc.add_str("alg", self.size.jwk_alg())?;
+        enc.add_str("kty", JWK_KEY_TYPE)?;
+        if enc.is_secret() {
+            if let Some(seed) = self.secret.as_ref() {
+                enc.add_as_base64("priv", &seed[..])?;
+            }
+        }
+        enc.add_as_base64("pub", &self.public)?;
+        Ok(())
+    }
+}
+
+impl FromJwk for MlDsaKeyPair {
+    fn from_jwk_parts(jwk: JwkParts<'_>) -> Result<Self, Error> {
+        if jwk.kty != JWK_KEY_TYPE {
+            return Err(err_msg!(InvalidKeyData, "Unsupported key type"));
+        }
+        let size = MlDsaTypes::from_jwk_alg(jwk.alg.as_ref())?;
+        let public = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(jwk.pub_key.as_ref())
+            .map_err(|_| err_msg!(InvalidKeyData, "Base64 decoding error"))?;
+        if public.len() != size.public_key_length() {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        if jwk.priv_key.is_some() {
+            let mut seed = Zeroizing::new([0u8; SEED_LENGTH]);
+            if jwk.priv_key.decode_base64(&mut seed[..])? != SEED_LENGTH {
+                return Err(err_msg!(InvalidKeyData));
+            }
+            let kp = Self::from_seed(size, &seed);
+            kp.check_public_bytes(&public)?;
+            Ok(kp)
+        } else {
+            Ok(Self {
+                secret: None,
+                public,
+                size,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_verify() {
+        let test_msg = b"This is a dummy message for use with tests";
+        for size in [
+            MlDsaTypes::MlDsa44,
+            MlDsaTypes::MlDsa65,
+            MlDsaTypes::MlDsa87,
+        ] {
+            let kp = MlDsaKeyPair::random(size).unwrap();
+            assert_eq!(
+                kp.to_public_bytes().unwrap().len(),
+                size.public_key_length()
+            );
+            let sig = kp.sign(test_msg).unwrap();
+            assert_eq!(sig.len(), size.signature_type().signature_length());
+            assert!(kp.verify_signature(test_msg, &sig));
+            assert!(!kp.verify_signature(b"Not the message", &sig));
+            assert!(!kp.verify_signature(test_msg, &sig[1..]));
+
+            let pk = MlDsaKeyPair::from_public_bytes(&kp.to_public_bytes().unwrap()).unwrap();
+            assert_eq!(pk.algorithm(), KeyAlg::MlDsa(size));
+            assert!(pk.verify_signature(test_msg, &sig));
+            assert!(pk.sign(test_msg).is_err());
+        }
+    }
+
+    #[test]
+    fn seed_round_trip() {
+        let seed = [7u8; SEED_LENGTH];
+        let kp = MlDsaKeyPair::from_secret_bytes(MlDsaTypes::MlDsa65, &seed).unwrap();
+        assert_eq!(&*kp.to_secret_bytes().unwrap(), &seed[..]);
+        let cmp = MlDsaKeyPair::from_secret_bytes(MlDsaTypes::MlDsa65, &seed).unwrap();
+        assert_eq!(
+            kp.to_public_bytes().unwrap(),
+            cmp.to_public_bytes().unwrap()
+        );
+        // signing is deterministic
+        assert_eq!(kp.sign(b"msg").unwrap(), cmp.sign(b"msg").unwrap());
+        assert!(kp
+            .write_signature(b"msg", Some(SignatureType::MlDsa44), &mut Vec::new())
+            .is_err());
+    }
+
+    #[test]
+    fn jwk_round_trip() {
+        let kp = MlDsaKeyPair::random(MlDsaTypes::MlDsa87).unwrap();
+        let jwk = kp
+            .to_jwk_public(None)
+            .expect("Error converting public key to JWK");
+        let parts = JwkParts::try_from_str(&jwk).expect("Error parsing JWK output");
+        assert_eq!(parts.kty, JWK_KEY_TYPE);
+        assert_eq!(parts.alg, Some("ML-DSA-87"));
+        assert!(parts.priv_key.is_none());
+        let pk_load = MlDsaKeyPair::from_jwk_parts(parts).unwrap();
+        assert_eq!(kp.to_public_bytes(), pk_load.to_public_bytes());
+
+        let jwk = kp
+            .to_jwk_secret(None)
+            .expect("Error converting private key to JWK");
+        let parts = JwkParts::from_slice(&jwk).expect("Error parsing JWK output");
+        let sk_load = MlDsaKeyPair::from_jwk_parts(parts).unwrap();
+        assert_eq!(
+            kp.to_secret_bytes().unwrap(),
+            sk_load.to_secret_bytes().unwrap()
+        );
+    }
+}
@@ -37,10 +37,26 @@ pub mod chacha20;
 #[cfg_attr(docsrs, doc(cfg(feature = "ed25519")))]
 pub mod ed25519;
 
+#[cfg(feature = "hmac")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hmac")))]
+pub mod hmac;
+
 #[cfg(feature = "ed25519")]
 #[cfg_attr(docsrs, doc(cfg(feature = "ed25519")))]
 pub mod x25519;
 
+#[cfg(feature = "ml_kem")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ml_kem")))]
+pub mod x25519_ml_kem;
+
+#[cfg(feature = "ed448")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ed448")))]
+pub mod ed448;
+
+#[cfg(feature = "ed448")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ed448")))]
+pub mod x448;
+
 #[cfg(feature = "ec_curves")]
 mod ec_common;
 
@@ -56,10 +72,22 @@ pub mod p256;
 #[cfg_attr(docsrs, doc(cfg(feature = "p384")))]
 pub mod p384;
 
+#[cfg(feature = "ml_dsa")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ml_dsa")))]
+pub mod ml_dsa;
+
 #[cfg(feature = "p256_hardware")]
 #[cfg_attr(docsrs, doc(cfg(feature = "p256_hardware")))]
 pub mod p256_hardware;
 
+#[cfg(feature = "rsa")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rsa")))]
+pub mod rsa;
+
+#[cfg(feature = "slh_dsa")]
+#[cfg_attr(docsrs, doc(cfg(feature = "slh_dsa")))]
+pub mod slh_dsa;
+
 /// Supported key algorithms
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Zeroize)]
 #[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
@@ -72,10 +100,24 @@ pub enum KeyAlg {
     Chacha20(Chacha20Types),
     /// Ed25519 signing key
     Ed25519,
+    /// HMAC-SHA2 signing key
+    Hmac(HmacTypes),
     /// Curve25519 elliptic curve key exchange key
     X25519,
+    /// Hybrid X25519 and ML-KEM-768 key encapsulation key
+    X25519MlKem768,
+    /// Ed448 signing key
+    Ed448,
+    /// Curve448 elliptic curve key exchange key
+    X448,
     /// Elliptic Curve key for signing or key exchange
     EcCurve(EcCurves),
+    /// RSA key for signing or key wrapping
+    Rsa(RsaTypes),
+    /// ML-DSA post-quantum signing key
+    MlDsa(MlDsaTypes),
+    /// SLH-DSA stateless hash-based signing key
+    SlhDsa(SlhDsaTypes),
 }
 
 impl KeyAlg {
@@ -87,17 +129,36 @@ impl KeyAlg {
             Self::Aes(AesTypes::A128CbcHs256) => "a128cbchs256",
             Self::Aes(AesTypes::A256CbcHs512) => "a256cbchs512",
             Self::Aes(AesTypes::A128Kw) => "a128kw",
+            Self::Aes(AesTypes::A192Kw) => "a192kw",
             Self::Aes(AesTypes::A256Kw) => "a256kw",
+            Self::Aes(AesTypes::A128GcmSiv) => "a128gcmsiv",
+            Self::Aes(AesTypes::A256GcmSiv) => "a256gcmsiv",
+            Self::Aes(AesTypes::A128Siv) => "a128siv",
+            Self::Aes(AesTypes::A256Siv) => "a256siv",
             Self::Bls12_381(BlsCurves::G1) => "bls12381g1",
             Self::Bls12_381(BlsCurves::G2) => "bls12381g2",
             Self::Bls12_381(BlsCurves::G1G2) => "bls12381g1g2",
             Self::Chacha20(Chacha20Types::C20P) => "c20p",
             Self::Chacha20(Chacha20Types::XC20P) => "xc20p",
             Self::Ed25519 => "ed25519",
+            Self::Hmac(HmacTypes::HS256) => "hs256",
+            Self::Hmac(HmacTypes::HS384) => "hs384",
+            Self::Hmac(HmacTypes::HS512) => "hs512",
             Self::X25519 => "x25519",
+            Self::X25519MlKem768 => "x25519mlkem768",
+            Self::Ed448 => "ed448",
+            Self::X448 => "x448",
             Self::EcCurve(EcCurves::Secp256k1) => "k256",
             Self::EcCurve(EcCurves::Secp256r1) => "p256",
             Self::EcCurve(EcCurves::Secp384r1) => "p384",
+            Self::Rsa(RsaTypes::Rsa2048) => "rsa2048",
+            Self::Rsa(RsaTypes::Rsa3072) => "rsa3072",
+            Self::Rsa(RsaTypes::Rsa4096) => "rsa4096",
+            Self::MlDsa(MlDsaTypes::MlDsa44) => "mldsa44",
+            Self::MlDsa(MlDsaTypes::MlDsa65) => "mldsa65",
+            Self::MlDsa(MlDsaTypes::MlDsa87) => "mldsa87",
+            Self::SlhDsa(SlhDsaTypes::Sha2_128s) => "slhdsasha2128s",
+            Self::SlhDsa(SlhDsaTypes::Sha2_128f) => "slhdsasha2128f",
         }
     }
 }
@@ -122,7 +183,12 @@ impl FromStr for KeyAlg {
                 Ok(Self::Aes(AesTypes::A256CbcHs512))
             }
             a if a == "a128kw" || a == "aes128kw" => Ok(Self::Aes(AesTypes::A128Kw)),
+            a if a == "a192kw" || a == "aes192kw" => Ok(Self::Aes(AesTypes::A192Kw)),
             a if a == "a256kw" || a == "aes256kw" => Ok(Self::Aes(AesTypes::A256Kw)),
+            a if a == "a128gcmsiv" || a == "aes128gcmsiv" => Ok(Self::Aes(AesTypes::A128GcmSiv)),
+            a if a == "a256gcmsiv" || a == "aes256gcmsiv" => Ok(Self::Aes(AesTypes::A256GcmSiv)),
+            a if a == "a128siv" || a == "aes128siv" => Ok(Self::Aes(AesTypes::A128Siv)),
+            a if a == "a256siv" || a == "aes256siv" => Ok(Self::Aes(AesTypes::A256Siv)),
             a if a == "bls12381g1" => Ok(Self::Bls12_381(BlsCurves::G1)),
             a if a == "bls12381g2" => Ok(Self::Bls12_381(BlsCurves::G2)),
             a if a == "bls12381g1g2" => Ok(Self::Bls12_381(BlsCurves::G1G2)),
@@ -131,10 +197,28 @@ impl FromStr for KeyAlg {
                 Ok(Self::Chacha20(Chacha20Types::XC20P))
             }
             a if a == "ed25519" => Ok(Self::Ed25519),
+            a if a == "hs256" || a == "hmacsha256" => Ok(Self::Hmac(HmacTypes::HS256)),
+            a if a == "hs384" || a == "hmacsha384" => Ok(Self::Hmac(HmacTypes::HS384)),
+            a if a == "hs512" || a == "hmacsha512" => Ok(Self::Hmac(HmacTypes::HS512)),
             a if a == "x25519" => Ok(Self::X25519),
+            a if a == "x25519mlkem768" => Ok(Self::X25519MlKem768),
+            a if a == "ed448" => Ok(Self::Ed448),
+            a if a == "x448" => Ok(Self::X448),
             a if a == "k256" || a == "secp256k1" => Ok(Self::EcCurve(EcCurves::Secp256k1)),
             a if a == "p256" || a == "secp256r1" => Ok(Self::EcCurve(EcCurves::Secp256r1)),
             a if a == "p384" || a == "secp384r1" => Ok(Self::EcCurve(EcCurves::Secp384r1)),
+            a if a == "rsa2048" => Ok(Self::Rsa(RsaTypes::Rsa2048)),
+            a if a == "rsa3072" => Ok(Self::Rsa(RsaTypes::Rsa3072)),
+            a if a == "rsa4096" => Ok(Self::Rsa(RsaTypes::Rsa4096)),
+            a if a == "mldsa44" => Ok(Self::MlDsa(MlDsaTypes::MlDsa44)),
+            a if a == "mldsa65" => Ok(Self::MlDsa(MlDsaTypes::MlDsa65)),
+            a if a == "mldsa87" => Ok(Self::MlDsa(MlDsaTypes::MlDsa87)),
+            a if a == "slhdsasha2128s" || a == "sphincssha2128s" => {
+                Ok(Self::SlhDsa(SlhDsaTypes::Sha2_128s))
+            }
+            a if a == "slhdsasha2128f" || a == "sphincssha2128f" => {
+                Ok(Self::SlhDsa(SlhDsaTypes::Sha2_128f))
+            }
             _ => Err(err_msg!(Unsupported, "Unknown key algorithm")),
         }
     }
@@ -226,8 +310,18 @@ pub enum AesTypes {
     A256CbcHs512,
     /// 128-bit AES Key Wrap
     A128Kw,
+    /// 192-bit AES Key Wrap
+    A192Kw,
     /// 256-bit AES Key Wrap
     A256Kw,
+    /// 128-bit AES-GCM-SIV
+    A128GcmSiv,
+    /// 256-bit AES-GCM-SIV
+    A256GcmSiv,
+    /// 128-bit AES-SIV (AES-SIV-CMAC-256)
+    A128Siv,
+    /// 256-bit AES-SIV (AES-SIV-CMAC-512)
+    A256Siv,
 }
 
 /// Supported public key types for Bls12_381
@@ -252,6 +346,18 @@ pub enum Chacha20Types {
     XC20P,
 }
 
+/// Supported algorithms for HMAC-SHA2
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Zeroize)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+pub enum HmacTypes {
+    /// HMAC using SHA-256
+    HS256,
+    /// HMAC using SHA-384
+    HS384,
+    /// HMAC using SHA-512
+    HS512,
+}
+
 /// Supported curves for ECC operations
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Zeroize)]
 #[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
@@ -264,6 +370,40 @@ pub enum EcCurves {
     Secp384r1,
 }
 
+/// Supported key sizes for RSA
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Zeroize)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+pub enum RsaTypes {
+    /// 2048-bit modulus
+    Rsa2048,
+    /// 3072-bit modulus
+    Rsa3072,
+    /// 4096-bit modulus
+    Rsa4096,
+}
+
+/// Supported parameter sets for ML-DSA
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Zeroize)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+pub enum MlDsaTypes {
+    /// ML-DSA-44 (NIST security category 2)
+    MlDsa44,
+    /// ML-DSA-65 (NIST security category 3)
+    MlDsa65,
+    /// ML-DSA-87 (NIST security category 5)
+    MlDsa87,
+}
+
+/// Supported parameter sets for SLH-DSA
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Zeroize)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+pub enum SlhDsaTypes {
+    /// SLH-DSA-SHA2-128s, optimized for signature size
+    Sha2_128s,
+    /// SLH-DSA-SHA2-128f, optimized for signing speed
+    Sha2_128f,
+}
+
 /// A trait for accessing the algorithm of a key, used when
 /// converting to generic `AnyKey` instances.
 pub trait HasKeyAlg: Debug {
@@ -4,8 +4,11 @@ use core::convert::TryFrom;
 
 use p256::{
     ecdsa::{
-        signature::{Signer, Verifier},
-        Signature, SigningKey, VerifyingKey,
+        signature::{
+            hazmat::{PrehashSigner, PrehashVerifier},
+            Signer, Verifier,
+        },
+        DerSignature, Signature, SigningKey, VerifyingKey,
     },
     elliptic_curve::{
         self,
@@ -25,7 +28,7 @@ use crate::{
     kdf::KeyExchange,
     random::KeyMaterial,
     repr::{KeyGen, KeyMeta, KeyPublicBytes, KeySecretBytes, KeypairBytes, KeypairMeta},
-    sign::{KeySigVerify, KeySign, SignatureType},
+    sign::{KeySigVerify, KeySign, SignatureFormat, SignatureType},
 };
 
 // SECURITY: PublicKey contains a p256::AffinePoint, which is always checked
@@ -79,6 +82,11 @@ impl P256KeyPair {
         }
     }
 
+    /// Access a temporary slice of the public key bytes in the uncompressed SEC1 encoding
+    pub fn with_uncompressed_public_bytes<O>(&self, f: impl FnOnce(&[u8]) -> O) -> O {
+        f(self.public.to_encoded_point(false).as_bytes())
+    }
+
     pub(crate) fn to_signing_key(&self) -> Option<SigningKey> {
         self.secret.clone().map(SigningKey::from)
     }
@@ -103,6 +111,73 @@ impl P256KeyPair {
             false
         }
     }
+
+    /// Sign a message digest computed by the caller with the secret key
+    pub fn sign_prehashed(&self, digest: &[u8]) -> Result<[u8; ES256_SIGNATURE_LENGTH], Error> {
+        let skey = self
+            .to_signing_key()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        let sig: Signature = skey
+            .sign_prehash(digest)
+            .map_err(|_| err_msg!(Usage, "Invalid message digest"))?;
+        Ok(sig.to_bytes().into())
+    }
+
+    /// Verify a signature over a message digest computed by the caller
+    pub fn verify_signature_prehashed(&self, digest: &[u8], signature: &[u8]) -> bool {
+        if let Ok(sig) = Signature::try_from(signature) {
+            let vk = VerifyingKey::from(&self.public);
+            vk.verify_prehash(digest, &sig).is_ok()
+        } else {
+            false
+        }
+    }
+
+    /// Sign a message with the secret key, normalizing the signature to low-S form
+    pub fn sign_low_s(&self, message: &[u8]) -> Option<[u8; ES256_SIGNATURE_LENGTH]> {
+        if let Some(skey) = self.to_signing_key() {
+            let sig: Signature = skey.sign(message);
+            let sig = sig.normalize_s().unwrap_or(sig);
+            let sigb: [u8; 64] = sig.to_bytes().into();
+            Some(sigb)
+        } else {
+            None
+        }
+    }
+
+    /// Verify a signature with the public key, rejecting high-S signatures
+    pub fn verify_signature_strict(&self, message: &[u8], signature: &[u8]) -> bool {
+        if let Ok(sig) = Signature::try_from(signature) {
+            if sig.normalize_s().is_some() {
+                // signature is not in low-S form
+                return false;
+            }
+            let vk = VerifyingKey::from(&self.public);
+            vk.verify(message, &sig).is_ok()
+        } else {
+            false
+        }
+    }
+
+    /// Sign a message with the secret key, producing a DER-encoded signature
+    pub fn sign_der(&self, message: &[u8]) -> Option<DerSignature> {
+        if let Some(skey) = self.to_signing_key() {
+            let sig: Signature = skey.sign(message);
+            Some(sig.to_der())
+        } else {
+            None
+        }
+    }
+
+    /// Verify a DER-encoded signature with the public key
+    pub fn verify_signature_der(&self, message: &[u8], signature: &[u8]) -> bool {
+        if let Ok(sig) = Signature::from_der(signature) {
+            let vk = VerifyingKey::from(&self.public);
+            vk.verify(message, &sig).is_ok()
+        } else {
+            false
+        }
+    }
 }
 
 impl HasKeyBackend for P256KeyPair {}
@@ -130,9 +205,8 @@ impl KeyGen for P256KeyPair {
 
 impl KeySecretBytes for P256KeyPair {
     fn from_secret_bytes(key: &[u8]) -> Result<Self, Error> {
-        #[allow(clippy::unnecessary_fallible_conversions)]
-        if let Ok(key) = key.try_into() {
-            if let Ok(sk) = SecretKey::from_bytes(key) {
+        if key.len() == SECRET_KEY_LENGTH {
+            if let Ok(sk) = SecretKey::from_bytes(key.into()) {
                 return Ok(Self::from_secret_key(sk));
             }
         }
@@ -183,7 +257,8 @@ impl KeypairBytes for P256KeyPair {
 
 impl KeyPublicBytes for P256KeyPair {
     fn from_public_bytes(key: &[u8]) -> Result<Self, Error> {
-        let pk = PublicKey::from_sec1_bytes(key).map_err(|_| err_msg!(InvalidKeyData))?;
+        let pk = ec_common::with_sec1_public_bytes(key, 32, PublicKey::from_sec1_bytes)
+            .map_err(|_| err_msg!(InvalidKeyData))?;
         Ok(Self {
             secret: None,
             public: pk,
@@ -215,6 +290,43 @@ impl KeySign for P256KeyPair {
             _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
         }
     }
+
+    fn write_signature_format(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        format: SignatureFormat,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        match (format, sig_type) {
+            (SignatureFormat::Raw, _) => self.write_signature(message, sig_type, out),
+            (SignatureFormat::Der, None | Some(SignatureType::ES256)) => {
+                if let Some(sig) = self.sign_der(message) {
+                    out.buffer_write(sig.as_bytes())?;
+                    Ok(())
+                } else {
+                    Err(err_msg!(Unsupported, "Undefined secret key"))
+                }
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+
+    fn write_signature_prehashed(
+        &self,
+        digest: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        match sig_type {
+            None | Some(SignatureType::ES256) => {
+                let sig = self.sign_prehashed(digest)?;
+                out.buffer_write(&sig[..])?;
+                Ok(())
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
 }
 
 impl KeySigVerify for P256KeyPair {
@@ -230,6 +342,38 @@ impl KeySigVerify for P256KeyPair {
             _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
         }
     }
+
+    fn verify_signature_format(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+        format: SignatureFormat,
+    ) -> Result<bool, Error> {
+        match (format, sig_type) {
+            (SignatureFormat::Raw, _) => {
+                KeySigVerify::verify_signature(self, message, signature, sig_type)
+            }
+            (SignatureFormat::Der, None | Some(SignatureType::ES256)) => {
+                Ok(self.verify_signature_der(message, signature))
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
+
+    fn verify_signature_prehashed(
+        &self,
+        digest: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        match sig_type {
+            None | Some(SignatureType::ES256) => {
+                Ok(self.verify_signature_prehashed(digest, signature))
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
 }
 
 impl ToJwk for P256KeyPair {
@@ -329,7 +473,7 @@ mod tests {
     use base64::Engine;
 
     use super::*;
-    use crate::repr::ToPublicBytes;
+    use crate::{buffer::Writer, repr::ToPublicBytes};
 
     #[test]
     fn jwk_expected() {
@@ -391,6 +535,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn public_key_encodings() {
+        let kp = P256KeyPair::random().unwrap();
+        let compressed = kp.to_public_bytes().unwrap();
+        let uncompressed = kp.with_uncompressed_public_bytes(|pk| pk.to_vec());
+        assert_eq!(compressed.len(), PUBLIC_KEY_LENGTH);
+        assert_eq!(uncompressed.len(), 65);
+        assert_eq!(uncompressed[0], 0x04);
+        for pk in [&compressed[..], &uncompressed[..], &uncompressed[1..]] {
+            let load = P256KeyPair::from_public_bytes(pk).unwrap();
+            assert_eq!(load.to_public_bytes().unwrap(), compressed);
+        }
+        assert!(P256KeyPair::from_public_bytes(&uncompressed[2..]).is_err());
+    }
+
     #[test]
     fn sign_verify_expected() {
         let test_msg = b"This is a dummy message for use with tests";
@@ -409,6 +568,77 @@ mod tests {
         assert!(!kp.verify_signature(&test_msg[..], &[0u8; 64]));
     }
 
+    #[test]
+    fn sign_verify_prehashed() {
+        use sha2::Digest;
+
+        let test_msg = b"This is a dummy message for use with tests";
+        let test_sig = &hex!(
+            "241f765f19d4e6148452f2249d2fa69882244a6ad6e70aadb8848a6409d20712
+            4e85faf9587100247de7bdace13a3073b47ec8a531ca91c1375b2b6134344413"
+        );
+        let test_pvt = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode("jpsQnnGQmL-YBIffH1136cspYG6-0iY7X1fCE9-E9LI")
+            .unwrap();
+        let kp = P256KeyPair::from_secret_bytes(&test_pvt).unwrap();
+        let digest = sha2::Sha256::digest(&test_msg[..]);
+        let sig = kp.sign_prehashed(&digest).unwrap();
+        assert_eq!(sig, &test_sig[..]);
+        assert!(kp.verify_signature_prehashed(&digest, &sig[..]));
+        assert!(kp.verify_signature(&test_msg[..], &sig[..]));
+        assert!(!kp.verify_signature_prehashed(&test_msg[..8], &sig[..]));
+        assert!(!kp.verify_signature_prehashed(&digest, &[0u8; 64]));
+        assert!(KeySigVerify::verify_signature_prehashed(
+            &kp,
+            &digest,
+            &sig,
+            Some(SignatureType::ES256)
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn sign_verify_low_s() {
+        let test_msg = b"This is a dummy message for use with tests";
+        let kp = P256KeyPair::random().unwrap();
+        let sig = kp.sign_low_s(&test_msg[..]).unwrap();
+        assert!(kp.verify_signature_strict(&test_msg[..], &sig[..]));
+        assert!(!kp.verify_signature_strict(b"Not the message", &sig[..]));
+
+        let low = Signature::try_from(&sig[..]).unwrap();
+        let high = Signature::from_scalars(low.r().to_bytes(), (-*low.s()).to_bytes()).unwrap();
+        let high_sig: [u8; 64] = high.to_bytes().into();
+        assert!(!kp.verify_signature_strict(&test_msg[..], &high_sig[..]));
+        assert_eq!(
+            high.normalize_s().map(|s| s.to_bytes()),
+            Some(low.to_bytes())
+        );
+    }
+
+    #[test]
+    fn sign_verify_der() {
+        let test_msg = b"This is a dummy message for use with tests";
+        let kp = P256KeyPair::random().unwrap();
+        let mut buf = [0u8; 72];
+        let mut writer = Writer::from_slice(&mut buf[..]);
+        kp.write_signature_format(&test_msg[..], None, SignatureFormat::Der, &mut writer)
+            .unwrap();
+        let sig_len = writer.position();
+        let sig = &buf[..sig_len];
+        assert_eq!(sig[0], 0x30);
+        assert!(kp
+            .verify_signature_format(&test_msg[..], sig, None, SignatureFormat::Der)
+            .unwrap());
+        assert!(!kp
+            .verify_signature_format(b"Not the message", sig, None, SignatureFormat::Der)
+            .unwrap());
+        assert!(!KeySigVerify::verify_signature(&kp, &test_msg[..], sig, None).unwrap());
+        let raw = kp.sign(&test_msg[..]).unwrap();
+        assert!(!kp
+            .verify_signature_format(&test_msg[..], &raw, None, SignatureFormat::Der)
+            .unwrap());
+    }
+
     #[test]
     fn key_exchange_random() {
         let kp1 = P256KeyPair::random().unwrap();
@@ -1,10 +1,13 @@
 //! Elliptic curve ECDH and ECDSA support on curve secp384r1
 
-use core::convert::{TryFrom, TryInto};
+use core::convert::TryFrom;
 
 use p384::{
     ecdsa::{
-        signature::{Signer, Verifier},
+        signature::{
+            hazmat::{PrehashSigner, PrehashVerifier},
+            Signer, Verifier,
+        },
         Signature, SigningKey, VerifyingKey,
     },
     elliptic_curve::{
@@ -78,6 +81,11 @@ impl P384KeyPair {
         }
     }
 
+    /// Access a temporary slice of the public key bytes in the uncompressed SEC1 encoding
+    pub fn with_uncompressed_public_bytes<O>(&self, f: impl FnOnce(&[u8]) -> O) -> O {
+        f(self.public.to_encoded_point(false).as_bytes())
+    }
+
     pub(crate) fn to_signing_key(&self) -> Option<SigningKey> {
         self.secret.clone().map(SigningKey::from)
     }
@@ -103,6 +111,29 @@ impl P384KeyPair {
             false
         }
     }
+
+    /// Sign a message digest computed by the caller with the secret key
+    pub fn sign_prehashed(&self, digest: &[u8]) -> Result<[u8; ES384_SIGNATURE_LENGTH], Error> {
+        let skey = self
+            .to_signing_key()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        let sig: Signature = skey
+            .sign_prehash(digest)
+            .map_err(|_| err_msg!(Usage, "Invalid message digest"))?;
+        let mut sigb = [0u8; 96];
+        sigb.copy_from_slice(&sig.to_bytes());
+        Ok(sigb)
+    }
+
+    /// Verify a signature over a message digest computed by the caller
+    pub fn verify_signature_prehashed(&self, digest: &[u8], signature: &[u8]) -> bool {
+        if let Ok(sig) = Signature::try_from(signature) {
+            let vk = VerifyingKey::from(&self.public);
+            vk.verify_prehash(digest, &sig).is_ok()
+        } else {
+            false
+        }
+    }
 }
 
 impl HasKeyBackend for P384KeyPair {}
@@ -130,9 +161,8 @@ impl KeyGen for P384KeyPair {
 
 impl KeySecretBytes for P384KeyPair {
     fn from_secret_bytes(key: &[u8]) -> Result<Self, Error> {
-        #[allow(clippy::unnecessary_fallible_conversions)]
-        if let Ok(key) = key.try_into() {
-            if let Ok(sk) = SecretKey::from_bytes(key) {
+        if key.len() == SECRET_KEY_LENGTH {
+            if let Ok(sk) = SecretKey::from_bytes(key.into()) {
                 return Ok(Self::from_secret_key(sk));
             }
         }
@@ -183,7 +213,8 @@ impl KeypairBytes for P384KeyPair {
 
 impl KeyPublicBytes for P384KeyPair {
     fn from_public_bytes(key: &[u8]) -> Result<Self, Error> {
-        let pk = PublicKey::from_sec1_bytes(key).map_err(|_| err_msg!(InvalidKeyData))?;
+        let pk = ec_common::with_sec1_public_bytes(key, 48, PublicKey::from_sec1_bytes)
+            .map_err(|_| err_msg!(InvalidKeyData))?;
         Ok(Self {
             secret: None,
             public: pk,
@@ -215,6 +246,22 @@ impl KeySign for P384KeyPair {
             _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
         }
     }
+
+    fn write_signature_prehashed(
+        &self,
+        digest: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        match sig_type {
+            None | Some(SignatureType::ES384) => {
+                let sig = self.sign_prehashed(digest)?;
+                out.buffer_write(&sig[..])?;
+                Ok(())
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
 }
 
 impl KeySigVerify for P384KeyPair {
@@ -230,6 +277,20 @@ impl KeySigVerify for P384KeyPair {
             _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
         }
     }
+
+    fn verify_signature_prehashed(
+        &self,
+        digest: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        match sig_type {
+            None | Some(SignatureType::ES384) => {
+                Ok(self.verify_signature_prehashed(digest, signature))
+            }
+            _ => Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+    }
 }
 
 impl ToJwk for P384KeyPair {
@@ -409,6 +470,35 @@ mod tests {
         assert!(!kp.verify_signature(&test_msg[..], &[0u8; 96]));
     }
 
+    #[test]
+    fn sign_verify_prehashed() {
+        use sha2::Digest;
+
+        let test_msg = b"This is a dummy message for use with tests";
+        let test_sig = &hex!(
+            "acf7e9f0975738d446b26aa1651ad699cac490a496d6f70221126c35d8e4fcc5a28f63f611557be9d4c321d8fa24dbf2
+             846e3bcbea2e45eff577974664b1e98fffdad8ddbe7bfa792c17a9981915aa63755cfd338fd28874de02c42d966ece67"
+        );
+        let test_pvt = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode("rgFYq-b_toGb-wN3URCk_e-6Sj2PtUvoefF284q9oKnVCi7sglAmCZkOv-2nOAeE")
+            .unwrap();
+        let kp = P384KeyPair::from_secret_bytes(&test_pvt).unwrap();
+        let digest = sha2::Sha384::digest(&test_msg[..]);
+        let sig = kp.sign_prehashed(&digest).unwrap();
+        assert_eq!(sig, &test_sig[..]);
+        assert!(kp.verify_signature_prehashed(&digest, &sig[..]));
+        assert!(kp.verify_signature(&test_msg[..], &sig[..]));
+        assert!(!kp.verify_signature_prehashed(&test_msg[..8], &sig[..]));
+        assert!(!kp.verify_signature_prehashed(&digest, &[0u8; 96]));
+        assert!(KeySigVerify::verify_signature_prehashed(
+            &kp,
+            &digest,
+            &sig,
+            Some(SignatureType::ES384)
+        )
+        .unwrap());
+    }
+
     #[test]
     fn key_exchange_random() {
         let kp1 = P384KeyPair::random().unwrap();
@@ -0,0 +1,395 @@
+//! RSA signature and key wrapping support
+
+use alloc::{vec, vec::Vec};
+use core::fmt::{self, Debug, Formatter};
+
+use base64::Engine;
+use rsa_core::{
+    pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey, EncodeRsaPrivateKey, EncodeRsaPublicKey},
+    traits::{PrivateKeyParts, PublicKeyParts},
+    BigUint, Oaep, Pkcs1v15Sign, Pss, RsaPrivateKey, RsaPublicKey,
+};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use super::{HasKeyAlg, HasKeyBackend, KeyAlg, RsaTypes};
+use crate::{
+    buffer::{SecretBytes, WriteBuffer},
+    error::Error,
+    jwk::{FromJwk, JwkEncoder, JwkParts, ToJwk},
+    random::{try_default_rng, KeyMaterial, RandomDet},
+    repr::{ToPublicBytes, ToSecretBytes},
+    sign::{KeySigVerify, KeySign, SignatureType},
+};
+
+/// The 'kty' value of an RSA JWK
+pub static JWK_KEY_TYPE: &str = "RSA";
+
+/// The public exponent used for generated keys
+pub const PUBLIC_EXPONENT: u32 = 65537;
+
+impl RsaTypes {
+    /// Get the modulus length in bits
+    pub const fn bits(&self) -> usize {
+        match self {
+            Self::Rsa2048 => 2048,
+            Self::Rsa3072 => 3072,
+            Self::Rsa4096 => 4096,
+        }
+    }
+
+    /// Determine the key size from a modulus length in bytes
+    pub fn from_modulus_length(len: usize) -> Result<Self, Error> {
+        match len {
+            256 => Ok(Self::Rsa2048),
+            384 => Ok(Self::Rsa3072),
+            512 => Ok(Self::Rsa4096),
+            _ => Err(err_msg!(Unsupported, "Unsupported RSA key size")),
+        }
+    }
+}
+
+/// An RSA public key or keypair
+#[derive(Clone)]
+pub struct RsaKeyPair {
+    // SECURITY: RsaPrivateKey zeroizes on drop
+    secret: Option<RsaPrivateKey>,
+    public: RsaPublicKey,
+    size: RsaTypes,
+}
+
+impl RsaKeyPair {
+    pub(crate) fn from_secret_key(sk: RsaPrivateKey) -> Result<Self, Error> {
+        let public = sk.to_public_key();
+        let size = RsaTypes::from_modulus_length(public.size())?;
+        Ok(Self {
+            secret: Some(sk),
+            public,
+            size,
+        })
+    }
+
+    pub(crate) fn from_public_key(pk: RsaPublicKey) -> Result<Self, Error> {
+        let size = RsaTypes::from_modulus_length(pk.size())?;
+        Ok(Self {
+            secret: None,
+            public: pk,
+            size,
+        })
+    }
+
+    #[cfg(feature = "pkcs8")]
+    pub(crate) fn secret_key(&self) -> Option<&RsaPrivateKey> {
+        self.secret.as_ref()
+    }
+
+    #[cfg(feature = "pkcs8")]
+    pub(crate) fn public_key(&self) -> &RsaPublicKey {
+        &self.public
+    }
+
+    /// Generate a new keypair of the given size from a key material generator
+    pub fn generate(size: RsaTypes, mut rng: impl KeyMaterial) -> Result<Self, Error> {
+        // prime generation requires an RNG, so seed a deterministic generator
+        // from the provided key material
+        let mut seed = Zeroizing::new([0u8; 32]);
+        rng.read_okm(&mut seed[..]);
+        let mut det = RandomDet::new(&seed[..]);
+        let sk =
+            RsaPrivateKey::new_with_exp(&mut det, size.bits(), &BigUint::from(PUBLIC_EXPONENT))
+                .map_err(|_| err_msg!(Unexpected, "Error generating RSA keypair"))?;
+        Self::from_secret_key(sk)
+    }
+
+    /// Generate a new random keypair of the given size
+    pub fn random(size: RsaTypes) -> Result<Self, Error> {
+        Self::generate(size, try_default_rng()?)
+    }
+
+    /// Load a keypair from a PKCS#1 DER-encoded private key
+    pub fn from_secret_bytes(key: &[u8]) -> Result<Self, Error> {
+        let sk = RsaPrivateKey::from_pkcs1_der(key).map_err(|_| err_msg!(InvalidKeyData))?;
+        Self::from_secret_key(sk)
+    }
+
+    /// Load a public key from a PKCS#1 DER-encoded public key
+    pub fn from_public_bytes(key: &[u8]) -> Result<Self, Error> {
+        let pk = RsaPublicKey::from_pkcs1_der(key).map_err(|_| err_msg!(InvalidKeyData))?;
+        Self::from_public_key(pk)
+    }
+
+    /// Accessor for the key size
+    pub fn key_size(&self) -> RsaTypes {
+        self.size
+    }
+
+    /// Sign a message with the secret key
+    pub fn sign(&self, message: &[u8], sig_type: SignatureType) -> Result<Vec<u8>, Error> {
+        let sk = self
+            .secret
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        let hashed = Sha256::digest(message);
+        match sig_type {
+            SignatureType::RS256 => sk.sign(Pkcs1v15Sign::new::<Sha256>(), &hashed),
+            SignatureType::PS256 => {
+                sk.sign_with_rng(&mut try_default_rng()?, Pss::new::<Sha256>(), &hashed)
+            }
+            _ => return Err(err_msg!(Unsupported, "Unsupported signature type")),
+        }
+        .map_err(|_| err_msg!(Unexpected, "Error creating RSA signature"))
+    }
+
+    /// Verify a signature against the public key
+    pub fn verify_signature(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: SignatureType,
+    ) -> Result<bool, Error> {
+        let hashed = Sha256::digest(message);
+        Ok(match sig_type {
+            SignatureType::RS256 => self
+                .public
+                .verify(Pkcs1v15Sign::new::<Sha256>(), &hashed, signature)
+                .is_ok(),
+            SignatureType::PS256 => self
+                .public
+                .verify(Pss::new::<Sha256>(), &hashed, signature)
+                .is_ok(),
+            _ => return Err(err_msg!(Unsupported, "Unsupported signature type")),
+        })
+    }
+
+    /// Encrypt a value using RSA-OAEP with SHA-256, as used for key wrapping
+    pub fn encrypt_oaep(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        self.public
+            .encrypt(&mut try_default_rng()?, Oaep::new::<Sha256>(), message)
+            .map_err(|_| err_msg!(Encryption, "Error performing RSA-OAEP encryption"))
+    }
+
+    /// Decrypt a value using RSA-OAEP with SHA-256, as used for key unwrapping
+    pub fn decrypt_oaep(&self, ciphertext: &[u8]) -> Result<SecretBytes, Error> {
+        let sk = self
+            .secret
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        sk.decrypt(Oaep::new::<Sha256>(), ciphertext)
+            .map(SecretBytes::from)
+            .map_err(|_| err_msg!(Encryption, "Error performing RSA-OAEP decryption"))
+    }
+}
+
+impl Debug for RsaKeyPair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RsaKeyPair")
+            .field(
+                "secret",
+                if self.secret.is_some() {
+                    &"<secret>"
+                } else {
+                    &"None"
+                },
+            )
+            .field("public", &self.public)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl HasKeyBackend for RsaKeyPair {}
+
+impl HasKeyAlg for RsaKeyPair {
+    fn algorithm(&self) -> KeyAlg {
+        KeyAlg::Rsa(self.size)
+    }
+}
+
+impl ToSecretBytes for RsaKeyPair {
+    fn secret_bytes_length(&self) -> Result<usize, Error> {
+        Ok(self.to_secret_bytes()?.len())
+    }
+
+    fn write_secret_bytes(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        let sk = self
+            .secret
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        let doc = sk
+            .to_pkcs1_der()
+            .map_err(|_| err_msg!(Unexpected, "Error encoding RSA private key"))?;
+        out.buffer_write(doc.as_bytes())
+    }
+}
+
+impl ToPublicBytes for RsaKeyPair {
+    fn public_bytes_length(&self) -> Result<usize, Error> {
+        Ok(self.to_public_bytes()?.len())
+    }
+
+    fn write_public_bytes(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        let doc = self
+            .public
+            .to_pkcs1_der()
+            .map_err(|_| err_msg!(Unexpected, "Error encoding RSA public key"))?;
+        out.buffer_write(doc.as_bytes())
+    }
+}
+
+impl KeySign for RsaKeyPair {
+    fn write_signature(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        let sig = self.sign(message, sig_type.unwrap_or(SignatureType::RS256))?;
+        out.buffer_write(&sig)
+    }
+}
+
+impl KeySigVerify for RsaKeyPair {
+    fn verify_signature(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        self.verify_signature(message, signature, sig_type.unwrap_or(SignatureType::RS256))
+    }
+}
+
+impl ToJwk for RsaKeyPair {
+    fn encode_jwk(&self, enc: &mut dyn JwkEncoder) -> Result<(), Error> {
+        // members are written in lexicographic order for thumbprint generation
+        if enc.is_secret() {
+            if let Some(sk) = self.secret.as_ref() {
+                let primes = sk.primes();
+                if primes.len() != 2 {
+                    return Err(err_msg!(Unsupported, "Unsupported multi-prime RSA key"));
+                }
+                let dp = sk.dp().ok_or_else(|| err_msg!(Unexpected))?;
+                let dq = sk.dq().ok_or_else(|| err_msg!(Unexpected))?;
+                let qi = sk.crt_coefficient().ok_or_else(|| err_msg!(Unexpected))?;
+                enc.add_as_base64("d", &Zeroizing::new(sk.d().to_bytes_be()))?;
+                enc.add_as_base64("dp", &Zeroizing::new(dp.to_bytes_be()))?;
+                enc.add_as_base64("dq", &Zeroizing::new(dq.to_bytes_be()))?;
+                enc.add_as_base64("e", &self.public.e().to_bytes_be())?;
+                enc.add_str("kty", JWK_KEY_TYPE)?;
+                enc.add_as_base64("n", &self.public.n().to_bytes_be())?;
+                enc.add_as_base64("p", &Zeroizing::new(primes[0].to_bytes_be()))?;
+                enc.add_as_base64("q", &Zeroizing::new(primes[1].to_bytes_be()))?;
+                enc.add_as_base64("qi", &Zeroizing::new(qi.to_bytes_be()))?;
+                return Ok(());
+            }
+        }
+        enc.add_as_base64("e", &self.public.e().to_bytes_be())?;
+        enc.add_str("kty", JWK_KEY_TYPE)?;
+        enc.add_as_base64("n", &self.public.n().to_bytes_be())?;
+        Ok(())
+    }
+}
+
+fn decode_uint(value: Option<&str>) -> Result<BigUint, Error> {
+    let value = value.ok_or_else(|| err_msg!(InvalidKeyData, "Missing RSA key parameter"))?;
+    let bytes = Zeroizing::new(
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(value)
+            .map_err(|_| err_msg!(InvalidKeyData, "Base64 decoding error"))?,
+    );
+    Ok(BigUint::from_bytes_be(&bytes))
+}
+
+impl FromJwk for RsaKeyPair {
+    fn from_jwk_parts(jwk: JwkParts<'_>) -> Result<Self, Error> {
+        if jwk.kty != JWK_KEY_TYPE {
+            return Err(err_msg!(InvalidKeyData, "Unsupported key type"));
+        }
+        let n = decode_uint(jwk.n.as_opt_str())?;
+        let e = decode_uint(jwk.e.as_opt_str())?;
+        if jwk.d.is_some() {
+            let d = decode_uint(jwk.d.as_opt_str())?;
+            let primes = if jwk.p.is_some() && jwk.q.is_some() {
+                vec![
+                    decode_uint(jwk.p.as_opt_str())?,
+                    decode_uint(jwk.q.as_opt_str())?,
+                ]
+            } else {
+                Vec::new()
+            };
+            let sk = RsaPrivateKey::from_components(n, e, d, primes)
+                .map_err(|_| err_msg!(InvalidKeyData))?;
+            sk.validate().map_err(|_| err_msg!(InvalidKeyData))?;
+            Self::from_secret_key(sk)
+        } else {
+            let pk = RsaPublicKey::new(n, e).map_err(|_| err_msg!(InvalidKeyData))?;
+            Self::from_public_key(pk)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_verify_round_trip() {
+        let kp = RsaKeyPair::random(RsaTypes::Rsa2048).unwrap();
+        let test_msg = b"This is a dummy message for use with tests";
+        for sig_type in [SignatureType::RS256, SignatureType::PS256] {
+            let sig = kp.sign(test_msg, sig_type).unwrap();
+            assert_eq!(sig.len(), 256);
+            assert!(kp.verify_signature(test_msg, &sig, sig_type).unwrap());
+            assert!(!kp
+                .verify_signature(b"Not the message", &sig, sig_type)
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn oaep_round_trip() {
+        let kp = RsaKeyPair::random(RsaTypes::Rsa2048).unwrap();
+        let secret = b"32-byte key for wrapping testing";
+        let enc = kp.encrypt_oaep(secret).unwrap();
+        assert_eq!(enc.len(), 256);
+        let dec = kp.decrypt_oaep(&enc).unwrap();
+        assert_eq!(dec, &secret[..]);
+    }
+
+    #[test]
+    fn jwk_round_trip() {
+        let kp = RsaKeyPair::random(RsaTypes::Rsa2048).unwrap();
+        let jwk = kp.to_jwk_public(None).unwrap();
+        let parts = JwkParts::try_from_str(&jwk).unwrap();
+        assert_eq!(parts.kty, JWK_KEY_TYPE);
+        assert_eq!(parts.e, "AQAB");
+        assert_eq!(parts.d, None);
+        let pk_load = RsaKeyPair::from_jwk_parts(parts).unwrap();
+        assert_eq!(
+            kp.to_public_bytes().unwrap(),
+            pk_load.to_public_bytes().unwrap()
+        );
+
+        let jwk = kp.to_jwk_secret(None).unwrap();
+        let parts = JwkParts::from_slice(&jwk).unwrap();
+        let sk_load = RsaKeyPair::from_jwk_parts(parts).unwrap();
+        assert_eq!(
+            kp.to_secret_bytes().unwrap(),
+            sk_load.to_secret_bytes().unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip_bytes() {
+        let kp = RsaKeyPair::random(RsaTypes::Rsa2048).unwrap();
+        let cmp = RsaKeyPair::from_secret_bytes(&kp.to_secret_bytes().unwrap()).unwrap();
+        assert_eq!(cmp.key_size(), RsaTypes::Rsa2048);
+        assert_eq!(
+            kp.to_public_bytes().unwrap(),
+            RsaKeyPair::from_public_bytes(&cmp.to_public_bytes().unwrap())
+                .unwrap()
+                .to_public_bytes()
+                .unwrap()
+        );
+    }
+}
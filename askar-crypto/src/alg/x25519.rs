@@ -222,6 +222,13 @@ impl KeyExchange for X25519KeyPair {
     }
 }
 
+impl X25519KeyPair {
+    /// Derive the X25519 public key corresponding to an Ed25519 public key
+    pub fn from_ed25519_public_bytes(key: &[u8]) -> Result<Self, Error> {
+        Ok(Ed25519KeyPair::from_public_bytes(key)?.to_x25519_keypair())
+    }
+}
+
 impl TryFrom<&Ed25519KeyPair> for X25519KeyPair {
     type Error = Error;
 
@@ -0,0 +1,255 @@
+//! Hybrid X25519 and ML-KEM-768 (FIPS 203) key encapsulation support
+
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use ml_kem::{
+    kem::Decapsulate, Ciphertext, EncapsulateDeterministic, Encoded, EncodedSizeUser, KemCore,
+    MlKem768, B32,
+};
+use zeroize::Zeroizing;
+
+use super::{x25519::X25519KeyPair, HasKeyAlg, HasKeyBackend, KeyAlg};
+use crate::{
+    buffer::{ArrayKey, WriteBuffer, Writer},
+    error::Error,
+    generic_array::typenum::U64,
+    kdf::KeyExchange,
+    random::{try_default_rng, KeyMaterial},
+    repr::{KeyGen, KeyPublicBytes, KeySecretBytes, ToPublicBytes, ToSecretBytes},
+};
+
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+/// The length of an ML-KEM-768 encapsulation key in bytes
+pub const ML_KEM_PUBLIC_KEY_LENGTH: usize = 1184;
+/// The length of an ML-KEM-768 ciphertext in bytes
+pub const ML_KEM_CIPHERTEXT_LENGTH: usize = 1088;
+/// The length of the ML-KEM-768 secret key seed (`d || z`) in bytes
+pub const ML_KEM_SEED_LENGTH: usize = 64;
+
+/// The length of a public key (X25519 public key followed by the ML-KEM encapsulation key)
+pub const PUBLIC_KEY_LENGTH: usize = super::x25519::PUBLIC_KEY_LENGTH + ML_KEM_PUBLIC_KEY_LENGTH;
+/// The length of a secret key (X25519 secret key followed by the ML-KEM seed)
+pub const SECRET_KEY_LENGTH: usize = super::x25519::SECRET_KEY_LENGTH + ML_KEM_SEED_LENGTH;
+/// The length of an encapsulated secret (ML-KEM ciphertext followed by the ephemeral X25519 key)
+pub const CIPHERTEXT_LENGTH: usize = ML_KEM_CIPHERTEXT_LENGTH + super::x25519::PUBLIC_KEY_LENGTH;
+/// The length of the combined shared secret (ML-KEM shared secret followed by the X25519 output)
+pub const SHARED_SECRET_LENGTH: usize = 64;
+
+/// A hybrid X25519 and ML-KEM-768 public key or keypair
+///
+/// Encapsulation performs an ephemeral-static X25519 key exchange alongside
+/// ML-KEM-768 encapsulation, so that the combined secret remains confidential
+/// as long as either component is secure. The combined secret is not suitable
+/// for direct use, see `kdf::hybrid::HybridKem` for the key derivation.
+#[derive(Clone)]
+pub struct X25519MlKem768KeyPair {
+    x25519: X25519KeyPair,
+    ml_kem_seed: Option<Zeroizing<[u8; ML_KEM_SEED_LENGTH]>>,
+    ml_kem_public: Vec<u8>,
+}
+
+impl X25519MlKem768KeyPair {
+    fn from_parts(x25519: X25519KeyPair, seed: &[u8; ML_KEM_SEED_LENGTH]) -> Self {
+        let (_, ek) = ml_kem_keypair(seed);
+        Self {
+            x25519,
+            ml_kem_seed: Some(Zeroizing::new(*seed)),
+            ml_kem_public: ek.as_bytes().to_vec(),
+        }
+    }
+
+    /// Accessor for the X25519 component of the key
+    pub fn x25519_key(&self) -> &X25519KeyPair {
+        &self.x25519
+    }
+
+    /// Encapsulate a new shared secret for this public key using the default RNG
+    ///
+    /// Returns the combined shared secret and the ciphertext to be sent to the
+    /// holder of the secret key.
+    pub fn encapsulate(&self) -> Result<(ArrayKey<U64>, Vec<u8>), Error> {
+        self.encapsulate_with_rng(try_default_rng()?)
+    }
+
+    /// Encapsulate a new shared secret for this public key
+    pub fn encapsulate_with_rng(
+        &self,
+        mut rng: impl KeyMaterial,
+    ) -> Result<(ArrayKey<U64>, Vec<u8>), Error> {
+        let ek = EncapsulationKey::from_bytes(
+            &Encoded::<EncapsulationKey>::try_from(&self.ml_kem_public[..])
+                .map_err(|_| err_msg!(InvalidKeyData))?,
+        );
+        // the ML-KEM message is followed by the ephemeral X25519 secret key
+        let mut okm = Zeroizing::new([0u8; 64]);
+        rng.read_okm(&mut okm[..]);
+        let mut m = Zeroizing::new([0u8; 32]);
+        m.copy_from_slice(&okm[..32]);
+        let (ct, ss) = ek
+            .encapsulate_deterministic(&B32::from(*m))
+            .map_err(|_| err_msg!(Encryption, "Error performing ML-KEM encapsulation"))?;
+        let ephem = X25519KeyPair::from_secret_bytes(&okm[32..])?;
+
+        let mut ciphertext = Vec::with_capacity(CIPHERTEXT_LENGTH);
+        ciphertext.extend_from_slice(&ct);
+        ephem.write_public_bytes(&mut ciphertext)?;
+        let secret = ArrayKey::<U64>::try_new_with(|buf| {
+            buf[..32].copy_from_slice(&ss);
+            ephem.write_key_exchange(&self.x25519, &mut Writer::from_slice(&mut buf[32..]))
+        })?;
+        Ok((secret, ciphertext))
+    }
+
+    /// Recover the combined shared secret from a ciphertext
+    pub fn decapsulate(&self, ciphertext: &[u8]) -> Result<ArrayKey<U64>, Error> {
+        let seed = self
+            .ml_kem_seed
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        if ciphertext.len() != CIPHERTEXT_LENGTH {
+            return Err(err_msg!(Invalid, "Invalid hybrid KEM ciphertext length"));
+        }
+        let (ct, ephem) = ciphertext.split_at(ML_KEM_CIPHERTEXT_LENGTH);
+        let ephem = X25519KeyPair::from_public_bytes(ephem)?;
+        let (dk, _) = ml_kem_keypair(seed);
+        let ss = dk
+            .decapsulate(&Ciphertext::<MlKem768>::try_from(ct).map_err(|_| err_msg!(Invalid))?)
+            .map_err(|_| err_msg!(Encryption, "Error performing ML-KEM decapsulation"))?;
+        ArrayKey::<U64>::try_new_with(|buf| {
+            buf[..32].copy_from_slice(&ss);
+            self.x25519
+                .write_key_exchange(&ephem, &mut Writer::from_slice(&mut buf[32..]))
+        })
+    }
+}
+
+fn ml_kem_keypair(
+    seed: &[u8; ML_KEM_SEED_LENGTH],
+) -> (<MlKem768 as KemCore>::DecapsulationKey, EncapsulationKey) {
+    let mut d = Zeroizing::new([0u8; 32]);
+    let mut z = Zeroizing::new([0u8; 32]);
+    d.copy_from_slice(&seed[..32]);
+    z.copy_from_slice(&seed[32..]);
+    MlKem768::generate_deterministic(&B32::from(*d), &B32::from(*z))
+}
+
+impl Debug for X25519MlKem768KeyPair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("X25519MlKem768KeyPair")
+            .field("x25519", &self.x25519)
+            .field(
+                "ml_kem_seed",
+                if self.ml_kem_seed.is_some() {
+                    &"<secret>"
+                } else {
+                    &"None"
+                },
+            )
+            .field("ml_kem_public", &&self.ml_kem_public[..])
+            .finish()
+    }
+}
+
+impl KeyGen for X25519MlKem768KeyPair {
+    fn generate(mut rng: impl KeyMaterial) -> Result<Self, Error> {
+        let mut sk = Zeroizing::new([0u8; SECRET_KEY_LENGTH]);
+        rng.read_okm(&mut sk[..]);
+        Self::from_secret_bytes(&sk[..])
+    }
+}
+
+impl HasKeyBackend for X25519MlKem768KeyPair {}
+
+impl HasKeyAlg for X25519MlKem768KeyPair {
+    fn algorithm(&self) -> KeyAlg {
+        KeyAlg::X25519MlKem768
+    }
+}
+
+impl X25519MlKem768KeyPair {
+    /// Load a keypair from the X25519 secret key followed by the ML-KEM seed
+    pub fn from_secret_bytes(key: &[u8]) -> Result<Self, Error> {
+        if key.len() != SECRET_KEY_LENGTH {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        let (x_sk, seed) = key.split_at(super::x25519::SECRET_KEY_LENGTH);
+        let x25519 = X25519KeyPair::from_secret_bytes(x_sk)?;
+        let seed: &[u8; ML_KEM_SEED_LENGTH] = seed.try_into().unwrap();
+        Ok(Self::from_parts(x25519, seed))
+    }
+
+    /// Load a public key from the X25519 public key followed by the ML-KEM encapsulation key
+    pub fn from_public_bytes(key: &[u8]) -> Result<Self, Error> {
+        if key.len() != PUBLIC_KEY_LENGTH {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        let (x_pk, ek) = key.split_at(super::x25519::PUBLIC_KEY_LENGTH);
+        Ok(Self {
+            x25519: X25519KeyPair::from_public_bytes(x_pk)?,
+            ml_kem_seed: None,
+            ml_kem_public: ek.to_vec(),
+        })
+    }
+}
+
+impl ToSecretBytes for X25519MlKem768KeyPair {
+    fn secret_bytes_length(&self) -> Result<usize, Error> {
+        Ok(SECRET_KEY_LENGTH)
+    }
+
+    fn write_secret_bytes(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        let seed = self
+            .ml_kem_seed
+            .as_ref()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        self.x25519.write_secret_bytes(out)?;
+        out.buffer_write(&seed[..])
+    }
+}
+
+impl ToPublicBytes for X25519MlKem768KeyPair {
+    fn public_bytes_length(&self) -> Result<usize, Error> {
+        Ok(PUBLIC_KEY_LENGTH)
+    }
+
+    fn write_public_bytes(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        self.x25519.write_public_bytes(out)?;
+        out.buffer_write(&self.ml_kem_public)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encapsulate_round_trip() {
+        let recip = X25519MlKem768KeyPair::random().unwrap();
+        let public =
+            X25519MlKem768KeyPair::from_public_bytes(&recip.to_public_bytes().unwrap()).unwrap();
+        let (secret, ciphertext) = public.encapsulate().unwrap();
+        assert_eq!(ciphertext.len(), CIPHERTEXT_LENGTH);
+        assert_eq!(recip.decapsulate(&ciphertext).unwrap(), secret);
+        assert!(public.decapsulate(&ciphertext).is_err());
+
+        // a modified X25519 ephemeral key changes the combined secret
+        let mut modified = ciphertext.clone();
+        modified[CIPHERTEXT_LENGTH - 1] ^= 1;
+        assert_ne!(recip.decapsulate(&modified).unwrap(), secret);
+        assert!(recip.decapsulate(&ciphertext[1..]).is_err());
+    }
+
+    #[test]
+    fn round_trip_bytes() {
+        let kp = X25519MlKem768KeyPair::random().unwrap();
+        let sk = kp.to_secret_bytes().unwrap();
+        assert_eq!(sk.len(), SECRET_KEY_LENGTH);
+        let cmp = X25519MlKem768KeyPair::from_secret_bytes(&sk).unwrap();
+        let pk = cmp.to_public_bytes().unwrap();
+        assert_eq!(pk.len(), PUBLIC_KEY_LENGTH);
+        assert_eq!(pk, kp.to_public_bytes().unwrap());
+        assert_eq!(cmp.x25519_key().algorithm(), KeyAlg::X25519);
+    }
+}
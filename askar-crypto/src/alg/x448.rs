@@ -0,0 +1,276 @@
+//! X448 key exchange support on Curve448
+
+use core::{
+    convert::TryInto,
+    fmt::{self, Debug, Formatter},
+};
+
+use subtle::ConstantTimeEq;
+use x448::{PublicKey, Secret};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::{HasKeyAlg, HasKeyBackend, KeyAlg};
+use crate::{
+    buffer::{ArrayKey, WriteBuffer},
+    error::Error,
+    generic_array::typenum::{U112, U56},
+    jwk::{FromJwk, JwkEncoder, JwkParts, ToJwk},
+    kdf::KeyExchange,
+    random::KeyMaterial,
+    repr::{KeyGen, KeyMeta, KeyPublicBytes, KeySecretBytes, KeypairBytes, KeypairMeta},
+};
+
+/// The length of a public key in bytes
+pub const PUBLIC_KEY_LENGTH: usize = 56;
+/// The length of a secret key in bytes
+pub const SECRET_KEY_LENGTH: usize = 56;
+/// The length of a keypair in bytes
+pub const KEYPAIR_LENGTH: usize = SECRET_KEY_LENGTH + PUBLIC_KEY_LENGTH;
+
+/// The 'kty' value of an X448 JWK
+pub static JWK_KEY_TYPE: &str = "OKP";
+/// The 'crv' value of an X448 JWK
+pub static JWK_CURVE: &str = "X448";
+
+/// An X448 public key or keypair
+#[derive(Clone)]
+pub struct X448KeyPair {
+    secret: Option<[u8; SECRET_KEY_LENGTH]>,
+    public: [u8; PUBLIC_KEY_LENGTH],
+}
+
+impl X448KeyPair {
+    #[inline]
+    pub(crate) fn from_secret_key(sk: &[u8; SECRET_KEY_LENGTH]) -> Result<Self, Error> {
+        let secret = Secret::from_bytes(&sk[..]).ok_or_else(|| err_msg!(InvalidKeyData))?;
+        let public = PublicKey::from(&secret);
+        // the secret is clamped on import, store the clamped representation
+        Ok(Self {
+            secret: Some(*secret.as_bytes()),
+            public: *public.as_bytes(),
+        })
+    }
+
+    pub(crate) fn check_public_bytes(&self, pk: &[u8]) -> Result<(), Error> {
+        if self.public.ct_eq(pk).into() {
+            Ok(())
+        } else {
+            Err(err_msg!(InvalidKeyData, "invalid x448 keypair"))
+        }
+    }
+}
+
+impl Debug for X448KeyPair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("X448KeyPair")
+            .field(
+                "secret",
+                if self.secret.is_some() {
+                    &"<secret>"
+                } else {
+                    &"None"
+                },
+            )
+            .field("public", &&self.public[..])
+            .finish()
+    }
+}
+
+impl HasKeyBackend for X448KeyPair {}
+
+impl HasKeyAlg for X448KeyPair {
+    fn algorithm(&self) -> KeyAlg {
+        KeyAlg::X448
+    }
+}
+
+impl KeyMeta for X448KeyPair {
+    type KeySize = U56;
+}
+
+impl KeyGen for X448KeyPair {
+    fn generate(rng: impl KeyMaterial) -> Result<Self, Error> {
+        let sk = ArrayKey::<U56>::generate(rng);
+        Self::from_secret_key((&*sk).try_into().unwrap())
+    }
+}
+
+impl KeySecretBytes for X448KeyPair {
+    fn from_secret_bytes(key: &[u8]) -> Result<Self, Error> {
+        let sk: &[u8; SECRET_KEY_LENGTH] = key.try_into().map_err(|_| err_msg!(InvalidKeyData))?;
+        Self::from_secret_key(sk)
+    }
+
+    fn with_secret_bytes<O>(&self, f: impl FnOnce(Option<&[u8]>) -> O) -> O {
+        f(self.secret.as_ref().map(|sk| &sk[..]))
+    }
+}
+
+impl KeypairMeta for X448KeyPair {
+    type PublicKeySize = U56;
+    type KeypairSize = U112;
+}
+
+impl KeypairBytes for X448KeyPair {
+    fn from_keypair_bytes(kp: &[u8]) -> Result<Self, Error> {
+        if kp.len() != KEYPAIR_LENGTH {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        let result = Self::from_secret_bytes(&kp[..SECRET_KEY_LENGTH])?;
+        result.check_public_bytes(&kp[SECRET_KEY_LENGTH..])?;
+        Ok(result)
+    }
+
+    fn with_keypair_bytes<O>(&self, f: impl FnOnce(Option<&[u8]>) -> O) -> O {
+        if let Some(secret) = self.secret.as_ref() {
+            ArrayKey::<<Self as KeypairMeta>::KeypairSize>::temp(|arr| {
+                arr[..SECRET_KEY_LENGTH].copy_from_slice(secret);
+                arr[SECRET_KEY_LENGTH..].copy_from_slice(&self.public[..]);
+                f(Some(&*arr))
+            })
+        } else {
+            f(None)
+        }
+    }
+}
+
+impl KeyPublicBytes for X448KeyPair {
+    fn from_public_bytes(key: &[u8]) -> Result<Self, Error> {
+        let pk = PublicKey::from_bytes(key).ok_or_else(|| err_msg!(InvalidKeyData))?;
+        Ok(Self {
+            secret: None,
+            public: *pk.as_bytes(),
+        })
+    }
+
+    fn with_public_bytes<O>(&self, f: impl FnOnce(&[u8]) -> O) -> O {
+        f(&self.public[..])
+    }
+}
+
+impl ToJwk for X448KeyPair {
+    fn encode_jwk(&self, enc: &mut dyn JwkEncoder) -> Result<(), Error> {
+        enc.add_str("crv", JWK_CURVE)?;
+        enc.add_str("kty", JWK_KEY_TYPE)?;
+        self.with_public_bytes(|buf| enc.add_as_base64("x", buf))?;
+        if enc.is_secret() {
+            self.with_secret_bytes(|buf| {
+                if let Some(sk) = buf {
+                    enc.add_as_base64("d", sk)
+                } else {
+                    Ok(())
+                }
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl FromJwk for X448KeyPair {
+    fn from_jwk_parts(jwk: JwkParts<'_>) -> Result<Self, Error> {
+        if jwk.kty != JWK_KEY_TYPE {
+            return Err(err_msg!(InvalidKeyData, "Unsupported key type"));
+        }
+        if jwk.crv != JWK_CURVE {
+            return Err(err_msg!(InvalidKeyData, "Unsupported key algorithm"));
+        }
+        ArrayKey::<U56>::temp(|pk_arr| {
+            if jwk.x.decode_base64(pk_arr)? != pk_arr.len() {
+                Err(err_msg!(InvalidKeyData))
+            } else if jwk.d.is_some() {
+                ArrayKey::<U56>::temp(|sk_arr| {
+                    if jwk.d.decode_base64(sk_arr)? != sk_arr.len() {
+                        Err(err_msg!(InvalidKeyData))
+                    } else {
+                        let kp = X448KeyPair::from_secret_bytes(sk_arr)?;
+                        kp.check_public_bytes(pk_arr)?;
+                        Ok(kp)
+                    }
+                })
+            } else {
+                X448KeyPair::from_public_bytes(pk_arr)
+            }
+        })
+    }
+}
+
+impl KeyExchange for X448KeyPair {
+    fn write_key_exchange(&self, other: &Self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        match self.secret.as_ref() {
+            Some(sk) => {
+                let sk = Secret::from_bytes(&sk[..]).ok_or_else(|| err_msg!(InvalidKeyData))?;
+                let pk = PublicKey::from_bytes(&other.public[..])
+                    .ok_or_else(|| err_msg!(InvalidKeyData))?;
+                let xk = sk
+                    .as_diffie_hellman(&pk)
+                    .ok_or_else(|| err_msg!(InvalidKeyData, "Invalid x448 key exchange"))?;
+                out.buffer_write(xk.as_bytes())?;
+                Ok(())
+            }
+            None => Err(err_msg!(MissingSecretKey)),
+        }
+    }
+}
+
+impl Drop for X448KeyPair {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for X448KeyPair {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::ToPublicBytes;
+
+    #[test]
+    fn jwk_round_trip() {
+        let kp = X448KeyPair::random().unwrap();
+        let jwk = kp
+            .to_jwk_public(None)
+            .expect("Error converting public key to JWK");
+        let jwk = JwkParts::try_from_str(&jwk).expect("Error parsing JWK output");
+        assert_eq!(jwk.kty, JWK_KEY_TYPE);
+        assert_eq!(jwk.crv, JWK_CURVE);
+        assert_eq!(jwk.d, None);
+        let pk_load = X448KeyPair::from_jwk_parts(jwk).unwrap();
+        assert_eq!(kp.to_public_bytes(), pk_load.to_public_bytes());
+
+        let jwk = kp
+            .to_jwk_secret(None)
+            .expect("Error converting private key to JWK");
+        let jwk = JwkParts::from_slice(&jwk).expect("Error parsing JWK output");
+        let sk_load = X448KeyPair::from_jwk_parts(jwk).unwrap();
+        assert_eq!(
+            kp.to_keypair_bytes().unwrap(),
+            sk_load.to_keypair_bytes().unwrap()
+        );
+    }
+
+    #[test]
+    fn key_exchange_random() {
+        let kp1 = X448KeyPair::random().unwrap();
+        let kp2 = X448KeyPair::random().unwrap();
+        assert_ne!(
+            kp1.to_keypair_bytes().unwrap(),
+            kp2.to_keypair_bytes().unwrap()
+        );
+
+        let xch1 = kp1.key_exchange_bytes(&kp2).unwrap();
+        let xch2 = kp2.key_exchange_bytes(&kp1).unwrap();
+        assert_eq!(xch1.len(), 56);
+        assert_eq!(xch1, xch2);
+    }
+
+    #[test]
+    fn round_trip_bytes() {
+        let kp = X448KeyPair::random().unwrap();
+        let cmp = X448KeyPair::from_keypair_bytes(&kp.to_keypair_bytes().unwrap()).unwrap();
+        assert_eq!(
+            kp.to_keypair_bytes().unwrap(),
+            cmp.to_keypair_bytes().unwrap()
+        );
+    }
+}
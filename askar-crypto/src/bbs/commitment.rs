@@ -0,0 +1,247 @@
+use alloc::vec::Vec;
+
+use bls12_381::{G1Affine, G1Projective, Scalar};
+use group::{ff::Field, Curve};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+use super::{
+    g1_from_bytes, scalar_from_bytes, scalar_to_bytes, ChallengeHasher, Generators, Message,
+    G1_LENGTH, SCALAR_LENGTH,
+};
+use crate::error::Error;
+
+const COMMITMENT_DST: &[u8] = b"BBS+_BLS12381_COMMITMENT_PROOF_";
+
+/// A commitment to a set of hidden messages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Commitment(pub(crate) G1Affine);
+
+impl Commitment {
+    /// Serialize the commitment to bytes
+    pub fn to_bytes(&self) -> [u8; G1_LENGTH] {
+        self.0.to_compressed()
+    }
+
+    /// Load a commitment from bytes
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, Error> {
+        Ok(Self(g1_from_bytes(buf)?))
+    }
+}
+
+/// The holder's blinding factor for a commitment
+#[derive(Clone, Debug, PartialEq, Eq, Zeroize)]
+pub struct Blinding(pub(crate) Scalar);
+
+impl Drop for Blinding {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// A builder for a commitment to hidden messages
+#[derive(Clone, Debug)]
+pub struct CommitmentBuilder<'g> {
+    generators: &'g Generators,
+    messages: Vec<(usize, Message)>,
+}
+
+impl<'g> CommitmentBuilder<'g> {
+    /// Create a new commitment builder
+    pub fn new(generators: &'g Generators) -> Self {
+        Self {
+            generators,
+            messages: Vec::new(),
+        }
+    }
+
+    /// Add a hidden message to the commitment
+    pub fn add_message(&mut self, index: usize, message: Message) -> Result<(), Error> {
+        if index >= self.generators.message_count() {
+            return Err(err_msg!(Usage, "Message index exceeds generator count"));
+        }
+        if self.messages.iter().any(|(idx, _)| *idx == index) {
+            return Err(err_msg!(Usage, "Duplicate message index"));
+        }
+        self.messages.push((index, message));
+        Ok(())
+    }
+
+    /// Create the commitment and a proof of knowledge of the committed messages
+    pub fn commit(
+        &self,
+        nonce: &[u8],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<(Commitment, CommitmentProof, Blinding), Error> {
+        let blinding = Blinding(Scalar::random(&mut rng));
+        let r_blinding = Scalar::random(&mut rng);
+        let mut commitment = self.generators.blinding() * blinding.0;
+        let mut t = self.generators.blinding() * r_blinding;
+        let mut randoms = Vec::with_capacity(self.messages.len());
+        for (index, message) in self.messages.iter() {
+            let base = self.generators.message(*index)?;
+            let r = Scalar::random(&mut rng);
+            commitment += base * message.0;
+            t += base * r;
+            randoms.push(r);
+        }
+        let commitment = Commitment(commitment.to_affine());
+        let challenge = commitment_challenge(&commitment, &t.to_affine(), &self.messages, nonce);
+        let responses = self
+            .messages
+            .iter()
+            .zip(randoms)
+            .map(|((index, message), r)| (*index, r + challenge * message.0))
+            .collect();
+        let proof = CommitmentProof {
+            challenge,
+            blinding_response: r_blinding + challenge * blinding.0,
+            responses,
+        };
+        Ok((commitment, proof, blinding))
+    }
+}
+
+fn commitment_challenge<T>(
+    commitment: &Commitment,
+    t: &G1Affine,
+    indices: &[(usize, T)],
+    nonce: &[u8],
+) -> Scalar {
+    let mut hasher = ChallengeHasher::new(COMMITMENT_DST);
+    hasher.update_g1(&commitment.0);
+    hasher.update_g1(t);
+    hasher.update_index(indices.len());
+    for (index, _) in indices {
+        hasher.update_index(*index);
+    }
+    hasher.update_bytes(nonce);
+    hasher.finalize()
+}
+
+/// A proof of knowledge of the messages hidden in a commitment
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitmentProof {
+    challenge: Scalar,
+    blinding_response: Scalar,
+    responses: Vec<(usize, Scalar)>,
+}
+
+impl CommitmentProof {
+    /// Accessor for the indices of the committed messages
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.responses.iter().map(|(index, _)| *index)
+    }
+
+    /// Verify the proof against a commitment
+    pub fn verify(
+        &self,
+        generators: &Generators,
+        commitment: &Commitment,
+        nonce: &[u8],
+    ) -> Result<(), Error> {
+        let mut t = generators.blinding() * self.blinding_response
+            - G1Projective::from(commitment.0) * self.challenge;
+        for (pos, (index, resp)) in self.responses.iter().enumerate() {
+            if self.responses[..pos].iter().any(|(idx, _)| idx == index) {
+                return Err(err_msg!(Invalid, "Duplicate message index"));
+            }
+            t += generators.message(*index)? * resp;
+        }
+        let challenge = commitment_challenge(commitment, &t.to_affine(), &self.responses, nonce);
+        if challenge == self.challenge {
+            Ok(())
+        } else {
+            Err(err_msg!(Invalid, "Commitment proof verification failed"))
+        }
+    }
+
+    /// Serialize the proof to bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf =
+            Vec::with_capacity(SCALAR_LENGTH * 2 + 4 + self.responses.len() * (SCALAR_LENGTH + 4));
+        buf.extend_from_slice(&scalar_to_bytes(&self.challenge));
+        buf.extend_from_slice(&scalar_to_bytes(&self.blinding_response));
+        buf.extend_from_slice(&(self.responses.len() as u32).to_be_bytes());
+        for (index, resp) in self.responses.iter() {
+            buf.extend_from_slice(&(*index as u32).to_be_bytes());
+            buf.extend_from_slice(&scalar_to_bytes(resp));
+        }
+        buf
+    }
+
+    /// Load a proof from bytes
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, Error> {
+        const HEADER_LENGTH: usize = SCALAR_LENGTH * 2 + 4;
+        if buf.len() < HEADER_LENGTH {
+            return Err(err_msg!(Invalid, "Invalid commitment proof length"));
+        }
+        let challenge = scalar_from_bytes(&buf[..SCALAR_LENGTH])?;
+        let blinding_response = scalar_from_bytes(&buf[SCALAR_LENGTH..(SCALAR_LENGTH * 2)])?;
+        let count = u32::from_be_bytes(buf[(SCALAR_LENGTH * 2)..HEADER_LENGTH].try_into().unwrap())
+            as usize;
+        let entries = &buf[HEADER_LENGTH..];
+        if entries.len() != count * (SCALAR_LENGTH + 4) {
+            return Err(err_msg!(Invalid, "Invalid commitment proof length"));
+        }
+        let responses = entries
+            .chunks_exact(SCALAR_LENGTH + 4)
+            .map(|entry| {
+                let index = u32::from_be_bytes(entry[..4].try_into().unwrap()) as usize;
+                Ok((index, scalar_from_bytes(&entry[4..])?))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self {
+            challenge,
+            blinding_response,
+            responses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alg::bls::{BlsKeyPair, G2},
+        bbs::Signature,
+        random::RandomDet,
+        repr::KeyGen,
+    };
+
+    #[test]
+    fn blind_sign_unblind() {
+        let mut rng = RandomDet::new(b"testseed");
+        let kp = BlsKeyPair::<G2>::generate(&mut rng).unwrap();
+        let gens = Generators::new(&kp, 3);
+        let messages = [
+            Message::hash(b"secret"),
+            Message::hash(b"two"),
+            Message::hash(b"three"),
+        ];
+        let nonce = b"issuer nonce";
+
+        let mut builder = CommitmentBuilder::new(&gens);
+        builder.add_message(0, messages[0]).unwrap();
+        assert!(builder.add_message(0, messages[0]).is_err());
+        assert!(builder.add_message(3, messages[0]).is_err());
+        let (commitment, proof, blinding) = builder.commit(nonce, &mut rng).unwrap();
+
+        let proof = CommitmentProof::from_bytes(&proof.to_bytes()).unwrap();
+        proof.verify(&gens, &commitment, nonce).unwrap();
+        assert!(proof.verify(&gens, &commitment, b"other nonce").is_err());
+        assert_eq!(proof.indices().collect::<Vec<_>>(), [0]);
+
+        let blind_sig = Signature::sign_blinded(
+            &kp,
+            &gens,
+            &commitment,
+            &[(1, messages[1]), (2, messages[2])],
+            &mut rng,
+        )
+        .unwrap();
+        assert!(blind_sig.verify(&gens, &messages).is_err());
+        let sig = blind_sig.unblind(&blinding);
+        sig.verify(&gens, &messages).unwrap();
+    }
+}
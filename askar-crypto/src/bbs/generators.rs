@@ -0,0 +1,88 @@
+use alloc::vec::Vec;
+
+use bls12_381::{hash_to_curve::HashToCurve, G1Affine, G1Projective, G2Affine};
+use group::Curve;
+
+use crate::{
+    alg::bls::{BlsKeyPair, ExpandMsgSha256, G2},
+    error::Error,
+};
+
+const GENERATOR_DST: &[u8] = b"BLS12381G1_XMD:SHA-256_SSWU_RO_BBS+_GENERATORS_";
+
+/// The message generators derived from a BBS+ public key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Generators {
+    public_key: G2Affine,
+    blinding: G1Affine,
+    messages: Vec<G1Affine>,
+}
+
+impl Generators {
+    /// Derive the generators for a G2 key pair and message count
+    pub fn new(key: &BlsKeyPair<G2>, message_count: usize) -> Self {
+        Self::from_public_key(*key.bls_public_key(), message_count)
+    }
+
+    /// Derive the generators for a G2 public key and message count
+    pub fn from_public_key(public_key: G2Affine, message_count: usize) -> Self {
+        let pk = public_key.to_compressed();
+        let blinding = hash_generator(&pk, 0);
+        let messages = (1..=message_count)
+            .map(|index| hash_generator(&pk, index))
+            .collect();
+        Self {
+            public_key,
+            blinding,
+            messages,
+        }
+    }
+
+    /// Accessor for the associated public key
+    pub fn public_key(&self) -> &G2Affine {
+        &self.public_key
+    }
+
+    /// Accessor for the number of supported messages
+    pub fn message_count(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Accessor for the blinding generator
+    pub fn blinding(&self) -> &G1Affine {
+        &self.blinding
+    }
+
+    /// Accessor for the generator of a message index
+    pub fn message(&self, index: usize) -> Result<&G1Affine, Error> {
+        self.messages
+            .get(index)
+            .ok_or_else(|| err_msg!(Usage, "Message index exceeds generator count"))
+    }
+}
+
+fn hash_generator(pk: &[u8; 96], index: usize) -> G1Affine {
+    let mut input = [0u8; 100];
+    input[..96].copy_from_slice(&pk[..]);
+    input[96..].copy_from_slice(&(index as u32).to_be_bytes());
+    <G1Projective as HashToCurve<ExpandMsgSha256>>::hash_to_curve(&input[..], GENERATOR_DST)
+        .to_affine()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::KeySecretBytes;
+
+    #[test]
+    fn generators_distinct() {
+        let sk = hex!("0d7359d57963ab8fbbde1852dcf553fedbc31f464d80ee7d40ae683122b45070");
+        let kp = BlsKeyPair::<G2>::from_secret_bytes(&sk[..]).unwrap();
+        let gens = Generators::new(&kp, 3);
+        assert_eq!(gens.message_count(), 3);
+        assert_ne!(gens.blinding(), gens.message(0).unwrap());
+        assert_ne!(gens.message(0).unwrap(), gens.message(1).unwrap());
+        assert!(gens.message(3).is_err());
+        assert_eq!(gens, Generators::new(&kp, 3));
+    }
+}
@@ -0,0 +1,44 @@
+use bls12_381::Scalar;
+use sha2::{Digest, Sha512};
+
+const MESSAGE_DST: &[u8] = b"BBS+_BLS12381_MESSAGE_";
+
+/// A message value to be included in a BBS+ signature
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Message(pub(crate) Scalar);
+
+impl Message {
+    /// Hash an arbitrary byte string into a message value
+    pub fn hash(input: impl AsRef<[u8]>) -> Self {
+        let mut hash = Sha512::new_with_prefix(MESSAGE_DST);
+        hash.update(input);
+        Self(Scalar::from_bytes_wide(&hash.finalize().into()))
+    }
+
+    /// Create a message directly from a scalar value
+    pub fn from_scalar(scalar: Scalar) -> Self {
+        Self(scalar)
+    }
+
+    /// Accessor for the scalar value of the message
+    pub fn as_scalar(&self) -> &Scalar {
+        &self.0
+    }
+}
+
+impl From<Scalar> for Message {
+    fn from(scalar: Scalar) -> Self {
+        Self(scalar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_message() {
+        assert_eq!(Message::hash(b"message"), Message::hash(b"message"));
+        assert_ne!(Message::hash(b"message"), Message::hash(b"other"));
+    }
+}
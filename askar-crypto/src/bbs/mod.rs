@@ -0,0 +1,84 @@
+//! BBS+ signature support on BLS12-381
+//!
+//! BBS+ signatures are created by a BLS12-381 G2 key pair over an ordered set of
+//! messages. The holder of a signature may request a signature over messages
+//! hidden within a commitment, and later prove knowledge of the signature while
+//! disclosing only a subset of the signed messages.
+
+use bls12_381::{G1Affine, Scalar};
+use group::GroupEncoding;
+use sha2::{Digest, Sha512};
+
+use crate::error::Error;
+
+mod commitment;
+pub use self::commitment::{Blinding, Commitment, CommitmentBuilder, CommitmentProof};
+
+mod generators;
+pub use self::generators::Generators;
+
+mod message;
+pub use self::message::Message;
+
+mod proof;
+pub use self::proof::{ProofBuilder, SignatureProof};
+
+mod signature;
+pub use self::signature::{Signature, SIGNATURE_LENGTH};
+
+/// The length of a serialized scalar value in bytes
+pub(crate) const SCALAR_LENGTH: usize = 32;
+
+/// The length of a serialized G1 point in bytes
+pub(crate) const G1_LENGTH: usize = 48;
+
+/// Serialize a scalar value in big-endian format
+pub(crate) fn scalar_to_bytes(s: &Scalar) -> [u8; SCALAR_LENGTH] {
+    let mut b = s.to_bytes();
+    b.reverse(); // into big-endian
+    b
+}
+
+/// Deserialize a scalar value in big-endian format
+pub(crate) fn scalar_from_bytes(b: &[u8]) -> Result<Scalar, Error> {
+    let mut sb: [u8; SCALAR_LENGTH] = b.try_into().map_err(|_| err_msg!(Invalid))?;
+    sb.reverse(); // into little-endian
+    Option::from(Scalar::from_bytes(&sb)).ok_or_else(|| err_msg!(Invalid, "Invalid scalar value"))
+}
+
+/// Deserialize a compressed G1 point
+pub(crate) fn g1_from_bytes(b: &[u8]) -> Result<G1Affine, Error> {
+    let gb: &[u8; G1_LENGTH] = b.try_into().map_err(|_| err_msg!(Invalid))?;
+    Option::from(G1Affine::from_compressed(gb)).ok_or_else(|| err_msg!(Invalid, "Invalid G1 point"))
+}
+
+/// Accumulate the inputs to a Fiat-Shamir challenge
+#[derive(Clone, Debug)]
+pub(crate) struct ChallengeHasher(Sha512);
+
+impl ChallengeHasher {
+    pub fn new(dst: &[u8]) -> Self {
+        Self(Sha512::new_with_prefix(dst))
+    }
+
+    pub fn update_g1(&mut self, point: &G1Affine) {
+        self.0.update(point.to_bytes());
+    }
+
+    pub fn update_scalar(&mut self, scalar: &Scalar) {
+        self.0.update(scalar_to_bytes(scalar));
+    }
+
+    pub fn update_index(&mut self, index: usize) {
+        self.0.update((index as u32).to_be_bytes());
+    }
+
+    pub fn update_bytes(&mut self, bytes: &[u8]) {
+        self.update_index(bytes.len());
+        self.0.update(bytes);
+    }
+
+    pub fn finalize(self) -> Scalar {
+        Scalar::from_bytes_wide(&self.0.finalize().into())
+    }
+}
@@ -0,0 +1,353 @@
+use alloc::{vec, vec::Vec};
+
+use bls12_381::{pairing, G1Affine, G1Projective, G2Affine, Scalar};
+use group::{ff::Field, Curve};
+use rand::{CryptoRng, RngCore};
+
+use super::{
+    g1_from_bytes, scalar_from_bytes, scalar_to_bytes, signature::compute_b, ChallengeHasher,
+    Generators, Message, Signature, G1_LENGTH, SCALAR_LENGTH,
+};
+use crate::error::Error;
+
+const PROOF_DST: &[u8] = b"BBS+_BLS12381_SIGNATURE_PROOF_";
+
+const PROOF_HEADER_LENGTH: usize = G1_LENGTH * 3 + SCALAR_LENGTH * 5 + 4;
+
+/// A builder for a selective disclosure proof of a BBS+ signature
+#[derive(Clone, Debug)]
+pub struct ProofBuilder<'g> {
+    generators: &'g Generators,
+    signature: Signature,
+    messages: Vec<Message>,
+    disclosed: Vec<bool>,
+}
+
+impl<'g> ProofBuilder<'g> {
+    /// Create a new proof builder for a signature and its messages
+    pub fn new(
+        generators: &'g Generators,
+        signature: &Signature,
+        messages: &[Message],
+    ) -> Result<Self, Error> {
+        if messages.len() != generators.message_count() {
+            return Err(err_msg!(Usage, "Message count does not match generators"));
+        }
+        Ok(Self {
+            generators,
+            signature: *signature,
+            messages: messages.to_vec(),
+            disclosed: vec![false; messages.len()],
+        })
+    }
+
+    /// Reveal a message in the generated proof
+    pub fn disclose(&mut self, index: usize) -> Result<(), Error> {
+        let flag = self
+            .disclosed
+            .get_mut(index)
+            .ok_or_else(|| err_msg!(Usage, "Message index exceeds generator count"))?;
+        *flag = true;
+        Ok(())
+    }
+
+    /// Accessor for the messages to be disclosed
+    pub fn disclosed_messages(&self) -> Vec<(usize, Message)> {
+        self.messages
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| self.disclosed[*index])
+            .map(|(index, message)| (index, *message))
+            .collect()
+    }
+
+    /// Generate a proof of knowledge of the signature
+    pub fn prove(
+        &self,
+        nonce: &[u8],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<SignatureProof, Error> {
+        let gens = self.generators;
+        let sig = &self.signature;
+        let b = compute_b(
+            gens,
+            &sig.s,
+            self.messages.iter().copied().enumerate(),
+            None,
+        )?;
+
+        let r1 = loop {
+            let r = Scalar::random(&mut rng);
+            if !bool::from(r.is_zero()) {
+                break r;
+            }
+        };
+        let r2 = Scalar::random(&mut rng);
+        let r3 = Option::<Scalar>::from(r1.invert()).unwrap();
+        let a_prime = sig.a * r1;
+        let b_r1 = b * r1;
+        let a_bar = a_prime * (-sig.e) + b_r1;
+        let d = b_r1 - gens.blinding() * r2;
+        let s_prime = sig.s - r2 * r3;
+
+        // proof of knowledge for a_bar - d = a_prime * -e + h0 * r2
+        let re = Scalar::random(&mut rng);
+        let rr2 = Scalar::random(&mut rng);
+        let t1 = a_prime * re + gens.blinding() * rr2;
+
+        // proof of knowledge for g1 + sum(h_i * m_i, disclosed)
+        //   = d * r3 + h0 * -s_prime + sum(h_j * -m_j, hidden)
+        let rr3 = Scalar::random(&mut rng);
+        let rs = Scalar::random(&mut rng);
+        let mut t2 = d * rr3 + gens.blinding() * rs;
+        let mut hidden = Vec::new();
+        for (index, message) in self.messages.iter().enumerate() {
+            if !self.disclosed[index] {
+                let r = Scalar::random(&mut rng);
+                t2 += gens.message(index)? * r;
+                hidden.push((index, message.0, r));
+            }
+        }
+
+        let mut affine = [G1Affine::identity(); 5];
+        G1Projective::batch_normalize(&[a_prime, a_bar, d, t1, t2], &mut affine);
+        let [a_prime, a_bar, d, t1, t2] = affine;
+        let challenge = proof_challenge(
+            gens,
+            &a_prime,
+            &a_bar,
+            &d,
+            &t1,
+            &t2,
+            &self.disclosed_messages(),
+            nonce,
+        );
+
+        Ok(SignatureProof {
+            a_prime,
+            a_bar,
+            d,
+            challenge,
+            e_response: re - challenge * sig.e,
+            r2_response: rr2 + challenge * r2,
+            r3_response: rr3 + challenge * r3,
+            s_response: rs - challenge * s_prime,
+            m_responses: hidden
+                .into_iter()
+                .map(|(index, m, r)| (index, r - challenge * m))
+                .collect(),
+        })
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn proof_challenge(
+    generators: &Generators,
+    a_prime: &G1Affine,
+    a_bar: &G1Affine,
+    d: &G1Affine,
+    t1: &G1Affine,
+    t2: &G1Affine,
+    disclosed: &[(usize, Message)],
+    nonce: &[u8],
+) -> Scalar {
+    let mut hasher = ChallengeHasher::new(PROOF_DST);
+    hasher.update_index(generators.message_count());
+    hasher.update_g1(a_prime);
+    hasher.update_g1(a_bar);
+    hasher.update_g1(d);
+    hasher.update_g1(t1);
+    hasher.update_g1(t2);
+    hasher.update_index(disclosed.len());
+    for (index, message) in disclosed {
+        hasher.update_index(*index);
+        hasher.update_scalar(&message.0);
+    }
+    hasher.update_bytes(nonce);
+    hasher.finalize()
+}
+
+/// A zero-knowledge proof of a BBS+ signature, disclosing a subset of the messages
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureProof {
+    a_prime: G1Affine,
+    a_bar: G1Affine,
+    d: G1Affine,
+    challenge: Scalar,
+    e_response: Scalar,
+    r2_response: Scalar,
+    r3_response: Scalar,
+    s_response: Scalar,
+    m_responses: Vec<(usize, Scalar)>,
+}
+
+impl SignatureProof {
+    /// Verify the proof against the disclosed messages
+    pub fn verify(
+        &self,
+        generators: &Generators,
+        disclosed: &[(usize, Message)],
+        nonce: &[u8],
+    ) -> Result<(), Error> {
+        // each message must be either disclosed or hidden, exactly once
+        let mut seen = vec![false; generators.message_count()];
+        for index in disclosed
+            .iter()
+            .map(|(index, _)| *index)
+            .chain(self.m_responses.iter().map(|(index, _)| *index))
+        {
+            match seen.get_mut(index) {
+                Some(flag) if !*flag => *flag = true,
+                _ => return Err(err_msg!(Invalid, "Invalid message indices")),
+            }
+        }
+        if seen.iter().any(|flag| !flag) {
+            return Err(err_msg!(Invalid, "Invalid message indices"));
+        }
+        if disclosed.windows(2).any(|w| w[0].0 >= w[1].0) {
+            return Err(err_msg!(
+                Usage,
+                "Disclosed messages must be ordered by index"
+            ));
+        }
+
+        if bool::from(self.a_prime.is_identity())
+            || pairing(&self.a_prime, generators.public_key())
+                != pairing(&self.a_bar, &G2Affine::generator())
+        {
+            return Err(err_msg!(Invalid, "Signature proof verification failed"));
+        }
+
+        let c = self.challenge;
+        let t1 = self.a_prime * self.e_response + generators.blinding() * self.r2_response
+            - (G1Projective::from(self.a_bar) - self.d) * c;
+        let mut y2 = G1Projective::generator();
+        for (index, message) in disclosed {
+            y2 += generators.message(*index)? * message.0;
+        }
+        let mut t2 = self.d * self.r3_response + generators.blinding() * self.s_response - y2 * c;
+        for (index, resp) in self.m_responses.iter() {
+            t2 += generators.message(*index)? * resp;
+        }
+
+        let challenge = proof_challenge(
+            generators,
+            &self.a_prime,
+            &self.a_bar,
+            &self.d,
+            &t1.to_affine(),
+            &t2.to_affine(),
+            disclosed,
+            nonce,
+        );
+        if challenge == self.challenge {
+            Ok(())
+        } else {
+            Err(err_msg!(Invalid, "Signature proof verification failed"))
+        }
+    }
+
+    /// Serialize the proof to bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf =
+            Vec::with_capacity(PROOF_HEADER_LENGTH + self.m_responses.len() * (SCALAR_LENGTH + 4));
+        buf.extend_from_slice(&self.a_prime.to_compressed());
+        buf.extend_from_slice(&self.a_bar.to_compressed());
+        buf.extend_from_slice(&self.d.to_compressed());
+        for s in [
+            &self.challenge,
+            &self.e_response,
+            &self.r2_response,
+            &self.r3_response,
+            &self.s_response,
+        ] {
+            buf.extend_from_slice(&scalar_to_bytes(s));
+        }
+        buf.extend_from_slice(&(self.m_responses.len() as u32).to_be_bytes());
+        for (index, resp) in self.m_responses.iter() {
+            buf.extend_from_slice(&(*index as u32).to_be_bytes());
+            buf.extend_from_slice(&scalar_to_bytes(resp));
+        }
+        buf
+    }
+
+    /// Load a proof from bytes
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, Error> {
+        if buf.len() < PROOF_HEADER_LENGTH {
+            return Err(err_msg!(Invalid, "Invalid signature proof length"));
+        }
+        let point = |idx: usize| g1_from_bytes(&buf[(idx * G1_LENGTH)..((idx + 1) * G1_LENGTH)]);
+        let scalar = |idx: usize| {
+            let start = G1_LENGTH * 3 + idx * SCALAR_LENGTH;
+            scalar_from_bytes(&buf[start..(start + SCALAR_LENGTH)])
+        };
+        let count = u32::from_be_bytes(
+            buf[(PROOF_HEADER_LENGTH - 4)..PROOF_HEADER_LENGTH]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        let entries = &buf[PROOF_HEADER_LENGTH..];
+        if entries.len() != count * (SCALAR_LENGTH + 4) {
+            return Err(err_msg!(Invalid, "Invalid signature proof length"));
+        }
+        let m_responses = entries
+            .chunks_exact(SCALAR_LENGTH + 4)
+            .map(|entry| {
+                let index = u32::from_be_bytes(entry[..4].try_into().unwrap()) as usize;
+                Ok((index, scalar_from_bytes(&entry[4..])?))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self {
+            a_prime: point(0)?,
+            a_bar: point(1)?,
+            d: point(2)?,
+            challenge: scalar(0)?,
+            e_response: scalar(1)?,
+            r2_response: scalar(2)?,
+            r3_response: scalar(3)?,
+            s_response: scalar(4)?,
+            m_responses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alg::bls::{BlsKeyPair, G2},
+        random::RandomDet,
+        repr::KeyGen,
+    };
+
+    #[test]
+    fn prove_selective_disclosure() {
+        let mut rng = RandomDet::new(b"testseed");
+        let kp = BlsKeyPair::<G2>::generate(&mut rng).unwrap();
+        let gens = Generators::new(&kp, 4);
+        let messages = [
+            Message::hash(b"one"),
+            Message::hash(b"two"),
+            Message::hash(b"three"),
+            Message::hash(b"four"),
+        ];
+        let sig = Signature::sign(&kp, &gens, &messages, &mut rng).unwrap();
+        let nonce = b"verifier nonce";
+
+        let mut builder = ProofBuilder::new(&gens, &sig, &messages).unwrap();
+        builder.disclose(1).unwrap();
+        builder.disclose(3).unwrap();
+        assert!(builder.disclose(4).is_err());
+        let disclosed = builder.disclosed_messages();
+        assert_eq!(disclosed, [(1, messages[1]), (3, messages[3])]);
+        let proof = builder.prove(nonce, &mut rng).unwrap();
+
+        let proof = SignatureProof::from_bytes(&proof.to_bytes()).unwrap();
+        proof.verify(&gens, &disclosed, nonce).unwrap();
+        assert!(proof.verify(&gens, &disclosed, b"other nonce").is_err());
+        assert!(proof
+            .verify(&gens, &[(1, messages[1]), (3, messages[2])], nonce)
+            .is_err());
+        assert!(proof.verify(&gens, &disclosed[..1], nonce).is_err());
+    }
+}
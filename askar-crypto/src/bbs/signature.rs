@@ -0,0 +1,181 @@
+use bls12_381::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+use group::{ff::Field, Curve, GroupEncoding};
+use rand::{CryptoRng, RngCore};
+
+use super::{
+    g1_from_bytes, scalar_from_bytes, scalar_to_bytes, Blinding, Commitment, Generators, Message,
+    G1_LENGTH, SCALAR_LENGTH,
+};
+use crate::{
+    alg::bls::{BlsKeyPair, G2},
+    error::Error,
+};
+
+/// The length of a serialized BBS+ signature in bytes
+pub const SIGNATURE_LENGTH: usize = G1_LENGTH + SCALAR_LENGTH * 2;
+
+/// A BBS+ signature over a set of messages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub(crate) a: G1Affine,
+    pub(crate) e: Scalar,
+    pub(crate) s: Scalar,
+}
+
+impl Signature {
+    /// Sign an ordered set of messages
+    pub fn sign(
+        key: &BlsKeyPair<G2>,
+        generators: &Generators,
+        messages: &[Message],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Self, Error> {
+        if messages.len() != generators.message_count() {
+            return Err(err_msg!(Usage, "Message count does not match generators"));
+        }
+        let s = Scalar::random(&mut rng);
+        let b = compute_b(generators, &s, messages.iter().copied().enumerate(), None)?;
+        Self::sign_b(key, b, s, rng)
+    }
+
+    /// Sign a set of known messages along with the hidden messages of a commitment
+    ///
+    /// The commitment proof must be verified by the caller before signing. The
+    /// resulting signature must be unblinded by the holder before use.
+    pub fn sign_blinded(
+        key: &BlsKeyPair<G2>,
+        generators: &Generators,
+        commitment: &Commitment,
+        messages: &[(usize, Message)],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Self, Error> {
+        let s = Scalar::random(&mut rng);
+        let b = compute_b(
+            generators,
+            &s,
+            messages.iter().copied(),
+            Some(commitment.0.into()),
+        )?;
+        Self::sign_b(key, b, s, rng)
+    }
+
+    fn sign_b(
+        key: &BlsKeyPair<G2>,
+        b: G1Projective,
+        s: Scalar,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Self, Error> {
+        let x = key
+            .bls_secret_scalar()
+            .ok_or_else(|| err_msg!(MissingSecretKey))?;
+        loop {
+            let e = Scalar::random(&mut rng);
+            if let Some(inv) = Option::<Scalar>::from((x + e).invert()) {
+                return Ok(Self {
+                    a: (b * inv).to_affine(),
+                    e,
+                    s,
+                });
+            }
+        }
+    }
+
+    /// Remove the holder's blinding factor from a blinded signature
+    pub fn unblind(&self, blinding: &Blinding) -> Self {
+        Self {
+            a: self.a,
+            e: self.e,
+            s: self.s + blinding.0,
+        }
+    }
+
+    /// Verify the signature over an ordered set of messages
+    pub fn verify(&self, generators: &Generators, messages: &[Message]) -> Result<(), Error> {
+        if messages.len() != generators.message_count() {
+            return Err(err_msg!(Usage, "Message count does not match generators"));
+        }
+        if bool::from(self.a.is_identity()) {
+            return Err(err_msg!(Invalid, "Invalid signature"));
+        }
+        let b = compute_b(
+            generators,
+            &self.s,
+            messages.iter().copied().enumerate(),
+            None,
+        )?;
+        let pk_e = G2Projective::from(generators.public_key()) + G2Projective::generator() * self.e;
+        let check =
+            pairing(&self.a, &pk_e.to_affine()) == pairing(&b.to_affine(), &G2Affine::generator());
+        if check {
+            Ok(())
+        } else {
+            Err(err_msg!(Invalid, "Signature verification failed"))
+        }
+    }
+
+    /// Serialize the signature to bytes
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_LENGTH] {
+        let mut buf = [0u8; SIGNATURE_LENGTH];
+        buf[..G1_LENGTH].copy_from_slice(self.a.to_bytes().as_ref());
+        buf[G1_LENGTH..(G1_LENGTH + SCALAR_LENGTH)].copy_from_slice(&scalar_to_bytes(&self.e));
+        buf[(G1_LENGTH + SCALAR_LENGTH)..].copy_from_slice(&scalar_to_bytes(&self.s));
+        buf
+    }
+
+    /// Load a signature from bytes
+    pub fn from_bytes(sig: &[u8]) -> Result<Self, Error> {
+        if sig.len() != SIGNATURE_LENGTH {
+            return Err(err_msg!(Invalid, "Invalid signature length"));
+        }
+        Ok(Self {
+            a: g1_from_bytes(&sig[..G1_LENGTH])?,
+            e: scalar_from_bytes(&sig[G1_LENGTH..(G1_LENGTH + SCALAR_LENGTH)])?,
+            s: scalar_from_bytes(&sig[(G1_LENGTH + SCALAR_LENGTH)..])?,
+        })
+    }
+}
+
+/// Compute `g1 + h0 * s + sum(h_i * m_i) + commitment`
+pub(crate) fn compute_b(
+    generators: &Generators,
+    s: &Scalar,
+    messages: impl IntoIterator<Item = (usize, Message)>,
+    commitment: Option<G1Projective>,
+) -> Result<G1Projective, Error> {
+    let mut b = G1Projective::generator() + generators.blinding() * s;
+    for (index, message) in messages {
+        b += generators.message(index)? * message.0;
+    }
+    if let Some(commitment) = commitment {
+        b += commitment;
+    }
+    Ok(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random::RandomDet, repr::KeyGen};
+
+    #[test]
+    fn sign_verify() {
+        let mut rng = RandomDet::new(b"testseed");
+        let kp = BlsKeyPair::<G2>::generate(&mut rng).unwrap();
+        let gens = Generators::new(&kp, 3);
+        let messages = [
+            Message::hash(b"one"),
+            Message::hash(b"two"),
+            Message::hash(b"three"),
+        ];
+        let sig = Signature::sign(&kp, &gens, &messages, &mut rng).unwrap();
+        sig.verify(&gens, &messages).unwrap();
+
+        let sig_load = Signature::from_bytes(&sig.to_bytes()).unwrap();
+        assert_eq!(sig, sig_load);
+
+        let mut wrong = messages;
+        wrong[1] = Message::hash(b"other");
+        assert!(sig.verify(&gens, &wrong).is_err());
+        assert!(sig.verify(&gens, &messages[..2]).is_err());
+    }
+}
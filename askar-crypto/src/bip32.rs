@@ -0,0 +1,501 @@
+//! BIP-32 and SLIP-0010 hierarchical deterministic key derivation
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    str::FromStr,
+};
+
+use hmac::{Hmac, Mac};
+use k256::{
+    elliptic_curve::{group::Curve, sec1::ToEncodedPoint, PrimeField},
+    FieldBytes, ProjectivePoint, PublicKey, Scalar, SecretKey,
+};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256, Sha512};
+use zeroize::Zeroize;
+
+use crate::{
+    alg::{ed25519::Ed25519KeyPair, k256::K256KeyPair},
+    buffer::ArrayKey,
+    error::Error,
+    generic_array::typenum::U32,
+    repr::{KeyPublicBytes, KeySecretBytes},
+};
+
+/// The offset of hardened child indices
+pub const HARDENED: u32 = 0x8000_0000;
+
+/// The length of a serialized extended key, excluding the checksum
+const EXTENDED_KEY_LENGTH: usize = 78;
+
+const XPRV_VERSION: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+
+/// Supported curves for hierarchical key derivation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HdCurve {
+    /// secp256k1 keys derived according to BIP-32
+    Secp256k1,
+    /// Ed25519 keys derived according to SLIP-0010, supporting only
+    /// hardened derivation
+    Ed25519,
+}
+
+impl HdCurve {
+    fn seed_key(&self) -> &'static [u8] {
+        match self {
+            Self::Secp256k1 => b"Bitcoin seed",
+            Self::Ed25519 => b"ed25519 seed",
+        }
+    }
+}
+
+/// A derivation path such as `m/44'/0'/0'/0/1`
+///
+/// Hardened indices are marked with a trailing `'` or `h`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Access the child indices of the path, with the hardened flag applied
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(err_msg!(Usage, "Derivation path must start with 'm'"));
+        }
+        let mut path = Vec::new();
+        for part in parts {
+            let (num, hardened) = match part.strip_suffix(|c| c == '\'' || c == 'h') {
+                Some(num) => (num, true),
+                None => (part, false),
+            };
+            let index = num
+                .parse::<u32>()
+                .ok()
+                .filter(|idx| *idx < HARDENED && !num.starts_with('+'))
+                .ok_or_else(|| err_msg!(Usage, "Invalid derivation path index"))?;
+            path.push(if hardened { index | HARDENED } else { index });
+        }
+        Ok(Self(path))
+    }
+}
+
+/// An extended private or public key
+#[derive(Clone)]
+pub struct ExtendedKey {
+    curve: HdCurve,
+    secret: Option<ArrayKey<U32>>,
+    public: [u8; 33],
+    chain_code: ArrayKey<U32>,
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: u32,
+}
+
+impl ExtendedKey {
+    /// Derive the master extended key from a seed value
+    pub fn from_seed(curve: HdCurve, seed: &[u8]) -> Result<Self, Error> {
+        if seed.len() < 16 || seed.len() > 64 {
+            return Err(err_msg!(Usage, "Invalid seed length for key derivation"));
+        }
+        let mut output = hmac_sha512(curve.seed_key(), &[seed]);
+        let result = Self::from_parts(curve, &output[..32], &output[32..], 0, [0; 4], 0);
+        output.zeroize();
+        result
+    }
+
+    fn from_parts(
+        curve: HdCurve,
+        secret: &[u8],
+        chain_code: &[u8],
+        depth: u8,
+        parent_fingerprint: [u8; 4],
+        child_number: u32,
+    ) -> Result<Self, Error> {
+        let mut public = [0u8; 33];
+        match curve {
+            HdCurve::Secp256k1 => {
+                let sk = SecretKey::from_slice(secret)
+                    .map_err(|_| err_msg!(InvalidKeyData, "Invalid derived key"))?;
+                public.copy_from_slice(sk.public_key().to_encoded_point(true).as_bytes());
+            }
+            HdCurve::Ed25519 => {
+                let kp = Ed25519KeyPair::from_secret_bytes(secret)?;
+                kp.with_public_bytes(|pk| public[1..].copy_from_slice(pk));
+            }
+        }
+        Ok(Self {
+            curve,
+            secret: Some(ArrayKey::from_slice(secret)),
+            public,
+            chain_code: ArrayKey::from_slice(chain_code),
+            depth,
+            parent_fingerprint,
+            child_number,
+        })
+    }
+
+    /// Accessor for the curve of the extended key
+    pub fn curve(&self) -> HdCurve {
+        self.curve
+    }
+
+    /// Accessor for the depth of the key in the derivation tree
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Accessor for the child index of the key, with the hardened flag applied
+    pub fn child_number(&self) -> u32 {
+        self.child_number
+    }
+
+    /// Determine whether the extended key includes the private key
+    pub fn is_private(&self) -> bool {
+        self.secret.is_some()
+    }
+
+    /// Calculate the fingerprint of the key, used to identify its children
+    pub fn fingerprint(&self) -> [u8; 4] {
+        let hash = Ripemd160::digest(Sha256::digest(self.public));
+        let mut fp = [0u8; 4];
+        fp.copy_from_slice(&hash[..4]);
+        fp
+    }
+
+    /// Derive a child key at the given index
+    ///
+    /// Hardened derivation requires the private key, and is the only form of
+    /// derivation supported for Ed25519.
+    pub fn derive_child(&self, index: u32) -> Result<Self, Error> {
+        let hardened = index & HARDENED != 0;
+        let depth = self
+            .depth
+            .checked_add(1)
+            .ok_or_else(|| err_msg!(Usage, "Maximum derivation depth exceeded"))?;
+        let index_bytes = index.to_be_bytes();
+        let mut output = match (hardened, self.secret.as_ref()) {
+            (true, Some(secret)) => hmac_sha512(
+                self.chain_code.as_ref(),
+                &[&[0u8], secret.as_ref(), &index_bytes],
+            ),
+            (true, None) => {
+                return Err(err_msg!(
+                    MissingSecretKey,
+                    "Hardened derivation requires a private key"
+                ))
+            }
+            (false, _) if self.curve == HdCurve::Ed25519 => {
+                return Err(err_msg!(
+                    Unsupported,
+                    "Only hardened derivation is supported for Ed25519"
+                ))
+            }
+            (false, _) => hmac_sha512(self.chain_code.as_ref(), &[&self.public, &index_bytes]),
+        };
+        let result = self.derive_from_output(&output, depth, index);
+        output.zeroize();
+        result
+    }
+
+    fn derive_from_output(&self, output: &[u8; 64], depth: u8, index: u32) -> Result<Self, Error> {
+        let (tweak, chain_code) = output.split_at(32);
+        let fingerprint = self.fingerprint();
+        if self.curve == HdCurve::Ed25519 {
+            return Self::from_parts(self.curve, tweak, chain_code, depth, fingerprint, index);
+        }
+        let tweak = Option::<Scalar>::from(Scalar::from_repr(*FieldBytes::from_slice(tweak)))
+            .ok_or_else(|| err_msg!(InvalidKeyData, "Invalid derived key"))?;
+        if let Some(secret) = self.secret.as_ref() {
+            let parent =
+                SecretKey::from_slice(secret.as_ref()).map_err(|_| err_msg!(InvalidKeyData))?;
+            let mut child = (tweak + parent.to_nonzero_scalar().as_ref()).to_repr();
+            let result =
+                Self::from_parts(self.curve, &child, chain_code, depth, fingerprint, index);
+            child.as_mut_slice().zeroize();
+            result
+        } else {
+            let parent =
+                PublicKey::from_sec1_bytes(&self.public).map_err(|_| err_msg!(InvalidKeyData))?;
+            let child = ProjectivePoint::GENERATOR * tweak + parent.to_projective();
+            let child = PublicKey::from_affine(Curve::to_affine(&child))
+                .map_err(|_| err_msg!(InvalidKeyData, "Invalid derived key"))?;
+            let mut public = [0u8; 33];
+            public.copy_from_slice(child.to_encoded_point(true).as_bytes());
+            Ok(Self {
+                curve: self.curve,
+                secret: None,
+                public,
+                chain_code: ArrayKey::from_slice(chain_code),
+                depth,
+                parent_fingerprint: fingerprint,
+                child_number: index,
+            })
+        }
+    }
+
+    /// Derive a descendant key following a derivation path
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, Error> {
+        let mut key = self.clone();
+        for index in path.indices() {
+            key = key.derive_child(*index)?;
+        }
+        Ok(key)
+    }
+
+    /// Create the extended public key, removing the private key
+    pub fn to_public(&self) -> Self {
+        Self {
+            secret: None,
+            ..self.clone()
+        }
+    }
+
+    /// Access the private key bytes
+    pub fn secret_bytes(&self) -> Option<&[u8]> {
+        self.secret.as_ref().map(|sk| &sk[..])
+    }
+
+    /// Access the public key bytes, in compressed SEC1 format for secp256k1
+    pub fn public_bytes(&self) -> &[u8] {
+        match self.curve {
+            HdCurve::Secp256k1 => &self.public,
+            HdCurve::Ed25519 => &self.public[1..],
+        }
+    }
+
+    /// Convert the extended key to a secp256k1 keypair or public key
+    pub fn to_k256_keypair(&self) -> Result<K256KeyPair, Error> {
+        match (self.curve, self.secret.as_ref()) {
+            (HdCurve::Secp256k1, Some(secret)) => K256KeyPair::from_secret_bytes(secret.as_ref()),
+            (HdCurve::Secp256k1, None) => K256KeyPair::from_public_bytes(&self.public),
+            _ => Err(err_msg!(Unsupported, "Extended key is not a secp256k1 key")),
+        }
+    }
+
+    /// Convert the extended key to an Ed25519 keypair
+    pub fn to_ed25519_keypair(&self) -> Result<Ed25519KeyPair, Error> {
+        match (self.curve, self.secret.as_ref()) {
+            (HdCurve::Ed25519, Some(secret)) => Ed25519KeyPair::from_secret_bytes(secret.as_ref()),
+            (HdCurve::Ed25519, None) => Ed25519KeyPair::from_public_bytes(&self.public[1..]),
+            _ => Err(err_msg!(Unsupported, "Extended key is not an Ed25519 key")),
+        }
+    }
+
+    /// Serialize a secp256k1 extended key in the `xprv` or `xpub` format
+    pub fn to_extended_string(&self, private: bool) -> Result<String, Error> {
+        if self.curve != HdCurve::Secp256k1 {
+            return Err(err_msg!(
+                Unsupported,
+                "Extended key serialization is only defined for secp256k1"
+            ));
+        }
+        let mut data = [0u8; EXTENDED_KEY_LENGTH + 4];
+        data[4] = self.depth;
+        data[5..9].copy_from_slice(&self.parent_fingerprint);
+        data[9..13].copy_from_slice(&self.child_number.to_be_bytes());
+        data[13..45].copy_from_slice(self.chain_code.as_ref());
+        if private {
+            let secret = self
+                .secret
+                .as_ref()
+                .ok_or_else(|| err_msg!(MissingSecretKey))?;
+            data[..4].copy_from_slice(&XPRV_VERSION);
+            data[46..78].copy_from_slice(secret.as_ref());
+        } else {
+            data[..4].copy_from_slice(&XPUB_VERSION);
+            data[45..78].copy_from_slice(&self.public);
+        }
+        let check = Sha256::digest(Sha256::digest(&data[..EXTENDED_KEY_LENGTH]));
+        data[EXTENDED_KEY_LENGTH..].copy_from_slice(&check[..4]);
+        let result = bs58::encode(&data[..]).into_string();
+        data.zeroize();
+        Ok(result)
+    }
+
+    /// Parse a secp256k1 extended key in the `xprv` or `xpub` format
+    pub fn from_extended_string(encoded: &str) -> Result<Self, Error> {
+        let mut data = bs58::decode(encoded)
+            .into_vec()
+            .map_err(|_| err_msg!(InvalidKeyData, "Invalid extended key encoding"))?;
+        let result = Self::from_extended_bytes(&data);
+        data.zeroize();
+        result
+    }
+
+    fn from_extended_bytes(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != EXTENDED_KEY_LENGTH + 4 {
+            return Err(err_msg!(InvalidKeyData, "Invalid extended key length"));
+        }
+        let check = Sha256::digest(Sha256::digest(&data[..EXTENDED_KEY_LENGTH]));
+        if check[..4] != data[EXTENDED_KEY_LENGTH..] {
+            return Err(err_msg!(InvalidKeyData, "Invalid extended key checksum"));
+        }
+        let depth = data[4];
+        let mut parent_fingerprint = [0u8; 4];
+        parent_fingerprint.copy_from_slice(&data[5..9]);
+        let child_number = u32::from_be_bytes(data[9..13].try_into().unwrap());
+        let chain_code = &data[13..45];
+        if data[..4] == XPRV_VERSION {
+            if data[45] != 0 {
+                return Err(err_msg!(InvalidKeyData, "Invalid extended private key"));
+            }
+            Self::from_parts(
+                HdCurve::Secp256k1,
+                &data[46..78],
+                chain_code,
+                depth,
+                parent_fingerprint,
+                child_number,
+            )
+        } else if data[..4] == XPUB_VERSION {
+            PublicKey::from_sec1_bytes(&data[45..78])
+                .map_err(|_| err_msg!(InvalidKeyData, "Invalid extended public key"))?;
+            let mut public = [0u8; 33];
+            public.copy_from_slice(&data[45..78]);
+            Ok(Self {
+                curve: HdCurve::Secp256k1,
+                secret: None,
+                public,
+                chain_code: ArrayKey::from_slice(chain_code),
+                depth,
+                parent_fingerprint,
+                child_number,
+            })
+        } else {
+            Err(err_msg!(Unsupported, "Unsupported extended key version"))
+        }
+    }
+}
+
+impl Debug for ExtendedKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtendedKey")
+            .field("curve", &self.curve)
+            .field("secret", &self.secret)
+            .field("public", &self.public)
+            .field("depth", &self.depth)
+            .field("child_number", &self.child_number)
+            .finish()
+    }
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> [u8; 64] {
+    let mut mac = <Hmac<Sha512> as Mac>::new_from_slice(key).expect("HMAC accepts any key size");
+    for part in data {
+        mac.update(part);
+    }
+    let mut output = [0u8; 64];
+    output.copy_from_slice(&mac.finalize().into_bytes());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::ToPublicBytes;
+
+    const SEED: [u8; 16] = hex!("000102030405060708090a0b0c0d0e0f");
+
+    #[test]
+    fn parse_path() {
+        let path = DerivationPath::from_str("m/44'/0h/0'/0/1").unwrap();
+        assert_eq!(
+            path.indices(),
+            &[44 | HARDENED, HARDENED, HARDENED, 0, 1][..]
+        );
+        assert!(DerivationPath::from_str("m").unwrap().indices().is_empty());
+        assert!(DerivationPath::from_str("44'/0").is_err());
+        assert!(DerivationPath::from_str("m/2147483648").is_err());
+        assert!(DerivationPath::from_str("m/a").is_err());
+    }
+
+    #[test]
+    // from BIP-32 test vector 1
+    fn bip32_expected() {
+        let master = ExtendedKey::from_seed(HdCurve::Secp256k1, &SEED).unwrap();
+        assert_eq!(
+            master.to_extended_string(true).unwrap(),
+            "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi"
+        );
+        assert_eq!(
+            master.to_extended_string(false).unwrap(),
+            "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8"
+        );
+
+        let child = master
+            .derive_path(&DerivationPath::from_str("m/0'").unwrap())
+            .unwrap();
+        assert_eq!(
+            child.to_extended_string(true).unwrap(),
+            "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7"
+        );
+
+        let grandchild = child.derive_child(1).unwrap();
+        assert_eq!(
+            grandchild.to_extended_string(true).unwrap(),
+            "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs"
+        );
+        let xpub = "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ";
+        assert_eq!(grandchild.to_extended_string(false).unwrap(), xpub);
+
+        // public derivation matches private derivation
+        let public_child = child.to_public().derive_child(1).unwrap();
+        assert!(!public_child.is_private());
+        assert_eq!(public_child.to_extended_string(false).unwrap(), xpub);
+        assert!(child.to_public().derive_child(HARDENED).is_err());
+
+        // round trip
+        let parsed = ExtendedKey::from_extended_string(xpub).unwrap();
+        assert_eq!(parsed.public_bytes(), grandchild.public_bytes());
+        assert_eq!(parsed.to_extended_string(false).unwrap(), xpub);
+        assert!(ExtendedKey::from_extended_string(&xpub[..xpub.len() - 1]).is_err());
+    }
+
+    #[test]
+    // from SLIP-0010 test vector 1 for ed25519
+    fn slip10_expected() {
+        let master = ExtendedKey::from_seed(HdCurve::Ed25519, &SEED).unwrap();
+        assert_eq!(
+            master.secret_bytes().unwrap(),
+            &hex!("2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7")[..]
+        );
+        assert_eq!(
+            master.public_bytes(),
+            &hex!("a4b2856bfec510abab89753fac1ac0e1112364e7d250545963f135f2a33188ed")[..]
+        );
+
+        let child = master
+            .derive_path(&DerivationPath::from_str("m/0'/1'").unwrap())
+            .unwrap();
+        assert_eq!(child.depth(), 2);
+        assert_eq!(child.parent_fingerprint, hex!("13dab143"));
+        assert_eq!(
+            child.secret_bytes().unwrap(),
+            &hex!("b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2")[..]
+        );
+        assert_eq!(
+            child.public_bytes(),
+            &hex!("1932a5270f335bed617d5b935c80aedb1a35bd9fc1e31acafd5372c30f5c1187")[..]
+        );
+        assert_eq!(
+            child
+                .to_ed25519_keypair()
+                .unwrap()
+                .to_public_bytes()
+                .unwrap(),
+            child.public_bytes()
+        );
+        assert!(master.derive_child(0).is_err());
+        assert!(master.to_extended_string(true).is_err());
+    }
+}
@@ -0,0 +1,260 @@
+//! BIP-39 mnemonic phrases for deterministic seed generation
+
+use core::fmt::{self, Debug, Formatter};
+
+use sha2::{Digest, Sha256, Sha512};
+use zeroize::Zeroize;
+
+use crate::{
+    buffer::{ArrayKey, SecretBytes},
+    error::Error,
+    generic_array::typenum::U64,
+    random::KeyMaterial,
+};
+
+mod english;
+use self::english::WORDS;
+
+/// The number of PBKDF2 rounds used in seed derivation
+const SEED_ROUNDS: u32 = 2048;
+
+/// The maximum length of the entropy and checksum in bytes
+const MAX_DATA_LENGTH: usize = 33;
+
+/// A BIP-39 mnemonic, which may be converted to and from a phrase of
+/// 12, 15, 18, 21 or 24 words using the English wordlist
+#[derive(Clone)]
+pub struct Mnemonic {
+    entropy: SecretBytes,
+}
+
+impl Mnemonic {
+    /// Generate a new mnemonic with the given number of words using a
+    /// key material generator
+    pub fn generate(word_count: usize, mut rng: impl KeyMaterial) -> Result<Self, Error> {
+        let len = entropy_length(word_count)?;
+        let mut entropy = [0u8; 32];
+        rng.read_okm(&mut entropy[..len]);
+        let result = Self::from_entropy(&entropy[..len]);
+        entropy.zeroize();
+        result
+    }
+
+    /// Generate a new random mnemonic with the given number of words
+    #[cfg(feature = "default_rng")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "default_rng")))]
+    pub fn random(word_count: usize) -> Result<Self, Error> {
+        Self::generate(word_count, crate::random::try_default_rng()?)
+    }
+
+    /// Create a mnemonic from 16, 20, 24, 28 or 32 bytes of entropy
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, Error> {
+        if entropy.len() < 16 || entropy.len() > 32 || entropy.len() % 4 != 0 {
+            return Err(err_msg!(Usage, "Invalid mnemonic entropy length"));
+        }
+        Ok(Self {
+            entropy: SecretBytes::from_slice(entropy),
+        })
+    }
+
+    /// Parse and validate a mnemonic phrase, including the checksum
+    ///
+    /// Words must be lowercase and separated by whitespace.
+    pub fn from_phrase(phrase: &str) -> Result<Self, Error> {
+        let mut data = [0u8; MAX_DATA_LENGTH];
+        let mut word_count = 0;
+        for word in phrase.split_whitespace() {
+            if word_count == 24 {
+                data.zeroize();
+                return Err(err_msg!(Invalid, "Invalid mnemonic length"));
+            }
+            let Ok(index) = WORDS.binary_search(&word) else {
+                data.zeroize();
+                return Err(err_msg!(Invalid, "Unknown mnemonic word"));
+            };
+            write_index(&mut data, word_count * 11, index);
+            word_count += 1;
+        }
+        let len = match entropy_length(word_count) {
+            Ok(len) => len,
+            Err(_) => {
+                data.zeroize();
+                return Err(err_msg!(Invalid, "Invalid mnemonic length"));
+            }
+        };
+        let check_bits = 8 - word_count / 3;
+        let valid = data[len] >> check_bits == checksum(&data[..len]) >> check_bits;
+        let result = if valid {
+            Self::from_entropy(&data[..len])
+        } else {
+            Err(err_msg!(Invalid, "Invalid mnemonic checksum"))
+        };
+        data.zeroize();
+        result
+    }
+
+    /// Access the entropy of the mnemonic
+    pub fn entropy(&self) -> &[u8] {
+        self.entropy.as_ref()
+    }
+
+    /// Get the number of words in the mnemonic phrase
+    pub fn word_count(&self) -> usize {
+        self.entropy.len() * 3 / 4
+    }
+
+    /// Encode the mnemonic as a phrase of space-separated words
+    pub fn to_phrase(&self) -> SecretBytes {
+        let len = self.entropy.len();
+        let mut data = [0u8; MAX_DATA_LENGTH];
+        data[..len].copy_from_slice(self.entropy.as_ref());
+        data[len] = checksum(self.entropy.as_ref());
+        let word_count = self.word_count();
+        let mut phrase = SecretBytes::with_capacity(word_count * 9);
+        for pos in 0..word_count {
+            if pos > 0 {
+                phrase.extend_from_slice(b" ");
+            }
+            phrase.extend_from_slice(WORDS[read_index(&data, pos * 11)].as_bytes());
+        }
+        data.zeroize();
+        phrase
+    }
+
+    /// Derive the 64-byte seed for the mnemonic and an optional passphrase
+    ///
+    /// The passphrase is expected to be in Unicode NFKD form.
+    pub fn to_seed(&self, passphrase: &str) -> ArrayKey<U64> {
+        let phrase = self.to_phrase();
+        let mut salt = SecretBytes::with_capacity(8 + passphrase.len());
+        salt.extend_from_slice(b"mnemonic");
+        salt.extend_from_slice(passphrase.as_bytes());
+        ArrayKey::new_with(|seed| {
+            pbkdf2::pbkdf2_hmac::<Sha512>(phrase.as_ref(), salt.as_ref(), SEED_ROUNDS, seed)
+        })
+    }
+}
+
+impl Debug for Mnemonic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mnemonic")
+            .field("word_count", &self.word_count())
+            .finish()
+    }
+}
+
+impl PartialEq for Mnemonic {
+    fn eq(&self, other: &Self) -> bool {
+        self.entropy == other.entropy
+    }
+}
+
+impl Eq for Mnemonic {}
+
+fn entropy_length(word_count: usize) -> Result<usize, Error> {
+    match word_count {
+        12 | 15 | 18 | 21 | 24 => Ok(word_count * 4 / 3),
+        _ => Err(err_msg!(Usage, "Unsupported mnemonic length")),
+    }
+}
+
+fn checksum(entropy: &[u8]) -> u8 {
+    Sha256::digest(entropy)[0]
+}
+
+fn read_index(data: &[u8], offset: usize) -> usize {
+    (offset..offset + 11).fold(0, |index, bit| {
+        (index << 1) | ((data[bit / 8] >> (7 - bit % 8)) & 1) as usize
+    })
+}
+
+fn write_index(data: &mut [u8], offset: usize, index: usize) {
+    for (pos, bit) in (offset..offset + 11).enumerate() {
+        if index & (1 << (10 - pos)) != 0 {
+            data[bit / 8] |= 1 << (7 - bit % 8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::RandomDet;
+
+    #[test]
+    // from the BIP-39 reference test vectors
+    fn phrase_expected() {
+        for (entropy, phrase) in [
+            (
+                &[0u8; 16][..],
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                abandon abandon about",
+            ),
+            (
+                &[0x7fu8; 16][..],
+                "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            ),
+            (
+                &[0x80u8; 16][..],
+                "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+            ),
+            (
+                &[0xffu8; 16][..],
+                "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+            ),
+            (
+                &[0u8; 32][..],
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                abandon abandon abandon abandon abandon art",
+            ),
+        ] {
+            let mnemonic = Mnemonic::from_entropy(entropy).unwrap();
+            assert_eq!(mnemonic.to_phrase().as_opt_str(), Some(phrase));
+            let parsed = Mnemonic::from_phrase(phrase).unwrap();
+            assert_eq!(parsed.entropy(), entropy);
+        }
+    }
+
+    #[test]
+    // from the BIP-39 reference test vectors
+    fn seed_expected() {
+        let mnemonic = Mnemonic::from_entropy(&[0u8; 16]).unwrap();
+        assert_eq!(
+            &mnemonic.to_seed("TREZOR")[..],
+            &hex!(
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f
+                09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+            )[..]
+        );
+        assert_ne!(mnemonic.to_seed(""), mnemonic.to_seed("TREZOR"));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        // invalid checksum
+        assert!(Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+            abandon abandon abandon"
+        )
+        .is_err());
+        // unknown word
+        assert!(Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+            abandon abandon aboutt"
+        )
+        .is_err());
+        // invalid length
+        assert!(Mnemonic::from_phrase("abandon abandon about").is_err());
+    }
+
+    #[test]
+    fn generate_round_trip() {
+        let mnemonic = Mnemonic::generate(24, RandomDet::new(b"seed")).unwrap();
+        assert_eq!(mnemonic.word_count(), 24);
+        let phrase = mnemonic.to_phrase();
+        let parsed = Mnemonic::from_phrase(phrase.as_opt_str().unwrap()).unwrap();
+        assert_eq!(parsed, mnemonic);
+        assert!(Mnemonic::generate(13, RandomDet::new(b"seed")).is_err());
+    }
+}
@@ -78,7 +78,7 @@ impl<L: ArrayLength<u8>> ArrayKey<L> {
     }
 
     /// Create a new array of random bytes
-    #[cfg(feature = "getrandom")]
+    #[cfg(feature = "default_rng")]
     #[inline]
     pub fn random() -> Self {
         Self::generate(crate::random::default_rng())
@@ -12,6 +12,9 @@ pub use self::array::ArrayKey;
 mod hash;
 pub use self::hash::HashBuffer;
 
+#[cfg(feature = "mlock")]
+pub(crate) mod protect;
+
 #[cfg(feature = "alloc")]
 mod secret;
 #[cfg(feature = "alloc")]
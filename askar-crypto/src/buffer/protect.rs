@@ -0,0 +1,167 @@
+//! Best-effort locking of memory pages containing secret data
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// Reference counts for locked pages, as page locks do not nest
+static LOCKED_PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+pub(crate) fn page_size() -> usize {
+    static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+    let mut size = PAGE_SIZE.load(Ordering::Relaxed);
+    if size == 0 {
+        size = sys::page_size();
+        PAGE_SIZE.store(size, Ordering::Relaxed);
+    }
+    size
+}
+
+fn page_range(ptr: *const u8, len: usize) -> impl Iterator<Item = usize> {
+    let page = page_size();
+    let start = ptr as usize & !(page - 1);
+    let end = (ptr as usize).saturating_add(len);
+    (start..end).step_by(page)
+}
+
+/// Lock the pages containing a memory region into RAM and exclude them
+/// from core dumps.
+///
+/// Failures, such as exceeding the locked memory limit, are ignored.
+pub(crate) fn protect_region(ptr: *const u8, len: usize) {
+    if len == 0 {
+        return;
+    }
+    let page = page_size();
+    let mut pages = LOCKED_PAGES.lock().unwrap_or_else(|e| e.into_inner());
+    for addr in page_range(ptr, len) {
+        let count = pages.entry(addr).or_insert(0);
+        if *count == 0 {
+            sys::lock_page(addr, page);
+        }
+        *count += 1;
+    }
+}
+
+/// Release the protection on a memory region previously passed to
+/// `protect_region`.
+pub(crate) fn unprotect_region(ptr: *const u8, len: usize) {
+    if len == 0 {
+        return;
+    }
+    let page = page_size();
+    let mut pages = LOCKED_PAGES.lock().unwrap_or_else(|e| e.into_inner());
+    for addr in page_range(ptr, len) {
+        if let Some(count) = pages.get_mut(&addr) {
+            *count -= 1;
+            if *count == 0 {
+                pages.remove(&addr);
+                sys::unlock_page(addr, page);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use libc::c_void;
+
+    pub fn page_size() -> usize {
+        match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+            size if size > 0 => size as usize,
+            _ => 4096,
+        }
+    }
+
+    pub fn lock_page(addr: usize, len: usize) {
+        unsafe {
+            libc::mlock(addr as *const c_void, len);
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            libc::madvise(addr as *mut c_void, len, libc::MADV_DONTDUMP);
+            #[cfg(target_os = "freebsd")]
+            libc::madvise(addr as *mut c_void, len, libc::MADV_NOCORE);
+        }
+    }
+
+    pub fn unlock_page(addr: usize, len: usize) {
+        unsafe {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            libc::madvise(addr as *mut c_void, len, libc::MADV_DODUMP);
+            #[cfg(target_os = "freebsd")]
+            libc::madvise(addr as *mut c_void, len, libc::MADV_CORE);
+            libc::munlock(addr as *const c_void, len);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use core::{ffi::c_void, mem::MaybeUninit};
+    use windows_sys::Win32::System::{
+        Memory::{VirtualLock, VirtualUnlock},
+        SystemInformation::GetSystemInfo,
+    };
+
+    pub fn page_size() -> usize {
+        let mut info = MaybeUninit::uninit();
+        unsafe {
+            GetSystemInfo(info.as_mut_ptr());
+            info.assume_init().dwPageSize as usize
+        }
+    }
+
+    pub fn lock_page(addr: usize, len: usize) {
+        unsafe {
+            VirtualLock(addr as *const c_void, len);
+        }
+    }
+
+    pub fn unlock_page(addr: usize, len: usize) {
+        unsafe {
+            VirtualUnlock(addr as *const c_void, len);
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    pub fn page_size() -> usize {
+        4096
+    }
+
+    pub fn lock_page(_addr: usize, _len: usize) {}
+
+    pub fn unlock_page(_addr: usize, _len: usize) {}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn page_start(ptr: *const u8) -> usize {
+        page_range(ptr, 1).next().unwrap()
+    }
+
+    pub(crate) fn lock_count(addr: usize) -> usize {
+        LOCKED_PAGES
+            .lock()
+            .unwrap()
+            .get(&addr)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn protect_nested() {
+        let page = page_size();
+        // allocate enough to avoid sharing pages with other allocations
+        let buf = vec![0u8; page * 64];
+        let mid = page_start(buf[page * 32..].as_ptr());
+        protect_region(buf.as_ptr(), buf.len());
+        protect_region(buf[page * 32..].as_ptr(), page);
+        assert_eq!(lock_count(mid), 2);
+        unprotect_region(buf[page * 32..].as_ptr(), page);
+        assert_eq!(lock_count(mid), 1);
+        unprotect_region(buf.as_ptr(), buf.len());
+        assert_eq!(lock_count(mid), 0);
+    }
+}
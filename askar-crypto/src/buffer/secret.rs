@@ -13,10 +13,27 @@ use super::{string::MaybeStr, HexRepr, ResizeBuffer, WriteBuffer};
 use crate::error::Error;
 
 /// A heap-allocated, zeroized byte buffer
-#[derive(Clone, Default, Zeroize)]
+///
+/// With the `mlock` feature, the pages holding the buffer are locked into
+/// memory and excluded from core dumps where supported.
+#[derive(Default, Zeroize)]
 pub struct SecretBytes(Vec<u8>);
 
 impl SecretBytes {
+    #[inline]
+    fn from_vec(inner: Vec<u8>) -> Self {
+        let slf = Self(inner);
+        #[cfg(feature = "mlock")]
+        super::protect::protect_region(slf.0.as_ptr(), slf.0.capacity());
+        slf
+    }
+
+    #[inline]
+    fn release(&mut self) {
+        #[cfg(feature = "mlock")]
+        super::protect::unprotect_region(self.0.as_ptr(), self.0.capacity());
+    }
+
     /// Create a new buffer using an initializer for the data
     pub fn new_with(len: usize, f: impl FnOnce(&mut [u8])) -> Self {
         let mut slf = Self::with_capacity(len);
@@ -28,23 +45,23 @@ impl SecretBytes {
     /// Create a new, empty buffer with an initial capacity
     #[inline]
     pub fn with_capacity(max_len: usize) -> Self {
-        Self(Vec::with_capacity(max_len))
+        Self::from_vec(Vec::with_capacity(max_len))
     }
 
     /// Create a new buffer from a slice
     #[inline]
     pub fn from_slice(data: &[u8]) -> Self {
-        let mut v = Vec::with_capacity(data.len());
-        v.extend_from_slice(data);
-        Self(v)
+        let mut slf = Self::with_capacity(data.len());
+        slf.0.extend_from_slice(data);
+        slf
     }
 
     /// Create a new buffer from a slice, with extra space reserved
     #[inline]
     pub fn from_slice_reserve(data: &[u8], reserve: usize) -> Self {
-        let mut v = Vec::with_capacity(data.len() + reserve);
-        v.extend_from_slice(data);
-        Self(v)
+        let mut slf = Self::with_capacity(data.len() + reserve);
+        slf.0.extend_from_slice(data);
+        slf
     }
 
     /// Accessor for the current capacity of the buffer
@@ -74,8 +91,11 @@ impl SecretBytes {
     pub fn ensure_capacity(&mut self, min_cap: usize) {
         let cap = self.0.capacity();
         if cap == 0 {
-            self.0.reserve(min_cap);
-        } else if cap > 0 && min_cap >= cap {
+            if min_cap > 0 {
+                // no secure data to copy, but the new allocation must be protected
+                *self = Self::with_capacity(min_cap);
+            }
+        } else if min_cap >= cap {
             // allocate a new buffer and copy the secure data over
             let new_cap = min_cap.max(cap * 2).max(32);
             let mut buf = Self::with_capacity(new_cap);
@@ -128,6 +148,7 @@ impl SecretBytes {
     #[inline]
     pub fn into_vec(mut self) -> Vec<u8> {
         // FIXME zeroize extra capacity in case it was used previously?
+        self.release();
         let mut v = Vec::new(); // note: no heap allocation for empty vec
         mem::swap(&mut v, &mut self.0);
         v
@@ -141,7 +162,7 @@ impl SecretBytes {
     pub(crate) fn splice(
         &mut self,
         range: Range<usize>,
-        iter: impl Iterator<Item = u8> + ExactSizeIterator,
+        iter: impl ExactSizeIterator<Item = u8>,
     ) -> Result<(), Error> {
         assert!(range.end >= range.start);
         let rem_len = range.len();
@@ -159,6 +180,12 @@ impl SecretBytes {
     }
 }
 
+impl Clone for SecretBytes {
+    fn clone(&self) -> Self {
+        Self::from_slice(&self.0)
+    }
+}
+
 impl Debug for SecretBytes {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         if cfg!(test) {
@@ -194,6 +221,7 @@ impl Deref for SecretBytes {
 impl Drop for SecretBytes {
     fn drop(&mut self) {
         self.zeroize();
+        self.release();
     }
 }
 
@@ -219,31 +247,31 @@ impl hash::Hash for SecretBytes {
 
 impl From<&[u8]> for SecretBytes {
     fn from(inner: &[u8]) -> Self {
-        Self(inner.to_vec())
+        Self::from_slice(inner)
     }
 }
 
 impl From<&str> for SecretBytes {
     fn from(inner: &str) -> Self {
-        Self(inner.as_bytes().to_vec())
+        Self::from_slice(inner.as_bytes())
     }
 }
 
 impl From<String> for SecretBytes {
     fn from(inner: String) -> Self {
-        Self(inner.into_bytes())
+        Self::from_vec(inner.into_bytes())
     }
 }
 
 impl From<Box<[u8]>> for SecretBytes {
     fn from(inner: Box<[u8]>) -> Self {
-        Self(inner.into())
+        Self::from_vec(inner.into())
     }
 }
 
 impl From<Vec<u8>> for SecretBytes {
     fn from(inner: Vec<u8>) -> Self {
-        Self(inner)
+        Self::from_vec(inner)
     }
 }
 
@@ -332,4 +360,19 @@ mod tests {
     fn resize_buffer_secret() {
         test_resize_buffer(SecretBytes::with_capacity(10));
     }
+
+    #[cfg(feature = "mlock")]
+    #[test]
+    fn grow_empty_protected() {
+        use super::super::protect::tests::{lock_count, page_start};
+
+        let page = super::super::protect::page_size();
+        let mut buf = SecretBytes::default();
+        // allocate enough to avoid sharing pages with other allocations
+        buf.extend_from_slice(&vec![1u8; page * 64]);
+        let mid = page_start(buf[page * 32..].as_ptr());
+        assert_eq!(lock_count(mid), 1);
+        drop(buf);
+        assert_eq!(lock_count(mid), 0);
+    }
 }
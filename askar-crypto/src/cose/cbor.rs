@@ -0,0 +1,281 @@
+//! Minimal CBOR (RFC 8949) encoding and decoding used by COSE structures
+
+use crate::{buffer::WriteBuffer, error::Error};
+
+pub(crate) const MAJOR_UINT: u8 = 0;
+pub(crate) const MAJOR_NEGINT: u8 = 1;
+pub(crate) const MAJOR_BYTES: u8 = 2;
+pub(crate) const MAJOR_TEXT: u8 = 3;
+pub(crate) const MAJOR_ARRAY: u8 = 4;
+pub(crate) const MAJOR_MAP: u8 = 5;
+pub(crate) const MAJOR_TAG: u8 = 6;
+pub(crate) const MAJOR_SIMPLE: u8 = 7;
+
+pub(crate) const SIMPLE_NULL: u64 = 22;
+
+const MAX_DEPTH: usize = 16;
+
+/// Write CBOR data items to a buffer
+#[derive(Debug)]
+pub(crate) struct CborEncoder<'e> {
+    out: &'e mut dyn WriteBuffer,
+}
+
+impl<'e> CborEncoder<'e> {
+    pub fn new(out: &'e mut dyn WriteBuffer) -> Self {
+        Self { out }
+    }
+
+    /// Write the initial bytes of a data item using the shortest encoding
+    pub fn head(&mut self, major: u8, arg: u64) -> Result<(), Error> {
+        let mt = major << 5;
+        if arg < 24 {
+            self.out.buffer_write(&[mt | arg as u8])
+        } else if arg <= u8::MAX as u64 {
+            self.out.buffer_write(&[mt | 24, arg as u8])
+        } else if arg <= u16::MAX as u64 {
+            self.out.buffer_write(&[mt | 25])?;
+            self.out.buffer_write(&(arg as u16).to_be_bytes())
+        } else if arg <= u32::MAX as u64 {
+            self.out.buffer_write(&[mt | 26])?;
+            self.out.buffer_write(&(arg as u32).to_be_bytes())
+        } else {
+            self.out.buffer_write(&[mt | 27])?;
+            self.out.buffer_write(&arg.to_be_bytes())
+        }
+    }
+
+    pub fn int(&mut self, value: i64) -> Result<(), Error> {
+        if value < 0 {
+            // encodes -1 - value, equivalent to the bitwise complement
+            self.head(MAJOR_NEGINT, !value as u64)
+        } else {
+            self.head(MAJOR_UINT, value as u64)
+        }
+    }
+
+    pub fn bytes(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.head(MAJOR_BYTES, data.len() as u64)?;
+        self.out.buffer_write(data)
+    }
+
+    pub fn text(&mut self, data: &str) -> Result<(), Error> {
+        self.head(MAJOR_TEXT, data.len() as u64)?;
+        self.out.buffer_write(data.as_bytes())
+    }
+
+    pub fn array(&mut self, len: usize) -> Result<(), Error> {
+        self.head(MAJOR_ARRAY, len as u64)
+    }
+
+    pub fn map(&mut self, len: usize) -> Result<(), Error> {
+        self.head(MAJOR_MAP, len as u64)
+    }
+
+    pub fn tag(&mut self, tag: u64) -> Result<(), Error> {
+        self.head(MAJOR_TAG, tag)
+    }
+
+    pub fn null(&mut self) -> Result<(), Error> {
+        self.head(MAJOR_SIMPLE, SIMPLE_NULL)
+    }
+}
+
+/// Read CBOR data items from a byte slice
+#[derive(Debug)]
+pub(crate) struct CborDecoder<'d> {
+    data: &'d [u8],
+    pos: usize,
+}
+
+impl<'d> CborDecoder<'d> {
+    pub fn new(data: &'d [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn read_slice(&mut self, len: u64) -> Result<&'d [u8], Error> {
+        let remain = self.data.len() - self.pos;
+        if len > remain as u64 {
+            return Err(invalid());
+        }
+        let start = self.pos;
+        self.pos += len as usize;
+        Ok(&self.data[start..self.pos])
+    }
+
+    /// Read the major type and argument of the next data item
+    pub fn read_head(&mut self) -> Result<(u8, u64), Error> {
+        let ib = self.read_slice(1)?[0];
+        let (major, info) = (ib >> 5, ib & 0x1f);
+        let arg = match info {
+            0..=23 => info as u64,
+            24..=27 => {
+                let len = 1 << (info - 24);
+                let mut buf = [0u8; 8];
+                buf[8 - len..].copy_from_slice(self.read_slice(len as u64)?);
+                u64::from_be_bytes(buf)
+            }
+            // indefinite-length items are not permitted
+            _ => return Err(invalid()),
+        };
+        Ok((major, arg))
+    }
+
+    /// Get the major type of the next data item without consuming it
+    pub fn peek_major(&self) -> Option<u8> {
+        self.data.get(self.pos).map(|ib| ib >> 5)
+    }
+
+    /// Check if the next data item is the simple value `null`
+    pub fn peek_null(&self) -> bool {
+        self.data.get(self.pos) == Some(&0xf6)
+    }
+
+    fn read_len(&mut self, major: u8) -> Result<usize, Error> {
+        let (mt, len) = self.read_head()?;
+        // every contained item occupies at least one byte
+        if mt != major || len > (self.data.len() - self.pos) as u64 {
+            return Err(invalid());
+        }
+        Ok(len as usize)
+    }
+
+    pub fn read_int(&mut self) -> Result<i64, Error> {
+        match self.read_head()? {
+            (MAJOR_UINT, arg) if arg <= i64::MAX as u64 => Ok(arg as i64),
+            (MAJOR_NEGINT, arg) if arg <= i64::MAX as u64 => Ok(!(arg as i64)),
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn read_bytes(&mut self) -> Result<&'d [u8], Error> {
+        let len = self.read_len(MAJOR_BYTES)?;
+        self.read_slice(len as u64)
+    }
+
+    pub fn read_text(&mut self) -> Result<&'d str, Error> {
+        let len = self.read_len(MAJOR_TEXT)?;
+        core::str::from_utf8(self.read_slice(len as u64)?).map_err(|_| invalid())
+    }
+
+    pub fn read_array(&mut self) -> Result<usize, Error> {
+        self.read_len(MAJOR_ARRAY)
+    }
+
+    pub fn read_map(&mut self) -> Result<usize, Error> {
+        self.read_len(MAJOR_MAP)
+    }
+
+    /// Read either a byte string or `null`
+    pub fn read_bytes_or_null(&mut self) -> Result<Option<&'d [u8]>, Error> {
+        if self.peek_null() {
+            self.pos += 1;
+            Ok(None)
+        } else {
+            self.read_bytes().map(Some)
+        }
+    }
+
+    /// Consume an optional leading tag, which must match if present
+    pub fn read_opt_tag(&mut self, tag: u64) -> Result<(), Error> {
+        if self.peek_major() == Some(MAJOR_TAG) {
+            let (_, found) = self.read_head()?;
+            if found != tag {
+                return Err(err_msg!(Invalid, "Unexpected CBOR tag"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Skip over the next data item, including any nested items
+    pub fn skip(&mut self) -> Result<(), Error> {
+        self.skip_depth(0)
+    }
+
+    fn skip_depth(&mut self, depth: usize) -> Result<(), Error> {
+        if depth > MAX_DEPTH {
+            return Err(err_msg!(Invalid, "Exceeded maximum CBOR nesting depth"));
+        }
+        match self.read_head()? {
+            (MAJOR_BYTES | MAJOR_TEXT, len) => {
+                self.read_slice(len)?;
+            }
+            (MAJOR_ARRAY, len) => {
+                for _ in 0..len {
+                    self.skip_depth(depth + 1)?;
+                }
+            }
+            (MAJOR_MAP, len) => {
+                for _ in 0..len.saturating_mul(2) {
+                    self.skip_depth(depth + 1)?;
+                }
+            }
+            (MAJOR_TAG, _) => self.skip_depth(depth + 1)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Ensure that all input has been consumed
+    pub fn finish(self) -> Result<(), Error> {
+        if self.pos == self.data.len() {
+            Ok(())
+        } else {
+            Err(err_msg!(Invalid, "Unexpected trailing CBOR data"))
+        }
+    }
+}
+
+#[inline]
+fn invalid() -> Error {
+    err_msg!(Invalid, "Invalid CBOR encoding")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn int_round_trip() {
+        for (value, expect) in [
+            (0i64, &hex!("00")[..]),
+            (23, &hex!("17")),
+            (24, &hex!("1818")),
+            (1000, &hex!("1903e8")),
+            (-1, &hex!("20")),
+            (-257, &hex!("390100")),
+            (i64::MIN, &hex!("3b7fffffffffffffff")),
+        ] {
+            let mut buf = Vec::new();
+            CborEncoder::new(&mut buf).int(value).unwrap();
+            assert_eq!(buf, expect);
+            let mut dec = CborDecoder::new(&buf);
+            assert_eq!(dec.read_int().unwrap(), value);
+            dec.finish().unwrap();
+        }
+    }
+
+    #[test]
+    fn skip_nested() {
+        // [1, {"a": h'01', 2: [true, null]}, 3]
+        let data = hex!("8301a2616141010282f5f603");
+        let mut dec = CborDecoder::new(&data);
+        assert_eq!(dec.read_array().unwrap(), 3);
+        assert_eq!(dec.read_int().unwrap(), 1);
+        dec.skip().unwrap();
+        assert_eq!(dec.read_int().unwrap(), 3);
+        dec.finish().unwrap();
+    }
+
+    #[test]
+    fn reject_invalid() {
+        // indefinite-length byte string
+        assert!(CborDecoder::new(&hex!("5f41ff")).read_bytes().is_err());
+        // truncated byte string
+        assert!(CborDecoder::new(&hex!("4301")).read_bytes().is_err());
+        // array length exceeding input
+        assert!(CborDecoder::new(&hex!("9a00010000")).read_array().is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! CBOR Web Token (RFC 8392) issuance and validation
+
+use alloc::{string::String, vec::Vec};
+use core::time::Duration;
+
+use super::{
+    cbor::{CborDecoder, CborEncoder, MAJOR_NEGINT, MAJOR_UINT},
+    CoseAlg, CoseSign1,
+};
+use crate::{
+    error::Error,
+    sign::{KeySigVerify, KeySign},
+};
+
+const CLAIM_ISS: i64 = 1;
+const CLAIM_SUB: i64 = 2;
+const CLAIM_AUD: i64 = 3;
+const CLAIM_EXP: i64 = 4;
+const CLAIM_NBF: i64 = 5;
+const CLAIM_IAT: i64 = 6;
+const CLAIM_CTI: i64 = 7;
+
+// the encoding of CBOR tag 61, which may optionally wrap a CWT
+const TAG_CWT: [u8; 2] = [0xd8, 0x3d];
+
+/// The registered claims of a CWT
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CwtClaims {
+    /// Issuer
+    pub iss: Option<String>,
+    /// Subject
+    pub sub: Option<String>,
+    /// Audience
+    pub aud: Option<String>,
+    /// Expiration time, in seconds since the Unix epoch
+    pub exp: Option<i64>,
+    /// Not-before time, in seconds since the Unix epoch
+    pub nbf: Option<i64>,
+    /// Issued-at time, in seconds since the Unix epoch
+    pub iat: Option<i64>,
+    /// Token identifier
+    pub cti: Option<Vec<u8>>,
+}
+
+impl CwtClaims {
+    /// Encode the claims as a CBOR map
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let count = [
+            self.iss.is_some(),
+            self.sub.is_some(),
+            self.aud.is_some(),
+            self.exp.is_some(),
+            self.nbf.is_some(),
+            self.iat.is_some(),
+            self.cti.is_some(),
+        ]
+        .into_iter()
+        .filter(|c| *c)
+        .count();
+        let mut buf = Vec::with_capacity(64);
+        let mut enc = CborEncoder::new(&mut buf);
+        enc.map(count)?;
+        for (label, value) in [
+            (CLAIM_ISS, &self.iss),
+            (CLAIM_SUB, &self.sub),
+            (CLAIM_AUD, &self.aud),
+        ] {
+            if let Some(value) = value {
+                enc.int(label)?;
+                enc.text(value)?;
+            }
+        }
+        for (label, value) in [
+            (CLAIM_EXP, self.exp),
+            (CLAIM_NBF, self.nbf),
+            (CLAIM_IAT, self.iat),
+        ] {
+            if let Some(value) = value {
+                enc.int(label)?;
+                enc.int(value)?;
+            }
+        }
+        if let Some(cti) = self.cti.as_deref() {
+            enc.int(CLAIM_CTI)?;
+            enc.bytes(cti)?;
+        }
+        Ok(buf)
+    }
+
+    /// Parse a CBOR-encoded claims set, ignoring unrecognized claims
+    pub fn from_bytes(claims: &[u8]) -> Result<Self, Error> {
+        let mut dec = CborDecoder::new(claims);
+        let mut result = Self::default();
+        let count = dec.read_map()?;
+        for _ in 0..count {
+            if !matches!(dec.peek_major(), Some(MAJOR_UINT | MAJOR_NEGINT)) {
+                dec.skip()?;
+                dec.skip()?;
+                continue;
+            }
+            let dup = match dec.read_int()? {
+                CLAIM_ISS => result.iss.replace(dec.read_text()?.into()).is_some(),
+                CLAIM_SUB => result.sub.replace(dec.read_text()?.into()).is_some(),
+                CLAIM_AUD => result.aud.replace(dec.read_text()?.into()).is_some(),
+                CLAIM_EXP => result.exp.replace(dec.read_int()?).is_some(),
+                CLAIM_NBF => result.nbf.replace(dec.read_int()?).is_some(),
+                CLAIM_IAT => result.iat.replace(dec.read_int()?).is_some(),
+                CLAIM_CTI => result.cti.replace(dec.read_bytes()?.to_vec()).is_some(),
+                _ => {
+                    dec.skip()?;
+                    false
+                }
+            };
+            if dup {
+                return Err(err_msg!(Invalid, "Duplicate CWT claim"));
+            }
+        }
+        dec.finish()?;
+        Ok(result)
+    }
+
+    /// Sign the claims, producing a CWT as a tagged COSE_Sign1 message
+    pub fn sign(
+        &self,
+        key: &(impl KeySign + ?Sized),
+        alg: CoseAlg,
+        kid: Option<&[u8]>,
+    ) -> Result<Vec<u8>, Error> {
+        CoseSign1::sign(key, alg, kid, &self.to_bytes()?, &[])?.to_bytes()
+    }
+}
+
+/// Parse the COSE_Sign1 message of a CWT without verifying it
+///
+/// This may be used to look up the verification key using the `kid` header.
+pub fn decode_cwt(token: &[u8]) -> Result<CoseSign1, Error> {
+    CoseSign1::from_bytes(token.strip_prefix(&TAG_CWT[..]).unwrap_or(token))
+}
+
+/// A validator for the signature and claims of a CWT
+#[derive(Clone, Debug)]
+pub struct CwtValidator<'v> {
+    now: i64,
+    issuer: Option<&'v str>,
+    audience: Option<&'v str>,
+    clock_skew: Duration,
+    require_exp: bool,
+}
+
+impl<'v> CwtValidator<'v> {
+    /// Create a new validator using the current time in seconds since the Unix epoch
+    pub fn new(now: u64) -> Self {
+        Self {
+            now: i64::try_from(now).unwrap_or(i64::MAX),
+            issuer: None,
+            audience: None,
+            clock_skew: Duration::ZERO,
+            require_exp: false,
+        }
+    }
+
+    /// Require the `iss` claim to match
+    pub fn issuer(mut self, issuer: &'v str) -> Self {
+        self.issuer = Some(issuer);
+        self
+    }
+
+    /// Require the `aud` claim to match
+    pub fn audience(mut self, audience: &'v str) -> Self {
+        self.audience = Some(audience);
+        self
+    }
+
+    /// Set the allowed clock skew when checking the `exp` and `nbf` claims
+    pub fn clock_skew(mut self, skew: Duration) -> Self {
+        self.clock_skew = skew;
+        self
+    }
+
+    /// Require the `exp` claim to be present
+    pub fn require_exp(mut self) -> Self {
+        self.require_exp = true;
+        self
+    }
+
+    /// Verify the signature of a CWT and validate its claims
+    pub fn verify(
+        &self,
+        token: &[u8],
+        key: &(impl KeySigVerify + ?Sized),
+    ) -> Result<CwtClaims, Error> {
+        self.verify_message(&decode_cwt(token)?, key)
+    }
+
+    /// Verify the signature of a decoded CWT and validate its claims
+    pub fn verify_message(
+        &self,
+        message: &CoseSign1,
+        key: &(impl KeySigVerify + ?Sized),
+    ) -> Result<CwtClaims, Error> {
+        if !message.verify(key, &[])? {
+            return Err(err_msg!(Invalid, "Invalid CWT signature"));
+        }
+        let claims = CwtClaims::from_bytes(message.payload().unwrap_or_default())?;
+        self.validate(&claims)?;
+        Ok(claims)
+    }
+
+    /// Validate a set of claims
+    pub fn validate(&self, claims: &CwtClaims) -> Result<(), Error> {
+        let skew = i64::try_from(self.clock_skew.as_secs()).unwrap_or(i64::MAX);
+        match claims.exp {
+            Some(exp) if self.now >= exp.saturating_add(skew) => {
+                return Err(err_msg!(Invalid, "CWT has expired"))
+            }
+            None if self.require_exp => return Err(err_msg!(Invalid, "Missing CWT claim: exp")),
+            _ => (),
+        }
+        if let Some(nbf) = claims.nbf {
+            if self.now.saturating_add(skew) < nbf {
+                return Err(err_msg!(Invalid, "CWT is not yet valid"));
+            }
+        }
+        if let Some(issuer) = self.issuer {
+            if claims.iss.as_deref() != Some(issuer) {
+                return Err(err_msg!(Invalid, "CWT issuer does not match"));
+            }
+        }
+        if let Some(audience) = self.audience {
+            if claims.aud.as_deref() != Some(audience) {
+                return Err(err_msg!(Invalid, "CWT audience does not match"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alg::ed25519::Ed25519KeyPair, repr::KeyGen};
+
+    fn claims() -> CwtClaims {
+        CwtClaims {
+            iss: Some("coap://as.example.com".into()),
+            sub: Some("erikw".into()),
+            aud: Some("coap://light.example.com".into()),
+            exp: Some(1444064944),
+            nbf: Some(1443944944),
+            iat: Some(1443944944),
+            cti: Some(vec![0x0b, 0x71]),
+        }
+    }
+
+    #[test]
+    fn claims_round_trip() {
+        // RFC 8392 appendix A.1
+        let expected = hex!(
+            "a70175636f61703a2f2f61732e6578616d706c652e636f6d02656572696b7703"
+            "7818636f61703a2f2f6c696768742e6578616d706c652e636f6d041a5612aeb0"
+            "051a5610d9f0061a5610d9f007420b71"
+        );
+        assert_eq!(claims().to_bytes().unwrap(), expected);
+        assert_eq!(CwtClaims::from_bytes(&expected).unwrap(), claims());
+        // duplicate claim
+        assert!(CwtClaims::from_bytes(&hex!("a2041a5612aeb0041a5612aeb0")).is_err());
+    }
+
+    #[test]
+    fn sign_verify() {
+        let key = Ed25519KeyPair::random().unwrap();
+        let token = claims()
+            .sign(&key, CoseAlg::EdDSA, Some(&b"key-1"[..]))
+            .unwrap();
+        assert_eq!(decode_cwt(&token).unwrap().kid(), Some(&b"key-1"[..]));
+
+        let validator = CwtValidator::new(1444000000)
+            .issuer("coap://as.example.com")
+            .audience("coap://light.example.com")
+            .require_exp();
+        assert_eq!(validator.verify(&token, &key).unwrap(), claims());
+        // tag 61 is accepted
+        let tagged = [&TAG_CWT[..], &token[..]].concat();
+        validator.verify(&tagged, &key).unwrap();
+
+        let other = Ed25519KeyPair::random().unwrap();
+        assert!(validator.verify(&token, &other).is_err());
+    }
+
+    #[test]
+    fn validate_claims() {
+        let claims = claims();
+        let exp = claims.exp.unwrap() as u64;
+        let nbf = claims.nbf.unwrap() as u64;
+        CwtValidator::new(nbf).validate(&claims).unwrap();
+        assert!(CwtValidator::new(exp).validate(&claims).is_err());
+        CwtValidator::new(exp)
+            .clock_skew(Duration::from_secs(30))
+            .validate(&claims)
+            .unwrap();
+        assert!(CwtValidator::new(nbf - 1).validate(&claims).is_err());
+        CwtValidator::new(nbf - 30)
+            .clock_skew(Duration::from_secs(30))
+            .validate(&claims)
+            .unwrap();
+        assert!(CwtValidator::new(nbf)
+            .issuer("other")
+            .validate(&claims)
+            .is_err());
+        assert!(CwtValidator::new(nbf)
+            .audience("other")
+            .validate(&claims)
+            .is_err());
+        assert!(CwtValidator::new(nbf)
+            .require_exp()
+            .validate(&CwtClaims::default())
+            .is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! COSE_Encrypt0 single-recipient encrypted messages
+
+use alloc::vec::Vec;
+
+use super::{
+    cbor::{CborDecoder, CborEncoder},
+    encode_protected, encode_unprotected, CoseAlg, HeaderParams,
+};
+use crate::{buffer::SecretBytes, encrypt::KeyAeadInPlace, error::Error};
+
+const TAG_COSE_ENCRYPT0: u64 = 16;
+
+/// A COSE_Encrypt0 message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoseEncrypt0 {
+    protected: Vec<u8>,
+    alg: CoseAlg,
+    kid: Option<Vec<u8>>,
+    iv: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl CoseEncrypt0 {
+    /// Encrypt a message using a symmetric key and nonce
+    pub fn encrypt(
+        key: &(impl KeyAeadInPlace + ?Sized),
+        alg: CoseAlg,
+        kid: Option<&[u8]>,
+        nonce: &[u8],
+        plaintext: &[u8],
+        external_aad: &[u8],
+    ) -> Result<Self, Error> {
+        if !alg.is_encryption() {
+            return Err(err_msg!(
+                Unsupported,
+                "Unsupported COSE encryption algorithm"
+            ));
+        }
+        let params = key.aead_params();
+        if nonce.len() != params.nonce_length {
+            return Err(err_msg!(InvalidNonce));
+        }
+        let protected = encode_protected(alg)?;
+        let aad = enc_structure(&protected, external_aad)?;
+        let mut ciphertext = Vec::with_capacity(plaintext.len() + params.tag_length);
+        ciphertext.extend_from_slice(plaintext);
+        key.encrypt_in_place(&mut ciphertext, nonce, &aad)?;
+        Ok(Self {
+            protected,
+            alg,
+            kid: kid.map(<[u8]>::to_vec),
+            iv: nonce.to_vec(),
+            ciphertext,
+        })
+    }
+
+    /// Parse a tagged or untagged COSE_Encrypt0 message
+    pub fn from_bytes(message: &[u8]) -> Result<Self, Error> {
+        let mut dec = CborDecoder::new(message);
+        dec.read_opt_tag(TAG_COSE_ENCRYPT0)?;
+        if dec.read_array()? != 3 {
+            return Err(err_msg!(Invalid, "Invalid COSE_Encrypt0 structure"));
+        }
+        let protected = dec.read_bytes()?;
+        let headers = HeaderParams::read(protected, &mut dec)?;
+        let ciphertext = dec
+            .read_bytes_or_null()?
+            .ok_or_else(|| err_msg!(Unsupported, "Detached COSE ciphertext is not supported"))?;
+        dec.finish()?;
+        let alg = headers.alg()?;
+        if !alg.is_encryption() {
+            return Err(err_msg!(
+                Unsupported,
+                "Unsupported COSE encryption algorithm"
+            ));
+        }
+        let iv = headers
+            .iv
+            .ok_or_else(|| err_msg!(Invalid, "Missing COSE IV"))?;
+        Ok(Self {
+            protected: protected.to_vec(),
+            alg,
+            kid: headers.kid.map(<[u8]>::to_vec),
+            iv: iv.to_vec(),
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+
+    /// Serialize the message as a tagged COSE_Encrypt0 structure
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut buf =
+            Vec::with_capacity(self.protected.len() + self.iv.len() + self.ciphertext.len() + 32);
+        let mut enc = CborEncoder::new(&mut buf);
+        enc.tag(TAG_COSE_ENCRYPT0)?;
+        enc.array(3)?;
+        enc.bytes(&self.protected)?;
+        encode_unprotected(&mut enc, self.kid.as_deref(), Some(self.iv.as_slice()))?;
+        enc.bytes(&self.ciphertext)?;
+        Ok(buf)
+    }
+
+    /// Accessor for the content encryption algorithm
+    pub fn alg(&self) -> CoseAlg {
+        self.alg
+    }
+
+    /// Accessor for the key identifier, if any
+    pub fn kid(&self) -> Option<&[u8]> {
+        self.kid.as_deref()
+    }
+
+    /// Accessor for the nonce
+    pub fn iv(&self) -> &[u8] {
+        &self.iv
+    }
+
+    /// Accessor for the ciphertext, including the authentication tag
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    /// Decrypt the message using a symmetric key
+    pub fn decrypt(
+        &self,
+        key: &(impl KeyAeadInPlace + ?Sized),
+        external_aad: &[u8],
+    ) -> Result<SecretBytes, Error> {
+        if self.iv.len() != key.aead_params().nonce_length {
+            return Err(err_msg!(InvalidNonce));
+        }
+        let aad = enc_structure(&self.protected, external_aad)?;
+        let mut buf = SecretBytes::from_slice(&self.ciphertext);
+        key.decrypt_in_place(&mut buf, &self.iv, &aad)?;
+        Ok(buf)
+    }
+}
+
+fn enc_structure(protected: &[u8], external_aad: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::with_capacity(protected.len() + external_aad.len() + 16);
+    let mut enc = CborEncoder::new(&mut buf);
+    enc.array(3)?;
+    enc.text("Encrypt0")?;
+    enc.bytes(protected)?;
+    enc.bytes(external_aad)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alg::{
+            aes::{A128Gcm, AesKey},
+            chacha20::{Chacha20Key, C20P},
+        },
+        repr::KeyGen,
+    };
+
+    #[test]
+    fn encrypt0_aes_gcm() {
+        let key = AesKey::<A128Gcm>::random().unwrap();
+        let nonce = [1u8; 12];
+        let msg = CoseEncrypt0::encrypt(
+            &key,
+            CoseAlg::A128GCM,
+            Some(&b"kid"[..]),
+            &nonce,
+            b"plaintext",
+            b"aad",
+        )
+        .unwrap();
+        let bytes = msg.to_bytes().unwrap();
+        // tag 16, protected {1: 1}
+        assert_eq!(&bytes[..6], &hex!("d08343a10101"));
+
+        let parsed = CoseEncrypt0::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, msg);
+        assert_eq!(parsed.kid(), Some(&b"kid"[..]));
+        assert_eq!(parsed.iv(), &nonce[..]);
+        assert_eq!(parsed.decrypt(&key, b"aad").unwrap(), &b"plaintext"[..]);
+        assert!(parsed.decrypt(&key, b"other").is_err());
+        let other = AesKey::<A128Gcm>::random().unwrap();
+        assert!(parsed.decrypt(&other, b"aad").is_err());
+    }
+
+    #[test]
+    fn encrypt0_chacha() {
+        let key = Chacha20Key::<C20P>::random().unwrap();
+        let msg = CoseEncrypt0::encrypt(
+            &key,
+            CoseAlg::ChaCha20Poly1305,
+            None,
+            &[2u8; 12],
+            b"plaintext",
+            b"",
+        )
+        .unwrap();
+        let parsed = CoseEncrypt0::from_bytes(&msg.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.alg(), CoseAlg::ChaCha20Poly1305);
+        assert_eq!(parsed.decrypt(&key, b"").unwrap(), &b"plaintext"[..]);
+        // invalid nonce length
+        assert!(
+            CoseEncrypt0::encrypt(&key, CoseAlg::ChaCha20Poly1305, None, &[0u8; 8], b"", b"")
+                .is_err()
+        );
+        assert!(CoseEncrypt0::encrypt(&key, CoseAlg::EdDSA, None, &[0u8; 12], b"", b"").is_err());
+    }
+}
@@ -0,0 +1,639 @@
+//! COSE_Key (RFC 9052) encoding and decoding
+
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use subtle::ConstantTimeEq;
+
+use super::cbor::{CborDecoder, CborEncoder, MAJOR_BYTES, MAJOR_NEGINT, MAJOR_UINT};
+#[cfg(feature = "ed448")]
+use crate::alg::{ed448::Ed448KeyPair, x448::X448KeyPair};
+use crate::{
+    alg::{
+        aes::{AesKey, AesType},
+        chacha20::{Chacha20Key, Chacha20Type},
+        ed25519::Ed25519KeyPair,
+        k256::K256KeyPair,
+        p256::P256KeyPair,
+        p384::P384KeyPair,
+        x25519::X25519KeyPair,
+        AesTypes, Chacha20Types, EcCurves, KeyAlg,
+    },
+    buffer::{SecretBytes, WriteBuffer},
+    error::Error,
+    repr::{KeyPublicBytes, KeySecretBytes},
+};
+
+const LABEL_KTY: i64 = 1;
+const LABEL_KID: i64 = 2;
+const LABEL_ALG: i64 = 3;
+// the crv parameter for OKP and EC2 keys, or k for symmetric keys
+const LABEL_CRV_K: i64 = -1;
+const LABEL_X: i64 = -2;
+const LABEL_Y: i64 = -3;
+const LABEL_D: i64 = -4;
+
+const KTY_OKP: i64 = 1;
+const KTY_EC2: i64 = 2;
+const KTY_SYMMETRIC: i64 = 4;
+
+const CRV_P256: i64 = 1;
+const CRV_P384: i64 = 2;
+const CRV_X25519: i64 = 4;
+const CRV_X448: i64 = 5;
+const CRV_ED25519: i64 = 6;
+const CRV_ED448: i64 = 7;
+const CRV_SECP256K1: i64 = 8;
+
+const ALG_A128GCM: i64 = 1;
+const ALG_A256GCM: i64 = 3;
+const ALG_A128KW: i64 = -3;
+const ALG_A192KW: i64 = -4;
+const ALG_A256KW: i64 = -5;
+const ALG_CHACHA20_POLY1305: i64 = 24;
+
+// sized for an uncompressed P-384 point
+const MAX_EC2_POINT: usize = 97;
+
+/// Accumulate the parameters of a COSE_Key
+pub trait CoseKeyEncoder {
+    /// Check if the secret key parameters should be included
+    fn is_secret(&self) -> bool;
+
+    /// Add an integer-valued parameter
+    fn add_int(&mut self, label: i64, value: i64) -> Result<(), Error>;
+
+    /// Add a byte string parameter
+    fn add_bytes(&mut self, label: i64, value: &[u8]) -> Result<(), Error>;
+}
+
+/// A COSE_Key encoder which writes a CBOR map to a buffer
+#[derive(Debug)]
+pub struct CoseKeyBufferEncoder<'b> {
+    buffer: &'b mut dyn WriteBuffer,
+    body: SecretBytes,
+    count: usize,
+    secret: bool,
+}
+
+impl<'b> CoseKeyBufferEncoder<'b> {
+    /// Create a new encoder
+    pub fn new(buffer: &'b mut dyn WriteBuffer, secret: bool) -> Self {
+        Self {
+            buffer,
+            body: SecretBytes::with_capacity(128),
+            count: 0,
+            secret,
+        }
+    }
+
+    /// Write the completed COSE_Key to the buffer
+    pub fn finalize(self) -> Result<(), Error> {
+        let Self {
+            buffer,
+            body,
+            count,
+            ..
+        } = self;
+        CborEncoder::new(&mut *buffer).map(count)?;
+        buffer.buffer_write(&body)
+    }
+}
+
+impl CoseKeyEncoder for CoseKeyBufferEncoder<'_> {
+    fn is_secret(&self) -> bool {
+        self.secret
+    }
+
+    fn add_int(&mut self, label: i64, value: i64) -> Result<(), Error> {
+        let mut enc = CborEncoder::new(&mut self.body);
+        enc.int(label)?;
+        enc.int(value)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn add_bytes(&mut self, label: i64, value: &[u8]) -> Result<(), Error> {
+        let mut enc = CborEncoder::new(&mut self.body);
+        enc.int(label)?;
+        enc.bytes(value)?;
+        self.count += 1;
+        Ok(())
+    }
+}
+
+/// Support for converting a key into a COSE_Key
+pub trait ToCoseKey {
+    /// Write the COSE_Key parameters to an encoder
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error>;
+
+    /// Create a COSE_Key of the public key
+    fn to_cose_key_public(&self) -> Result<Vec<u8>, Error> {
+        let mut v = Vec::with_capacity(128);
+        let mut buf = CoseKeyBufferEncoder::new(&mut v, false);
+        self.encode_cose_key(&mut buf)?;
+        buf.finalize()?;
+        Ok(v)
+    }
+
+    /// Create a COSE_Key of the secret key
+    fn to_cose_key_secret(&self) -> Result<SecretBytes, Error> {
+        let mut v = SecretBytes::with_capacity(128);
+        let mut buf = CoseKeyBufferEncoder::new(&mut v, true);
+        self.encode_cose_key(&mut buf)?;
+        buf.finalize()?;
+        Ok(v)
+    }
+}
+
+/// Support for loading a key instance from a COSE_Key
+pub trait FromCoseKey: Sized {
+    /// Import the key from a CBOR-encoded COSE_Key
+    fn from_cose_key(cose_key: &[u8]) -> Result<Self, Error> {
+        let parts = CoseKeyParts::from_slice(cose_key)?;
+        Self::from_cose_key_parts(parts)
+    }
+
+    /// Import the key from a parsed COSE_Key
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error>;
+}
+
+/// A parsed COSE_Key
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoseKeyParts<'a> {
+    /// Key type
+    pub kty: i64,
+    /// Key ID
+    pub kid: Option<&'a [u8]>,
+    /// Key algorithm
+    pub alg: Option<i64>,
+    /// Curve type
+    pub crv: Option<i64>,
+    /// Curve key public x coordinate
+    pub x: Option<&'a [u8]>,
+    /// Curve key public y coordinate
+    pub y: Option<&'a [u8]>,
+    /// Curve key private key bytes
+    pub d: Option<&'a [u8]>,
+    /// Used by symmetric keys like AES
+    pub k: Option<&'a [u8]>,
+}
+
+impl<'a> CoseKeyParts<'a> {
+    /// Parse a COSE_Key from a byte slice
+    pub fn from_slice(cose_key: &'a [u8]) -> Result<Self, Error> {
+        let mut dec = CborDecoder::new(cose_key);
+        let mut parts = Self::default();
+        let mut kty = None;
+        let count = dec.read_map()?;
+        for _ in 0..count {
+            if !matches!(dec.peek_major(), Some(MAJOR_UINT | MAJOR_NEGINT)) {
+                // ignore text labels
+                dec.skip()?;
+                dec.skip()?;
+                continue;
+            }
+            let label = dec.read_int()?;
+            let dup = match label {
+                LABEL_KTY => kty.replace(read_int_value(&mut dec)?).is_some(),
+                LABEL_KID => parts.kid.replace(dec.read_bytes()?).is_some(),
+                LABEL_ALG => {
+                    if matches!(dec.peek_major(), Some(MAJOR_UINT | MAJOR_NEGINT)) {
+                        parts.alg.replace(dec.read_int()?).is_some()
+                    } else {
+                        // text algorithm identifiers are not recognized
+                        dec.skip()?;
+                        false
+                    }
+                }
+                LABEL_CRV_K if dec.peek_major() == Some(MAJOR_BYTES) => {
+                    parts.k.replace(dec.read_bytes()?).is_some()
+                }
+                LABEL_CRV_K => parts.crv.replace(read_int_value(&mut dec)?).is_some(),
+                LABEL_X => parts.x.replace(dec.read_bytes()?).is_some(),
+                LABEL_Y => {
+                    if dec.peek_major() != Some(MAJOR_BYTES) {
+                        return Err(err_msg!(
+                            Unsupported,
+                            "Compressed COSE_Key points are not supported"
+                        ));
+                    }
+                    parts.y.replace(dec.read_bytes()?).is_some()
+                }
+                LABEL_D => parts.d.replace(dec.read_bytes()?).is_some(),
+                _ => {
+                    dec.skip()?;
+                    false
+                }
+            };
+            if dup {
+                return Err(err_msg!(Invalid, "Duplicate COSE_Key parameter"));
+            }
+        }
+        dec.finish()?;
+        parts.kty = kty.ok_or_else(|| err_msg!(Invalid, "Missing COSE_Key type"))?;
+        Ok(parts)
+    }
+
+    /// Determine the key algorithm of the COSE_Key
+    pub fn key_alg(&self) -> Result<KeyAlg, Error> {
+        match (self.kty, self.crv, self.alg) {
+            (KTY_OKP, Some(CRV_ED25519), _) => Ok(KeyAlg::Ed25519),
+            (KTY_OKP, Some(CRV_X25519), _) => Ok(KeyAlg::X25519),
+            (KTY_OKP, Some(CRV_ED448), _) => Ok(KeyAlg::Ed448),
+            (KTY_OKP, Some(CRV_X448), _) => Ok(KeyAlg::X448),
+            (KTY_EC2, Some(CRV_P256), _) => Ok(KeyAlg::EcCurve(EcCurves::Secp256r1)),
+            (KTY_EC2, Some(CRV_P384), _) => Ok(KeyAlg::EcCurve(EcCurves::Secp384r1)),
+            (KTY_EC2, Some(CRV_SECP256K1), _) => Ok(KeyAlg::EcCurve(EcCurves::Secp256k1)),
+            (KTY_SYMMETRIC, _, Some(alg)) => symmetric_key_alg(alg)
+                .ok_or_else(|| err_msg!(Unsupported, "Unsupported COSE_Key algorithm")),
+            (KTY_SYMMETRIC, _, None) => Err(err_msg!(
+                Unsupported,
+                "Symmetric COSE_Key must specify an algorithm"
+            )),
+            _ => Err(err_msg!(Unsupported, "Unsupported COSE_Key type")),
+        }
+    }
+}
+
+impl Debug for CoseKeyParts<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoseKeyParts")
+            .field("kty", &self.kty)
+            .field("kid", &self.kid)
+            .field("alg", &self.alg)
+            .field("crv", &self.crv)
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .field("d", &self.d.map(|_| "<secret>"))
+            .field("k", &self.k.map(|_| "<secret>"))
+            .finish()
+    }
+}
+
+#[inline]
+fn read_int_value(dec: &mut CborDecoder<'_>) -> Result<i64, Error> {
+    if matches!(dec.peek_major(), Some(MAJOR_UINT | MAJOR_NEGINT)) {
+        dec.read_int()
+    } else {
+        Err(err_msg!(
+            Unsupported,
+            "Unsupported COSE_Key parameter value"
+        ))
+    }
+}
+
+fn symmetric_key_alg(alg: i64) -> Option<KeyAlg> {
+    match alg {
+        ALG_A128GCM => Some(KeyAlg::Aes(AesTypes::A128Gcm)),
+        ALG_A256GCM => Some(KeyAlg::Aes(AesTypes::A256Gcm)),
+        ALG_A128KW => Some(KeyAlg::Aes(AesTypes::A128Kw)),
+        ALG_A192KW => Some(KeyAlg::Aes(AesTypes::A192Kw)),
+        ALG_A256KW => Some(KeyAlg::Aes(AesTypes::A256Kw)),
+        ALG_CHACHA20_POLY1305 => Some(KeyAlg::Chacha20(Chacha20Types::C20P)),
+        _ => None,
+    }
+}
+
+fn symmetric_alg_id(alg: KeyAlg) -> Option<i64> {
+    match alg {
+        KeyAlg::Aes(AesTypes::A128Gcm) => Some(ALG_A128GCM),
+        KeyAlg::Aes(AesTypes::A256Gcm) => Some(ALG_A256GCM),
+        KeyAlg::Aes(AesTypes::A128Kw) => Some(ALG_A128KW),
+        KeyAlg::Aes(AesTypes::A192Kw) => Some(ALG_A192KW),
+        KeyAlg::Aes(AesTypes::A256Kw) => Some(ALG_A256KW),
+        KeyAlg::Chacha20(Chacha20Types::C20P) => Some(ALG_CHACHA20_POLY1305),
+        _ => None,
+    }
+}
+
+fn encode_okp<K: KeyPublicBytes + KeySecretBytes>(
+    key: &K,
+    crv: i64,
+    enc: &mut dyn CoseKeyEncoder,
+) -> Result<(), Error> {
+    enc.add_int(LABEL_KTY, KTY_OKP)?;
+    enc.add_int(LABEL_CRV_K, crv)?;
+    key.with_public_bytes(|pk| enc.add_bytes(LABEL_X, pk))?;
+    if enc.is_secret() {
+        key.with_secret_bytes(|sk| match sk {
+            Some(sk) => enc.add_bytes(LABEL_D, sk),
+            None => Ok(()),
+        })?;
+    }
+    Ok(())
+}
+
+fn encode_ec2<K: KeySecretBytes>(
+    key: &K,
+    crv: i64,
+    uncompressed: &[u8],
+    enc: &mut dyn CoseKeyEncoder,
+) -> Result<(), Error> {
+    // skip the leading tag byte of the SEC1 encoding
+    let (x, y) = uncompressed[1..].split_at((uncompressed.len() - 1) / 2);
+    enc.add_int(LABEL_KTY, KTY_EC2)?;
+    enc.add_int(LABEL_CRV_K, crv)?;
+    enc.add_bytes(LABEL_X, x)?;
+    enc.add_bytes(LABEL_Y, y)?;
+    if enc.is_secret() {
+        key.with_secret_bytes(|sk| match sk {
+            Some(sk) => enc.add_bytes(LABEL_D, sk),
+            None => Ok(()),
+        })?;
+    }
+    Ok(())
+}
+
+fn encode_symmetric<K: KeySecretBytes>(
+    key: &K,
+    alg: KeyAlg,
+    enc: &mut dyn CoseKeyEncoder,
+) -> Result<(), Error> {
+    if !enc.is_secret() {
+        return Err(err_msg!(Unsupported, "Cannot export as a public key"));
+    }
+    enc.add_int(LABEL_KTY, KTY_SYMMETRIC)?;
+    if let Some(alg_id) = symmetric_alg_id(alg) {
+        enc.add_int(LABEL_ALG, alg_id)?;
+    }
+    key.with_secret_bytes(|sk| match sk {
+        Some(sk) => enc.add_bytes(LABEL_CRV_K, sk),
+        None => Err(err_msg!(MissingSecretKey)),
+    })
+}
+
+fn load_keypair<K: KeyPublicBytes + KeySecretBytes>(
+    public: &[u8],
+    secret: Option<&[u8]>,
+) -> Result<K, Error> {
+    let pk = K::from_public_bytes(public)?;
+    if let Some(sk) = secret {
+        let kp = K::from_secret_bytes(sk)?;
+        let matched: bool = kp
+            .with_public_bytes(|a| pk.with_public_bytes(|b| a.ct_eq(b)))
+            .into();
+        if !matched {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        Ok(kp)
+    } else {
+        Ok(pk)
+    }
+}
+
+fn load_okp<K: KeyPublicBytes + KeySecretBytes>(
+    cose_key: CoseKeyParts<'_>,
+    crv: i64,
+) -> Result<K, Error> {
+    if cose_key.kty != KTY_OKP {
+        return Err(err_msg!(InvalidKeyData, "Unsupported key type"));
+    }
+    if cose_key.crv != Some(crv) {
+        return Err(err_msg!(InvalidKeyData, "Unsupported key algorithm"));
+    }
+    let x = cose_key.x.ok_or_else(|| err_msg!(InvalidKeyData))?;
+    load_keypair(x, cose_key.d)
+}
+
+fn load_ec2<K: KeyPublicBytes + KeySecretBytes>(
+    cose_key: CoseKeyParts<'_>,
+    crv: i64,
+) -> Result<K, Error> {
+    if cose_key.kty != KTY_EC2 {
+        return Err(err_msg!(InvalidKeyData, "Unsupported key type"));
+    }
+    if cose_key.crv != Some(crv) {
+        return Err(err_msg!(InvalidKeyData, "Unsupported key algorithm"));
+    }
+    let (Some(x), Some(y)) = (cose_key.x, cose_key.y) else {
+        return Err(err_msg!(InvalidKeyData));
+    };
+    let len = x.len() + y.len() + 1;
+    if x.len() != y.len() || len > MAX_EC2_POINT {
+        return Err(err_msg!(InvalidKeyData));
+    }
+    let mut point = [0u8; MAX_EC2_POINT];
+    point[0] = 4;
+    point[1..=x.len()].copy_from_slice(x);
+    point[x.len() + 1..len].copy_from_slice(y);
+    load_keypair(&point[..len], cose_key.d)
+}
+
+fn load_symmetric<K: KeySecretBytes>(cose_key: CoseKeyParts<'_>, alg: KeyAlg) -> Result<K, Error> {
+    if cose_key.kty != KTY_SYMMETRIC {
+        return Err(err_msg!(InvalidKeyData, "Unsupported key type"));
+    }
+    if cose_key.alg.is_some() && cose_key.alg != symmetric_alg_id(alg) {
+        return Err(err_msg!(InvalidKeyData, "Unsupported key algorithm"));
+    }
+    let k = cose_key.k.ok_or_else(|| err_msg!(InvalidKeyData))?;
+    K::from_secret_bytes(k)
+}
+
+impl ToCoseKey for Ed25519KeyPair {
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error> {
+        encode_okp(self, CRV_ED25519, enc)
+    }
+}
+
+impl FromCoseKey for Ed25519KeyPair {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        load_okp(cose_key, CRV_ED25519)
+    }
+}
+
+impl ToCoseKey for X25519KeyPair {
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error> {
+        encode_okp(self, CRV_X25519, enc)
+    }
+}
+
+impl FromCoseKey for X25519KeyPair {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        load_okp(cose_key, CRV_X25519)
+    }
+}
+
+#[cfg(feature = "ed448")]
+impl ToCoseKey for Ed448KeyPair {
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error> {
+        encode_okp(self, CRV_ED448, enc)
+    }
+}
+
+#[cfg(feature = "ed448")]
+impl FromCoseKey for Ed448KeyPair {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        load_okp(cose_key, CRV_ED448)
+    }
+}
+
+#[cfg(feature = "ed448")]
+impl ToCoseKey for X448KeyPair {
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error> {
+        encode_okp(self, CRV_X448, enc)
+    }
+}
+
+#[cfg(feature = "ed448")]
+impl FromCoseKey for X448KeyPair {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        load_okp(cose_key, CRV_X448)
+    }
+}
+
+impl ToCoseKey for K256KeyPair {
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error> {
+        self.with_uncompressed_public_bytes(|pk| encode_ec2(self, CRV_SECP256K1, pk, enc))
+    }
+}
+
+impl FromCoseKey for K256KeyPair {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        load_ec2(cose_key, CRV_SECP256K1)
+    }
+}
+
+impl ToCoseKey for P256KeyPair {
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error> {
+        self.with_uncompressed_public_bytes(|pk| encode_ec2(self, CRV_P256, pk, enc))
+    }
+}
+
+impl FromCoseKey for P256KeyPair {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        load_ec2(cose_key, CRV_P256)
+    }
+}
+
+impl ToCoseKey for P384KeyPair {
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error> {
+        self.with_uncompressed_public_bytes(|pk| encode_ec2(self, CRV_P384, pk, enc))
+    }
+}
+
+impl FromCoseKey for P384KeyPair {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        load_ec2(cose_key, CRV_P384)
+    }
+}
+
+impl<T: AesType> ToCoseKey for AesKey<T> {
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error> {
+        encode_symmetric(self, KeyAlg::Aes(T::ALG_TYPE), enc)
+    }
+}
+
+impl<T: AesType> FromCoseKey for AesKey<T> {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        load_symmetric(cose_key, KeyAlg::Aes(T::ALG_TYPE))
+    }
+}
+
+impl<T: Chacha20Type> ToCoseKey for Chacha20Key<T> {
+    fn encode_cose_key(&self, enc: &mut dyn CoseKeyEncoder) -> Result<(), Error> {
+        encode_symmetric(self, KeyAlg::Chacha20(T::ALG_TYPE), enc)
+    }
+}
+
+impl<T: Chacha20Type> FromCoseKey for Chacha20Key<T> {
+    fn from_cose_key_parts(cose_key: CoseKeyParts<'_>) -> Result<Self, Error> {
+        load_symmetric(cose_key, KeyAlg::Chacha20(T::ALG_TYPE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alg::aes::{A128CbcHs256, A128Gcm},
+        repr::{KeyGen, KeypairBytes, ToPublicBytes, ToSecretBytes},
+    };
+
+    #[test]
+    fn ed25519_round_trip() {
+        // RFC 8032 test vector 1
+        let kp = Ed25519KeyPair::from_secret_bytes(&hex!(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"
+        ))
+        .unwrap();
+        let public = kp.to_cose_key_public().unwrap();
+        assert_eq!(
+            public,
+            hex!(
+                "a3010120062158"
+                "20d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+            )
+        );
+        let parts = CoseKeyParts::from_slice(&public).unwrap();
+        assert_eq!(parts.key_alg().unwrap(), KeyAlg::Ed25519);
+        assert!(parts.d.is_none());
+        let pk = Ed25519KeyPair::from_cose_key(&public).unwrap();
+        assert_eq!(pk.to_public_bytes().unwrap(), kp.to_public_bytes().unwrap());
+
+        let secret = kp.to_cose_key_secret().unwrap();
+        let sk = Ed25519KeyPair::from_cose_key(&secret).unwrap();
+        assert_eq!(
+            sk.to_keypair_bytes().unwrap(),
+            kp.to_keypair_bytes().unwrap()
+        );
+        assert!(X25519KeyPair::from_cose_key(&secret).is_err());
+    }
+
+    #[test]
+    fn p256_round_trip() {
+        let kp = P256KeyPair::random().unwrap();
+        let public = kp.to_cose_key_public().unwrap();
+        let parts = CoseKeyParts::from_slice(&public).unwrap();
+        assert_eq!(
+            parts.key_alg().unwrap(),
+            KeyAlg::EcCurve(EcCurves::Secp256r1)
+        );
+        assert_eq!(parts.x.map(<[u8]>::len), Some(32));
+        assert_eq!(parts.y.map(<[u8]>::len), Some(32));
+        let pk = P256KeyPair::from_cose_key(&public).unwrap();
+        assert_eq!(pk.to_public_bytes().unwrap(), kp.to_public_bytes().unwrap());
+
+        let secret = kp.to_cose_key_secret().unwrap();
+        let sk = P256KeyPair::from_cose_key(&secret).unwrap();
+        assert_eq!(
+            sk.to_keypair_bytes().unwrap(),
+            kp.to_keypair_bytes().unwrap()
+        );
+
+        // mismatched public key
+        let other = P256KeyPair::random().unwrap().to_cose_key_public().unwrap();
+        let mut parts = CoseKeyParts::from_slice(&other).unwrap();
+        parts.d = CoseKeyParts::from_slice(&secret).unwrap().d;
+        assert!(P256KeyPair::from_cose_key_parts(parts).is_err());
+    }
+
+    #[test]
+    fn symmetric_round_trip() {
+        let key = AesKey::<A128Gcm>::random().unwrap();
+        assert!(key.to_cose_key_public().is_err());
+        let secret = key.to_cose_key_secret().unwrap();
+        let parts = CoseKeyParts::from_slice(&secret).unwrap();
+        assert_eq!(parts.alg, Some(ALG_A128GCM));
+        assert_eq!(parts.key_alg().unwrap(), KeyAlg::Aes(AesTypes::A128Gcm));
+        let loaded = AesKey::<A128Gcm>::from_cose_key(&secret).unwrap();
+        assert_eq!(
+            loaded.to_secret_bytes().unwrap(),
+            key.to_secret_bytes().unwrap()
+        );
+        assert!(AesKey::<A128CbcHs256>::from_cose_key(&secret).is_err());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        // missing kty
+        assert!(CoseKeyParts::from_slice(&hex!("a1200a")).is_err());
+        // duplicate kty
+        assert!(CoseKeyParts::from_slice(&hex!("a201010102")).is_err());
+        // trailing data
+        assert!(CoseKeyParts::from_slice(&hex!("a1010400")).is_err());
+        // compressed point
+        assert!(CoseKeyParts::from_slice(&hex!("a2010222f5")).is_err());
+    }
+}
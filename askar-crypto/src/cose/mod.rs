@@ -0,0 +1,188 @@
+//! CBOR Object Signing and Encryption (COSE) support
+
+use alloc::vec::Vec;
+
+use crate::{error::Error, sign::SignatureType};
+
+mod cbor;
+use self::cbor::{CborDecoder, CborEncoder, MAJOR_NEGINT, MAJOR_UINT};
+
+pub mod cwt;
+
+mod encrypt0;
+pub use self::encrypt0::CoseEncrypt0;
+
+mod key;
+pub use self::key::{CoseKeyBufferEncoder, CoseKeyEncoder, CoseKeyParts, FromCoseKey, ToCoseKey};
+
+mod sign1;
+pub use self::sign1::CoseSign1;
+
+const HEADER_ALG: i64 = 1;
+const HEADER_CRIT: i64 = 2;
+const HEADER_KID: i64 = 4;
+const HEADER_IV: i64 = 5;
+
+/// Supported COSE algorithms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CoseAlg {
+    /// EdDSA using Ed25519
+    EdDSA,
+    /// ECDSA using P-256 and SHA-256
+    ES256,
+    /// ECDSA using K-256 and SHA-256
+    ES256K,
+    /// ECDSA using P-384 and SHA-384
+    ES384,
+    /// RSASSA-PKCS1-v1_5 using SHA-256
+    RS256,
+    /// RSASSA-PSS using SHA-256 and MGF1 with SHA-256
+    PS256,
+    /// AES-GCM using a 128-bit key
+    A128GCM,
+    /// AES-GCM using a 256-bit key
+    A256GCM,
+    /// ChaCha20-Poly1305 using a 256-bit key
+    ChaCha20Poly1305,
+}
+
+impl CoseAlg {
+    /// Get the registered algorithm identifier
+    pub const fn id(&self) -> i64 {
+        match self {
+            Self::EdDSA => -8,
+            Self::ES256 => -7,
+            Self::ES256K => -47,
+            Self::ES384 => -35,
+            Self::RS256 => -257,
+            Self::PS256 => -37,
+            Self::A128GCM => 1,
+            Self::A256GCM => 3,
+            Self::ChaCha20Poly1305 => 24,
+        }
+    }
+
+    /// Look up an algorithm by its registered identifier
+    pub fn from_id(id: i64) -> Result<Self, Error> {
+        Ok(match id {
+            -8 => Self::EdDSA,
+            -7 => Self::ES256,
+            -47 => Self::ES256K,
+            -35 => Self::ES384,
+            -257 => Self::RS256,
+            -37 => Self::PS256,
+            1 => Self::A128GCM,
+            3 => Self::A256GCM,
+            24 => Self::ChaCha20Poly1305,
+            _ => return Err(err_msg!(Unsupported, "Unsupported COSE algorithm")),
+        })
+    }
+
+    /// Get the signature type for a signature algorithm
+    pub const fn sig_type(&self) -> Option<SignatureType> {
+        match self {
+            Self::EdDSA => Some(SignatureType::EdDSA),
+            Self::ES256 => Some(SignatureType::ES256),
+            Self::ES256K => Some(SignatureType::ES256K),
+            Self::ES384 => Some(SignatureType::ES384),
+            Self::RS256 => Some(SignatureType::RS256),
+            Self::PS256 => Some(SignatureType::PS256),
+            _ => None,
+        }
+    }
+
+    /// Check if this is a content encryption algorithm
+    pub const fn is_encryption(&self) -> bool {
+        matches!(self, Self::A128GCM | Self::A256GCM | Self::ChaCha20Poly1305)
+    }
+}
+
+/// Recognized parameters of the protected and unprotected headers
+#[derive(Debug, Default)]
+struct HeaderParams<'h> {
+    alg: Option<i64>,
+    kid: Option<&'h [u8]>,
+    iv: Option<&'h [u8]>,
+}
+
+impl<'h> HeaderParams<'h> {
+    /// Read the serialized protected header and the unprotected header map
+    fn read(protected: &'h [u8], dec: &mut CborDecoder<'h>) -> Result<Self, Error> {
+        let mut params = Self::default();
+        if !protected.is_empty() {
+            let mut prot = CborDecoder::new(protected);
+            params.read_map(&mut prot, true)?;
+            prot.finish()?;
+        }
+        params.read_map(dec, false)?;
+        Ok(params)
+    }
+
+    fn read_map(&mut self, dec: &mut CborDecoder<'h>, protected: bool) -> Result<(), Error> {
+        let count = dec.read_map()?;
+        for _ in 0..count {
+            if !matches!(dec.peek_major(), Some(MAJOR_UINT | MAJOR_NEGINT)) {
+                dec.skip()?;
+                dec.skip()?;
+                continue;
+            }
+            let dup = match dec.read_int()? {
+                HEADER_ALG if protected => self.alg.replace(dec.read_int()?).is_some(),
+                HEADER_CRIT if protected => {
+                    return Err(err_msg!(
+                        Unsupported,
+                        "Unsupported critical COSE header parameters"
+                    ))
+                }
+                HEADER_KID => self.kid.replace(dec.read_bytes()?).is_some(),
+                HEADER_IV => self.iv.replace(dec.read_bytes()?).is_some(),
+                HEADER_ALG | HEADER_CRIT => {
+                    return Err(err_msg!(Invalid, "COSE header parameter must be protected"))
+                }
+                _ => {
+                    dec.skip()?;
+                    false
+                }
+            };
+            if dup {
+                return Err(err_msg!(Invalid, "Duplicate COSE header parameter"));
+            }
+        }
+        Ok(())
+    }
+
+    fn alg(&self) -> Result<CoseAlg, Error> {
+        CoseAlg::from_id(
+            self.alg
+                .ok_or_else(|| err_msg!(Invalid, "Missing COSE algorithm"))?,
+        )
+    }
+}
+
+/// Encode a protected header containing the algorithm identifier
+fn encode_protected(alg: CoseAlg) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::with_capacity(8);
+    let mut enc = CborEncoder::new(&mut buf);
+    enc.map(1)?;
+    enc.int(HEADER_ALG)?;
+    enc.int(alg.id())?;
+    Ok(buf)
+}
+
+/// Encode the unprotected header map
+fn encode_unprotected(
+    enc: &mut CborEncoder<'_>,
+    kid: Option<&[u8]>,
+    iv: Option<&[u8]>,
+) -> Result<(), Error> {
+    enc.map(kid.is_some() as usize + iv.is_some() as usize)?;
+    if let Some(kid) = kid {
+        enc.int(HEADER_KID)?;
+        enc.bytes(kid)?;
+    }
+    if let Some(iv) = iv {
+        enc.int(HEADER_IV)?;
+        enc.bytes(iv)?;
+    }
+    Ok(())
+}
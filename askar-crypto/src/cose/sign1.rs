@@ -0,0 +1,218 @@
+//! COSE_Sign1 single-signer messages
+
+use alloc::vec::Vec;
+
+use super::{
+    cbor::{CborDecoder, CborEncoder},
+    encode_protected, encode_unprotected, CoseAlg, HeaderParams,
+};
+use crate::{
+    error::Error,
+    sign::{KeySigVerify, KeySign, SignatureType},
+};
+
+const TAG_COSE_SIGN1: u64 = 18;
+
+/// A COSE_Sign1 message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoseSign1 {
+    protected: Vec<u8>,
+    alg: CoseAlg,
+    kid: Option<Vec<u8>>,
+    payload: Option<Vec<u8>>,
+    signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    /// Sign a payload, including it in the message
+    pub fn sign(
+        key: &(impl KeySign + ?Sized),
+        alg: CoseAlg,
+        kid: Option<&[u8]>,
+        payload: &[u8],
+        external_aad: &[u8],
+    ) -> Result<Self, Error> {
+        let mut msg = Self::sign_detached(key, alg, kid, payload, external_aad)?;
+        msg.payload = Some(payload.to_vec());
+        Ok(msg)
+    }
+
+    /// Sign a payload which is transported separately from the message
+    pub fn sign_detached(
+        key: &(impl KeySign + ?Sized),
+        alg: CoseAlg,
+        kid: Option<&[u8]>,
+        payload: &[u8],
+        external_aad: &[u8],
+    ) -> Result<Self, Error> {
+        let sig_type = sig_type(alg)?;
+        let protected = encode_protected(alg)?;
+        let tbs = sig_structure(&protected, external_aad, payload)?;
+        let mut signature = Vec::with_capacity(sig_type.signature_length());
+        key.write_signature(&tbs, Some(sig_type), &mut signature)?;
+        Ok(Self {
+            protected,
+            alg,
+            kid: kid.map(<[u8]>::to_vec),
+            payload: None,
+            signature,
+        })
+    }
+
+    /// Parse a tagged or untagged COSE_Sign1 message
+    pub fn from_bytes(message: &[u8]) -> Result<Self, Error> {
+        let mut dec = CborDecoder::new(message);
+        dec.read_opt_tag(TAG_COSE_SIGN1)?;
+        if dec.read_array()? != 4 {
+            return Err(err_msg!(Invalid, "Invalid COSE_Sign1 structure"));
+        }
+        let protected = dec.read_bytes()?;
+        let headers = HeaderParams::read(protected, &mut dec)?;
+        let payload = dec.read_bytes_or_null()?;
+        let signature = dec.read_bytes()?;
+        dec.finish()?;
+        Ok(Self {
+            protected: protected.to_vec(),
+            alg: headers.alg()?,
+            kid: headers.kid.map(<[u8]>::to_vec),
+            payload: payload.map(<[u8]>::to_vec),
+            signature: signature.to_vec(),
+        })
+    }
+
+    /// Serialize the message as a tagged COSE_Sign1 structure
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::with_capacity(
+            self.protected.len()
+                + self.payload.as_ref().map(Vec::len).unwrap_or_default()
+                + self.signature.len()
+                + 32,
+        );
+        let mut enc = CborEncoder::new(&mut buf);
+        enc.tag(TAG_COSE_SIGN1)?;
+        enc.array(4)?;
+        enc.bytes(&self.protected)?;
+        encode_unprotected(&mut enc, self.kid.as_deref(), None)?;
+        match self.payload.as_deref() {
+            Some(payload) => enc.bytes(payload)?,
+            None => enc.null()?,
+        }
+        enc.bytes(&self.signature)?;
+        Ok(buf)
+    }
+
+    /// Accessor for the signature algorithm
+    pub fn alg(&self) -> CoseAlg {
+        self.alg
+    }
+
+    /// Accessor for the key identifier, if any
+    pub fn kid(&self) -> Option<&[u8]> {
+        self.kid.as_deref()
+    }
+
+    /// Accessor for the payload, unless it is detached
+    pub fn payload(&self) -> Option<&[u8]> {
+        self.payload.as_deref()
+    }
+
+    /// Accessor for the signature
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Verify the signature over the included payload
+    pub fn verify(
+        &self,
+        key: &(impl KeySigVerify + ?Sized),
+        external_aad: &[u8],
+    ) -> Result<bool, Error> {
+        let payload = self
+            .payload
+            .as_deref()
+            .ok_or_else(|| err_msg!(Usage, "Missing payload for detached COSE_Sign1"))?;
+        self.verify_detached(key, payload, external_aad)
+    }
+
+    /// Verify the signature over a detached payload
+    pub fn verify_detached(
+        &self,
+        key: &(impl KeySigVerify + ?Sized),
+        payload: &[u8],
+        external_aad: &[u8],
+    ) -> Result<bool, Error> {
+        let tbs = sig_structure(&self.protected, external_aad, payload)?;
+        key.verify_signature(&tbs, &self.signature, Some(sig_type(self.alg)?))
+    }
+}
+
+fn sig_type(alg: CoseAlg) -> Result<SignatureType, Error> {
+    alg.sig_type()
+        .ok_or_else(|| err_msg!(Unsupported, "Unsupported COSE signature algorithm"))
+}
+
+fn sig_structure(protected: &[u8], external_aad: &[u8], payload: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::with_capacity(protected.len() + external_aad.len() + payload.len() + 32);
+    let mut enc = CborEncoder::new(&mut buf);
+    enc.array(4)?;
+    enc.text("Signature1")?;
+    enc.bytes(protected)?;
+    enc.bytes(external_aad)?;
+    enc.bytes(payload)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alg::{ed25519::Ed25519KeyPair, p256::P256KeyPair},
+        repr::{KeyGen, KeySecretBytes},
+    };
+
+    #[test]
+    fn sign1_ed25519() {
+        // RFC 8032 test vector 1
+        let key = Ed25519KeyPair::from_secret_bytes(&hex!(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"
+        ))
+        .unwrap();
+        let msg = CoseSign1::sign(&key, CoseAlg::EdDSA, Some(&b"11"[..]), b"payload", b"").unwrap();
+        let bytes = msg.to_bytes().unwrap();
+        // tag 18, protected {1: -8}, unprotected {4: '11'}
+        assert_eq!(&bytes[..11], &hex!("d28443a10127a104423131"));
+
+        let parsed = CoseSign1::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, msg);
+        assert_eq!(parsed.alg(), CoseAlg::EdDSA);
+        assert_eq!(parsed.kid(), Some(&b"11"[..]));
+        assert_eq!(parsed.payload(), Some(&b"payload"[..]));
+        assert!(parsed.verify(&key, b"").unwrap());
+        assert!(!parsed.verify(&key, b"aad").unwrap());
+        // untagged input is also accepted
+        assert_eq!(CoseSign1::from_bytes(&bytes[1..]).unwrap(), msg);
+    }
+
+    #[test]
+    fn sign1_p256_detached() {
+        let key = P256KeyPair::random().unwrap();
+        let msg = CoseSign1::sign_detached(&key, CoseAlg::ES256, None, b"payload", b"aad").unwrap();
+        let parsed = CoseSign1::from_bytes(&msg.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.payload(), None);
+        assert!(parsed.verify(&key, b"aad").is_err());
+        assert!(parsed.verify_detached(&key, b"payload", b"aad").unwrap());
+        assert!(!parsed.verify_detached(&key, b"other", b"aad").unwrap());
+        let other = P256KeyPair::random().unwrap();
+        assert!(!parsed.verify_detached(&other, b"payload", b"aad").unwrap());
+    }
+
+    #[test]
+    fn sign1_invalid() {
+        let key = P256KeyPair::random().unwrap();
+        assert!(CoseSign1::sign(&key, CoseAlg::A128GCM, None, b"payload", b"").is_err());
+        // protected header with crit parameter
+        assert!(CoseSign1::from_bytes(&hex!("8446a20126028101a0f640")).is_err());
+        // wrong tag
+        assert!(CoseSign1::from_bytes(&hex!("d08443a10126a0f640")).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+//! Elliptic Curve Integrated Encryption Scheme
+//!
+//! A one-shot construction combining an ephemeral-static ECDH key exchange,
+//! an HKDF key derivation and an AEAD cipher. The HKDF input keying material
+//! is the uncompressed ephemeral public key followed by the shared secret,
+//! with no salt or info, and the resulting envelope consists of the
+//! uncompressed ephemeral public key, the AEAD nonce, and the ciphertext
+//! with the appended tag.
+
+use alloc::vec::Vec;
+
+use hkdf::Hkdf;
+use sha2::{Sha256, Sha512};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{
+    alg::{
+        aes::{A128Gcm, A256Gcm, AesKey},
+        chacha20::{Chacha20Key, C20P},
+        k256::K256KeyPair,
+        p256::P256KeyPair,
+    },
+    buffer::{SecretBytes, WriteBuffer, Writer},
+    encrypt::KeyAeadInPlace,
+    error::Error,
+    kdf::KeyExchange,
+    random::KeyMaterial,
+    repr::{KeyGen, KeyPublicBytes, KeySecretBytes},
+};
+
+/// The length of the AEAD nonces
+pub const NONCE_LENGTH: usize = 12;
+
+/// The length of the AEAD tags
+pub const TAG_LENGTH: usize = 16;
+
+/// Supported ECIES AEAD algorithms
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EciesAead {
+    /// AES-128-GCM
+    Aes128Gcm,
+    /// AES-256-GCM
+    #[default]
+    Aes256Gcm,
+    /// ChaCha20-Poly1305
+    ChaCha20Poly1305,
+}
+
+impl EciesAead {
+    /// Get the length of the AEAD key
+    pub const fn key_length(&self) -> usize {
+        match self {
+            Self::Aes128Gcm => 16,
+            Self::Aes256Gcm | Self::ChaCha20Poly1305 => 32,
+        }
+    }
+}
+
+/// Supported ECIES key derivation functions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EciesKdf {
+    /// HKDF with SHA-256
+    #[default]
+    HkdfSha256,
+    /// HKDF with SHA-512
+    HkdfSha512,
+}
+
+/// The algorithm selection for ECIES encryption
+///
+/// The default is HKDF-SHA256 with AES-256-GCM.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EciesParams {
+    /// The AEAD algorithm
+    pub aead: EciesAead,
+    /// The key derivation function
+    pub kdf: EciesKdf,
+}
+
+impl EciesParams {
+    /// Create a new parameter set
+    pub const fn new(aead: EciesAead, kdf: EciesKdf) -> Self {
+        Self { aead, kdf }
+    }
+
+    fn derive_key(&self, enc: &[u8], dh: &[u8]) -> Result<AeadKey, Error> {
+        let mut key = [0u8; 32];
+        let key = &mut key[..self.aead.key_length()];
+        let mut ikm = Zeroizing::new(Vec::with_capacity(enc.len() + dh.len()));
+        ikm.extend_from_slice(enc);
+        ikm.extend_from_slice(dh);
+        match self.kdf {
+            EciesKdf::HkdfSha256 => Hkdf::<Sha256>::new(None, &ikm).expand(&[], key),
+            EciesKdf::HkdfSha512 => Hkdf::<Sha512>::new(None, &ikm).expand(&[], key),
+        }
+        .map_err(|_| err_msg!(Unexpected))?;
+        let aead_key = match self.aead {
+            EciesAead::Aes128Gcm => AeadKey::Aes128Gcm(AesKey::from_secret_bytes(key)?),
+            EciesAead::Aes256Gcm => AeadKey::Aes256Gcm(AesKey::from_secret_bytes(key)?),
+            EciesAead::ChaCha20Poly1305 => {
+                AeadKey::ChaCha20Poly1305(Chacha20Key::from_secret_bytes(key)?)
+            }
+        };
+        key.zeroize();
+        Ok(aead_key)
+    }
+}
+
+#[derive(Debug)]
+enum AeadKey {
+    Aes128Gcm(AesKey<A128Gcm>),
+    Aes256Gcm(AesKey<A256Gcm>),
+    ChaCha20Poly1305(Chacha20Key<C20P>),
+}
+
+impl AeadKey {
+    fn as_aead(&self) -> &dyn KeyAeadInPlace {
+        match self {
+            Self::Aes128Gcm(k) => k,
+            Self::Aes256Gcm(k) => k,
+            Self::ChaCha20Poly1305(k) => k,
+        }
+    }
+}
+
+/// Key types supporting ECIES encryption
+pub trait EciesKey: KeyExchange + KeyGen + Sized {
+    /// The length of the serialized ephemeral public key
+    const ENC_LENGTH: usize;
+
+    /// Serialize the public key in uncompressed form
+    fn write_ecies_public(&self, out: &mut dyn WriteBuffer) -> Result<(), Error>;
+
+    /// Deserialize an ephemeral public key
+    fn from_ecies_public(enc: &[u8]) -> Result<Self, Error>;
+
+    /// Encrypt a message to this public key using the default RNG
+    #[cfg(feature = "default_rng")]
+    fn ecies_encrypt(&self, params: EciesParams, message: &[u8]) -> Result<Vec<u8>, Error> {
+        self.ecies_encrypt_with_rng(params, message, crate::random::try_default_rng()?)
+    }
+
+    /// Encrypt a message to this public key, producing a self-contained envelope
+    fn ecies_encrypt_with_rng(
+        &self,
+        params: EciesParams,
+        message: &[u8],
+        mut rng: impl KeyMaterial,
+    ) -> Result<Vec<u8>, Error> {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        rng.read_okm(&mut nonce);
+        let ephemeral = Self::generate(rng)?;
+
+        let mut out =
+            Vec::with_capacity(Self::ENC_LENGTH + NONCE_LENGTH + message.len() + TAG_LENGTH);
+        ephemeral.write_ecies_public(&mut out)?;
+        let mut dh = SecretBytes::with_capacity(32);
+        ephemeral.write_key_exchange(self, &mut dh)?;
+        let key = params.derive_key(&out, &dh)?;
+        out.extend_from_slice(&nonce);
+        let header_len = out.len();
+        out.extend_from_slice(message);
+        key.as_aead().encrypt_in_place(
+            &mut Writer::from_vec_skip(&mut out, header_len),
+            &nonce,
+            &[],
+        )?;
+        Ok(out)
+    }
+
+    /// Decrypt an envelope using this secret key
+    fn ecies_decrypt(&self, params: EciesParams, envelope: &[u8]) -> Result<SecretBytes, Error> {
+        if envelope.len() < Self::ENC_LENGTH + NONCE_LENGTH + TAG_LENGTH {
+            return Err(err_msg!(Invalid, "Invalid ECIES envelope length"));
+        }
+        let (enc, rest) = envelope.split_at(Self::ENC_LENGTH);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+        let ephemeral = Self::from_ecies_public(enc)?;
+        let mut dh = SecretBytes::with_capacity(32);
+        self.write_key_exchange(&ephemeral, &mut dh)?;
+        let key = params.derive_key(enc, &dh)?;
+        let mut buf = SecretBytes::from_slice(ciphertext);
+        key.as_aead().decrypt_in_place(&mut buf, nonce, &[])?;
+        Ok(buf)
+    }
+}
+
+impl EciesKey for P256KeyPair {
+    const ENC_LENGTH: usize = 65;
+
+    fn write_ecies_public(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        self.with_uncompressed_public_bytes(|pk| out.buffer_write(pk))
+    }
+
+    fn from_ecies_public(enc: &[u8]) -> Result<Self, Error> {
+        if enc.len() != Self::ENC_LENGTH {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        Self::from_public_bytes(enc)
+    }
+}
+
+impl EciesKey for K256KeyPair {
+    const ENC_LENGTH: usize = 65;
+
+    fn write_ecies_public(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        self.with_uncompressed_public_bytes(|pk| out.buffer_write(pk))
+    }
+
+    fn from_ecies_public(enc: &[u8]) -> Result<Self, Error> {
+        if enc.len() != Self::ENC_LENGTH {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        Self::from_public_bytes(enc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::ToPublicBytes;
+
+    fn round_trip<K: EciesKey + KeyPublicBytes>(params: EciesParams) {
+        let recip = K::random().unwrap();
+        let recip_pub = K::from_public_bytes(&recip.to_public_bytes().unwrap()).unwrap();
+        let envelope = recip_pub.ecies_encrypt(params, b"message").unwrap();
+        assert_eq!(
+            envelope.len(),
+            K::ENC_LENGTH + NONCE_LENGTH + b"message".len() + TAG_LENGTH
+        );
+        let msg = recip.ecies_decrypt(params, &envelope).unwrap();
+        assert_eq!(&msg[..], b"message");
+
+        assert!(recip_pub.ecies_decrypt(params, &envelope).is_err());
+        assert!(recip
+            .ecies_decrypt(params, &envelope[..envelope.len() - 1])
+            .is_err());
+        let mut modified = envelope.clone();
+        modified[K::ENC_LENGTH] ^= 1;
+        assert!(recip.ecies_decrypt(params, &modified).is_err());
+    }
+
+    #[test]
+    fn p256_round_trip() {
+        round_trip::<P256KeyPair>(EciesParams::default());
+        round_trip::<P256KeyPair>(EciesParams::new(EciesAead::Aes128Gcm, EciesKdf::HkdfSha512));
+    }
+
+    #[test]
+    fn k256_round_trip() {
+        round_trip::<K256KeyPair>(EciesParams::default());
+        round_trip::<K256KeyPair>(EciesParams::new(
+            EciesAead::ChaCha20Poly1305,
+            EciesKdf::HkdfSha256,
+        ));
+    }
+
+    #[test]
+    fn params_mismatch() {
+        let recip = K256KeyPair::random().unwrap();
+        let envelope = recip
+            .ecies_encrypt(EciesParams::default(), b"message")
+            .unwrap();
+        assert!(recip
+            .ecies_decrypt(
+                EciesParams::new(EciesAead::Aes256Gcm, EciesKdf::HkdfSha512),
+                &envelope
+            )
+            .is_err());
+    }
+}
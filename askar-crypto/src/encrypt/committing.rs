@@ -0,0 +1,147 @@
+//! Key-committing AEAD encryption
+
+use hkdf::Hkdf;
+use hmac::{Mac, SimpleHmac};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use super::{KeyAeadInPlace, KeyAeadParams};
+use crate::{buffer::ResizeBuffer, error::Error, repr::KeySecretBytes};
+
+/// The length of the key commitment appended to each ciphertext
+pub const COMMITMENT_LENGTH: usize = 32;
+
+/// The HKDF info label used to derive the commitment key from the AEAD key
+const COMMITMENT_KEY_LABEL: &[u8] = b"askar key commitment";
+
+/// An AEAD key wrapper which binds each ciphertext to the encryption key
+///
+/// Standard AEAD modes allow a ciphertext to be crafted which decrypts
+/// successfully under many different keys, enabling partitioning oracle
+/// attacks against candidate keys. A commitment to the key and nonce,
+/// computed with HMAC-SHA256 under a commitment key derived from the AEAD
+/// key using HKDF-SHA256, is appended after the verification tag and checked
+/// before decryption, so that a ciphertext only decrypts under the key which
+/// produced it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Committing<K>(pub K);
+
+impl<K: KeySecretBytes> Committing<K> {
+    fn commitment(&self, nonce: &[u8]) -> Result<[u8; COMMITMENT_LENGTH], Error> {
+        self.0.with_secret_bytes(|sk| {
+            let sk = sk.ok_or_else(|| err_msg!(MissingSecretKey))?;
+            // the AEAD key is never used directly as a MAC key
+            let mut commit_key = Zeroizing::new([0u8; 32]);
+            Hkdf::<Sha256>::new(None, sk)
+                .expand(COMMITMENT_KEY_LABEL, &mut commit_key[..])
+                .map_err(|_| err_msg!(Unexpected, "Error deriving commitment key"))?;
+            let mut mac = <SimpleHmac<Sha256> as Mac>::new_from_slice(&commit_key[..])
+                .map_err(|_| err_msg!(InvalidKeyData))?;
+            mac.update(nonce);
+            Ok(mac.finalize().into_bytes().into())
+        })
+    }
+}
+
+impl<K: KeyAeadInPlace + KeySecretBytes> KeyAeadInPlace for Committing<K> {
+    /// Encrypt a secret value in place, appending the verification tag and
+    /// key commitment
+    fn encrypt_in_place(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<usize, Error> {
+        let commitment = self.commitment(nonce)?;
+        let ctext_len = self.0.encrypt_in_place(buffer, nonce, aad)?;
+        buffer.buffer_write(&commitment)?;
+        Ok(ctext_len)
+    }
+
+    /// Verify the key commitment and decrypt an encrypted value in place
+    fn decrypt_in_place(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Error> {
+        let buf_len = buffer.as_ref().len();
+        if buf_len < COMMITMENT_LENGTH {
+            return Err(err_msg!(Encryption, "Invalid size for encrypted data"));
+        }
+        let commit_start = buf_len - COMMITMENT_LENGTH;
+        let commitment = self.commitment(nonce)?;
+        if !bool::from(buffer.as_ref()[commit_start..].ct_eq(&commitment[..])) {
+            return Err(err_msg!(Encryption, "AEAD key commitment mismatch"));
+        }
+        buffer.buffer_resize(commit_start)?;
+        self.0.decrypt_in_place(buffer, nonce, aad)
+    }
+
+    fn aead_params(&self) -> KeyAeadParams {
+        let params = self.0.aead_params();
+        KeyAeadParams {
+            nonce_length: params.nonce_length,
+            tag_length: params.tag_length + COMMITMENT_LENGTH,
+        }
+    }
+
+    fn aead_padding(&self, msg_len: usize) -> usize {
+        self.0.aead_padding(msg_len)
+    }
+}
+
+#[cfg(all(test, feature = "chacha"))]
+mod tests {
+    use super::*;
+    use crate::alg::chacha20::{Chacha20Key, C20P};
+    use crate::buffer::SecretBytes;
+    use crate::encrypt::KeyAeadMeta;
+    use crate::repr::{KeyGen, ToSecretBytes};
+
+    #[test]
+    fn committing_round_trip() {
+        let key = Committing(Chacha20Key::<C20P>::random().unwrap());
+        let nonce = Chacha20Key::<C20P>::random_nonce();
+        let input = b"hello";
+        let mut buffer = SecretBytes::from_slice(input);
+        key.encrypt_in_place(&mut buffer, &nonce, b"aad").unwrap();
+        assert_eq!(buffer.len(), input.len() + key.aead_params().tag_length);
+        let mut dec = buffer.clone();
+        key.decrypt_in_place(&mut dec, &nonce, b"aad").unwrap();
+        assert_eq!(&dec[..], input);
+
+        // the commitment is checked against the decryption key
+        let other = Committing(Chacha20Key::<C20P>::random().unwrap());
+        let mut dec = buffer.clone();
+        assert!(other.decrypt_in_place(&mut dec, &nonce, b"aad").is_err());
+
+        let enc_len = buffer.len();
+        buffer.as_mut()[enc_len - 1] ^= 1;
+        assert!(key.decrypt_in_place(&mut buffer, &nonce, b"aad").is_err());
+    }
+
+    #[test]
+    fn commitment_key_derived() {
+        let key = Committing(Chacha20Key::<C20P>::random().unwrap());
+        let nonce = Chacha20Key::<C20P>::random_nonce();
+        let mut buffer = SecretBytes::from_slice(b"hello");
+        key.encrypt_in_place(&mut buffer, &nonce, &[]).unwrap();
+        let commitment = &buffer[buffer.len() - COMMITMENT_LENGTH..];
+
+        let sk = key.0.to_secret_bytes().unwrap();
+        let mut commit_key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, &sk[..])
+            .expand(COMMITMENT_KEY_LABEL, &mut commit_key)
+            .unwrap();
+        let mut mac = <SimpleHmac<Sha256> as Mac>::new_from_slice(&commit_key).unwrap();
+        mac.update(&nonce);
+        assert_eq!(commitment, &mac.finalize().into_bytes()[..]);
+
+        // the AEAD key itself is not used as the MAC key
+        let mut mac = <SimpleHmac<Sha256> as Mac>::new_from_slice(&sk[..]).unwrap();
+        mac.update(&nonce);
+        assert_ne!(commitment, &mac.finalize().into_bytes()[..]);
+    }
+}
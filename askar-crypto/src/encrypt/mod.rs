@@ -1,9 +1,17 @@
 //! AEAD encryption traits and parameters
 
-use crate::{buffer::ResizeBuffer, error::Error, generic_array::ArrayLength};
+use core::{mem, ops::Range};
 
-#[cfg(feature = "getrandom")]
-use crate::generic_array::GenericArray;
+use crate::{
+    buffer::ResizeBuffer,
+    error::Error,
+    generic_array::{ArrayLength, GenericArray},
+    random::KeyMaterial,
+};
+
+#[cfg(feature = "committing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "committing")))]
+pub mod committing;
 
 #[cfg(feature = "crypto_box")]
 #[cfg_attr(docsrs, doc(cfg(feature = "crypto_box")))]
@@ -28,6 +36,100 @@ pub trait KeyAeadInPlace {
         aad: &[u8],
     ) -> Result<(), Error>;
 
+    /// Encrypt a secret value in place, appending the verification tag, with
+    /// associated data supplied as a sequence of chunks
+    ///
+    /// Key types which cannot process the chunks incrementally will join
+    /// them into a temporary buffer, requiring the `alloc` feature.
+    fn encrypt_in_place_chunked(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        nonce: &[u8],
+        aad: &[&[u8]],
+    ) -> Result<usize, Error> {
+        match aad {
+            [] => self.encrypt_in_place(buffer, nonce, &[]),
+            [aad] => self.encrypt_in_place(buffer, nonce, aad),
+            #[cfg(feature = "alloc")]
+            _ => self.encrypt_in_place(buffer, nonce, &aad.concat()),
+            #[cfg(not(feature = "alloc"))]
+            _ => Err(err_msg!(Unsupported, "Multi-chunk AAD requires allocation")),
+        }
+    }
+
+    /// Decrypt an encrypted (verification tag appended) value in place, with
+    /// associated data supplied as a sequence of chunks
+    fn decrypt_in_place_chunked(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        nonce: &[u8],
+        aad: &[&[u8]],
+    ) -> Result<(), Error> {
+        match aad {
+            [] => self.decrypt_in_place(buffer, nonce, &[]),
+            [aad] => self.decrypt_in_place(buffer, nonce, aad),
+            #[cfg(feature = "alloc")]
+            _ => self.decrypt_in_place(buffer, nonce, &aad.concat()),
+            #[cfg(not(feature = "alloc"))]
+            _ => Err(err_msg!(Unsupported, "Multi-chunk AAD requires allocation")),
+        }
+    }
+
+    /// Encrypt a secret value in place, writing the verification tag to a
+    /// separate buffer of the expected tag length
+    ///
+    /// This is not supported by AEAD modes which pad the ciphertext.
+    fn encrypt_in_place_detached(
+        &self,
+        _buffer: &mut [u8],
+        _nonce: &[u8],
+        _aad: &[u8],
+        _tag: &mut [u8],
+    ) -> Result<(), Error> {
+        Err(err_msg!(
+            Unsupported,
+            "Detached tags not supported for this key type"
+        ))
+    }
+
+    /// Decrypt an encrypted value in place, using a separate verification tag
+    fn decrypt_in_place_detached(
+        &self,
+        _buffer: &mut [u8],
+        _nonce: &[u8],
+        _aad: &[u8],
+        _tag: &[u8],
+    ) -> Result<(), Error> {
+        Err(err_msg!(
+            Unsupported,
+            "Detached tags not supported for this key type"
+        ))
+    }
+
+    /// Encrypt a message in place within a buffer which also holds the nonce
+    /// and receives the verification tag, at the positions given by the layout
+    fn encrypt_in_place_at(
+        &self,
+        buffer: &mut [u8],
+        layout: &AeadLayout,
+        aad: &[u8],
+    ) -> Result<(), Error> {
+        let [nonce, message, tag] = layout.split(buffer, self.aead_params())?;
+        self.encrypt_in_place_detached(message, nonce, aad, tag)
+    }
+
+    /// Decrypt a message in place within a buffer which also holds the nonce
+    /// and verification tag, at the positions given by the layout
+    fn decrypt_in_place_at(
+        &self,
+        buffer: &mut [u8],
+        layout: &AeadLayout,
+        aad: &[u8],
+    ) -> Result<(), Error> {
+        let [nonce, message, tag] = layout.split(buffer, self.aead_params())?;
+        self.decrypt_in_place_detached(message, nonce, aad, tag)
+    }
+
     /// Get the nonce and tag length for encryption
     fn aead_params(&self) -> KeyAeadParams;
 
@@ -44,13 +146,89 @@ pub trait KeyAeadMeta {
     /// The size of the AEAD tag
     type TagSize: ArrayLength<u8>;
 
-    /// Generate a new random nonce
-    #[cfg(feature = "getrandom")]
-    fn random_nonce() -> GenericArray<u8, Self::NonceSize> {
+    /// Generate a new nonce from a key material generator
+    fn generate_nonce(mut rng: impl KeyMaterial) -> GenericArray<u8, Self::NonceSize> {
         let mut nonce = GenericArray::default();
-        crate::random::fill_random(nonce.as_mut_slice());
+        rng.read_okm(nonce.as_mut_slice());
         nonce
     }
+
+    /// Generate a new random nonce
+    #[cfg(feature = "default_rng")]
+    fn random_nonce() -> GenericArray<u8, Self::NonceSize> {
+        Self::generate_nonce(crate::random::default_rng())
+    }
+}
+
+/// Object-safe trait for key types which perform deterministic authenticated encryption
+///
+/// Encrypting the same message with the same key and associated data always
+/// produces the same ciphertext, which allows encrypted values to be compared
+/// for equality. This reveals which ciphertexts share a plaintext, as well as the
+/// length of each plaintext, so it should only be used for values which must be
+/// searchable, and never for values drawn from a small set of possibilities.
+pub trait KeyAeadDeterministic {
+    /// Encrypt a secret value in place, prepending the synthetic IV
+    fn encrypt_deterministic(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[&[u8]],
+    ) -> Result<(), Error>;
+
+    /// Decrypt an encrypted (synthetic IV prepended) value in place
+    fn decrypt_deterministic(
+        &self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[&[u8]],
+    ) -> Result<(), Error>;
+}
+
+/// The positions of the nonce, message and verification tag within a
+/// combined buffer, such as an envelope with a fixed layout
+///
+/// The lengths of the nonce and tag are determined by the AEAD parameters,
+/// and none of the regions may overlap.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AeadLayout {
+    /// The offset of the nonce
+    pub nonce_offset: usize,
+    /// The range of the message
+    pub message: Range<usize>,
+    /// The offset of the verification tag
+    pub tag_offset: usize,
+}
+
+impl AeadLayout {
+    /// Split a buffer into the nonce, message and tag regions
+    fn split<'b>(
+        &self,
+        buffer: &'b mut [u8],
+        params: KeyAeadParams,
+    ) -> Result<[&'b mut [u8]; 3], Error> {
+        if self.message.end < self.message.start {
+            return Err(err_msg!(Usage, "Invalid AEAD buffer layout"));
+        }
+        let mut regions = [
+            (0, self.nonce_offset, params.nonce_length),
+            (1, self.message.start, self.message.len()),
+            (2, self.tag_offset, params.tag_length),
+        ];
+        regions.sort_unstable_by_key(|(_, start, _)| *start);
+        let mut parts: [&'b mut [u8]; 3] = Default::default();
+        let mut rest = buffer;
+        let mut pos = 0;
+        for (idx, start, len) in regions {
+            if start < pos || start - pos > rest.len() || len > rest.len() - (start - pos) {
+                return Err(err_msg!(Usage, "Invalid AEAD buffer layout"));
+            }
+            let (_, tail) = mem::take(&mut rest).split_at_mut(start - pos);
+            let (part, tail) = tail.split_at_mut(len);
+            parts[idx] = part;
+            rest = tail;
+            pos = start + len;
+        }
+        Ok(parts)
+    }
 }
 
 /// A structure combining the AEAD parameters
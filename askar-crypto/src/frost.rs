@@ -0,0 +1,258 @@
+//! FROST two-round threshold signatures (RFC 9591)
+//!
+//! Key shares may be produced by a trusted dealer or through the distributed
+//! key generation protocol. Signing proceeds in two rounds: each participant
+//! publishes a nonce commitment, then produces a signature share over the
+//! signing package. The aggregated signature is a standard Ed25519 signature
+//! for the `Ed25519Sha512` ciphersuite, or a BIP-340 Schnorr signature for the
+//! `Secp256K1Sha256TR` ciphersuite.
+
+use alloc::{collections::BTreeMap, string::ToString, vec::Vec};
+
+use rand::{CryptoRng, RngCore};
+
+pub use frost_core::{
+    keys::{KeyPackage, PublicKeyPackage, SecretShare},
+    round1::{SigningCommitments, SigningNonces},
+    round2::SignatureShare,
+    Ciphersuite, Identifier, Signature, SigningPackage,
+};
+pub use frost_ed25519::Ed25519Sha512;
+pub use frost_secp256k1_tr::Secp256K1Sha256TR;
+
+use crate::{
+    alg::{ed25519::Ed25519KeyPair, k256::K256KeyPair},
+    error::Error,
+    repr::KeyPublicBytes,
+};
+
+/// Map a FROST error, keeping its description as the cause. The error type
+/// is not `Send` or `Sync` for an arbitrary ciphersuite.
+macro_rules! frost_err {
+    ($($params:tt)*) => {
+        |err: frost_core::Error<_>| err_map!($($params)*)(err.to_string())
+    };
+}
+
+/// Generate key shares for a group using a trusted dealer
+///
+/// Returns the key package for each participant, and the public key package
+/// shared by the group.
+#[allow(clippy::type_complexity)]
+pub fn generate_with_dealer<C: Ciphersuite>(
+    max_signers: u16,
+    min_signers: u16,
+    mut rng: impl CryptoRng + RngCore,
+) -> Result<(BTreeMap<Identifier<C>, KeyPackage<C>>, PublicKeyPackage<C>), Error> {
+    let (shares, public) = frost_core::keys::generate_with_dealer(
+        max_signers,
+        min_signers,
+        frost_core::keys::IdentifierList::Default,
+        &mut rng,
+    )
+    .map_err(frost_err!(Usage, "Error generating FROST key shares"))?;
+    let mut packages = BTreeMap::new();
+    for (ident, share) in shares {
+        let package = KeyPackage::try_from(share)
+            .map_err(frost_err!(InvalidKeyData, "Invalid FROST secret share"))?;
+        packages.insert(ident, package);
+    }
+    Ok((packages, public))
+}
+
+/// Distributed key generation without a trusted dealer
+pub mod dkg {
+    use super::*;
+
+    pub use frost_core::keys::dkg::{round1, round2};
+
+    /// Perform the first round of key generation for a participant
+    ///
+    /// The returned package is broadcast to all other participants.
+    pub fn part1<C: Ciphersuite>(
+        identifier: Identifier<C>,
+        max_signers: u16,
+        min_signers: u16,
+        mut rng: impl CryptoRng + RngCore,
+    ) -> Result<(round1::SecretPackage<C>, round1::Package<C>), Error> {
+        frost_core::keys::dkg::part1(identifier, max_signers, min_signers, &mut rng)
+            .map_err(frost_err!(Usage, "Error in FROST key generation"))
+    }
+
+    /// Perform the second round of key generation for a participant
+    ///
+    /// Each returned package is sent privately to the corresponding participant.
+    #[allow(clippy::type_complexity)]
+    pub fn part2<C: Ciphersuite>(
+        secret: round1::SecretPackage<C>,
+        round1_packages: &BTreeMap<Identifier<C>, round1::Package<C>>,
+    ) -> Result<
+        (
+            round2::SecretPackage<C>,
+            BTreeMap<Identifier<C>, round2::Package<C>>,
+        ),
+        Error,
+    > {
+        frost_core::keys::dkg::part2(secret, round1_packages)
+            .map_err(frost_err!(Invalid, "Error in FROST key generation"))
+    }
+
+    /// Complete key generation, producing the participant's key package and
+    /// the public key package for the group
+    pub fn part3<C: Ciphersuite>(
+        secret: &round2::SecretPackage<C>,
+        round1_packages: &BTreeMap<Identifier<C>, round1::Package<C>>,
+        round2_packages: &BTreeMap<Identifier<C>, round2::Package<C>>,
+    ) -> Result<(KeyPackage<C>, PublicKeyPackage<C>), Error> {
+        frost_core::keys::dkg::part3(secret, round1_packages, round2_packages)
+            .map_err(frost_err!(Invalid, "Error in FROST key generation"))
+    }
+}
+
+/// Generate signing nonces and the corresponding commitments for a participant
+///
+/// The nonces must be kept secret and used for a single signature share.
+pub fn commit<C: Ciphersuite>(
+    key_package: &KeyPackage<C>,
+    mut rng: impl CryptoRng + RngCore,
+) -> (SigningNonces<C>, SigningCommitments<C>) {
+    frost_core::round1::commit(key_package.signing_share(), &mut rng)
+}
+
+/// Produce a signature share for a signing package
+pub fn sign<C: Ciphersuite>(
+    signing_package: &SigningPackage<C>,
+    nonces: &SigningNonces<C>,
+    key_package: &KeyPackage<C>,
+) -> Result<SignatureShare<C>, Error> {
+    frost_core::round2::sign(signing_package, nonces, key_package)
+        .map_err(frost_err!(Invalid, "Error creating FROST signature share"))
+}
+
+/// Aggregate signature shares into a serialized group signature
+pub fn aggregate<C: Ciphersuite>(
+    signing_package: &SigningPackage<C>,
+    shares: &BTreeMap<Identifier<C>, SignatureShare<C>>,
+    public: &PublicKeyPackage<C>,
+) -> Result<Vec<u8>, Error> {
+    frost_core::aggregate(signing_package, shares, public)
+        .map_err(frost_err!(
+            Invalid,
+            "Error aggregating FROST signature shares"
+        ))?
+        .serialize()
+        .map_err(frost_err!(Unexpected, "Error serializing FROST signature"))
+}
+
+/// Get the serialized group public key
+pub fn group_public_bytes<C: Ciphersuite>(public: &PublicKeyPackage<C>) -> Result<Vec<u8>, Error> {
+    public
+        .verifying_key()
+        .serialize()
+        .map_err(frost_err!(Unexpected, "Error serializing FROST group key"))
+}
+
+/// Get the group public key as an Ed25519 key
+pub fn ed25519_group_key(
+    public: &PublicKeyPackage<Ed25519Sha512>,
+) -> Result<Ed25519KeyPair, Error> {
+    Ed25519KeyPair::from_public_bytes(&group_public_bytes(public)?)
+}
+
+/// Get the group public key as a secp256k1 key
+pub fn k256_group_key(public: &PublicKeyPackage<Secp256K1Sha256TR>) -> Result<K256KeyPair, Error> {
+    K256KeyPair::from_public_bytes(&group_public_bytes(public)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::default_rng;
+
+    fn sign_with_quorum<C: Ciphersuite>(
+        packages: &BTreeMap<Identifier<C>, KeyPackage<C>>,
+        public: &PublicKeyPackage<C>,
+        signers: usize,
+        message: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let mut nonces = BTreeMap::new();
+        let mut commitments = BTreeMap::new();
+        for (ident, package) in packages.iter().take(signers) {
+            let (n, c) = commit(package, default_rng());
+            nonces.insert(*ident, n);
+            commitments.insert(*ident, c);
+        }
+        let signing_package = SigningPackage::new(commitments, message);
+        let mut shares = BTreeMap::new();
+        for (ident, n) in &nonces {
+            shares.insert(*ident, sign(&signing_package, n, &packages[ident])?);
+        }
+        aggregate(&signing_package, &shares, public)
+    }
+
+    #[test]
+    fn ed25519_dealer_sign() {
+        let (packages, public) =
+            generate_with_dealer::<Ed25519Sha512>(5, 3, default_rng()).unwrap();
+        let sig = sign_with_quorum(&packages, &public, 3, b"message").unwrap();
+        let group_key = ed25519_group_key(&public).unwrap();
+        assert!(group_key.verify_signature(b"message", &sig));
+        assert!(!group_key.verify_signature(b"other message", &sig));
+        assert!(sign_with_quorum(&packages, &public, 2, b"message").is_err());
+    }
+
+    #[test]
+    fn k256_dealer_sign() {
+        let (packages, public) =
+            generate_with_dealer::<Secp256K1Sha256TR>(3, 2, default_rng()).unwrap();
+        let sig = sign_with_quorum(&packages, &public, 2, b"message").unwrap();
+        let sig = Signature::<Secp256K1Sha256TR>::deserialize(&sig).unwrap();
+        public.verifying_key().verify(b"message", &sig).unwrap();
+        k256_group_key(&public).unwrap();
+    }
+
+    #[test]
+    fn ed25519_dkg_sign() {
+        let idents: Vec<Identifier<Ed25519Sha512>> = (1..=3u16)
+            .map(|i| Identifier::try_from(i).unwrap())
+            .collect();
+        let mut round1_secrets = BTreeMap::new();
+        let mut round1_packages = BTreeMap::new();
+        for ident in &idents {
+            let (secret, package) = dkg::part1(*ident, 3, 2, default_rng()).unwrap();
+            round1_secrets.insert(*ident, secret);
+            round1_packages.insert(*ident, package);
+        }
+
+        let mut round2_secrets = BTreeMap::new();
+        let mut round2_packages = BTreeMap::new();
+        for (ident, secret) in round1_secrets {
+            let mut received = round1_packages.clone();
+            received.remove(&ident);
+            let (secret, packages) = dkg::part2(secret, &received).unwrap();
+            round2_secrets.insert(ident, secret);
+            for (recip, package) in packages {
+                round2_packages
+                    .entry(recip)
+                    .or_insert_with(BTreeMap::new)
+                    .insert(ident, package);
+            }
+        }
+
+        let mut packages = BTreeMap::new();
+        let mut public = None;
+        for (ident, secret) in &round2_secrets {
+            let mut received = round1_packages.clone();
+            received.remove(ident);
+            let (package, group) = dkg::part3(secret, &received, &round2_packages[ident]).unwrap();
+            packages.insert(*ident, package);
+            public = Some(group);
+        }
+        let public = public.unwrap();
+
+        let sig = sign_with_quorum(&packages, &public, 2, b"message").unwrap();
+        assert!(ed25519_group_key(&public)
+            .unwrap()
+            .verify_signature(b"message", &sig));
+    }
+}
@@ -0,0 +1,628 @@
+//! Hybrid Public Key Encryption (RFC 9180)
+//!
+//! Supports the base, PSK, auth and auth-PSK modes using DHKEM(X25519, HKDF-SHA256)
+//! or DHKEM(P-256, HKDF-SHA256), HKDF-SHA256, and the AES-GCM and ChaCha20-Poly1305
+//! AEADs.
+
+use core::fmt::{self, Debug, Formatter};
+
+use hkdf::{Hkdf, HkdfExtract};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::{
+    alg::{
+        aes::{A128Gcm, A256Gcm, AesKey},
+        chacha20::{Chacha20Key, C20P},
+        p256::P256KeyPair,
+        x25519::X25519KeyPair,
+    },
+    buffer::{ResizeBuffer, WriteBuffer, Writer},
+    encrypt::KeyAeadInPlace,
+    error::Error,
+    kdf::KeyExchange,
+    repr::{KeyPublicBytes, KeySecretBytes},
+};
+
+const HPKE_VERSION: &[u8] = b"HPKE-v1";
+
+/// The identifier of HKDF-SHA256
+const KDF_ID: u16 = 0x0001;
+
+/// The output length of the KDF hash function
+const HASH_LENGTH: usize = 32;
+
+/// The length of the AEAD nonces
+const NONCE_LENGTH: usize = 12;
+
+/// The maximum length of an encapsulated key
+pub const MAX_ENC_LENGTH: usize = 65;
+
+/// Supported HPKE modes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HpkeMode {
+    /// Encryption to a public key
+    Base,
+    /// Encryption to a public key, authenticated by a pre-shared key
+    Psk,
+    /// Encryption to a public key, authenticated by a sender key
+    Auth,
+    /// Encryption to a public key, authenticated by a pre-shared key and sender key
+    AuthPsk,
+}
+
+impl HpkeMode {
+    /// Get the mode identifier
+    pub const fn id(&self) -> u8 {
+        match self {
+            Self::Base => 0x00,
+            Self::Psk => 0x01,
+            Self::Auth => 0x02,
+            Self::AuthPsk => 0x03,
+        }
+    }
+}
+
+/// Supported HPKE AEAD algorithms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HpkeAead {
+    /// AES-128-GCM
+    Aes128Gcm,
+    /// AES-256-GCM
+    Aes256Gcm,
+    /// ChaCha20-Poly1305
+    ChaCha20Poly1305,
+}
+
+impl HpkeAead {
+    /// Get the AEAD identifier
+    pub const fn id(&self) -> u16 {
+        match self {
+            Self::Aes128Gcm => 0x0001,
+            Self::Aes256Gcm => 0x0002,
+            Self::ChaCha20Poly1305 => 0x0003,
+        }
+    }
+
+    /// Get the length of the AEAD key
+    pub const fn key_length(&self) -> usize {
+        match self {
+            Self::Aes128Gcm => 16,
+            Self::Aes256Gcm | Self::ChaCha20Poly1305 => 32,
+        }
+    }
+}
+
+/// A pre-shared key and its identifier
+#[derive(Clone, Copy, Debug)]
+pub struct HpkePsk<'p> {
+    /// The pre-shared key value
+    pub psk: &'p [u8],
+    /// The pre-shared key identifier
+    pub psk_id: &'p [u8],
+}
+
+/// Key types supporting use in a Diffie-Hellman based KEM
+pub trait HpkeKemKey: KeyExchange + Sized {
+    /// The KEM identifier
+    const KEM_ID: u16;
+    /// The length of the serialized public key
+    const ENC_LENGTH: usize;
+
+    /// Serialize the public key
+    fn write_kem_public(&self, out: &mut dyn WriteBuffer) -> Result<(), Error>;
+
+    /// Deserialize a public key
+    fn from_kem_public(enc: &[u8]) -> Result<Self, Error>;
+}
+
+impl HpkeKemKey for X25519KeyPair {
+    const KEM_ID: u16 = 0x0020;
+    const ENC_LENGTH: usize = 32;
+
+    fn write_kem_public(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        self.with_public_bytes(|pk| out.buffer_write(pk))
+    }
+
+    fn from_kem_public(enc: &[u8]) -> Result<Self, Error> {
+        Self::from_public_bytes(enc)
+    }
+}
+
+impl HpkeKemKey for P256KeyPair {
+    const KEM_ID: u16 = 0x0010;
+    const ENC_LENGTH: usize = 65;
+
+    fn write_kem_public(&self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        self.with_uncompressed_public_bytes(|pk| out.buffer_write(pk))
+    }
+
+    fn from_kem_public(enc: &[u8]) -> Result<Self, Error> {
+        if enc.len() != Self::ENC_LENGTH {
+            return Err(err_msg!(InvalidKeyData));
+        }
+        Self::from_public_bytes(enc)
+    }
+}
+
+/// Accumulate a suite identifier
+struct SuiteId {
+    buf: [u8; 10],
+    len: usize,
+}
+
+impl SuiteId {
+    fn kem(kem_id: u16) -> Self {
+        let mut buf = [0u8; 10];
+        buf[..3].copy_from_slice(b"KEM");
+        buf[3..5].copy_from_slice(&kem_id.to_be_bytes());
+        Self { buf, len: 5 }
+    }
+
+    fn hpke(kem_id: u16, aead: HpkeAead) -> Self {
+        let mut buf = [0u8; 10];
+        buf[..4].copy_from_slice(b"HPKE");
+        buf[4..6].copy_from_slice(&kem_id.to_be_bytes());
+        buf[6..8].copy_from_slice(&KDF_ID.to_be_bytes());
+        buf[8..10].copy_from_slice(&aead.id().to_be_bytes());
+        Self { buf, len: 10 }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    fn labeled_extract(&self, salt: &[u8], label: &[u8], ikm: &[&[u8]]) -> Hkdf<Sha256> {
+        let mut extract = HkdfExtract::<Sha256>::new(Some(salt));
+        extract.input_ikm(HPKE_VERSION);
+        extract.input_ikm(self.as_bytes());
+        extract.input_ikm(label);
+        for part in ikm {
+            extract.input_ikm(part);
+        }
+        extract.finalize().1
+    }
+
+    fn labeled_extract_bytes(&self, salt: &[u8], label: &[u8], ikm: &[u8]) -> [u8; HASH_LENGTH] {
+        let mut extract = HkdfExtract::<Sha256>::new(Some(salt));
+        extract.input_ikm(HPKE_VERSION);
+        extract.input_ikm(self.as_bytes());
+        extract.input_ikm(label);
+        extract.input_ikm(ikm);
+        let mut out = [0u8; HASH_LENGTH];
+        out.copy_from_slice(&extract.finalize().0);
+        out
+    }
+
+    fn labeled_expand(
+        &self,
+        prk: &Hkdf<Sha256>,
+        label: &[u8],
+        info: &[&[u8]],
+        out: &mut [u8],
+    ) -> Result<(), Error> {
+        let len = u16::try_from(out.len())
+            .map_err(|_| err_msg!(Usage, "Exceeded maximum output length"))?
+            .to_be_bytes();
+        let mut parts: [&[u8]; 8] = [&[]; 8];
+        if info.len() > parts.len() - 4 {
+            return Err(err_msg!(Unexpected));
+        }
+        parts[0] = &len[..];
+        parts[1] = HPKE_VERSION;
+        parts[2] = self.as_bytes();
+        parts[3] = label;
+        parts[4..(4 + info.len())].copy_from_slice(info);
+        prk.expand_multi_info(&parts[..(4 + info.len())], out)
+            .map_err(|_| err_msg!(Usage, "Exceeded maximum output length"))
+    }
+}
+
+/// Perform the DHKEM encapsulation or decapsulation key derivation
+fn extract_and_expand<K: HpkeKemKey>(
+    dh: &[u8],
+    kem_context: &[u8],
+    shared_secret: &mut [u8; HASH_LENGTH],
+) -> Result<(), Error> {
+    let suite_id = SuiteId::kem(K::KEM_ID);
+    let prk = suite_id.labeled_extract(&[], b"eae_prk", &[dh]);
+    suite_id.labeled_expand(
+        &prk,
+        b"shared_secret",
+        &[kem_context],
+        &mut shared_secret[..],
+    )
+}
+
+/// Perform a key exchange, rejecting an all-zero output
+fn write_dh<K: HpkeKemKey>(sk: &K, pk: &K, out: &mut Writer<'_, [u8]>) -> Result<(), Error> {
+    let start = out.position();
+    sk.write_key_exchange(pk, out)?;
+    let end = out.position();
+    if out.as_ref()[start..end]
+        .ct_eq(&[0u8; 32][..(end - start)])
+        .into()
+    {
+        return Err(err_msg!(InvalidKeyData, "Invalid key exchange output"));
+    }
+    Ok(())
+}
+
+fn derive_shared_secret<K: HpkeKemKey>(
+    dh_key: &K,
+    dh_pub: &K,
+    auth_key: Option<(&K, &K)>,
+    enc: &[u8],
+    recip: &K,
+    sender: Option<&K>,
+    shared_secret: &mut [u8; HASH_LENGTH],
+) -> Result<(), Error> {
+    let mut dh_buf = [0u8; 64];
+    let mut dh = Writer::from_slice(&mut dh_buf[..]);
+    write_dh(dh_key, dh_pub, &mut dh)?;
+    if let Some((sk, pk)) = auth_key {
+        write_dh(sk, pk, &mut dh)?;
+    }
+    let dh_len = dh.position();
+
+    let mut ctx_buf = [0u8; MAX_ENC_LENGTH * 3];
+    let mut kem_context = Writer::from_slice(&mut ctx_buf[..]);
+    kem_context.buffer_write(enc)?;
+    recip.write_kem_public(&mut kem_context)?;
+    if let Some(sender) = sender {
+        sender.write_kem_public(&mut kem_context)?;
+    }
+    let ctx_len = kem_context.position();
+
+    let result = extract_and_expand::<K>(&dh_buf[..dh_len], &ctx_buf[..ctx_len], shared_secret);
+    dh_buf.zeroize();
+    result
+}
+
+fn key_schedule(
+    kem_id: u16,
+    aead: HpkeAead,
+    mode: HpkeMode,
+    shared_secret: &[u8],
+    info: &[u8],
+    psk: Option<HpkePsk<'_>>,
+) -> Result<HpkeContext, Error> {
+    let (psk, psk_id) = match psk {
+        Some(HpkePsk { psk, psk_id }) => {
+            if psk.is_empty() || psk_id.is_empty() {
+                return Err(err_msg!(Usage, "Invalid pre-shared key"));
+            }
+            (psk, psk_id)
+        }
+        None => (&[][..], &[][..]),
+    };
+    let suite_id = SuiteId::hpke(kem_id, aead);
+    let psk_id_hash = suite_id.labeled_extract_bytes(&[], b"psk_id_hash", psk_id);
+    let info_hash = suite_id.labeled_extract_bytes(&[], b"info_hash", info);
+    let mode_id = [mode.id()];
+    let ks_context: [&[u8]; 3] = [&mode_id, &psk_id_hash, &info_hash];
+    let secret = suite_id.labeled_extract(shared_secret, b"secret", &[psk]);
+
+    let mut key = [0u8; 32];
+    let key = &mut key[..aead.key_length()];
+    suite_id.labeled_expand(&secret, b"key", &ks_context, key)?;
+    let aead_key = match aead {
+        HpkeAead::Aes128Gcm => AeadKey::Aes128Gcm(AesKey::from_secret_bytes(key)?),
+        HpkeAead::Aes256Gcm => AeadKey::Aes256Gcm(AesKey::from_secret_bytes(key)?),
+        HpkeAead::ChaCha20Poly1305 => {
+            AeadKey::ChaCha20Poly1305(Chacha20Key::from_secret_bytes(key)?)
+        }
+    };
+    key.zeroize();
+
+    let mut base_nonce = [0u8; NONCE_LENGTH];
+    suite_id.labeled_expand(&secret, b"base_nonce", &ks_context, &mut base_nonce)?;
+    let mut exporter_secret = [0u8; HASH_LENGTH];
+    suite_id.labeled_expand(&secret, b"exp", &ks_context, &mut exporter_secret)?;
+
+    Ok(HpkeContext {
+        aead: aead_key,
+        base_nonce,
+        exporter_secret,
+        seq: 0,
+        suite_id,
+    })
+}
+
+fn select_mode(psk: Option<HpkePsk<'_>>, auth: bool) -> HpkeMode {
+    match (psk.is_some(), auth) {
+        (false, false) => HpkeMode::Base,
+        (true, false) => HpkeMode::Psk,
+        (false, true) => HpkeMode::Auth,
+        (true, true) => HpkeMode::AuthPsk,
+    }
+}
+
+/// Set up an HPKE sender context using a random ephemeral key,
+/// writing the encapsulated key to the provided buffer
+#[cfg(feature = "default_rng")]
+pub fn setup_sender<K: HpkeKemKey + crate::repr::KeyGen>(
+    aead: HpkeAead,
+    recipient: &K,
+    info: &[u8],
+    psk: Option<HpkePsk<'_>>,
+    sender: Option<&K>,
+    enc: &mut dyn WriteBuffer,
+) -> Result<HpkeContext, Error> {
+    let ephemeral = K::random()?;
+    setup_sender_with_ephemeral(aead, &ephemeral, recipient, info, psk, sender, enc)
+}
+
+/// Set up an HPKE sender context using the provided ephemeral key,
+/// writing the encapsulated key to the provided buffer
+pub fn setup_sender_with_ephemeral<K: HpkeKemKey>(
+    aead: HpkeAead,
+    ephemeral: &K,
+    recipient: &K,
+    info: &[u8],
+    psk: Option<HpkePsk<'_>>,
+    sender: Option<&K>,
+    enc: &mut dyn WriteBuffer,
+) -> Result<HpkeContext, Error> {
+    let mode = select_mode(psk, sender.is_some());
+    let mut enc_buf = [0u8; MAX_ENC_LENGTH];
+    let mut enc_writer = Writer::from_slice(&mut enc_buf[..]);
+    ephemeral.write_kem_public(&mut enc_writer)?;
+    let enc_len = enc_writer.position();
+    let mut shared_secret = [0u8; HASH_LENGTH];
+    derive_shared_secret(
+        ephemeral,
+        recipient,
+        sender.map(|sk| (sk, recipient)),
+        &enc_buf[..enc_len],
+        recipient,
+        sender,
+        &mut shared_secret,
+    )?;
+    let result = key_schedule(K::KEM_ID, aead, mode, &shared_secret, info, psk);
+    shared_secret.zeroize();
+    enc.buffer_write(&enc_buf[..enc_len])?;
+    result
+}
+
+/// Set up an HPKE recipient context from an encapsulated key
+pub fn setup_recipient<K: HpkeKemKey>(
+    aead: HpkeAead,
+    enc: &[u8],
+    recipient: &K,
+    info: &[u8],
+    psk: Option<HpkePsk<'_>>,
+    sender: Option<&K>,
+) -> Result<HpkeContext, Error> {
+    if enc.len() != K::ENC_LENGTH {
+        return Err(err_msg!(InvalidKeyData, "Invalid encapsulated key length"));
+    }
+    let mode = select_mode(psk, sender.is_some());
+    let ephemeral = K::from_kem_public(enc)?;
+    let mut shared_secret = [0u8; HASH_LENGTH];
+    derive_shared_secret(
+        recipient,
+        &ephemeral,
+        sender.map(|pk| (recipient, pk)),
+        enc,
+        recipient,
+        sender,
+        &mut shared_secret,
+    )?;
+    let result = key_schedule(K::KEM_ID, aead, mode, &shared_secret, info, psk);
+    shared_secret.zeroize();
+    result
+}
+
+#[derive(Debug)]
+enum AeadKey {
+    Aes128Gcm(AesKey<A128Gcm>),
+    Aes256Gcm(AesKey<A256Gcm>),
+    ChaCha20Poly1305(Chacha20Key<C20P>),
+}
+
+impl AeadKey {
+    fn as_aead(&self) -> &dyn KeyAeadInPlace {
+        match self {
+            Self::Aes128Gcm(k) => k,
+            Self::Aes256Gcm(k) => k,
+            Self::ChaCha20Poly1305(k) => k,
+        }
+    }
+}
+
+/// An HPKE encryption context for a sender or recipient
+pub struct HpkeContext {
+    aead: AeadKey,
+    base_nonce: [u8; NONCE_LENGTH],
+    exporter_secret: [u8; HASH_LENGTH],
+    seq: u64,
+    suite_id: SuiteId,
+}
+
+impl HpkeContext {
+    fn next_nonce(&self) -> Result<[u8; NONCE_LENGTH], Error> {
+        if self.seq == u64::MAX {
+            return Err(err_msg!(Usage, "Message limit reached"));
+        }
+        let mut nonce = self.base_nonce;
+        for (n, s) in nonce[(NONCE_LENGTH - 8)..]
+            .iter_mut()
+            .zip(self.seq.to_be_bytes())
+        {
+            *n ^= s;
+        }
+        Ok(nonce)
+    }
+
+    /// Encrypt a message in place, appending the verification tag
+    pub fn seal_in_place(
+        &mut self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[u8],
+    ) -> Result<usize, Error> {
+        let nonce = self.next_nonce()?;
+        let len = self.aead.as_aead().encrypt_in_place(buffer, &nonce, aad)?;
+        self.seq += 1;
+        Ok(len)
+    }
+
+    /// Decrypt a message in place
+    pub fn open_in_place(
+        &mut self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[u8],
+    ) -> Result<(), Error> {
+        let nonce = self.next_nonce()?;
+        self.aead.as_aead().decrypt_in_place(buffer, &nonce, aad)?;
+        self.seq += 1;
+        Ok(())
+    }
+
+    /// Derive a secret from the exporter secret of the context
+    pub fn export(&self, exporter_context: &[u8], out: &mut [u8]) -> Result<(), Error> {
+        let prk = Hkdf::<Sha256>::from_prk(&self.exporter_secret[..])
+            .map_err(|_| err_msg!(Unexpected))?;
+        self.suite_id
+            .labeled_expand(&prk, b"sec", &[exporter_context], out)
+    }
+}
+
+impl Debug for HpkeContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HpkeContext")
+            .field("aead", &self.aead)
+            .field("seq", &self.seq)
+            .finish()
+    }
+}
+
+impl Drop for HpkeContext {
+    fn drop(&mut self) {
+        self.base_nonce.zeroize();
+        self.exporter_secret.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer::SecretBytes, repr::KeyGen};
+
+    // from RFC 9180 Appendix A.1.1
+    #[test]
+    fn x25519_base_expected() {
+        let ephem = X25519KeyPair::from_secret_bytes(&hex!(
+            "52c4a758a802cd8b936eceea314432798d5baf2d7e9235dc084ab1b9cfa2f736"
+        ))
+        .unwrap();
+        let recip = X25519KeyPair::from_secret_bytes(&hex!(
+            "4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8"
+        ))
+        .unwrap();
+        let info = hex!("4f6465206f6e2061204772656369616e2055726e");
+        let mut enc = [0u8; MAX_ENC_LENGTH];
+        let mut enc_writer = Writer::from_slice(&mut enc[..]);
+        let mut ctx = setup_sender_with_ephemeral(
+            HpkeAead::Aes128Gcm,
+            &ephem,
+            &recip,
+            &info,
+            None,
+            None,
+            &mut enc_writer,
+        )
+        .unwrap();
+        let enc_len = enc_writer.position();
+        assert_eq!(
+            &enc[..enc_len],
+            &hex!("37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431")[..]
+        );
+        let mut buf = SecretBytes::from_slice(&hex!(
+            "4265617574792069732074727574682c20747275746820626561757479"
+        ));
+        ctx.seal_in_place(&mut buf, &hex!("436f756e742d30"))
+            .unwrap();
+        assert_eq!(
+            &buf[..],
+            &hex!(
+                "f938558b5d72f1a23810b4be2ab4f84331acc02fc97babc53a52ae8218a355a9
+                6d8770ac83d07bea87e13c512a"
+            )[..]
+        );
+
+        let mut ctx = setup_recipient(
+            HpkeAead::Aes128Gcm,
+            &enc[..enc_len],
+            &recip,
+            &info,
+            None,
+            None,
+        )
+        .unwrap();
+        ctx.open_in_place(&mut buf, &hex!("436f756e742d30"))
+            .unwrap();
+        assert_eq!(&buf[..], b"Beauty is truth, truth beauty");
+    }
+
+    fn round_trip<K: HpkeKemKey + KeyGen>(aead: HpkeAead) {
+        let recip = K::random().unwrap();
+        let sender = K::random().unwrap();
+        let psk = HpkePsk {
+            psk: b"pre-shared key value",
+            psk_id: b"psk id",
+        };
+        for (psk, sender) in [
+            (None, None),
+            (Some(psk), None),
+            (None, Some(&sender)),
+            (Some(psk), Some(&sender)),
+        ] {
+            let mut enc = SecretBytes::with_capacity(MAX_ENC_LENGTH);
+            let mut ctx = setup_sender(aead, &recip, b"info", psk, sender, &mut enc).unwrap();
+            assert_eq!(enc.len(), K::ENC_LENGTH);
+            let mut buf = SecretBytes::from_slice(b"message");
+            ctx.seal_in_place(&mut buf, b"aad").unwrap();
+            let mut exp_send = [0u8; 32];
+            ctx.export(b"context", &mut exp_send).unwrap();
+
+            let mut ctx = setup_recipient(aead, &enc, &recip, b"info", psk, sender).unwrap();
+            let mut exp_recv = [0u8; 32];
+            ctx.export(b"context", &mut exp_recv).unwrap();
+            assert_eq!(exp_send, exp_recv);
+            assert!(ctx.open_in_place(&mut buf.clone(), b"other").is_err());
+            ctx.open_in_place(&mut buf, b"aad").unwrap();
+            assert_eq!(&buf[..], b"message");
+
+            // the recipient must use the same mode as the sender
+            let mut ctx = setup_recipient(aead, &enc, &recip, b"info", None, None).unwrap();
+            if psk.is_some() || sender.is_some() {
+                let mut buf = SecretBytes::from_slice(b"message");
+                let mut send = setup_sender(
+                    aead,
+                    &recip,
+                    b"info",
+                    psk,
+                    sender,
+                    &mut SecretBytes::with_capacity(0),
+                )
+                .unwrap();
+                send.seal_in_place(&mut buf, b"aad").unwrap();
+                assert!(ctx.open_in_place(&mut buf, b"aad").is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn x25519_round_trip() {
+        round_trip::<X25519KeyPair>(HpkeAead::Aes128Gcm);
+        round_trip::<X25519KeyPair>(HpkeAead::ChaCha20Poly1305);
+    }
+
+    #[test]
+    fn p256_round_trip() {
+        round_trip::<P256KeyPair>(HpkeAead::Aes128Gcm);
+        round_trip::<P256KeyPair>(HpkeAead::Aes256Gcm);
+    }
+}
@@ -5,7 +5,10 @@ use serde::{
     Serializer,
 };
 
-use super::{ops::KeyOpsSet, ToJwk};
+use super::{
+    ops::{JwkKeyUse, KeyOpsSet},
+    write_jwk_thumbprint_hash, JwkThumbprintHash, ToJwk,
+};
 use crate::{
     alg::KeyAlg,
     buffer::{WriteBuffer, Writer},
@@ -24,6 +27,45 @@ fn write_hex_buffer(mut buffer: impl Write, value: &[u8]) -> Result<(), Error> {
     .map_err(|_| err_msg!(Unexpected, "Error writing to JWK buffer"))
 }
 
+/// A fixed-size buffer holding a computed JWK thumbprint
+#[derive(Clone, Copy, Debug)]
+struct KidBuffer {
+    buf: [u8; 86],
+    len: usize,
+}
+
+impl KidBuffer {
+    fn compute<K: ToJwk + ?Sized>(
+        key: &K,
+        alg: Option<KeyAlg>,
+        hash: JwkThumbprintHash,
+    ) -> Result<Self, Error> {
+        let mut kid = Self {
+            buf: [0u8; 86],
+            len: 0,
+        };
+        write_jwk_thumbprint_hash(key, alg, hash, &mut kid)?;
+        Ok(kid)
+    }
+
+    fn as_str(&self) -> &str {
+        // the thumbprint is always base64-URL encoded
+        core::str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+}
+
+impl WriteBuffer for KidBuffer {
+    fn buffer_write(&mut self, data: &[u8]) -> Result<(), Error> {
+        let end = self.len + data.len();
+        if end > self.buf.len() {
+            return Err(err_msg!(ExceededBuffer));
+        }
+        self.buf[self.len..end].copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+}
+
 /// Supported modes for JWK encoding
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum JwkEncoderMode {
@@ -72,8 +114,11 @@ pub struct JwkBufferEncoder<'b, B: WriteBuffer> {
     buffer: &'b mut B,
     empty: bool,
     alg: Option<KeyAlg>,
+    alg_param: Option<&'b str>,
     key_ops: Option<KeyOpsSet>,
+    key_use: Option<JwkKeyUse>,
     kid: Option<&'b str>,
+    kid_thumbprint: Option<KidBuffer>,
 }
 
 impl<'b, B: WriteBuffer> JwkBufferEncoder<'b, B> {
@@ -84,8 +129,11 @@ impl<'b, B: WriteBuffer> JwkBufferEncoder<'b, B> {
             buffer,
             empty: true,
             alg: None,
+            alg_param: None,
             key_ops: None,
+            key_use: None,
             kid: None,
+            kid_thumbprint: None,
         }
     }
 
@@ -117,11 +165,40 @@ impl<'b, B: WriteBuffer> JwkBufferEncoder<'b, B> {
         Self { kid, ..self }
     }
 
+    /// Set the `alg` parameter of the JWK
+    pub fn alg_param(self, alg_param: Option<&'b str>) -> Self {
+        Self { alg_param, ..self }
+    }
+
+    /// Set the intended public key use
+    pub fn key_use(self, key_use: Option<JwkKeyUse>) -> Self {
+        Self { key_use, ..self }
+    }
+
+    /// Use the JWK thumbprint of the key as the key identifier
+    ///
+    /// This takes precedence over any key identifier set with `kid`.
+    pub fn kid_thumbprint<K: ToJwk + ?Sized>(
+        self,
+        key: &K,
+        hash: JwkThumbprintHash,
+    ) -> Result<Self, Error> {
+        let kid_thumbprint = Some(KidBuffer::compute(key, self.alg, hash)?);
+        Ok(Self {
+            kid_thumbprint,
+            ..self
+        })
+    }
+
     /// Complete the JWK output
     pub fn finalize(mut self) -> Result<(), Error> {
+        if let Some(alg) = self.alg_param {
+            self.add_str("alg", alg)?;
+        }
         if let Some(ops) = self.key_ops {
             self.start_attr("key_ops")?;
             let buffer = &mut *self.buffer;
+            buffer.buffer_write(b"[")?;
             for (idx, op) in ops.into_iter().enumerate() {
                 if idx > 0 {
                     buffer.buffer_write(b",\"")?;
@@ -133,9 +210,14 @@ impl<'b, B: WriteBuffer> JwkBufferEncoder<'b, B> {
             }
             buffer.buffer_write(b"]")?;
         }
-        if let Some(kid) = self.kid {
+        if let Some(kid) = self.kid_thumbprint {
+            self.add_str("kid", kid.as_str())?;
+        } else if let Some(kid) = self.kid {
             self.add_str("kid", kid)?;
         }
+        if let Some(key_use) = self.key_use {
+            self.add_str("use", key_use.as_str())?;
+        }
         if !self.empty {
             self.buffer.buffer_write(b"}")?;
         }
@@ -179,8 +261,11 @@ pub struct JwkSerialize<'s, K: ToJwk> {
     mode: JwkEncoderMode,
     key: &'s K,
     alg: Option<KeyAlg>,
+    alg_param: Option<&'s str>,
     key_ops: Option<KeyOpsSet>,
+    key_use: Option<JwkKeyUse>,
     kid: Option<&'s str>,
+    kid_thumbprint: Option<JwkThumbprintHash>,
 }
 
 impl<'s, K: ToJwk> JwkSerialize<'s, K> {
@@ -188,10 +273,13 @@ impl<'s, K: ToJwk> JwkSerialize<'s, K> {
     pub fn new(key: &'s K, mode: JwkEncoderMode) -> Self {
         Self {
             alg: None,
+            alg_param: None,
             mode,
             key,
             key_ops: None,
+            key_use: None,
             kid: None,
+            kid_thumbprint: None,
         }
     }
 
@@ -201,8 +289,11 @@ impl<'s, K: ToJwk> JwkSerialize<'s, K> {
             mode: JwkEncoderMode::PublicKey,
             key,
             alg: None,
+            alg_param: None,
             key_ops: None,
+            key_use: None,
             kid: None,
+            kid_thumbprint: None,
         }
     }
 
@@ -212,8 +303,11 @@ impl<'s, K: ToJwk> JwkSerialize<'s, K> {
             mode: JwkEncoderMode::SecretKey,
             key,
             alg: None,
+            alg_param: None,
             key_ops: None,
+            key_use: None,
             kid: None,
+            kid_thumbprint: None,
         }
     }
 
@@ -223,8 +317,11 @@ impl<'s, K: ToJwk> JwkSerialize<'s, K> {
             mode: JwkEncoderMode::Thumbprint,
             key,
             alg: None,
+            alg_param: None,
             key_ops: None,
+            key_use: None,
             kid: None,
+            kid_thumbprint: None,
         }
     }
 
@@ -242,6 +339,26 @@ impl<'s, K: ToJwk> JwkSerialize<'s, K> {
     pub fn kid(self, kid: Option<&'s str>) -> Self {
         Self { kid, ..self }
     }
+
+    /// Set the `alg` parameter of the JWK
+    pub fn alg_param(self, alg_param: Option<&'s str>) -> Self {
+        Self { alg_param, ..self }
+    }
+
+    /// Set the intended public key use
+    pub fn key_use(self, key_use: Option<JwkKeyUse>) -> Self {
+        Self { key_use, ..self }
+    }
+
+    /// Use the JWK thumbprint of the key as the key ID
+    ///
+    /// This takes precedence over any key ID set with `kid`.
+    pub fn kid_thumbprint(self, kid_thumbprint: Option<JwkThumbprintHash>) -> Self {
+        Self {
+            kid_thumbprint,
+            ..self
+        }
+    }
 }
 
 impl<'s, K: ToJwk> Serialize for JwkSerialize<'s, K> {
@@ -267,9 +384,9 @@ impl<'s, K: ToJwk> Serialize for JwkSerialize<'s, K> {
             }
 
             fn add_as_base64(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
-                // in practice these values have a limited length.
+                // in practice these values have a limited length (up to an ML-DSA-87 public key).
                 // it would be nice to use collect_str, but that's not supported by serde-json-core.
-                let mut buf = [0u8; 256];
+                let mut buf = [0u8; 3456];
                 let mut w = Writer::from_slice(&mut buf);
                 write_hex_buffer(&mut w, value)?;
                 self.map
@@ -291,12 +408,22 @@ impl<'s, K: ToJwk> Serialize for JwkSerialize<'s, K> {
         self.key
             .encode_jwk(&mut enc)
             .map_err(|err| <S::Error as serde::ser::Error>::custom(err.message()))?;
+        if let Some(alg) = self.alg_param {
+            map.serialize_entry("alg", alg)?;
+        }
         if let Some(ops) = self.key_ops {
             map.serialize_entry("key_ops", &ops)?;
         }
-        if let Some(kid) = self.kid {
+        if let Some(hash) = self.kid_thumbprint {
+            let kid = KidBuffer::compute(self.key, self.alg, hash)
+                .map_err(|err| <S::Error as serde::ser::Error>::custom(err.message()))?;
+            map.serialize_entry("kid", kid.as_str())?;
+        } else if let Some(kid) = self.kid {
             map.serialize_entry("kid", kid)?;
         }
+        if let Some(key_use) = self.key_use {
+            map.serialize_entry("use", key_use.as_str())?;
+        }
         map.end()
     }
 }
@@ -338,4 +465,59 @@ mod tests {
         assert_eq!(parts.k, None);
         assert_eq!(parts.key_ops, Some(KeyOps::Sign | KeyOps::Verify));
     }
+
+    #[cfg(all(feature = "alloc", feature = "ed25519"))]
+    #[test]
+    fn encode_computed_kid() {
+        use super::{JwkBufferEncoder, JwkSerialize};
+        use crate::{
+            alg::ed25519::Ed25519KeyPair,
+            jwk::{JwkEncoderMode, JwkKeyUse, JwkParts, JwkThumbprintHash, KeyOps, ToJwk},
+            repr::KeySecretBytes,
+        };
+        use alloc::vec::Vec;
+
+        let kp = Ed25519KeyPair::from_secret_bytes(&hex!(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"
+        ))
+        .unwrap();
+        let thumbprint = kp.to_jwk_thumbprint(None).unwrap();
+        assert_eq!(
+            kp.jwk_thumbprint(JwkThumbprintHash::Sha256).unwrap(),
+            thumbprint
+        );
+        for hash in [JwkThumbprintHash::Sha384, JwkThumbprintHash::Sha512] {
+            assert_eq!(
+                kp.jwk_thumbprint(hash).unwrap().len(),
+                hash.thumbprint_length()
+            );
+        }
+
+        let mut buf = Vec::new();
+        let mut enc = JwkBufferEncoder::new(&mut buf, JwkEncoderMode::PublicKey)
+            .alg_param(Some("EdDSA"))
+            .key_ops(Some(KeyOps::Sign | KeyOps::Verify))
+            .key_use(Some(JwkKeyUse::Signature))
+            .kid(Some("ignored"))
+            .kid_thumbprint(&kp, JwkThumbprintHash::Sha256)
+            .unwrap();
+        kp.encode_jwk(&mut enc).unwrap();
+        enc.finalize().unwrap();
+        let parts = JwkParts::from_slice(&buf).unwrap();
+        assert_eq!(parts.kid, Some(thumbprint.as_str()));
+        assert_eq!(parts.alg, Some("EdDSA"));
+        assert_eq!(parts.key_ops, Some(KeyOps::Sign | KeyOps::Verify));
+
+        let mut buf = [0u8; 512];
+        let len = serde_json_core::to_slice(
+            &JwkSerialize::as_public(&kp)
+                .key_use(Some(JwkKeyUse::Signature))
+                .kid_thumbprint(Some(JwkThumbprintHash::Sha256)),
+            &mut buf,
+        )
+        .unwrap();
+        let parts = JwkParts::from_slice(&buf[..len]).unwrap();
+        assert_eq!(parts.kid, Some(thumbprint.as_str()));
+        assert_eq!(parts.key_ops, Some(KeyOps::Sign | KeyOps::Verify));
+    }
 }
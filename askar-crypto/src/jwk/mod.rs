@@ -3,8 +3,11 @@
 #[cfg(feature = "alloc")]
 use alloc::{string::String, vec::Vec};
 
+use core::fmt::Debug;
+
 use base64::Engine;
-use sha2::Sha256;
+use digest::Digest;
+use sha2::{Sha256, Sha384, Sha512};
 
 #[cfg(feature = "alloc")]
 use crate::buffer::SecretBytes;
@@ -18,7 +21,7 @@ mod encode;
 pub use self::encode::{JwkBufferEncoder, JwkEncoder, JwkEncoderMode, JwkSerialize};
 
 mod ops;
-pub use self::ops::{KeyOps, KeyOpsSet};
+pub use self::ops::{JwkKeyUse, KeyOps, KeyOpsSet};
 
 mod parts;
 pub use self::parts::JwkParts;
@@ -37,6 +40,15 @@ pub trait ToJwk {
         Ok(String::from_utf8(v).unwrap())
     }
 
+    /// Create the JWK thumbprint of the key using a specific hash function
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    fn jwk_thumbprint(&self, hash: JwkThumbprintHash) -> Result<String, Error> {
+        let mut v = Vec::with_capacity(hash.thumbprint_length());
+        write_jwk_thumbprint_hash(self, None, hash, &mut v)?;
+        Ok(String::from_utf8(v).unwrap())
+    }
+
     /// Create a JWK of the public key
     #[cfg(feature = "alloc")]
     #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
@@ -60,18 +72,63 @@ pub trait ToJwk {
     }
 }
 
-/// Encode a key's JWK into a buffer
+/// Supported hash functions for JWK thumbprints (RFC 7638)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum JwkThumbprintHash {
+    /// SHA-256
+    #[default]
+    Sha256,
+    /// SHA-384
+    Sha384,
+    /// SHA-512
+    Sha512,
+}
+
+impl JwkThumbprintHash {
+    /// The length of the base64-URL encoded thumbprint
+    pub const fn thumbprint_length(&self) -> usize {
+        match self {
+            Self::Sha256 => 43,
+            Self::Sha384 => 64,
+            Self::Sha512 => 86,
+        }
+    }
+}
+
+/// Encode a key's JWK thumbprint into a buffer
 pub fn write_jwk_thumbprint<K: ToJwk + ?Sized>(
     key: &K,
     alg: Option<KeyAlg>,
     output: &mut dyn WriteBuffer,
 ) -> Result<(), Error> {
-    let mut hasher = HashBuffer::<Sha256>::new();
+    write_jwk_thumbprint_hash(key, alg, JwkThumbprintHash::Sha256, output)
+}
+
+/// Encode a key's JWK thumbprint into a buffer using a specific hash function
+pub fn write_jwk_thumbprint_hash<K: ToJwk + ?Sized>(
+    key: &K,
+    alg: Option<KeyAlg>,
+    hash: JwkThumbprintHash,
+    output: &mut dyn WriteBuffer,
+) -> Result<(), Error> {
+    match hash {
+        JwkThumbprintHash::Sha256 => thumbprint_digest::<Sha256, K>(key, alg, output),
+        JwkThumbprintHash::Sha384 => thumbprint_digest::<Sha384, K>(key, alg, output),
+        JwkThumbprintHash::Sha512 => thumbprint_digest::<Sha512, K>(key, alg, output),
+    }
+}
+
+fn thumbprint_digest<D: Debug + Digest, K: ToJwk + ?Sized>(
+    key: &K,
+    alg: Option<KeyAlg>,
+    output: &mut dyn WriteBuffer,
+) -> Result<(), Error> {
+    let mut hasher = HashBuffer::<D>::new();
     let mut buf = JwkBufferEncoder::new(&mut hasher, JwkEncoderMode::Thumbprint).alg(alg);
     key.encode_jwk(&mut buf)?;
     buf.finalize()?;
     let hash = hasher.finalize();
-    let mut buf = [0u8; 43];
+    let mut buf = [0u8; 86];
     let len = base64::engine::general_purpose::URL_SAFE_NO_PAD
         .encode_slice(hash, &mut buf)
         .map_err(|_| err_msg!(Unexpected, "Base64 encoding error"))?;
@@ -82,6 +82,51 @@ impl KeyOps {
     }
 }
 
+/// Supported JWK public key uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+pub enum JwkKeyUse {
+    /// The key is used for signatures
+    Signature,
+    /// The key is used for encryption
+    Encryption,
+}
+
+impl JwkKeyUse {
+    /// String representation of the key use
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Signature => "sig",
+            Self::Encryption => "enc",
+        }
+    }
+
+    /// Parse a key use from a string reference
+    pub fn try_from_str(key_use: &str) -> Option<Self> {
+        match key_use {
+            "sig" => Some(Self::Signature),
+            "enc" => Some(Self::Encryption),
+            _ => None,
+        }
+    }
+
+    /// Get the key operations associated with the key use
+    pub fn key_ops(&self) -> KeyOpsSet {
+        match self {
+            Self::Signature => KeyOps::Sign | KeyOps::Verify,
+            Self::Encryption => {
+                KeyOps::Encrypt | KeyOps::Decrypt | KeyOps::WrapKey | KeyOps::UnwrapKey
+            }
+        }
+    }
+}
+
+impl Display for JwkKeyUse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl BitOr<Self> for KeyOps {
     type Output = KeyOpsSet;
 
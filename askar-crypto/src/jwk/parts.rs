@@ -11,7 +11,7 @@ use serde::{
     ser::{Serialize, SerializeMap, Serializer},
 };
 
-use super::ops::{KeyOps, KeyOpsSet};
+use super::ops::{JwkKeyUse, KeyOpsSet};
 use crate::error::Error;
 
 /// A parsed JWK
@@ -34,6 +34,24 @@ pub struct JwkParts<'a> {
     pub d: OptAttr<'a>,
     /// Used by symmetric keys like AES
     pub k: OptAttr<'a>,
+    /// RSA modulus
+    pub n: OptAttr<'a>,
+    /// RSA public exponent
+    pub e: OptAttr<'a>,
+    /// RSA first prime factor
+    pub p: OptAttr<'a>,
+    /// RSA second prime factor
+    pub q: OptAttr<'a>,
+    /// RSA first factor CRT exponent
+    pub dp: OptAttr<'a>,
+    /// RSA second factor CRT exponent
+    pub dq: OptAttr<'a>,
+    /// RSA first CRT coefficient
+    pub qi: OptAttr<'a>,
+    /// Algorithm key public key bytes (`pub`)
+    pub pub_key: OptAttr<'a>,
+    /// Algorithm key private key bytes (`priv`)
+    pub priv_key: OptAttr<'a>,
     /// Recognized key operations
     pub key_ops: Option<KeyOpsSet>,
 }
@@ -151,6 +169,15 @@ impl<'de> Visitor<'de> for JwkMapVisitor<'de> {
         let mut y = None;
         let mut d = None;
         let mut k = None;
+        let mut n = None;
+        let mut e = None;
+        let mut p = None;
+        let mut q = None;
+        let mut dp = None;
+        let mut dq = None;
+        let mut qi = None;
+        let mut pub_key = None;
+        let mut priv_key = None;
         let mut key_ops = None;
 
         while let Some(key) = access.next_key::<&str>()? {
@@ -163,16 +190,18 @@ impl<'de> Visitor<'de> for JwkMapVisitor<'de> {
                 "y" => y = Some(access.next_value()?),
                 "d" => d = Some(access.next_value()?),
                 "k" => k = Some(access.next_value()?),
+                "n" => n = Some(access.next_value()?),
+                "e" => e = Some(access.next_value()?),
+                "p" => p = Some(access.next_value()?),
+                "q" => q = Some(access.next_value()?),
+                "dp" => dp = Some(access.next_value()?),
+                "dq" => dq = Some(access.next_value()?),
+                "qi" => qi = Some(access.next_value()?),
+                "pub" => pub_key = Some(access.next_value()?),
+                "priv" => priv_key = Some(access.next_value()?),
                 "use" => {
-                    let ops = match access.next_value()? {
-                        "enc" => {
-                            KeyOps::Encrypt | KeyOps::Decrypt | KeyOps::WrapKey | KeyOps::UnwrapKey
-                        }
-                        "sig" => KeyOps::Sign | KeyOps::Verify,
-                        _ => KeyOpsSet::new(),
-                    };
-                    if !ops.is_empty() {
-                        key_ops = Some(key_ops.unwrap_or_default() | ops);
+                    if let Some(key_use) = JwkKeyUse::try_from_str(access.next_value()?) {
+                        key_ops = Some(key_ops.unwrap_or_default() | key_use.key_ops());
                     }
                 }
                 "key_ops" => key_ops = Some(access.next_value()?),
@@ -190,6 +219,15 @@ impl<'de> Visitor<'de> for JwkMapVisitor<'de> {
                 y: y.into(),
                 d: d.into(),
                 k: k.into(),
+                n: n.into(),
+                e: e.into(),
+                p: p.into(),
+                q: q.into(),
+                dp: dp.into(),
+                dq: dq.into(),
+                qi: qi.into(),
+                pub_key: pub_key.into(),
+                priv_key: priv_key.into(),
                 key_ops,
             })
         } else {
@@ -222,6 +260,15 @@ impl Serialize for JwkParts<'_> {
         if let Some(d) = self.d.as_opt_str() {
             map.serialize_entry("d", d)?;
         }
+        if let Some(dp) = self.dp.as_opt_str() {
+            map.serialize_entry("dp", dp)?;
+        }
+        if let Some(dq) = self.dq.as_opt_str() {
+            map.serialize_entry("dq", dq)?;
+        }
+        if let Some(e) = self.e.as_opt_str() {
+            map.serialize_entry("e", e)?;
+        }
         if let Some(k) = self.k.as_opt_str() {
             map.serialize_entry("k", k)?;
         }
@@ -229,6 +276,24 @@ impl Serialize for JwkParts<'_> {
             map.serialize_entry("kid", kid)?;
         }
         map.serialize_entry("kty", self.kty)?;
+        if let Some(n) = self.n.as_opt_str() {
+            map.serialize_entry("n", n)?;
+        }
+        if let Some(p) = self.p.as_opt_str() {
+            map.serialize_entry("p", p)?;
+        }
+        if let Some(priv_key) = self.priv_key.as_opt_str() {
+            map.serialize_entry("priv", priv_key)?;
+        }
+        if let Some(pub_key) = self.pub_key.as_opt_str() {
+            map.serialize_entry("pub", pub_key)?;
+        }
+        if let Some(q) = self.q.as_opt_str() {
+            map.serialize_entry("q", q)?;
+        }
+        if let Some(qi) = self.qi.as_opt_str() {
+            map.serialize_entry("qi", qi)?;
+        }
         if let Some(x) = self.x.as_opt_str() {
             map.serialize_entry("x", x)?;
         }
@@ -245,6 +310,26 @@ impl Serialize for JwkParts<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::jwk::KeyOps;
+
+    #[test]
+    fn parse_sample_rsa() {
+        // from RFC 7517 appendix A.1
+        let jwk = r#"{
+            "kty": "RSA",
+            "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+            "e": "AQAB",
+            "alg": "RS256",
+            "kid": "2011-04-29"
+        }"#;
+        let parts = JwkParts::try_from_str(jwk).unwrap();
+        assert_eq!(parts.kty, "RSA");
+        assert_eq!(parts.e, Some("AQAB"));
+        assert!(parts.n.is_some());
+        assert_eq!(parts.crv, None);
+        assert_eq!(parts.d, None);
+        assert_eq!(parts.p, None);
+    }
 
     #[test]
     fn parse_sample_okp() {
@@ -267,6 +352,7 @@ mod tests {
         assert_eq!(parts.y, None);
         assert_eq!(parts.d, Some("nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A"));
         assert_eq!(parts.k, None);
+        assert_eq!(parts.n, None);
         assert_eq!(parts.key_ops, Some(KeyOps::Sign | KeyOps::Verify));
 
         // check serialization
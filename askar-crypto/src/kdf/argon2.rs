@@ -20,6 +20,7 @@ pub const PARAMS_INTERACTIVE: Params = Params {
     version: Version::V0x13,
     mem_cost: 32768,
     time_cost: 4,
+    parallelism: 1,
 };
 /// Standard parameters for 'moderate' level
 pub const PARAMS_MODERATE: Params = Params {
@@ -27,6 +28,7 @@ pub const PARAMS_MODERATE: Params = Params {
     version: Version::V0x13,
     mem_cost: 131072,
     time_cost: 6,
+    parallelism: 1,
 };
 
 /// Parameters to the argon2 key derivation
@@ -36,6 +38,44 @@ pub struct Params {
     version: Version,
     mem_cost: u32,
     time_cost: u32,
+    parallelism: u32,
+}
+
+impl Params {
+    /// Create a new set of parameters, checking that they are within the
+    /// supported bounds
+    pub fn new(
+        alg: Algorithm,
+        version: Version,
+        mem_cost: u32,
+        time_cost: u32,
+        parallelism: u32,
+    ) -> Result<Self, Error> {
+        argon2::Params::new(mem_cost, time_cost, parallelism, None)
+            .map_err(|_| err_msg!(Usage, "Invalid argon2 parameters"))?;
+        Ok(Self {
+            alg,
+            version,
+            mem_cost,
+            time_cost,
+            parallelism,
+        })
+    }
+
+    /// Accessor for the memory cost in KiB
+    pub fn mem_cost(&self) -> u32 {
+        self.mem_cost
+    }
+
+    /// Accessor for the number of iterations
+    pub fn time_cost(&self) -> u32 {
+        self.time_cost
+    }
+
+    /// Accessor for the degree of parallelism
+    pub fn parallelism(&self) -> u32 {
+        self.parallelism
+    }
 }
 
 /// Struct wrapping the KDF functionality
@@ -71,11 +111,14 @@ impl KeyDerivation for Argon2<'_> {
         let mut pbuild = argon2::ParamsBuilder::new();
         pbuild
             .m_cost(self.params.mem_cost)
-            .t_cost(self.params.time_cost);
+            .t_cost(self.params.time_cost)
+            .p_cost(self.params.parallelism);
         argon2::Argon2::new(
             self.params.alg,
             self.params.version,
-            pbuild.build().unwrap(),
+            pbuild
+                .build()
+                .map_err(|_| err_msg!(Usage, "Invalid argon2 parameters"))?,
         )
         .hash_password_into(self.password, self.salt, key_output)
         .map_err(|_| err_msg!(Unexpected, "Error deriving key"))
@@ -100,4 +143,13 @@ mod tests {
             hex!("9ef87bcf828c46c0136a0d1d9e391d713f75b327c6dc190455bd36c1bae33259")
         );
     }
+
+    #[test]
+    fn custom_params() {
+        let params = Params::new(Algorithm::Argon2i, Version::V0x13, 32768, 4, 1).unwrap();
+        assert_eq!(params, PARAMS_INTERACTIVE);
+        assert_eq!(params.parallelism(), 1);
+        assert!(Params::new(Algorithm::Argon2i, Version::V0x13, 32768, 0, 1).is_err());
+        assert!(Params::new(Algorithm::Argon2i, Version::V0x13, 4, 1, 1).is_err());
+    }
 }
@@ -1,10 +1,13 @@
 //! ECDH-1PU key derivation
 
+use core::marker::PhantomData;
+
+use digest::{Digest, FixedOutputReset};
 use sha2::Sha256;
-use zeroize::Zeroize;
+use zeroize::Zeroizing;
 
 use super::{
-    concat::{ConcatKDFHash, ConcatKDFParams},
+    concat::{ConcatKDF, ConcatKDFParams},
     KeyDerivation, KeyExchange,
 };
 use crate::{
@@ -12,9 +15,14 @@ use crate::{
     error::Error,
 };
 
+/// The maximum supported length of the combined shared secret Ze || Zs (P-521)
+const MAX_SECRET_LENGTH: usize = 132;
+
 /// An instantiation of the ECDH-1PU key derivation
+///
+/// The ConcatKDF hash function defaults to SHA-256.
 #[derive(Debug)]
-pub struct Ecdh1PU<'d, Key: KeyExchange + ?Sized> {
+pub struct Ecdh1PU<'d, Key: KeyExchange + ?Sized, H = Sha256> {
     ephem_key: &'d Key,
     send_key: &'d Key,
     recip_key: &'d Key,
@@ -23,6 +31,7 @@ pub struct Ecdh1PU<'d, Key: KeyExchange + ?Sized> {
     apv: &'d [u8],
     cc_tag: &'d [u8],
     receive: bool,
+    _hash: PhantomData<H>,
 }
 
 impl<'d, Key: KeyExchange + ?Sized> Ecdh1PU<'d, Key> {
@@ -47,33 +56,52 @@ impl<'d, Key: KeyExchange + ?Sized> Ecdh1PU<'d, Key> {
             apv,
             cc_tag,
             receive,
+            _hash: PhantomData,
+        }
+    }
+}
+
+impl<'d, Key: KeyExchange + ?Sized, H> Ecdh1PU<'d, Key, H> {
+    /// Use an alternative hash function for the ConcatKDF
+    pub fn with_hash<T>(self) -> Ecdh1PU<'d, Key, T> {
+        Ecdh1PU {
+            ephem_key: self.ephem_key,
+            send_key: self.send_key,
+            recip_key: self.recip_key,
+            alg: self.alg,
+            apu: self.apu,
+            apv: self.apv,
+            cc_tag: self.cc_tag,
+            receive: self.receive,
+            _hash: PhantomData,
         }
     }
 }
 
-impl<Key: KeyExchange + ?Sized> KeyDerivation for Ecdh1PU<'_, Key> {
+impl<Key, H> KeyDerivation for Ecdh1PU<'_, Key, H>
+where
+    Key: KeyExchange + ?Sized,
+    H: Digest + FixedOutputReset,
+{
     fn derive_key_bytes(&mut self, key_output: &mut [u8]) -> Result<(), Error> {
         let output_len = key_output.len();
-        // one-pass KDF only produces 256 bits of output
-        if output_len > 32 {
-            return Err(err_msg!(Unsupported, "Exceeded maximum output length"));
-        }
         if self.cc_tag.len() > 128 {
             return Err(err_msg!(Unsupported, "Exceeded maximum length for cc_tag"));
         }
-        let mut kdf = ConcatKDFHash::<Sha256>::new();
-        kdf.start_pass();
 
-        // hash Zs and Ze directly into the KDF
+        // Z is the concatenation of Ze and Zs
+        let mut z = Zeroizing::new([0u8; MAX_SECRET_LENGTH]);
+        let mut z_w = Writer::from_slice(&mut z[..]);
         if self.receive {
             self.recip_key
-                .write_key_exchange(self.ephem_key, &mut kdf)?;
-            self.recip_key.write_key_exchange(self.send_key, &mut kdf)?;
+                .write_key_exchange(self.ephem_key, &mut z_w)?;
+            self.recip_key.write_key_exchange(self.send_key, &mut z_w)?;
         } else {
             self.ephem_key
-                .write_key_exchange(self.recip_key, &mut kdf)?;
-            self.send_key.write_key_exchange(self.recip_key, &mut kdf)?;
+                .write_key_exchange(self.recip_key, &mut z_w)?;
+            self.send_key.write_key_exchange(self.recip_key, &mut z_w)?;
         }
+        let z_len = z_w.position();
 
         // the authentication tag is appended to pub_info, if any.
         let mut pub_info = [0u8; 132];
@@ -84,19 +112,17 @@ impl<Key: KeyExchange + ?Sized> KeyDerivation for Ecdh1PU<'_, Key> {
             pub_w.buffer_write(self.cc_tag)?;
         }
 
-        kdf.hash_params(ConcatKDFParams {
-            alg: self.alg,
-            apu: self.apu,
-            apv: self.apv,
-            pub_info: pub_w.as_ref(),
-            prv_info: &[],
-        });
-
-        let mut key = kdf.finish_pass();
-        key_output.copy_from_slice(&key[..output_len]);
-        key.zeroize();
-
-        Ok(())
+        ConcatKDF::<H>::derive_key(
+            &z[..z_len],
+            ConcatKDFParams {
+                alg: self.alg,
+                apu: self.apu,
+                apv: self.apv,
+                pub_info: pub_w.as_ref(),
+                prv_info: &[],
+            },
+            key_output,
+        )
     }
 }
 
@@ -1,17 +1,25 @@
 //! ECDH-ES key derivation
 
+use core::marker::PhantomData;
+
+use digest::{Digest, FixedOutputReset};
 use sha2::Sha256;
-use zeroize::Zeroize;
+use zeroize::Zeroizing;
 
 use super::{
-    concat::{ConcatKDFHash, ConcatKDFParams},
+    concat::{ConcatKDF, ConcatKDFParams},
     KeyDerivation, KeyExchange,
 };
-use crate::error::Error;
+use crate::{buffer::Writer, error::Error};
+
+/// The maximum supported length of the shared secret Z (P-521)
+const MAX_SECRET_LENGTH: usize = 66;
 
 /// An instantiation of the ECDH-ES key derivation
+///
+/// The ConcatKDF hash function defaults to SHA-256.
 #[derive(Debug)]
-pub struct EcdhEs<'d, Key>
+pub struct EcdhEs<'d, Key, H = Sha256>
 where
     Key: KeyExchange + ?Sized,
 {
@@ -21,6 +29,7 @@ where
     apu: &'d [u8],
     apv: &'d [u8],
     receive: bool,
+    _hash: PhantomData<H>,
 }
 
 impl<'d, Key: KeyExchange + ?Sized> EcdhEs<'d, Key> {
@@ -40,42 +49,55 @@ impl<'d, Key: KeyExchange + ?Sized> EcdhEs<'d, Key> {
             apu,
             apv,
             receive,
+            _hash: PhantomData,
         }
     }
 }
 
-impl<Key: KeyExchange + ?Sized> KeyDerivation for EcdhEs<'_, Key> {
-    fn derive_key_bytes(&mut self, key_output: &mut [u8]) -> Result<(), Error> {
-        let output_len = key_output.len();
-        // one-pass KDF only produces 256 bits of output
-        if output_len > 32 {
-            return Err(err_msg!(Unsupported, "Exceeded maximum output length"));
+impl<'d, Key: KeyExchange + ?Sized, H> EcdhEs<'d, Key, H> {
+    /// Use an alternative hash function for the ConcatKDF
+    pub fn with_hash<T>(self) -> EcdhEs<'d, Key, T> {
+        EcdhEs {
+            ephem_key: self.ephem_key,
+            recip_key: self.recip_key,
+            alg: self.alg,
+            apu: self.apu,
+            apv: self.apv,
+            receive: self.receive,
+            _hash: PhantomData,
         }
-        let mut kdf = ConcatKDFHash::<Sha256>::new();
-        kdf.start_pass();
+    }
+}
 
-        // hash Z directly into the KDF
+impl<Key, H> KeyDerivation for EcdhEs<'_, Key, H>
+where
+    Key: KeyExchange + ?Sized,
+    H: Digest + FixedOutputReset,
+{
+    fn derive_key_bytes(&mut self, key_output: &mut [u8]) -> Result<(), Error> {
+        let output_len = key_output.len();
+        let mut z = Zeroizing::new([0u8; MAX_SECRET_LENGTH]);
+        let mut z_w = Writer::from_slice(&mut z[..]);
         if self.receive {
             self.recip_key
-                .write_key_exchange(self.ephem_key, &mut kdf)?;
+                .write_key_exchange(self.ephem_key, &mut z_w)?;
         } else {
             self.ephem_key
-                .write_key_exchange(self.recip_key, &mut kdf)?;
+                .write_key_exchange(self.recip_key, &mut z_w)?;
         }
-
-        kdf.hash_params(ConcatKDFParams {
-            alg: self.alg,
-            apu: self.apu,
-            apv: self.apv,
-            pub_info: &((output_len as u32) * 8).to_be_bytes(), // output length in bits
-            prv_info: &[],
-        });
-
-        let mut key = kdf.finish_pass();
-        key_output.copy_from_slice(&key[..output_len]);
-        key.zeroize();
-
-        Ok(())
+        let z_len = z_w.position();
+
+        ConcatKDF::<H>::derive_key(
+            &z[..z_len],
+            ConcatKDFParams {
+                alg: self.alg,
+                apu: self.apu,
+                apv: self.apv,
+                pub_info: &((output_len as u32) * 8).to_be_bytes(), // output length in bits
+                prv_info: &[],
+            },
+            key_output,
+        )
     }
 }
 
@@ -122,4 +144,33 @@ mod tests {
             hex!("2f3636918ddb57fe0b3569113f19c4b6c518c2843f8930f05db25cd55dee53c1")
         );
     }
+
+    #[cfg(feature = "p384")]
+    #[test]
+    fn p384_multi_pass_output() {
+        use crate::alg::p384::P384KeyPair;
+        use crate::repr::{KeyGen, KeyPublicBytes, ToPublicBytes};
+
+        let recip = P384KeyPair::random().unwrap();
+        let recip_pk = P384KeyPair::from_public_bytes(&recip.to_public_bytes().unwrap()).unwrap();
+        let ephem = P384KeyPair::random().unwrap();
+        let ephem_pk = P384KeyPair::from_public_bytes(&ephem.to_public_bytes().unwrap()).unwrap();
+
+        let mut send_output = [0u8; 64];
+        let mut recv_output = [0u8; 64];
+        EcdhEs::new(&ephem, &recip_pk, b"A256CBC-HS512", b"Alice", b"Bob", false)
+            .derive_key_bytes(&mut send_output)
+            .unwrap();
+        EcdhEs::new(&ephem_pk, &recip, b"A256CBC-HS512", b"Alice", b"Bob", true)
+            .derive_key_bytes(&mut recv_output)
+            .unwrap();
+        assert_eq!(send_output, recv_output);
+        assert_ne!(send_output[..32], send_output[32..]);
+
+        EcdhEs::new(&ephem, &recip_pk, b"A256CBC-HS512", b"Alice", b"Bob", false)
+            .with_hash::<sha2::Sha384>()
+            .derive_key_bytes(&mut recv_output)
+            .unwrap();
+        assert_ne!(send_output, recv_output);
+    }
 }
@@ -0,0 +1,206 @@
+//! HKDF key derivation (RFC 5869)
+
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+
+use digest::{core_api::BlockSizeUser, Digest};
+use hkdf::SimpleHkdf;
+use sha2::{Sha256, Sha384, Sha512};
+
+use super::KeyDerivation;
+use crate::error::Error;
+
+/// Supported hash functions for HKDF key derivation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HkdfHash {
+    /// HKDF-SHA256
+    #[default]
+    Sha256,
+    /// HKDF-SHA384
+    Sha384,
+    /// HKDF-SHA512
+    Sha512,
+    /// HKDF-SHA3-256
+    #[cfg(feature = "sha3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "sha3")))]
+    Sha3_256,
+    /// HKDF-SHA3-512
+    #[cfg(feature = "sha3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "sha3")))]
+    Sha3_512,
+}
+
+/// Parameters for deriving a key using HKDF
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HkdfParams<'p> {
+    /// The hash function
+    pub hash: HkdfHash,
+    /// The optional salt for the extract step
+    pub salt: Option<&'p [u8]>,
+    /// The context and application specific info for the expand step
+    pub info: &'p [u8],
+}
+
+impl<'p> HkdfParams<'p> {
+    /// Create a new set of HKDF-SHA256 parameters
+    pub fn new(salt: Option<&'p [u8]>, info: &'p [u8]) -> Self {
+        Self {
+            hash: HkdfHash::Sha256,
+            salt,
+            info,
+        }
+    }
+
+    /// Select the hash function
+    pub fn with_hash(self, hash: HkdfHash) -> Self {
+        Self { hash, ..self }
+    }
+
+    /// Derive key material from input key material using these parameters
+    pub fn derive_key_bytes(&self, ikm: &[u8], key_output: &mut [u8]) -> Result<(), Error> {
+        let mut derive = Hkdf::new(ikm, self.salt, self.info);
+        match self.hash {
+            HkdfHash::Sha256 => derive.derive_key_bytes(key_output),
+            HkdfHash::Sha384 => derive.with_hash::<Sha384>().derive_key_bytes(key_output),
+            HkdfHash::Sha512 => derive.with_hash::<Sha512>().derive_key_bytes(key_output),
+            #[cfg(feature = "sha3")]
+            HkdfHash::Sha3_256 => derive
+                .with_hash::<sha3::Sha3_256>()
+                .derive_key_bytes(key_output),
+            #[cfg(feature = "sha3")]
+            HkdfHash::Sha3_512 => derive
+                .with_hash::<sha3::Sha3_512>()
+                .derive_key_bytes(key_output),
+        }
+    }
+}
+
+/// An instantiation of the HKDF extract-and-expand key derivation
+///
+/// The hash function defaults to SHA-256.
+pub struct Hkdf<'d, H = Sha256> {
+    ikm: &'d [u8],
+    salt: Option<&'d [u8]>,
+    info: &'d [u8],
+    _hash: PhantomData<H>,
+}
+
+impl<'d> Hkdf<'d> {
+    /// Create a new KDF instance
+    pub fn new(ikm: &'d [u8], salt: Option<&'d [u8]>, info: &'d [u8]) -> Self {
+        Self {
+            ikm,
+            salt,
+            info,
+            _hash: PhantomData,
+        }
+    }
+}
+
+impl<'d, H> Hkdf<'d, H> {
+    /// Use an alternative hash function
+    pub fn with_hash<T>(self) -> Hkdf<'d, T> {
+        Hkdf {
+            ikm: self.ikm,
+            salt: self.salt,
+            info: self.info,
+            _hash: PhantomData,
+        }
+    }
+}
+
+impl<H> Debug for Hkdf<'_, H> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hkdf")
+            .field("salt", &self.salt)
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+impl<H> KeyDerivation for Hkdf<'_, H>
+where
+    H: Digest + BlockSizeUser + Clone,
+{
+    fn derive_key_bytes(&mut self, key_output: &mut [u8]) -> Result<(), Error> {
+        SimpleHkdf::<H>::new(self.salt, self.ikm)
+            .expand(self.info, key_output)
+            .map_err(|_| err_msg!(Usage, "Exceeded maximum output length"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // from RFC 5869 Appendix A.1
+    fn expected_sha256_output() {
+        let ikm = hex!("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b");
+        let salt = hex!("000102030405060708090a0b0c");
+        let info = hex!("f0f1f2f3f4f5f6f7f8f9");
+        let mut okm = [0u8; 42];
+        Hkdf::new(&ikm, Some(&salt), &info)
+            .derive_key_bytes(&mut okm)
+            .unwrap();
+        assert_eq!(
+            okm,
+            hex!(
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf
+                34007208d5b887185865"
+            )
+        );
+    }
+
+    #[test]
+    fn alternative_hash() {
+        let mut okm_384 = [0u8; 48];
+        let mut okm_512 = [0u8; 48];
+        Hkdf::new(b"secret", None, b"info")
+            .with_hash::<Sha384>()
+            .derive_key_bytes(&mut okm_384)
+            .unwrap();
+        Hkdf::new(b"secret", None, b"info")
+            .with_hash::<Sha512>()
+            .derive_key_bytes(&mut okm_512)
+            .unwrap();
+        assert_ne!(okm_384, okm_512);
+
+        let mut okm = [0u8; 48];
+        HkdfParams::new(None, b"info")
+            .with_hash(HkdfHash::Sha384)
+            .derive_key_bytes(b"secret", &mut okm)
+            .unwrap();
+        assert_eq!(okm, okm_384);
+
+        // the output is limited to 255 blocks
+        let mut long = [0u8; 255 * 48 + 1];
+        assert!(Hkdf::new(b"secret", None, b"info")
+            .with_hash::<Sha384>()
+            .derive_key_bytes(&mut long)
+            .is_err());
+    }
+
+    #[cfg(feature = "sha3")]
+    #[test]
+    fn sha3_output() {
+        let mut okm_sha2 = [0u8; 32];
+        let mut okm_sha3 = [0u8; 32];
+        HkdfParams::new(None, b"info")
+            .derive_key_bytes(b"secret", &mut okm_sha2)
+            .unwrap();
+        HkdfParams::new(None, b"info")
+            .with_hash(HkdfHash::Sha3_256)
+            .derive_key_bytes(b"secret", &mut okm_sha3)
+            .unwrap();
+        assert_ne!(okm_sha2, okm_sha3);
+        let mut check = [0u8; 32];
+        Hkdf::new(b"secret", None, b"info")
+            .with_hash::<sha3::Sha3_256>()
+            .derive_key_bytes(&mut check)
+            .unwrap();
+        assert_eq!(check, okm_sha3);
+    }
+}
@@ -0,0 +1,161 @@
+//! Hybrid X25519 and ML-KEM-768 key agreement
+
+use alloc::vec::Vec;
+
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use super::{
+    concat::{ConcatKDFHash, ConcatKDFParams},
+    FromKeyDerivation, KeyDerivation,
+};
+use crate::{
+    alg::x25519_ml_kem::X25519MlKem768KeyPair,
+    error::Error,
+    random::{try_default_rng, KeyMaterial},
+    repr::ToPublicBytes,
+};
+
+/// An instantiation of the hybrid X25519 and ML-KEM-768 key agreement
+///
+/// The combined shared secret, the ephemeral X25519 public key and the
+/// recipient's X25519 public key are passed through the ConcatKDF with
+/// SHA-256, following the input ordering of the X-Wing combiner.
+#[derive(Debug)]
+pub struct HybridKem<'d> {
+    recip_key: &'d X25519MlKem768KeyPair,
+    alg: &'d [u8],
+    apu: &'d [u8],
+    apv: &'d [u8],
+}
+
+impl<'d> HybridKem<'d> {
+    /// Create a new KDF instance
+    pub fn new(
+        recip_key: &'d X25519MlKem768KeyPair,
+        alg: &'d [u8],
+        apu: &'d [u8],
+        apv: &'d [u8],
+    ) -> Self {
+        Self {
+            recip_key,
+            alg,
+            apu,
+            apv,
+        }
+    }
+
+    /// Encapsulate a new key to the recipient using the default RNG
+    ///
+    /// Returns the derived key and the ciphertext to be sent to the recipient.
+    pub fn encapsulate<K: FromKeyDerivation>(&self) -> Result<(K, Vec<u8>), Error> {
+        self.encapsulate_with_rng(try_default_rng()?)
+    }
+
+    /// Encapsulate a new key to the recipient
+    pub fn encapsulate_with_rng<K: FromKeyDerivation>(
+        &self,
+        rng: impl KeyMaterial,
+    ) -> Result<(K, Vec<u8>), Error> {
+        let (secret, ciphertext) = self.recip_key.encapsulate_with_rng(rng)?;
+        let key = K::from_key_derivation(self.derivation(&secret, &ciphertext))?;
+        Ok((key, ciphertext))
+    }
+
+    /// Recover the encapsulated key using the recipient's secret key
+    pub fn decapsulate<K: FromKeyDerivation>(&self, ciphertext: &[u8]) -> Result<K, Error> {
+        let secret = self.recip_key.decapsulate(ciphertext)?;
+        K::from_key_derivation(self.derivation(&secret, ciphertext))
+    }
+
+    fn derivation<'s>(&'s self, secret: &'s [u8], ciphertext: &'s [u8]) -> HybridDerivation<'s> {
+        HybridDerivation {
+            kem: self,
+            secret,
+            ciphertext,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HybridDerivation<'d> {
+    kem: &'d HybridKem<'d>,
+    secret: &'d [u8],
+    ciphertext: &'d [u8],
+}
+
+impl KeyDerivation for HybridDerivation<'_> {
+    fn derive_key_bytes(&mut self, key_output: &mut [u8]) -> Result<(), Error> {
+        let output_len = key_output.len();
+        // one-pass KDF only produces 256 bits of output
+        if output_len > 32 {
+            return Err(err_msg!(Unsupported, "Exceeded maximum output length"));
+        }
+        let mut kdf = ConcatKDFHash::<Sha256>::new();
+        kdf.start_pass();
+
+        // ss_M || ss_X || ct_X || pk_X
+        kdf.hash_message(self.secret);
+        kdf.hash_message(&self.ciphertext[self.ciphertext.len() - 32..]);
+        self.kem
+            .recip_key
+            .x25519_key()
+            .write_public_bytes(&mut kdf)?;
+
+        kdf.hash_params(ConcatKDFParams {
+            alg: self.kem.alg,
+            apu: self.kem.apu,
+            apv: self.kem.apv,
+            pub_info: &((output_len as u32) * 8).to_be_bytes(), // output length in bits
+            prv_info: &[],
+        });
+
+        let mut key = kdf.finish_pass();
+        key_output.copy_from_slice(&key[..output_len]);
+        key.zeroize();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[cfg(feature = "aes")]
+    #[test]
+    fn encapsulate_decapsulate() {
+        use crate::{
+            alg::aes::{A256Kw, AesKey},
+            repr::{KeyGen, ToSecretBytes},
+        };
+
+        let recip = X25519MlKem768KeyPair::random().unwrap();
+        let recip_pub =
+            X25519MlKem768KeyPair::from_public_bytes(&recip.to_public_bytes().unwrap()).unwrap();
+
+        let (key, ciphertext) = HybridKem::new(&recip_pub, b"A256KW", b"Alice", b"Bob")
+            .encapsulate::<AesKey<A256Kw>>()
+            .unwrap();
+        let recv = HybridKem::new(&recip, b"A256KW", b"Alice", b"Bob")
+            .decapsulate::<AesKey<A256Kw>>(&ciphertext)
+            .unwrap();
+        assert_eq!(
+            key.to_secret_bytes().unwrap(),
+            recv.to_secret_bytes().unwrap()
+        );
+
+        // the derived key is bound to the KDF parameters
+        let other = HybridKem::new(&recip, b"A256KW", b"Alice", b"Carol")
+            .decapsulate::<AesKey<A256Kw>>(&ciphertext)
+            .unwrap();
+        assert_ne!(
+            key.to_secret_bytes().unwrap(),
+            other.to_secret_bytes().unwrap()
+        );
+        assert!(HybridKem::new(&recip_pub, b"A256KW", b"Alice", b"Bob")
+            .decapsulate::<AesKey<A256Kw>>(&ciphertext)
+            .is_err());
+    }
+}
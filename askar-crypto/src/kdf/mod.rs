@@ -8,12 +8,36 @@ use crate::{buffer::WriteBuffer, error::Error};
 #[cfg_attr(docsrs, doc(cfg(feature = "argon2")))]
 pub mod argon2;
 
+#[cfg(feature = "blake3")]
+#[cfg_attr(docsrs, doc(cfg(feature = "blake3")))]
+pub mod blake3;
+
 pub mod concat;
 
 pub mod ecdh_1pu;
 
 pub mod ecdh_es;
 
+#[cfg(feature = "hkdf")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hkdf")))]
+pub mod hkdf;
+
+#[cfg(feature = "ml_kem")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ml_kem")))]
+pub mod hybrid;
+
+#[cfg(feature = "password")]
+#[cfg_attr(docsrs, doc(cfg(feature = "password")))]
+pub mod password;
+
+#[cfg(feature = "sha3")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sha3")))]
+pub mod sha3;
+
+#[cfg(feature = "x3dh")]
+#[cfg_attr(docsrs, doc(cfg(feature = "x3dh")))]
+pub mod x3dh;
+
 /// Trait for keys supporting Diffie-Helman key exchange
 pub trait KeyExchange<Rhs: ?Sized = Self> {
     /// Perform a key exchange, writing the result to the provided buffer.
@@ -0,0 +1,465 @@
+//! Password hashing with Argon2id, scrypt and PBKDF2
+//!
+//! Password hashes are encoded in the PHC string format, for example
+//! `$argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>`, so that the algorithm and
+//! parameters may be recovered when the hash is verified.
+
+use alloc::{format, string::String};
+use core::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use base64::{display::Base64Display, engine::general_purpose::STANDARD_NO_PAD, Engine};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::{error::Error, random::try_fill_random};
+
+/// The length of the randomly generated password salt
+pub const SALT_LENGTH: usize = 16;
+
+/// The length of the encoded password hash output
+pub const HASH_LENGTH: usize = 32;
+
+const MAX_SALT_LENGTH: usize = 64;
+const MAX_HASH_LENGTH: usize = 64;
+
+/// Supported password hashing algorithms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PasswordHashAlg {
+    /// Argon2id (RFC 9106)
+    Argon2id,
+    /// scrypt (RFC 7914)
+    Scrypt,
+    /// PBKDF2 with HMAC-SHA256 (RFC 8018)
+    Pbkdf2Sha256,
+}
+
+impl PasswordHashAlg {
+    /// Get the PHC string identifier of the algorithm
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Argon2id => "argon2id",
+            Self::Scrypt => "scrypt",
+            Self::Pbkdf2Sha256 => "pbkdf2-sha256",
+        }
+    }
+}
+
+impl FromStr for PasswordHashAlg {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "argon2id" => Ok(Self::Argon2id),
+            "scrypt" => Ok(Self::Scrypt),
+            "pbkdf2-sha256" => Ok(Self::Pbkdf2Sha256),
+            _ => Err(err_msg!(Unsupported, "Unsupported password hash algorithm")),
+        }
+    }
+}
+
+impl Display for PasswordHashAlg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Predefined password hashing cost levels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PasswordProfile {
+    /// Suitable for interactive logins on constrained devices
+    Interactive,
+    /// A balance between cost and responsiveness
+    Moderate,
+    /// Suitable for long-term protection of highly sensitive secrets
+    Sensitive,
+}
+
+/// Parameters for a password hashing algorithm
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PasswordHashParams {
+    /// Argon2id parameters
+    Argon2id {
+        /// The memory cost in KiB
+        m_cost: u32,
+        /// The number of iterations
+        t_cost: u32,
+        /// The degree of parallelism
+        p_cost: u32,
+    },
+    /// scrypt parameters
+    Scrypt {
+        /// The base-2 logarithm of the CPU/memory cost
+        log_n: u8,
+        /// The block size
+        r: u32,
+        /// The degree of parallelism
+        p: u32,
+    },
+    /// PBKDF2-HMAC-SHA256 parameters
+    Pbkdf2Sha256 {
+        /// The number of iterations
+        rounds: u32,
+    },
+}
+
+impl PasswordHashParams {
+    /// Get the parameters for an algorithm at a predefined cost level
+    pub const fn profile(alg: PasswordHashAlg, profile: PasswordProfile) -> Self {
+        use PasswordProfile::*;
+        match alg {
+            PasswordHashAlg::Argon2id => {
+                let (m_cost, t_cost) = match profile {
+                    Interactive => (19456, 2),
+                    Moderate => (65536, 3),
+                    Sensitive => (262144, 4),
+                };
+                Self::Argon2id {
+                    m_cost,
+                    t_cost,
+                    p_cost: 1,
+                }
+            }
+            PasswordHashAlg::Scrypt => Self::Scrypt {
+                log_n: match profile {
+                    Interactive => 15,
+                    Moderate => 17,
+                    Sensitive => 20,
+                },
+                r: 8,
+                p: 1,
+            },
+            PasswordHashAlg::Pbkdf2Sha256 => Self::Pbkdf2Sha256 {
+                rounds: match profile {
+                    Interactive => 600_000,
+                    Moderate => 1_200_000,
+                    Sensitive => 5_000_000,
+                },
+            },
+        }
+    }
+
+    /// Accessor for the password hashing algorithm
+    pub const fn algorithm(&self) -> PasswordHashAlg {
+        match self {
+            Self::Argon2id { .. } => PasswordHashAlg::Argon2id,
+            Self::Scrypt { .. } => PasswordHashAlg::Scrypt,
+            Self::Pbkdf2Sha256 { .. } => PasswordHashAlg::Pbkdf2Sha256,
+        }
+    }
+
+    /// Derive key material from a password and salt using these parameters
+    pub fn derive(&self, password: &[u8], salt: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        match *self {
+            Self::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+            } => {
+                let params = argon2::Params::new(m_cost, t_cost, p_cost, Some(output.len()))
+                    .map_err(|_| err_msg!(Usage, "Invalid Argon2 parameters"))?;
+                argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password_into(password, salt, output)
+                    .map_err(|_| err_msg!(Unexpected, "Error deriving key"))
+            }
+            Self::Scrypt { log_n, r, p } => {
+                let params = scrypt::Params::new(log_n, r, p, output.len())
+                    .map_err(|_| err_msg!(Usage, "Invalid scrypt parameters"))?;
+                scrypt::scrypt(password, salt, &params, output)
+                    .map_err(|_| err_msg!(Unexpected, "Error deriving key"))
+            }
+            Self::Pbkdf2Sha256 { rounds } => {
+                if rounds == 0 {
+                    return Err(err_msg!(Usage, "Invalid PBKDF2 parameters"));
+                }
+                pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, rounds, output);
+                Ok(())
+            }
+        }
+    }
+
+    /// Hash a password with a random salt, producing a PHC string
+    pub fn hash_password(&self, password: &[u8]) -> Result<String, Error> {
+        let mut salt = [0u8; SALT_LENGTH];
+        try_fill_random(&mut salt)?;
+        let mut hash = [0u8; HASH_LENGTH];
+        self.derive(password, &salt, &mut hash)?;
+        let result = format!(
+            "${}${}${}${}",
+            self.algorithm(),
+            self,
+            Base64Display::new(&salt, &STANDARD_NO_PAD),
+            Base64Display::new(&hash, &STANDARD_NO_PAD)
+        );
+        hash.zeroize();
+        Ok(result)
+    }
+
+    fn parse(alg: PasswordHashAlg, version: Option<&str>, params: &str) -> Result<Self, Error> {
+        let names: &[&str] = match (alg, version) {
+            // a missing version indicates the older Argon2 version 0x10
+            (PasswordHashAlg::Argon2id, Some("v=19")) => &["m", "t", "p"],
+            (PasswordHashAlg::Argon2id, _) => {
+                return Err(err_msg!(Unsupported, "Unsupported Argon2 version"))
+            }
+            (_, Some(_)) => return Err(err_msg!(Invalid, "Invalid password hash format")),
+            (PasswordHashAlg::Scrypt, None) => &["ln", "r", "p"],
+            (PasswordHashAlg::Pbkdf2Sha256, None) => &["i"],
+        };
+        let mut values = [None; 3];
+        for param in params.split(',') {
+            let (name, value) = param
+                .split_once('=')
+                .ok_or_else(|| err_msg!(Invalid, "Invalid password hash parameters"))?;
+            let idx = names
+                .iter()
+                .position(|n| *n == name)
+                .ok_or_else(|| err_msg!(Invalid, "Unknown password hash parameter"))?;
+            let value = u32::from_str(value)
+                .map_err(|_| err_msg!(Invalid, "Invalid password hash parameters"))?;
+            if values[idx].replace(value).is_some() {
+                return Err(err_msg!(Invalid, "Duplicate password hash parameter"));
+            }
+        }
+        let get = |idx: usize| {
+            values[idx].ok_or_else(|| err_msg!(Invalid, "Missing password hash parameter"))
+        };
+        Ok(match alg {
+            PasswordHashAlg::Argon2id => Self::Argon2id {
+                m_cost: get(0)?,
+                t_cost: get(1)?,
+                p_cost: get(2)?,
+            },
+            PasswordHashAlg::Scrypt => Self::Scrypt {
+                log_n: u8::try_from(get(0)?)
+                    .map_err(|_| err_msg!(Invalid, "Invalid password hash parameters"))?,
+                r: get(1)?,
+                p: get(2)?,
+            },
+            PasswordHashAlg::Pbkdf2Sha256 => Self::Pbkdf2Sha256 { rounds: get(0)? },
+        })
+    }
+}
+
+impl Display for PasswordHashParams {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+            } => write!(f, "v=19$m={m_cost},t={t_cost},p={p_cost}"),
+            Self::Scrypt { log_n, r, p } => write!(f, "ln={log_n},r={r},p={p}"),
+            Self::Pbkdf2Sha256 { rounds } => write!(f, "i={rounds}"),
+        }
+    }
+}
+
+/// Decode the parameters of a PHC string password hash
+pub fn decode_params(phc: &str) -> Result<PasswordHashParams, Error> {
+    let mut salt = [0u8; MAX_SALT_LENGTH];
+    let mut hash = [0u8; MAX_HASH_LENGTH];
+    let (params, _, _) = decode_phc(phc, &mut salt, &mut hash)?;
+    Ok(params)
+}
+
+/// Verify a password against a PHC string password hash
+pub fn verify_password(password: &[u8], phc: &str) -> Result<bool, Error> {
+    let mut salt = [0u8; MAX_SALT_LENGTH];
+    let mut hash = [0u8; MAX_HASH_LENGTH];
+    let (params, salt, hash) = decode_phc(phc, &mut salt, &mut hash)?;
+    let mut check = [0u8; MAX_HASH_LENGTH];
+    let result = params
+        .derive(password, salt, &mut check[..hash.len()])
+        .map(|_| bool::from(check[..hash.len()].ct_eq(hash)));
+    check.zeroize();
+    hash.zeroize();
+    result
+}
+
+fn decode_phc<'b>(
+    phc: &str,
+    salt_buf: &'b mut [u8],
+    hash_buf: &'b mut [u8],
+) -> Result<(PasswordHashParams, &'b [u8], &'b mut [u8]), Error> {
+    let invalid = || err_msg!(Invalid, "Invalid password hash format");
+    let mut parts = phc.split('$');
+    if parts.next() != Some("") {
+        return Err(invalid());
+    }
+    let alg = PasswordHashAlg::from_str(parts.next().unwrap_or_default())?;
+    let mut field = parts.next();
+    let version = match field {
+        Some(v) if v.starts_with("v=") => {
+            field = parts.next();
+            Some(v)
+        }
+        _ => None,
+    };
+    let params = PasswordHashParams::parse(alg, version, field.ok_or_else(invalid)?)?;
+    let (salt, hash) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(salt), Some(hash), None) => (salt, hash),
+        _ => return Err(invalid()),
+    };
+    let salt_len = STANDARD_NO_PAD
+        .decode_slice(salt, salt_buf)
+        .map_err(|_| err_msg!(Invalid, "Invalid password hash salt"))?;
+    let hash_len = STANDARD_NO_PAD
+        .decode_slice(hash, hash_buf)
+        .map_err(|_| err_msg!(Invalid, "Invalid password hash output"))?;
+    if hash_len < 16 {
+        return Err(err_msg!(Invalid, "Invalid password hash output"));
+    }
+    Ok((params, &salt_buf[..salt_len], &mut hash_buf[..hash_len]))
+}
+
+/// Select parameters for an algorithm such that hashing a password takes
+/// approximately the target duration on the current device
+///
+/// Argon2id keeps the memory cost of the `Interactive` profile and scales the
+/// number of iterations, scrypt scales the CPU/memory cost, and PBKDF2 scales
+/// the number of iterations. The result never falls below the `Interactive`
+/// profile.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub fn calibrate(
+    alg: PasswordHashAlg,
+    target: std::time::Duration,
+) -> Result<PasswordHashParams, Error> {
+    let base = match PasswordHashParams::profile(alg, PasswordProfile::Interactive) {
+        PasswordHashParams::Argon2id { m_cost, p_cost, .. } => PasswordHashParams::Argon2id {
+            m_cost,
+            t_cost: 1,
+            p_cost,
+        },
+        PasswordHashParams::Scrypt { r, p, .. } => PasswordHashParams::Scrypt { log_n: 10, r, p },
+        PasswordHashParams::Pbkdf2Sha256 { .. } => {
+            PasswordHashParams::Pbkdf2Sha256 { rounds: 10_000 }
+        }
+    };
+    let mut output = [0u8; HASH_LENGTH];
+    let start = std::time::Instant::now();
+    base.derive(b"calibration", &[0u8; SALT_LENGTH], &mut output)?;
+    // the cost of each algorithm is approximately linear in the scaled parameter
+    let scale = target.as_secs_f64() / start.elapsed().as_secs_f64().max(1e-6);
+    let scale_u32 = |value: u32| (value as f64 * scale).clamp(1.0, u32::MAX as f64) as u32;
+    let params = match base {
+        PasswordHashParams::Argon2id {
+            m_cost,
+            t_cost,
+            p_cost,
+        } => PasswordHashParams::Argon2id {
+            m_cost,
+            t_cost: scale_u32(t_cost).max(2),
+            p_cost,
+        },
+        PasswordHashParams::Scrypt { log_n, r, p } => PasswordHashParams::Scrypt {
+            log_n: (log_n as f64 + scale.log2().round()).clamp(15.0, 24.0) as u8,
+            r,
+            p,
+        },
+        PasswordHashParams::Pbkdf2Sha256 { rounds } => PasswordHashParams::Pbkdf2Sha256 {
+            rounds: scale_u32(rounds).max(600_000),
+        },
+    };
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pbkdf2_expected() {
+        // from RFC 7914 section 11
+        let mut output = [0u8; 64];
+        PasswordHashParams::Pbkdf2Sha256 { rounds: 1 }
+            .derive(b"passwd", b"salt", &mut output)
+            .unwrap();
+        assert_eq!(
+            output,
+            hex!(
+                "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc
+                49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+            )
+        );
+    }
+
+    #[test]
+    fn scrypt_expected() {
+        // from RFC 7914 section 12
+        let mut output = [0u8; 64];
+        PasswordHashParams::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 16,
+        }
+        .derive(b"password", b"NaCl", &mut output)
+        .unwrap();
+        assert_eq!(
+            output,
+            hex!(
+                "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162
+                2eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640"
+            )
+        );
+    }
+
+    #[test]
+    fn phc_round_trip() {
+        for alg in [
+            PasswordHashAlg::Argon2id,
+            PasswordHashAlg::Scrypt,
+            PasswordHashAlg::Pbkdf2Sha256,
+        ] {
+            let params = match alg {
+                PasswordHashAlg::Argon2id => PasswordHashParams::Argon2id {
+                    m_cost: 64,
+                    t_cost: 1,
+                    p_cost: 1,
+                },
+                PasswordHashAlg::Scrypt => PasswordHashParams::Scrypt {
+                    log_n: 4,
+                    r: 8,
+                    p: 1,
+                },
+                PasswordHashAlg::Pbkdf2Sha256 => PasswordHashParams::Pbkdf2Sha256 { rounds: 100 },
+            };
+            let phc = params.hash_password(b"my password").unwrap();
+            assert!(phc.starts_with(&format!("${}$", alg)));
+            assert_eq!(decode_params(&phc).unwrap(), params);
+            assert!(verify_password(b"my password", &phc).unwrap());
+            assert!(!verify_password(b"other password", &phc).unwrap());
+        }
+    }
+
+    #[test]
+    fn phc_decode() {
+        let phc = "$argon2id$v=19$m=65536,t=3,p=4$c29tZXNhbHQ$RdescudvJCsgt3ub+b+dWRWJTmaaJObG";
+        assert_eq!(
+            decode_params(phc).unwrap(),
+            PasswordHashParams::Argon2id {
+                m_cost: 65536,
+                t_cost: 3,
+                p_cost: 4
+            }
+        );
+        for invalid in [
+            "$argon2id$v=16$m=65536,t=3,p=4",
+            "$argon2id$m=65536,t=3,p=4",
+            "$argon2id$v=19$m=65536,t=3,p=4,p=4",
+            "$scrypt$ln=15,r=8",
+            "$scrypt$ln=300,r=8,p=1",
+            "$pbkdf2-sha256$i=1,x=1",
+            "$bcrypt$i=1",
+        ] {
+            let phc = format!("{}$c29tZXNhbHQ$RdescudvJCsgt3ub+b+dWRWJTmaaJObG", invalid);
+            assert!(decode_params(&phc).is_err(), "{}", invalid);
+        }
+        // the hash output is too short
+        assert!(decode_params("$pbkdf2-sha256$i=1$c29tZXNhbHQ$RdescudvJCsgt3ub").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! SHA-3 and SHAKE key derivation and seed-based key generation
+
+use core::{
+    fmt::{self, Debug, Formatter},
+    str::FromStr,
+};
+
+use sha3::{
+    digest::{ExtendableOutput, Update, XofReader},
+    Digest, Sha3_256, Sha3_512, Shake128, Shake128Reader, Shake256, Shake256Reader,
+};
+
+use super::KeyDerivation;
+use crate::{
+    alg::normalize_alg,
+    error::Error,
+    random::{KeyMaterial, RandomDet},
+};
+
+/// Supported SHA-3 family hash functions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Sha3Hash {
+    /// SHA3-256 (FIPS 202)
+    Sha3_256,
+    /// SHA3-512 (FIPS 202)
+    Sha3_512,
+    /// SHAKE128 extendable-output function (FIPS 202)
+    Shake128,
+    /// SHAKE256 extendable-output function (FIPS 202)
+    Shake256,
+}
+
+impl Sha3Hash {
+    /// Get the length of the hash output, or `None` for extendable-output
+    /// functions.
+    pub const fn output_length(&self) -> Option<usize> {
+        match self {
+            Self::Sha3_256 => Some(32),
+            Self::Sha3_512 => Some(64),
+            Self::Shake128 | Self::Shake256 => None,
+        }
+    }
+}
+
+impl FromStr for Sha3Hash {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize_alg(s)? {
+            a if a == "sha3256" => Ok(Self::Sha3_256),
+            a if a == "sha3512" => Ok(Self::Sha3_512),
+            a if a == "shake128" => Ok(Self::Shake128),
+            a if a == "shake256" => Ok(Self::Shake256),
+            _ => Err(err_msg!(Unsupported, "Unknown hash algorithm")),
+        }
+    }
+}
+
+/// Key derivation by hashing input key material and context info
+///
+/// The output of the SHA3-256 and SHA3-512 hashes is limited to the digest
+/// length, while the SHAKE functions support any output length.
+pub struct Sha3Kdf<'d> {
+    hash: Sha3Hash,
+    ikm: &'d [u8],
+    info: &'d [u8],
+}
+
+impl<'d> Sha3Kdf<'d> {
+    /// Create a new KDF instance
+    pub fn new(hash: Sha3Hash, ikm: &'d [u8], info: &'d [u8]) -> Self {
+        Self { hash, ikm, info }
+    }
+}
+
+impl Debug for Sha3Kdf<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sha3Kdf")
+            .field("hash", &self.hash)
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+impl KeyDerivation for Sha3Kdf<'_> {
+    fn derive_key_bytes(&mut self, key_output: &mut [u8]) -> Result<(), Error> {
+        if let Some(len) = self.hash.output_length() {
+            if key_output.len() > len {
+                return Err(err_msg!(Usage, "Exceeded maximum output length"));
+            }
+        }
+        match self.hash {
+            Sha3Hash::Sha3_256 => {
+                let digest = Sha3_256::new_with_prefix(self.ikm)
+                    .chain_update(self.info)
+                    .finalize();
+                key_output.copy_from_slice(&digest[..key_output.len()]);
+            }
+            Sha3Hash::Sha3_512 => {
+                let digest = Sha3_512::new_with_prefix(self.ikm)
+                    .chain_update(self.info)
+                    .finalize();
+                key_output.copy_from_slice(&digest[..key_output.len()]);
+            }
+            Sha3Hash::Shake128 => {
+                let mut xof = Shake128::default();
+                Update::update(&mut xof, self.ikm);
+                Update::update(&mut xof, self.info);
+                xof.finalize_xof().read(key_output);
+            }
+            Sha3Hash::Shake256 => {
+                let mut xof = Shake256::default();
+                Update::update(&mut xof, self.ikm);
+                Update::update(&mut xof, self.info);
+                xof.finalize_xof().read(key_output);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Deterministic key material derived from a seed using a SHA-3 family hash
+///
+/// The SHAKE functions produce the key material directly. For SHA3-256 and
+/// SHA3-512, the digest of the seed is used to initialize the deterministic
+/// generator of the default seed method.
+pub enum Sha3KeyGen {
+    /// A SHAKE128 output stream
+    Shake128(Shake128Reader),
+    /// A SHAKE256 output stream
+    Shake256(Shake256Reader),
+    /// A deterministic generator seeded by a fixed-length digest
+    Digest(RandomDet),
+}
+
+impl Sha3KeyGen {
+    /// Construct a new `Sha3KeyGen` from a seed value
+    pub fn new(hash: Sha3Hash, seed: &[u8]) -> Self {
+        match hash {
+            Sha3Hash::Sha3_256 => Self::Digest(RandomDet::new(&Sha3_256::digest(seed))),
+            Sha3Hash::Sha3_512 => Self::Digest(RandomDet::new(&Sha3_512::digest(seed))),
+            Sha3Hash::Shake128 => {
+                let mut xof = Shake128::default();
+                Update::update(&mut xof, seed);
+                Self::Shake128(xof.finalize_xof())
+            }
+            Sha3Hash::Shake256 => {
+                let mut xof = Shake256::default();
+                Update::update(&mut xof, seed);
+                Self::Shake256(xof.finalize_xof())
+            }
+        }
+    }
+}
+
+impl Debug for Sha3KeyGen {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Sha3KeyGen {{}}")
+    }
+}
+
+impl KeyMaterial for Sha3KeyGen {
+    fn read_okm(&mut self, buf: &mut [u8]) {
+        match self {
+            Self::Shake128(reader) => reader.read(buf),
+            Self::Shake256(reader) => reader.read(buf),
+            Self::Digest(rng) => rng.read_okm(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_from_str() {
+        assert_eq!(Sha3Hash::from_str("sha3_256").unwrap(), Sha3Hash::Sha3_256);
+        assert_eq!(Sha3Hash::from_str("SHAKE-256").unwrap(), Sha3Hash::Shake256);
+        assert!(Sha3Hash::from_str("sha256").is_err());
+    }
+
+    #[test]
+    // from the FIPS 202 example values for an empty message
+    fn expected_output() {
+        let mut okm = [0u8; 32];
+        Sha3Kdf::new(Sha3Hash::Sha3_256, b"", b"")
+            .derive_key_bytes(&mut okm)
+            .unwrap();
+        assert_eq!(
+            okm,
+            hex!("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a")
+        );
+        Sha3Kdf::new(Sha3Hash::Shake128, b"", b"")
+            .derive_key_bytes(&mut okm)
+            .unwrap();
+        assert_eq!(
+            okm,
+            hex!("7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26")
+        );
+
+        let mut long = [0u8; 65];
+        assert!(Sha3Kdf::new(Sha3Hash::Sha3_512, b"", b"")
+            .derive_key_bytes(&mut long)
+            .is_err());
+    }
+
+    #[test]
+    fn keygen_deterministic() {
+        for hash in [
+            Sha3Hash::Sha3_256,
+            Sha3Hash::Sha3_512,
+            Sha3Hash::Shake128,
+            Sha3Hash::Shake256,
+        ] {
+            let mut a = [0u8; 64];
+            let mut b = [0u8; 64];
+            Sha3KeyGen::new(hash, b"seed").read_okm(&mut a);
+            Sha3KeyGen::new(hash, b"seed").read_okm(&mut b);
+            assert_eq!(a, b);
+            Sha3KeyGen::new(hash, b"other").read_okm(&mut b);
+            assert_ne!(a, b);
+        }
+    }
+}
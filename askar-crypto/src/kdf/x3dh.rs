@@ -0,0 +1,242 @@
+//! X3DH initial key agreement
+//!
+//! Implements the Extended Triple Diffie-Hellman handshake used by the Signal
+//! protocol, with Ed25519 identity keys (converted to X25519 for the key
+//! exchanges) and X25519 signed and one-time prekeys. The resulting shared
+//! secret is derived with HKDF-SHA256 as a [`KeyDerivation`].
+
+use core::fmt::{self, Debug, Formatter};
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use super::{KeyDerivation, KeyExchange};
+use crate::{
+    alg::{ed25519::Ed25519KeyPair, x25519::X25519KeyPair},
+    buffer::{WriteBuffer, Writer},
+    error::Error,
+    repr::KeyPublicBytes,
+};
+
+/// The length of an encoded public key
+pub const ENCODED_KEY_LENGTH: usize = 33;
+
+/// The length of the associated data produced by the handshake
+pub const ASSOCIATED_DATA_LENGTH: usize = ENCODED_KEY_LENGTH * 2;
+
+/// The curve type prefix of an encoded X25519 public key
+const KEY_TYPE_X25519: u8 = 0x05;
+
+/// Encode an X25519 public key, as used for prekey signatures and the associated data
+pub fn encode_public_key(key: &X25519KeyPair) -> [u8; ENCODED_KEY_LENGTH] {
+    let mut enc = [0u8; ENCODED_KEY_LENGTH];
+    enc[0] = KEY_TYPE_X25519;
+    key.with_public_bytes(|pk| enc[1..].copy_from_slice(pk));
+    enc
+}
+
+/// Sign a prekey with an identity key
+pub fn sign_prekey(identity: &Ed25519KeyPair, prekey: &X25519KeyPair) -> Result<[u8; 64], Error> {
+    identity
+        .sign(&encode_public_key(prekey))
+        .ok_or_else(|| err_msg!(MissingSecretKey))
+}
+
+/// The published prekey bundle of a responder
+#[derive(Debug)]
+pub struct PreKeyBundle<'b> {
+    /// The identity key of the responder
+    pub identity_key: &'b Ed25519KeyPair,
+    /// The signed prekey of the responder
+    pub signed_prekey: &'b X25519KeyPair,
+    /// The signature over the encoded signed prekey by the identity key
+    pub signed_prekey_signature: &'b [u8],
+    /// An optional one-time prekey of the responder
+    pub one_time_prekey: Option<&'b X25519KeyPair>,
+}
+
+impl PreKeyBundle<'_> {
+    /// Verify the signature on the signed prekey
+    pub fn verify(&self) -> Result<(), Error> {
+        if self.identity_key.verify_signature(
+            &encode_public_key(self.signed_prekey),
+            self.signed_prekey_signature,
+        ) {
+            Ok(())
+        } else {
+            Err(err_msg!(Invalid, "Invalid signed prekey signature"))
+        }
+    }
+}
+
+/// The result of an X3DH handshake
+pub struct X3dh<'d> {
+    dh: Zeroizing<[u8; 160]>,
+    dh_len: usize,
+    associated_data: [u8; ASSOCIATED_DATA_LENGTH],
+    info: &'d [u8],
+}
+
+impl<'d> X3dh<'d> {
+    /// Perform the handshake as the initiator, using a new ephemeral key
+    ///
+    /// The signature on the responder's signed prekey is verified.
+    pub fn initiator(
+        identity: &Ed25519KeyPair,
+        ephemeral: &X25519KeyPair,
+        bundle: &PreKeyBundle<'_>,
+        info: &'d [u8],
+    ) -> Result<Self, Error> {
+        bundle.verify()?;
+        let ik_a = identity.to_x25519_keypair();
+        let ik_b = bundle.identity_key.to_x25519_keypair();
+        Self::derive(
+            [
+                (&ik_a, bundle.signed_prekey),
+                (ephemeral, &ik_b),
+                (ephemeral, bundle.signed_prekey),
+            ],
+            bundle.one_time_prekey.map(|opk| (ephemeral, opk)),
+            (&ik_a, &ik_b),
+            info,
+        )
+    }
+
+    /// Perform the handshake as the responder, using the initiator's
+    /// identity key and ephemeral public key
+    pub fn responder(
+        identity: &Ed25519KeyPair,
+        signed_prekey: &X25519KeyPair,
+        one_time_prekey: Option<&X25519KeyPair>,
+        initiator_identity: &Ed25519KeyPair,
+        initiator_ephemeral: &X25519KeyPair,
+        info: &'d [u8],
+    ) -> Result<Self, Error> {
+        let ik_a = initiator_identity.to_x25519_keypair();
+        let ik_b = identity.to_x25519_keypair();
+        Self::derive(
+            [
+                (signed_prekey, &ik_a),
+                (&ik_b, initiator_ephemeral),
+                (signed_prekey, initiator_ephemeral),
+            ],
+            one_time_prekey.map(|opk| (opk, initiator_ephemeral)),
+            (&ik_a, &ik_b),
+            info,
+        )
+    }
+
+    fn derive(
+        exchanges: [(&X25519KeyPair, &X25519KeyPair); 3],
+        one_time: Option<(&X25519KeyPair, &X25519KeyPair)>,
+        identities: (&X25519KeyPair, &X25519KeyPair),
+        info: &'d [u8],
+    ) -> Result<Self, Error> {
+        let mut dh = Zeroizing::new([0u8; 160]);
+        let mut w = Writer::from_slice(&mut dh[..]);
+        // the input key material is prefixed by 32 0xFF bytes
+        w.buffer_write(&[0xFF; 32])?;
+        for (sk, pk) in exchanges.into_iter().chain(one_time) {
+            sk.write_key_exchange(pk, &mut w)?;
+        }
+        let dh_len = w.position();
+
+        let mut associated_data = [0u8; ASSOCIATED_DATA_LENGTH];
+        associated_data[..ENCODED_KEY_LENGTH].copy_from_slice(&encode_public_key(identities.0));
+        associated_data[ENCODED_KEY_LENGTH..].copy_from_slice(&encode_public_key(identities.1));
+
+        Ok(Self {
+            dh,
+            dh_len,
+            associated_data,
+            info,
+        })
+    }
+
+    /// Accessor for the associated data binding the identities of both parties
+    pub fn associated_data(&self) -> &[u8] {
+        &self.associated_data[..]
+    }
+}
+
+impl Debug for X3dh<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("X3dh")
+            .field("associated_data", &&self.associated_data[..])
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+impl KeyDerivation for X3dh<'_> {
+    fn derive_key_bytes(&mut self, key_output: &mut [u8]) -> Result<(), Error> {
+        Hkdf::<Sha256>::new(Some(&[0u8; 32]), &self.dh[..self.dh_len])
+            .expand(self.info, key_output)
+            .map_err(|_| err_msg!(Usage, "Exceeded maximum output length"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::{KeyGen, ToPublicBytes};
+
+    #[test]
+    fn handshake() {
+        let alice_ik = Ed25519KeyPair::random().unwrap();
+        let bob_ik = Ed25519KeyPair::random().unwrap();
+        let bob_spk = X25519KeyPair::random().unwrap();
+        let bob_opk = X25519KeyPair::random().unwrap();
+        let sig = sign_prekey(&bob_ik, &bob_spk).unwrap();
+
+        let bob_ik_pub =
+            Ed25519KeyPair::from_public_bytes(&bob_ik.to_public_bytes().unwrap()).unwrap();
+        let bob_spk_pub =
+            X25519KeyPair::from_public_bytes(&bob_spk.to_public_bytes().unwrap()).unwrap();
+        let bob_opk_pub =
+            X25519KeyPair::from_public_bytes(&bob_opk.to_public_bytes().unwrap()).unwrap();
+
+        for one_time in [false, true] {
+            let bundle = PreKeyBundle {
+                identity_key: &bob_ik_pub,
+                signed_prekey: &bob_spk_pub,
+                signed_prekey_signature: &sig,
+                one_time_prekey: one_time.then_some(&bob_opk_pub),
+            };
+            let ephem = X25519KeyPair::random().unwrap();
+            let mut send = X3dh::initiator(&alice_ik, &ephem, &bundle, b"MyProtocol").unwrap();
+
+            let alice_ik_pub =
+                Ed25519KeyPair::from_public_bytes(&alice_ik.to_public_bytes().unwrap()).unwrap();
+            let ephem_pub =
+                X25519KeyPair::from_public_bytes(&ephem.to_public_bytes().unwrap()).unwrap();
+            let mut recv = X3dh::responder(
+                &bob_ik,
+                &bob_spk,
+                one_time.then_some(&bob_opk),
+                &alice_ik_pub,
+                &ephem_pub,
+                b"MyProtocol",
+            )
+            .unwrap();
+
+            assert_eq!(send.associated_data(), recv.associated_data());
+            let mut sk_send = [0u8; 32];
+            let mut sk_recv = [0u8; 32];
+            send.derive_key_bytes(&mut sk_send).unwrap();
+            recv.derive_key_bytes(&mut sk_recv).unwrap();
+            assert_eq!(sk_send, sk_recv);
+        }
+
+        // a prekey signed by another identity is rejected
+        let bundle = PreKeyBundle {
+            identity_key: &alice_ik,
+            signed_prekey: &bob_spk_pub,
+            signed_prekey_signature: &sig,
+            one_time_prekey: None,
+        };
+        let ephem = X25519KeyPair::random().unwrap();
+        assert!(X3dh::initiator(&alice_ik, &ephem, &bundle, b"MyProtocol").is_err());
+    }
+}
@@ -29,20 +29,92 @@ pub use self::error::{Error, ErrorKind};
 // re-export
 pub use aead::generic_array;
 
+// the accel module inspects the configuration flags of the `aes` and `chacha20` crates
+#[allow(unexpected_cfgs)]
+pub mod accel;
+
 pub mod alg;
 
 pub mod backend;
 
+#[cfg(feature = "bbs")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bbs")))]
+pub mod bbs;
+
+#[cfg(feature = "bip32")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bip32")))]
+pub mod bip32;
+
+#[cfg(feature = "bip39")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bip39")))]
+pub mod bip39;
+
 pub mod buffer;
 
+#[cfg(feature = "cose")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cose")))]
+pub mod cose;
+
+#[cfg(feature = "ecies")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ecies")))]
+pub mod ecies;
+
 pub mod encrypt;
 
+#[cfg(feature = "frost")]
+#[cfg_attr(docsrs, doc(cfg(feature = "frost")))]
+pub mod frost;
+
+#[cfg(feature = "hpke")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hpke")))]
+pub mod hpke;
+
 pub mod jwk;
 
 pub mod kdf;
 
+#[cfg(feature = "mac")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mac")))]
+pub mod mac;
+
+#[cfg(feature = "multikey")]
+#[cfg_attr(docsrs, doc(cfg(feature = "multikey")))]
+pub mod multikey;
+
+#[cfg(feature = "noise")]
+#[cfg_attr(docsrs, doc(cfg(feature = "noise")))]
+pub mod noise;
+
+#[cfg(feature = "pake")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pake")))]
+pub mod pake;
+
+#[cfg(feature = "pkcs8")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pkcs8")))]
+pub mod pkcs8;
+
 pub mod random;
 
+#[cfg(feature = "ratchet")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ratchet")))]
+pub mod ratchet;
+
+pub mod secret;
+
 pub mod sign;
 
+#[cfg(feature = "sss")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sss")))]
+pub mod sss;
+
+pub mod stream;
+
+#[cfg(feature = "unauthenticated")]
+#[cfg_attr(docsrs, doc(cfg(feature = "unauthenticated")))]
+pub mod unauthenticated;
+
 pub mod repr;
+
+#[cfg(feature = "x509")]
+#[cfg_attr(docsrs, doc(cfg(feature = "x509")))]
+pub mod x509;
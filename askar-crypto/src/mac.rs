@@ -0,0 +1,304 @@
+//! Message authentication code traits and parameters
+
+use core::str::FromStr;
+
+use cipher::{consts::U16, BlockCipher, BlockEncrypt, BlockSizeUser};
+use cmac::{Cmac, Mac};
+use sha3::{
+    digest::{ExtendableOutput, Update, XofReader},
+    CShake128, CShake128Core, CShake256, CShake256Core,
+};
+use subtle::ConstantTimeEq;
+
+#[cfg(feature = "alloc")]
+use crate::buffer::SecretBytes;
+use crate::{
+    alg::normalize_alg,
+    buffer::{WriteBuffer, Writer},
+    error::Error,
+};
+
+/// The maximum supported length of a MAC tag
+pub const MAX_MAC_LENGTH: usize = 64;
+
+/// Message authentication operations
+pub trait KeyMac {
+    /// Compute a MAC of the requested type over a message and write it
+    /// to the provided buffer.
+    ///
+    /// The customization string is only supported by the KMAC types.
+    fn write_mac(
+        &self,
+        message: &[u8],
+        mac_type: MacType,
+        customization: &[u8],
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error>;
+
+    /// Check the validity of a MAC over a message with the specified MAC
+    /// type and customization string.
+    fn verify_mac(
+        &self,
+        message: &[u8],
+        tag: &[u8],
+        mac_type: MacType,
+        customization: &[u8],
+    ) -> Result<bool, Error> {
+        let mut buf = [0u8; MAX_MAC_LENGTH];
+        let mut w = Writer::from_slice(&mut buf[..]);
+        self.write_mac(message, mac_type, customization, &mut w)?;
+        let len = w.position();
+        Ok(bool::from(buf[..len].ct_eq(tag)))
+    }
+
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    /// Compute a MAC of the requested type and return an allocated buffer.
+    fn create_mac(
+        &self,
+        message: &[u8],
+        mac_type: MacType,
+        customization: &[u8],
+    ) -> Result<SecretBytes, Error> {
+        let mut buf = SecretBytes::with_capacity(mac_type.tag_length());
+        self.write_mac(message, mac_type, customization, &mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// Supported message authentication code types
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MacType {
+    /// AES-CMAC (NIST SP 800-38B)
+    Cmac,
+    /// KMAC128 (NIST SP 800-185) with a 256-bit output
+    Kmac128,
+    /// KMAC256 (NIST SP 800-185) with a 512-bit output
+    Kmac256,
+    /// BLAKE3 keyed hash with a 256-bit key and output
+    #[cfg(feature = "blake3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blake3")))]
+    Blake3,
+}
+
+impl FromStr for MacType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize_alg(s)? {
+            a if a == "cmac" || a == "aescmac" => Ok(Self::Cmac),
+            a if a == "kmac128" => Ok(Self::Kmac128),
+            a if a == "kmac256" => Ok(Self::Kmac256),
+            #[cfg(feature = "blake3")]
+            a if a == "blake3" => Ok(Self::Blake3),
+            _ => Err(err_msg!(Unsupported, "Unknown MAC algorithm")),
+        }
+    }
+}
+
+impl MacType {
+    /// Get the length of the MAC output.
+    pub const fn tag_length(&self) -> usize {
+        match self {
+            Self::Cmac => 16,
+            Self::Kmac128 => 32,
+            Self::Kmac256 => 64,
+            #[cfg(feature = "blake3")]
+            Self::Blake3 => 32,
+        }
+    }
+}
+
+/// Compute an AES-CMAC tag with the provided block cipher key
+pub(crate) fn cmac<C>(key: &[u8], message: &[u8], out: &mut dyn WriteBuffer) -> Result<(), Error>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + Clone,
+    Cmac<C>: Mac + cmac::digest::KeyInit,
+{
+    let mut mac = <Cmac<C> as cmac::digest::KeyInit>::new_from_slice(key)
+        .map_err(|_| err_msg!(InvalidKeyData))?;
+    Mac::update(&mut mac, message);
+    out.buffer_write(&mac.finalize().into_bytes()[..])
+}
+
+/// Compute a MAC tag for any of the key-agnostic MAC types
+pub(crate) fn keyed_mac(
+    mac_type: MacType,
+    key: &[u8],
+    message: &[u8],
+    customization: &[u8],
+    out: &mut dyn WriteBuffer,
+) -> Result<(), Error> {
+    match mac_type {
+        MacType::Kmac128 | MacType::Kmac256 => kmac(mac_type, key, message, customization, out),
+        #[cfg(feature = "blake3")]
+        MacType::Blake3 => {
+            if !customization.is_empty() {
+                return Err(err_msg!(Usage, "Customization not supported for BLAKE3"));
+            }
+            let key: &[u8; 32] = key
+                .try_into()
+                .map_err(|_| err_msg!(Unsupported, "BLAKE3 MAC requires a 256-bit key"))?;
+            out.buffer_write(blake3::keyed_hash(key, message).as_bytes())
+        }
+        MacType::Cmac => Err(err_msg!(
+            Unsupported,
+            "CMAC not supported for this key type"
+        )),
+    }
+}
+
+/// Compute a KMAC128 or KMAC256 tag with the provided key
+fn kmac(
+    mac_type: MacType,
+    key: &[u8],
+    message: &[u8],
+    customization: &[u8],
+    out: &mut dyn WriteBuffer,
+) -> Result<(), Error> {
+    let mut tag = [0u8; MAX_MAC_LENGTH];
+    let tag_len = mac_type.tag_length();
+    match mac_type {
+        MacType::Kmac128 => {
+            let mut xof = CShake128::from_core(CShake128Core::new_with_function_name(
+                b"KMAC",
+                customization,
+            ));
+            kmac_absorb(&mut xof, 168, key, message, tag_len);
+            xof.finalize_xof().read(&mut tag[..tag_len]);
+        }
+        MacType::Kmac256 => {
+            let mut xof = CShake256::from_core(CShake256Core::new_with_function_name(
+                b"KMAC",
+                customization,
+            ));
+            kmac_absorb(&mut xof, 136, key, message, tag_len);
+            xof.finalize_xof().read(&mut tag[..tag_len]);
+        }
+        _ => return Err(err_msg!(Unsupported, "Unsupported KMAC type")),
+    }
+    out.buffer_write(&tag[..tag_len])
+}
+
+fn kmac_absorb(xof: &mut impl Update, rate: usize, key: &[u8], message: &[u8], tag_len: usize) {
+    // bytepad(encode_string(K), rate)
+    let mut enc = [0u8; 9];
+    let rate_enc = left_encode(rate as u64, &mut enc);
+    xof.update(rate_enc);
+    let mut padded = rate_enc.len();
+    let key_enc = left_encode(key.len() as u64 * 8, &mut enc);
+    xof.update(key_enc);
+    padded += key_enc.len() + key.len();
+    xof.update(key);
+    let zeros = [0u8; 168];
+    xof.update(&zeros[..(rate - padded % rate) % rate]);
+    xof.update(message);
+    xof.update(right_encode(tag_len as u64 * 8, &mut enc));
+}
+
+fn left_encode(value: u64, buf: &mut [u8; 9]) -> &[u8] {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count().min(7);
+    let len = 8 - skip;
+    buf[0] = len as u8;
+    buf[1..=len].copy_from_slice(&bytes[skip..]);
+    &buf[..=len]
+}
+
+fn right_encode(value: u64, buf: &mut [u8; 9]) -> &[u8] {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count().min(7);
+    let len = 8 - skip;
+    buf[..len].copy_from_slice(&bytes[skip..]);
+    buf[len] = len as u8;
+    &buf[..=len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mac_type_from_str() {
+        assert_eq!(MacType::from_str("AES-CMAC").unwrap(), MacType::Cmac);
+        assert_eq!(MacType::from_str("kmac256").unwrap(), MacType::Kmac256);
+        assert!(MacType::from_str("hmac").is_err());
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    // from the BLAKE3 test vectors, with an empty input
+    fn blake3_expected() {
+        let mut tag = [0u8; 32];
+        let mut w = Writer::from_slice(&mut tag[..]);
+        keyed_mac(
+            MacType::Blake3,
+            b"whats the Elvish word for friend",
+            &[],
+            &[],
+            &mut w,
+        )
+        .unwrap();
+        assert_eq!(
+            tag,
+            hex!("92b2b75604ed3c761f9d6f62392c8a9227ad0ea3f09573e783f1498a4ed60d26")
+        );
+
+        let mut w = Writer::from_slice(&mut tag[..]);
+        assert!(keyed_mac(MacType::Blake3, &[0u8; 16], &[], &[], &mut w).is_err());
+    }
+
+    #[test]
+    fn encode_lengths() {
+        assert_eq!(left_encode(0, &mut [0u8; 9]), &[1u8, 0]);
+        assert_eq!(left_encode(168, &mut [0u8; 9]), &[1u8, 168]);
+        assert_eq!(left_encode(256, &mut [0u8; 9]), &[2u8, 1, 0]);
+        assert_eq!(right_encode(512, &mut [0u8; 9]), &[2u8, 0, 2]);
+    }
+
+    #[test]
+    // from NIST SP 800-185 KMAC samples #1, #2 and #4
+    fn kmac_expected() {
+        let key = hex!("404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f");
+        let data = hex!("00010203");
+        let mut tag = [0u8; MAX_MAC_LENGTH];
+
+        let mut w = Writer::from_slice(&mut tag[..]);
+        kmac(MacType::Kmac128, &key, &data, b"", &mut w).unwrap();
+        assert_eq!(
+            &tag[..32],
+            &hex!("e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e")
+        );
+
+        let mut w = Writer::from_slice(&mut tag[..]);
+        kmac(
+            MacType::Kmac128,
+            &key,
+            &data,
+            b"My Tagged Application",
+            &mut w,
+        )
+        .unwrap();
+        assert_eq!(
+            &tag[..32],
+            &hex!("3b1fba963cd8b0b59e8c1a6d71888b7143651af8ba0a7070c0979e2811324aa5")
+        );
+
+        let mut w = Writer::from_slice(&mut tag[..]);
+        kmac(
+            MacType::Kmac256,
+            &key,
+            &data,
+            b"My Tagged Application",
+            &mut w,
+        )
+        .unwrap();
+        assert_eq!(
+            tag,
+            hex!(
+                "20c570c31346f703c9ac36c61c03cb64c3970d0cfc787e9b79599d273a68d2f7
+                f69d4cc3de9d104a351689f27cf6f5951f0103f33f4f24871024d9c27773a8dd"
+            )
+        );
+    }
+}
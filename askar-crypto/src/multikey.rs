@@ -0,0 +1,268 @@
+//! Multikey encoding of public keys
+//!
+//! A multikey is the base58-btc multibase encoding of a public key prefixed
+//! with the unsigned varint multicodec identifier for its key type, as used by
+//! `did:key` identifiers and Multikey verification methods.
+
+use alloc::{string::String, vec::Vec};
+
+#[cfg(feature = "rsa")]
+use crate::alg::rsa::RsaKeyPair;
+use crate::{
+    alg::{BlsCurves, EcCurves, HasKeyAlg, KeyAlg},
+    error::Error,
+    repr::KeyPublicBytes,
+};
+
+/// The multibase prefix for base58-btc encoding
+pub const MULTIBASE_BASE58_BTC: char = 'z';
+
+/// Multicodec identifier for Ed25519 public keys
+pub const MULTICODEC_ED25519_PUB: u64 = 0xed;
+/// Multicodec identifier for X25519 public keys
+pub const MULTICODEC_X25519_PUB: u64 = 0xec;
+/// Multicodec identifier for Ed448 public keys
+pub const MULTICODEC_ED448_PUB: u64 = 0x1203;
+/// Multicodec identifier for X448 public keys
+pub const MULTICODEC_X448_PUB: u64 = 0x1204;
+/// Multicodec identifier for compressed secp256k1 public keys
+pub const MULTICODEC_SECP256K1_PUB: u64 = 0xe7;
+/// Multicodec identifier for compressed P-256 public keys
+pub const MULTICODEC_P256_PUB: u64 = 0x1200;
+/// Multicodec identifier for compressed P-384 public keys
+pub const MULTICODEC_P384_PUB: u64 = 0x1201;
+/// Multicodec identifier for BLS12-381 G1 public keys
+pub const MULTICODEC_BLS12381_G1_PUB: u64 = 0xea;
+/// Multicodec identifier for BLS12-381 G2 public keys
+pub const MULTICODEC_BLS12381_G2_PUB: u64 = 0xeb;
+/// Multicodec identifier for concatenated BLS12-381 G1 and G2 public keys
+pub const MULTICODEC_BLS12381_G1G2_PUB: u64 = 0xee;
+/// Multicodec identifier for PKCS#1 DER-encoded RSA public keys
+pub const MULTICODEC_RSA_PUB: u64 = 0x1205;
+
+/// Get the multicodec identifier for a public key algorithm
+pub const fn multicodec_code(alg: KeyAlg) -> Option<u64> {
+    match alg {
+        KeyAlg::Ed25519 => Some(MULTICODEC_ED25519_PUB),
+        KeyAlg::X25519 => Some(MULTICODEC_X25519_PUB),
+        KeyAlg::Ed448 => Some(MULTICODEC_ED448_PUB),
+        KeyAlg::X448 => Some(MULTICODEC_X448_PUB),
+        KeyAlg::EcCurve(EcCurves::Secp256k1) => Some(MULTICODEC_SECP256K1_PUB),
+        KeyAlg::EcCurve(EcCurves::Secp256r1) => Some(MULTICODEC_P256_PUB),
+        KeyAlg::EcCurve(EcCurves::Secp384r1) => Some(MULTICODEC_P384_PUB),
+        KeyAlg::Bls12_381(BlsCurves::G1) => Some(MULTICODEC_BLS12381_G1_PUB),
+        KeyAlg::Bls12_381(BlsCurves::G2) => Some(MULTICODEC_BLS12381_G2_PUB),
+        KeyAlg::Bls12_381(BlsCurves::G1G2) => Some(MULTICODEC_BLS12381_G1G2_PUB),
+        KeyAlg::Rsa(_) => Some(MULTICODEC_RSA_PUB),
+        _ => None,
+    }
+}
+
+/// Look up the key algorithm for a multicodec identifier and public key
+fn multicodec_key_alg(code: u64, _public: &[u8]) -> Result<KeyAlg, Error> {
+    Ok(match code {
+        MULTICODEC_ED25519_PUB => KeyAlg::Ed25519,
+        MULTICODEC_X25519_PUB => KeyAlg::X25519,
+        MULTICODEC_ED448_PUB => KeyAlg::Ed448,
+        MULTICODEC_X448_PUB => KeyAlg::X448,
+        MULTICODEC_SECP256K1_PUB => KeyAlg::EcCurve(EcCurves::Secp256k1),
+        MULTICODEC_P256_PUB => KeyAlg::EcCurve(EcCurves::Secp256r1),
+        MULTICODEC_P384_PUB => KeyAlg::EcCurve(EcCurves::Secp384r1),
+        MULTICODEC_BLS12381_G1_PUB => KeyAlg::Bls12_381(BlsCurves::G1),
+        MULTICODEC_BLS12381_G2_PUB => KeyAlg::Bls12_381(BlsCurves::G2),
+        MULTICODEC_BLS12381_G1G2_PUB => KeyAlg::Bls12_381(BlsCurves::G1G2),
+        // the key size is determined by the encoded modulus
+        #[cfg(feature = "rsa")]
+        MULTICODEC_RSA_PUB => KeyAlg::Rsa(RsaKeyPair::from_public_bytes(_public)?.key_size()),
+        _ => return Err(err_msg!(Unsupported, "Unsupported multicodec key type")),
+    })
+}
+
+/// Encode a public key as a multikey string
+pub fn encode_multikey(alg: KeyAlg, public: &[u8]) -> Result<String, Error> {
+    let code = multicodec_code(alg)
+        .ok_or_else(|| err_msg!(Unsupported, "Unsupported key algorithm for multikey"))?;
+    Ok(encode_multibase(&encode_multicodec(code, public)))
+}
+
+/// Decode a multikey string into its key algorithm and public key bytes
+pub fn decode_multikey(multikey: &str) -> Result<(KeyAlg, Vec<u8>), Error> {
+    let mut buf = decode_multibase(multikey)?;
+    let (code, prefix_len) = read_varint(&buf)?;
+    let public = buf.split_off(prefix_len);
+    let alg = multicodec_key_alg(code, &public)?;
+    Ok((alg, public))
+}
+
+/// Encode binary data as a base58-btc multibase string
+pub fn encode_multibase(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len() * 138 / 100 + 2);
+    result.push(MULTIBASE_BASE58_BTC);
+    result.push_str(&bs58::encode(data).into_string());
+    result
+}
+
+/// Decode a base58-btc multibase string
+pub fn decode_multibase(encoded: &str) -> Result<Vec<u8>, Error> {
+    let encoded = encoded
+        .strip_prefix(MULTIBASE_BASE58_BTC)
+        .ok_or_else(|| err_msg!(Unsupported, "Unsupported multibase encoding"))?;
+    bs58::decode(encoded)
+        .into_vec()
+        .map_err(|_| err_msg!(Invalid, "Invalid base58 encoding"))
+}
+
+/// Prefix binary data with an unsigned varint multicodec identifier
+pub fn encode_multicodec(code: u64, data: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(data.len() + 3);
+    write_varint(code, &mut buf);
+    buf.extend_from_slice(data);
+    buf
+}
+
+/// Split binary data into its multicodec identifier and contents
+pub fn decode_multicodec(data: &[u8]) -> Result<(u64, &[u8]), Error> {
+    let (code, prefix_len) = read_varint(data)?;
+    Ok((code, &data[prefix_len..]))
+}
+
+fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(buf: &[u8]) -> Result<(u64, usize), Error> {
+    let mut value = 0u64;
+    // multicodec identifiers are limited to 9 bytes
+    for (idx, byte) in buf.iter().take(9).enumerate() {
+        value |= ((byte & 0x7f) as u64) << (idx * 7);
+        if byte & 0x80 == 0 {
+            if idx > 0 && *byte == 0 {
+                return Err(err_msg!(Invalid, "Invalid multicodec prefix"));
+            }
+            return Ok((value, idx + 1));
+        }
+    }
+    Err(err_msg!(Invalid, "Invalid multicodec prefix"))
+}
+
+/// Support for encoding a public key as a multikey
+pub trait ToMultikey {
+    /// Encode the public key as a multibase string with a multicodec prefix
+    fn to_multikey(&self) -> Result<String, Error>;
+}
+
+/// Support for loading a public key from a multikey
+pub trait FromMultikey: Sized {
+    /// Decode a public key from a multibase string with a multicodec prefix
+    fn from_multikey(multikey: &str) -> Result<Self, Error>;
+}
+
+impl<K: KeyPublicBytes + HasKeyAlg> ToMultikey for K {
+    fn to_multikey(&self) -> Result<String, Error> {
+        self.with_public_bytes(|public| encode_multikey(self.algorithm(), public))
+    }
+}
+
+impl<K: KeyPublicBytes + HasKeyAlg> FromMultikey for K {
+    fn from_multikey(multikey: &str) -> Result<Self, Error> {
+        let (alg, public) = decode_multikey(multikey)?;
+        let key = Self::from_public_bytes(&public)?;
+        if key.algorithm() != alg {
+            return Err(err_msg!(Unsupported, "Unexpected multikey algorithm"));
+        }
+        Ok(key)
+    }
+}
+
+#[cfg(feature = "rsa")]
+impl ToMultikey for RsaKeyPair {
+    fn to_multikey(&self) -> Result<String, Error> {
+        use crate::repr::ToPublicBytes;
+        encode_multikey(self.algorithm(), &self.to_public_bytes()?)
+    }
+}
+
+#[cfg(feature = "rsa")]
+impl FromMultikey for RsaKeyPair {
+    fn from_multikey(multikey: &str) -> Result<Self, Error> {
+        match decode_multikey(multikey)? {
+            (KeyAlg::Rsa(_), public) => Self::from_public_bytes(&public),
+            _ => Err(err_msg!(Unsupported, "Unexpected multikey algorithm")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::ToPublicBytes;
+
+    #[test]
+    fn varint_round_trip() {
+        for code in [MULTICODEC_ED25519_PUB, MULTICODEC_P256_PUB, 0x7f, 0x3fff] {
+            let mut buf = Vec::new();
+            write_varint(code, &mut buf);
+            assert_eq!(read_varint(&buf).unwrap(), (code, buf.len()));
+        }
+        let mut buf = Vec::new();
+        write_varint(MULTICODEC_P256_PUB, &mut buf);
+        assert_eq!(buf, [0x80, 0x24]);
+        assert!(read_varint(&[0x80]).is_err());
+        assert!(read_varint(&[0x80, 0x00]).is_err());
+    }
+
+    #[cfg(feature = "ed25519")]
+    #[test]
+    fn ed25519_multikey() {
+        use crate::alg::{ed25519::Ed25519KeyPair, x25519::X25519KeyPair};
+
+        // from the did:key test vectors
+        let multikey = "z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp";
+        let public = hex!("3b6a27bcceb6a42d62a3a8d02a6f0d73653215771de243a63ac048a18b59da29");
+        let key = Ed25519KeyPair::from_multikey(multikey).unwrap();
+        assert_eq!(&*key.to_public_bytes().unwrap(), &public[..]);
+        assert_eq!(key.to_multikey().unwrap(), multikey);
+        assert_eq!(
+            decode_multikey(multikey).unwrap(),
+            (KeyAlg::Ed25519, public.to_vec())
+        );
+
+        // the multicodec prefix must match the key type
+        assert!(X25519KeyPair::from_multikey(multikey).is_err());
+        assert!(Ed25519KeyPair::from_multikey(&multikey[1..]).is_err());
+    }
+
+    #[cfg(all(feature = "ec_curves", feature = "default_rng"))]
+    #[test]
+    fn ec_multikey_prefix() {
+        use crate::{
+            alg::{k256::K256KeyPair, p256::P256KeyPair, p384::P384KeyPair},
+            repr::KeyGen,
+        };
+
+        let key = P256KeyPair::random().unwrap();
+        let multikey = key.to_multikey().unwrap();
+        assert!(multikey.starts_with("zDn"));
+        assert_eq!(
+            P256KeyPair::from_multikey(&multikey)
+                .unwrap()
+                .to_public_bytes()
+                .unwrap(),
+            key.to_public_bytes().unwrap()
+        );
+        assert!(K256KeyPair::random()
+            .unwrap()
+            .to_multikey()
+            .unwrap()
+            .starts_with("zQ3s"));
+        assert!(P384KeyPair::random()
+            .unwrap()
+            .to_multikey()
+            .unwrap()
+            .starts_with("z82"));
+    }
+}
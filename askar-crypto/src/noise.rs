@@ -0,0 +1,535 @@
+//! Noise Protocol Framework handshakes
+//!
+//! Supports the `XX` and `IK` handshake patterns with X25519, ChaCha20-Poly1305
+//! and SHA-256 (for example, `Noise_XX_25519_ChaChaPoly_SHA256`). The local
+//! static key is only used to perform key exchanges, so its secret key is never
+//! exported. A completed handshake is split into a pair of transport cipher states.
+
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use crate::{
+    alg::{
+        chacha20::{Chacha20Key, C20P},
+        x25519::X25519KeyPair,
+    },
+    buffer::SecretBytes,
+    encrypt::KeyAeadInPlace,
+    error::Error,
+    kdf::KeyExchange,
+    repr::{KeyGen, KeyPublicBytes, KeySecretBytes},
+};
+
+/// The maximum length of a Noise message
+pub const MAX_MESSAGE_LENGTH: usize = 65535;
+
+const DH_LENGTH: usize = 32;
+const HASH_LENGTH: usize = 32;
+const TAG_LENGTH: usize = 16;
+
+/// Supported Noise handshake patterns
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NoisePattern {
+    /// Mutual authentication with static keys transmitted during the handshake
+    XX,
+    /// Mutual authentication where the initiator knows the responder's static key
+    IK,
+}
+
+impl NoisePattern {
+    /// Get the full protocol name
+    pub const fn protocol_name(&self) -> &'static str {
+        match self {
+            Self::XX => "Noise_XX_25519_ChaChaPoly_SHA256",
+            Self::IK => "Noise_IK_25519_ChaChaPoly_SHA256",
+        }
+    }
+
+    const fn messages(&self) -> &'static [&'static [Token]] {
+        use Token::*;
+        match self {
+            Self::XX => &[&[E], &[E, EE, S, ES], &[S, SE]],
+            Self::IK => &[&[E, ES, S, SS], &[E, EE, SE]],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token {
+    E,
+    S,
+    EE,
+    ES,
+    SE,
+    SS,
+}
+
+/// A cipher state used for handshake or transport encryption
+pub struct CipherState {
+    key: Option<[u8; 32]>,
+    nonce: u64,
+}
+
+impl CipherState {
+    fn new(key: Option<[u8; 32]>) -> Self {
+        Self { key, nonce: 0 }
+    }
+
+    /// Check whether the cipher state has been initialized with a key
+    pub fn has_key(&self) -> bool {
+        self.key.is_some()
+    }
+
+    fn nonce_bytes(&self) -> Result<[u8; 12], Error> {
+        if self.nonce == u64::MAX {
+            return Err(err_msg!(Usage, "Message limit reached"));
+        }
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.nonce.to_le_bytes());
+        Ok(nonce)
+    }
+
+    /// Encrypt a message with associated data
+    pub fn encrypt_with_ad(&mut self, ad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::with_capacity(plaintext.len() + TAG_LENGTH);
+        out.extend_from_slice(plaintext);
+        if let Some(key) = self.key.as_ref() {
+            let nonce = self.nonce_bytes()?;
+            Chacha20Key::<C20P>::from_secret_bytes(key)?.encrypt_in_place(&mut out, &nonce, ad)?;
+            self.nonce += 1;
+        }
+        Ok(out)
+    }
+
+    /// Decrypt a message with associated data
+    ///
+    /// The nonce is only advanced when decryption succeeds.
+    pub fn decrypt_with_ad(&mut self, ad: &[u8], ciphertext: &[u8]) -> Result<SecretBytes, Error> {
+        let mut buf = SecretBytes::from_slice(ciphertext);
+        if let Some(key) = self.key.as_ref() {
+            let nonce = self.nonce_bytes()?;
+            Chacha20Key::<C20P>::from_secret_bytes(key)?.decrypt_in_place(&mut buf, &nonce, ad)?;
+            self.nonce += 1;
+        }
+        Ok(buf)
+    }
+
+    /// Replace the key with a new key derived from the current key
+    pub fn rekey(&mut self) -> Result<(), Error> {
+        let key = self
+            .key
+            .as_mut()
+            .ok_or_else(|| err_msg!(Usage, "Cipher state has no key"))?;
+        // encrypt 32 zero bytes using the maximum nonce value
+        let mut nonce = [0xFF; 12];
+        nonce[..4].fill(0);
+        let mut buf = SecretBytes::new_with(32, |_| ());
+        Chacha20Key::<C20P>::from_secret_bytes(&key[..])?.encrypt_in_place(
+            &mut buf,
+            &nonce,
+            &[],
+        )?;
+        key.copy_from_slice(&buf[..32]);
+        Ok(())
+    }
+}
+
+impl Debug for CipherState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CipherState")
+            .field("has_key", &self.key.is_some())
+            .field("nonce", &self.nonce)
+            .finish()
+    }
+}
+
+impl Drop for CipherState {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+struct SymmetricState {
+    cipher: CipherState,
+    ck: [u8; HASH_LENGTH],
+    h: [u8; HASH_LENGTH],
+}
+
+impl SymmetricState {
+    fn new(protocol_name: &str) -> Self {
+        let mut h = [0u8; HASH_LENGTH];
+        let name = protocol_name.as_bytes();
+        if name.len() <= HASH_LENGTH {
+            h[..name.len()].copy_from_slice(name);
+        } else {
+            h.copy_from_slice(&Sha256::digest(name));
+        }
+        Self {
+            cipher: CipherState::new(None),
+            ck: h,
+            h,
+        }
+    }
+
+    fn hkdf(&self, ikm: &[u8], out: &mut [u8]) -> Result<(), Error> {
+        Hkdf::<Sha256>::new(Some(&self.ck), ikm)
+            .expand(&[], out)
+            .map_err(|_| err_msg!(Unexpected))
+    }
+
+    fn mix_key(&mut self, ikm: &[u8]) -> Result<(), Error> {
+        let mut okm = [0u8; HASH_LENGTH * 2];
+        self.hkdf(ikm, &mut okm)?;
+        self.ck.copy_from_slice(&okm[..HASH_LENGTH]);
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&okm[HASH_LENGTH..]);
+        self.cipher = CipherState::new(Some(key));
+        key.zeroize();
+        okm.zeroize();
+        Ok(())
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        let h = Sha256::new()
+            .chain_update(self.h)
+            .chain_update(data)
+            .finalize();
+        self.h.copy_from_slice(&h);
+    }
+
+    fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let ciphertext = self.cipher.encrypt_with_ad(&self.h, plaintext)?;
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<SecretBytes, Error> {
+        let plaintext = self.cipher.decrypt_with_ad(&self.h, ciphertext)?;
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    fn split(&self) -> Result<(CipherState, CipherState), Error> {
+        let mut okm = [0u8; 64];
+        self.hkdf(&[], &mut okm)?;
+        let mut k1 = [0u8; 32];
+        let mut k2 = [0u8; 32];
+        k1.copy_from_slice(&okm[..32]);
+        k2.copy_from_slice(&okm[32..]);
+        okm.zeroize();
+        Ok((CipherState::new(Some(k1)), CipherState::new(Some(k2))))
+    }
+}
+
+impl Drop for SymmetricState {
+    fn drop(&mut self) {
+        self.ck.zeroize();
+    }
+}
+
+/// The state of a Noise handshake in progress
+pub struct NoiseHandshake<'k> {
+    pattern: NoisePattern,
+    initiator: bool,
+    s: &'k X25519KeyPair,
+    e: Option<X25519KeyPair>,
+    rs: Option<X25519KeyPair>,
+    re: Option<X25519KeyPair>,
+    symmetric: SymmetricState,
+    message_index: usize,
+}
+
+impl<'k> NoiseHandshake<'k> {
+    /// Start a new handshake
+    ///
+    /// The remote static key must be provided to the initiator of an `IK` handshake.
+    pub fn new(
+        pattern: NoisePattern,
+        initiator: bool,
+        static_key: &'k X25519KeyPair,
+        remote_static: Option<&X25519KeyPair>,
+        prologue: &[u8],
+    ) -> Result<Self, Error> {
+        let mut symmetric = SymmetricState::new(pattern.protocol_name());
+        symmetric.mix_hash(prologue);
+        let rs = match (pattern, initiator) {
+            (NoisePattern::IK, true) => {
+                let rs =
+                    remote_static.ok_or_else(|| err_msg!(Usage, "Remote static key required"))?;
+                rs.with_public_bytes(|pk| symmetric.mix_hash(pk));
+                Some(X25519KeyPair::from_public_bytes(&public_bytes(rs))?)
+            }
+            (NoisePattern::IK, false) => {
+                static_key.with_public_bytes(|pk| symmetric.mix_hash(pk));
+                None
+            }
+            _ => None,
+        };
+        Ok(Self {
+            pattern,
+            initiator,
+            s: static_key,
+            e: None,
+            rs,
+            re: None,
+            symmetric,
+            message_index: 0,
+        })
+    }
+
+    /// Check whether all handshake messages have been processed
+    pub fn is_finished(&self) -> bool {
+        self.message_index == self.pattern.messages().len()
+    }
+
+    /// Check whether the next handshake message is to be written by this party
+    pub fn is_my_turn(&self) -> bool {
+        (self.message_index % 2 == 0) == self.initiator
+    }
+
+    /// Accessor for the remote static key, once it is known
+    pub fn remote_static(&self) -> Option<&X25519KeyPair> {
+        self.rs.as_ref()
+    }
+
+    /// Accessor for the current handshake hash
+    pub fn handshake_hash(&self) -> [u8; HASH_LENGTH] {
+        self.symmetric.h
+    }
+
+    fn next_tokens(&self, write: bool) -> Result<&'static [Token], Error> {
+        if self.is_finished() {
+            return Err(err_msg!(Usage, "Handshake is already complete"));
+        }
+        if self.is_my_turn() != write {
+            return Err(err_msg!(Usage, "Unexpected handshake message order"));
+        }
+        Ok(self.pattern.messages()[self.message_index])
+    }
+
+    fn dh(&mut self, token: Token) -> Result<(), Error> {
+        let missing = || err_msg!(Usage, "Missing key for handshake");
+        let (local, remote) = match (token, self.initiator) {
+            (Token::EE, _) => (self.e.as_ref(), self.re.as_ref()),
+            (Token::ES, true) | (Token::SE, false) => (self.e.as_ref(), self.rs.as_ref()),
+            (Token::ES, false) | (Token::SE, true) => (Some(self.s), self.re.as_ref()),
+            (Token::SS, _) => (Some(self.s), self.rs.as_ref()),
+            _ => return Err(err_msg!(Unexpected)),
+        };
+        let shared = local
+            .ok_or_else(missing)?
+            .key_exchange_bytes(remote.ok_or_else(missing)?)?;
+        self.symmetric.mix_key(&shared)
+    }
+
+    /// Write the next handshake message, including an optional payload
+    pub fn write_message(&mut self, payload: &[u8]) -> Result<Vec<u8>, Error> {
+        let tokens = self.next_tokens(true)?;
+        let mut out = Vec::new();
+        for token in tokens {
+            match token {
+                Token::E => {
+                    let e = X25519KeyPair::random()?;
+                    let pk = public_bytes(&e);
+                    out.extend_from_slice(&pk);
+                    self.symmetric.mix_hash(&pk);
+                    self.e.replace(e);
+                }
+                Token::S => {
+                    let ct = self.symmetric.encrypt_and_hash(&public_bytes(self.s))?;
+                    out.extend_from_slice(&ct);
+                }
+                dh => self.dh(*dh)?,
+            }
+        }
+        out.extend_from_slice(&self.symmetric.encrypt_and_hash(payload)?);
+        if out.len() > MAX_MESSAGE_LENGTH {
+            return Err(err_msg!(Usage, "Exceeded maximum message length"));
+        }
+        self.message_index += 1;
+        Ok(out)
+    }
+
+    /// Read the next handshake message, returning the decrypted payload
+    pub fn read_message(&mut self, message: &[u8]) -> Result<SecretBytes, Error> {
+        let tokens = self.next_tokens(false)?;
+        if message.len() > MAX_MESSAGE_LENGTH {
+            return Err(err_msg!(Invalid, "Exceeded maximum message length"));
+        }
+        let mut remain = message;
+        let mut take = |len: usize| {
+            if remain.len() < len {
+                return Err(err_msg!(Invalid, "Invalid handshake message length"));
+            }
+            let (value, rest) = remain.split_at(len);
+            remain = rest;
+            Ok(value)
+        };
+        for token in tokens {
+            match token {
+                Token::E => {
+                    let pk = take(DH_LENGTH)?;
+                    self.re.replace(X25519KeyPair::from_public_bytes(pk)?);
+                    self.symmetric.mix_hash(pk);
+                }
+                Token::S => {
+                    let len = if self.symmetric.cipher.has_key() {
+                        DH_LENGTH + TAG_LENGTH
+                    } else {
+                        DH_LENGTH
+                    };
+                    let pk = self.symmetric.decrypt_and_hash(take(len)?)?;
+                    self.rs.replace(X25519KeyPair::from_public_bytes(&pk)?);
+                }
+                dh => self.dh(*dh)?,
+            }
+        }
+        let payload = self.symmetric.decrypt_and_hash(remain)?;
+        self.message_index += 1;
+        Ok(payload)
+    }
+
+    /// Complete the handshake, producing the transport cipher states
+    pub fn into_transport(self) -> Result<NoiseTransport, Error> {
+        if !self.is_finished() {
+            return Err(err_msg!(Usage, "Handshake is not complete"));
+        }
+        let (c1, c2) = self.symmetric.split()?;
+        let (send, receive) = if self.initiator { (c1, c2) } else { (c2, c1) };
+        Ok(NoiseTransport {
+            send,
+            receive,
+            handshake_hash: self.symmetric.h,
+        })
+    }
+}
+
+impl Debug for NoiseHandshake<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NoiseHandshake")
+            .field("pattern", &self.pattern)
+            .field("initiator", &self.initiator)
+            .field("message_index", &self.message_index)
+            .finish()
+    }
+}
+
+/// The transport cipher states of a completed handshake
+#[derive(Debug)]
+pub struct NoiseTransport {
+    /// The cipher state for outgoing messages
+    pub send: CipherState,
+    /// The cipher state for incoming messages
+    pub receive: CipherState,
+    handshake_hash: [u8; HASH_LENGTH],
+}
+
+impl NoiseTransport {
+    /// Accessor for the final handshake hash, which may be used for channel binding
+    pub fn handshake_hash(&self) -> [u8; HASH_LENGTH] {
+        self.handshake_hash
+    }
+
+    /// Encrypt an outgoing transport message
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        if plaintext.len() + TAG_LENGTH > MAX_MESSAGE_LENGTH {
+            return Err(err_msg!(Usage, "Exceeded maximum message length"));
+        }
+        self.send.encrypt_with_ad(&[], plaintext)
+    }
+
+    /// Decrypt an incoming transport message
+    pub fn decrypt(&mut self, ciphertext: &[u8]) -> Result<SecretBytes, Error> {
+        if ciphertext.len() > MAX_MESSAGE_LENGTH {
+            return Err(err_msg!(Invalid, "Exceeded maximum message length"));
+        }
+        self.receive.decrypt_with_ad(&[], ciphertext)
+    }
+}
+
+fn public_bytes(key: &X25519KeyPair) -> [u8; DH_LENGTH] {
+    let mut pk = [0u8; DH_LENGTH];
+    key.with_public_bytes(|b| pk.copy_from_slice(b));
+    pk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transport_round_trip(mut init: NoiseTransport, mut resp: NoiseTransport) {
+        assert_eq!(init.handshake_hash(), resp.handshake_hash());
+        let msg = init.encrypt(b"hello responder").unwrap();
+        assert_eq!(&resp.decrypt(&msg).unwrap()[..], b"hello responder");
+        assert!(resp.decrypt(&msg).is_err());
+        let msg = resp.encrypt(b"hello initiator").unwrap();
+        assert_eq!(&init.decrypt(&msg).unwrap()[..], b"hello initiator");
+
+        init.send.rekey().unwrap();
+        resp.receive.rekey().unwrap();
+        let msg = init.encrypt(b"after rekey").unwrap();
+        assert_eq!(&resp.decrypt(&msg).unwrap()[..], b"after rekey");
+    }
+
+    #[test]
+    fn handshake_xx() {
+        let init_s = X25519KeyPair::random().unwrap();
+        let resp_s = X25519KeyPair::random().unwrap();
+        let mut init =
+            NoiseHandshake::new(NoisePattern::XX, true, &init_s, None, b"prologue").unwrap();
+        let mut resp =
+            NoiseHandshake::new(NoisePattern::XX, false, &resp_s, None, b"prologue").unwrap();
+
+        assert!(resp.write_message(b"").is_err());
+        let m1 = init.write_message(b"").unwrap();
+        assert_eq!(m1.len(), DH_LENGTH);
+        resp.read_message(&m1).unwrap();
+        let m2 = resp.write_message(b"payload 2").unwrap();
+        assert_eq!(&init.read_message(&m2).unwrap()[..], b"payload 2");
+        let m3 = init.write_message(b"payload 3").unwrap();
+        assert_eq!(&resp.read_message(&m3).unwrap()[..], b"payload 3");
+        assert!(init.is_finished() && resp.is_finished());
+
+        assert_eq!(
+            public_bytes(init.remote_static().unwrap()),
+            public_bytes(&resp_s)
+        );
+        assert_eq!(
+            public_bytes(resp.remote_static().unwrap()),
+            public_bytes(&init_s)
+        );
+        transport_round_trip(
+            init.into_transport().unwrap(),
+            resp.into_transport().unwrap(),
+        );
+    }
+
+    #[test]
+    fn handshake_ik() {
+        let init_s = X25519KeyPair::random().unwrap();
+        let resp_s = X25519KeyPair::random().unwrap();
+        let resp_pub = X25519KeyPair::from_public_bytes(&public_bytes(&resp_s)).unwrap();
+        assert!(NoiseHandshake::new(NoisePattern::IK, true, &init_s, None, b"").is_err());
+        let mut init =
+            NoiseHandshake::new(NoisePattern::IK, true, &init_s, Some(&resp_pub), b"").unwrap();
+        let mut resp = NoiseHandshake::new(NoisePattern::IK, false, &resp_s, None, b"").unwrap();
+
+        let m1 = init.write_message(b"early data").unwrap();
+        assert_eq!(&resp.read_message(&m1).unwrap()[..], b"early data");
+        let m2 = resp.write_message(b"").unwrap();
+        init.read_message(&m2).unwrap();
+        assert!(init.into_transport().is_ok());
+
+        // a mismatched prologue causes the handshake to fail
+        let mut init =
+            NoiseHandshake::new(NoisePattern::IK, true, &init_s, Some(&resp_pub), b"a").unwrap();
+        let mut resp = NoiseHandshake::new(NoisePattern::IK, false, &resp_s, None, b"b").unwrap();
+        let m1 = init.write_message(b"").unwrap();
+        assert!(resp.read_message(&m1).is_err());
+        assert!(resp.into_transport().is_err());
+    }
+}
@@ -0,0 +1,315 @@
+//! Password-authenticated key exchange
+//!
+//! Implements SPAKE2 (RFC 9382) over edwards25519 with SHA-256, HKDF and HMAC
+//! (`SPAKE2-edwards25519-SHA256-HKDF-HMAC`). Each party sends a single blinded
+//! point and the parties then exchange key confirmation messages, so that
+//! neither the password nor a key derived from it is ever transmitted.
+//!
+//! Because the password is hashed directly to a scalar, a low-entropy secret
+//! such as a PIN should first be hardened with a memory-hard password hash
+//! shared by both parties.
+
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_POINT,
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    traits::IsIdentity,
+};
+use hkdf::Hkdf;
+use hmac::{Mac, SimpleHmac};
+use sha2::{Digest, Sha256, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::{
+    buffer::{SecretBytes, WriteBuffer},
+    error::Error,
+    random::try_fill_random,
+};
+
+/// The length of a SPAKE2 share message
+pub const SHARE_LENGTH: usize = 32;
+
+/// The length of a key confirmation message
+pub const CONFIRMATION_LENGTH: usize = 32;
+
+/// The length of the shared secret produced by the exchange
+pub const SHARED_KEY_LENGTH: usize = 16;
+
+// point constants M and N for edwards25519, from RFC 9382 section 6
+const POINT_M: [u8; 32] = [
+    0xd0, 0x48, 0x03, 0x2c, 0x6e, 0xa0, 0xb6, 0xd6, 0x97, 0xdd, 0xc2, 0xe8, 0x6b, 0xda, 0x85, 0xa3,
+    0x3a, 0xda, 0xc9, 0x20, 0xf1, 0xbf, 0x18, 0xe1, 0xb0, 0xc6, 0xd1, 0x66, 0xa5, 0xce, 0xcd, 0xaf,
+];
+const POINT_N: [u8; 32] = [
+    0xd3, 0xbf, 0xb5, 0x18, 0xf4, 0x4f, 0x34, 0x30, 0xf2, 0x9d, 0x0c, 0x92, 0xaf, 0x50, 0x38, 0x65,
+    0xa1, 0xed, 0x32, 0x81, 0xdc, 0x69, 0xb3, 0x5d, 0xd8, 0x68, 0xba, 0x85, 0xf8, 0x86, 0xc4, 0xab,
+];
+
+/// The role of a party in a SPAKE2 exchange
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Spake2Role {
+    /// The initiating party (`A`), typically the client
+    A,
+    /// The responding party (`B`), typically the server
+    B,
+}
+
+/// The state of a SPAKE2 exchange awaiting the peer's share
+pub struct Spake2 {
+    role: Spake2Role,
+    w: Scalar,
+    x: Scalar,
+    share: [u8; SHARE_LENGTH],
+    id_a: Vec<u8>,
+    id_b: Vec<u8>,
+    aad: Vec<u8>,
+}
+
+impl Spake2 {
+    /// Start a new exchange using a (hardened) password and the identities
+    /// of both parties, which may be empty
+    ///
+    /// The associated data is bound to the key confirmation messages.
+    pub fn start(
+        role: Spake2Role,
+        password: &[u8],
+        id_a: &[u8],
+        id_b: &[u8],
+        aad: &[u8],
+    ) -> Result<Self, Error> {
+        let mut seed = [0u8; 64];
+        try_fill_random(&mut seed)?;
+        let x = Scalar::from_bytes_mod_order_wide(&seed);
+        seed.zeroize();
+        Self::start_with_scalar(role, password, id_a, id_b, aad, x)
+    }
+
+    fn start_with_scalar(
+        role: Spake2Role,
+        password: &[u8],
+        id_a: &[u8],
+        id_b: &[u8],
+        aad: &[u8],
+        x: Scalar,
+    ) -> Result<Self, Error> {
+        let mut hash = [0u8; 64];
+        hash.copy_from_slice(&Sha512::digest(password));
+        let w = Scalar::from_bytes_mod_order_wide(&hash);
+        hash.zeroize();
+        let blind = match role {
+            Spake2Role::A => decode_point(&POINT_M)?,
+            Spake2Role::B => decode_point(&POINT_N)?,
+        };
+        let share = (ED25519_BASEPOINT_POINT * x + blind * w)
+            .compress()
+            .to_bytes();
+        Ok(Self {
+            role,
+            w,
+            x,
+            share,
+            id_a: id_a.to_vec(),
+            id_b: id_b.to_vec(),
+            aad: aad.to_vec(),
+        })
+    }
+
+    /// Accessor for the role of this party
+    pub fn role(&self) -> Spake2Role {
+        self.role
+    }
+
+    /// Accessor for the share to be sent to the peer
+    pub fn share(&self) -> [u8; SHARE_LENGTH] {
+        self.share
+    }
+
+    /// Process the peer's share, producing the pending shared secret and
+    /// key confirmation messages
+    pub fn finish(self, peer_share: &[u8]) -> Result<Spake2Confirm, Error> {
+        let peer_share: &[u8; SHARE_LENGTH] = peer_share
+            .try_into()
+            .map_err(|_| err_msg!(Invalid, "Invalid SPAKE2 share length"))?;
+        let peer = decode_point(peer_share)?;
+        if peer.is_small_order() {
+            return Err(err_msg!(Invalid, "Invalid SPAKE2 share"));
+        }
+        let peer_blind = match self.role {
+            Spake2Role::A => decode_point(&POINT_N)?,
+            Spake2Role::B => decode_point(&POINT_M)?,
+        };
+        let k = ((peer - peer_blind * self.w) * self.x).mul_by_cofactor();
+        if k.is_identity() {
+            return Err(err_msg!(Invalid, "Invalid SPAKE2 share"));
+        }
+        let mut k = k.compress().to_bytes();
+        let mut w = self.w.to_bytes();
+
+        let (share_a, share_b) = match self.role {
+            Spake2Role::A => (&self.share, peer_share),
+            Spake2Role::B => (peer_share, &self.share),
+        };
+        let mut tt = SecretBytes::with_capacity(
+            self.id_a.len() + self.id_b.len() + SHARE_LENGTH * 3 + w.len() + 48,
+        );
+        for part in [
+            &self.id_a[..],
+            &self.id_b[..],
+            &share_a[..],
+            &share_b[..],
+            &k[..],
+            &w[..],
+        ] {
+            tt.buffer_write(&(part.len() as u64).to_le_bytes())?;
+            tt.buffer_write(part)?;
+        }
+        k.zeroize();
+        w.zeroize();
+
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&Sha256::digest(&tt));
+        let (ke, ka) = hash.split_at(SHARED_KEY_LENGTH);
+        let shared_key = SecretBytes::from_slice(ke);
+        let mut info = Vec::with_capacity(16 + self.aad.len());
+        info.extend_from_slice(b"ConfirmationKeys");
+        info.extend_from_slice(&self.aad);
+        let mut kc = [0u8; 32];
+        Hkdf::<Sha256>::new(None, ka)
+            .expand(&info, &mut kc)
+            .map_err(|_| err_msg!(Unexpected))?;
+        hash.zeroize();
+        let (kc_a, kc_b) = kc.split_at(16);
+        let confirm_a = confirmation_mac(kc_a, &tt);
+        let confirm_b = confirmation_mac(kc_b, &tt);
+        kc.zeroize();
+
+        let (confirmation, expected) = match self.role {
+            Spake2Role::A => (confirm_a, confirm_b),
+            Spake2Role::B => (confirm_b, confirm_a),
+        };
+        Ok(Spake2Confirm {
+            shared_key,
+            confirmation,
+            expected,
+        })
+    }
+}
+
+impl Debug for Spake2 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spake2")
+            .field("role", &self.role)
+            .field("share", &self.share)
+            .finish()
+    }
+}
+
+impl Drop for Spake2 {
+    fn drop(&mut self) {
+        self.w.zeroize();
+        self.x.zeroize();
+    }
+}
+
+/// A completed SPAKE2 exchange awaiting key confirmation
+pub struct Spake2Confirm {
+    shared_key: SecretBytes,
+    confirmation: [u8; CONFIRMATION_LENGTH],
+    expected: [u8; CONFIRMATION_LENGTH],
+}
+
+impl Spake2Confirm {
+    /// Accessor for the key confirmation message to be sent to the peer
+    pub fn confirmation(&self) -> [u8; CONFIRMATION_LENGTH] {
+        self.confirmation
+    }
+
+    /// Verify the peer's key confirmation message, releasing the shared secret
+    pub fn verify(self, peer_confirmation: &[u8]) -> Result<SecretBytes, Error> {
+        if bool::from(peer_confirmation.ct_eq(&self.expected[..])) {
+            Ok(self.shared_key)
+        } else {
+            Err(err_msg!(Invalid, "SPAKE2 key confirmation failed"))
+        }
+    }
+}
+
+impl Debug for Spake2Confirm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spake2Confirm")
+            .field("confirmation", &self.confirmation)
+            .finish()
+    }
+}
+
+fn decode_point(bytes: &[u8; 32]) -> Result<EdwardsPoint, Error> {
+    CompressedEdwardsY(*bytes)
+        .decompress()
+        .ok_or_else(|| err_msg!(Invalid, "Invalid SPAKE2 share"))
+}
+
+fn confirmation_mac(key: &[u8], tt: &[u8]) -> [u8; CONFIRMATION_LENGTH] {
+    let mut mac =
+        <SimpleHmac<Sha256> as Mac>::new_from_slice(key).expect("Incompatible HMAC key length");
+    mac.update(tt);
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(pw_a: &[u8], pw_b: &[u8]) -> (Spake2Confirm, Spake2Confirm) {
+        let a = Spake2::start(Spake2Role::A, pw_a, b"client", b"server", b"aad").unwrap();
+        let b = Spake2::start(Spake2Role::B, pw_b, b"client", b"server", b"aad").unwrap();
+        let share_a = a.share();
+        let share_b = b.share();
+        (a.finish(&share_b).unwrap(), b.finish(&share_a).unwrap())
+    }
+
+    #[test]
+    fn spake2_round_trip() {
+        let (a, b) = exchange(b"123456", b"123456");
+        let (conf_a, conf_b) = (a.confirmation(), b.confirmation());
+        assert_ne!(conf_a, conf_b);
+        let key_a = a.verify(&conf_b).unwrap();
+        let key_b = b.verify(&conf_a).unwrap();
+        assert_eq!(key_a.len(), SHARED_KEY_LENGTH);
+        assert_eq!(key_a, key_b);
+    }
+
+    #[test]
+    fn spake2_wrong_password() {
+        let (a, b) = exchange(b"123456", b"654321");
+        let (conf_a, conf_b) = (a.confirmation(), b.confirmation());
+        assert!(a.verify(&conf_b).is_err());
+        assert!(b.verify(&conf_a).is_err());
+    }
+
+    #[test]
+    fn spake2_invalid_share() {
+        let a = Spake2::start(Spake2Role::A, b"123456", b"", b"", b"").unwrap();
+        let identity = EdwardsPoint::default().compress().to_bytes();
+        assert!(a.finish(&identity).is_err());
+        let a = Spake2::start(Spake2Role::A, b"123456", b"", b"", b"").unwrap();
+        assert!(a.finish(&[0u8; 16]).is_err());
+    }
+
+    #[test]
+    fn spake2_deterministic() {
+        let a =
+            Spake2::start_with_scalar(Spake2Role::A, b"password", b"", b"", b"", Scalar::from(2u8))
+                .unwrap();
+        let b = Spake2::start_with_scalar(Spake2Role::B, b"password", b"", b"", b"", Scalar::ONE)
+            .unwrap();
+        let (share_a, share_b) = (a.share(), b.share());
+        let a = a.finish(&share_b).unwrap();
+        let b = b.finish(&share_a).unwrap();
+        assert_eq!(a.shared_key, b.shared_key);
+        assert_eq!(a.expected, b.confirmation);
+    }
+}
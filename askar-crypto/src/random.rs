@@ -1,15 +1,19 @@
 //! Support for random number generation
 
 use core::fmt::{self, Debug, Formatter};
+#[cfg(all(feature = "default_rng", not(feature = "getrandom")))]
+use core::num::NonZeroU32;
 
 use aead::generic_array::{typenum::Unsigned, GenericArray};
 use chacha20::{
     cipher::{KeyIvInit, KeySizeUser, StreamCipher},
     ChaCha20,
 };
+#[cfg(feature = "default_rng")]
+use once_cell::race::OnceRef;
 use rand::{CryptoRng, RngCore, SeedableRng};
 
-#[cfg(all(feature = "alloc", feature = "getrandom"))]
+#[cfg(all(feature = "alloc", feature = "default_rng"))]
 use crate::buffer::SecretBytes;
 use crate::error::Error;
 
@@ -34,29 +38,163 @@ impl<C: CryptoRng + RngCore> KeyMaterial for C {
     }
 }
 
-#[cfg(feature = "getrandom")]
-#[cfg_attr(docsrs, doc(cfg(feature = "getrandom")))]
+/// A function producing random bytes, which may be installed in place
+/// of the system random number generator
+pub type RandomSource = fn(&mut [u8]);
+
+#[cfg(feature = "default_rng")]
+static RANDOM_SOURCE: OnceRef<'static, RandomSource> = OnceRef::new();
+
+/// Install a global source of random bytes to be used by the default random
+/// number generator.
+///
+/// This affects key generation and nonces wherever an explicit generator is
+/// not provided, allowing reproducible output for known-answer tests or the
+/// use of a hardware random number generator. Any source installed outside
+/// of tests must be cryptographically secure.
+///
+/// The source may only be installed once, and an error is returned if a
+/// source is already in effect:
+///
+/// ```
+/// use askar_crypto::random::{set_random_source, RandomSource};
+///
+/// fn hardware_random(buf: &mut [u8]) {
+///     // read from the hardware generator
+/// #   buf.fill(1);
+/// }
+///
+/// static SOURCE: RandomSource = hardware_random;
+/// set_random_source(&SOURCE).unwrap();
+/// ```
+///
+/// When the `getrandom` feature is disabled, as on `no_std` targets without
+/// an operating system generator, a source must be installed before any
+/// random values are requested. Until then, fallible operations such as
+/// [`KeyGen::random`](crate::repr::KeyGen::random) return an `Unsupported`
+/// error.
+#[cfg(feature = "default_rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default_rng")))]
+pub fn set_random_source(source: &'static RandomSource) -> Result<(), Error> {
+    RANDOM_SOURCE
+        .set(source)
+        .map_err(|_| err_msg!(Usage, "A random source is already installed"))
+}
+
+#[cfg(feature = "default_rng")]
+#[inline]
+fn random_source_available() -> bool {
+    cfg!(feature = "getrandom") || RANDOM_SOURCE.get().is_some()
+}
+
+#[cfg(all(feature = "getrandom", feature = "std_rng"))]
+type SystemRng = rand::rngs::ThreadRng;
+#[cfg(all(feature = "getrandom", not(feature = "std_rng")))]
+type SystemRng = rand::rngs::OsRng;
+
+/// The system random number generator, unless replaced by `set_random_source`
+#[cfg(feature = "default_rng")]
+#[derive(Clone, Debug, Default)]
+struct DefaultRng {
+    #[cfg(feature = "getrandom")]
+    system: SystemRng,
+}
+
+#[cfg(feature = "default_rng")]
+impl CryptoRng for DefaultRng {}
+
+#[cfg(feature = "default_rng")]
+impl RngCore for DefaultRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0; 4];
+        self.fill_bytes(&mut buf[..]);
+        u32::from_le_bytes(buf)
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0; 8];
+        self.fill_bytes(&mut buf[..]);
+        u64::from_le_bytes(buf)
+    }
+
+    /// Fill a buffer with random bytes.
+    ///
+    /// This method panics if no random source is available, which is prevented
+    /// by obtaining the generator from [`try_default_rng`].
+    #[inline]
+    fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        if self.try_fill_bytes(bytes).is_err() {
+            panic!("No random source installed: see askar_crypto::random::set_random_source")
+        }
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, bytes: &mut [u8]) -> Result<(), rand::Error> {
+        if let Some(source) = RANDOM_SOURCE.get() {
+            source(bytes);
+            return Ok(());
+        }
+        #[cfg(feature = "getrandom")]
+        {
+            self.system.try_fill_bytes(bytes)
+        }
+        #[cfg(not(feature = "getrandom"))]
+        {
+            Err(rand::Error::from(NO_RANDOM_SOURCE))
+        }
+    }
+}
+
+/// The error code returned by the default generator when no source is available
+#[cfg(all(feature = "default_rng", not(feature = "getrandom")))]
+const NO_RANDOM_SOURCE: NonZeroU32 = match NonZeroU32::new(rand::Error::CUSTOM_START) {
+    Some(code) => code,
+    None => unreachable!(),
+};
+
+#[cfg(feature = "default_rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default_rng")))]
 #[inline]
 /// Obtain an instance of the default random number generator
+///
+/// The generator panics when used if no random source is available. Use
+/// [`try_default_rng`] where the error may be returned instead.
 pub fn default_rng() -> impl CryptoRng + RngCore + Debug + Clone {
-    #[cfg(feature = "std_rng")]
-    {
-        rand::rngs::ThreadRng::default()
-    }
-    #[cfg(not(feature = "std_rng"))]
-    {
-        rand::rngs::OsRng
+    DefaultRng::default()
+}
+
+#[cfg(feature = "default_rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default_rng")))]
+#[inline]
+/// Obtain an instance of the default random number generator, or an
+/// `Unsupported` error if no random source is available
+pub fn try_default_rng() -> Result<impl CryptoRng + RngCore + Debug + Clone, Error> {
+    if random_source_available() {
+        Ok(DefaultRng::default())
+    } else {
+        Err(err_msg!(Unsupported, "No random source installed"))
     }
 }
 
 /// Fill a mutable slice with random data using the
 /// system random number generator.
-#[cfg(feature = "getrandom")]
+#[cfg(feature = "default_rng")]
 #[inline(always)]
 pub fn fill_random(value: &mut [u8]) {
     default_rng().fill_bytes(value);
 }
 
+/// Fill a mutable slice with random data using the system random number
+/// generator, or return an `Unsupported` error if no random source is available
+#[cfg(feature = "default_rng")]
+#[inline]
+pub fn try_fill_random(value: &mut [u8]) -> Result<(), Error> {
+    try_default_rng()?.fill_bytes(value);
+    Ok(())
+}
+
 /// Written to be compatible with randombytes_deterministic in libsodium,
 /// used to generate a deterministic symmetric encryption key
 pub fn fill_random_deterministic(seed: &[u8], output: &mut [u8]) -> Result<(), Error> {
@@ -130,7 +268,7 @@ impl RandomDet {
     }
 }
 
-#[cfg(all(feature = "alloc", feature = "getrandom"))]
+#[cfg(all(feature = "alloc", feature = "default_rng"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
 /// Create a new `SecretBytes` instance with random data.
 #[inline(always)]
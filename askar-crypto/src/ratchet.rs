@@ -0,0 +1,604 @@
+//! Double Ratchet session state with header encryption
+//!
+//! Implements the Double Ratchet algorithm as specified by the Signal protocol,
+//! using X25519 for the Diffie-Hellman ratchet, HKDF-SHA256 as the root key KDF,
+//! HMAC-SHA256 as the chain key KDF, and AES-256-GCM for message and header
+//! encryption. The session is typically initialized from the output of an X3DH
+//! handshake, and the state may be persisted using its byte encoding.
+
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use hkdf::Hkdf;
+use hmac::{Mac, SimpleHmac};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::{
+    alg::{
+        aes::{A256Gcm, AesKey},
+        x25519::X25519KeyPair,
+    },
+    buffer::{SecretBytes, Writer},
+    encrypt::KeyAeadInPlace,
+    error::Error,
+    kdf::KeyExchange,
+    random::try_fill_random,
+    repr::{KeyGen, KeyPublicBytes, KeySecretBytes, ToSecretBytes},
+};
+
+/// The maximum number of message keys skipped within a single chain
+pub const MAX_SKIP: u32 = 1000;
+
+/// The maximum number of skipped message keys retained by a session
+pub const MAX_SKIPPED_KEYS: usize = 2000;
+
+/// The current session state encoding version
+const STATE_VERSION: u8 = 1;
+
+/// The length of the message header plaintext: DH public key, PN and N
+const HEADER_LENGTH: usize = 40;
+
+/// The length of the AEAD nonces
+const NONCE_LENGTH: usize = 12;
+
+/// The length of the AEAD tags
+const TAG_LENGTH: usize = 16;
+
+/// The length of an encrypted message header
+pub const ENC_HEADER_LENGTH: usize = NONCE_LENGTH + HEADER_LENGTH + TAG_LENGTH;
+
+const KDF_RK_INFO: &[u8] = b"askar-ratchet-root";
+const KDF_MK_INFO: &[u8] = b"askar-ratchet-message";
+
+type Key = [u8; 32];
+
+#[derive(Clone)]
+struct SkippedKey {
+    header_key: Key,
+    n: u32,
+    message_key: Key,
+}
+
+impl Drop for SkippedKey {
+    fn drop(&mut self) {
+        self.header_key.zeroize();
+        self.message_key.zeroize();
+    }
+}
+
+struct Header {
+    dh: Key,
+    pn: u32,
+    n: u32,
+}
+
+impl Header {
+    fn to_bytes(&self) -> [u8; HEADER_LENGTH] {
+        let mut buf = [0u8; HEADER_LENGTH];
+        buf[..32].copy_from_slice(&self.dh);
+        buf[32..36].copy_from_slice(&self.pn.to_be_bytes());
+        buf[36..].copy_from_slice(&self.n.to_be_bytes());
+        buf
+    }
+
+    fn from_bytes(buf: &[u8]) -> Result<Self, Error> {
+        if buf.len() != HEADER_LENGTH {
+            return Err(err_msg!(Invalid, "Invalid message header"));
+        }
+        let mut dh = [0u8; 32];
+        dh.copy_from_slice(&buf[..32]);
+        Ok(Self {
+            dh,
+            pn: u32::from_be_bytes(buf[32..36].try_into().unwrap()),
+            n: u32::from_be_bytes(buf[36..].try_into().unwrap()),
+        })
+    }
+}
+
+/// The state of a Double Ratchet session
+#[derive(Clone)]
+pub struct RatchetState {
+    dhs: Key,
+    dhr: Option<Key>,
+    rk: Key,
+    cks: Option<Key>,
+    ckr: Option<Key>,
+    ns: u32,
+    nr: u32,
+    pn: u32,
+    hks: Option<Key>,
+    hkr: Option<Key>,
+    nhks: Key,
+    nhkr: Key,
+    skipped: Vec<SkippedKey>,
+}
+
+impl RatchetState {
+    /// Initialize the session of the party sending the first message
+    ///
+    /// The shared secret and the two shared header keys must be agreed in
+    /// advance, for example by deriving them from an X3DH handshake.
+    pub fn init_initiator(
+        secret: &Key,
+        remote_key: &X25519KeyPair,
+        shared_header_key_a: &Key,
+        shared_next_header_key_b: &Key,
+    ) -> Result<Self, Error> {
+        let dhs = X25519KeyPair::random()?;
+        let dhr = public_array(remote_key);
+        let (rk, cks, nhks) = kdf_rk(secret, &dhs.key_exchange_bytes(remote_key)?)?;
+        Ok(Self {
+            dhs: secret_array(&dhs)?,
+            dhr: Some(dhr),
+            rk,
+            cks: Some(cks),
+            ckr: None,
+            ns: 0,
+            nr: 0,
+            pn: 0,
+            hks: Some(*shared_header_key_a),
+            hkr: None,
+            nhks,
+            nhkr: *shared_next_header_key_b,
+            skipped: Vec::new(),
+        })
+    }
+
+    /// Initialize the session of the party receiving the first message
+    ///
+    /// The keypair must correspond to the public key used by the initiator.
+    pub fn init_responder(
+        secret: &Key,
+        keypair: &X25519KeyPair,
+        shared_header_key_a: &Key,
+        shared_next_header_key_b: &Key,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            dhs: secret_array(keypair)?,
+            dhr: None,
+            rk: *secret,
+            cks: None,
+            ckr: None,
+            ns: 0,
+            nr: 0,
+            pn: 0,
+            hks: None,
+            hkr: None,
+            nhks: *shared_next_header_key_b,
+            nhkr: *shared_header_key_a,
+            skipped: Vec::new(),
+        })
+    }
+
+    /// Encrypt a message, advancing the sending chain
+    ///
+    /// Returns the encrypted header followed by the ciphertext.
+    pub fn encrypt(&mut self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+        let (Some(cks), Some(hks)) = (self.cks.as_ref(), self.hks.as_ref()) else {
+            return Err(err_msg!(Usage, "Session cannot send before receiving"));
+        };
+        let (cks, mut mk) = kdf_ck(cks);
+        let header = Header {
+            dh: public_array(&X25519KeyPair::from_secret_bytes(&self.dhs)?),
+            pn: self.pn,
+            n: self.ns,
+        };
+
+        let mut out = Vec::with_capacity(ENC_HEADER_LENGTH + plaintext.len() + TAG_LENGTH);
+        out.resize(NONCE_LENGTH, 0);
+        try_fill_random(&mut out[..NONCE_LENGTH])?;
+        out.extend_from_slice(&header.to_bytes());
+        let header_key = AesKey::<A256Gcm>::from_secret_bytes(hks)?;
+        let nonce: [u8; NONCE_LENGTH] = out[..NONCE_LENGTH].try_into().unwrap();
+        header_key.encrypt_in_place(
+            &mut Writer::from_vec_skip(&mut out, NONCE_LENGTH),
+            &nonce,
+            &[],
+        )?;
+
+        let mut msg_aad = Vec::with_capacity(aad.len() + ENC_HEADER_LENGTH);
+        msg_aad.extend_from_slice(aad);
+        msg_aad.extend_from_slice(&out);
+        out.extend_from_slice(plaintext);
+        let result = message_crypt(&mk, |key, nonce| {
+            key.encrypt_in_place(
+                &mut Writer::from_vec_skip(&mut out, ENC_HEADER_LENGTH),
+                nonce,
+                &msg_aad,
+            )
+            .map(drop)
+        });
+        mk.zeroize();
+        result?;
+
+        self.cks.replace(cks);
+        self.ns += 1;
+        Ok(out)
+    }
+
+    /// Decrypt a message, advancing the receiving chain
+    ///
+    /// The session state is only updated if the message is successfully decrypted.
+    pub fn decrypt(&mut self, message: &[u8], aad: &[u8]) -> Result<SecretBytes, Error> {
+        if message.len() < ENC_HEADER_LENGTH + TAG_LENGTH {
+            return Err(err_msg!(Invalid, "Invalid message length"));
+        }
+        let (enc_header, ciphertext) = message.split_at(ENC_HEADER_LENGTH);
+        let mut msg_aad = Vec::with_capacity(aad.len() + ENC_HEADER_LENGTH);
+        msg_aad.extend_from_slice(aad);
+        msg_aad.extend_from_slice(enc_header);
+
+        // try the stored keys for skipped messages
+        for idx in 0..self.skipped.len() {
+            let skipped = &self.skipped[idx];
+            if let Ok(header) = decrypt_header(&skipped.header_key, enc_header) {
+                if header.n == skipped.n {
+                    let plaintext = decrypt_message(&skipped.message_key, ciphertext, &msg_aad)?;
+                    self.skipped.remove(idx);
+                    return Ok(plaintext);
+                }
+            }
+        }
+
+        let mut state = self.clone();
+        let (header, dh_ratchet) = state.decrypt_header(enc_header)?;
+        if dh_ratchet {
+            state.skip_message_keys(header.pn)?;
+            state.dh_ratchet(&header)?;
+        }
+        state.skip_message_keys(header.n)?;
+        let ckr = state
+            .ckr
+            .as_ref()
+            .ok_or_else(|| err_msg!(Invalid, "Missing receiving chain"))?;
+        let (ckr, mut mk) = kdf_ck(ckr);
+        state.ckr.replace(ckr);
+        state.nr += 1;
+        let result = decrypt_message(&mk, ciphertext, &msg_aad);
+        mk.zeroize();
+        let plaintext = result?;
+        *self = state;
+        Ok(plaintext)
+    }
+
+    fn decrypt_header(&self, enc_header: &[u8]) -> Result<(Header, bool), Error> {
+        if let Some(hkr) = self.hkr.as_ref() {
+            if let Ok(header) = decrypt_header(hkr, enc_header) {
+                return Ok((header, false));
+            }
+        }
+        let header = decrypt_header(&self.nhkr, enc_header)
+            .map_err(|_| err_msg!(Encryption, "Message header decryption failed"))?;
+        Ok((header, true))
+    }
+
+    fn skip_message_keys(&mut self, until: u32) -> Result<(), Error> {
+        if until < self.nr {
+            return Err(err_msg!(Invalid, "Message key already used"));
+        }
+        if self.nr.saturating_add(MAX_SKIP) < until {
+            return Err(err_msg!(Invalid, "Exceeded maximum skipped messages"));
+        }
+        if let (Some(mut ckr), Some(hkr)) = (self.ckr, self.hkr) {
+            while self.nr < until {
+                let (next, message_key) = kdf_ck(&ckr);
+                ckr = next;
+                if self.skipped.len() >= MAX_SKIPPED_KEYS {
+                    self.skipped.remove(0);
+                }
+                self.skipped.push(SkippedKey {
+                    header_key: hkr,
+                    n: self.nr,
+                    message_key,
+                });
+                self.nr += 1;
+            }
+            self.ckr.replace(ckr);
+            ckr.zeroize();
+        }
+        Ok(())
+    }
+
+    fn dh_ratchet(&mut self, header: &Header) -> Result<(), Error> {
+        self.pn = self.ns;
+        self.ns = 0;
+        self.nr = 0;
+        self.hks.replace(self.nhks);
+        self.hkr.replace(self.nhkr);
+        self.dhr.replace(header.dh);
+        let remote = X25519KeyPair::from_public_bytes(&header.dh)?;
+
+        let dhs = X25519KeyPair::from_secret_bytes(&self.dhs)?;
+        let (rk, ckr, nhkr) = kdf_rk(&self.rk, &dhs.key_exchange_bytes(&remote)?)?;
+        self.rk = rk;
+        self.ckr.replace(ckr);
+        self.nhkr = nhkr;
+
+        let dhs = X25519KeyPair::random()?;
+        self.dhs = secret_array(&dhs)?;
+        let (rk, cks, nhks) = kdf_rk(&self.rk, &dhs.key_exchange_bytes(&remote)?)?;
+        self.rk = rk;
+        self.cks.replace(cks);
+        self.nhks = nhks;
+        Ok(())
+    }
+
+    /// Encode the session state for storage
+    pub fn to_bytes(&self) -> SecretBytes {
+        let mut buf =
+            SecretBytes::with_capacity(2 + 32 * 10 + 16 + self.skipped.len() * (2 * 32 + 4));
+        let flags = [
+            self.dhr.is_some(),
+            self.cks.is_some(),
+            self.ckr.is_some(),
+            self.hks.is_some(),
+            self.hkr.is_some(),
+        ]
+        .iter()
+        .enumerate()
+        .fold(0u8, |f, (i, set)| f | ((*set as u8) << i));
+        buf.extend_from_slice(&[STATE_VERSION, flags]);
+        buf.extend_from_slice(&self.dhs);
+        if let Some(dhr) = self.dhr.as_ref() {
+            buf.extend_from_slice(dhr);
+        }
+        buf.extend_from_slice(&self.rk);
+        for key in [&self.cks, &self.ckr].into_iter().flatten() {
+            buf.extend_from_slice(key);
+        }
+        for n in [self.ns, self.nr, self.pn] {
+            buf.extend_from_slice(&n.to_be_bytes());
+        }
+        for key in [&self.hks, &self.hkr].into_iter().flatten() {
+            buf.extend_from_slice(key);
+        }
+        buf.extend_from_slice(&self.nhks);
+        buf.extend_from_slice(&self.nhkr);
+        buf.extend_from_slice(&(self.skipped.len() as u32).to_be_bytes());
+        for skipped in &self.skipped {
+            buf.extend_from_slice(&skipped.header_key);
+            buf.extend_from_slice(&skipped.n.to_be_bytes());
+            buf.extend_from_slice(&skipped.message_key);
+        }
+        buf
+    }
+
+    /// Load a session state from its encoded form
+    pub fn from_bytes(state: &[u8]) -> Result<Self, Error> {
+        let mut reader = StateReader(state);
+        let [version, flags] = reader.take::<2>()?;
+        if version != STATE_VERSION {
+            return Err(err_msg!(Unsupported, "Unsupported ratchet state version"));
+        }
+        let has = |bit: u8| flags & (1 << bit) != 0;
+        let dhs = reader.take()?;
+        let dhr = reader.take_opt(has(0))?;
+        let rk = reader.take()?;
+        let cks = reader.take_opt(has(1))?;
+        let ckr = reader.take_opt(has(2))?;
+        let ns = reader.take_u32()?;
+        let nr = reader.take_u32()?;
+        let pn = reader.take_u32()?;
+        let hks = reader.take_opt(has(3))?;
+        let hkr = reader.take_opt(has(4))?;
+        let nhks = reader.take()?;
+        let nhkr = reader.take()?;
+        let count = reader.take_u32()? as usize;
+        if count > MAX_SKIPPED_KEYS {
+            return Err(err_msg!(Invalid, "Invalid ratchet state"));
+        }
+        let mut skipped = Vec::with_capacity(count);
+        for _ in 0..count {
+            skipped.push(SkippedKey {
+                header_key: reader.take()?,
+                n: reader.take_u32()?,
+                message_key: reader.take()?,
+            });
+        }
+        if !reader.0.is_empty() {
+            return Err(err_msg!(Invalid, "Invalid ratchet state"));
+        }
+        Ok(Self {
+            dhs,
+            dhr,
+            rk,
+            cks,
+            ckr,
+            ns,
+            nr,
+            pn,
+            hks,
+            hkr,
+            nhks,
+            nhkr,
+            skipped,
+        })
+    }
+}
+
+impl Debug for RatchetState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RatchetState")
+            .field("ns", &self.ns)
+            .field("nr", &self.nr)
+            .field("pn", &self.pn)
+            .field("skipped", &self.skipped.len())
+            .finish()
+    }
+}
+
+impl Drop for RatchetState {
+    fn drop(&mut self) {
+        self.dhs.zeroize();
+        self.rk.zeroize();
+        self.cks.zeroize();
+        self.ckr.zeroize();
+        self.hks.zeroize();
+        self.hkr.zeroize();
+        self.nhks.zeroize();
+        self.nhkr.zeroize();
+    }
+}
+
+struct StateReader<'r>(&'r [u8]);
+
+impl StateReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        if self.0.len() < N {
+            return Err(err_msg!(Invalid, "Invalid ratchet state"));
+        }
+        let (value, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(value.try_into().unwrap())
+    }
+
+    fn take_opt(&mut self, present: bool) -> Result<Option<Key>, Error> {
+        if present {
+            self.take().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn take_u32(&mut self) -> Result<u32, Error> {
+        self.take().map(u32::from_be_bytes)
+    }
+}
+
+fn public_array(key: &X25519KeyPair) -> Key {
+    let mut pk = [0u8; 32];
+    key.with_public_bytes(|b| pk.copy_from_slice(b));
+    pk
+}
+
+fn secret_array(key: &X25519KeyPair) -> Result<Key, Error> {
+    let mut sk = [0u8; 32];
+    sk.copy_from_slice(&key.to_secret_bytes()?);
+    Ok(sk)
+}
+
+/// Derive a new root key, chain key and next header key
+fn kdf_rk(rk: &Key, dh_out: &[u8]) -> Result<(Key, Key, Key), Error> {
+    let mut okm = [0u8; 96];
+    Hkdf::<Sha256>::new(Some(rk), dh_out)
+        .expand(KDF_RK_INFO, &mut okm)
+        .map_err(|_| err_msg!(Unexpected))?;
+    let mut keys = ([0u8; 32], [0u8; 32], [0u8; 32]);
+    keys.0.copy_from_slice(&okm[..32]);
+    keys.1.copy_from_slice(&okm[32..64]);
+    keys.2.copy_from_slice(&okm[64..]);
+    okm.zeroize();
+    Ok(keys)
+}
+
+/// Derive the next chain key and a message key
+fn kdf_ck(ck: &Key) -> (Key, Key) {
+    let derive = |tag: u8| {
+        let mut mac = SimpleHmac::<Sha256>::new_from_slice(ck).expect("HMAC accepts any key size");
+        mac.update(&[tag]);
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&mac.finalize().into_bytes());
+        out
+    };
+    (derive(0x02), derive(0x01))
+}
+
+/// Perform an operation with the encryption key and nonce derived from a message key
+fn message_crypt<R>(
+    mk: &Key,
+    f: impl FnOnce(&AesKey<A256Gcm>, &[u8]) -> Result<R, Error>,
+) -> Result<R, Error> {
+    let mut okm = [0u8; 32 + NONCE_LENGTH];
+    Hkdf::<Sha256>::new(None, mk)
+        .expand(KDF_MK_INFO, &mut okm)
+        .map_err(|_| err_msg!(Unexpected))?;
+    let key = AesKey::<A256Gcm>::from_secret_bytes(&okm[..32]);
+    let nonce: [u8; NONCE_LENGTH] = okm[32..].try_into().unwrap();
+    okm.zeroize();
+    f(&key?, &nonce)
+}
+
+fn decrypt_message(mk: &Key, ciphertext: &[u8], aad: &[u8]) -> Result<SecretBytes, Error> {
+    message_crypt(mk, |key, nonce| {
+        let mut buf = SecretBytes::from_slice(ciphertext);
+        key.decrypt_in_place(&mut buf, nonce, aad)?;
+        Ok(buf)
+    })
+}
+
+fn decrypt_header(hk: &Key, enc_header: &[u8]) -> Result<Header, Error> {
+    let (nonce, ciphertext) = enc_header.split_at(NONCE_LENGTH);
+    let mut buf = ciphertext.to_vec();
+    AesKey::<A256Gcm>::from_secret_bytes(hk)?.decrypt_in_place(&mut buf, nonce, &[])?;
+    Header::from_bytes(&buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_pair() -> (RatchetState, RatchetState) {
+        let secret = [1u8; 32];
+        let hka = [2u8; 32];
+        let nhkb = [3u8; 32];
+        let bob_key = X25519KeyPair::random().unwrap();
+        let bob_pub = X25519KeyPair::from_public_bytes(&public_array(&bob_key)).unwrap();
+        let alice = RatchetState::init_initiator(&secret, &bob_pub, &hka, &nhkb).unwrap();
+        let bob = RatchetState::init_responder(&secret, &bob_key, &hka, &nhkb).unwrap();
+        (alice, bob)
+    }
+
+    #[test]
+    fn exchange_messages() {
+        let (mut alice, mut bob) = session_pair();
+        assert!(bob.encrypt(b"hello", b"ad").is_err());
+
+        let m1 = alice.encrypt(b"hello bob", b"ad").unwrap();
+        assert_eq!(&bob.decrypt(&m1, b"ad").unwrap()[..], b"hello bob");
+        // replays are rejected
+        assert!(bob.decrypt(&m1, b"ad").is_err());
+
+        let r1 = bob.encrypt(b"hello alice", b"ad").unwrap();
+        let r2 = bob.encrypt(b"again", b"ad").unwrap();
+        assert!(alice.decrypt(&r1, b"other").is_err());
+        assert_eq!(&alice.decrypt(&r1, b"ad").unwrap()[..], b"hello alice");
+        assert_eq!(&alice.decrypt(&r2, b"ad").unwrap()[..], b"again");
+
+        let m2 = alice.encrypt(b"second", b"ad").unwrap();
+        assert_eq!(&bob.decrypt(&m2, b"ad").unwrap()[..], b"second");
+    }
+
+    #[test]
+    fn out_of_order() {
+        let (mut alice, mut bob) = session_pair();
+        let m1 = alice.encrypt(b"one", b"").unwrap();
+        let m2 = alice.encrypt(b"two", b"").unwrap();
+        let m3 = alice.encrypt(b"three", b"").unwrap();
+        assert_eq!(&bob.decrypt(&m3, b"").unwrap()[..], b"three");
+        let r1 = bob.encrypt(b"reply", b"").unwrap();
+        assert_eq!(&alice.decrypt(&r1, b"").unwrap()[..], b"reply");
+        let m4 = alice.encrypt(b"four", b"").unwrap();
+        assert_eq!(&bob.decrypt(&m4, b"").unwrap()[..], b"four");
+        assert_eq!(&bob.decrypt(&m1, b"").unwrap()[..], b"one");
+        assert_eq!(&bob.decrypt(&m2, b"").unwrap()[..], b"two");
+        assert!(bob.decrypt(&m2, b"").is_err());
+    }
+
+    #[test]
+    fn persist_state() {
+        let (mut alice, mut bob) = session_pair();
+        let m1 = alice.encrypt(b"one", b"").unwrap();
+        let m2 = alice.encrypt(b"two", b"").unwrap();
+        assert_eq!(&bob.decrypt(&m2, b"").unwrap()[..], b"two");
+
+        let mut bob = RatchetState::from_bytes(&bob.to_bytes()).unwrap();
+        assert_eq!(&bob.decrypt(&m1, b"").unwrap()[..], b"one");
+        let mut alice = RatchetState::from_bytes(&alice.to_bytes()).unwrap();
+        let r1 = bob.encrypt(b"reply", b"").unwrap();
+        assert_eq!(&alice.decrypt(&r1, b"").unwrap()[..], b"reply");
+
+        let enc = alice.to_bytes();
+        assert!(RatchetState::from_bytes(&enc[..enc.len() - 1]).is_err());
+    }
+}
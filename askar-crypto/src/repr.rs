@@ -15,9 +15,9 @@ pub trait KeyGen: Sized {
     fn generate(rng: impl KeyMaterial) -> Result<Self, Error>;
 
     /// Generate a new random key.
-    #[cfg(feature = "getrandom")]
+    #[cfg(feature = "default_rng")]
     fn random() -> Result<Self, Error> {
-        Self::generate(crate::random::default_rng())
+        Self::generate(crate::random::try_default_rng()?)
     }
 }
 
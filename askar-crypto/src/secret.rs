@@ -0,0 +1,45 @@
+//! Zeroizing buffers and constant-time helpers for handling secret values
+//!
+//! These are the same primitives used internally for key material, made
+//! available for application code handling short-lived derived secrets.
+
+use subtle::ConstantTimeEq;
+
+pub use crate::buffer::ArrayKey;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use crate::buffer::SecretBytes;
+pub use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Compare two byte slices for equality in constant time
+///
+/// The running time depends only on the lengths of the inputs, and slices
+/// of different lengths always compare as unequal.
+#[inline]
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ct_eq_slices() {
+        assert!(ct_eq(b"", b""));
+        assert!(ct_eq(b"secret", b"secret"));
+        assert!(!ct_eq(b"secret", b"secreT"));
+        assert!(!ct_eq(b"secret", b"secret1"));
+    }
+
+    #[test]
+    fn zeroizing_buffers() {
+        let mut otp = Zeroizing::new([7u8; 6]);
+        assert!(ct_eq(&otp[..], &[7u8; 6]));
+        otp.zeroize();
+        assert_eq!(*otp, [0u8; 6]);
+
+        let sb = SecretBytes::from_slice(b"pairwise");
+        assert!(ct_eq(&sb, b"pairwise"));
+    }
+}
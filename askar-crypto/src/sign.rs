@@ -1,10 +1,19 @@
 //! Signature traits and parameters
 
-use core::str::FromStr;
+use core::{
+    fmt::{self, Debug, Formatter},
+    str::FromStr,
+};
+
+use sha2::{Digest, Sha256, Sha384, Sha512};
 
 #[cfg(feature = "alloc")]
 use crate::buffer::SecretBytes;
-use crate::{alg::normalize_alg, buffer::WriteBuffer, error::Error};
+use crate::{
+    alg::{normalize_alg, EcCurves, HasKeyAlg, KeyAlg},
+    buffer::WriteBuffer,
+    error::Error,
+};
 
 /// Signature creation operations
 pub trait KeySign: KeySigVerify {
@@ -17,6 +26,32 @@ pub trait KeySign: KeySigVerify {
         out: &mut dyn WriteBuffer,
     ) -> Result<(), Error>;
 
+    /// Create a signature of the requested type and encoding and write it
+    /// to the provided buffer.
+    fn write_signature_format(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        format: SignatureFormat,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        match format {
+            SignatureFormat::Raw => self.write_signature(message, sig_type, out),
+            _ => Err(err_msg!(Unsupported, "Unsupported signature format")),
+        }
+    }
+
+    /// Create a signature of the requested type over a message digest
+    /// computed by the caller, and write it to the provided buffer.
+    fn write_signature_prehashed(
+        &self,
+        _digest: &[u8],
+        _sig_type: Option<SignatureType>,
+        _out: &mut dyn WriteBuffer,
+    ) -> Result<(), Error> {
+        Err(err_msg!(Unsupported, "Prehashed signing is not supported"))
+    }
+
     #[cfg(feature = "alloc")]
     #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
     /// Create a signature of the requested type and return an allocated
@@ -42,6 +77,35 @@ pub trait KeySigVerify {
         signature: &[u8],
         sig_type: Option<SignatureType>,
     ) -> Result<bool, Error>;
+
+    /// Check the validity of signature over a message with the
+    /// specified signature type and encoding.
+    fn verify_signature_format(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+        format: SignatureFormat,
+    ) -> Result<bool, Error> {
+        match format {
+            SignatureFormat::Raw => self.verify_signature(message, signature, sig_type),
+            _ => Err(err_msg!(Unsupported, "Unsupported signature format")),
+        }
+    }
+
+    /// Check the validity of signature over a message digest computed
+    /// by the caller with the specified signature type.
+    fn verify_signature_prehashed(
+        &self,
+        _digest: &[u8],
+        _signature: &[u8],
+        _sig_type: Option<SignatureType>,
+    ) -> Result<bool, Error> {
+        Err(err_msg!(
+            Unsupported,
+            "Prehashed verification is not supported"
+        ))
+    }
 }
 
 /// Supported signature types
@@ -49,12 +113,44 @@ pub trait KeySigVerify {
 pub enum SignatureType {
     /// Standard signature output for ed25519
     EdDSA,
+    /// Ed25519ph signature over a SHA-512 message digest
+    Ed25519ph,
     /// Elliptic curve DSA using P-256 and SHA-256
     ES256,
     /// Elliptic curve DSA using K-256 and SHA-256
     ES256K,
+    /// Recoverable elliptic curve DSA using K-256 and SHA-256
+    ES256KR,
     /// Elliptic curve DSA using P-384 and SHA-384
     ES384,
+    /// EdDSA using Ed448
+    Ed448,
+    /// HMAC using SHA-256
+    HS256,
+    /// HMAC using SHA-384
+    HS384,
+    /// HMAC using SHA-512
+    HS512,
+    /// RSASSA-PKCS1-v1_5 using SHA-256
+    RS256,
+    /// RSASSA-PSS using SHA-256 and MGF1 with SHA-256
+    PS256,
+    /// BLS12-381 signature using the basic scheme
+    BlsBasic,
+    /// BLS12-381 signature using the message augmentation scheme
+    BlsAug,
+    /// BLS12-381 signature using the proof-of-possession scheme
+    BlsPop,
+    /// ML-DSA-44 (FIPS 204) signature
+    MlDsa44,
+    /// ML-DSA-65 (FIPS 204) signature
+    MlDsa65,
+    /// ML-DSA-87 (FIPS 204) signature
+    MlDsa87,
+    /// SLH-DSA-SHA2-128s (FIPS 205) signature
+    SlhDsaSha2_128s,
+    /// SLH-DSA-SHA2-128f (FIPS 205) signature
+    SlhDsaSha2_128f,
 }
 
 impl FromStr for SignatureType {
@@ -63,20 +159,275 @@ impl FromStr for SignatureType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match normalize_alg(s)? {
             a if a == "eddsa" => Ok(Self::EdDSA),
+            a if a == "ed25519ph" => Ok(Self::Ed25519ph),
             a if a == "es256" => Ok(Self::ES256),
             a if a == "es256k" => Ok(Self::ES256K),
+            a if a == "es256kr" => Ok(Self::ES256KR),
             a if a == "es384" => Ok(Self::ES384),
+            a if a == "ed448" => Ok(Self::Ed448),
+            a if a == "hs256" => Ok(Self::HS256),
+            a if a == "hs384" => Ok(Self::HS384),
+            a if a == "hs512" => Ok(Self::HS512),
+            a if a == "rs256" => Ok(Self::RS256),
+            a if a == "ps256" => Ok(Self::PS256),
+            a if a == "blsbasic" => Ok(Self::BlsBasic),
+            a if a == "blsaug" => Ok(Self::BlsAug),
+            a if a == "blspop" => Ok(Self::BlsPop),
+            a if a == "mldsa44" => Ok(Self::MlDsa44),
+            a if a == "mldsa65" => Ok(Self::MlDsa65),
+            a if a == "mldsa87" => Ok(Self::MlDsa87),
+            a if a == "slhdsasha2128s" => Ok(Self::SlhDsaSha2_128s),
+            a if a == "slhdsasha2128f" => Ok(Self::SlhDsaSha2_128f),
             _ => Err(err_msg!(Unsupported, "Unknown signature algorithm")),
         }
     }
 }
 
+/// Supported signature encodings
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SignatureFormat {
+    /// Fixed-width signature output, such as r || s for ECDSA
+    #[default]
+    Raw,
+    /// ASN.1 DER encoded ECDSA signature
+    Der,
+}
+
+impl FromStr for SignatureFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize_alg(s)? {
+            a if a == "raw" => Ok(Self::Raw),
+            a if a == "der" => Ok(Self::Der),
+            _ => Err(err_msg!(Unsupported, "Unknown signature format")),
+        }
+    }
+}
+
 impl SignatureType {
     /// Get the length of the signature output.
     pub const fn signature_length(&self) -> usize {
         match self {
-            Self::EdDSA | Self::ES256 | Self::ES256K => 64,
-            Self::ES384 => 96,
+            Self::HS256 => 32,
+            Self::HS384 => 48,
+            Self::EdDSA | Self::Ed25519ph | Self::ES256 | Self::ES256K | Self::HS512 => 64,
+            Self::ES256KR => 65,
+            // G2 signature length, G1 signatures are 48 bytes
+            Self::ES384 | Self::BlsBasic | Self::BlsAug | Self::BlsPop => 96,
+            Self::Ed448 => 114,
+            // varies with the modulus size, this is the maximum supported
+            Self::RS256 | Self::PS256 => 512,
+            Self::MlDsa44 => 2420,
+            Self::MlDsa65 => 3309,
+            Self::MlDsa87 => 4627,
+            Self::SlhDsaSha2_128s => 7856,
+            Self::SlhDsaSha2_128f => 17088,
+        }
+    }
+}
+
+/// The message digest accumulated by a streaming signer or verifier
+#[derive(Clone)]
+enum StreamDigest {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+impl StreamDigest {
+    fn resolve<K: HasKeyAlg + ?Sized>(
+        key: &K,
+        sig_type: Option<SignatureType>,
+    ) -> Result<(SignatureType, Self), Error> {
+        let sig_type = match (sig_type, key.algorithm()) {
+            (Some(sig_type), _) => sig_type,
+            (None, KeyAlg::Ed25519) => SignatureType::Ed25519ph,
+            (None, KeyAlg::EcCurve(EcCurves::Secp256r1)) => SignatureType::ES256,
+            (None, KeyAlg::EcCurve(EcCurves::Secp256k1)) => SignatureType::ES256K,
+            (None, KeyAlg::EcCurve(EcCurves::Secp384r1)) => SignatureType::ES384,
+            _ => {
+                return Err(err_msg!(
+                    Unsupported,
+                    "Streaming signatures are not supported for this key type"
+                ))
+            }
+        };
+        let digest = match sig_type {
+            SignatureType::ES256 | SignatureType::ES256K => Self::Sha256(Sha256::new()),
+            SignatureType::ES384 => Self::Sha384(Sha384::new()),
+            SignatureType::Ed25519ph => Self::Sha512(Sha512::new()),
+            _ => {
+                return Err(err_msg!(
+                    Unsupported,
+                    "Streaming is not supported for this signature type"
+                ))
+            }
+        };
+        Ok((sig_type, digest))
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(d) => d.update(data),
+            Self::Sha384(d) => d.update(data),
+            Self::Sha512(d) => d.update(data),
         }
     }
+
+    fn finalize<O>(self, f: impl FnOnce(&[u8]) -> O) -> O {
+        match self {
+            Self::Sha256(d) => f(&d.finalize()),
+            Self::Sha384(d) => f(&d.finalize()),
+            Self::Sha512(d) => f(&d.finalize()),
+        }
+    }
+}
+
+/// Incremental signing of a message which is provided in parts
+///
+/// When no signature type is given, Ed25519 keys produce Ed25519ph signatures
+/// and ECDSA keys use the digest corresponding to the curve.
+pub struct Signer<'k, K: ?Sized> {
+    key: &'k K,
+    sig_type: SignatureType,
+    digest: StreamDigest,
+}
+
+impl<'k, K: KeySign + HasKeyAlg + ?Sized> Signer<'k, K> {
+    /// Start a new signature with a signing key
+    pub fn new(key: &'k K, sig_type: Option<SignatureType>) -> Result<Self, Error> {
+        let (sig_type, digest) = StreamDigest::resolve(key, sig_type)?;
+        Ok(Self {
+            key,
+            sig_type,
+            digest,
+        })
+    }
+
+    /// Accessor for the signature type
+    pub fn signature_type(&self) -> SignatureType {
+        self.sig_type
+    }
+
+    /// Add a part of the message
+    pub fn update(&mut self, data: &[u8]) {
+        self.digest.update(data)
+    }
+
+    /// Create the signature and write it to the provided buffer
+    pub fn write_signature(self, out: &mut dyn WriteBuffer) -> Result<(), Error> {
+        let (key, sig_type) = (self.key, self.sig_type);
+        self.digest
+            .finalize(|digest| key.write_signature_prehashed(digest, Some(sig_type), out))
+    }
+
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    /// Create the signature and return an allocated buffer
+    pub fn finish(self) -> Result<SecretBytes, Error> {
+        let mut buf = SecretBytes::with_capacity(self.sig_type.signature_length());
+        self.write_signature(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+impl<K: ?Sized> Debug for Signer<'_, K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Signer")
+            .field("sig_type", &self.sig_type)
+            .finish()
+    }
+}
+
+/// Incremental verification of a signature over a message which is provided in parts
+pub struct Verifier<'k, K: ?Sized> {
+    key: &'k K,
+    sig_type: SignatureType,
+    digest: StreamDigest,
+}
+
+impl<'k, K: KeySigVerify + HasKeyAlg + ?Sized> Verifier<'k, K> {
+    /// Start a new signature verification with a verification key
+    pub fn new(key: &'k K, sig_type: Option<SignatureType>) -> Result<Self, Error> {
+        let (sig_type, digest) = StreamDigest::resolve(key, sig_type)?;
+        Ok(Self {
+            key,
+            sig_type,
+            digest,
+        })
+    }
+
+    /// Accessor for the signature type
+    pub fn signature_type(&self) -> SignatureType {
+        self.sig_type
+    }
+
+    /// Add a part of the message
+    pub fn update(&mut self, data: &[u8]) {
+        self.digest.update(data)
+    }
+
+    /// Check the validity of the signature over the accumulated message
+    pub fn finish(self, signature: &[u8]) -> Result<bool, Error> {
+        let (key, sig_type) = (self.key, self.sig_type);
+        self.digest
+            .finalize(|digest| key.verify_signature_prehashed(digest, signature, Some(sig_type)))
+    }
+}
+
+impl<K: ?Sized> Debug for Verifier<'_, K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Verifier")
+            .field("sig_type", &self.sig_type)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repr::KeyGen;
+
+    #[cfg(feature = "ed25519")]
+    #[test]
+    fn stream_ed25519() {
+        use crate::alg::ed25519::Ed25519KeyPair;
+
+        let kp = Ed25519KeyPair::random().unwrap();
+        let mut signer = Signer::new(&kp, None).unwrap();
+        assert_eq!(signer.signature_type(), SignatureType::Ed25519ph);
+        signer.update(b"hello ");
+        signer.update(b"world");
+        let sig = signer.finish().unwrap();
+        assert!(kp.verify_signature_prehashed(&Sha512::digest(b"hello world"), &sig[..]));
+
+        let mut verifier = Verifier::new(&kp, None).unwrap();
+        verifier.update(b"hello world");
+        assert!(verifier.finish(&sig[..]).unwrap());
+        let mut verifier = Verifier::new(&kp, None).unwrap();
+        verifier.update(b"hello");
+        assert!(!verifier.finish(&sig[..]).unwrap());
+        assert!(Signer::new(&kp, Some(SignatureType::EdDSA)).is_err());
+    }
+
+    #[cfg(feature = "ec_curves")]
+    #[test]
+    fn stream_ecdsa() {
+        use crate::alg::{p256::P256KeyPair, p384::P384KeyPair};
+
+        let kp = P256KeyPair::random().unwrap();
+        let mut signer = Signer::new(&kp, None).unwrap();
+        signer.update(b"hello ");
+        signer.update(b"world");
+        let sig = signer.finish().unwrap();
+        assert!(KeySigVerify::verify_signature(&kp, b"hello world", &sig[..], None).unwrap());
+
+        let kp = P384KeyPair::random().unwrap();
+        let sig = kp.create_signature(b"hello world", None).unwrap();
+        let mut verifier = Verifier::new(&kp, None).unwrap();
+        assert_eq!(verifier.signature_type(), SignatureType::ES384);
+        verifier.update(b"hello world");
+        assert!(verifier.finish(&sig[..]).unwrap());
+    }
 }
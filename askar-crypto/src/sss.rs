@@ -0,0 +1,305 @@
+//! Shamir secret sharing over GF(2^8)
+//!
+//! A secret is split into a number of shares, any `threshold` of which are
+//! sufficient to reconstruct it. Each encoded share consists of a header
+//! (the format version, a random identifier for the share set, the threshold
+//! and the share index), the share payload, and a truncated SHA-256 tag over
+//! the preceding bytes. A digest of the secret is shared alongside it, so that
+//! an incorrect reconstruction is detected.
+
+use alloc::vec::Vec;
+
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    buffer::{ResizeBuffer, SecretBytes},
+    error::Error,
+    random::KeyMaterial,
+    repr::ToSecretBytes,
+};
+
+/// The current share format version
+pub const SHARE_VERSION: u8 = 1;
+
+/// The length of the share header
+pub const HEADER_LENGTH: usize = 8;
+
+/// The length of the share integrity tag
+pub const TAG_LENGTH: usize = 8;
+
+/// The length of the secret digest included in each share payload
+const DIGEST_LENGTH: usize = 4;
+
+/// Metadata describing an encoded share
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShareInfo {
+    /// The share format version
+    pub version: u8,
+    /// The random identifier of the share set
+    pub id: [u8; 4],
+    /// The number of shares required for reconstruction
+    pub threshold: u8,
+    /// The index of the share, starting at 1
+    pub index: u8,
+}
+
+impl ShareInfo {
+    /// Parse and validate the header and integrity tag of an encoded share
+    pub fn from_share(share: &[u8]) -> Result<Self, Error> {
+        if share.len() < HEADER_LENGTH + DIGEST_LENGTH + TAG_LENGTH {
+            return Err(err_msg!(Invalid, "Invalid share length"));
+        }
+        let (body, tag) = share.split_at(share.len() - TAG_LENGTH);
+        if !bool::from(share_tag(body)[..].ct_eq(tag)) {
+            return Err(err_msg!(Invalid, "Invalid share tag"));
+        }
+        if body[0] != SHARE_VERSION {
+            return Err(err_msg!(Unsupported, "Unsupported share version"));
+        }
+        let info = Self {
+            version: body[0],
+            id: [body[1], body[2], body[3], body[4]],
+            threshold: body[5],
+            index: body[6],
+        };
+        if info.threshold == 0 || info.index == 0 || body[7] != 0 {
+            return Err(err_msg!(Invalid, "Invalid share header"));
+        }
+        Ok(info)
+    }
+
+    fn write_header(&self, out: &mut Vec<u8>) {
+        out.push(self.version);
+        out.extend_from_slice(&self.id);
+        out.push(self.threshold);
+        out.push(self.index);
+        // reserved
+        out.push(0);
+    }
+}
+
+/// Split a secret into `count` shares, `threshold` of which are required to
+/// reconstruct it, using the default RNG
+#[cfg(feature = "default_rng")]
+pub fn split_secret(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<SecretBytes>, Error> {
+    split_secret_with_rng(secret, threshold, count, crate::random::try_default_rng()?)
+}
+
+/// Split the secret bytes of a key into `count` shares, `threshold` of which
+/// are required to reconstruct them, using the default RNG
+#[cfg(feature = "default_rng")]
+pub fn split_key(
+    key: &impl ToSecretBytes,
+    threshold: u8,
+    count: u8,
+) -> Result<Vec<SecretBytes>, Error> {
+    let secret = key.to_secret_bytes()?;
+    split_secret(&secret, threshold, count)
+}
+
+/// Split a secret into `count` shares, `threshold` of which are required to
+/// reconstruct it
+pub fn split_secret_with_rng(
+    secret: &[u8],
+    threshold: u8,
+    count: u8,
+    mut rng: impl KeyMaterial,
+) -> Result<Vec<SecretBytes>, Error> {
+    if threshold == 0 || count < threshold {
+        return Err(err_msg!(Usage, "Invalid share threshold"));
+    }
+    if secret.is_empty() {
+        return Err(err_msg!(Usage, "Cannot split an empty secret"));
+    }
+
+    let mut payload = SecretBytes::with_capacity(secret.len() + DIGEST_LENGTH);
+    payload.extend_from_slice(secret);
+    payload.extend_from_slice(&secret_digest(secret));
+
+    // the set identifier, followed by the random polynomial coefficients
+    let coeff_len = payload.len() * (threshold as usize - 1);
+    let okm = SecretBytes::new_with(4 + coeff_len, |buf| rng.read_okm(buf));
+    let mut id = [0u8; 4];
+    id.copy_from_slice(&okm[..4]);
+    let coeffs = &okm[4..];
+
+    let mut shares = Vec::with_capacity(count as usize);
+    for index in 1..=count {
+        let info = ShareInfo {
+            version: SHARE_VERSION,
+            id,
+            threshold,
+            index,
+        };
+        let mut share = Vec::with_capacity(HEADER_LENGTH + payload.len() + TAG_LENGTH);
+        info.write_header(&mut share);
+        for (pos, byte) in payload.iter().enumerate() {
+            // evaluate the polynomial using Horner's method
+            let mut y = 0u8;
+            for c in coeffs.chunks_exact(payload.len()).rev() {
+                y = gf_mul(y, index) ^ c[pos];
+            }
+            share.push(gf_mul(y, index) ^ byte);
+        }
+        let tag = share_tag(&share);
+        share.extend_from_slice(&tag);
+        shares.push(SecretBytes::from(share));
+    }
+    Ok(shares)
+}
+
+/// Reconstruct a secret from a set of shares
+///
+/// At least `threshold` distinct shares from the same set must be provided.
+pub fn combine_shares<S: AsRef<[u8]>>(shares: &[S]) -> Result<SecretBytes, Error> {
+    let first = shares
+        .first()
+        .ok_or_else(|| err_msg!(Usage, "No shares provided"))?
+        .as_ref();
+    let info = ShareInfo::from_share(first)?;
+    let share_len = first.len();
+    let threshold = info.threshold as usize;
+
+    let mut indices = Vec::with_capacity(threshold);
+    for share in shares {
+        let share = share.as_ref();
+        let cmp = ShareInfo::from_share(share)?;
+        if cmp.id != info.id || cmp.threshold != info.threshold || share.len() != share_len {
+            return Err(err_msg!(Invalid, "Shares are not from the same set"));
+        }
+        if indices.contains(&cmp.index) {
+            return Err(err_msg!(Invalid, "Duplicate share index"));
+        }
+        indices.push(cmp.index);
+        if indices.len() == threshold {
+            break;
+        }
+    }
+    if indices.len() < threshold {
+        return Err(err_msg!(Usage, "Insufficient shares for reconstruction"));
+    }
+
+    // Lagrange interpolation at x = 0
+    let payload_len = share_len - HEADER_LENGTH - TAG_LENGTH;
+    let mut payload = SecretBytes::new_with(payload_len, |_| ());
+    for (i, share) in shares.iter().take(threshold).enumerate() {
+        let xi = indices[i];
+        let mut basis = 1u8;
+        for (j, xj) in indices.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_mul(*xj, gf_inv(xj ^ xi)));
+            }
+        }
+        let ys = &share.as_ref()[HEADER_LENGTH..(HEADER_LENGTH + payload_len)];
+        for (out, y) in payload.as_mut().iter_mut().zip(ys) {
+            *out ^= gf_mul(basis, *y);
+        }
+    }
+
+    let secret_len = payload_len - DIGEST_LENGTH;
+    let valid = secret_digest(&payload[..secret_len])[..].ct_eq(&payload[secret_len..]);
+    if !bool::from(valid) {
+        return Err(err_msg!(Invalid, "Invalid reconstructed secret"));
+    }
+    payload.buffer_resize(secret_len)?;
+    Ok(payload)
+}
+
+fn secret_digest(secret: &[u8]) -> [u8; DIGEST_LENGTH] {
+    let mut digest = [0u8; DIGEST_LENGTH];
+    digest.copy_from_slice(&Sha256::digest(secret)[..DIGEST_LENGTH]);
+    digest
+}
+
+fn share_tag(body: &[u8]) -> [u8; TAG_LENGTH] {
+    let mut tag = [0u8; TAG_LENGTH];
+    tag.copy_from_slice(&Sha256::digest(body)[..TAG_LENGTH]);
+    tag
+}
+
+/// Multiply in GF(2^8) with the AES reduction polynomial, in constant time
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut r = 0u8;
+    for _ in 0..8 {
+        r ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    r
+}
+
+/// Invert a non-zero element of GF(2^8) as a^254
+fn gf_inv(a: u8) -> u8 {
+    let a2 = gf_mul(a, a);
+    let a4 = gf_mul(a2, a2);
+    let a8 = gf_mul(a4, a4);
+    let a16 = gf_mul(a8, a8);
+    let a32 = gf_mul(a16, a16);
+    let a64 = gf_mul(a32, a32);
+    let a128 = gf_mul(a64, a64);
+    gf_mul(
+        gf_mul(gf_mul(a2, a4), gf_mul(a8, a16)),
+        gf_mul(gf_mul(a32, a64), a128),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gf_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn split_combine() {
+        let secret = b"a very secret value";
+        let shares = split_secret(secret, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        let info = ShareInfo::from_share(&shares[4]).unwrap();
+        assert_eq!(info.version, SHARE_VERSION);
+        assert_eq!(info.threshold, 3);
+        assert_eq!(info.index, 5);
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let parts: Vec<&[u8]> = subset.iter().map(|i| &shares[*i][..]).collect();
+            assert_eq!(&combine_shares(&parts).unwrap()[..], &secret[..]);
+        }
+        assert!(combine_shares(&shares[..2]).is_err());
+        assert!(combine_shares(&[&shares[0][..], &shares[0][..], &shares[1][..]]).is_err());
+
+        // shares from a different split cannot be mixed
+        let other = split_secret(secret, 3, 5).unwrap();
+        assert!(combine_shares(&[&shares[0][..], &shares[1][..], &other[2][..]]).is_err());
+
+        // modified shares are rejected
+        let mut modified = shares[0].clone();
+        modified.as_mut()[HEADER_LENGTH] ^= 1;
+        assert!(ShareInfo::from_share(&modified).is_err());
+        assert!(combine_shares(&[&modified[..], &shares[1][..], &shares[2][..]]).is_err());
+    }
+
+    #[test]
+    fn threshold_one() {
+        let shares = split_secret(b"secret", 1, 2).unwrap();
+        assert_eq!(&combine_shares(&shares[1..]).unwrap()[..], b"secret");
+        assert!(split_secret(b"secret", 3, 2).is_err());
+        assert!(split_secret(b"secret", 0, 2).is_err());
+    }
+
+    #[cfg(feature = "ed25519")]
+    #[test]
+    fn split_key_bytes() {
+        use crate::{alg::ed25519::Ed25519KeyPair, repr::KeyGen};
+
+        let key = Ed25519KeyPair::random().unwrap();
+        let shares = split_key(&key, 2, 3).unwrap();
+        let secret = combine_shares(&shares[1..]).unwrap();
+        assert_eq!(secret, key.to_secret_bytes().unwrap());
+    }
+}
@@ -0,0 +1,255 @@
+//! STREAM construction for chunked AEAD encryption
+//!
+//! Each chunk is encrypted using a nonce composed of a caller-provided prefix,
+//! a 32-bit big-endian chunk counter, and a one-byte final chunk flag. This
+//! prevents reordering, truncation and extension of the encrypted chunks.
+
+use core::fmt::{self, Debug, Formatter};
+
+use crate::{buffer::ResizeBuffer, encrypt::KeyAeadInPlace, error::Error};
+
+/// The number of nonce bytes reserved for the chunk counter and final flag
+pub const NONCE_OVERHEAD: usize = 5;
+
+const MAX_NONCE_LENGTH: usize = 32;
+
+/// The shared nonce state of an encryption or decryption stream
+#[derive(Clone)]
+struct StreamNonce {
+    nonce: [u8; MAX_NONCE_LENGTH],
+    prefix_len: usize,
+    counter: u32,
+    finished: bool,
+}
+
+impl StreamNonce {
+    fn new(key: &(impl KeyAeadInPlace + ?Sized), nonce_prefix: &[u8]) -> Result<Self, Error> {
+        let nonce_len = key.aead_params().nonce_length;
+        if !(NONCE_OVERHEAD..=MAX_NONCE_LENGTH).contains(&nonce_len) {
+            return Err(err_msg!(
+                Unsupported,
+                "Key type does not support stream encryption"
+            ));
+        }
+        let prefix_len = nonce_len - NONCE_OVERHEAD;
+        if nonce_prefix.len() != prefix_len {
+            return Err(err_msg!(InvalidNonce));
+        }
+        let mut nonce = [0u8; MAX_NONCE_LENGTH];
+        nonce[..prefix_len].copy_from_slice(nonce_prefix);
+        Ok(Self {
+            nonce,
+            prefix_len,
+            counter: 0,
+            finished: false,
+        })
+    }
+
+    /// Produce the nonce for the next chunk
+    fn current(&mut self, last: bool) -> Result<&[u8], Error> {
+        if self.finished {
+            return Err(err_msg!(Usage, "Stream has already been finalized"));
+        }
+        let pos = self.prefix_len;
+        self.nonce[pos..(pos + 4)].copy_from_slice(&self.counter.to_be_bytes());
+        self.nonce[pos + 4] = last as u8;
+        Ok(&self.nonce[..(pos + NONCE_OVERHEAD)])
+    }
+
+    /// Advance the chunk counter after a successful operation
+    fn advance(&mut self, last: bool) -> Result<(), Error> {
+        if last {
+            self.finished = true;
+        } else {
+            self.counter = self
+                .counter
+                .checked_add(1)
+                .ok_or_else(|| err_msg!(Usage, "Stream chunk limit exceeded"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for StreamNonce {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamNonce")
+            .field("prefix", &&self.nonce[..self.prefix_len])
+            .field("counter", &self.counter)
+            .field("finished", &self.finished)
+            .finish()
+    }
+}
+
+/// Get the length of the nonce prefix used for stream encryption with a key
+pub fn nonce_prefix_length(key: &(impl KeyAeadInPlace + ?Sized)) -> Result<usize, Error> {
+    let nonce_len = key.aead_params().nonce_length;
+    if !(NONCE_OVERHEAD..=MAX_NONCE_LENGTH).contains(&nonce_len) {
+        Err(err_msg!(
+            Unsupported,
+            "Key type does not support stream encryption"
+        ))
+    } else {
+        Ok(nonce_len - NONCE_OVERHEAD)
+    }
+}
+
+/// Encrypt a sequence of chunks using the STREAM construction
+pub struct EncryptStream<'k, K: KeyAeadInPlace + ?Sized> {
+    key: &'k K,
+    nonce: StreamNonce,
+}
+
+impl<'k, K: KeyAeadInPlace + ?Sized> EncryptStream<'k, K> {
+    /// Create a new encryption stream from a key and a unique nonce prefix
+    pub fn new(key: &'k K, nonce_prefix: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            key,
+            nonce: StreamNonce::new(key, nonce_prefix)?,
+        })
+    }
+
+    /// Encrypt an intermediate chunk in place, appending the verification tag
+    pub fn encrypt_chunk(
+        &mut self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[u8],
+    ) -> Result<usize, Error> {
+        let nonce = self.nonce.current(false)?;
+        let len = self.key.encrypt_in_place(buffer, nonce, aad)?;
+        self.nonce.advance(false)?;
+        Ok(len)
+    }
+
+    /// Encrypt the final chunk in place, appending the verification tag
+    pub fn encrypt_last(
+        &mut self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[u8],
+    ) -> Result<usize, Error> {
+        let nonce = self.nonce.current(true)?;
+        let len = self.key.encrypt_in_place(buffer, nonce, aad)?;
+        self.nonce.advance(true)?;
+        Ok(len)
+    }
+
+    /// Check whether the final chunk has been encrypted
+    pub fn is_finished(&self) -> bool {
+        self.nonce.finished
+    }
+}
+
+impl<K: KeyAeadInPlace + ?Sized> Debug for EncryptStream<'_, K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptStream")
+            .field("nonce", &self.nonce)
+            .finish()
+    }
+}
+
+/// Decrypt a sequence of chunks produced using the STREAM construction
+pub struct DecryptStream<'k, K: KeyAeadInPlace + ?Sized> {
+    key: &'k K,
+    nonce: StreamNonce,
+}
+
+impl<'k, K: KeyAeadInPlace + ?Sized> DecryptStream<'k, K> {
+    /// Create a new decryption stream from a key and the nonce prefix
+    pub fn new(key: &'k K, nonce_prefix: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            key,
+            nonce: StreamNonce::new(key, nonce_prefix)?,
+        })
+    }
+
+    /// Decrypt an intermediate chunk in place
+    pub fn decrypt_chunk(
+        &mut self,
+        buffer: &mut dyn ResizeBuffer,
+        aad: &[u8],
+    ) -> Result<(), Error> {
+        let nonce = self.nonce.current(false)?;
+        self.key.decrypt_in_place(buffer, nonce, aad)?;
+        self.nonce.advance(false)
+    }
+
+    /// Decrypt the final chunk in place
+    pub fn decrypt_last(&mut self, buffer: &mut dyn ResizeBuffer, aad: &[u8]) -> Result<(), Error> {
+        let nonce = self.nonce.current(true)?;
+        self.key.decrypt_in_place(buffer, nonce, aad)?;
+        self.nonce.advance(true)
+    }
+
+    /// Check whether the final chunk has been decrypted
+    pub fn is_finished(&self) -> bool {
+        self.nonce.finished
+    }
+}
+
+impl<K: KeyAeadInPlace + ?Sized> Debug for DecryptStream<'_, K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecryptStream")
+            .field("nonce", &self.nonce)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "chacha"))]
+mod tests {
+    use super::*;
+    use crate::{
+        alg::chacha20::{Chacha20Key, XC20P},
+        buffer::SecretBytes,
+        repr::KeyGen,
+    };
+
+    #[test]
+    fn stream_round_trip() {
+        let key = Chacha20Key::<XC20P>::random().unwrap();
+        let prefix = [1u8; 19];
+        assert_eq!(nonce_prefix_length(&key).unwrap(), prefix.len());
+        assert!(EncryptStream::new(&key, &prefix[..4]).is_err());
+
+        let mut enc = EncryptStream::new(&key, &prefix).unwrap();
+        let mut chunks = [
+            SecretBytes::from_slice(b"first chunk"),
+            SecretBytes::from_slice(b"second chunk"),
+            SecretBytes::from_slice(b"last chunk"),
+        ];
+        enc.encrypt_chunk(&mut chunks[0], &[]).unwrap();
+        enc.encrypt_chunk(&mut chunks[1], &[]).unwrap();
+        enc.encrypt_last(&mut chunks[2], &[]).unwrap();
+        assert!(enc.is_finished());
+        assert!(enc
+            .encrypt_chunk(&mut SecretBytes::from_slice(b"extra"), &[])
+            .is_err());
+
+        let mut dec = DecryptStream::new(&key, &prefix).unwrap();
+        // chunks must be decrypted in order
+        assert!(dec.decrypt_chunk(&mut chunks[1].clone(), &[]).is_err());
+        // an intermediate chunk cannot be decrypted as the final chunk
+        assert!(dec.decrypt_last(&mut chunks[0].clone(), &[]).is_err());
+        // the final chunk cannot be decrypted as an intermediate chunk
+        let mut dec_trunc = DecryptStream::new(&key, &prefix).unwrap();
+        dec_trunc
+            .decrypt_chunk(&mut chunks[0].clone(), &[])
+            .unwrap();
+        dec_trunc
+            .decrypt_chunk(&mut chunks[1].clone(), &[])
+            .unwrap();
+        assert!(dec_trunc
+            .decrypt_chunk(&mut chunks[2].clone(), &[])
+            .is_err());
+
+        for (idx, chunk) in chunks.iter_mut().enumerate() {
+            if idx == 2 {
+                dec.decrypt_last(chunk, &[]).unwrap();
+            } else {
+                dec.decrypt_chunk(chunk, &[]).unwrap();
+            }
+        }
+        assert!(dec.is_finished());
+        assert_eq!(&chunks[0][..], b"first chunk");
+        assert_eq!(&chunks[1][..], b"second chunk");
+        assert_eq!(&chunks[2][..], b"last chunk");
+    }
+}
@@ -0,0 +1,149 @@
+//! Unauthenticated cipher modes for legacy interoperability
+//!
+//! These modes provide confidentiality only: ciphertexts are malleable and
+//! decryption does not detect tampering. They are intended solely for reading
+//! and producing legacy formats, and an AEAD algorithm should be used otherwise.
+
+use cbc::{Decryptor as CbcDec, Encryptor as CbcEnc};
+use cipher::{
+    block_padding::Pkcs7, consts::U16, BlockCipher, BlockDecryptMut, BlockEncryptMut,
+    BlockSizeUser, IvSizeUser, KeyInit, KeyIvInit, StreamCipher, StreamCipherSeek,
+};
+use ctr::Ctr128BE;
+
+use crate::{buffer::ResizeBuffer, error::Error, generic_array::typenum::Unsigned};
+
+/// Unauthenticated encryption operations
+pub trait KeyUnauthenticatedCipher {
+    /// Apply the AES-CTR keystream to a buffer in place, encrypting or
+    /// decrypting it
+    ///
+    /// The 16-byte initial counter block is incremented as a 128-bit
+    /// big-endian integer.
+    fn apply_ctr_unauthenticated(&self, _buffer: &mut [u8], _iv: &[u8]) -> Result<(), Error> {
+        Err(err_msg!(
+            Unsupported,
+            "AES-CTR not supported for this key type"
+        ))
+    }
+
+    /// Encrypt a buffer in place using AES-CBC with PKCS#7 padding
+    fn encrypt_cbc_unauthenticated(
+        &self,
+        _buffer: &mut dyn ResizeBuffer,
+        _iv: &[u8],
+    ) -> Result<(), Error> {
+        Err(err_msg!(
+            Unsupported,
+            "AES-CBC not supported for this key type"
+        ))
+    }
+
+    /// Decrypt a buffer in place using AES-CBC, removing the PKCS#7 padding
+    fn decrypt_cbc_unauthenticated(
+        &self,
+        _buffer: &mut dyn ResizeBuffer,
+        _iv: &[u8],
+    ) -> Result<(), Error> {
+        Err(err_msg!(
+            Unsupported,
+            "AES-CBC not supported for this key type"
+        ))
+    }
+
+    /// Apply the raw ChaCha20 (or XChaCha20) keystream to a buffer in place,
+    /// encrypting or decrypting it
+    ///
+    /// The keystream starts at the given 64-byte block counter. Messages
+    /// produced by a detached-tag ChaCha20-Poly1305 encryption begin at block 1.
+    fn apply_chacha20_unauthenticated(
+        &self,
+        _buffer: &mut [u8],
+        _nonce: &[u8],
+        _counter: u32,
+    ) -> Result<(), Error> {
+        Err(err_msg!(
+            Unsupported,
+            "ChaCha20 not supported for this key type"
+        ))
+    }
+}
+
+fn check_iv<C: BlockCipher>(iv: &[u8]) -> Result<(), Error> {
+    if iv.len() != C::BlockSize::USIZE {
+        Err(err_msg!(InvalidNonce))
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn ctr_apply<C>(key: &[u8], iv: &[u8], buffer: &mut [u8]) -> Result<(), Error>
+where
+    C: BlockCipher + BlockEncryptMut + BlockSizeUser<BlockSize = U16> + KeyInit,
+    Ctr128BE<C>: KeyIvInit + StreamCipher,
+{
+    check_iv::<C>(iv)?;
+    let mut cipher = <Ctr128BE<C> as KeyIvInit>::new_from_slices(key, iv)
+        .map_err(|_| err_msg!(InvalidKeyData))?;
+    cipher
+        .try_apply_keystream(buffer)
+        .map_err(|_| err_msg!(Encryption, "AES-CTR message size exceeds maximum supported"))
+}
+
+pub(crate) fn cbc_encrypt<C>(
+    key: &[u8],
+    iv: &[u8],
+    buffer: &mut dyn ResizeBuffer,
+) -> Result<(), Error>
+where
+    C: BlockCipher + BlockEncryptMut + KeyInit,
+{
+    check_iv::<C>(iv)?;
+    let msg_len = buffer.as_ref().len();
+    let pad_len = C::BlockSize::USIZE - (msg_len % C::BlockSize::USIZE);
+    buffer.buffer_extend(pad_len)?;
+    <CbcEnc<C> as KeyIvInit>::new_from_slices(key, iv)
+        .map_err(|_| err_msg!(InvalidKeyData))?
+        .encrypt_padded_mut::<Pkcs7>(buffer.as_mut(), msg_len)
+        .map_err(|_| err_msg!(Encryption, "AES-CBC encryption error"))?;
+    Ok(())
+}
+
+pub(crate) fn cbc_decrypt<C>(
+    key: &[u8],
+    iv: &[u8],
+    buffer: &mut dyn ResizeBuffer,
+) -> Result<(), Error>
+where
+    C: BlockCipher + BlockDecryptMut + KeyInit,
+{
+    check_iv::<C>(iv)?;
+    let dec_len = <CbcDec<C> as KeyIvInit>::new_from_slices(key, iv)
+        .map_err(|_| err_msg!(InvalidKeyData))?
+        .decrypt_padded_mut::<Pkcs7>(buffer.as_mut())
+        .map_err(|_| err_msg!(Encryption, "AES-CBC decryption error"))?
+        .len();
+    buffer.buffer_resize(dec_len)?;
+    Ok(())
+}
+
+pub(crate) fn stream_apply<C>(
+    key: &[u8],
+    nonce: &[u8],
+    counter: u32,
+    buffer: &mut [u8],
+) -> Result<(), Error>
+where
+    C: KeyIvInit + StreamCipher + StreamCipherSeek,
+{
+    if nonce.len() != <C as IvSizeUser>::IvSize::USIZE {
+        return Err(err_msg!(InvalidNonce));
+    }
+    let mut cipher = C::new_from_slices(key, nonce).map_err(|_| err_msg!(InvalidKeyData))?;
+    cipher
+        .try_seek(counter as u64 * 64)
+        .map_err(|_| err_msg!(Usage, "Invalid keystream position"))?;
+    cipher
+        .try_apply_keystream(buffer)
+        .map_err(|_| err_msg!(Encryption, "Message size exceeds maximum supported"))
+}
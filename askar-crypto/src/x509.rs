@@ -0,0 +1,703 @@
+//! X.509 certificate and certificate signing request support
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{str::FromStr, time::Duration};
+
+use x509_cert::{
+    attr::Attribute,
+    der::{
+        asn1::{
+            Any, BitString, GeneralizedTime, Ia5String, Null, OctetString, SetOfVec, UintRef,
+            UtcTime,
+        },
+        oid::{AssociatedOid, ObjectIdentifier},
+        pem::{self, LineEnding},
+        Decode, DecodeValue, Encode, EncodeValue, Header, Length, Reader, Sequence, Writer,
+    },
+    ext::{
+        pkix::{name::GeneralName, BasicConstraints, KeyUsage, KeyUsages},
+        Extension,
+    },
+    name::Name,
+    request::{self, CertReq, CertReqInfo, ExtensionReq},
+    serial_number::SerialNumber,
+    spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned},
+    time::{Time, Validity},
+    Certificate, TbsCertificate, Version,
+};
+
+use crate::{
+    alg::{EcCurves, KeyAlg},
+    error::Error,
+    pkcs8::{public_key_alg, FromPkcs8, ToPkcs8},
+    random::try_fill_random,
+    sign::{KeySigVerify, KeySign, SignatureType},
+};
+
+/// The PEM label of an X.509 certificate
+pub const PEM_LABEL_CERTIFICATE: &str = "CERTIFICATE";
+/// The PEM label of a PKCS#10 certificate signing request
+pub const PEM_LABEL_CERTIFICATE_REQUEST: &str = "CERTIFICATE REQUEST";
+
+const OID_ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+const OID_ED448: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.113");
+const OID_ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const OID_ECDSA_WITH_SHA384: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.3");
+const OID_SHA256_WITH_RSA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.11");
+
+/// A subject alternative name of a certificate
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubjectAltName {
+    /// A DNS host name
+    DnsName(String),
+    /// An e-mail address
+    Email(String),
+    /// A uniform resource identifier
+    Uri(String),
+    /// An IPv4 or IPv6 address in network byte order
+    IpAddress(Vec<u8>),
+}
+
+impl SubjectAltName {
+    fn to_general_name(&self) -> Result<GeneralName, Error> {
+        let ia5 = |value: &str| {
+            Ia5String::new(value).map_err(|_| err_msg!(Usage, "Invalid subject alternative name"))
+        };
+        Ok(match self {
+            Self::DnsName(name) => GeneralName::DnsName(ia5(name)?),
+            Self::Email(email) => GeneralName::Rfc822Name(ia5(email)?),
+            Self::Uri(uri) => GeneralName::UniformResourceIdentifier(ia5(uri)?),
+            Self::IpAddress(addr) => {
+                if addr.len() != 4 && addr.len() != 16 {
+                    return Err(err_msg!(Usage, "Invalid IP address length"));
+                }
+                GeneralName::IpAddress(
+                    OctetString::new(addr.as_slice()).map_err(|_| encoding_error())?,
+                )
+            }
+        })
+    }
+
+    fn from_general_name(name: &GeneralName) -> Option<Self> {
+        match name {
+            GeneralName::DnsName(name) => Some(Self::DnsName(name.to_string())),
+            GeneralName::Rfc822Name(email) => Some(Self::Email(email.to_string())),
+            GeneralName::UniformResourceIdentifier(uri) => Some(Self::Uri(uri.to_string())),
+            GeneralName::IpAddress(addr) => Some(Self::IpAddress(addr.as_bytes().to_vec())),
+            _ => None,
+        }
+    }
+}
+
+/// A builder for self-signed certificates and certificate signing requests
+#[derive(Clone, Debug)]
+pub struct CertificateBuilder {
+    subject: Name,
+    alt_names: Vec<SubjectAltName>,
+    not_before: u64,
+    not_after: Option<u64>,
+    serial_number: Option<Vec<u8>>,
+    ca: bool,
+}
+
+impl CertificateBuilder {
+    /// Create a new builder from an RFC 4514 distinguished name, such as `CN=example.com,O=Org`
+    pub fn new(subject: &str) -> Result<Self, Error> {
+        let subject =
+            Name::from_str(subject).map_err(|_| err_msg!(Usage, "Invalid distinguished name"))?;
+        Ok(Self {
+            subject,
+            alt_names: Vec::new(),
+            not_before: 0,
+            not_after: None,
+            serial_number: None,
+            ca: false,
+        })
+    }
+
+    /// Add a subject alternative name
+    pub fn alt_name(mut self, name: SubjectAltName) -> Self {
+        self.alt_names.push(name);
+        self
+    }
+
+    /// Set the validity period in seconds since the Unix epoch
+    pub fn validity(mut self, not_before: u64, not_after: u64) -> Self {
+        self.not_before = not_before;
+        self.not_after = Some(not_after);
+        self
+    }
+
+    /// Set the serial number, otherwise a random serial number is assigned
+    pub fn serial_number(mut self, serial: &[u8]) -> Self {
+        self.serial_number = Some(serial.to_vec());
+        self
+    }
+
+    /// Mark the certificate as a certificate authority
+    pub fn ca(mut self, ca: bool) -> Self {
+        self.ca = ca;
+        self
+    }
+
+    /// Produce a certificate for the public key, signed by the same key
+    pub fn self_signed(
+        &self,
+        key: &(impl KeySign + ToPkcs8 + ?Sized),
+    ) -> Result<X509Certificate, Error> {
+        let not_after = self
+            .not_after
+            .ok_or_else(|| err_msg!(Usage, "Certificate validity period must be set"))?;
+        if not_after <= self.not_before {
+            return Err(err_msg!(Usage, "Invalid certificate validity period"));
+        }
+        let (sig_alg, public_key) = SignatureAlg::for_key(key)?;
+        let serial_number = match self.serial_number.as_deref() {
+            Some(serial) => SerialNumber::new(serial),
+            None => {
+                let mut serial = [0u8; 16];
+                try_fill_random(&mut serial)?;
+                // ensure a positive integer with no leading zero byte
+                serial[0] = (serial[0] & 0x7f) | 0x40;
+                SerialNumber::new(&serial)
+            }
+        }
+        .map_err(|_| err_msg!(Usage, "Invalid certificate serial number"))?;
+        let algorithm = sig_alg.identifier()?;
+        let tbs_certificate = TbsCertificate {
+            version: Version::V3,
+            serial_number,
+            signature: algorithm.clone(),
+            issuer: self.subject.clone(),
+            validity: Validity {
+                not_before: encode_time(self.not_before)?,
+                not_after: encode_time(not_after)?,
+            },
+            subject: self.subject.clone(),
+            subject_public_key_info: public_key,
+            issuer_unique_id: None,
+            subject_unique_id: None,
+            extensions: Some(self.extensions()?),
+        };
+        let tbs = tbs_certificate.to_der().map_err(|_| encoding_error())?;
+        let signature = sig_alg.sign(key, &tbs)?;
+        Ok(X509Certificate {
+            inner: Certificate {
+                tbs_certificate,
+                signature_algorithm: algorithm,
+                signature,
+            },
+        })
+    }
+
+    /// Produce a DER-encoded PKCS#10 certificate signing request
+    pub fn certificate_request(
+        &self,
+        key: &(impl KeySign + ToPkcs8 + ?Sized),
+    ) -> Result<Vec<u8>, Error> {
+        let (sig_alg, public_key) = SignatureAlg::for_key(key)?;
+        let attributes = match self.alt_names_extension()? {
+            Some(alt_names) => {
+                let ext_req = Any::encode_from(&ExtensionReq(vec![alt_names]))
+                    .map_err(|_| encoding_error())?;
+                let attr = Attribute {
+                    oid: ExtensionReq::OID,
+                    values: SetOfVec::try_from(vec![ext_req]).map_err(|_| encoding_error())?,
+                };
+                SetOfVec::try_from(vec![attr]).map_err(|_| encoding_error())?
+            }
+            None => SetOfVec::new(),
+        };
+        let info = CertReqInfo {
+            version: request::Version::V1,
+            subject: self.subject.clone(),
+            public_key,
+            attributes,
+        };
+        let tbs = info.to_der().map_err(|_| encoding_error())?;
+        let signature = sig_alg.sign(key, &tbs)?;
+        CertReq {
+            info,
+            algorithm: sig_alg.identifier()?,
+            signature,
+        }
+        .to_der()
+        .map_err(|_| encoding_error())
+    }
+
+    /// Produce a PEM-encoded PKCS#10 certificate signing request
+    pub fn certificate_request_pem(
+        &self,
+        key: &(impl KeySign + ToPkcs8 + ?Sized),
+    ) -> Result<String, Error> {
+        encode_pem(
+            PEM_LABEL_CERTIFICATE_REQUEST,
+            &self.certificate_request(key)?,
+        )
+    }
+
+    fn extensions(&self) -> Result<Vec<Extension>, Error> {
+        let mut usage = KeyUsage(KeyUsages::DigitalSignature.into());
+        if self.ca {
+            usage.0 |= KeyUsages::KeyCertSign;
+        }
+        let mut exts = vec![
+            encode_extension(
+                &BasicConstraints {
+                    ca: self.ca,
+                    path_len_constraint: None,
+                },
+                true,
+            )?,
+            encode_extension(&usage, true)?,
+        ];
+        exts.extend(self.alt_names_extension()?);
+        Ok(exts)
+    }
+
+    fn alt_names_extension(&self) -> Result<Option<Extension>, Error> {
+        if self.alt_names.is_empty() {
+            return Ok(None);
+        }
+        let names = self
+            .alt_names
+            .iter()
+            .map(SubjectAltName::to_general_name)
+            .collect::<Result<Vec<_>, _>>()?;
+        encode_extension(&x509_cert::ext::pkix::SubjectAltName(names), false).map(Some)
+    }
+}
+
+/// A parsed X.509 certificate
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct X509Certificate {
+    inner: Certificate,
+}
+
+impl X509Certificate {
+    /// Parse a DER-encoded certificate
+    pub fn from_der(der: &[u8]) -> Result<Self, Error> {
+        let inner = Certificate::from_der(der)
+            .map_err(|_| err_msg!(Invalid, "Invalid X.509 certificate"))?;
+        Ok(Self { inner })
+    }
+
+    /// Parse a PEM-encoded certificate
+    pub fn from_pem(pem: &str) -> Result<Self, Error> {
+        let (label, der) = pem::decode_vec(pem.as_bytes())
+            .map_err(|_| err_msg!(Invalid, "Invalid PEM document"))?;
+        if label != PEM_LABEL_CERTIFICATE {
+            return Err(err_msg!(Invalid, "Unexpected PEM label"));
+        }
+        Self::from_der(&der)
+    }
+
+    /// Encode the certificate in DER format
+    pub fn to_der(&self) -> Result<Vec<u8>, Error> {
+        self.inner.to_der().map_err(|_| encoding_error())
+    }
+
+    /// Encode the certificate in PEM format
+    pub fn to_pem(&self) -> Result<String, Error> {
+        encode_pem(PEM_LABEL_CERTIFICATE, &self.to_der()?)
+    }
+
+    /// Accessor for the subject distinguished name
+    pub fn subject(&self) -> String {
+        self.inner.tbs_certificate.subject.to_string()
+    }
+
+    /// Accessor for the issuer distinguished name
+    pub fn issuer(&self) -> String {
+        self.inner.tbs_certificate.issuer.to_string()
+    }
+
+    /// Accessor for the serial number
+    pub fn serial_number(&self) -> &[u8] {
+        self.inner.tbs_certificate.serial_number.as_bytes()
+    }
+
+    /// Accessor for the start of the validity period in seconds since the Unix epoch
+    pub fn not_before(&self) -> u64 {
+        self.inner
+            .tbs_certificate
+            .validity
+            .not_before
+            .to_unix_duration()
+            .as_secs()
+    }
+
+    /// Accessor for the end of the validity period in seconds since the Unix epoch
+    pub fn not_after(&self) -> u64 {
+        self.inner
+            .tbs_certificate
+            .validity
+            .not_after
+            .to_unix_duration()
+            .as_secs()
+    }
+
+    /// Check if the certificate is within its validity period
+    pub fn is_valid_at(&self, now: u64) -> bool {
+        self.not_before() <= now && now <= self.not_after()
+    }
+
+    /// Get the supported subject alternative names of the certificate
+    pub fn subject_alt_names(&self) -> Result<Vec<SubjectAltName>, Error> {
+        let Some(ext) = self.extension(&x509_cert::ext::pkix::SubjectAltName::OID) else {
+            return Ok(Vec::new());
+        };
+        let names = x509_cert::ext::pkix::SubjectAltName::from_der(ext.extn_value.as_bytes())
+            .map_err(|_| err_msg!(Invalid, "Invalid subject alternative names"))?;
+        Ok(names
+            .0
+            .iter()
+            .filter_map(SubjectAltName::from_general_name)
+            .collect())
+    }
+
+    /// Check if the certificate is marked as a certificate authority
+    pub fn is_ca(&self) -> Result<bool, Error> {
+        let Some(ext) = self.extension(&BasicConstraints::OID) else {
+            return Ok(false);
+        };
+        let constraints = BasicConstraints::from_der(ext.extn_value.as_bytes())
+            .map_err(|_| err_msg!(Invalid, "Invalid basic constraints"))?;
+        Ok(constraints.ca)
+    }
+
+    /// Get the DER-encoded SubjectPublicKeyInfo of the certificate
+    pub fn public_key_der(&self) -> Result<Vec<u8>, Error> {
+        self.inner
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(|_| encoding_error())
+    }
+
+    /// Load the public key of the certificate
+    pub fn public_key<K: FromPkcs8>(&self) -> Result<K, Error> {
+        K::from_public_key_der(&self.public_key_der()?)
+    }
+
+    /// Verify the certificate signature using the public key of the issuer
+    pub fn verify_signature(
+        &self,
+        issuer: &(impl KeySigVerify + ToPkcs8 + ?Sized),
+    ) -> Result<bool, Error> {
+        let (sig_alg, _) = SignatureAlg::for_key(issuer)?;
+        if self.inner.signature_algorithm.oid != sig_alg.oid
+            || self.inner.tbs_certificate.signature != self.inner.signature_algorithm
+        {
+            return Ok(false);
+        }
+        let Some(signature) = self.inner.signature.as_bytes() else {
+            return Ok(false);
+        };
+        let tbs = self
+            .inner
+            .tbs_certificate
+            .to_der()
+            .map_err(|_| encoding_error())?;
+        sig_alg.verify(issuer, &tbs, signature)
+    }
+
+    fn extension(&self, oid: &ObjectIdentifier) -> Option<&Extension> {
+        self.inner
+            .tbs_certificate
+            .extensions
+            .as_ref()?
+            .iter()
+            .find(|ext| ext.extn_id == *oid)
+    }
+}
+
+/// The signature algorithm associated with a signing key
+#[derive(Clone, Copy, Debug)]
+struct SignatureAlg {
+    oid: ObjectIdentifier,
+    sig_type: SignatureType,
+    // the length of the ECDSA signature scalars, if applicable
+    ecdsa_len: Option<usize>,
+}
+
+impl SignatureAlg {
+    fn for_key(key: &(impl ToPkcs8 + ?Sized)) -> Result<(Self, SubjectPublicKeyInfoOwned), Error> {
+        let spki = key.to_public_key_der()?;
+        let (oid, sig_type, ecdsa_len) = match public_key_alg(&spki)? {
+            KeyAlg::Ed25519 => (OID_ED25519, SignatureType::EdDSA, None),
+            KeyAlg::Ed448 => (OID_ED448, SignatureType::Ed448, None),
+            KeyAlg::EcCurve(EcCurves::Secp256r1) => {
+                (OID_ECDSA_WITH_SHA256, SignatureType::ES256, Some(32))
+            }
+            KeyAlg::EcCurve(EcCurves::Secp256k1) => {
+                (OID_ECDSA_WITH_SHA256, SignatureType::ES256K, Some(32))
+            }
+            KeyAlg::EcCurve(EcCurves::Secp384r1) => {
+                (OID_ECDSA_WITH_SHA384, SignatureType::ES384, Some(48))
+            }
+            KeyAlg::Rsa(_) => (OID_SHA256_WITH_RSA, SignatureType::RS256, None),
+            _ => {
+                return Err(err_msg!(
+                    Unsupported,
+                    "Unsupported key type for X.509 signatures"
+                ))
+            }
+        };
+        let spki = SubjectPublicKeyInfoOwned::from_der(&spki).map_err(|_| encoding_error())?;
+        Ok((
+            Self {
+                oid,
+                sig_type,
+                ecdsa_len,
+            },
+            spki,
+        ))
+    }
+
+    fn identifier(&self) -> Result<AlgorithmIdentifierOwned, Error> {
+        // RSA signature algorithms carry explicit NULL parameters
+        let parameters = if self.sig_type == SignatureType::RS256 {
+            Some(Any::encode_from(&Null).map_err(|_| encoding_error())?)
+        } else {
+            None
+        };
+        Ok(AlgorithmIdentifierOwned {
+            oid: self.oid,
+            parameters,
+        })
+    }
+
+    fn sign(&self, key: &(impl KeySign + ?Sized), tbs: &[u8]) -> Result<BitString, Error> {
+        let sig = key.create_signature(tbs, Some(self.sig_type))?;
+        let sig = if self.ecdsa_len.is_some() {
+            EcdsaSignature::from_raw(&sig)?
+                .to_der()
+                .map_err(|_| encoding_error())?
+        } else {
+            sig.into_vec()
+        };
+        BitString::from_bytes(&sig).map_err(|_| encoding_error())
+    }
+
+    fn verify(
+        &self,
+        key: &(impl KeySigVerify + ?Sized),
+        tbs: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, Error> {
+        match self.ecdsa_len {
+            Some(len) => match EcdsaSignature::from_der(signature)
+                .ok()
+                .and_then(|sig| sig.to_raw(len))
+            {
+                Some(raw) => key.verify_signature(tbs, &raw, Some(self.sig_type)),
+                None => Ok(false),
+            },
+            None => key.verify_signature(tbs, signature, Some(self.sig_type)),
+        }
+    }
+}
+
+/// The DER encoding of an ECDSA signature (RFC 3279)
+struct EcdsaSignature<'a> {
+    r: UintRef<'a>,
+    s: UintRef<'a>,
+}
+
+impl<'a> EcdsaSignature<'a> {
+    fn from_raw(raw: &'a [u8]) -> Result<Self, Error> {
+        let (r, s) = raw.split_at(raw.len() / 2);
+        Ok(Self {
+            r: UintRef::new(r).map_err(|_| encoding_error())?,
+            s: UintRef::new(s).map_err(|_| encoding_error())?,
+        })
+    }
+
+    fn to_raw(&self, len: usize) -> Option<Vec<u8>> {
+        let mut raw = vec![0u8; len * 2];
+        for (idx, value) in [self.r.as_bytes(), self.s.as_bytes()]
+            .into_iter()
+            .enumerate()
+        {
+            if value.len() > len {
+                return None;
+            }
+            let end = (idx + 1) * len;
+            raw[end - value.len()..end].copy_from_slice(value);
+        }
+        Some(raw)
+    }
+}
+
+impl<'a> DecodeValue<'a> for EcdsaSignature<'a> {
+    fn decode_value<R: Reader<'a>>(reader: &mut R, header: Header) -> x509_cert::der::Result<Self> {
+        reader.read_nested(header.length, |reader| {
+            Ok(Self {
+                r: reader.decode()?,
+                s: reader.decode()?,
+            })
+        })
+    }
+}
+
+impl EncodeValue for EcdsaSignature<'_> {
+    fn value_len(&self) -> x509_cert::der::Result<Length> {
+        self.r.encoded_len()? + self.s.encoded_len()?
+    }
+
+    fn encode_value(&self, writer: &mut impl Writer) -> x509_cert::der::Result<()> {
+        self.r.encode(writer)?;
+        self.s.encode(writer)
+    }
+}
+
+impl<'a> Sequence<'a> for EcdsaSignature<'a> {}
+
+#[inline]
+fn encoding_error() -> Error {
+    err_msg!(Unexpected, "Error encoding X.509 structure")
+}
+
+fn encode_pem(label: &str, der: &[u8]) -> Result<String, Error> {
+    pem::encode_string(label, LineEnding::LF, der).map_err(|_| encoding_error())
+}
+
+fn encode_extension<E: AssociatedOid + Encode>(
+    value: &E,
+    critical: bool,
+) -> Result<Extension, Error> {
+    Ok(Extension {
+        extn_id: E::OID,
+        critical,
+        extn_value: OctetString::new(value.to_der().map_err(|_| encoding_error())?)
+            .map_err(|_| encoding_error())?,
+    })
+}
+
+/// Encode a timestamp as UTCTime through 2049, and GeneralizedTime afterward (RFC 5280)
+fn encode_time(secs: u64) -> Result<Time, Error> {
+    let time = Duration::from_secs(secs);
+    match UtcTime::from_unix_duration(time) {
+        Ok(time) => Ok(Time::UtcTime(time)),
+        Err(_) => GeneralizedTime::from_unix_duration(time)
+            .map(Time::GeneralTime)
+            .map_err(|_| err_msg!(Usage, "Invalid certificate validity period")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alg::{ed25519::Ed25519KeyPair, p256::P256KeyPair},
+        repr::{KeyGen, ToPublicBytes},
+    };
+
+    #[test]
+    fn self_signed_p256() {
+        let key = P256KeyPair::random().unwrap();
+        let cert = CertificateBuilder::new("CN=example.com,O=Example")
+            .unwrap()
+            .alt_name(SubjectAltName::DnsName("example.com".into()))
+            .alt_name(SubjectAltName::IpAddress(vec![127, 0, 0, 1]))
+            .validity(1700000000, 1800000000)
+            .serial_number(&[0x01, 0x02])
+            .self_signed(&key)
+            .unwrap();
+
+        let parsed = X509Certificate::from_pem(&cert.to_pem().unwrap()).unwrap();
+        assert_eq!(parsed, cert);
+        assert_eq!(parsed.subject(), "CN=example.com,O=Example");
+        assert_eq!(parsed.issuer(), parsed.subject());
+        assert_eq!(parsed.serial_number(), &[0x01, 0x02]);
+        assert_eq!(parsed.not_before(), 1700000000);
+        assert_eq!(parsed.not_after(), 1800000000);
+        assert!(parsed.is_valid_at(1750000000));
+        assert!(!parsed.is_valid_at(1800000001));
+        assert!(!parsed.is_ca().unwrap());
+        assert_eq!(
+            parsed.subject_alt_names().unwrap(),
+            vec![
+                SubjectAltName::DnsName("example.com".into()),
+                SubjectAltName::IpAddress(vec![127, 0, 0, 1]),
+            ]
+        );
+
+        let public = parsed.public_key::<P256KeyPair>().unwrap();
+        assert_eq!(
+            public.to_public_bytes().unwrap(),
+            key.to_public_bytes().unwrap()
+        );
+        assert!(parsed.verify_signature(&public).unwrap());
+        let other = P256KeyPair::random().unwrap();
+        assert!(!parsed.verify_signature(&other).unwrap());
+        let other = Ed25519KeyPair::random().unwrap();
+        assert!(!parsed.verify_signature(&other).unwrap());
+    }
+
+    #[test]
+    fn self_signed_ed25519() {
+        let key = Ed25519KeyPair::random().unwrap();
+        let cert = CertificateBuilder::new("CN=Test CA")
+            .unwrap()
+            .validity(0, 4102444800)
+            .ca(true)
+            .self_signed(&key)
+            .unwrap();
+        let parsed = X509Certificate::from_der(&cert.to_der().unwrap()).unwrap();
+        assert!(parsed.is_ca().unwrap());
+        // dates from 2050 are encoded as GeneralizedTime
+        assert_eq!(parsed.not_after(), 4102444800);
+        assert!(parsed.verify_signature(&key).unwrap());
+
+        // validity is required
+        assert!(CertificateBuilder::new("CN=Test")
+            .unwrap()
+            .self_signed(&key)
+            .is_err());
+        // key agreement keys cannot sign
+        #[cfg(feature = "any_key")]
+        {
+            use alloc::boxed::Box;
+
+            use crate::alg::{AnyKey, AnyKeyCreate, KeyAlg};
+
+            let key = Box::<AnyKey>::random(KeyAlg::X25519).unwrap();
+            assert!(CertificateBuilder::new("CN=Test")
+                .unwrap()
+                .validity(0, 1)
+                .self_signed(&*key)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn certificate_request() {
+        let key = P256KeyPair::random().unwrap();
+        let csr = CertificateBuilder::new("CN=client")
+            .unwrap()
+            .alt_name(SubjectAltName::Uri("did:example:123".into()))
+            .certificate_request(&key)
+            .unwrap();
+        let parsed = CertReq::from_der(&csr).unwrap();
+        assert_eq!(parsed.info.subject.to_string(), "CN=client");
+        assert_eq!(parsed.algorithm.oid, OID_ECDSA_WITH_SHA256);
+        assert_eq!(parsed.info.attributes.len(), 1);
+        let tbs = parsed.info.to_der().unwrap();
+        let sig = SignatureAlg::for_key(&key).unwrap().0;
+        assert!(sig
+            .verify(&key, &tbs, parsed.signature.as_bytes().unwrap())
+            .unwrap());
+
+        let pem = CertificateBuilder::new("CN=client")
+            .unwrap()
+            .certificate_request_pem(&key)
+            .unwrap();
+        assert!(pem.starts_with("-----BEGIN CERTIFICATE REQUEST-----"));
+    }
+}
@@ -0,0 +1,33 @@
+//! The random source is installed once per process, so this test runs in its
+//! own binary to avoid affecting key generation in other tests.
+
+#![cfg(feature = "default_rng")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use askar_crypto::random::{
+    fill_random, fill_random_deterministic, set_random_source, RandomSource,
+};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn counter_source(buf: &mut [u8]) {
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    fill_random_deterministic(&count.to_le_bytes(), buf).unwrap();
+}
+
+static SOURCE: RandomSource = counter_source;
+static OTHER_SOURCE: RandomSource = counter_source;
+
+#[test]
+fn random_source_override() {
+    set_random_source(&SOURCE).expect("Error installing random source");
+    assert!(set_random_source(&OTHER_SOURCE).is_err());
+
+    let mut output = [0u8; 32];
+    fill_random(&mut output);
+    let mut expected = [0u8; 32];
+    fill_random_deterministic(&0usize.to_le_bytes(), &mut expected).unwrap();
+    assert_eq!(output, expected);
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 1);
+}
@@ -1,20 +1,36 @@
 //! Generic backend support
 
-use std::{fmt::Debug, sync::Arc};
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
 
 use super::{Backend, BackendSession, ManageBackend};
 use crate::{
-    backend::OrderBy,
-    entry::{Entry, EntryKind, EntryOperation, EntryTag, Scan, TagFilter},
+    backend::{OrderBy, RekeyProgress},
+    crypto::buffer::SecretBytes,
+    entry::{
+        AuditRecord, Entry, EntryKind, EntryOperation, EntryTag, EntryUpdate, EntryVersion,
+        ProfileDetails, Scan, ScanPosition, Subscription, TagFilter,
+    },
     error::Error,
     future::BoxFuture,
     options::IntoOptions,
     protect::{PassKey, StoreKeyMethod},
 };
 
+#[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+use super::indexeddb;
+
+#[cfg(feature = "mssql")]
+use super::mssql;
+
 #[cfg(feature = "postgres")]
 use super::postgres;
 
+#[cfg(feature = "redb")]
+use super::redb;
+
+#[cfg(feature = "remote")]
+use super::remote;
+
 #[cfg(feature = "sqlite")]
 use super::sqlite;
 
@@ -59,11 +75,60 @@ impl<B: Backend> Backend for WrapBackend<B> {
         self.0.list_profiles()
     }
 
+    #[inline]
+    fn list_profile_details(
+        &self,
+        name_prefix: Option<String>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<ProfileDetails>, Error>> {
+        self.0.list_profile_details(name_prefix, offset, limit)
+    }
+
+    #[inline]
+    fn set_soft_delete(&self, enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.set_soft_delete(enabled)
+    }
+
+    #[inline]
+    fn set_audit_log(&self, enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.set_audit_log(enabled)
+    }
+
+    #[inline]
+    fn subscribe(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+    ) -> BoxFuture<'_, Result<Subscription, Error>> {
+        self.0.subscribe(profile, kind, category)
+    }
+
+    #[inline]
+    fn get_meta(&self, name: String) -> BoxFuture<'_, Result<Option<SecretBytes>, Error>> {
+        self.0.get_meta(name)
+    }
+
+    #[inline]
+    fn set_meta(
+        &self,
+        name: String,
+        value: Option<SecretBytes>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.set_meta(name, value)
+    }
+
     #[inline]
     fn remove_profile(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
         self.0.remove_profile(name)
     }
 
+    #[inline]
+    fn rename_profile(&self, from: String, to: String) -> BoxFuture<'_, Result<bool, Error>> {
+        self.0.rename_profile(from, to)
+    }
+
     #[inline]
     fn scan(
         &self,
@@ -75,12 +140,57 @@ impl<B: Backend> Backend for WrapBackend<B> {
         limit: Option<i64>,
         order_by: Option<OrderBy>,
         descending: bool,
+        include_deleted: bool,
     ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
         self.0.scan(
-            profile, kind, category, tag_filter, offset, limit, order_by, descending,
+            profile,
+            kind,
+            category,
+            tag_filter,
+            offset,
+            limit,
+            order_by,
+            descending,
+            include_deleted,
+        )
+    }
+
+    #[inline]
+    fn scan_after(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        after: ScanPosition,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        self.0.scan_after(
+            profile,
+            kind,
+            category,
+            tag_filter,
+            after,
+            limit,
+            order_by,
+            descending,
+            include_deleted,
         )
     }
 
+    #[inline]
+    fn seal_token(&self, data: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        self.0.seal_token(data)
+    }
+
+    #[inline]
+    fn open_token(&self, token: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        self.0.open_token(token)
+    }
+
     #[inline]
     fn session(&self, profile: Option<String>, transaction: bool) -> Result<Self::Session, Error> {
         Ok(AnyBackendSession(Box::new(
@@ -97,6 +207,45 @@ impl<B: Backend> Backend for WrapBackend<B> {
         self.0.rekey(method, key)
     }
 
+    #[inline]
+    fn rekey_with_progress(
+        &mut self,
+        method: StoreKeyMethod,
+        key: PassKey<'_>,
+        progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.rekey_with_progress(method, key, progress)
+    }
+
+    #[inline]
+    fn rekey_profile(
+        &self,
+        profile: String,
+        progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.rekey_profile(profile, progress)
+    }
+
+    #[inline]
+    fn add_key_slot(
+        &self,
+        name: String,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.add_key_slot(name, method, pass_key)
+    }
+
+    #[inline]
+    fn remove_key_slot(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        self.0.remove_key_slot(name)
+    }
+
+    #[inline]
+    fn list_key_slots(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        self.0.list_key_slots()
+    }
+
     #[inline]
     fn close(&self) -> BoxFuture<'_, Result<(), Error>> {
         self.0.close()
@@ -132,11 +281,60 @@ impl Backend for AnyBackend {
         self.0.list_profiles()
     }
 
+    #[inline]
+    fn list_profile_details(
+        &self,
+        name_prefix: Option<String>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<ProfileDetails>, Error>> {
+        self.0.list_profile_details(name_prefix, offset, limit)
+    }
+
+    #[inline]
+    fn set_soft_delete(&self, enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.set_soft_delete(enabled)
+    }
+
+    #[inline]
+    fn set_audit_log(&self, enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.set_audit_log(enabled)
+    }
+
+    #[inline]
+    fn subscribe(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+    ) -> BoxFuture<'_, Result<Subscription, Error>> {
+        self.0.subscribe(profile, kind, category)
+    }
+
+    #[inline]
+    fn get_meta(&self, name: String) -> BoxFuture<'_, Result<Option<SecretBytes>, Error>> {
+        self.0.get_meta(name)
+    }
+
+    #[inline]
+    fn set_meta(
+        &self,
+        name: String,
+        value: Option<SecretBytes>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.set_meta(name, value)
+    }
+
     #[inline]
     fn remove_profile(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
         self.0.remove_profile(name)
     }
 
+    #[inline]
+    fn rename_profile(&self, from: String, to: String) -> BoxFuture<'_, Result<bool, Error>> {
+        self.0.rename_profile(from, to)
+    }
+
     #[inline]
     fn scan(
         &self,
@@ -148,12 +346,57 @@ impl Backend for AnyBackend {
         limit: Option<i64>,
         order_by: Option<OrderBy>,
         descending: bool,
+        include_deleted: bool,
     ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
         self.0.scan(
-            profile, kind, category, tag_filter, offset, limit, order_by, descending,
+            profile,
+            kind,
+            category,
+            tag_filter,
+            offset,
+            limit,
+            order_by,
+            descending,
+            include_deleted,
+        )
+    }
+
+    #[inline]
+    fn scan_after(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        after: ScanPosition,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        self.0.scan_after(
+            profile,
+            kind,
+            category,
+            tag_filter,
+            after,
+            limit,
+            order_by,
+            descending,
+            include_deleted,
         )
     }
 
+    #[inline]
+    fn seal_token(&self, data: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        self.0.seal_token(data)
+    }
+
+    #[inline]
+    fn open_token(&self, token: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        self.0.open_token(token)
+    }
+
     #[inline]
     fn session(&self, profile: Option<String>, transaction: bool) -> Result<Self::Session, Error> {
         Ok(AnyBackendSession(Box::new(
@@ -175,6 +418,50 @@ impl Backend for AnyBackend {
         }
     }
 
+    #[inline]
+    fn rekey_with_progress(
+        &mut self,
+        method: StoreKeyMethod,
+        key: PassKey<'_>,
+        progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        match Arc::get_mut(&mut self.0) {
+            Some(inner) => inner.rekey_with_progress(method, key, progress),
+            None => Box::pin(std::future::ready(Err(err_msg!(
+                "Cannot re-key a store with multiple references"
+            )))),
+        }
+    }
+
+    #[inline]
+    fn rekey_profile(
+        &self,
+        profile: String,
+        progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.rekey_profile(profile, progress)
+    }
+
+    #[inline]
+    fn add_key_slot(
+        &self,
+        name: String,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.add_key_slot(name, method, pass_key)
+    }
+
+    #[inline]
+    fn remove_key_slot(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        self.0.remove_key_slot(name)
+    }
+
+    #[inline]
+    fn list_key_slots(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        self.0.list_key_slots()
+    }
+
     #[inline]
     fn close(&self) -> BoxFuture<'_, Result<(), Error>> {
         self.0.close()
@@ -192,8 +479,29 @@ impl BackendSession for AnyBackendSession {
         kind: Option<EntryKind>,
         category: Option<&'q str>,
         tag_filter: Option<TagFilter>,
+        include_deleted: bool,
     ) -> BoxFuture<'q, Result<i64, Error>> {
-        self.0.count(kind, category, tag_filter)
+        self.0.count(kind, category, tag_filter, include_deleted)
+    }
+
+    /// Count the number of matching records in the store for each category
+    fn count_by_category<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<BTreeMap<String, i64>, Error>> {
+        self.0.count_by_category(kind, tag_filter)
+    }
+
+    /// Count the matching records in a category for each distinct value of a tag
+    fn list_tag_values<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        tag_name: &'q str,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<BTreeMap<String, i64>, Error>> {
+        self.0.list_tag_values(kind, category, tag_name, tag_filter)
     }
 
     /// Fetch a single record from the store by category and name
@@ -207,6 +515,17 @@ impl BackendSession for AnyBackendSession {
         self.0.fetch(kind, category, name, for_update)
     }
 
+    /// Fetch a set of records from the store by category and name
+    fn fetch_many<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        names: &'q [String],
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Vec<Option<Entry>>, Error>> {
+        self.0.fetch_many(kind, category, names, for_update)
+    }
+
     /// Fetch all matching records from the store
     fn fetch_all<'q>(
         &'q mut self,
@@ -233,6 +552,102 @@ impl BackendSession for AnyBackendSession {
         self.0.remove_all(kind, category, tag_filter)
     }
 
+    /// Permanently remove all records which have passed their expiry time
+    fn remove_expired<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        self.0.remove_expired(kind, category)
+    }
+
+    /// Restore a record which has been soft deleted
+    fn restore<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.0.restore(kind, category, name)
+    }
+
+    /// Permanently remove soft deleted records, returning the number removed
+    fn purge<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        older_than_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        self.0.purge(kind, older_than_ms)
+    }
+
+    /// Enable or disable the preservation of prior record versions for a category
+    fn set_history<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        enabled: bool,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.0.set_history(kind, category, enabled)
+    }
+
+    /// Fetch the prior versions of a record, most recent first
+    fn fetch_history<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        limit: Option<i64>,
+    ) -> BoxFuture<'q, Result<Vec<EntryVersion>, Error>> {
+        self.0.fetch_history(kind, category, name, limit)
+    }
+
+    /// Fetch a single prior version of a record
+    fn fetch_version<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        version: i64,
+    ) -> BoxFuture<'q, Result<Option<EntryVersion>, Error>> {
+        self.0.fetch_version(kind, category, name, version)
+    }
+
+    /// Remove preserved record versions, returning the number removed
+    fn prune_history<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        older_than_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        self.0.prune_history(kind, category, older_than_ms)
+    }
+
+    /// Fetch the audit log of the profile in order of sequence number
+    fn fetch_audit_log(
+        &mut self,
+        after_id: Option<i64>,
+        limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<AuditRecord>, Error>> {
+        self.0.fetch_audit_log(after_id, limit)
+    }
+
+    /// Fetch a metadata value of the profile
+    fn get_profile_meta<'q>(
+        &'q mut self,
+        name: &'q str,
+    ) -> BoxFuture<'q, Result<Option<SecretBytes>, Error>> {
+        self.0.get_profile_meta(name)
+    }
+
+    /// Set or remove a metadata value of the profile
+    fn set_profile_meta<'q>(
+        &'q mut self,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.0.set_profile_meta(name, value)
+    }
+
     /// Insert or replace a record in the store
     #[allow(clippy::too_many_arguments)]
     fn update<'q>(
@@ -249,6 +664,54 @@ impl BackendSession for AnyBackendSession {
             .update(kind, operation, category, name, value, tags, expiry_ms)
     }
 
+    /// Replace or remove a record only if its current version matches
+    fn update_versioned<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+        version: i64,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.0.update_versioned(
+            kind, operation, category, name, value, tags, expiry_ms, version,
+        )
+    }
+
+    /// Perform a batch of record updates, returning the outcome of each
+    fn update_many<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        updates: Vec<EntryUpdate>,
+    ) -> BoxFuture<'q, Result<Vec<Result<(), Error>>, Error>> {
+        self.0.update_many(kind, updates)
+    }
+
+    /// Replace the search index terms for a record
+    fn update_search_terms<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        terms: &'q [String],
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.0.update_search_terms(kind, category, name, terms)
+    }
+
+    /// Fetch the records in a category indexed with all of the given search terms
+    fn search_values<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        terms: &'q [String],
+        limit: Option<i64>,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        self.0.search_values(kind, category, terms, limit)
+    }
+
     /// Test the connection to the store
     fn ping(&mut self) -> BoxFuture<'_, Result<(), Error>> {
         self.0.ping()
@@ -274,6 +737,20 @@ impl<'a> ManageBackend<'a> for &'a str {
             debug!("Open store with options: {:?}", &opts);
 
             match opts.scheme.as_ref() {
+                #[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+                "indexeddb" => {
+                    let opts = indexeddb::IndexedDbStoreOptions::new(opts)?;
+                    let mgr = opts.open(method, pass_key, profile).await?;
+                    Ok(into_any_backend(mgr))
+                }
+
+                #[cfg(feature = "mssql")]
+                "mssql" => {
+                    let opts = mssql::MssqlStoreOptions::new(opts)?;
+                    let mgr = opts.open(method, pass_key, profile).await?;
+                    Ok(into_any_backend(mgr))
+                }
+
                 #[cfg(feature = "postgres")]
                 "postgres" => {
                     let opts = postgres::PostgresStoreOptions::new(opts)?;
@@ -281,6 +758,20 @@ impl<'a> ManageBackend<'a> for &'a str {
                     Ok(into_any_backend(mgr))
                 }
 
+                #[cfg(feature = "redb")]
+                "redb" => {
+                    let opts = redb::RedbStoreOptions::new(opts)?;
+                    let mgr = opts.open(method, pass_key, profile).await?;
+                    Ok(into_any_backend(mgr))
+                }
+
+                #[cfg(feature = "remote")]
+                "remote" => {
+                    let opts = remote::RemoteStoreOptions::new(opts)?;
+                    let mgr = opts.open(method, pass_key, profile).await?;
+                    Ok(into_any_backend(mgr))
+                }
+
                 #[cfg(feature = "sqlite")]
                 "sqlite" => {
                     let opts = sqlite::SqliteStoreOptions::new(opts)?;
@@ -309,6 +800,20 @@ impl<'a> ManageBackend<'a> for &'a str {
             debug!("Provision store with options: {:?}", &opts);
 
             match opts.scheme.as_ref() {
+                #[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+                "indexeddb" => {
+                    let opts = indexeddb::IndexedDbStoreOptions::new(opts)?;
+                    let mgr = opts.provision(method, pass_key, profile, recreate).await?;
+                    Ok(into_any_backend(mgr))
+                }
+
+                #[cfg(feature = "mssql")]
+                "mssql" => {
+                    let opts = mssql::MssqlStoreOptions::new(opts)?;
+                    let mgr = opts.provision(method, pass_key, profile, recreate).await?;
+                    Ok(into_any_backend(mgr))
+                }
+
                 #[cfg(feature = "postgres")]
                 "postgres" => {
                     let opts = postgres::PostgresStoreOptions::new(opts)?;
@@ -316,6 +821,20 @@ impl<'a> ManageBackend<'a> for &'a str {
                     Ok(into_any_backend(mgr))
                 }
 
+                #[cfg(feature = "redb")]
+                "redb" => {
+                    let opts = redb::RedbStoreOptions::new(opts)?;
+                    let mgr = opts.provision(method, pass_key, profile, recreate).await?;
+                    Ok(into_any_backend(mgr))
+                }
+
+                #[cfg(feature = "remote")]
+                "remote" => {
+                    let opts = remote::RemoteStoreOptions::new(opts)?;
+                    let mgr = opts.provision(method, pass_key, profile, recreate).await?;
+                    Ok(into_any_backend(mgr))
+                }
+
                 #[cfg(feature = "sqlite")]
                 "sqlite" => {
                     let opts = sqlite::SqliteStoreOptions::new(opts)?;
@@ -338,12 +857,36 @@ impl<'a> ManageBackend<'a> for &'a str {
             debug!("Remove store with options: {:?}", &opts);
 
             match opts.scheme.as_ref() {
+                #[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+                "indexeddb" => {
+                    let opts = indexeddb::IndexedDbStoreOptions::new(opts)?;
+                    Ok(opts.remove().await?)
+                }
+
+                #[cfg(feature = "mssql")]
+                "mssql" => {
+                    let opts = mssql::MssqlStoreOptions::new(opts)?;
+                    Ok(opts.remove().await?)
+                }
+
                 #[cfg(feature = "postgres")]
                 "postgres" => {
                     let opts = postgres::PostgresStoreOptions::new(opts)?;
                     Ok(opts.remove().await?)
                 }
 
+                #[cfg(feature = "redb")]
+                "redb" => {
+                    let opts = redb::RedbStoreOptions::new(opts)?;
+                    Ok(opts.remove().await?)
+                }
+
+                #[cfg(feature = "remote")]
+                "remote" => {
+                    let opts = remote::RemoteStoreOptions::new(opts)?;
+                    Ok(opts.remove().await?)
+                }
+
                 #[cfg(feature = "sqlite")]
                 "sqlite" => {
                     let opts = sqlite::SqliteStoreOptions::new(opts)?;
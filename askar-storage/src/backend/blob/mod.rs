@@ -0,0 +1,887 @@
+//! Offloading of large record values to object storage
+//!
+//! A [`BlobBackend`] wraps another backend, writing record values above a
+//! size threshold to an object store (such as S3, GCS or Azure Blob Storage)
+//! while the record itself, with its tags, remains in the wrapped backend.
+//! Each offloaded value is encrypted in chunks using a random key, which is
+//! held in the record value in place of the original value.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+use futures_lite::stream;
+use object_store::{path::Path, ObjectStore};
+use serde::{Deserialize, Serialize};
+
+use super::{Backend, BackendSession, OrderBy, RekeyProgress};
+use crate::{
+    crypto::{
+        alg::chacha20::{Chacha20Key, C20P},
+        buffer::SecretBytes,
+        encrypt::KeyAeadMeta,
+        generic_array::typenum::Unsigned,
+        repr::{KeyGen, KeySecretBytes, ToSecretBytes},
+        stream::{nonce_prefix_length, DecryptStream, EncryptStream},
+    },
+    entry::{
+        AuditRecord, Entry, EntryKind, EntryOperation, EntryTag, ProfileDetails, Scan,
+        ScanPosition, Subscription, TagFilter,
+    },
+    error::Error,
+    future::{spawn_ok, unblock, BoxFuture},
+    protect::{PassKey, StoreKeyMethod},
+};
+
+pub use object_store;
+
+/// The default size above which record values are offloaded
+pub const DEFAULT_THRESHOLD: usize = 64 * 1024;
+
+// the reserved tag marking a record with an offloaded value
+const BLOB_TAG: &str = "askar:blob";
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+type BlobKey = Chacha20Key<C20P>;
+
+const CHUNK_TAG_SIZE: usize = <BlobKey as KeyAeadMeta>::TagSize::USIZE;
+
+/// A backend storing large record values in an object store
+pub struct BlobBackend<B: Backend> {
+    inner: B,
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+    threshold: usize,
+}
+
+impl<B: Backend> BlobBackend<B> {
+    /// Wrap a backend, offloading values larger than `threshold` bytes to
+    /// the object store under the given path prefix
+    pub fn new(inner: B, store: Arc<dyn ObjectStore>, prefix: &str, threshold: usize) -> Self {
+        Self {
+            inner,
+            store,
+            prefix: Path::from(prefix),
+            threshold,
+        }
+    }
+
+    /// Access the wrapped backend
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    fn blobs(&self) -> BlobStore {
+        BlobStore {
+            store: self.store.clone(),
+            prefix: self.prefix.clone(),
+        }
+    }
+
+    // resolve the blob values of the rows returned by a scan of the wrapped backend
+    fn resolve_scan(&self, scan: Scan<'static, Entry>) -> Scan<'static, Entry> {
+        let page_size = scan.page_size();
+        let blobs = self.blobs();
+        let stream = stream::unfold(Some(scan), move |scan| {
+            let blobs = blobs.clone();
+            async move {
+                let mut scan = scan?;
+                match scan.fetch_next().await {
+                    Ok(Some(rows)) => Some((blobs.resolve_all(rows).await, Some(scan))),
+                    Ok(None) => None,
+                    Err(err) => Some((Err(err), None)),
+                }
+            }
+        });
+        Scan::new(stream, page_size)
+    }
+}
+
+impl<B: Backend> Debug for BlobBackend<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobBackend")
+            .field("inner", &self.inner)
+            .field("store", &self.store)
+            .field("prefix", &self.prefix)
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+impl<B: Backend> Backend for BlobBackend<B> {
+    type Session = BlobSession<B::Session>;
+
+    fn create_profile(&self, name: Option<String>) -> BoxFuture<'_, Result<String, Error>> {
+        self.inner.create_profile(name)
+    }
+
+    fn get_active_profile(&self) -> String {
+        self.inner.get_active_profile()
+    }
+
+    fn get_default_profile(&self) -> BoxFuture<'_, Result<String, Error>> {
+        self.inner.get_default_profile()
+    }
+
+    fn set_default_profile(&self, profile: String) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.set_default_profile(profile)
+    }
+
+    fn list_profiles(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        self.inner.list_profiles()
+    }
+
+    fn list_profile_details(
+        &self,
+        name_prefix: Option<String>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<ProfileDetails>, Error>> {
+        self.inner.list_profile_details(name_prefix, offset, limit)
+    }
+
+    fn set_audit_log(&self, enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.set_audit_log(enabled)
+    }
+
+    fn subscribe(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+    ) -> BoxFuture<'_, Result<Subscription, Error>> {
+        self.inner.subscribe(profile, kind, category)
+    }
+
+    fn get_meta(&self, name: String) -> BoxFuture<'_, Result<Option<SecretBytes>, Error>> {
+        self.inner.get_meta(name)
+    }
+
+    fn set_meta(
+        &self,
+        name: String,
+        value: Option<SecretBytes>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.set_meta(name, value)
+    }
+
+    fn rename_profile(&self, from: String, to: String) -> BoxFuture<'_, Result<bool, Error>> {
+        // stored objects are located by the record values, independent of the profile name
+        self.inner.rename_profile(from, to)
+    }
+
+    fn remove_profile(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async move {
+            // the profile may not be accessible to this backend instance
+            let mut paths = Vec::new();
+            if let Ok(mut scan) = self
+                .inner
+                .scan(
+                    Some(name.clone()),
+                    None,
+                    None,
+                    Some(TagFilter::exist(vec![BLOB_TAG.to_string()])),
+                    None,
+                    None,
+                    None,
+                    false,
+                    // soft deleted records still hold their objects
+                    true,
+                )
+                .await
+            {
+                while let Some(rows) = scan.fetch_next().await? {
+                    for row in rows {
+                        paths.push(BlobRef::decode(&row.value)?.location());
+                    }
+                }
+            }
+            let removed = self.inner.remove_profile(name).await?;
+            if removed {
+                let blobs = self.blobs();
+                for path in paths {
+                    blobs.delete(&path).await?;
+                }
+            }
+            Ok(removed)
+        })
+    }
+
+    fn rekey(
+        &mut self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        // the blob keys are held in the record values, and are re-encrypted
+        // along with them
+        self.inner.rekey(method, pass_key)
+    }
+
+    fn rekey_with_progress(
+        &mut self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+        progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.rekey_with_progress(method, pass_key, progress)
+    }
+
+    fn rekey_profile(
+        &self,
+        profile: String,
+        progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.rekey_profile(profile, progress)
+    }
+
+    fn add_key_slot(
+        &self,
+        name: String,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.add_key_slot(name, method, pass_key)
+    }
+
+    fn remove_key_slot(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        self.inner.remove_key_slot(name)
+    }
+
+    fn list_key_slots(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        self.inner.list_key_slots()
+    }
+
+    fn scan(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(async move {
+            let scan = self
+                .inner
+                .scan(
+                    profile,
+                    kind,
+                    category,
+                    tag_filter,
+                    offset,
+                    limit,
+                    order_by,
+                    descending,
+                    include_deleted,
+                )
+                .await?;
+            Ok(self.resolve_scan(scan))
+        })
+    }
+
+    fn scan_after(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        after: ScanPosition,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(async move {
+            let scan = self
+                .inner
+                .scan_after(
+                    profile,
+                    kind,
+                    category,
+                    tag_filter,
+                    after,
+                    limit,
+                    order_by,
+                    descending,
+                    include_deleted,
+                )
+                .await?;
+            Ok(self.resolve_scan(scan))
+        })
+    }
+
+    fn seal_token(&self, data: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        self.inner.seal_token(data)
+    }
+
+    fn open_token(&self, token: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        self.inner.open_token(token)
+    }
+
+    fn session(&self, profile: Option<String>, transaction: bool) -> Result<Self::Session, Error> {
+        Ok(BlobSession {
+            inner: self.inner.session(profile, transaction)?,
+            blobs: self.blobs(),
+            threshold: self.threshold,
+            transaction,
+            created: Vec::new(),
+            released: Vec::new(),
+        })
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.close()
+    }
+}
+
+/// A session against a store with offloaded record values
+///
+/// Within a transaction, objects replaced or removed are only deleted once
+/// the transaction is committed, and objects created are deleted if it is
+/// rolled back.
+pub struct BlobSession<S: BackendSession> {
+    inner: S,
+    blobs: BlobStore,
+    threshold: usize,
+    transaction: bool,
+    created: Vec<Path>,
+    released: Vec<Path>,
+}
+
+impl<S: BackendSession> BlobSession<S> {
+    async fn fetch_blob_ref(
+        &mut self,
+        kind: EntryKind,
+        category: &str,
+        name: &str,
+    ) -> Result<Option<BlobRef>, Error> {
+        match self.inner.fetch(kind, category, name, true).await? {
+            Some(entry) if has_blob_tag(&entry.tags) => Ok(Some(BlobRef::decode(&entry.value)?)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn release(&mut self, path: Path) -> Result<(), Error> {
+        if self.transaction {
+            self.released.push(path);
+            Ok(())
+        } else {
+            self.blobs.delete(&path).await
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn update_entry(
+        &mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &str,
+        name: &str,
+        value: Option<&[u8]>,
+        tags: Option<&[EntryTag]>,
+        expiry_ms: Option<i64>,
+        version: Option<i64>,
+    ) -> Result<(), Error> {
+        if tags.map(has_blob_tag).unwrap_or(false) {
+            return Err(err_msg!(Input, "Reserved tag name: {}", BLOB_TAG));
+        }
+        let previous = self.fetch_blob_ref(kind, category, name).await?;
+        let offload = value
+            .filter(|value| operation != EntryOperation::Remove && value.len() > self.threshold);
+
+        if let Some(value) = offload {
+            let blob_ref = self.blobs.put(value).await?;
+            let path = blob_ref.location();
+            let mut blob_tags = tags.map(<[EntryTag]>::to_vec).unwrap_or_default();
+            blob_tags.push(EntryTag::Encrypted(BLOB_TAG.to_string(), String::new()));
+            let res = self
+                .inner_update(
+                    kind,
+                    operation,
+                    category,
+                    name,
+                    Some(&blob_ref.encode()?),
+                    Some(&blob_tags),
+                    expiry_ms,
+                    version,
+                )
+                .await;
+            if let Err(err) = res {
+                self.blobs.delete(&path).await?;
+                return Err(err);
+            }
+            if self.transaction {
+                self.created.push(path);
+            }
+        } else {
+            self.inner_update(
+                kind, operation, category, name, value, tags, expiry_ms, version,
+            )
+            .await?;
+        }
+
+        if let Some(previous) = previous {
+            self.release(previous.location()).await?;
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn inner_update(
+        &mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &str,
+        name: &str,
+        value: Option<&[u8]>,
+        tags: Option<&[EntryTag]>,
+        expiry_ms: Option<i64>,
+        version: Option<i64>,
+    ) -> Result<(), Error> {
+        match version {
+            Some(version) => {
+                self.inner
+                    .update_versioned(
+                        kind, operation, category, name, value, tags, expiry_ms, version,
+                    )
+                    .await
+            }
+            None => {
+                self.inner
+                    .update(kind, operation, category, name, value, tags, expiry_ms)
+                    .await
+            }
+        }
+    }
+}
+
+impl<S: BackendSession> Debug for BlobSession<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobSession")
+            .field("inner", &self.inner)
+            .field("transaction", &self.transaction)
+            .finish()
+    }
+}
+
+impl<S: BackendSession> Drop for BlobSession<S> {
+    fn drop(&mut self) {
+        // the dropped transaction is rolled back
+        if !self.created.is_empty() {
+            let blobs = self.blobs.clone();
+            let created = std::mem::take(&mut self.created);
+            spawn_ok(async move {
+                for path in created {
+                    if let Err(err) = blobs.delete(&path).await {
+                        warn!("Error removing object for dropped transaction: {}", err);
+                    }
+                }
+            });
+        }
+    }
+}
+
+impl<S: BackendSession> BackendSession for BlobSession<S> {
+    fn count<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        include_deleted: bool,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        self.inner
+            .count(kind, category, tag_filter, include_deleted)
+    }
+
+    fn count_by_category<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<BTreeMap<String, i64>, Error>> {
+        self.inner.count_by_category(kind, tag_filter)
+    }
+
+    fn list_tag_values<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        tag_name: &'q str,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<BTreeMap<String, i64>, Error>> {
+        self.inner
+            .list_tag_values(kind, category, tag_name, tag_filter)
+    }
+
+    fn fetch<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Option<Entry>, Error>> {
+        Box::pin(async move {
+            match self.inner.fetch(kind, category, name, for_update).await? {
+                Some(entry) => Ok(Some(self.blobs.resolve(entry).await?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn fetch_many<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        names: &'q [String],
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Vec<Option<Entry>>, Error>> {
+        Box::pin(async move {
+            let rows = self
+                .inner
+                .fetch_many(kind, category, names, for_update)
+                .await?;
+            let mut results = Vec::with_capacity(rows.len());
+            for row in rows {
+                results.push(match row {
+                    Some(entry) => Some(self.blobs.resolve(entry).await?),
+                    None => None,
+                });
+            }
+            Ok(results)
+        })
+    }
+
+    fn fetch_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        Box::pin(async move {
+            let rows = self
+                .inner
+                .fetch_all(
+                    kind, category, tag_filter, limit, order_by, descending, for_update,
+                )
+                .await?;
+            self.blobs.resolve_all(rows).await
+        })
+    }
+
+    fn fetch_audit_log(
+        &mut self,
+        after_id: Option<i64>,
+        limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<AuditRecord>, Error>> {
+        self.inner.fetch_audit_log(after_id, limit)
+    }
+
+    fn get_profile_meta<'q>(
+        &'q mut self,
+        name: &'q str,
+    ) -> BoxFuture<'q, Result<Option<SecretBytes>, Error>> {
+        self.inner.get_profile_meta(name)
+    }
+
+    fn set_profile_meta<'q>(
+        &'q mut self,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.inner.set_profile_meta(name, value)
+    }
+
+    fn remove_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        Box::pin(async move {
+            let blob_filter = TagFilter::exist(vec![BLOB_TAG.to_string()]);
+            let offloaded = self
+                .inner
+                .fetch_all(
+                    kind,
+                    category,
+                    Some(match tag_filter.clone() {
+                        Some(filter) => TagFilter::all_of(vec![filter, blob_filter]),
+                        None => blob_filter,
+                    }),
+                    None,
+                    None,
+                    false,
+                    true,
+                )
+                .await?;
+            let removed = self.inner.remove_all(kind, category, tag_filter).await?;
+            for row in offloaded {
+                self.release(BlobRef::decode(&row.value)?.location())
+                    .await?;
+            }
+            Ok(removed)
+        })
+    }
+
+    fn update<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        Box::pin(self.update_entry(
+            kind, operation, category, name, value, tags, expiry_ms, None,
+        ))
+    }
+
+    fn update_versioned<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+        version: i64,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        Box::pin(self.update_entry(
+            kind,
+            operation,
+            category,
+            name,
+            value,
+            tags,
+            expiry_ms,
+            Some(version),
+        ))
+    }
+
+    fn update_search_terms<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        terms: &'q [String],
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.inner.update_search_terms(kind, category, name, terms)
+    }
+
+    fn search_values<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        terms: &'q [String],
+        limit: Option<i64>,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        Box::pin(async move {
+            let rows = self
+                .inner
+                .search_values(kind, category, terms, limit)
+                .await?;
+            self.blobs.resolve_all(rows).await
+        })
+    }
+
+    fn ping(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.ping()
+    }
+
+    fn close(&mut self, commit: bool) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            self.inner.close(commit).await?;
+            let expired = if commit {
+                self.created.clear();
+                std::mem::take(&mut self.released)
+            } else {
+                self.released.clear();
+                std::mem::take(&mut self.created)
+            };
+            for path in expired {
+                self.blobs.delete(&path).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Clone)]
+struct BlobStore {
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+}
+
+impl BlobStore {
+    async fn put(&self, value: &[u8]) -> Result<BlobRef, Error> {
+        let path = self.prefix.child(uuid::Uuid::new_v4().to_string());
+        let value = value.to_vec();
+        let (blob_ref, data) = unblock(move || {
+            let key = BlobKey::random()?;
+            let data = encrypt_blob(&key, &value)?;
+            let blob_ref = BlobRef {
+                path: path.to_string(),
+                size: value.len() as u64,
+                key: key.to_secret_bytes()?,
+            };
+            Result::<_, Error>::Ok((blob_ref, data))
+        })
+        .await?;
+        self.store
+            .put(&blob_ref.location(), data.into())
+            .await
+            .map_err(err_map!(Backend, "Error writing object"))?;
+        Ok(blob_ref)
+    }
+
+    async fn get(&self, blob_ref: BlobRef) -> Result<SecretBytes, Error> {
+        let data = self
+            .store
+            .get(&blob_ref.location())
+            .await
+            .map_err(err_map!(Backend, "Error reading object"))?
+            .bytes()
+            .await
+            .map_err(err_map!(Backend, "Error reading object"))?;
+        unblock(move || {
+            let key = BlobKey::from_secret_bytes(&blob_ref.key)?;
+            let value = decrypt_blob(&key, &data)?;
+            if value.len() as u64 != blob_ref.size {
+                return Err(err_msg!(Encryption, "Object size mismatch"));
+            }
+            Ok(value)
+        })
+        .await
+    }
+
+    async fn delete(&self, path: &Path) -> Result<(), Error> {
+        match self.store.delete(path).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(err) => Err(err_msg!(Backend, "Error removing object").with_cause(err)),
+        }
+    }
+
+    async fn resolve(&self, mut entry: Entry) -> Result<Entry, Error> {
+        if has_blob_tag(&entry.tags) {
+            let blob_ref = BlobRef::decode(&entry.value)?;
+            entry.value = self.get(blob_ref).await?;
+            entry.tags.retain(|tag| tag.name() != BLOB_TAG);
+        }
+        Ok(entry)
+    }
+
+    async fn resolve_all(&self, rows: Vec<Entry>) -> Result<Vec<Entry>, Error> {
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            result.push(self.resolve(row).await?);
+        }
+        Ok(result)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BlobRef {
+    path: String,
+    size: u64,
+    key: SecretBytes,
+}
+
+impl BlobRef {
+    fn location(&self) -> Path {
+        Path::from(self.path.as_str())
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        serde_cbor::to_vec(self).map_err(err_map!(Unexpected, "Error encoding object reference"))
+    }
+
+    fn decode(value: &[u8]) -> Result<Self, Error> {
+        serde_cbor::from_slice(value)
+            .map_err(err_map!(Unexpected, "Error decoding object reference"))
+    }
+}
+
+fn has_blob_tag(tags: &[EntryTag]) -> bool {
+    tags.iter().any(|tag| tag.name() == BLOB_TAG)
+}
+
+// each object is encrypted using a unique key, so the nonce prefix
+// for the chunk stream may be fixed
+fn blob_nonce_prefix(key: &BlobKey) -> Result<Vec<u8>, Error> {
+    Ok(vec![0u8; nonce_prefix_length(key)?])
+}
+
+fn encrypt_blob(key: &BlobKey, value: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut stream = EncryptStream::new(key, &blob_nonce_prefix(key)?)?;
+    let chunk_count = (value.len() + CHUNK_SIZE - 1) / CHUNK_SIZE;
+    let mut data = Vec::with_capacity(value.len() + chunk_count.max(1) * CHUNK_TAG_SIZE);
+    let mut chunks = value.chunks(CHUNK_SIZE).peekable();
+    if chunks.peek().is_none() {
+        stream.encrypt_last(&mut data, &[])?;
+    }
+    while let Some(chunk) = chunks.next() {
+        let mut buffer = SecretBytes::from_slice(chunk);
+        if chunks.peek().is_some() {
+            stream.encrypt_chunk(&mut buffer, &[])?;
+        } else {
+            stream.encrypt_last(&mut buffer, &[])?;
+        }
+        data.extend_from_slice(buffer.as_ref());
+    }
+    Ok(data)
+}
+
+fn decrypt_blob(key: &BlobKey, data: &[u8]) -> Result<SecretBytes, Error> {
+    let mut stream = DecryptStream::new(key, &blob_nonce_prefix(key)?)?;
+    let mut value = SecretBytes::with_capacity(data.len());
+    let mut chunks = data.chunks(CHUNK_SIZE + CHUNK_TAG_SIZE).peekable();
+    while let Some(chunk) = chunks.next() {
+        let mut buffer = SecretBytes::from_slice(chunk);
+        if chunks.peek().is_some() {
+            stream.decrypt_chunk(&mut buffer, &[])?;
+        } else {
+            stream.decrypt_last(&mut buffer, &[])?;
+        }
+        value.extend_from_slice(buffer.as_ref());
+    }
+    if !stream.is_finished() {
+        return Err(err_msg!(Encryption, "Truncated object"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blob_encrypt_round_trip() {
+        let key = BlobKey::random().unwrap();
+        for size in [0, 10, CHUNK_SIZE, CHUNK_SIZE * 2 + 5] {
+            let value = (0..size).map(|i| i as u8).collect::<Vec<_>>();
+            let data = encrypt_blob(&key, &value).unwrap();
+            assert_eq!(decrypt_blob(&key, &data).unwrap().as_ref(), &value[..]);
+        }
+    }
+
+    #[test]
+    fn blob_decrypt_truncated() {
+        let key = BlobKey::random().unwrap();
+        let value = vec![1u8; CHUNK_SIZE * 2 + 5];
+        let data = encrypt_blob(&key, &value).unwrap();
+        // dropping the final chunk must be detected
+        assert!(decrypt_blob(&key, &data[..(CHUNK_SIZE + CHUNK_TAG_SIZE) * 2]).is_err());
+    }
+}
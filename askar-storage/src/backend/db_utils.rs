@@ -1,32 +1,69 @@
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 use std::future::Future;
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 use std::ops::{Deref, DerefMut};
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 use std::sync::Arc;
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+use itertools::Itertools;
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 use sqlx::{
     pool::PoolConnection, Arguments, Database, Encode, Error as SqlxError, IntoArguments, Pool,
     TransactionManager, Type,
 };
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+use subtle::ConstantTimeEq;
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 use crate::{
-    entry::{EncEntryTag, Entry, EntryKind, EntryTag, TagFilter},
-    error::Error,
+    entry::{AuditRecord, ChangeEvent, ChangeOperation, EntryOperation, EntryUpdate, TagFilter},
     future::BoxFuture,
-    protect::{EntryEncryptor, KeyCache, PassKey, ProfileId, ProfileKey, StoreKey, StoreKeyMethod},
+    protect::{KeyCache, ProfileId, StoreKeyReference},
     wql::{
         sql::TagSqlEncoder,
         tags::{tag_query, TagQueryEncoder},
     },
 };
+use crate::{
+    entry::{EncEntryTag, Entry, EntryKind, EntryTag, ScanPosition, SortValue},
+    error::Error,
+    protect::{EntryEncryptor, PassKey, ProfileKey, StoreKey, StoreKeyMethod},
+};
 
 use super::OrderBy;
 
 /// cbindgen:ignore
 pub const PAGE_SIZE: usize = 32;
 
+// the plaintext tag value used to order records, bound to the encrypted tag name
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+const ORDER_TAG_VALUE: &str = "(SELECT it.value FROM items_tags it
+    WHERE it.item_id = i.id AND it.plaintext = 1 AND it.name = $1
+    ORDER BY it.value LIMIT 1)";
+
 pub type Expiry = chrono::DateTime<chrono::Utc>;
 
+/// The sending half of an in-process channel of committed record changes
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub(crate) type ChangeSender = tokio::sync::broadcast::Sender<EncChangeEvent>;
+
+/// A record change, with the record fields encrypted using the profile key
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[derive(Clone, Debug)]
+pub(crate) struct EncChangeEvent {
+    pub operation: ChangeOperation,
+    pub profile_id: ProfileId,
+    pub kind: EntryKind,
+    pub category: Vec<u8>,
+    pub name: Vec<u8>,
+    pub tags: Vec<EncEntryTag>,
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 pub(crate) type Connection<DB> = <DB as Database>::Connection;
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 #[derive(Debug)]
 pub(crate) enum DbSessionState<DB: ExtDatabase> {
     Active { conn: PoolConnection<DB> },
@@ -34,15 +71,23 @@ pub(crate) enum DbSessionState<DB: ExtDatabase> {
     Closed,
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 unsafe impl<DB: ExtDatabase> Sync for DbSessionState<DB> where DB::Connection: Send {}
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 #[derive(Debug)]
 pub struct DbSession<DB: ExtDatabase> {
     profile_key: DbSessionKey,
+    session_id: String,
+    store_key: Arc<StoreKey>,
     state: DbSessionState<DB>,
     txn_depth: usize,
+    changes: Option<ChangeSender>,
+    pending_changes: Vec<EncChangeEvent>,
+    pending_access: Option<(Arc<KeyCache>, i64)>,
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<DB: ExtDatabase> DbSession<DB> {
     pub(crate) fn new(
         pool: Pool<DB>,
@@ -54,9 +99,59 @@ impl<DB: ExtDatabase> DbSession<DB> {
         DB: Database,
     {
         Self {
+            store_key: cache.store_key.clone(),
             profile_key: DbSessionKey::Pending { cache, profile },
+            session_id: uuid::Uuid::new_v4().to_string(),
             state: DbSessionState::Pending { pool, transaction },
             txn_depth: 0,
+            changes: None,
+            pending_changes: Vec::new(),
+            pending_access: None,
+        }
+    }
+
+    /// Publish the record changes committed by this session
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn with_changes(mut self, changes: ChangeSender) -> Self {
+        self.changes.replace(changes);
+        self
+    }
+
+    /// Check whether any subscribers are listening for record changes
+    #[inline]
+    pub(crate) fn tracks_changes(&self) -> bool {
+        self.changes
+            .as_ref()
+            .map(|changes| changes.receiver_count() > 0)
+            .unwrap_or(false)
+    }
+
+    // publish a record change, deferred until the active transaction is committed
+    pub(crate) fn push_change(&mut self, change: EncChangeEvent) {
+        if let Some(changes) = self.changes.as_ref() {
+            if self.txn_depth == 0 {
+                // no receivers remaining is not an error
+                changes.send(change).ok();
+            } else {
+                self.pending_changes.push(change);
+            }
+        }
+    }
+
+    fn publish_changes(&mut self) {
+        if let Some(changes) = self.changes.as_ref() {
+            for change in self.pending_changes.drain(..) {
+                changes.send(change).ok();
+            }
+        }
+    }
+
+    /// Get the details of the session recorded in the audit log
+    #[inline]
+    pub(crate) fn audit_session(&self) -> AuditSession {
+        AuditSession {
+            session_id: self.session_id.clone(),
+            store_key: self.store_key.clone(),
         }
     }
 
@@ -124,9 +219,24 @@ impl<DB: ExtDatabase> DbSession<DB> {
                 std::mem::swap(profile, &mut get_profile);
                 let in_txn = self.in_transaction();
                 let (profile_id, key) = init_key
-                    .call_once(self.connection_mut().unwrap(), cache, get_profile, in_txn)
+                    .call_once(
+                        self.connection_mut().unwrap(),
+                        cache.clone(),
+                        get_profile,
+                        in_txn,
+                    )
                     .await?;
                 self.profile_key = DbSessionKey::Active { profile_id, key };
+                let now_ms = chrono::Utc::now().timestamp_millis();
+                if cache.profile_access_due(profile_id, now_ms) {
+                    if self.txn_depth > 0 {
+                        // keep the access out of the caller's transaction
+                        self.pending_access.replace((cache, now_ms));
+                    } else {
+                        let conn = self.connection_mut().unwrap();
+                        record_profile_access(conn, &cache, profile_id, now_ms).await;
+                    }
+                }
                 profile_id
             }
             DbSessionKey::Active { profile_id, .. } => *profile_id,
@@ -152,14 +262,24 @@ impl<DB: ExtDatabase> DbSession<DB> {
         if self.txn_depth > 0 {
             self.txn_depth = 0;
             if let DbSessionState::Active { mut conn, .. } = state {
-                if commit {
+                let result = if commit {
                     debug!("Commit transaction on close");
                     DB::TransactionManager::commit(&mut conn).await
                 } else {
                     debug!("Roll-back transaction on close");
                     DB::TransactionManager::rollback(&mut conn).await
+                };
+                if result.is_ok() && commit {
+                    self.publish_changes();
+                } else {
+                    self.pending_changes.clear();
+                }
+                result.map_err(err_map!(Backend, "Error closing transaction"))?;
+                if let (Some((cache, now_ms)), DbSessionKey::Active { profile_id, .. }) =
+                    (self.pending_access.take(), &self.profile_key)
+                {
+                    record_profile_access(&mut conn, &cache, *profile_id, now_ms).await;
                 }
-                .map_err(err_map!(Backend, "Error closing transaction"))?;
                 conn.return_to_pool().await;
             } else {
                 warn!("Could not close out transaction: session not active");
@@ -169,6 +289,7 @@ impl<DB: ExtDatabase> DbSession<DB> {
     }
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<DB: ExtDatabase> Drop for DbSession<DB> {
     fn drop(&mut self) {
         if self.txn_depth > 0 {
@@ -183,6 +304,21 @@ impl<DB: ExtDatabase> Drop for DbSession<DB> {
     }
 }
 
+// recording the access is best-effort, as the database may be read-only
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+async fn record_profile_access<DB: ExtDatabase>(
+    conn: &mut PoolConnection<DB>,
+    cache: &KeyCache,
+    profile_id: ProfileId,
+    now_ms: i64,
+) {
+    match DB::record_profile_access(conn, profile_id, now_ms).await {
+        Ok(()) => cache.set_profile_access(profile_id, now_ms),
+        Err(err) => warn!("Error recording profile access: {}", err),
+    }
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 pub(crate) trait GetProfileKey<'a, DB: Database> {
     type Fut: Future<Output = Result<(ProfileId, Arc<ProfileKey>), Error>>;
     fn call_once(
@@ -194,6 +330,7 @@ pub(crate) trait GetProfileKey<'a, DB: Database> {
     ) -> Self::Fut;
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<'a, DB: Database, F, Fut> GetProfileKey<'a, DB> for F
 where
     F: FnOnce(&'a mut PoolConnection<DB>, Arc<KeyCache>, String, bool) -> Fut,
@@ -211,6 +348,7 @@ where
     }
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 #[derive(Debug)]
 pub(crate) enum DbSessionKey {
     Active {
@@ -223,6 +361,7 @@ pub(crate) enum DbSessionKey {
     },
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 pub trait ExtDatabase: Database {
     fn start_transaction(
         conn: &mut Connection<Self>,
@@ -230,19 +369,29 @@ pub trait ExtDatabase: Database {
     ) -> BoxFuture<'_, Result<(), SqlxError>> {
         <Self as Database>::TransactionManager::begin(conn)
     }
+
+    /// Record the time of the latest access of a profile
+    fn record_profile_access(
+        conn: &mut Connection<Self>,
+        profile_id: ProfileId,
+        now_ms: i64,
+    ) -> BoxFuture<'_, Result<(), SqlxError>>;
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 pub enum DbSessionRef<'q, DB: ExtDatabase> {
     Owned(DbSession<DB>),
     Borrowed(&'q mut DbSession<DB>),
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<DB: ExtDatabase> DbSessionRef<'_, DB> {
     pub fn is_owned(&self) -> bool {
         matches!(self, Self::Owned(_))
     }
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<'q, DB: ExtDatabase> Deref for DbSessionRef<'q, DB> {
     type Target = DbSession<DB>;
 
@@ -254,6 +403,7 @@ impl<'q, DB: ExtDatabase> Deref for DbSessionRef<'q, DB> {
     }
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<'q, DB: ExtDatabase> DerefMut for DbSessionRef<'q, DB> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         match self {
@@ -263,11 +413,13 @@ impl<'q, DB: ExtDatabase> DerefMut for DbSessionRef<'q, DB> {
     }
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 pub(crate) struct DbSessionActive<'a, DB: ExtDatabase> {
     inner: &'a mut DbSession<DB>,
     pub(crate) profile_id: ProfileId,
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<'q, DB: ExtDatabase> DbSessionActive<'q, DB> {
     #[inline]
     pub fn connection_mut(&mut self) -> &mut Connection<DB> {
@@ -282,6 +434,21 @@ impl<'q, DB: ExtDatabase> DbSessionActive<'q, DB> {
         self.inner.in_transaction()
     }
 
+    #[inline]
+    pub(crate) fn audit_session(&self) -> AuditSession {
+        self.inner.audit_session()
+    }
+
+    #[inline]
+    pub fn tracks_changes(&self) -> bool {
+        self.inner.tracks_changes()
+    }
+
+    #[inline]
+    pub fn push_change(&mut self, change: EncChangeEvent) {
+        self.inner.push_change(change)
+    }
+
     #[allow(unused)]
     pub async fn begin<'t>(&'t mut self) -> Result<DbSessionTxn<'t, DB>, Error>
     where
@@ -292,10 +459,12 @@ impl<'q, DB: ExtDatabase> DbSessionActive<'q, DB> {
             .await
             .map_err(err_map!(Backend, "Error starting nested transaction"))?;
         self.inner.txn_depth += 1;
+        let change_mark = self.inner.pending_changes.len();
         Ok(DbSessionTxn {
             inner: &mut *self.inner,
             profile_id: self.profile_id,
             rollback: true,
+            change_mark,
         })
     }
 
@@ -309,85 +478,168 @@ impl<'q, DB: ExtDatabase> DbSessionActive<'q, DB> {
                 .await
                 .map_err(err_map!(Backend, "Error starting transaction"))?;
             self.inner.txn_depth += 1;
+            let change_mark = self.inner.pending_changes.len();
             Ok(DbSessionTxn {
                 inner: &mut *self.inner,
                 profile_id: self.profile_id,
                 rollback: true,
+                change_mark,
             })
         } else {
+            let change_mark = self.inner.pending_changes.len();
             Ok(DbSessionTxn {
                 inner: &mut *self.inner,
                 profile_id: self.profile_id,
                 rollback: false,
+                change_mark,
             })
         }
     }
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 pub(crate) struct DbSessionTxn<'a, DB: ExtDatabase> {
     inner: &'a mut DbSession<DB>,
     pub(crate) profile_id: ProfileId,
     rollback: bool,
+    change_mark: usize,
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<'a, DB: ExtDatabase> DbSessionTxn<'a, DB> {
     pub fn connection_mut(&mut self) -> &mut Connection<DB> {
         self.inner.connection_mut().unwrap().as_mut()
     }
 
+    #[inline]
+    pub(crate) fn audit_session(&self) -> AuditSession {
+        self.inner.audit_session()
+    }
+
+    #[inline]
+    pub fn tracks_changes(&self) -> bool {
+        self.inner.tracks_changes()
+    }
+
+    #[inline]
+    pub fn push_change(&mut self, change: EncChangeEvent) {
+        self.inner.push_change(change)
+    }
+
     pub async fn commit(mut self) -> Result<(), Error> {
         if self.rollback {
             self.rollback = false;
             self.inner.txn_depth -= 1;
             let conn = self.connection_mut();
             debug!("Commit transaction");
-            DB::TransactionManager::commit(conn)
-                .await
-                .map_err(err_map!(Backend, "Error committing transaction"))?;
+            if let Err(err) = DB::TransactionManager::commit(conn).await {
+                self.inner.pending_changes.truncate(self.change_mark);
+                return Err(err_msg!(Backend, "Error committing transaction").with_cause(err));
+            }
+            if self.inner.txn_depth == 0 {
+                self.inner.publish_changes();
+            }
         }
         Ok(())
     }
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<'a, DB: ExtDatabase> Drop for DbSessionTxn<'a, DB> {
     fn drop(&mut self) {
         if self.rollback {
             self.inner.txn_depth -= 1;
+            self.inner.pending_changes.truncate(self.change_mark);
             debug!("Roll-back dropped nested transaction");
             DB::TransactionManager::start_rollback(self.connection_mut());
         }
     }
 }
 
-pub(crate) trait RunInTransaction<'a, 'q: 'a, DB: ExtDatabase> {
-    type Fut: Future<Output = Result<(), Error>>;
-    fn call_once(self, conn: &'a mut DbSessionActive<'q, DB>) -> Self::Fut;
+/// The details of a session recorded in the audit log
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[derive(Clone, Debug)]
+pub(crate) struct AuditSession {
+    pub session_id: String,
+    pub store_key: Arc<StoreKey>,
 }
 
-impl<'a, 'q: 'a, DB: ExtDatabase, F, Fut> RunInTransaction<'a, 'q, DB> for F
-where
-    F: FnOnce(&'a mut DbSessionActive<'q, DB>) -> Fut,
-    Fut: Future<Output = Result<(), Error>> + 'a,
-{
-    type Fut = Fut;
-    fn call_once(self, conn: &'a mut DbSessionActive<'q, DB>) -> Self::Fut {
-        self(conn)
+/// A record of the audit log, with the category encrypted using the profile key
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[derive(Clone, Debug)]
+pub(crate) struct EncAuditRecord {
+    pub id: i64,
+    pub profile_id: ProfileId,
+    pub session_id: String,
+    pub operation: String,
+    pub kind: Option<EntryKind>,
+    pub category: Option<Vec<u8>>,
+    pub item_id: Option<i64>,
+    pub created_at: i64,
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+impl EncAuditRecord {
+    /// Compute the authentication code for the record, which is chained to the
+    /// code of the previous record for the same profile
+    pub fn compute_mac(&self, store_key: &StoreKey, prev_mac: &[u8]) -> Result<Vec<u8>, Error> {
+        fn push_field(data: &mut Vec<u8>, field: &[u8]) {
+            data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            data.extend_from_slice(field);
+        }
+        let mut data = Vec::with_capacity(128);
+        push_field(&mut data, prev_mac);
+        push_field(&mut data, &self.profile_id.to_be_bytes());
+        push_field(&mut data, self.session_id.as_bytes());
+        push_field(&mut data, self.operation.as_bytes());
+        push_field(
+            &mut data,
+            &self.kind.map(|k| vec![k as u8]).unwrap_or_default(),
+        );
+        push_field(&mut data, self.category.as_deref().unwrap_or_default());
+        push_field(
+            &mut data,
+            &self
+                .item_id
+                .map(|id| id.to_be_bytes().to_vec())
+                .unwrap_or_default(),
+        );
+        push_field(&mut data, &self.created_at.to_be_bytes());
+        store_key.compute_audit_mac(&data)
     }
 }
 
 pub struct EncScanEntry {
+    pub id: Option<i64>,
     pub kind: EntryKind,
     pub category: Vec<u8>,
     pub name: Vec<u8>,
     pub value: Vec<u8>,
     pub tags: Vec<u8>,
+    pub version: Option<i64>,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    pub deleted_at: Option<i64>,
+}
+
+/// A batch record update, with the record fields encrypted using the profile key
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub struct EncEntryUpdate {
+    pub operation: EntryOperation,
+    pub category: Vec<u8>,
+    pub name: Vec<u8>,
+    pub value: Vec<u8>,
+    pub tags: Option<Vec<EncEntryTag>>,
+    pub expiry_ms: Option<i64>,
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 pub struct QueryParams<'q, DB: Database> {
     args: DB::Arguments<'q>,
     count: usize,
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<'q, DB: Database> QueryParams<'q, DB> {
     pub fn new() -> Self {
         Self {
@@ -420,6 +672,7 @@ impl<'q, DB: Database> QueryParams<'q, DB> {
     }
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl<'q, DB> IntoArguments<'q, DB> for QueryParams<'q, DB>
 where
     DB: Database,
@@ -430,6 +683,7 @@ where
     }
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 pub trait QueryPrepare {
     type DB: Database;
 
@@ -456,18 +710,83 @@ pub trait QueryPrepare {
         query
     }
 
-    fn order_by_query(mut query: String, order_by: OrderBy, descending: bool) -> String {
+    fn order_by_query<'q>(
+        mut query: String,
+        args: &mut QueryParams<'q, Self::DB>,
+        order_by: EncOrderBy,
+        descending: bool,
+    ) -> String
+    where
+        Vec<u8>: for<'e> Encode<'e, Self::DB> + Type<Self::DB>,
+    {
+        let direction = if descending { " DESC" } else { "" };
         query.push_str(" ORDER BY ");
-        match order_by {
-            OrderBy::Id => query.push_str("id"),
-        }
-        if descending {
-            query.push_str(" DESC");
+        let value = match order_by {
+            EncOrderBy::Id => None,
+            EncOrderBy::Tag(name) => {
+                let tag_idx = (args.len() + 1) as i64;
+                args.push(name);
+                Some(replace_arg_placeholders::<Self>(ORDER_TAG_VALUE, tag_idx))
+            }
+            EncOrderBy::CreatedAt => Some("i.created_at".to_string()),
+            EncOrderBy::UpdatedAt => Some("i.updated_at".to_string()),
+        };
+        if let Some(value) = value {
+            query.push_str(&format!(
+                "CASE WHEN {value} IS NULL THEN 0 ELSE 1 END{direction}, {value}{direction}, "
+            ));
         }
+        query.push_str("id");
+        query.push_str(direction);
+        query
+    }
+
+    fn position_query<'q>(
+        mut query: String,
+        args: &mut QueryParams<'q, Self::DB>,
+        order_by: &EncOrderBy,
+        after: ScanPosition,
+        descending: bool,
+    ) -> String
+    where
+        i64: for<'e> Encode<'e, Self::DB> + Type<Self::DB>,
+        Vec<u8>: for<'e> Encode<'e, Self::DB> + Type<Self::DB>,
+    {
+        let value = match order_by {
+            EncOrderBy::Id => None,
+            EncOrderBy::Tag(name) => {
+                let tag_idx = (args.len() + 1) as i64;
+                args.push(name.clone());
+                Some(replace_arg_placeholders::<Self>(ORDER_TAG_VALUE, tag_idx))
+            }
+            EncOrderBy::CreatedAt => Some("i.created_at".to_string()),
+            EncOrderBy::UpdatedAt => Some("i.updated_at".to_string()),
+        };
+        let position_value = match (&value, after.value) {
+            (None, _) | (_, None) => None,
+            (Some(_), Some(SortValue::Int(ts))) => {
+                args.push(ts);
+                Some(Self::placeholder(args.len() as i64))
+            }
+            (Some(_), Some(SortValue::Text(text))) => {
+                args.push(text.into_bytes());
+                Some(Self::placeholder(args.len() as i64))
+            }
+        };
+        args.push(after.id);
+        let position_id = Self::placeholder(args.len() as i64);
+        query.push_str(" AND "); // assumes WHERE already occurs
+        query.push_str(&position_filter(
+            value.as_deref(),
+            position_value.as_deref(),
+            &position_id,
+            descending,
+        ));
         query
     }
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 pub fn replace_arg_placeholders<Q: QueryPrepare + ?Sized>(
     filter: &str,
     start_index: i64,
@@ -508,6 +827,21 @@ pub fn replace_arg_placeholders<Q: QueryPrepare + ?Sized>(
     buffer
 }
 
+/// Encode record tags in the format produced for stored record history
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub(crate) fn encode_tags(tags: &[EncEntryTag]) -> String {
+    tags.iter()
+        .map(|tag| {
+            format!(
+                "{}:{}:{}",
+                if tag.plaintext { 1 } else { 0 },
+                hex::encode_upper(&tag.name),
+                hex::encode_upper(&tag.value)
+            )
+        })
+        .join(",")
+}
+
 pub(crate) fn decode_tags(tags: Vec<u8>) -> Result<Vec<EncEntryTag>, ()> {
     let mut idx = 0;
     let mut plaintext;
@@ -577,7 +911,312 @@ pub fn decrypt_scan_entry(
     let tags = key.decrypt_entry_tags(
         decode_tags(enc_entry.tags).map_err(|_| err_msg!(Unexpected, "Error decoding tags"))?,
     )?;
-    Ok(Entry::new(enc_entry.kind, category, name, value, tags))
+    Ok(Entry::new(enc_entry.kind, category, name, value, tags)
+        .with_version(enc_entry.version)
+        .with_timestamps(enc_entry.created_at, enc_entry.updated_at)
+        .with_deleted_at(enc_entry.deleted_at)
+        .with_row_id(enc_entry.id))
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub(crate) fn decrypt_change_event(
+    change: EncChangeEvent,
+    key: &ProfileKey,
+) -> Result<ChangeEvent, Error> {
+    Ok(ChangeEvent {
+        operation: change.operation,
+        kind: change.kind,
+        category: key.decrypt_entry_category(change.category)?,
+        name: key.decrypt_entry_name(change.name)?,
+        tags: key.decrypt_entry_tags(change.tags)?,
+    })
+}
+
+/// A record of a profile being re-encrypted using a new profile key
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[derive(Debug)]
+pub(crate) struct EncRekeyEntry {
+    pub id: i64,
+    pub category: Vec<u8>,
+    pub name: Vec<u8>,
+    pub value: Vec<u8>,
+    pub tags: Vec<EncEntryTag>,
+    /// The ID, value and tags of each stored version of the record
+    pub history: Vec<(i64, Vec<u8>, Option<String>)>,
+    /// The encrypted search terms indexed for the record
+    pub search_terms: Option<Vec<u8>>,
+    /// The search index tokens derived for the record using the new key
+    pub search_tokens: Vec<Vec<u8>>,
+}
+
+/// Check the chain of authentication codes for a sequence of audit log
+/// records and decrypt their categories
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub(crate) fn verify_audit_records(
+    records: Vec<(EncAuditRecord, Vec<u8>)>,
+    prev_mac: Option<Vec<u8>>,
+    store_key: &StoreKey,
+    key: &ProfileKey,
+) -> Result<Vec<AuditRecord>, Error> {
+    let mut prev_mac = prev_mac.unwrap_or_default();
+    records
+        .into_iter()
+        .map(|(record, mac)| {
+            let expected = record.compute_mac(store_key, &prev_mac)?;
+            if !bool::from(expected.ct_eq(&mac)) {
+                return Err(err_msg!(
+                    Encryption,
+                    "Audit log record {} failed verification",
+                    record.id
+                ));
+            }
+            prev_mac = mac;
+            Ok(AuditRecord {
+                id: record.id,
+                session_id: record.session_id,
+                operation: record.operation,
+                kind: record.kind,
+                // categories recorded before the profile was rekeyed
+                // cannot be decrypted
+                category: record
+                    .category
+                    .and_then(|c| key.decrypt_entry_category(c).ok()),
+                item_id: record.item_id,
+                timestamp_ms: record.created_at,
+            })
+        })
+        .collect()
+}
+
+/// Re-encrypt a batch of profile records from the previous profile key to a new key
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub(crate) fn rekey_entry_batch(
+    entries: Vec<EncRekeyEntry>,
+    prev_key: &ProfileKey,
+    key: &ProfileKey,
+) -> Result<Vec<EncRekeyEntry>, Error> {
+    let mut batch = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let category = prev_key.decrypt_entry_category(entry.category)?;
+        let name = prev_key.decrypt_entry_name(entry.name)?;
+        let rekey_value = |enc_value: Vec<u8>| {
+            let value =
+                prev_key.decrypt_entry_value(category.as_bytes(), name.as_bytes(), enc_value)?;
+            key.encrypt_entry_value(category.as_bytes(), name.as_bytes(), value)
+        };
+        let rekey_tags = |enc_tags: Vec<EncEntryTag>| {
+            key.encrypt_entry_tags(prev_key.decrypt_entry_tags(enc_tags)?)
+        };
+        let value = rekey_value(entry.value)?;
+        let tags = rekey_tags(entry.tags)?;
+        let mut history = Vec::with_capacity(entry.history.len());
+        for (id, enc_value, enc_tags) in entry.history {
+            let enc_tags = enc_tags
+                .map(|enc_tags| {
+                    let enc_tags = decode_tags(enc_tags.into_bytes())
+                        .map_err(|_| err_msg!(Unexpected, "Error decoding tags"))?;
+                    Result::<_, Error>::Ok(encode_tags(&rekey_tags(enc_tags)?))
+                })
+                .transpose()?;
+            history.push((id, rekey_value(enc_value)?, enc_tags));
+        }
+        let (search_terms, search_tokens) = match entry.search_terms {
+            Some(enc_terms) => {
+                let terms = decrypt_search_terms(prev_key, &category, &name, enc_terms)?;
+                (
+                    Some(encrypt_search_terms(key, &category, &name, &terms)?),
+                    search_tokens(key, &category, &terms)?,
+                )
+            }
+            None => (None, Vec::new()),
+        };
+        batch.push(EncRekeyEntry {
+            id: entry.id,
+            category: key.encrypt_entry_category(ProfileKey::prepare_input(category.as_bytes()))?,
+            name: key.encrypt_entry_name(ProfileKey::prepare_input(name.as_bytes()))?,
+            value,
+            tags,
+            history,
+            search_terms,
+            search_tokens,
+        });
+    }
+    Ok(batch)
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub fn encrypt_entry_updates(
+    updates: Vec<EntryUpdate>,
+    key: &ProfileKey,
+) -> Result<Vec<EncEntryUpdate>, Error> {
+    let mut batch = Vec::with_capacity(updates.len());
+    for upd in updates {
+        let category = ProfileKey::prepare_input(upd.category.as_bytes());
+        let name = ProfileKey::prepare_input(upd.name.as_bytes());
+        let (value, tags) = if upd.operation == EntryOperation::Remove {
+            (Vec::new(), None)
+        } else {
+            let value = ProfileKey::prepare_input(upd.value.as_deref().unwrap_or_default());
+            let tags = upd
+                .tags
+                .as_deref()
+                .map(prepare_tags)
+                .transpose()?
+                .map(|t| key.encrypt_entry_tags(t))
+                .transpose()?;
+            (
+                key.encrypt_entry_value(category.as_ref(), name.as_ref(), value)?,
+                tags,
+            )
+        };
+        batch.push(EncEntryUpdate {
+            operation: upd.operation,
+            category: key.encrypt_entry_category(category)?,
+            name: key.encrypt_entry_name(name)?,
+            value,
+            tags,
+            expiry_ms: upd.expiry_ms,
+        });
+    }
+    Ok(batch)
+}
+
+/// A record ordering, with any tag name encrypted using the profile key
+pub enum EncOrderBy {
+    Id,
+    Tag(Vec<u8>),
+    CreatedAt,
+    UpdatedAt,
+}
+
+pub fn encode_order_by(
+    order_by: Option<OrderBy>,
+    key: &ProfileKey,
+) -> Result<Option<EncOrderBy>, Error> {
+    order_by
+        .map(|order_by| match order_by {
+            OrderBy::Id => Ok(EncOrderBy::Id),
+            OrderBy::Tag(name) => Ok(EncOrderBy::Tag(
+                key.encrypt_tag_name(ProfileKey::prepare_input(name.as_bytes()))?,
+            )),
+            OrderBy::CreatedAt => Ok(EncOrderBy::CreatedAt),
+            OrderBy::UpdatedAt => Ok(EncOrderBy::UpdatedAt),
+        })
+        .transpose()
+}
+
+/// Build a filter clause matching the records which follow a scan position,
+/// given the expression for the ordering value, if any, and the placeholders
+/// bound to the sort value and ID of the position. Records without a sort value
+/// are ordered first in ascending order, consistent with `order_by_query`.
+pub fn position_filter(
+    value: Option<&str>,
+    position_value: Option<&str>,
+    position_id: &str,
+    descending: bool,
+) -> String {
+    match (value, position_value) {
+        (None, _) if descending => format!("i.id < {position_id}"),
+        (None, _) => format!("i.id > {position_id}"),
+        (Some(value), Some(pos)) if descending => format!(
+            "({value} < {pos} OR ({value} = {pos} AND i.id < {position_id}) OR {value} IS NULL)"
+        ),
+        (Some(value), Some(pos)) => {
+            format!("({value} > {pos} OR ({value} = {pos} AND i.id > {position_id}))")
+        }
+        (Some(value), None) if descending => {
+            format!("({value} IS NULL AND i.id < {position_id})")
+        }
+        (Some(value), None) => format!("({value} IS NOT NULL OR i.id > {position_id})"),
+    }
+}
+
+/// Derive the unique search index tokens for a set of terms
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub fn search_tokens(
+    key: &ProfileKey,
+    category: &str,
+    terms: &[String],
+) -> Result<Vec<Vec<u8>>, Error> {
+    let mut tokens = terms
+        .iter()
+        .map(|term| key.search_token(category.as_bytes(), term.as_bytes()))
+        .collect::<Result<Vec<_>, Error>>()?;
+    tokens.sort_unstable();
+    tokens.dedup();
+    Ok(tokens)
+}
+
+/// Encrypt the search terms indexed for a record
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub fn encrypt_search_terms(
+    key: &ProfileKey,
+    category: &str,
+    name: &str,
+    terms: &[String],
+) -> Result<Vec<u8>, Error> {
+    let terms =
+        serde_json::to_vec(terms).map_err(err_map!(Unexpected, "Error encoding search terms"))?;
+    key.encrypt_search_terms(category.as_bytes(), name.as_bytes(), terms.into())
+}
+
+/// Decrypt the search terms indexed for a record
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub fn decrypt_search_terms(
+    key: &ProfileKey,
+    category: &str,
+    name: &str,
+    enc_terms: Vec<u8>,
+) -> Result<Vec<String>, Error> {
+    let terms = key.decrypt_search_terms(category.as_bytes(), name.as_bytes(), enc_terms)?;
+    serde_json::from_slice(terms.as_ref())
+        .map_err(err_map!(Unexpected, "Error decoding search terms"))
+}
+
+/// Encode a filter clause matching the records indexed with all of the search terms
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub fn encode_search_filter<Q: QueryPrepare>(
+    key: &ProfileKey,
+    category: &str,
+    terms: &[String],
+    offset: usize,
+) -> Result<(String, Vec<Vec<u8>>), Error> {
+    let tokens = search_tokens(key, category, terms)?;
+    if tokens.is_empty() {
+        return Err(err_msg!(Input, "No search terms provided"));
+    }
+    let args_in = Itertools::intersperse(std::iter::repeat("$$").take(tokens.len()), ", ")
+        .collect::<String>();
+    let filter = format!(
+        "i.id IN (SELECT item_id FROM items_search WHERE token IN ({}) \
+        GROUP BY item_id HAVING COUNT(*) = {})",
+        args_in,
+        tokens.len()
+    );
+    Ok((
+        replace_arg_placeholders::<Q>(&filter, (offset as i64) + 1),
+        tokens,
+    ))
+}
+
+/// Encode a filter clause matching the records with any of the given names
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub fn encode_name_filter<Q: QueryPrepare>(
+    key: &ProfileKey,
+    names: &[String],
+    offset: usize,
+) -> Result<(String, Vec<Vec<u8>>), Error> {
+    let enc_names = names
+        .iter()
+        .map(|name| key.encrypt_entry_name(ProfileKey::prepare_input(name.as_bytes())))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let args_in = Itertools::intersperse(std::iter::repeat("$$").take(enc_names.len()), ", ")
+        .collect::<String>();
+    let filter = format!("i.name IN ({})", args_in);
+    Ok((
+        replace_arg_placeholders::<Q>(&filter, (offset as i64) + 1),
+        enc_names,
+    ))
 }
 
 pub fn expiry_timestamp(expire_ms: i64) -> Result<Expiry, Error> {
@@ -586,6 +1225,7 @@ pub fn expiry_timestamp(expire_ms: i64) -> Result<Expiry, Error> {
         .ok_or_else(|| err_msg!(Unexpected, "Invalid expiry timestamp"))
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 #[allow(clippy::type_complexity)]
 pub fn encode_tag_filter<Q: QueryPrepare>(
     tag_filter: Option<TagFilter>,
@@ -632,13 +1272,14 @@ pub fn prepare_tags(tags: &[EntryTag]) -> Result<Vec<EntryTag>, Error> {
     Ok(result)
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 pub fn extend_query<'q, Q: QueryPrepare>(
     query: &str,
     args: &mut QueryParams<'q, Q::DB>,
     tag_filter: Option<(String, Vec<Vec<u8>>)>,
     offset: Option<i64>,
     limit: Option<i64>,
-    order_by: Option<OrderBy>,
+    order_by: Option<EncOrderBy>,
     descending: bool,
 ) -> Result<String, Error>
 where
@@ -654,7 +1295,7 @@ where
     // Only add ordering, and limit/offset, if the query starts with SELECT
     if query.trim_start().to_uppercase().starts_with("SELECT") {
         if let Some(order_by_value) = order_by {
-            query = Q::order_by_query(query, order_by_value, descending);
+            query = Q::order_by_query(query, args, order_by_value, descending);
         };
 
         if offset.is_some() || limit.is_some() {
@@ -664,6 +1305,29 @@ where
     Ok(query)
 }
 
+/// Extend a scan query to return the records following a position in the
+/// scan ordering
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub fn extend_query_after<'q, Q: QueryPrepare>(
+    query: &str,
+    args: &mut QueryParams<'q, Q::DB>,
+    tag_filter: Option<(String, Vec<Vec<u8>>)>,
+    after: ScanPosition,
+    limit: Option<i64>,
+    order_by: EncOrderBy,
+    descending: bool,
+) -> Result<String, Error>
+where
+    i64: for<'e> Encode<'e, Q::DB> + Type<Q::DB>,
+    Vec<u8>: for<'e> Encode<'e, Q::DB> + Type<Q::DB>,
+{
+    let query = extend_query::<Q>(query, args, tag_filter, None, None, None, false)?;
+    let query = Q::position_query(query, args, &order_by, after, descending);
+    let query = Q::order_by_query(query, args, order_by, descending);
+    Ok(Q::limit_query(query, args, None, limit))
+}
+
+#[cfg(any(feature = "mssql", feature = "redb"))]
 pub fn init_keys(
     method: StoreKeyMethod,
     pass_key: PassKey<'_>,
@@ -686,6 +1350,65 @@ pub fn init_keys(
     ))
 }
 
+/// Generate the keys for a new store, in which the profile keys are encrypted
+/// using a random master key
+///
+/// The master key is returned in hex form, encrypted using the key resolved
+/// for the store key reference. An unprotected store has no master key.
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[allow(clippy::type_complexity)]
+pub(crate) fn init_master_keys(
+    method: StoreKeyMethod,
+    pass_key: PassKey<'_>,
+) -> Result<(ProfileKey, Vec<u8>, StoreKey, String, Option<String>), Error> {
+    if method == StoreKeyMethod::RawKey && pass_key.is_empty() {
+        // disallow random key for a new database
+        return Err(err_msg!(
+            Input,
+            "Cannot create a store with a blank raw key"
+        ));
+    }
+    let (wrap_key, store_key_ref) = method.resolve(pass_key)?;
+    let (store_key, master_key) = new_master_key(&wrap_key)?;
+    let profile_key = ProfileKey::new()?;
+    let enc_profile_key = encode_profile_key(&profile_key, &store_key)?;
+    Ok((
+        profile_key,
+        enc_profile_key,
+        store_key,
+        store_key_ref.into_uri(),
+        master_key,
+    ))
+}
+
+// generate a random master key, encrypted using the given key,
+// unless the store is to be unprotected
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn new_master_key(wrap_key: &StoreKey) -> Result<(StoreKey, Option<String>), Error> {
+    if wrap_key.is_empty() {
+        Ok((StoreKey::empty(), None))
+    } else {
+        let master_key = StoreKey::random()?;
+        let wrapped = hex::encode(wrap_key.wrap_key(&master_key)?);
+        Ok((master_key, Some(wrapped)))
+    }
+}
+
+/// Encrypt the master key of a store using a new pass key, returning the
+/// new store key reference and the encrypted master key in hex form
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub(crate) fn rewrap_master_key(
+    method: StoreKeyMethod,
+    pass_key: PassKey<'_>,
+    master_key: &StoreKey,
+) -> Result<(String, String), Error> {
+    let (wrap_key, store_key_ref) = method.resolve(pass_key)?;
+    Ok((
+        store_key_ref.into_uri(),
+        hex::encode(wrap_key.wrap_key(master_key)?),
+    ))
+}
+
 pub fn encode_profile_key(
     profile_key: &ProfileKey,
     store_key: &StoreKey,
@@ -693,6 +1416,168 @@ pub fn encode_profile_key(
     store_key.wrap_data(profile_key.to_bytes()?)
 }
 
+/// The progress of an incomplete store rekey, as recorded in the store configuration
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[derive(Debug, Default)]
+pub(crate) struct RekeyState {
+    /// The reference for the new store key
+    pub key_ref: Option<String>,
+    /// The new store key, encrypted using the previous store key
+    pub wrapped_key: Option<Vec<u8>>,
+    /// The new store key, encrypted using the key for the new key reference,
+    /// when the new store key is a master key
+    pub master_key: Option<String>,
+    /// The ID of the last profile re-encrypted using the new store key
+    pub last_id: i64,
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+impl RekeyState {
+    /// Load a value from the store configuration
+    pub fn load_config(&mut self, name: &str, value: String) -> Result<(), Error> {
+        match name {
+            "rekey_ref" => {
+                self.key_ref.replace(value);
+            }
+            "rekey_key" => {
+                self.wrapped_key.replace(
+                    hex::decode(value).map_err(|_| err_msg!(Unexpected, "Invalid rekey state"))?,
+                );
+            }
+            "rekey_master" => {
+                self.master_key.replace(value);
+            }
+            "rekey_progress" => {
+                self.last_id = value
+                    .parse()
+                    .map_err(|_| err_msg!(Unexpected, "Invalid rekey state"))?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Resolve the new store key, resuming an incomplete rekey when present
+    ///
+    /// Unless the store is to be unprotected, the new store key is a random
+    /// master key. Returns the new store key along with the key reference,
+    /// the encrypted key and the encrypted master key to be recorded in the
+    /// store configuration.
+    #[allow(clippy::type_complexity)]
+    pub fn resolve_key(
+        &self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+        prev_key: &StoreKey,
+    ) -> Result<(StoreKey, String, String, Option<String>), Error> {
+        if let (Some(key_ref), Some(wrapped_key)) = (&self.key_ref, &self.wrapped_key) {
+            let wrap_ref = StoreKeyReference::parse_uri(key_ref)?;
+            if !wrap_ref.compare_method(&method) {
+                return Err(err_msg!(Input, "Store key method mismatch"));
+            }
+            let wrap_key = wrap_ref.resolve_with(Some(&method), pass_key)?;
+            let store_key = prev_key.unwrap_key(wrapped_key.clone())?;
+            let matched = match &self.master_key {
+                Some(master_key) => hex::decode(master_key)
+                    .ok()
+                    .and_then(|master_key| wrap_key.unwrap_key(master_key).ok())
+                    .map(|master_key| master_key.matches(&store_key))
+                    .unwrap_or(false),
+                None => wrap_key.matches(&store_key),
+            };
+            if !matched {
+                return Err(err_msg!(
+                    Input,
+                    "Store key does not match the incomplete rekey"
+                ));
+            }
+            Ok((
+                store_key,
+                key_ref.clone(),
+                hex::encode(wrapped_key),
+                self.master_key.clone(),
+            ))
+        } else {
+            let (wrap_key, store_key_ref) = method.resolve(pass_key)?;
+            let (store_key, master_key) = new_master_key(&wrap_key)?;
+            let wrapped_key = hex::encode(prev_key.wrap_key(&store_key)?);
+            Ok((store_key, store_key_ref.into_uri(), wrapped_key, master_key))
+        }
+    }
+}
+
+/// Resolve the store key using the pass key for the store key reference, or
+/// for one of the key slots of the store
+///
+/// When the store has a master key, it is decrypted using the key for the store
+/// key reference or else the first key slot for which the pass key is valid.
+/// Otherwise the store key is the key resolved for the store key reference.
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub(crate) fn unlock_store_key(
+    key_ref: &str,
+    master_key: Option<&str>,
+    key_slots: Vec<(String, Vec<u8>)>,
+    method: Option<StoreKeyMethod>,
+    pass_key: PassKey<'_>,
+) -> Result<StoreKey, Error> {
+    let compare_method = |wrap_ref: &StoreKeyReference| {
+        method
+            .as_ref()
+            .map(|method| wrap_ref.compare_method(method))
+            .unwrap_or(true)
+    };
+    let wrap_ref = StoreKeyReference::parse_uri(key_ref)?;
+    let master_key = match master_key {
+        Some(master_key) => {
+            hex::decode(master_key).map_err(|_| err_msg!(Unexpected, "Invalid store master key"))?
+        }
+        None => {
+            if !compare_method(&wrap_ref) {
+                return Err(err_msg!(Input, "Store key method mismatch"));
+            }
+            return wrap_ref.resolve_with(method.as_ref(), pass_key);
+        }
+    };
+    let slots = Some((key_ref.to_string(), master_key))
+        .into_iter()
+        .chain(key_slots);
+    for (slot_ref, wrapped_key) in slots {
+        let slot_ref = StoreKeyReference::parse_uri(&slot_ref)?;
+        if !compare_method(&slot_ref) {
+            continue;
+        }
+        if let Ok(slot_key) = slot_ref.resolve_with(method.as_ref(), pass_key.as_ref()) {
+            if let Ok(store_key) = slot_key.unwrap_key(wrapped_key) {
+                return Ok(store_key);
+            }
+        }
+    }
+    Err(err_msg!(Encryption, "Error unlocking store key"))
+}
+
+/// Encrypt the store key for a new key slot, returning the key reference
+/// and the encrypted store key
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub(crate) fn wrap_key_slot(
+    method: StoreKeyMethod,
+    pass_key: PassKey<'_>,
+    store_key: &StoreKey,
+) -> Result<(String, Vec<u8>), Error> {
+    if store_key.is_empty() {
+        return Err(err_msg!(
+            Unsupported,
+            "Key slots are not supported for unprotected stores"
+        ));
+    }
+    if method == StoreKeyMethod::Unprotected
+        || (method == StoreKeyMethod::RawKey && pass_key.is_empty())
+    {
+        return Err(err_msg!(Input, "A pass key is required for a key slot"));
+    }
+    let (slot_key, slot_ref) = method.resolve(pass_key)?;
+    Ok((slot_ref.into_uri(), slot_key.wrap_key(store_key)?))
+}
+
 #[inline]
 pub fn random_profile_name() -> String {
     uuid::Uuid::new_v4().to_string()
@@ -0,0 +1,1055 @@
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::sync::Arc;
+
+use async_lock::{Mutex, MutexGuard, MutexGuardArc};
+use futures_lite::stream;
+use rexie::{KeyRange, Rexie, TransactionMode};
+use send_wrapper::SendWrapper;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+
+use super::{Backend, BackendSession, OrderBy};
+use crate::{
+    entry::{EncEntryTag, Entry, EntryKind, EntryOperation, EntryTag, Scan, TagFilter},
+    error::Error,
+    future::{spawn_ok, unblock, BoxFuture},
+    protect::{EntryEncryptor, KeyCache, PassKey, ProfileId, ProfileKey, StoreKeyMethod},
+    wql::{
+        matcher::{TagMatch, TagMatchEncoder},
+        tags::{tag_query, TagQueryEncoder},
+    },
+};
+
+mod provision;
+pub use provision::IndexedDbStoreOptions;
+
+const CONFIG_STORE: &str = "config";
+const PROFILES_STORE: &str = "profiles";
+const PROFILE_NAME_INDEX: &str = "name";
+const ITEMS_STORE: &str = "items";
+const ITEM_KEY_INDEX: &str = "key";
+const ITEM_PROFILE_INDEX: &str = "profile_id";
+const PAGE_SIZE: usize = 32;
+
+/// An IndexedDB browser store
+pub struct IndexedDbBackend {
+    db: Arc<SendWrapper<Rexie>>,
+    active_profile: String,
+    key_cache: Arc<KeyCache>,
+    txn_lock: Arc<Mutex<()>>,
+    name: String,
+}
+
+impl IndexedDbBackend {
+    pub(crate) fn new(
+        db: Rexie,
+        active_profile: String,
+        key_cache: KeyCache,
+        name: String,
+    ) -> Self {
+        Self {
+            db: Arc::new(SendWrapper::new(db)),
+            active_profile,
+            key_cache: Arc::new(key_cache),
+            txn_lock: Arc::new(Mutex::new(())),
+            name,
+        }
+    }
+}
+
+impl Debug for IndexedDbBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IndexedDbStore")
+            .field("active_profile", &self.active_profile)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl Backend for IndexedDbBackend {
+    type Session = IndexedDbSession;
+
+    fn create_profile(&self, name: Option<String>) -> BoxFuture<'_, Result<String, Error>> {
+        let name = name.unwrap_or_else(random_profile_name);
+        wrap_future(async move {
+            let store_key = self.key_cache.store_key.clone();
+            let (profile_key, enc_key) = unblock(move || {
+                let profile_key = ProfileKey::new()?;
+                let enc_key = store_key.wrap_data(profile_key.to_bytes()?)?;
+                Result::<_, Error>::Ok((profile_key, enc_key))
+            })
+            .await?;
+            let _lock = self.txn_lock.lock().await;
+            let pid = insert_profile(&self.db, &name, enc_key).await?;
+            self.key_cache
+                .add_profile(name.clone(), pid, Arc::new(profile_key))
+                .await;
+            Ok(name)
+        })
+    }
+
+    fn get_active_profile(&self) -> String {
+        self.active_profile.clone()
+    }
+
+    fn get_default_profile(&self) -> BoxFuture<'_, Result<String, Error>> {
+        wrap_future(async move {
+            Ok(get_config(&self.db, "default_profile")
+                .await?
+                .unwrap_or_default())
+        })
+    }
+
+    fn set_default_profile(&self, profile: String) -> BoxFuture<'_, Result<(), Error>> {
+        wrap_future(async move {
+            let _lock = self.txn_lock.lock().await;
+            set_config(&self.db, "default_profile", &profile).await
+        })
+    }
+
+    fn list_profiles(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        wrap_future(async move {
+            let txn = self
+                .db
+                .transaction(&[PROFILES_STORE], TransactionMode::ReadOnly)
+                .map_err(idb_err("Error fetching profile list"))?;
+            let rows = txn
+                .store(PROFILES_STORE)
+                .map_err(idb_err("Error fetching profile list"))?
+                .get_all(None, None)
+                .await
+                .map_err(idb_err("Error fetching profile list"))?;
+            txn.done()
+                .await
+                .map_err(idb_err("Error fetching profile list"))?;
+            rows.into_iter()
+                .map(|row| Ok(from_js::<ProfileRecord>(row)?.name))
+                .collect()
+        })
+    }
+
+    fn remove_profile(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        wrap_future(async move {
+            let _lock = self.txn_lock.lock().await;
+            remove_profile(&self.db, &name).await
+        })
+    }
+
+    fn rekey(
+        &mut self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        let pass_key = pass_key.into_owned();
+        wrap_future(async move {
+            let (store_key, store_key_ref) = unblock(move || method.resolve(pass_key)).await?;
+            let store_key = Arc::new(store_key);
+            let _lock = self.txn_lock.lock().await;
+            let mut profiles = fetch_profiles(&self.db).await?;
+            for profile in profiles.iter_mut() {
+                let profile_key = self
+                    .key_cache
+                    .load_key(std::mem::take(&mut profile.profile_key))
+                    .await?;
+                profile.profile_key = unblock({
+                    let store_key = store_key.clone();
+                    move || store_key.wrap_data(profile_key.to_bytes()?)
+                })
+                .await?;
+            }
+            let txn = self
+                .db
+                .transaction(&[CONFIG_STORE, PROFILES_STORE], TransactionMode::ReadWrite)
+                .map_err(idb_err("Error updating profile keys"))?;
+            let store = txn
+                .store(PROFILES_STORE)
+                .map_err(idb_err("Error updating profile keys"))?;
+            for profile in profiles {
+                store
+                    .put(&to_js(&profile)?, None)
+                    .await
+                    .map_err(idb_err("Error updating profile key"))?;
+            }
+            txn.store(CONFIG_STORE)
+                .map_err(idb_err("Error updating store key"))?
+                .put(
+                    &to_js(&ConfigRecord {
+                        name: "key".to_string(),
+                        value: store_key_ref.into_uri(),
+                    })?,
+                    None,
+                )
+                .await
+                .map_err(idb_err("Error updating store key"))?;
+            txn.done()
+                .await
+                .map_err(idb_err("Error updating profile keys"))?;
+            self.key_cache = Arc::new(KeyCache::new(store_key));
+            Ok(())
+        })
+    }
+
+    fn scan(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        _include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        wrap_future(async move {
+            let mut session = self.session(profile, false)?;
+            let (profile_id, key) = session.acquire_key().await?;
+            let rows = perform_scan(
+                &self.db,
+                profile_id,
+                &key,
+                kind,
+                category.as_deref(),
+                tag_filter,
+                offset,
+                limit,
+                order_by,
+                descending,
+            )
+            .await?;
+            let mut batches = Vec::with_capacity(rows.len() / PAGE_SIZE + 1);
+            let mut rows = rows.into_iter().peekable();
+            while rows.peek().is_some() {
+                let batch = rows.by_ref().take(PAGE_SIZE).collect::<Vec<_>>();
+                batches.push(decrypt_batch(batch, &key));
+            }
+            Ok(Scan::new(stream::iter(batches), PAGE_SIZE))
+        })
+    }
+
+    fn seal_token(&self, data: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        wrap_future(async move { self.key_cache.store_key.seal_token(&data) })
+    }
+
+    fn open_token(&self, token: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        wrap_future(async move { self.key_cache.store_key.open_token(&token) })
+    }
+
+    fn session(&self, profile: Option<String>, transaction: bool) -> Result<Self::Session, Error> {
+        Ok(IndexedDbSession {
+            db: self.db.clone(),
+            key_cache: self.key_cache.clone(),
+            txn_lock: self.txn_lock.clone(),
+            profile: profile.unwrap_or_else(|| self.active_profile.clone()),
+            profile_key: None,
+            transaction,
+            txn: None,
+        })
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), Error>> {
+        // the database connection is closed when the last reference is dropped
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+/// A session against an IndexedDB store
+///
+/// IndexedDB transactions are committed automatically as soon as no requests
+/// are pending, so they cannot span the lifetime of a session. Instead, a
+/// transactional session holds the store write lock and records the previous
+/// state of each modified record, which is restored on roll-back.
+pub struct IndexedDbSession {
+    db: Arc<SendWrapper<Rexie>>,
+    key_cache: Arc<KeyCache>,
+    txn_lock: Arc<Mutex<()>>,
+    profile: String,
+    profile_key: Option<(ProfileId, Arc<ProfileKey>)>,
+    transaction: bool,
+    txn: Option<SessionTxn>,
+}
+
+struct SessionTxn {
+    _lock: MutexGuardArc<()>,
+    undo: Vec<UndoOp>,
+}
+
+enum UndoOp {
+    Remove(i64),
+    Restore(ItemRecord),
+}
+
+impl Debug for IndexedDbSession {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IndexedDbSession")
+            .field("profile", &self.profile)
+            .field("transaction", &self.transaction)
+            .finish()
+    }
+}
+
+impl IndexedDbSession {
+    async fn acquire_key(&mut self) -> Result<(ProfileId, Arc<ProfileKey>), Error> {
+        if self.transaction && self.txn.is_none() {
+            debug!("Start transaction");
+            self.txn.replace(SessionTxn {
+                _lock: self.txn_lock.lock_arc().await,
+                undo: Vec::new(),
+            });
+        }
+        if let Some((pid, key)) = self.profile_key.as_ref() {
+            return Ok((*pid, key.clone()));
+        }
+        let (pid, key) = if let Some((pid, key)) = self.key_cache.get_profile(&self.profile).await {
+            (pid, key)
+        } else if let Some(profile) = fetch_profile(&self.db, &self.profile).await? {
+            let pid = profile
+                .id
+                .ok_or_else(|| err_msg!(Unexpected, "Missing profile identifier"))?;
+            let key = Arc::new(self.key_cache.load_key(profile.profile_key).await?);
+            self.key_cache
+                .add_profile(self.profile.clone(), pid, key.clone())
+                .await;
+            (pid, key)
+        } else {
+            return Err(err_msg!(NotFound, "Profile not found"));
+        };
+        self.profile_key.replace((pid, key.clone()));
+        Ok((pid, key))
+    }
+}
+
+impl Drop for IndexedDbSession {
+    fn drop(&mut self) {
+        if let Some(txn) = self.txn.take() {
+            debug!("Dropped transaction: roll-back");
+            let db = self.db.clone();
+            spawn_ok(SendWrapper::new(async move {
+                if let Err(err) = rollback(&db, txn).await {
+                    warn!("Error rolling back dropped transaction: {}", err);
+                }
+            }));
+        }
+    }
+}
+
+impl BackendSession for IndexedDbSession {
+    fn count<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        _include_deleted: bool,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        wrap_future(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let rows = perform_scan(
+                &self.db, profile_id, &key, kind, category, tag_filter, None, None, None, false,
+            )
+            .await?;
+            Ok(rows.len() as i64)
+        })
+    }
+
+    fn fetch<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        _for_update: bool,
+    ) -> BoxFuture<'q, Result<Option<Entry>, Error>> {
+        wrap_future(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let (enc_category, enc_name) = unblock({
+                let key = key.clone();
+                let category = ProfileKey::prepare_input(category.as_bytes());
+                let name = ProfileKey::prepare_input(name.as_bytes());
+                move || {
+                    Result::<_, Error>::Ok((
+                        key.encrypt_entry_category(category)?,
+                        key.encrypt_entry_name(name)?,
+                    ))
+                }
+            })
+            .await?;
+            let item_key = item_key(profile_id, kind, &enc_category, &enc_name);
+            if let Some(row) = fetch_item(&self.db, &item_key).await? {
+                if row.is_expired(now_millis()) {
+                    return Ok(None);
+                }
+                let value =
+                    key.decrypt_entry_value(category.as_bytes(), name.as_bytes(), row.value)?;
+                let tags =
+                    key.decrypt_entry_tags(row.tags.into_iter().map(Into::into).collect())?;
+                Ok(Some(Entry::new(kind, category, name, value, tags)))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    fn fetch_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        _for_update: bool,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        wrap_future(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let rows = perform_scan(
+                &self.db, profile_id, &key, kind, category, tag_filter, None, limit, order_by,
+                descending,
+            )
+            .await?;
+            decrypt_batch(rows, &key)
+        })
+    }
+
+    fn remove_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        wrap_future(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let _lock = lock_unless_txn(&self.txn, &self.txn_lock).await;
+            let rows = perform_scan(
+                &self.db, profile_id, &key, kind, category, tag_filter, None, None, None, false,
+            )
+            .await?;
+            let count = rows.len() as i64;
+            let undo = self.txn.as_mut().map(|txn| &mut txn.undo);
+            remove_items(&self.db, rows, undo).await?;
+            Ok(count)
+        })
+    }
+
+    fn update<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        let category = ProfileKey::prepare_input(category.as_bytes());
+        let name = ProfileKey::prepare_input(name.as_bytes());
+
+        match operation {
+            op @ (EntryOperation::Insert
+            | EntryOperation::Replace
+            | EntryOperation::InsertOrReplace) => {
+                let value = ProfileKey::prepare_input(value.unwrap_or_default());
+                let tags = tags.map(|t| t.to_vec());
+                wrap_future(async move {
+                    let (profile_id, key) = self.acquire_key().await?;
+                    let (enc_category, enc_name, enc_value, enc_tags) = unblock(move || {
+                        let enc_value =
+                            key.encrypt_entry_value(category.as_ref(), name.as_ref(), value)?;
+                        Result::<_, Error>::Ok((
+                            key.encrypt_entry_category(category)?,
+                            key.encrypt_entry_name(name)?,
+                            enc_value,
+                            tags.map(|t| key.encrypt_entry_tags(t)).transpose()?,
+                        ))
+                    })
+                    .await?;
+                    let record = ItemRecord {
+                        id: None,
+                        profile_id,
+                        kind: kind as u8,
+                        key: item_key(profile_id, kind, &enc_category, &enc_name),
+                        category: enc_category,
+                        name: enc_name,
+                        value: enc_value,
+                        tags: enc_tags
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(Into::into)
+                            .collect(),
+                        expiry: expiry_ms.map(|ms| now_millis() + ms),
+                    };
+                    let _lock = lock_unless_txn(&self.txn, &self.txn_lock).await;
+                    let undo = self.txn.as_mut().map(|txn| &mut txn.undo);
+                    perform_insert(&self.db, record, op, undo).await
+                })
+            }
+
+            EntryOperation::Remove => wrap_future(async move {
+                let (profile_id, key) = self.acquire_key().await?;
+                let (enc_category, enc_name) = unblock(move || {
+                    Result::<_, Error>::Ok((
+                        key.encrypt_entry_category(category)?,
+                        key.encrypt_entry_name(name)?,
+                    ))
+                })
+                .await?;
+                let item_key = item_key(profile_id, kind, &enc_category, &enc_name);
+                let _lock = lock_unless_txn(&self.txn, &self.txn_lock).await;
+                match fetch_item(&self.db, &item_key).await? {
+                    Some(row) if !row.is_expired(now_millis()) => {
+                        let undo = self.txn.as_mut().map(|txn| &mut txn.undo);
+                        remove_items(&self.db, vec![row], undo).await
+                    }
+                    _ => Err(err_msg!(NotFound, "Entry not found")),
+                }
+            }),
+        }
+    }
+
+    fn ping(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+        wrap_future(async move {
+            self.acquire_key().await?;
+            if fetch_profile(&self.db, &self.profile).await?.is_none() {
+                Err(err_msg!(NotFound, "Session profile has been removed"))
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    fn close(&mut self, commit: bool) -> BoxFuture<'_, Result<(), Error>> {
+        wrap_future(async move {
+            if let Some(txn) = self.txn.take() {
+                if commit {
+                    debug!("Commit transaction on close");
+                } else {
+                    debug!("Roll-back transaction on close");
+                    rollback(&self.db, txn).await?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ConfigRecord {
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProfileRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<ProfileId>,
+    name: String,
+    #[serde(with = "serde_bytes")]
+    profile_key: Vec<u8>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ItemRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<i64>,
+    profile_id: ProfileId,
+    kind: u8,
+    key: String,
+    #[serde(with = "serde_bytes")]
+    category: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    name: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    value: Vec<u8>,
+    tags: Vec<TagRecord>,
+    expiry: Option<i64>,
+}
+
+impl ItemRecord {
+    fn is_expired(&self, now: i64) -> bool {
+        matches!(self.expiry, Some(expiry) if expiry <= now)
+    }
+
+    // the lowest value of a plaintext tag, given the encrypted tag name
+    fn plaintext_tag(&self, name: &[u8]) -> Option<&[u8]> {
+        self.tags
+            .iter()
+            .filter(|tag| tag.plaintext && tag.name == name)
+            .map(|tag| tag.value.as_slice())
+            .min()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct TagRecord {
+    #[serde(with = "serde_bytes")]
+    name: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    value: Vec<u8>,
+    plaintext: bool,
+}
+
+impl From<EncEntryTag> for TagRecord {
+    fn from(tag: EncEntryTag) -> Self {
+        Self {
+            name: tag.name,
+            value: tag.value,
+            plaintext: tag.plaintext,
+        }
+    }
+}
+
+impl From<TagRecord> for EncEntryTag {
+    fn from(tag: TagRecord) -> Self {
+        Self {
+            name: tag.name,
+            value: tag.value,
+            plaintext: tag.plaintext,
+        }
+    }
+}
+
+// IndexedDB handles are bound to the browser thread, which is the only
+// thread available to wasm32 targets
+#[inline]
+fn wrap_future<'a, T>(fut: impl Future<Output = T> + 'a) -> BoxFuture<'a, T> {
+    Box::pin(SendWrapper::new(fut))
+}
+
+// writes outside of a transaction hold the store lock for their duration
+async fn lock_unless_txn<'a>(
+    txn: &Option<SessionTxn>,
+    txn_lock: &'a Mutex<()>,
+) -> Option<MutexGuard<'a, ()>> {
+    if txn.is_none() {
+        Some(txn_lock.lock().await)
+    } else {
+        None
+    }
+}
+
+fn idb_err(message: &'static str) -> impl FnOnce(rexie::Error) -> Error {
+    move |err| err_msg!(Backend, "{}: {}", message, err)
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, Error> {
+    serde_wasm_bindgen::to_value(value)
+        .map_err(|err| err_msg!(Unexpected, "Error encoding record: {}", err))
+}
+
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, Error> {
+    serde_wasm_bindgen::from_value(value)
+        .map_err(|err| err_msg!(Unexpected, "Error decoding record: {}", err))
+}
+
+#[inline]
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+#[inline]
+fn random_profile_name() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+// the unique lookup key for a record, equivalent to the unique index
+// on (profile_id, kind, category, name) used by the SQL backends
+fn item_key(
+    profile_id: ProfileId,
+    kind: EntryKind,
+    enc_category: &[u8],
+    enc_name: &[u8],
+) -> String {
+    format!(
+        "{}:{}:{}:{}",
+        profile_id,
+        kind as u8,
+        hex::encode(enc_category),
+        hex::encode(enc_name)
+    )
+}
+
+async fn get_config(db: &Rexie, name: &str) -> Result<Option<String>, Error> {
+    let txn = db
+        .transaction(&[CONFIG_STORE], TransactionMode::ReadOnly)
+        .map_err(idb_err("Error fetching store configuration"))?;
+    let row = txn
+        .store(CONFIG_STORE)
+        .map_err(idb_err("Error fetching store configuration"))?
+        .get(JsValue::from_str(name))
+        .await
+        .map_err(idb_err("Error fetching store configuration"))?;
+    txn.done()
+        .await
+        .map_err(idb_err("Error fetching store configuration"))?;
+    row.map(|row| Ok(from_js::<ConfigRecord>(row)?.value))
+        .transpose()
+}
+
+async fn set_config(db: &Rexie, name: &str, value: &str) -> Result<(), Error> {
+    let txn = db
+        .transaction(&[CONFIG_STORE], TransactionMode::ReadWrite)
+        .map_err(idb_err("Error updating store configuration"))?;
+    txn.store(CONFIG_STORE)
+        .map_err(idb_err("Error updating store configuration"))?
+        .put(
+            &to_js(&ConfigRecord {
+                name: name.to_string(),
+                value: value.to_string(),
+            })?,
+            None,
+        )
+        .await
+        .map_err(idb_err("Error updating store configuration"))?;
+    txn.done()
+        .await
+        .map_err(idb_err("Error updating store configuration"))?;
+    Ok(())
+}
+
+async fn fetch_profile(db: &Rexie, name: &str) -> Result<Option<ProfileRecord>, Error> {
+    let txn = db
+        .transaction(&[PROFILES_STORE], TransactionMode::ReadOnly)
+        .map_err(idb_err("Error fetching profile"))?;
+    let row = txn
+        .store(PROFILES_STORE)
+        .and_then(|store| store.index(PROFILE_NAME_INDEX))
+        .map_err(idb_err("Error fetching profile"))?
+        .get(JsValue::from_str(name))
+        .await
+        .map_err(idb_err("Error fetching profile"))?;
+    txn.done()
+        .await
+        .map_err(idb_err("Error fetching profile"))?;
+    row.map(from_js).transpose()
+}
+
+async fn fetch_profiles(db: &Rexie) -> Result<Vec<ProfileRecord>, Error> {
+    let txn = db
+        .transaction(&[PROFILES_STORE], TransactionMode::ReadOnly)
+        .map_err(idb_err("Error fetching profiles"))?;
+    let rows = txn
+        .store(PROFILES_STORE)
+        .map_err(idb_err("Error fetching profiles"))?
+        .get_all(None, None)
+        .await
+        .map_err(idb_err("Error fetching profiles"))?;
+    txn.done()
+        .await
+        .map_err(idb_err("Error fetching profiles"))?;
+    rows.into_iter().map(from_js).collect()
+}
+
+async fn insert_profile(db: &Rexie, name: &str, enc_key: Vec<u8>) -> Result<ProfileId, Error> {
+    let txn = db
+        .transaction(&[PROFILES_STORE], TransactionMode::ReadWrite)
+        .map_err(idb_err("Error creating profile"))?;
+    let store = txn
+        .store(PROFILES_STORE)
+        .map_err(idb_err("Error creating profile"))?;
+    let found = store
+        .index(PROFILE_NAME_INDEX)
+        .map_err(idb_err("Error creating profile"))?
+        .get(JsValue::from_str(name))
+        .await
+        .map_err(idb_err("Error creating profile"))?;
+    if found.is_some() {
+        txn.abort()
+            .await
+            .map_err(idb_err("Error creating profile"))?;
+        return Err(err_msg!(Duplicate, "Duplicate profile name"));
+    }
+    let pid = store
+        .add(
+            &to_js(&ProfileRecord {
+                id: None,
+                name: name.to_string(),
+                profile_key: enc_key,
+            })?,
+            None,
+        )
+        .await
+        .map_err(idb_err("Error creating profile"))?;
+    txn.done()
+        .await
+        .map_err(idb_err("Error creating profile"))?;
+    pid.as_f64()
+        .map(|pid| pid as ProfileId)
+        .ok_or_else(|| err_msg!(Unexpected, "Invalid profile identifier"))
+}
+
+async fn remove_profile(db: &Rexie, name: &str) -> Result<bool, Error> {
+    let pid = match fetch_profile(db, name).await? {
+        Some(ProfileRecord { id: Some(pid), .. }) => pid,
+        _ => return Ok(false),
+    };
+    let txn = db
+        .transaction(&[PROFILES_STORE, ITEMS_STORE], TransactionMode::ReadWrite)
+        .map_err(idb_err("Error removing profile"))?;
+    let items = txn
+        .store(ITEMS_STORE)
+        .map_err(idb_err("Error removing profile"))?;
+    let rows = items
+        .index(ITEM_PROFILE_INDEX)
+        .map_err(idb_err("Error removing profile"))?
+        .get_all(Some(profile_range(pid)?), None)
+        .await
+        .map_err(idb_err("Error removing profile"))?;
+    for row in rows {
+        if let Some(id) = from_js::<ItemRecord>(row)?.id {
+            items
+                .delete(JsValue::from_f64(id as f64))
+                .await
+                .map_err(idb_err("Error removing profile"))?;
+        }
+    }
+    txn.store(PROFILES_STORE)
+        .map_err(idb_err("Error removing profile"))?
+        .delete(JsValue::from_f64(pid as f64))
+        .await
+        .map_err(idb_err("Error removing profile"))?;
+    txn.done()
+        .await
+        .map_err(idb_err("Error removing profile"))?;
+    Ok(true)
+}
+
+fn profile_range(profile_id: ProfileId) -> Result<KeyRange, Error> {
+    KeyRange::only(&JsValue::from_f64(profile_id as f64))
+        .map_err(idb_err("Error creating key range"))
+}
+
+async fn fetch_item(db: &Rexie, item_key: &str) -> Result<Option<ItemRecord>, Error> {
+    let txn = db
+        .transaction(&[ITEMS_STORE], TransactionMode::ReadOnly)
+        .map_err(idb_err("Error performing fetch query"))?;
+    let row = txn
+        .store(ITEMS_STORE)
+        .and_then(|store| store.index(ITEM_KEY_INDEX))
+        .map_err(idb_err("Error performing fetch query"))?
+        .get(JsValue::from_str(item_key))
+        .await
+        .map_err(idb_err("Error performing fetch query"))?;
+    txn.done()
+        .await
+        .map_err(idb_err("Error performing fetch query"))?;
+    row.map(from_js).transpose()
+}
+
+async fn perform_insert(
+    db: &Rexie,
+    mut record: ItemRecord,
+    operation: EntryOperation,
+    undo: Option<&mut Vec<UndoOp>>,
+) -> Result<(), Error> {
+    let txn = db
+        .transaction(&[ITEMS_STORE], TransactionMode::ReadWrite)
+        .map_err(idb_err("Error updating entry"))?;
+    let store = txn
+        .store(ITEMS_STORE)
+        .map_err(idb_err("Error updating entry"))?;
+    let existing = store
+        .index(ITEM_KEY_INDEX)
+        .map_err(idb_err("Error updating entry"))?
+        .get(JsValue::from_str(&record.key))
+        .await
+        .map_err(idb_err("Error updating entry"))?
+        .map(from_js::<ItemRecord>)
+        .transpose()?;
+    let undo_op = match existing {
+        Some(prev) if operation == EntryOperation::Insert && !prev.is_expired(now_millis()) => {
+            txn.abort().await.map_err(idb_err("Error updating entry"))?;
+            return Err(err_msg!(Duplicate, "Duplicate entry"));
+        }
+        Some(prev) => {
+            trace!("Update entry");
+            record.id = prev.id;
+            store
+                .put(&to_js(&record)?, None)
+                .await
+                .map_err(idb_err("Error updating existing entry"))?;
+            UndoOp::Restore(prev)
+        }
+        None if operation == EntryOperation::Replace => {
+            txn.abort().await.map_err(idb_err("Error updating entry"))?;
+            return Err(err_msg!(NotFound, "Error updating existing entry"));
+        }
+        None => {
+            trace!("Insert entry");
+            let id = store
+                .add(&to_js(&record)?, None)
+                .await
+                .map_err(idb_err("Error inserting new entry"))?
+                .as_f64()
+                .ok_or_else(|| err_msg!(Unexpected, "Invalid entry identifier"))?;
+            UndoOp::Remove(id as i64)
+        }
+    };
+    txn.done().await.map_err(idb_err("Error updating entry"))?;
+    if let Some(undo) = undo {
+        undo.push(undo_op);
+    }
+    Ok(())
+}
+
+async fn remove_items(
+    db: &Rexie,
+    rows: Vec<ItemRecord>,
+    undo: Option<&mut Vec<UndoOp>>,
+) -> Result<(), Error> {
+    trace!("Remove entries");
+    let txn = db
+        .transaction(&[ITEMS_STORE], TransactionMode::ReadWrite)
+        .map_err(idb_err("Error removing entry"))?;
+    let store = txn
+        .store(ITEMS_STORE)
+        .map_err(idb_err("Error removing entry"))?;
+    for row in rows.iter() {
+        if let Some(id) = row.id {
+            store
+                .delete(JsValue::from_f64(id as f64))
+                .await
+                .map_err(idb_err("Error removing entry"))?;
+        }
+    }
+    txn.done().await.map_err(idb_err("Error removing entry"))?;
+    if let Some(undo) = undo {
+        undo.extend(rows.into_iter().map(UndoOp::Restore));
+    }
+    Ok(())
+}
+
+async fn rollback(db: &Rexie, txn: SessionTxn) -> Result<(), Error> {
+    if txn.undo.is_empty() {
+        return Ok(());
+    }
+    let idb_txn = db
+        .transaction(&[ITEMS_STORE], TransactionMode::ReadWrite)
+        .map_err(idb_err("Error rolling back transaction"))?;
+    let store = idb_txn
+        .store(ITEMS_STORE)
+        .map_err(idb_err("Error rolling back transaction"))?;
+    for op in txn.undo.into_iter().rev() {
+        match op {
+            UndoOp::Remove(id) => store.delete(JsValue::from_f64(id as f64)).await,
+            UndoOp::Restore(row) => store.put(&to_js(&row)?, None).await.map(|_| ()),
+        }
+        .map_err(idb_err("Error rolling back transaction"))?;
+    }
+    idb_txn
+        .done()
+        .await
+        .map_err(idb_err("Error rolling back transaction"))?;
+    Ok(())
+}
+
+fn encode_tag_filter(
+    tag_filter: Option<TagFilter>,
+    key: &ProfileKey,
+) -> Result<Option<TagMatch>, Error> {
+    if let Some(tag_filter) = tag_filter {
+        let tag_query = tag_query(tag_filter.query)?;
+        let mut enc = TagMatchEncoder::new(
+            |name| key.encrypt_tag_name(ProfileKey::prepare_input(name.as_bytes())),
+            |value| key.encrypt_tag_value(ProfileKey::prepare_input(value.as_bytes())),
+        );
+        enc.encode_query(&tag_query)
+    } else {
+        Ok(None)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn perform_scan(
+    db: &Rexie,
+    profile_id: ProfileId,
+    key: &ProfileKey,
+    kind: Option<EntryKind>,
+    category: Option<&str>,
+    tag_filter: Option<TagFilter>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+    order_by: Option<OrderBy>,
+    descending: bool,
+) -> Result<Vec<ItemRecord>, Error> {
+    let enc_category = category
+        .map(|c| key.encrypt_entry_category(ProfileKey::prepare_input(c.as_bytes())))
+        .transpose()?;
+    let tag_filter = encode_tag_filter(tag_filter, key)?;
+    let order_tag = match order_by.as_ref() {
+        Some(OrderBy::Tag(name)) => {
+            Some(key.encrypt_tag_name(ProfileKey::prepare_input(name.as_bytes()))?)
+        }
+        Some(OrderBy::CreatedAt | OrderBy::UpdatedAt) => {
+            return Err(err_msg!(
+                Unsupported,
+                "Ordering by record timestamps is not supported by this backend"
+            ))
+        }
+        _ => None,
+    };
+
+    let txn = db
+        .transaction(&[ITEMS_STORE], TransactionMode::ReadOnly)
+        .map_err(idb_err("Error performing scan query"))?;
+    let rows = txn
+        .store(ITEMS_STORE)
+        .and_then(|store| store.index(ITEM_PROFILE_INDEX))
+        .map_err(idb_err("Error performing scan query"))?
+        .get_all(Some(profile_range(profile_id)?), None)
+        .await
+        .map_err(idb_err("Error performing scan query"))?;
+    txn.done()
+        .await
+        .map_err(idb_err("Error performing scan query"))?;
+
+    let now = now_millis();
+    let mut results = Vec::new();
+    for row in rows {
+        let row: ItemRecord = from_js(row)?;
+        if row.is_expired(now)
+            || kind.map(|k| k as u8 != row.kind).unwrap_or(false)
+            || enc_category
+                .as_ref()
+                .map(|c| c != &row.category)
+                .unwrap_or(false)
+        {
+            continue;
+        }
+        if let Some(filter) = tag_filter.as_ref() {
+            let tags = row.tags.iter().cloned().map(Into::into).collect::<Vec<_>>();
+            if !filter.matches(&tags) {
+                continue;
+            }
+        }
+        results.push(row);
+    }
+    if order_by.is_some() {
+        match order_tag {
+            Some(name) => results
+                .sort_by_cached_key(|row| (row.plaintext_tag(&name).map(<[u8]>::to_vec), row.id)),
+            None => results.sort_by_key(|row| row.id),
+        }
+        if descending {
+            results.reverse();
+        }
+    }
+    let offset = offset.unwrap_or(0).max(0) as usize;
+    let limit = limit.filter(|l| *l >= 0).map(|l| l as usize);
+    Ok(results
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect())
+}
+
+fn decrypt_batch(rows: Vec<ItemRecord>, key: &ProfileKey) -> Result<Vec<Entry>, Error> {
+    let mut batch = Vec::with_capacity(rows.len());
+    for row in rows {
+        let kind = EntryKind::try_from(row.kind as usize)?;
+        let category = key.decrypt_entry_category(row.category)?;
+        let name = key.decrypt_entry_name(row.name)?;
+        let value = key.decrypt_entry_value(category.as_bytes(), name.as_bytes(), row.value)?;
+        let tags = key.decrypt_entry_tags(row.tags.into_iter().map(Into::into).collect())?;
+        batch.push(Entry::new(kind, category, name, value, tags));
+    }
+    Ok(batch)
+}
@@ -0,0 +1,209 @@
+use rexie::{Index, ObjectStore, Rexie};
+
+use super::{
+    fetch_profile, get_config, idb_err, insert_profile, random_profile_name, set_config,
+    IndexedDbBackend, CONFIG_STORE, ITEMS_STORE, ITEM_KEY_INDEX, ITEM_PROFILE_INDEX,
+    PROFILES_STORE, PROFILE_NAME_INDEX,
+};
+use crate::{
+    backend::ManageBackend,
+    error::Error,
+    future::{unblock, BoxFuture},
+    options::IntoOptions,
+    protect::{KeyCache, PassKey, ProfileKey, StoreKeyMethod, StoreKeyReference},
+};
+
+const STORE_VERSION: &str = "1";
+
+/// Configuration options for IndexedDB stores
+#[derive(Debug)]
+pub struct IndexedDbStoreOptions {
+    pub(crate) name: String,
+}
+
+impl IndexedDbStoreOptions {
+    /// Initialize `IndexedDbStoreOptions` from a generic set of options
+    pub fn new<'a>(options: impl IntoOptions<'a>) -> Result<Self, Error> {
+        let opts = options.into_options()?;
+        let mut name = opts.host.to_string();
+        name.push_str(&opts.path);
+        if name.is_empty() {
+            return Err(err_msg!(Input, "Missing IndexedDB database name"));
+        }
+        Ok(Self { name })
+    }
+
+    async fn connect(&self) -> Result<Rexie, Error> {
+        Rexie::builder(&self.name)
+            .version(1)
+            .add_object_store(ObjectStore::new(CONFIG_STORE).key_path("name"))
+            .add_object_store(
+                ObjectStore::new(PROFILES_STORE)
+                    .key_path("id")
+                    .auto_increment(true)
+                    .add_index(Index::new(PROFILE_NAME_INDEX, "name").unique(true)),
+            )
+            .add_object_store(
+                ObjectStore::new(ITEMS_STORE)
+                    .key_path("id")
+                    .auto_increment(true)
+                    .add_index(Index::new(ITEM_KEY_INDEX, "key").unique(true))
+                    .add_index(Index::new(ITEM_PROFILE_INDEX, "profile_id")),
+            )
+            .build()
+            .await
+            .map_err(idb_err("Error opening IndexedDB database"))
+    }
+
+    /// Provision a new IndexedDB store from these configuration options
+    pub async fn provision(
+        self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+        profile: Option<String>,
+        recreate: bool,
+    ) -> Result<IndexedDbBackend, Error> {
+        if recreate {
+            self.remove_db().await?;
+        }
+        let db = self.connect().await?;
+
+        if !recreate && get_config(&db, "version").await?.is_some() {
+            return open_db(db, Some(method), pass_key, profile, self.name).await;
+        }
+        // else: no store version, assume empty database
+
+        if method == StoreKeyMethod::RawKey && pass_key.is_empty() {
+            // disallow random key for a new database
+            return Err(err_msg!(
+                Input,
+                "Cannot create a store with a blank raw key"
+            ));
+        }
+        let (profile_key, enc_profile_key, store_key, store_key_ref) = unblock({
+            let pass_key = pass_key.into_owned();
+            move || {
+                let (store_key, store_key_ref) = method.resolve(pass_key)?;
+                let profile_key = ProfileKey::new()?;
+                let enc_profile_key = store_key.wrap_data(profile_key.to_bytes()?)?;
+                Result::<_, Error>::Ok((profile_key, enc_profile_key, store_key, store_key_ref))
+            }
+        })
+        .await?;
+
+        let default_profile = profile.unwrap_or_else(random_profile_name);
+        set_config(&db, "default_profile", &default_profile).await?;
+        set_config(&db, "key", &store_key_ref.into_uri()).await?;
+        let profile_id = insert_profile(&db, &default_profile, enc_profile_key).await?;
+        // written last, so that an interrupted provisioning is not treated as a valid store
+        set_config(&db, "version", STORE_VERSION).await?;
+
+        let mut key_cache = KeyCache::new(store_key);
+        key_cache.add_profile_mut(default_profile.clone(), profile_id, profile_key);
+
+        Ok(IndexedDbBackend::new(
+            db,
+            default_profile,
+            key_cache,
+            self.name,
+        ))
+    }
+
+    /// Open an existing IndexedDB store from this set of configuration options
+    pub async fn open(
+        self,
+        method: Option<StoreKeyMethod>,
+        pass_key: PassKey<'_>,
+        profile: Option<String>,
+    ) -> Result<IndexedDbBackend, Error> {
+        let db = self.connect().await?;
+        if get_config(&db, "version").await?.is_none() {
+            // opening the database has created it, so it must be removed again
+            db.close();
+            self.remove_db().await?;
+            return Err(err_msg!(NotFound, "The requested database was not found"));
+        }
+        open_db(db, method, pass_key, profile, self.name).await
+    }
+
+    /// Remove the IndexedDB store defined by these configuration options
+    pub async fn remove(self) -> Result<bool, Error> {
+        self.remove_db().await?;
+        Ok(true)
+    }
+
+    async fn remove_db(&self) -> Result<(), Error> {
+        Rexie::delete(&self.name)
+            .await
+            .map_err(idb_err("Error removing IndexedDB database"))
+    }
+}
+
+impl<'a> ManageBackend<'a> for IndexedDbStoreOptions {
+    type Backend = IndexedDbBackend;
+
+    fn open_backend(
+        self,
+        method: Option<StoreKeyMethod>,
+        pass_key: PassKey<'a>,
+        profile: Option<String>,
+    ) -> BoxFuture<'a, Result<IndexedDbBackend, Error>> {
+        super::wrap_future(self.open(method, pass_key, profile))
+    }
+
+    fn provision_backend(
+        self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'a>,
+        profile: Option<String>,
+        recreate: bool,
+    ) -> BoxFuture<'a, Result<IndexedDbBackend, Error>> {
+        super::wrap_future(self.provision(method, pass_key, profile, recreate))
+    }
+
+    fn remove_backend(self) -> BoxFuture<'a, Result<bool, Error>> {
+        super::wrap_future(self.remove())
+    }
+}
+
+async fn open_db(
+    db: Rexie,
+    method: Option<StoreKeyMethod>,
+    pass_key: PassKey<'_>,
+    profile: Option<String>,
+    name: String,
+) -> Result<IndexedDbBackend, Error> {
+    if get_config(&db, "version").await?.as_deref() != Some(STORE_VERSION) {
+        return Err(err_msg!(Unsupported, "Unsupported store version"));
+    }
+    let profile = profile
+        .or(get_config(&db, "default_profile").await?)
+        .ok_or_else(|| err_msg!(Unsupported, "Default store profile not found"))?;
+    let store_key = if let Some(store_key_ref) = get_config(&db, "key").await? {
+        let wrap_ref = StoreKeyReference::parse_uri(&store_key_ref)?;
+        if let Some(method) = &method {
+            if !wrap_ref.compare_method(method) {
+                return Err(err_msg!(Input, "Store key method mismatch"));
+            }
+        }
+        unblock({
+            let pass_key = pass_key.into_owned();
+            move || wrap_ref.resolve_with(method.as_ref(), pass_key)
+        })
+        .await?
+    } else {
+        return Err(err_msg!(Unsupported, "Store key not found"));
+    };
+
+    let mut key_cache = KeyCache::new(store_key);
+    let row = fetch_profile(&db, &profile)
+        .await?
+        .ok_or_else(|| err_msg!(NotFound, "Profile not found"))?;
+    let profile_id = row
+        .id
+        .ok_or_else(|| err_msg!(Unexpected, "Missing profile identifier"))?;
+    let profile_key = key_cache.load_key(row.profile_key).await?;
+    key_cache.add_profile_mut(profile.clone(), profile_id, profile_key);
+
+    Ok(IndexedDbBackend::new(db, profile, key_cache, name))
+}
@@ -1,35 +1,130 @@
 //! Storage backends supported by aries-askar
 
-use std::fmt::Debug;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Display, Formatter},
+    str::FromStr,
+    sync::Arc,
+};
 
 use crate::{
-    entry::{Entry, EntryKind, EntryOperation, EntryTag, Scan, TagFilter},
+    crypto::buffer::SecretBytes,
+    entry::{
+        AuditRecord, Entry, EntryKind, EntryOperation, EntryTag, EntryUpdate, EntryVersion,
+        ProfileDetails, Scan, ScanCursor, ScanPosition, Subscription, TagFilter,
+    },
     error::{Error, ErrorKind},
     future::BoxFuture,
     protect::{PassKey, StoreKeyMethod},
 };
 
-#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[cfg(any(
+    feature = "mssql",
+    feature = "postgres",
+    feature = "redb",
+    feature = "sqlite"
+))]
 pub(crate) mod db_utils;
 
+#[cfg(feature = "blob")]
+#[cfg_attr(docsrs, doc(cfg(feature = "blob")))]
+/// Object storage support for large record values
+pub mod blob;
+
+#[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "indexeddb")))]
+/// IndexedDB browser storage support
+pub mod indexeddb;
+
+#[cfg(feature = "mssql")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mssql")))]
+/// SQL Server database support
+pub mod mssql;
+
 #[cfg(feature = "postgres")]
 #[cfg_attr(docsrs, doc(cfg(feature = "postgres")))]
 /// Postgres database support
 pub mod postgres;
 
+#[cfg(feature = "redb")]
+#[cfg_attr(docsrs, doc(cfg(feature = "redb")))]
+/// Embedded redb database support
+pub mod redb;
+
+#[cfg(feature = "redis")]
+#[cfg_attr(docsrs, doc(cfg(feature = "redis")))]
+/// Redis caching support
+pub mod redis;
+
+#[cfg(feature = "remote")]
+#[cfg_attr(docsrs, doc(cfg(feature = "remote")))]
+/// Remote storage service support
+pub mod remote;
+
 #[cfg(feature = "sqlite")]
 #[cfg_attr(docsrs, doc(cfg(feature = "sqlite")))]
 /// Sqlite database support
 pub mod sqlite;
 
 /// Enum to support custom ordering in record queries
-#[derive(Debug, Default)]
+///
+/// Records with equal sort values are ordered by ID, in the same direction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub enum OrderBy {
     /// Order by ID field
     #[default]
     Id,
+    /// Order by the value of a plaintext tag. Records without the tag are
+    /// ordered before those with a value, when sorting in ascending order
+    Tag(String),
+    /// Order by the creation time of the record. Records without a creation
+    /// time are ordered first, when sorting in ascending order
+    CreatedAt,
+    /// Order by the last modification time of the record. Records without a
+    /// modification time are ordered first, when sorting in ascending order
+    UpdatedAt,
 }
 
+impl FromStr for OrderBy {
+    type Err = Error;
+
+    /// Parse an ordering of the form `id`, `created_at`, `updated_at` or `tag:<name>`
+    fn from_str(order_by: &str) -> Result<Self, Error> {
+        if order_by.eq_ignore_ascii_case("id") {
+            Ok(Self::Id)
+        } else if order_by.eq_ignore_ascii_case("created_at") {
+            Ok(Self::CreatedAt)
+        } else if order_by.eq_ignore_ascii_case("updated_at") {
+            Ok(Self::UpdatedAt)
+        } else if let Some(name) = order_by.strip_prefix("tag:") {
+            // plaintext tag names may be given with the prefix used in tag filters
+            let name = name.strip_prefix('~').unwrap_or(name);
+            if name.is_empty() {
+                Err(err_msg!(Input, "Missing tag name for ordering"))
+            } else {
+                Ok(Self::Tag(name.to_string()))
+            }
+        } else {
+            Err(err_msg!(Unsupported, "Unsupported ordering: {}", order_by))
+        }
+    }
+}
+
+impl Display for OrderBy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id => f.write_str("id"),
+            Self::Tag(name) => write!(f, "tag:{}", name),
+            Self::CreatedAt => f.write_str("created_at"),
+            Self::UpdatedAt => f.write_str("updated_at"),
+        }
+    }
+}
+
+/// A callback receiving the number of records processed and the total
+/// number of records to be processed by a rekey operation
+pub type RekeyProgress = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
 /// Represents a generic backend implementation
 pub trait Backend: Debug + Send + Sync {
     /// The type of session managed by this backend
@@ -50,10 +145,93 @@ pub trait Backend: Debug + Send + Sync {
     /// Get the details of all store profiles
     fn list_profiles(&self) -> BoxFuture<'_, Result<Vec<String>, Error>>;
 
+    /// Get the details of store profiles in order of profile name
+    ///
+    /// When `name_prefix` is provided, only profiles with names starting
+    /// with the prefix are returned.
+    fn list_profile_details(
+        &self,
+        _name_prefix: Option<String>,
+        _offset: Option<i64>,
+        _limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<ProfileDetails>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Profile details not supported by this backend"
+        ))))
+    }
+
+    /// Enable or disable soft deletion of records
+    ///
+    /// When enabled, removed records are retained as deleted until purged.
+    fn set_soft_delete(&self, _enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Soft deletion not supported by this backend"
+        ))))
+    }
+
+    /// Enable or disable the audit log of mutating operations
+    fn set_audit_log(&self, _enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Audit log not supported by this backend"
+        ))))
+    }
+
+    /// Subscribe to committed changes to the records of a profile
+    fn subscribe(
+        &self,
+        _profile: Option<String>,
+        _kind: Option<EntryKind>,
+        _category: Option<String>,
+    ) -> BoxFuture<'_, Result<Subscription, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Change subscriptions not supported by this backend"
+        ))))
+    }
+
+    /// Fetch a store-level metadata value
+    fn get_meta(&self, _name: String) -> BoxFuture<'_, Result<Option<SecretBytes>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Store metadata not supported by this backend"
+        ))))
+    }
+
+    /// Set or remove a store-level metadata value
+    ///
+    /// Store metadata is encrypted using the store key and is not associated
+    /// with any profile.
+    fn set_meta(
+        &self,
+        _name: String,
+        _value: Option<SecretBytes>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Store metadata not supported by this backend"
+        ))))
+    }
+
     /// Remove an existing profile
     fn remove_profile(&self, name: String) -> BoxFuture<'_, Result<bool, Error>>;
 
+    /// Rename an existing profile, returning `false` if it was not found
+    ///
+    /// The active profile of the backend may not be renamed.
+    fn rename_profile(&self, _from: String, _to: String) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Profile renaming not supported by this backend"
+        ))))
+    }
+
     /// Create a [`Scan`] against the store
+    ///
+    /// Records which have been soft deleted are only returned when
+    /// `include_deleted` is set.
     #[allow(clippy::too_many_arguments)]
     fn scan(
         &self,
@@ -65,8 +243,47 @@ pub trait Backend: Debug + Send + Sync {
         limit: Option<i64>,
         order_by: Option<OrderBy>,
         descending: bool,
+        include_deleted: bool,
     ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>>;
 
+    /// Create a [`Scan`] against the store, returning the records which follow
+    /// the given position in the scan ordering
+    #[allow(clippy::too_many_arguments)]
+    fn scan_after(
+        &self,
+        _profile: Option<String>,
+        _kind: Option<EntryKind>,
+        _category: Option<String>,
+        _tag_filter: Option<TagFilter>,
+        _after: ScanPosition,
+        _limit: Option<i64>,
+        _order_by: Option<OrderBy>,
+        _descending: bool,
+        _include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Scan positions not supported by this backend"
+        ))))
+    }
+
+    /// Encrypt data issued by the store as an opaque token, using a key
+    /// derived from the store key
+    fn seal_token(&self, _data: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Tokens not supported by this backend"
+        ))))
+    }
+
+    /// Decrypt a token produced by [`Backend::seal_token`]
+    fn open_token(&self, _token: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Tokens not supported by this backend"
+        ))))
+    }
+
     /// Create a new session against the store
     fn session(&self, profile: Option<String>, transaction: bool) -> Result<Self::Session, Error>;
 
@@ -77,6 +294,67 @@ pub trait Backend: Debug + Send + Sync {
         key: PassKey<'_>,
     ) -> BoxFuture<'_, Result<(), Error>>;
 
+    /// Replace the wrapping key of the store, reporting progress
+    ///
+    /// Backends supporting incremental rekeying re-encrypt the store in batches,
+    /// invoking the `progress` callback after each batch. An interrupted rekey
+    /// may be resumed by repeating it with the same key method and pass key.
+    fn rekey_with_progress(
+        &mut self,
+        method: StoreKeyMethod,
+        key: PassKey<'_>,
+        _progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.rekey(method, key)
+    }
+
+    /// Replace the key of a single profile, re-encrypting the records of the profile
+    ///
+    /// Records are processed in batches, invoking the `progress` callback after
+    /// each batch. The search index is rebuilt from the retained search terms.
+    fn rekey_profile(
+        &self,
+        _profile: String,
+        _progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Profile rekeying not supported by this backend"
+        ))))
+    }
+
+    /// Add a named key slot, allowing the store to be opened using another pass key
+    ///
+    /// The master key of the store is encrypted using the key resolved for the
+    /// slot. Rekeying the store leaves its key slots unchanged.
+    fn add_key_slot(
+        &self,
+        _name: String,
+        _method: StoreKeyMethod,
+        _pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Key slots not supported by this backend"
+        ))))
+    }
+
+    /// Remove a key slot, returning `false` if it was not found
+    fn remove_key_slot(&self, _name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Key slots not supported by this backend"
+        ))))
+    }
+
+    /// Get the names of the key slots of the store
+    fn list_key_slots(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Key slots not supported by this backend"
+        ))))
+    }
+
     /// Close the store instance
     fn close(&self) -> BoxFuture<'_, Result<(), Error>>;
 }
@@ -109,14 +387,80 @@ pub trait ManageBackend<'a> {
 
 /// Query from a generic backend implementation
 pub trait BackendSession: Debug + Send {
-    /// Count the number of matching records in the store
+    /// Count the number of matching records in the store, including those
+    /// which have been soft deleted when `include_deleted` is set
     fn count<'q>(
         &'q mut self,
         kind: Option<EntryKind>,
         category: Option<&'q str>,
         tag_filter: Option<TagFilter>,
+        include_deleted: bool,
     ) -> BoxFuture<'q, Result<i64, Error>>;
 
+    /// Count the number of matching records in the store for each category
+    fn count_by_category<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<BTreeMap<String, i64>, Error>> {
+        Box::pin(async move {
+            let rows = self
+                .fetch_all(kind, None, tag_filter, None, None, false, false)
+                .await?;
+            let mut counts = BTreeMap::new();
+            for row in rows {
+                *counts.entry(row.category).or_insert(0) += 1;
+            }
+            Ok(counts)
+        })
+    }
+
+    /// Count the matching records in a category for each distinct value of a tag
+    ///
+    /// Plaintext tag names are indicated by a `~` prefix, as in tag filters.
+    fn list_tag_values<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        tag_name: &'q str,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<BTreeMap<String, i64>, Error>> {
+        Box::pin(async move {
+            let rows = self
+                .fetch_all(
+                    Some(kind),
+                    Some(category),
+                    tag_filter,
+                    None,
+                    None,
+                    false,
+                    false,
+                )
+                .await?;
+            let (name, plaintext) = match tag_name.strip_prefix('~') {
+                Some(name) => (name, true),
+                None => (tag_name, false),
+            };
+            let mut counts = BTreeMap::new();
+            for row in rows {
+                let mut values = row
+                    .tags
+                    .iter()
+                    .filter(|tag| {
+                        tag.name() == name && matches!(tag, EntryTag::Plaintext(..)) == plaintext
+                    })
+                    .map(EntryTag::value)
+                    .collect::<Vec<_>>();
+                values.sort_unstable();
+                values.dedup();
+                for value in values {
+                    *counts.entry(value.to_string()).or_insert(0) += 1;
+                }
+            }
+            Ok(counts)
+        })
+    }
+
     /// Fetch a single record from the store by category and name
     fn fetch<'q>(
         &'q mut self,
@@ -126,6 +470,26 @@ pub trait BackendSession: Debug + Send {
         for_update: bool,
     ) -> BoxFuture<'q, Result<Option<Entry>, Error>>;
 
+    /// Fetch a set of records from the store by category and name
+    ///
+    /// The result contains an entry for each requested name, in the same
+    /// order, which is `None` when the record was not found.
+    fn fetch_many<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        names: &'q [String],
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Vec<Option<Entry>>, Error>> {
+        Box::pin(async move {
+            let mut results = Vec::with_capacity(names.len());
+            for name in names {
+                results.push(self.fetch(kind, category, name, for_update).await?);
+            }
+            Ok(results)
+        })
+    }
+
     /// Fetch all matching records from the store
     #[allow(clippy::too_many_arguments)]
     fn fetch_all<'q>(
@@ -143,7 +507,7 @@ pub trait BackendSession: Debug + Send {
     fn import_scan<'q>(
         &'q mut self,
         mut scan: Scan<'q, Entry>,
-    ) -> BoxFuture<'_, Result<(), Error>> {
+    ) -> BoxFuture<'q, Result<(), Error>> {
         Box::pin(async move {
             while let Some(rows) = scan.fetch_next().await? {
                 for entry in rows {
@@ -171,6 +535,144 @@ pub trait BackendSession: Debug + Send {
         tag_filter: Option<TagFilter>,
     ) -> BoxFuture<'q, Result<i64, Error>>;
 
+    /// Permanently remove all records which have passed their expiry time
+    fn remove_expired<'q>(
+        &'q mut self,
+        _kind: Option<EntryKind>,
+        _category: Option<&'q str>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Removing expired records not supported by this backend"
+        ))))
+    }
+
+    /// Restore a record which has been soft deleted
+    fn restore<'q>(
+        &'q mut self,
+        _kind: EntryKind,
+        _category: &'q str,
+        _name: &'q str,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Soft deletion not supported by this backend"
+        ))))
+    }
+
+    /// Permanently remove soft deleted records, returning the number removed
+    ///
+    /// When `older_than_ms` is provided, only records deleted at least that
+    /// many milliseconds ago are removed.
+    fn purge<'q>(
+        &'q mut self,
+        _kind: Option<EntryKind>,
+        _older_than_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Soft deletion not supported by this backend"
+        ))))
+    }
+
+    /// Enable or disable the preservation of prior record versions for a category
+    fn set_history<'q>(
+        &'q mut self,
+        _kind: EntryKind,
+        _category: &'q str,
+        _enabled: bool,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Record history not supported by this backend"
+        ))))
+    }
+
+    /// Fetch the prior versions of a record, most recent first
+    fn fetch_history<'q>(
+        &'q mut self,
+        _kind: EntryKind,
+        _category: &'q str,
+        _name: &'q str,
+        _limit: Option<i64>,
+    ) -> BoxFuture<'q, Result<Vec<EntryVersion>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Record history not supported by this backend"
+        ))))
+    }
+
+    /// Fetch a single prior version of a record
+    fn fetch_version<'q>(
+        &'q mut self,
+        _kind: EntryKind,
+        _category: &'q str,
+        _name: &'q str,
+        _version: i64,
+    ) -> BoxFuture<'q, Result<Option<EntryVersion>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Record history not supported by this backend"
+        ))))
+    }
+
+    /// Remove preserved record versions, returning the number removed
+    ///
+    /// When `older_than_ms` is provided, only versions replaced at least that
+    /// many milliseconds ago are removed.
+    fn prune_history<'q>(
+        &'q mut self,
+        _kind: Option<EntryKind>,
+        _category: Option<&'q str>,
+        _older_than_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Record history not supported by this backend"
+        ))))
+    }
+
+    /// Fetch the audit log of the profile in order of sequence number
+    ///
+    /// When `after_id` is provided, only records following that sequence
+    /// number are returned.
+    fn fetch_audit_log(
+        &mut self,
+        _after_id: Option<i64>,
+        _limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<AuditRecord>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Audit log not supported by this backend"
+        ))))
+    }
+
+    /// Fetch a metadata value of the profile
+    fn get_profile_meta<'q>(
+        &'q mut self,
+        _name: &'q str,
+    ) -> BoxFuture<'q, Result<Option<SecretBytes>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Profile metadata not supported by this backend"
+        ))))
+    }
+
+    /// Set or remove a metadata value of the profile
+    ///
+    /// Profile metadata is encrypted using the profile key, and is not
+    /// included in record counts, scans or exports.
+    fn set_profile_meta<'q>(
+        &'q mut self,
+        _name: &'q str,
+        _value: Option<&'q [u8]>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Profile metadata not supported by this backend"
+        ))))
+    }
+
     /// Insert or replace a record in the store
     #[allow(clippy::too_many_arguments)]
     fn update<'q>(
@@ -184,6 +686,85 @@ pub trait BackendSession: Debug + Send {
         expiry_ms: Option<i64>,
     ) -> BoxFuture<'q, Result<(), Error>>;
 
+    /// Replace or remove a record only if its current version matches
+    ///
+    /// A `Conflict` error is returned if the record has been modified since
+    /// the expected version was read.
+    #[allow(clippy::too_many_arguments)]
+    fn update_versioned<'q>(
+        &'q mut self,
+        _kind: EntryKind,
+        _operation: EntryOperation,
+        _category: &'q str,
+        _name: &'q str,
+        _value: Option<&'q [u8]>,
+        _tags: Option<&'q [EntryTag]>,
+        _expiry_ms: Option<i64>,
+        _version: i64,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Versioned updates not supported by this backend"
+        ))))
+    }
+
+    /// Perform a batch of record updates, returning the outcome of each
+    ///
+    /// A duplicate or missing record is reported in the result for that
+    /// update and does not prevent the remaining updates from being applied.
+    fn update_many<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        updates: Vec<EntryUpdate>,
+    ) -> BoxFuture<'q, Result<Vec<Result<(), Error>>, Error>> {
+        Box::pin(async move {
+            let mut results = Vec::with_capacity(updates.len());
+            for upd in updates {
+                let result = self
+                    .update(
+                        kind,
+                        upd.operation,
+                        &upd.category,
+                        &upd.name,
+                        upd.value.as_deref(),
+                        upd.tags.as_deref(),
+                        upd.expiry_ms,
+                    )
+                    .await;
+                results.push(batch_result(result)?);
+            }
+            Ok(results)
+        })
+    }
+
+    /// Replace the search index terms for a record
+    fn update_search_terms<'q>(
+        &'q mut self,
+        _kind: EntryKind,
+        _category: &'q str,
+        _name: &'q str,
+        _terms: &'q [String],
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Search index not supported by this backend"
+        ))))
+    }
+
+    /// Fetch the records in a category indexed with all of the given search terms
+    fn search_values<'q>(
+        &'q mut self,
+        _kind: EntryKind,
+        _category: &'q str,
+        _terms: &'q [String],
+        _limit: Option<i64>,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Search index not supported by this backend"
+        ))))
+    }
+
     /// Test the connection to the store
     fn ping(&mut self) -> BoxFuture<'_, Result<(), Error>>;
 
@@ -191,12 +772,145 @@ pub trait BackendSession: Debug + Send {
     fn close(&mut self, commit: bool) -> BoxFuture<'_, Result<(), Error>>;
 }
 
-/// Insert all records from a given profile
+/// Create a [`Scan`] against the store which tracks a [`ScanCursor`]
+///
+/// Records are ordered by ID unless another ordering is given, so that the
+/// scan may be resumed from the position of the last row returned.
+#[allow(clippy::too_many_arguments)]
+pub async fn scan_with_cursor<B: Backend>(
+    backend: &B,
+    profile: Option<String>,
+    kind: Option<EntryKind>,
+    category: Option<String>,
+    tag_filter: Option<TagFilter>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+    order_by: Option<OrderBy>,
+    descending: bool,
+    include_deleted: bool,
+) -> Result<Scan<'static, Entry>, Error> {
+    let order_by = order_by.unwrap_or_default();
+    let cursor = ScanCursor::new(
+        kind,
+        category.clone(),
+        tag_filter.as_ref(),
+        Some(&order_by),
+        descending,
+        include_deleted,
+        offset,
+        limit,
+    )?;
+    let scan = backend
+        .scan(
+            profile,
+            kind,
+            category,
+            tag_filter,
+            Some(cursor.offset),
+            limit,
+            Some(order_by),
+            descending,
+            include_deleted,
+        )
+        .await?;
+    Ok(scan.with_cursor(cursor))
+}
+
+/// Continue a scan from a [`ScanCursor`], returning at most `limit` further rows
+///
+/// The profile is not recorded in the cursor, and must be provided again.
+pub async fn resume_scan<B: Backend>(
+    backend: &B,
+    profile: Option<String>,
+    cursor: &ScanCursor,
+    limit: Option<i64>,
+) -> Result<Scan<'static, Entry>, Error> {
+    let kind = cursor
+        .kind
+        .map(|kind| EntryKind::try_from(kind as usize))
+        .transpose()?;
+    let tag_filter = cursor
+        .tag_filter
+        .as_deref()
+        .map(TagFilter::from_str)
+        .transpose()?;
+    let order_by = cursor
+        .order_by
+        .as_deref()
+        .map(OrderBy::from_str)
+        .transpose()?;
+    let after = match cursor.after.clone() {
+        Some(after) => after,
+        None => {
+            return scan_with_cursor(
+                backend,
+                profile,
+                kind,
+                cursor.category.clone(),
+                tag_filter,
+                Some(cursor.offset),
+                limit,
+                order_by,
+                cursor.descending,
+                cursor.include_deleted,
+            )
+            .await
+        }
+    };
+    let next = ScanCursor::new(
+        kind,
+        cursor.category.clone(),
+        tag_filter.as_ref(),
+        order_by.as_ref(),
+        cursor.descending,
+        cursor.include_deleted,
+        None,
+        limit,
+    )?
+    .with_position(after.clone());
+    let scan = backend
+        .scan_after(
+            profile,
+            kind,
+            cursor.category.clone(),
+            tag_filter,
+            after,
+            limit,
+            order_by,
+            cursor.descending,
+            cursor.include_deleted,
+        )
+        .await?;
+    Ok(scan.with_cursor(next))
+}
+
+/// Copy all records of a profile into a new profile
+///
+/// The target profile is created with its own profile key, and is removed
+/// again if the copy fails. An error with kind `Duplicate` is returned when the
+/// target profile already exists. Only the current records of the profile are
+/// copied: soft-deleted records, preserved record history, indexed search terms
+/// and profile metadata are not.
 pub async fn copy_profile<A: Backend, B: Backend>(
     from_backend: &A,
     to_backend: &B,
     from_profile: &str,
     to_profile: &str,
+) -> Result<(), Error> {
+    to_backend.create_profile(Some(to_profile.into())).await?;
+    if let Err(err) = import_profile(from_backend, to_backend, from_profile, to_profile).await {
+        to_backend.remove_profile(to_profile.into()).await?;
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Insert all records from a given profile into an existing, empty profile
+pub async fn import_profile<A: Backend, B: Backend>(
+    from_backend: &A,
+    to_backend: &B,
+    from_profile: &str,
+    to_profile: &str,
 ) -> Result<(), Error> {
     let scan = from_backend
         .scan(
@@ -208,15 +922,11 @@ pub async fn copy_profile<A: Backend, B: Backend>(
             None,
             None,
             false,
+            false,
         )
         .await?;
-    if let Err(e) = to_backend.create_profile(Some(to_profile.into())).await {
-        if e.kind() != ErrorKind::Duplicate {
-            return Err(e);
-        }
-    }
     let mut txn = to_backend.session(Some(to_profile.into()), true)?;
-    let count = txn.count(None, None, None).await?;
+    let count = txn.count(None, None, None, false).await?;
     if count > 0 {
         return Err(err_msg!(Input, "Profile targeted for import is not empty"));
     }
@@ -236,10 +946,28 @@ pub async fn copy_store<'m, B: Backend, M: ManageBackend<'m>>(
     let default_profile = source.get_default_profile().await?;
     let profile_ids = source.list_profiles().await?;
     let target = target
-        .provision_backend(key_method, pass_key, Some(default_profile), recreate)
+        .provision_backend(
+            key_method,
+            pass_key,
+            Some(default_profile.clone()),
+            recreate,
+        )
         .await?;
     for profile in profile_ids {
-        copy_profile(source, &target, &profile, &profile).await?;
+        if profile == default_profile {
+            // the default profile is created when the target is provisioned
+            import_profile(source, &target, &profile, &profile).await?;
+        } else {
+            copy_profile(source, &target, &profile, &profile).await?;
+        }
     }
     Ok(target)
 }
+
+/// Separate the outcome of a single batch update from errors aborting the batch
+pub(crate) fn batch_result(result: Result<(), Error>) -> Result<Result<(), Error>, Error> {
+    match result {
+        Err(e) if !matches!(e.kind(), ErrorKind::Duplicate | ErrorKind::NotFound) => Err(e),
+        result => Ok(result),
+    }
+}
@@ -0,0 +1,1308 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::DerefMut;
+use std::sync::Arc;
+
+use async_stream::try_stream;
+
+use bb8::{Pool, PooledConnection};
+use bb8_tiberius::ConnectionManager;
+
+use futures_lite::{
+    pin,
+    stream::{Stream, StreamExt},
+};
+
+use tiberius::{error::Error as TiberiusError, FromSql, Query, Row};
+
+use super::{
+    db_utils::{
+        decode_tags, decrypt_scan_batch, encode_order_by, encode_profile_key, expiry_timestamp,
+        position_filter, prepare_tags, random_profile_name, EncOrderBy, EncScanEntry, PAGE_SIZE,
+    },
+    Backend, BackendSession,
+};
+use crate::{
+    backend::OrderBy,
+    entry::{
+        EncEntryTag, Entry, EntryKind, EntryOperation, EntryTag, Scan, ScanPosition, SortValue,
+        TagFilter,
+    },
+    error::Error,
+    future::{spawn_ok, unblock, BoxFuture},
+    protect::{EntryEncryptor, KeyCache, PassKey, ProfileId, ProfileKey, StoreKeyMethod},
+    wql::{
+        mssql::TagMssqlEncoder,
+        tags::{tag_query, TagQueryEncoder},
+    },
+};
+
+mod provision;
+pub use self::provision::MssqlStoreOptions;
+
+pub(crate) type MssqlPool = Pool<ConnectionManager>;
+type MssqlConnection = PooledConnection<'static, ConnectionManager>;
+
+const CONFIG_FETCH_QUERY: &str = "SELECT value FROM config WHERE name = @P1";
+const CONFIG_UPDATE_QUERY: &str = "MERGE config WITH (HOLDLOCK) AS c
+    USING (SELECT @P1 AS name, @P2 AS value) AS s ON c.name = s.name
+    WHEN MATCHED THEN UPDATE SET value = s.value
+    WHEN NOT MATCHED THEN INSERT (name, value) VALUES (s.name, s.value);";
+const COUNT_QUERY: &str = "SELECT COUNT_BIG(*) FROM items i
+    WHERE profile_id = @P1
+    AND (kind = @P2 OR @P2 IS NULL)
+    AND (category = @P3 OR @P3 IS NULL)
+    AND (expiry IS NULL OR expiry > SYSUTCDATETIME())";
+const DELETE_QUERY: &str = "DELETE FROM items
+    WHERE profile_id = @P1 AND kind = @P2 AND category = @P3 AND name = @P4";
+const FETCH_QUERY: &str = "SELECT id, value,
+    (SELECT STRING_AGG(CONCAT(it.plaintext, ':', CONVERT(VARCHAR(MAX), it.name, 2),
+        ':', CONVERT(VARCHAR(MAX), it.value, 2)), ',')
+        FROM items_tags it WHERE it.item_id = i.id) tags,
+    DATEDIFF_BIG(MILLISECOND, '1970-01-01', expiry) expires_at
+    FROM items i
+    WHERE profile_id = @P1 AND kind = @P2 AND category = @P3 AND name = @P4
+    AND (expiry IS NULL OR expiry > SYSUTCDATETIME())";
+const FETCH_QUERY_UPDATE: &str = "SELECT id, value,
+    (SELECT STRING_AGG(CONCAT(it.plaintext, ':', CONVERT(VARCHAR(MAX), it.name, 2),
+        ':', CONVERT(VARCHAR(MAX), it.value, 2)), ',')
+        FROM items_tags it WHERE it.item_id = i.id) tags,
+    DATEDIFF_BIG(MILLISECOND, '1970-01-01', expiry) expires_at
+    FROM items i WITH (UPDLOCK, ROWLOCK)
+    WHERE profile_id = @P1 AND kind = @P2 AND category = @P3 AND name = @P4
+    AND (expiry IS NULL OR expiry > SYSUTCDATETIME())";
+const INSERT_QUERY: &str = "INSERT INTO items (profile_id, kind, category, name, value, expiry)
+    OUTPUT INSERTED.id VALUES (@P1, @P2, @P3, @P4, @P5, @P6)";
+const UPDATE_QUERY: &str = "UPDATE items SET value = @P5, expiry = @P6
+    OUTPUT INSERTED.id
+    WHERE profile_id = @P1 AND kind = @P2 AND category = @P3 AND name = @P4";
+const UPSERT_QUERY: &str = "MERGE items WITH (HOLDLOCK) AS i
+    USING (SELECT @P1 AS profile_id, @P2 AS kind, @P3 AS category, @P4 AS name) AS s
+    ON i.profile_id = s.profile_id AND i.kind = s.kind
+        AND i.category = s.category AND i.name = s.name
+    WHEN MATCHED THEN UPDATE SET value = @P5, expiry = @P6
+    WHEN NOT MATCHED THEN INSERT (profile_id, kind, category, name, value, expiry)
+        VALUES (@P1, @P2, @P3, @P4, @P5, @P6)
+    OUTPUT INSERTED.id;";
+const SCAN_QUERY: &str = "SELECT id, kind, category, name, value,
+    (SELECT STRING_AGG(CONCAT(it.plaintext, ':', CONVERT(VARCHAR(MAX), it.name, 2),
+        ':', CONVERT(VARCHAR(MAX), it.value, 2)), ',')
+        FROM items_tags it WHERE it.item_id = i.id) tags
+    FROM items i WHERE profile_id = @P1
+    AND (kind = @P2 OR @P2 IS NULL)
+    AND (category = @P3 OR @P3 IS NULL)
+    AND (expiry IS NULL OR expiry > SYSUTCDATETIME())";
+const SCAN_QUERY_UPDATE: &str = "SELECT id, kind, category, name, value,
+    (SELECT STRING_AGG(CONCAT(it.plaintext, ':', CONVERT(VARCHAR(MAX), it.name, 2),
+        ':', CONVERT(VARCHAR(MAX), it.value, 2)), ',')
+        FROM items_tags it WHERE it.item_id = i.id) tags,
+    DATEDIFF_BIG(MILLISECOND, '1970-01-01', expiry) expires_at
+    FROM items i WITH (UPDLOCK, ROWLOCK) WHERE profile_id = @P1
+    AND (kind = @P2 OR @P2 IS NULL)
+    AND (category = @P3 OR @P3 IS NULL)
+    AND (expiry IS NULL OR expiry > SYSUTCDATETIME())";
+const DELETE_ALL_QUERY: &str = "DELETE i FROM items i
+    WHERE profile_id = @P1
+    AND (kind = @P2 OR @P2 IS NULL)
+    AND (category = @P3 OR @P3 IS NULL)";
+const DELETE_EXPIRED_QUERY: &str = "DELETE FROM items
+    WHERE profile_id = @P1
+    AND (kind = @P2 OR @P2 IS NULL)
+    AND (category = @P3 OR @P3 IS NULL)
+    AND expiry IS NOT NULL AND expiry <= SYSUTCDATETIME()";
+const TAG_INSERT_QUERY: &str = "INSERT INTO items_tags
+    (item_id, name, value, plaintext) VALUES (@P1, @P2, @P3, @P4)";
+const TAG_DELETE_QUERY: &str = "DELETE FROM items_tags
+    WHERE item_id = @P1";
+
+/// A SQL Server database store
+pub struct MssqlBackend {
+    conn_pool: MssqlPool,
+    active_profile: String,
+    key_cache: Arc<KeyCache>,
+    host: String,
+    name: String,
+}
+
+impl MssqlBackend {
+    pub(crate) fn new(
+        conn_pool: MssqlPool,
+        active_profile: String,
+        key_cache: KeyCache,
+        host: String,
+        name: String,
+    ) -> Self {
+        Self {
+            conn_pool,
+            active_profile,
+            key_cache: Arc::new(key_cache),
+            host,
+            name,
+        }
+    }
+
+    async fn acquire(&self) -> Result<MssqlConnection, Error> {
+        self.conn_pool
+            .get_owned()
+            .await
+            .map_err(err_map!(Backend, "Error acquiring database connection"))
+    }
+}
+
+impl Backend for MssqlBackend {
+    type Session = MssqlSession;
+
+    fn create_profile(&self, name: Option<String>) -> BoxFuture<'_, Result<String, Error>> {
+        let name = name.unwrap_or_else(random_profile_name);
+        Box::pin(async move {
+            let store_key = self.key_cache.store_key.clone();
+            let (profile_key, enc_key) = unblock(move || {
+                let profile_key = ProfileKey::new()?;
+                let enc_key = encode_profile_key(&profile_key, &store_key)?;
+                Result::<_, Error>::Ok((profile_key, enc_key))
+            })
+            .await?;
+            let mut conn = self.acquire().await?;
+            let pid = insert_profile(&mut conn, &name, enc_key).await?;
+            self.key_cache
+                .add_profile(name.clone(), pid, Arc::new(profile_key))
+                .await;
+            Ok(name)
+        })
+    }
+
+    fn get_active_profile(&self) -> String {
+        self.active_profile.clone()
+    }
+
+    fn get_default_profile(&self) -> BoxFuture<'_, Result<String, Error>> {
+        Box::pin(async move {
+            let mut conn = self.acquire().await?;
+            let profile = get_config(&mut conn, "default_profile")
+                .await
+                .map_err(err_map!(Backend, "Error fetching default profile name"))?;
+            Ok(profile.unwrap_or_default())
+        })
+    }
+
+    fn set_default_profile(&self, profile: String) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            let mut conn = self.acquire().await?;
+            set_config(&mut conn, "default_profile", &profile)
+                .await
+                .map_err(err_map!(Backend, "Error setting default profile name"))?;
+            Ok(())
+        })
+    }
+
+    fn list_profiles(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        Box::pin(async move {
+            let mut conn = self.acquire().await?;
+            let rows = conn
+                .simple_query("SELECT name FROM profiles")
+                .await?
+                .into_first_result()
+                .await
+                .map_err(err_map!(Backend, "Error fetching profile list"))?;
+            let names = rows
+                .iter()
+                .flat_map(|r| r.try_get::<&str, _>(0).ok().flatten().map(str::to_owned))
+                .collect();
+            Ok(names)
+        })
+    }
+
+    fn remove_profile(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut conn = self.acquire().await?;
+            let mut query = Query::new("DELETE FROM profiles WHERE name = @P1");
+            query.bind(name.as_str());
+            let ret = query
+                .execute(&mut *conn)
+                .await
+                .map_err(err_map!(Backend, "Error removing profile"))?
+                .total()
+                != 0;
+            Ok(ret)
+        })
+    }
+
+    fn rekey(
+        &mut self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        let pass_key = pass_key.into_owned();
+        Box::pin(async move {
+            let (store_key, store_key_ref) = unblock(move || method.resolve(pass_key)).await?;
+            let store_key = Arc::new(store_key);
+            let mut conn = self.acquire().await?;
+            execute_simple(&mut conn, "BEGIN TRANSACTION").await?;
+            let res = async {
+                let rows = conn
+                    .simple_query("SELECT id, profile_key FROM profiles WITH (UPDLOCK)")
+                    .await?
+                    .into_first_result()
+                    .await?;
+                let mut upd_keys = BTreeMap::<ProfileId, Vec<u8>>::new();
+                for row in rows {
+                    let pid = get_value(&row, 0)?;
+                    let enc_key = get_value::<&[u8]>(&row, 1)?.to_vec();
+                    let profile_key = self.key_cache.load_key(enc_key).await?;
+                    let upd_key = unblock({
+                        let store_key = store_key.clone();
+                        move || encode_profile_key(&profile_key, &store_key)
+                    })
+                    .await?;
+                    upd_keys.insert(pid, upd_key);
+                }
+                for (pid, key) in upd_keys {
+                    let mut query =
+                        Query::new("UPDATE profiles SET profile_key = @P1 WHERE id = @P2");
+                    query.bind(key);
+                    query.bind(pid);
+                    if query.execute(&mut *conn).await?.total() != 1 {
+                        return Err(err_msg!(Backend, "Error updating profile key"));
+                    }
+                }
+                let mut query = Query::new("UPDATE config SET value = @P1 WHERE name = 'key'");
+                query.bind(store_key_ref.into_uri());
+                if query.execute(&mut *conn).await?.total() != 1 {
+                    return Err(err_msg!(Backend, "Error updating store key"));
+                }
+                Result::<_, Error>::Ok(())
+            }
+            .await;
+            end_transaction(&mut conn, res.is_ok()).await?;
+            res?;
+            self.key_cache = Arc::new(KeyCache::new(store_key));
+            Ok(())
+        })
+    }
+
+    fn scan(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        _include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(async move {
+            let mut session = Box::new(self.session(profile, false)?);
+            let (profile_id, key) = session.acquire_key().await?;
+            let scan = perform_scan(
+                session,
+                profile_id,
+                key.clone(),
+                kind,
+                category.clone(),
+                tag_filter,
+                None,
+                offset,
+                limit,
+                order_by,
+                descending,
+                false,
+            );
+            let stream = scan.then(move |enc_rows| {
+                let category = category.clone();
+                let key = key.clone();
+                unblock(move || decrypt_scan_batch(category, enc_rows?, &key))
+            });
+            Ok(Scan::new(stream, PAGE_SIZE))
+        })
+    }
+
+    fn scan_after(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        after: ScanPosition,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        _include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(async move {
+            let mut session = Box::new(self.session(profile, false)?);
+            let (profile_id, key) = session.acquire_key().await?;
+            let scan = perform_scan(
+                session,
+                profile_id,
+                key.clone(),
+                kind,
+                category.clone(),
+                tag_filter,
+                Some(after),
+                None,
+                limit,
+                order_by,
+                descending,
+                false,
+            );
+            let stream = scan.then(move |enc_rows| {
+                let category = category.clone();
+                let key = key.clone();
+                unblock(move || decrypt_scan_batch(category, enc_rows?, &key))
+            });
+            Ok(Scan::new(stream, PAGE_SIZE))
+        })
+    }
+
+    fn seal_token(&self, data: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        let store_key = self.key_cache.store_key.clone();
+        Box::pin(async move { unblock(move || store_key.seal_token(&data)).await })
+    }
+
+    fn open_token(&self, token: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        let store_key = self.key_cache.store_key.clone();
+        Box::pin(async move { unblock(move || store_key.open_token(&token)).await })
+    }
+
+    fn session(&self, profile: Option<String>, transaction: bool) -> Result<Self::Session, Error> {
+        Ok(MssqlSession::new(
+            self.conn_pool.clone(),
+            self.key_cache.clone(),
+            profile.unwrap_or_else(|| self.active_profile.clone()),
+            transaction,
+        ))
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), Error>> {
+        // pooled connections are closed when the pool is dropped
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+impl Debug for MssqlBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MssqlStore")
+            .field("active_profile", &self.active_profile)
+            .field("host", &self.host)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+/// A session against a SQL Server store
+pub struct MssqlSession {
+    pool: MssqlPool,
+    cache: Arc<KeyCache>,
+    profile: String,
+    transaction: bool,
+    conn: Option<MssqlConnection>,
+    profile_key: Option<(ProfileId, Arc<ProfileKey>)>,
+    // whether a database transaction is currently open on the connection
+    txn_open: bool,
+}
+
+impl MssqlSession {
+    pub(crate) fn new(
+        pool: MssqlPool,
+        cache: Arc<KeyCache>,
+        profile: String,
+        transaction: bool,
+    ) -> Self {
+        Self {
+            pool,
+            cache,
+            profile,
+            transaction,
+            conn: None,
+            profile_key: None,
+            txn_open: false,
+        }
+    }
+
+    async fn acquire_key(&mut self) -> Result<(ProfileId, Arc<ProfileKey>), Error> {
+        if self.conn.is_none() {
+            let mut conn = self
+                .pool
+                .get_owned()
+                .await
+                .map_err(err_map!(Backend, "Error acquiring database connection"))?;
+            if self.transaction {
+                execute_simple(&mut conn, "BEGIN TRANSACTION").await?;
+                self.txn_open = true;
+            }
+            self.conn.replace(conn);
+        }
+        if let Some(key) = self.profile_key.as_ref() {
+            return Ok(key.clone());
+        }
+        let key = resolve_profile_key(
+            self.conn.as_mut().unwrap(),
+            self.cache.clone(),
+            self.profile.clone(),
+            self.transaction,
+        )
+        .await?;
+        self.profile_key.replace(key.clone());
+        Ok(key)
+    }
+
+    fn connection_mut(&mut self) -> Result<&mut MssqlConnection, Error> {
+        self.conn
+            .as_mut()
+            .ok_or_else(|| err_msg!(Unexpected, "Session is not active"))
+    }
+
+    // perform a set of statements atomically, starting a database transaction
+    // if one is not already active for the session
+    async fn begin_atomic(&mut self) -> Result<bool, Error> {
+        if self.txn_open {
+            Ok(false)
+        } else {
+            execute_simple(self.connection_mut()?, "BEGIN TRANSACTION").await?;
+            self.txn_open = true;
+            Ok(true)
+        }
+    }
+
+    async fn end_atomic(&mut self, started: bool, commit: bool) -> Result<(), Error> {
+        if started {
+            self.txn_open = false;
+            end_transaction(self.connection_mut()?, commit).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn close(&mut self, commit: bool) -> Result<(), Error> {
+        if let Some(mut conn) = self.conn.take() {
+            if self.txn_open {
+                self.txn_open = false;
+                end_transaction(&mut conn, commit).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Debug for MssqlSession {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MssqlSession")
+            .field("profile", &self.profile)
+            .field("transaction", &self.transaction)
+            .field("active", &self.conn.is_some())
+            .finish()
+    }
+}
+
+impl Drop for MssqlSession {
+    fn drop(&mut self) {
+        if self.txn_open {
+            self.txn_open = false;
+            if let Some(mut conn) = self.conn.take() {
+                debug!("Dropped transaction: roll-back");
+                spawn_ok(async move {
+                    if let Err(err) = end_transaction(&mut conn, false).await {
+                        warn!("Error rolling back transaction: {}", err);
+                    }
+                });
+            }
+        } else {
+            debug!("Dropped pool connection")
+        }
+    }
+}
+
+impl BackendSession for MssqlSession {
+    fn count<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        _include_deleted: bool,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        let enc_category = category.map(|c| ProfileKey::prepare_input(c.as_bytes()));
+
+        Box::pin(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let (enc_category, tag_filter) = unblock(move || {
+                Result::<_, Error>::Ok((
+                    enc_category
+                        .map(|c| key.encrypt_entry_category(c))
+                        .transpose()?,
+                    encode_tag_filter(tag_filter, &key, 4)?,
+                ))
+            })
+            .await?;
+            let (filter, filter_args) = match tag_filter {
+                Some((filter, args)) => (Some(filter), args),
+                None => (None, Vec::new()),
+            };
+            let mut query = Query::new(extend_query(
+                COUNT_QUERY,
+                filter.as_deref(),
+                None,
+                None,
+                None,
+                false,
+                0,
+            ));
+            query.bind(profile_id);
+            query.bind(kind.map(|k| k as i16));
+            query.bind(enc_category);
+            for arg in filter_args {
+                query.bind(arg);
+            }
+            let row = query
+                .query(&mut **self.connection_mut()?)
+                .await?
+                .into_row()
+                .await
+                .map_err(err_map!(Backend, "Error performing count query"))?
+                .ok_or_else(|| err_msg!(Backend, "Error performing count query"))?;
+            get_value(&row, 0)
+        })
+    }
+
+    fn fetch(
+        &mut self,
+        kind: EntryKind,
+        category: &str,
+        name: &str,
+        for_update: bool,
+    ) -> BoxFuture<'_, Result<Option<Entry>, Error>> {
+        let category = category.to_string();
+        let name = name.to_string();
+
+        Box::pin(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let (enc_category, enc_name) = unblock({
+                let key = key.clone();
+                let category = ProfileKey::prepare_input(category.as_bytes());
+                let name = ProfileKey::prepare_input(name.as_bytes());
+                move || {
+                    Result::<_, Error>::Ok((
+                        key.encrypt_entry_category(category)?,
+                        key.encrypt_entry_name(name)?,
+                    ))
+                }
+            })
+            .await?;
+            let mut query = Query::new(if for_update && self.transaction {
+                FETCH_QUERY_UPDATE
+            } else {
+                FETCH_QUERY
+            });
+            query.bind(profile_id);
+            query.bind(kind as i16);
+            query.bind(enc_category);
+            query.bind(enc_name);
+            let row = query
+                .query(&mut **self.connection_mut()?)
+                .await?
+                .into_row()
+                .await
+                .map_err(err_map!(Backend, "Error performing fetch query"))?;
+            if let Some(row) = row {
+                let value = get_value::<&[u8]>(&row, 1)?.to_vec();
+                let tags = row.try_get::<&str, _>(2)?.map(|t| t.as_bytes().to_vec());
+                let expires_at = row.try_get::<i64, _>(3)?;
+                let (category, name, value, tags) = unblock(move || {
+                    let value = key.decrypt_entry_value(category.as_ref(), name.as_ref(), value)?;
+                    let tags = if let Some(enc_tags) = tags {
+                        key.decrypt_entry_tags(
+                            decode_tags(enc_tags)
+                                .map_err(|_| err_msg!(Unexpected, "Error decoding tags"))?,
+                        )?
+                    } else {
+                        Vec::new()
+                    };
+                    Result::<_, Error>::Ok((category, name, value, tags))
+                })
+                .await?;
+                Ok(Some(
+                    Entry::new(kind, category, name, value, tags).with_expiry(expires_at),
+                ))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    fn fetch_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        let category = category.map(|c| c.to_string());
+        Box::pin(async move {
+            let for_update = for_update && self.transaction;
+            let (profile_id, key) = self.acquire_key().await?;
+            let scan = perform_scan(
+                &mut *self,
+                profile_id,
+                key.clone(),
+                kind,
+                category.clone(),
+                tag_filter,
+                None,
+                None,
+                limit,
+                order_by,
+                descending,
+                for_update,
+            );
+            pin!(scan);
+            let mut enc_rows = vec![];
+            while let Some(rows) = scan.try_next().await? {
+                enc_rows.extend(rows)
+            }
+            unblock(move || decrypt_scan_batch(category, enc_rows, &key)).await
+        })
+    }
+
+    fn remove_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        let enc_category = category.map(|c| ProfileKey::prepare_input(c.as_bytes()));
+
+        Box::pin(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let (enc_category, tag_filter) = unblock(move || {
+                Result::<_, Error>::Ok((
+                    enc_category
+                        .map(|c| key.encrypt_entry_category(c))
+                        .transpose()?,
+                    encode_tag_filter(tag_filter, &key, 4)?,
+                ))
+            })
+            .await?;
+            let (filter, filter_args) = match tag_filter {
+                Some((filter, args)) => (Some(filter), args),
+                None => (None, Vec::new()),
+            };
+            let mut query = Query::new(extend_query(
+                DELETE_ALL_QUERY,
+                filter.as_deref(),
+                None,
+                None,
+                None,
+                false,
+                0,
+            ));
+            query.bind(profile_id);
+            query.bind(kind.map(|k| k as i16));
+            query.bind(enc_category);
+            for arg in filter_args {
+                query.bind(arg);
+            }
+            let removed = query
+                .execute(&mut **self.connection_mut()?)
+                .await
+                .map_err(err_map!(Backend, "Error removing entries"))?
+                .total();
+            Ok(removed as i64)
+        })
+    }
+
+    fn remove_expired<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        let enc_category = category.map(|c| ProfileKey::prepare_input(c.as_bytes()));
+
+        Box::pin(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let enc_category = unblock(move || {
+                enc_category
+                    .map(|c| key.encrypt_entry_category(c))
+                    .transpose()
+            })
+            .await?;
+            let mut query = Query::new(DELETE_EXPIRED_QUERY);
+            query.bind(profile_id);
+            query.bind(kind.map(|k| k as i16));
+            query.bind(enc_category);
+            let removed = query
+                .execute(&mut **self.connection_mut()?)
+                .await
+                .map_err(err_map!(Backend, "Error removing expired entries"))?
+                .total();
+            Ok(removed as i64)
+        })
+    }
+
+    fn update<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        let category = ProfileKey::prepare_input(category.as_bytes());
+        let name = ProfileKey::prepare_input(name.as_bytes());
+
+        match operation {
+            op @ (EntryOperation::Insert
+            | EntryOperation::Replace
+            | EntryOperation::InsertOrReplace) => {
+                let value = ProfileKey::prepare_input(value.unwrap_or_default());
+                let tags = tags.map(prepare_tags);
+                Box::pin(async move {
+                    let (profile_id, key) = self.acquire_key().await?;
+                    let (enc_category, enc_name, enc_value, enc_tags) = unblock(move || {
+                        let enc_value =
+                            key.encrypt_entry_value(category.as_ref(), name.as_ref(), value)?;
+                        Result::<_, Error>::Ok((
+                            key.encrypt_entry_category(category)?,
+                            key.encrypt_entry_name(name)?,
+                            enc_value,
+                            tags.transpose()?
+                                .map(|t| key.encrypt_entry_tags(t))
+                                .transpose()?,
+                        ))
+                    })
+                    .await?;
+                    let started = self.begin_atomic().await?;
+                    let res = perform_insert(
+                        self.connection_mut()?,
+                        profile_id,
+                        kind,
+                        enc_category,
+                        enc_name,
+                        enc_value,
+                        enc_tags,
+                        expiry_ms,
+                        op,
+                    )
+                    .await;
+                    self.end_atomic(started, res.is_ok()).await?;
+                    res
+                })
+            }
+
+            EntryOperation::Remove => Box::pin(async move {
+                let (profile_id, key) = self.acquire_key().await?;
+                let (enc_category, enc_name) = unblock(move || {
+                    Result::<_, Error>::Ok((
+                        key.encrypt_entry_category(category)?,
+                        key.encrypt_entry_name(name)?,
+                    ))
+                })
+                .await?;
+                perform_remove(
+                    self.connection_mut()?,
+                    profile_id,
+                    kind,
+                    enc_category,
+                    enc_name,
+                )
+                .await
+            }),
+        }
+    }
+
+    fn ping(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            let (profile_id, _) = self.acquire_key().await?;
+            if self.transaction {
+                // the profile row is locked, perform a typical ping
+                execute_simple(self.connection_mut()?, "SELECT 1")
+                    .await
+                    .map_err(err_map!(Backend, "Error pinging session"))?;
+            } else {
+                let mut query = Query::new("SELECT COUNT_BIG(*) FROM profiles WHERE id = @P1");
+                query.bind(profile_id);
+                let row = query
+                    .query(&mut **self.connection_mut()?)
+                    .await?
+                    .into_row()
+                    .await
+                    .map_err(err_map!(Backend, "Error pinging session"))?;
+                let count: i64 = row.map(|r| get_value(&r, 0)).transpose()?.unwrap_or(0);
+                if count == 0 {
+                    return Err(err_msg!(NotFound, "Session profile has been removed"));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn close(&mut self, commit: bool) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(self.close(commit))
+    }
+}
+
+// SQL Server error numbers raised for a unique constraint or index violation
+fn is_duplicate_error(err: &TiberiusError) -> bool {
+    matches!(err, TiberiusError::Server(e) if e.code() == 2601 || e.code() == 2627)
+}
+
+fn get_value<'r, T: FromSql<'r>>(row: &'r Row, index: usize) -> Result<T, Error> {
+    row.try_get(index)?
+        .ok_or_else(|| err_msg!(Unexpected, "Unexpected null value in result"))
+}
+
+pub(crate) async fn execute_simple(conn: &mut MssqlConnection, sql: &str) -> Result<(), Error> {
+    conn.simple_query(sql).await?.into_results().await?;
+    Ok(())
+}
+
+pub(crate) async fn end_transaction(conn: &mut MssqlConnection, commit: bool) -> Result<(), Error> {
+    execute_simple(
+        conn,
+        if commit {
+            "COMMIT TRANSACTION"
+        } else {
+            "ROLLBACK TRANSACTION"
+        },
+    )
+    .await
+    .map_err(err_map!(Backend, "Error completing transaction"))
+}
+
+pub(crate) async fn get_config(
+    conn: &mut MssqlConnection,
+    name: &str,
+) -> Result<Option<String>, Error> {
+    let mut query = Query::new(CONFIG_FETCH_QUERY);
+    query.bind(name);
+    let row = query.query(&mut **conn).await?.into_row().await?;
+    Ok(row.and_then(|r| r.try_get::<&str, _>(0).ok().flatten().map(str::to_owned)))
+}
+
+pub(crate) async fn set_config(
+    conn: &mut MssqlConnection,
+    name: &str,
+    value: &str,
+) -> Result<(), Error> {
+    let mut query = Query::new(CONFIG_UPDATE_QUERY);
+    query.bind(name);
+    query.bind(value);
+    query.execute(&mut **conn).await?;
+    Ok(())
+}
+
+pub(crate) async fn insert_profile(
+    conn: &mut MssqlConnection,
+    name: &str,
+    enc_key: Vec<u8>,
+) -> Result<ProfileId, Error> {
+    let mut query =
+        Query::new("INSERT INTO profiles (name, profile_key) OUTPUT INSERTED.id VALUES (@P1, @P2)");
+    query.bind(name);
+    query.bind(enc_key);
+    let row = match query.query(&mut **conn).await {
+        Ok(stream) => stream.into_row().await,
+        Err(err) => Err(err),
+    };
+    match row {
+        Ok(Some(row)) => get_value(&row, 0),
+        Ok(None) => Err(err_msg!(Backend, "Error inserting profile")),
+        Err(err) if is_duplicate_error(&err) => Err(err_msg!(Duplicate, "Duplicate profile name")),
+        Err(err) => Err(err_msg!(Backend, "Error inserting profile").with_cause(err)),
+    }
+}
+
+async fn resolve_profile_key(
+    conn: &mut MssqlConnection,
+    cache: Arc<KeyCache>,
+    profile: String,
+    in_txn: bool,
+) -> Result<(ProfileId, Arc<ProfileKey>), Error> {
+    if let Some((pid, key)) = cache.get_profile(profile.as_str()).await {
+        if in_txn {
+            // lock the profile row to prevent it from being removed
+            let mut query = Query::new("SELECT id FROM profiles WITH (UPDLOCK) WHERE id = @P1");
+            query.bind(pid);
+            if query.query(&mut **conn).await?.into_row().await?.is_none() {
+                return Err(err_msg!(NotFound, "Session profile has been removed"));
+            }
+        }
+        Ok((pid, key))
+    } else {
+        let mut query =
+            Query::new("SELECT id, profile_key FROM profiles WITH (UPDLOCK) WHERE name = @P1");
+        query.bind(profile.as_str());
+        if let Some(row) = query.query(&mut **conn).await?.into_row().await? {
+            let pid = get_value(&row, 0)?;
+            let enc_key = get_value::<&[u8]>(&row, 1)?.to_vec();
+            let key = Arc::new(cache.load_key(enc_key).await?);
+            cache.add_profile(profile, pid, key.clone()).await;
+            Ok((pid, key))
+        } else {
+            Err(err_msg!(NotFound, "Profile not found"))
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn encode_tag_filter(
+    tag_filter: Option<TagFilter>,
+    key: &ProfileKey,
+    first_index: usize,
+) -> Result<Option<(String, Vec<Vec<u8>>)>, Error> {
+    if let Some(tag_filter) = tag_filter {
+        let tag_query = tag_query(tag_filter.query)?;
+        let mut enc = TagMssqlEncoder::new(
+            |name| key.encrypt_tag_name(ProfileKey::prepare_input(name.as_bytes())),
+            |value| key.encrypt_tag_value(ProfileKey::prepare_input(value.as_bytes())),
+            first_index,
+        );
+        if let Some(filter) = enc.encode_query(&tag_query)? {
+            Ok(Some((filter, enc.arguments)))
+        } else {
+            Ok(None)
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+// the plaintext tag value used to order records, bound to the encrypted tag name
+fn order_tag_value(index: usize) -> String {
+    format!(
+        "(SELECT MIN(it.value) FROM items_tags it WHERE it.item_id = i.id \
+        AND it.plaintext = 1 AND it.name = @P{})",
+        index
+    )
+}
+
+// append the tag filter, ordering and paging clauses to a query. the encrypted name of
+// any ordering tag, followed by the offset and limit, are bound as parameters starting
+// from the placeholder index `paging_index`
+fn extend_query(
+    query: &str,
+    filter: Option<&str>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+    order_by: Option<&EncOrderBy>,
+    descending: bool,
+    mut paging_index: usize,
+) -> String {
+    let mut query = query.to_string();
+    if let Some(filter) = filter {
+        query.push_str(" AND "); // assumes WHERE already occurs
+        query.push_str(filter);
+    }
+    let paged = offset.is_some() || limit.is_some();
+    if order_by.is_some() || paged {
+        // OFFSET .. FETCH requires an ORDER BY clause
+        let direction = if descending { " DESC" } else { "" };
+        query.push_str(" ORDER BY ");
+        if let Some(EncOrderBy::Tag(_)) = order_by {
+            let value = order_tag_value(paging_index);
+            query.push_str(&format!(
+                "CASE WHEN {value} IS NULL THEN 0 ELSE 1 END{direction}, {value}{direction}, "
+            ));
+            paging_index += 1;
+        }
+        query.push_str("i.id");
+        query.push_str(direction);
+    }
+    if paged {
+        query.push_str(&format!(" OFFSET @P{} ROWS", paging_index));
+        if limit.is_some() {
+            query.push_str(&format!(" FETCH NEXT @P{} ROWS ONLY", paging_index + 1));
+        }
+    }
+    query
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn perform_insert(
+    conn: &mut MssqlConnection,
+    profile_id: ProfileId,
+    kind: EntryKind,
+    enc_category: Vec<u8>,
+    enc_name: Vec<u8>,
+    enc_value: Vec<u8>,
+    enc_tags: Option<Vec<EncEntryTag>>,
+    expiry_ms: Option<i64>,
+    operation: EntryOperation,
+) -> Result<(), Error> {
+    let expiry = expiry_ms
+        .map(expiry_timestamp)
+        .transpose()?
+        .map(|exp| exp.naive_utc());
+    let mut query = Query::new(match operation {
+        EntryOperation::Insert => INSERT_QUERY,
+        EntryOperation::InsertOrReplace => UPSERT_QUERY,
+        _ => UPDATE_QUERY,
+    });
+    query.bind(profile_id);
+    query.bind(kind as i16);
+    query.bind(enc_category);
+    query.bind(enc_name);
+    query.bind(enc_value);
+    query.bind(expiry);
+    let row = match query.query(&mut **conn).await {
+        Ok(stream) => stream.into_row().await,
+        Err(err) => Err(err),
+    };
+    let row_id: i64 = if operation == EntryOperation::Insert {
+        trace!("Insert entry");
+        match row {
+            Ok(Some(row)) => get_value(&row, 0)?,
+            Ok(None) => return Err(err_msg!(Backend, "Error inserting entry")),
+            Err(err) if is_duplicate_error(&err) => {
+                return Err(err_msg!(Duplicate, "Duplicate entry"))
+            }
+            Err(err) => return Err(err_msg!(Backend, "Error inserting entry").with_cause(err)),
+        }
+    } else {
+        let row_id = if operation == EntryOperation::InsertOrReplace {
+            trace!("Insert or replace entry");
+            match row {
+                Ok(Some(row)) => get_value(&row, 0)?,
+                Ok(None) => return Err(err_msg!(Backend, "Error inserting or replacing entry")),
+                Err(err) => {
+                    return Err(
+                        err_msg!(Backend, "Error inserting or replacing entry").with_cause(err)
+                    )
+                }
+            }
+        } else {
+            trace!("Update entry");
+            match row {
+                Ok(Some(row)) => get_value(&row, 0)?,
+                _ => return Err(err_msg!(NotFound, "Error updating existing entry")),
+            }
+        };
+        let mut query = Query::new(TAG_DELETE_QUERY);
+        query.bind(row_id);
+        query
+            .execute(&mut **conn)
+            .await
+            .map_err(err_map!(Backend, "Error removing existing entry tags"))?;
+        row_id
+    };
+    if let Some(tags) = enc_tags {
+        for tag in tags {
+            let mut query = Query::new(TAG_INSERT_QUERY);
+            query.bind(row_id);
+            query.bind(tag.name);
+            query.bind(tag.value);
+            query.bind(tag.plaintext as i16);
+            query
+                .execute(&mut **conn)
+                .await
+                .map_err(err_map!(Backend, "Error inserting entry tags"))?;
+        }
+    }
+    Ok(())
+}
+
+async fn perform_remove(
+    conn: &mut MssqlConnection,
+    profile_id: ProfileId,
+    kind: EntryKind,
+    enc_category: Vec<u8>,
+    enc_name: Vec<u8>,
+) -> Result<(), Error> {
+    trace!("Remove entry");
+    let mut query = Query::new(DELETE_QUERY);
+    query.bind(profile_id);
+    query.bind(kind as i16);
+    query.bind(enc_category);
+    query.bind(enc_name);
+    let done = query
+        .execute(&mut **conn)
+        .await
+        .map_err(err_map!(Backend, "Error removing entry"))?;
+    if done.total() == 0 {
+        Err(err_msg!(NotFound, "Entry not found"))
+    } else {
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn perform_scan<'a>(
+    mut session: impl DerefMut<Target = MssqlSession> + Send + 'a,
+    profile_id: ProfileId,
+    key: Arc<ProfileKey>,
+    kind: Option<EntryKind>,
+    category: Option<String>,
+    tag_filter: Option<TagFilter>,
+    after: Option<ScanPosition>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+    order_by: Option<OrderBy>,
+    descending: bool,
+    for_update: bool,
+) -> impl Stream<Item = Result<Vec<EncScanEntry>, Error>> + 'a {
+    try_stream! {
+        let (enc_category, tag_filter, order_by) = unblock({
+            let key = key.clone();
+            let enc_category = category.map(|c| ProfileKey::prepare_input(c.as_bytes()));
+            move || {
+                Result::<_, Error>::Ok((
+                    enc_category
+                        .map(|c| key.encrypt_entry_category(c))
+                        .transpose()?,
+                    encode_tag_filter(tag_filter, &key, 4)?,
+                    encode_order_by(order_by, &key)?,
+                ))
+            }
+        }).await?;
+        if matches!(order_by, Some(EncOrderBy::CreatedAt | EncOrderBy::UpdatedAt)) {
+            Err(err_msg!(
+                Unsupported,
+                "Ordering by record timestamps is not supported by this backend"
+            ))?;
+        }
+        let (mut filter, filter_args) = match tag_filter {
+            Some((filter, args)) => (Some(filter), args),
+            None => (None, Vec::new()),
+        };
+        let mut paging_index = filter_args.len() + 4;
+        let position = match after {
+            Some(after) => {
+                // the position clause binds the ordering tag name, the sort value and the ID
+                let order_tag = match order_by.as_ref() {
+                    Some(EncOrderBy::Tag(name)) => Some(name.clone()),
+                    _ => None,
+                };
+                let value = order_tag.as_ref().map(|_| order_tag_value(paging_index));
+                paging_index += order_tag.is_some() as usize;
+                let sort_value = match (&value, after.value) {
+                    (Some(_), Some(SortValue::Text(text))) => Some(text.into_bytes()),
+                    _ => None,
+                };
+                let value_placeholder = sort_value.as_ref().map(|_| format!("@P{}", paging_index));
+                paging_index += sort_value.is_some() as usize;
+                let clause = position_filter(
+                    value.as_deref(),
+                    value_placeholder.as_deref(),
+                    &format!("@P{}", paging_index),
+                    descending,
+                );
+                paging_index += 1;
+                filter = Some(match filter {
+                    Some(filter) => format!("{} AND {}", filter, clause),
+                    None => clause,
+                });
+                Some((order_tag, sort_value, after.id))
+            }
+            None => None,
+        };
+        let mut query = Query::new(extend_query(
+            if for_update { SCAN_QUERY_UPDATE } else { SCAN_QUERY },
+            filter.as_deref(),
+            offset,
+            limit,
+            order_by.as_ref(),
+            descending,
+            paging_index,
+        ));
+        query.bind(profile_id);
+        query.bind(kind.map(|k| k as i16));
+        query.bind(enc_category);
+        for arg in filter_args {
+            query.bind(arg);
+        }
+        if let Some((order_tag, sort_value, id)) = position {
+            if let Some(order_tag) = order_tag {
+                query.bind(order_tag);
+            }
+            if let Some(sort_value) = sort_value {
+                query.bind(sort_value);
+            }
+            query.bind(id);
+        }
+        if let Some(EncOrderBy::Tag(name)) = order_by {
+            query.bind(name);
+        }
+        if offset.is_some() || limit.is_some() {
+            query.bind(offset.unwrap_or(0));
+            if let Some(limit) = limit {
+                query.bind(limit);
+            }
+        }
+        let mut batch = Vec::with_capacity(PAGE_SIZE);
+
+        let conn = session.connection_mut()?;
+        let mut rows = query.query(&mut **conn).await?.into_row_stream();
+        while let Some(row) = rows.try_next().await? {
+            let tags = row.try_get::<&str, _>(5)?.map(|t| t.as_bytes().to_vec()).unwrap_or_default();
+            let kind: i16 = get_value(&row, 1)?;
+            let kind = EntryKind::try_from(kind as usize)?;
+            batch.push(EncScanEntry {
+                id: Some(get_value(&row, 0)?),
+                kind,
+                category: get_value::<&[u8]>(&row, 2)?.to_vec(),
+                name: get_value::<&[u8]>(&row, 3)?.to_vec(),
+                value: get_value::<&[u8]>(&row, 4)?.to_vec(),
+                tags,
+                version: None,
+                created_at: None,
+                updated_at: None,
+                deleted_at: None,
+            });
+            if batch.len() == PAGE_SIZE {
+                yield batch.split_off(0);
+            }
+        }
+        drop(rows);
+        drop(session);
+
+        if !batch.is_empty() {
+            yield batch;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mssql_extend_query() {
+        assert_eq!(
+            extend_query(
+                "SELECT id FROM items i WHERE profile_id = @P1",
+                Some("i.id IN (@P4)"),
+                Some(10),
+                Some(5),
+                None,
+                true,
+                5,
+            ),
+            "SELECT id FROM items i WHERE profile_id = @P1 AND i.id IN (@P4) \
+            ORDER BY i.id DESC OFFSET @P5 ROWS FETCH NEXT @P6 ROWS ONLY"
+        );
+    }
+
+    #[test]
+    fn mssql_extend_query_tag_order() {
+        assert_eq!(
+            extend_query(
+                "SELECT id FROM items i WHERE profile_id = @P1",
+                None,
+                None,
+                Some(5),
+                Some(&EncOrderBy::Tag(vec![1])),
+                false,
+                4,
+            ),
+            "SELECT id FROM items i WHERE profile_id = @P1 ORDER BY \
+            CASE WHEN (SELECT MIN(it.value) FROM items_tags it WHERE it.item_id = i.id \
+            AND it.plaintext = 1 AND it.name = @P4) IS NULL THEN 0 ELSE 1 END, \
+            (SELECT MIN(it.value) FROM items_tags it WHERE it.item_id = i.id \
+            AND it.plaintext = 1 AND it.name = @P4), i.id OFFSET @P5 ROWS FETCH NEXT @P6 ROWS ONLY"
+        );
+    }
+}
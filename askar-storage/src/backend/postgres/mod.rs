@@ -11,22 +11,33 @@ use futures_lite::{
 
 use sqlx::{
     pool::PoolConnection,
-    postgres::{PgPool, Postgres},
-    Acquire, Row,
+    postgres::{PgListener, PgPool, Postgres},
+    Acquire, Error as SqlxError, Executor, Row,
 };
 
+use serde::Deserialize;
+
 use super::{
+    batch_result,
     db_utils::{
-        decode_tags, decrypt_scan_batch, encode_profile_key, encode_tag_filter, expiry_timestamp,
-        extend_query, prepare_tags, random_profile_name, replace_arg_placeholders, DbSession,
-        DbSessionActive, DbSessionRef, DbSessionTxn, EncScanEntry, ExtDatabase, QueryParams,
-        QueryPrepare, PAGE_SIZE,
+        decode_tags, decrypt_change_event, decrypt_scan_batch, decrypt_scan_entry,
+        encode_name_filter, encode_order_by, encode_profile_key, encode_search_filter,
+        encode_tag_filter, encrypt_entry_updates, encrypt_search_terms, expiry_timestamp,
+        extend_query, extend_query_after, prepare_tags, random_profile_name, rekey_entry_batch,
+        replace_arg_placeholders, rewrap_master_key, search_tokens, verify_audit_records,
+        wrap_key_slot, AuditSession, Connection, DbSession, DbSessionActive, DbSessionRef,
+        DbSessionTxn, EncAuditRecord, EncChangeEvent, EncOrderBy, EncRekeyEntry, EncScanEntry,
+        ExtDatabase, QueryParams, QueryPrepare, RekeyState, PAGE_SIZE,
     },
-    Backend, BackendSession,
+    Backend, BackendSession, RekeyProgress,
 };
 use crate::{
     backend::OrderBy,
-    entry::{EncEntryTag, Entry, EntryKind, EntryOperation, EntryTag, Scan, TagFilter},
+    crypto::buffer::SecretBytes,
+    entry::{
+        AuditRecord, ChangeOperation, EncEntryTag, Entry, EntryKind, EntryOperation, EntryTag,
+        EntryUpdate, EntryVersion, ProfileDetails, Scan, ScanPosition, Subscription, TagFilter,
+    },
     error::Error,
     future::{unblock, BoxFuture},
     protect::{EntryEncryptor, KeyCache, PassKey, ProfileId, ProfileKey, StoreKeyMethod},
@@ -44,48 +55,298 @@ const CONFIG_FETCH_QUERY: &str = "SELECT value FROM config WHERE name = $1";
 const CONFIG_UPDATE_QUERY: &str = "INSERT INTO config (name, value) VALUES ($1, $2)
     ON CONFLICT(name) DO UPDATE SET value = excluded.value";
 const COUNT_QUERY: &str = "SELECT COUNT(*) FROM items i
+    WHERE profile_id = $1
+    AND (kind = $2 OR $2 IS NULL)
+    AND (category = $3 OR $3 IS NULL)
+    AND (expiry IS NULL OR expiry > CURRENT_TIMESTAMP)
+    AND deleted_at IS NULL";
+const COUNT_ALL_QUERY: &str = "SELECT COUNT(*) FROM items i
     WHERE profile_id = $1
     AND (kind = $2 OR $2 IS NULL)
     AND (category = $3 OR $3 IS NULL)
     AND (expiry IS NULL OR expiry > CURRENT_TIMESTAMP)";
+const COUNT_CATEGORY_QUERY: &str = "SELECT category, COUNT(*) FROM items i
+    WHERE profile_id = $1
+    AND (kind = $2 OR $2 IS NULL)
+    AND (expiry IS NULL OR expiry > CURRENT_TIMESTAMP)
+    AND deleted_at IS NULL";
 const DELETE_QUERY: &str = "DELETE FROM items
-    WHERE profile_id = $1 AND kind = $2 AND category = $3 AND name = $4";
+    WHERE profile_id = $1 AND kind = $2 AND category = $3 AND name = $4
+    AND (version = $5 OR $5 IS NULL) AND deleted_at IS NULL
+    RETURNING id";
+const SOFT_DELETE_QUERY: &str = "UPDATE items SET deleted_at = CURRENT_TIMESTAMP
+    WHERE profile_id = $1 AND kind = $2 AND category = $3 AND name = $4
+    AND (version = $5 OR $5 IS NULL) AND deleted_at IS NULL
+    RETURNING id";
 const FETCH_QUERY: &str = "SELECT id, value,
     (SELECT ARRAY_TO_STRING(ARRAY_AGG(it.plaintext || ':'
         || ENCODE(it.name, 'hex') || ':' || ENCODE(it.value, 'hex')), ',')
-        FROM items_tags it WHERE it.item_id = i.id) tags
+        FROM items_tags it WHERE it.item_id = i.id) tags, version,
+    created_at, updated_at, (EXTRACT(EPOCH FROM expiry) * 1000)::BIGINT
     FROM items i
     WHERE profile_id = $1 AND kind = $2 AND category = $3 AND name = $4
-    AND (expiry IS NULL OR expiry > CURRENT_TIMESTAMP)";
+    AND (expiry IS NULL OR expiry > CURRENT_TIMESTAMP)
+    AND deleted_at IS NULL";
 const FETCH_QUERY_UPDATE: &str = "SELECT id, value,
     (SELECT ARRAY_TO_STRING(ARRAY_AGG(it.plaintext || ':'
         || ENCODE(it.name, 'hex') || ':' || ENCODE(it.value, 'hex')), ',')
-        FROM items_tags it WHERE it.item_id = i.id) tags
+        FROM items_tags it WHERE it.item_id = i.id) tags, version,
+    created_at, updated_at, (EXTRACT(EPOCH FROM expiry) * 1000)::BIGINT
     FROM items i
     WHERE profile_id = $1 AND kind = $2 AND category = $3 AND name = $4
-    AND (expiry IS NULL OR expiry > CURRENT_TIMESTAMP) FOR NO KEY UPDATE";
-const INSERT_QUERY: &str = "INSERT INTO items (profile_id, kind, category, name, value, expiry)
-    VALUES ($1, $2, $3, $4, $5, $6)
+    AND (expiry IS NULL OR expiry > CURRENT_TIMESTAMP)
+    AND deleted_at IS NULL FOR NO KEY UPDATE";
+const INSERT_QUERY: &str = "INSERT INTO items (profile_id, kind, category, name, value, expiry,
+        created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
     ON CONFLICT DO NOTHING RETURNING id";
-const UPDATE_QUERY: &str = "UPDATE items SET value=$5, expiry=$6
+const UPDATE_QUERY: &str = "UPDATE items SET value=$5, expiry=$6, version=version+1,
+    updated_at=$8
     WHERE profile_id=$1 AND kind=$2 AND category=$3 AND name=$4
+    AND (version=$7 OR $7 IS NULL) AND deleted_at IS NULL
+    RETURNING id";
+const UPSERT_QUERY: &str = "INSERT INTO items (profile_id, kind, category, name, value, expiry,
+        created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+    ON CONFLICT (profile_id, kind, category, name)
+    DO UPDATE SET value = excluded.value, expiry = excluded.expiry,
+        version = items.version + 1, updated_at = excluded.updated_at, deleted_at = NULL
     RETURNING id";
 const SCAN_QUERY: &str = "SELECT id, kind, category, name, value,
     (SELECT ARRAY_TO_STRING(ARRAY_AGG(it.plaintext || ':'
         || ENCODE(it.name, 'hex') || ':' || ENCODE(it.value, 'hex')), ',')
-        FROM items_tags it WHERE it.item_id = i.id) tags
+        FROM items_tags it WHERE it.item_id = i.id) tags, version,
+    created_at, updated_at, (EXTRACT(EPOCH FROM deleted_at) * 1000)::BIGINT
     FROM items i WHERE profile_id = $1
     AND (kind = $2 OR $2 IS NULL)
     AND (category = $3 OR $3 IS NULL)
-    AND (expiry IS NULL OR expiry > CURRENT_TIMESTAMP)";
+    AND (expiry IS NULL OR expiry > CURRENT_TIMESTAMP)
+    AND deleted_at IS NULL";
 const DELETE_ALL_QUERY: &str = "DELETE FROM items i
     WHERE profile_id = $1
     AND (kind = $2 OR $2 IS NULL)
-    AND (category = $3 OR $3 IS NULL)";
+    AND (category = $3 OR $3 IS NULL)
+    AND deleted_at IS NULL";
+const SOFT_DELETE_ALL_QUERY: &str = "UPDATE items i SET deleted_at = CURRENT_TIMESTAMP
+    WHERE profile_id = $1
+    AND (kind = $2 OR $2 IS NULL)
+    AND (category = $3 OR $3 IS NULL)
+    AND deleted_at IS NULL";
+const DELETE_EXPIRED_QUERY: &str = "DELETE FROM items
+    WHERE profile_id = $1
+    AND (kind = $2 OR $2 IS NULL)
+    AND (category = $3 OR $3 IS NULL)
+    AND expiry IS NOT NULL AND expiry <= CURRENT_TIMESTAMP";
+const SCAN_ALL_QUERY: &str = "SELECT id, kind, category, name, value,
+    (SELECT ARRAY_TO_STRING(ARRAY_AGG(it.plaintext || ':'
+        || ENCODE(it.name, 'hex') || ':' || ENCODE(it.value, 'hex')), ',')
+        FROM items_tags it WHERE it.item_id = i.id) tags, version,
+    created_at, updated_at, (EXTRACT(EPOCH FROM deleted_at) * 1000)::BIGINT
+    FROM items i WHERE profile_id = $1
+    AND (kind = $2 OR $2 IS NULL)
+    AND (category = $3 OR $3 IS NULL)
+    AND (expiry IS NULL OR expiry > CURRENT_TIMESTAMP)";
+const RESTORE_QUERY: &str = "UPDATE items SET deleted_at = NULL
+    WHERE profile_id = $1 AND kind = $2 AND category = $3 AND name = $4
+    AND deleted_at IS NOT NULL
+    RETURNING id";
+const PURGE_QUERY: &str = "DELETE FROM items
+    WHERE profile_id = $1
+    AND (kind = $2 OR $2 IS NULL)
+    AND deleted_at IS NOT NULL
+    AND ($3::timestamptz IS NULL OR deleted_at <= $3)";
+const PURGE_ENTRY_QUERY: &str = "DELETE FROM items
+    WHERE profile_id = $1 AND kind = $2 AND category = $3 AND name = $4
+    AND deleted_at IS NOT NULL";
+const TAG_VALUES_QUERY: &str = "SELECT it.value, COUNT(DISTINCT i.id) FROM items i
+    JOIN items_tags it ON it.item_id = i.id
+    WHERE i.profile_id = $1 AND i.kind = $2 AND i.category = $3
+    AND it.name = $4 AND it.plaintext = $5
+    AND (i.expiry IS NULL OR i.expiry > CURRENT_TIMESTAMP)
+    AND i.deleted_at IS NULL";
+const HISTORY_ENABLE_QUERY: &str = "INSERT INTO items_history_categories
+    (profile_id, kind, category) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING";
+const HISTORY_DISABLE_QUERY: &str = "DELETE FROM items_history_categories
+    WHERE profile_id = $1 AND kind = $2 AND category = $3";
+const HISTORY_INSERT_QUERY: &str = "INSERT INTO items_history
+    (item_id, version, value, tags, replaced_at)
+    SELECT i.id, i.version, i.value,
+        (SELECT ARRAY_TO_STRING(ARRAY_AGG(it.plaintext || ':'
+            || ENCODE(it.name, 'hex') || ':' || ENCODE(it.value, 'hex')), ',')
+            FROM items_tags it WHERE it.item_id = i.id), $6
+    FROM items i
+    JOIN items_history_categories hc ON hc.profile_id = i.profile_id
+        AND hc.kind = i.kind AND hc.category = i.category
+    WHERE i.profile_id = $1 AND i.kind = $2 AND i.category = $3 AND i.name = $4
+    AND (i.version = $5 OR $5 IS NULL) AND i.deleted_at IS NULL";
+const HISTORY_FETCH_QUERY: &str = "SELECT h.version, i.name, h.value, h.tags, h.replaced_at
+    FROM items_history h JOIN items i ON i.id = h.item_id
+    WHERE i.profile_id = $1 AND i.kind = $2 AND i.category = $3 AND i.name = $4
+    AND (h.version = $5 OR $5 IS NULL)
+    ORDER BY h.version DESC LIMIT $6";
+const HISTORY_PRUNE_QUERY: &str = "DELETE FROM items_history
+    WHERE item_id IN (SELECT id FROM items WHERE profile_id = $1
+        AND (kind = $2 OR $2 IS NULL) AND (category = $3 OR $3 IS NULL))
+    AND (replaced_at <= $4 OR $4 IS NULL)";
+const AUDIT_INSERT_QUERY: &str = "INSERT INTO audit_log
+    (profile_id, session_id, operation, kind, category, item_id, created_at, mac)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)";
+// serialize the writers of the audit log for a profile until the transaction ends
+const AUDIT_LOCK_QUERY: &str = "SELECT pg_advisory_xact_lock('audit_log'::regclass::oid::int4, $1)";
+const AUDIT_LATEST_QUERY: &str = "SELECT mac FROM audit_log
+    WHERE profile_id = $1 AND (id <= $2 OR $2 IS NULL)
+    ORDER BY id DESC LIMIT 1";
+const AUDIT_FETCH_QUERY: &str = "SELECT id, session_id, operation, kind, category, item_id,
+    created_at, mac FROM audit_log
+    WHERE profile_id = $1 AND (id > $2 OR $2 IS NULL)
+    ORDER BY id LIMIT $3";
+const STORE_META_FETCH_QUERY: &str = "SELECT value FROM store_meta WHERE name = $1";
+const STORE_META_UPDATE_QUERY: &str = "INSERT INTO store_meta (name, value) VALUES ($1, $2)
+    ON CONFLICT(name) DO UPDATE SET value = excluded.value";
+const STORE_META_DELETE_QUERY: &str = "DELETE FROM store_meta WHERE name = $1";
+const PROFILE_META_FETCH_QUERY: &str = "SELECT value FROM profile_meta
+    WHERE profile_id = $1 AND name = $2";
+const PROFILE_META_UPDATE_QUERY: &str = "INSERT INTO profile_meta (profile_id, name, value)
+    VALUES ($1, $2, $3)
+    ON CONFLICT(profile_id, name) DO UPDATE SET value = excluded.value";
+const PROFILE_META_DELETE_QUERY: &str = "DELETE FROM profile_meta
+    WHERE profile_id = $1 AND name = $2";
+const PROFILE_DETAILS_QUERY: &str = "SELECT p.name, p.created_at, p.last_access,
+    (SELECT COUNT(*) FROM items i WHERE i.profile_id = p.id AND i.deleted_at IS NULL)
+    FROM profiles p
+    WHERE $1::text IS NULL OR LEFT(p.name, LENGTH($1)) = $1
+    ORDER BY p.name OFFSET $2 LIMIT $3";
+const PROFILE_ACCESS_QUERY: &str = "UPDATE profiles SET last_access = $1
+    WHERE id IN (SELECT id FROM profiles WHERE id = $2 FOR NO KEY UPDATE SKIP LOCKED)";
+const KEY_SLOT_INSERT_QUERY: &str = "INSERT INTO key_slots (name, key_ref, wrapped_key)
+    VALUES ($1, $2, $3) ON CONFLICT DO NOTHING";
+const STORE_REKEY_STATE_QUERY: &str = "SELECT name, value FROM config
+    WHERE name IN ('rekey_ref', 'rekey_key', 'rekey_master', 'rekey_progress')";
+const STORE_REKEY_CLEAR_QUERY: &str = "DELETE FROM config
+    WHERE name IN ('rekey_ref', 'rekey_key', 'rekey_master', 'rekey_progress')";
+const STORE_REKEY_COUNT_QUERY: &str = "SELECT COUNT(*), COUNT(CASE WHEN id <= $1 THEN 1 END)
+    FROM profiles";
+const STORE_REKEY_PROFILES_QUERY: &str = "SELECT id, profile_key FROM profiles
+    WHERE id > $1 ORDER BY id LIMIT $2 FOR UPDATE";
+const REKEY_COUNT_QUERY: &str = "SELECT COUNT(*) FROM items WHERE profile_id = $1";
+const REKEY_ITEMS_QUERY: &str = "SELECT id, category, name, value FROM items
+    WHERE profile_id = $1 AND id > $2 ORDER BY id LIMIT $3";
+const REKEY_TAGS_QUERY: &str = "SELECT item_id, name, value, plaintext FROM items_tags
+    WHERE item_id IN (SELECT id FROM items
+        WHERE profile_id = $1 AND id > $2 ORDER BY id LIMIT $3)";
+const REKEY_HISTORY_QUERY: &str = "SELECT item_id, id, value, tags FROM items_history
+    WHERE item_id IN (SELECT id FROM items
+        WHERE profile_id = $1 AND id > $2 ORDER BY id LIMIT $3)
+    ORDER BY id";
+const REKEY_ITEM_UPDATE_QUERY: &str = "UPDATE items SET category = $1, name = $2, value = $3
+    WHERE id = $4";
+const REKEY_HISTORY_UPDATE_QUERY: &str = "UPDATE items_history SET value = $1, tags = $2
+    WHERE id = $3";
+const REKEY_CATEGORIES_QUERY: &str = "SELECT DISTINCT category FROM items_history_categories
+    WHERE profile_id = $1";
+const REKEY_CATEGORY_UPDATE_QUERY: &str = "UPDATE items_history_categories SET category = $1
+    WHERE profile_id = $2 AND category = $3";
+const REKEY_META_QUERY: &str = "SELECT name, value FROM profile_meta WHERE profile_id = $1";
+const REKEY_META_UPDATE_QUERY: &str = "UPDATE profile_meta SET name = $1, value = $2
+    WHERE profile_id = $3 AND name = $4";
+const REKEY_SEARCH_CHECK_QUERY: &str = "SELECT EXISTS(SELECT 1 FROM items_search s
+    JOIN items i ON i.id = s.item_id WHERE i.profile_id = $1
+    AND NOT EXISTS(SELECT 1 FROM items_search_terms t WHERE t.item_id = s.item_id))";
+const REKEY_SEARCH_QUERY: &str = "SELECT item_id, terms FROM items_search_terms
+    WHERE item_id IN (SELECT id FROM items
+        WHERE profile_id = $1 AND id > $2 ORDER BY id LIMIT $3)";
+const REKEY_SEARCH_UPDATE_QUERY: &str = "UPDATE items_search_terms SET terms = $1
+    WHERE item_id = $2";
 const TAG_INSERT_QUERY: &str = "INSERT INTO items_tags
     (item_id, name, value, plaintext) VALUES ($1, $2, $3, $4)";
 const TAG_DELETE_QUERY: &str = "DELETE FROM items_tags
     WHERE item_id=$1";
+const CHANGES_CHANNEL: &str = "askar_changes";
+const CHANGES_TRIGGER_CHECK_QUERY: &str = "SELECT EXISTS(SELECT 1 FROM pg_trigger
+    WHERE tgrelid = 'items'::regclass AND tgname = 'items_notify')";
+// record each committed change to an item and publish its ID, keeping the
+// notification small. The trigger is deferred so that the recorded tags are
+// those present when the transaction is committed.
+const CHANGES_TRIGGER_INSTALL: &str = "
+    CREATE TABLE IF NOT EXISTS items_changes (
+        id BIGSERIAL,
+        profile_id BIGINT NOT NULL,
+        kind SMALLINT NOT NULL,
+        category BYTEA NOT NULL,
+        name BYTEA NOT NULL,
+        operation TEXT NOT NULL,
+        tags TEXT NULL,
+        created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY(id)
+    );
+    CREATE INDEX IF NOT EXISTS ix_items_changes_created_at ON items_changes(created_at);
+
+    CREATE OR REPLACE FUNCTION items_notify() RETURNS TRIGGER AS $$
+    DECLARE
+        op TEXT;
+        rec RECORD;
+        change_id BIGINT;
+    BEGIN
+        IF TG_OP = 'INSERT' THEN
+            op := 'insert';
+            rec := NEW;
+        ELSIF TG_OP = 'DELETE' THEN
+            IF OLD.deleted_at IS NOT NULL THEN
+                RETURN NULL;
+            END IF;
+            op := 'remove';
+            rec := OLD;
+        ELSIF NEW.deleted_at IS NOT NULL THEN
+            IF OLD.deleted_at IS NOT NULL THEN
+                RETURN NULL;
+            END IF;
+            op := 'remove';
+            rec := NEW;
+        ELSIF OLD.deleted_at IS NOT NULL THEN
+            op := 'insert';
+            rec := NEW;
+        ELSE
+            op := 'update';
+            rec := NEW;
+        END IF;
+        INSERT INTO items_changes (profile_id, kind, category, name, operation, tags)
+        VALUES (rec.profile_id, rec.kind, rec.category, rec.name, op,
+            CASE WHEN op = 'remove' THEN NULL ELSE
+                (SELECT ARRAY_TO_STRING(ARRAY_AGG(it.plaintext || ':'
+                    || ENCODE(it.name, 'hex') || ':' || ENCODE(it.value, 'hex')), ',')
+                    FROM items_tags it WHERE it.item_id = rec.id)
+            END)
+        RETURNING id INTO change_id;
+        -- changes are only retained until subscribers have been notified
+        IF change_id % 100 = 0 THEN
+            DELETE FROM items_changes
+                WHERE created_at < CURRENT_TIMESTAMP - INTERVAL '5 minutes';
+        END IF;
+        PERFORM pg_notify('askar_changes', json_build_object(
+            'schema', TG_TABLE_SCHEMA,
+            'id', change_id
+        )::text);
+        RETURN NULL;
+    END;
+    $$ LANGUAGE plpgsql;
+
+    CREATE CONSTRAINT TRIGGER items_notify AFTER INSERT OR UPDATE OR DELETE ON items
+        DEFERRABLE INITIALLY DEFERRED
+        FOR EACH ROW EXECUTE PROCEDURE items_notify();
+";
+const CHANGE_FETCH_QUERY: &str = "SELECT profile_id, kind, category, name, operation, tags
+    FROM items_changes WHERE id = $1";
+const ITEM_ID_QUERY: &str = "SELECT id FROM items
+    WHERE profile_id = $1 AND kind = $2 AND category = $3 AND name = $4
+    AND deleted_at IS NULL";
+const SEARCH_INSERT_QUERY: &str = "INSERT INTO items_search
+    (item_id, token) VALUES ($1, $2) ON CONFLICT DO NOTHING";
+const SEARCH_DELETE_QUERY: &str = "DELETE FROM items_search
+    WHERE item_id=$1";
+const SEARCH_TERMS_INSERT_QUERY: &str = "INSERT INTO items_search_terms
+    (item_id, terms) VALUES ($1, $2)";
+const SEARCH_TERMS_DELETE_QUERY: &str = "DELETE FROM items_search_terms
+    WHERE item_id=$1";
 
 /// A PostgreSQL database store
 pub struct PostgresBackend {
@@ -129,11 +390,12 @@ impl Backend for PostgresBackend {
             .await?;
             let mut conn = self.conn_pool.acquire().await?;
             let res = sqlx::query_scalar(
-                "INSERT INTO profiles (name, profile_key) VALUES ($1, $2) 
+                "INSERT INTO profiles (name, profile_key, created_at) VALUES ($1, $2, $3)
                 ON CONFLICT DO NOTHING RETURNING id",
             )
             .bind(&name)
             .bind(enc_key)
+            .bind(chrono::Utc::now().timestamp_millis())
             .fetch_optional(conn.as_mut())
             .await?;
             conn.return_to_pool().await;
@@ -192,6 +454,116 @@ impl Backend for PostgresBackend {
         })
     }
 
+    fn list_profile_details(
+        &self,
+        name_prefix: Option<String>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<ProfileDetails>, Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn_pool.acquire().await?;
+            let rows = sqlx::query(PROFILE_DETAILS_QUERY)
+                .bind(name_prefix)
+                .bind(offset)
+                .bind(limit)
+                .fetch_all(conn.as_mut())
+                .await
+                .map_err(err_map!(Backend, "Error fetching profile details"))?;
+            conn.return_to_pool().await;
+            rows.into_iter()
+                .map(|row| {
+                    Result::<_, Error>::Ok(ProfileDetails {
+                        name: row.try_get(0)?,
+                        created_at: row.try_get(1)?,
+                        last_access: row.try_get(2)?,
+                        item_count: row.try_get(3)?,
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn set_soft_delete(&self, enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn_pool.acquire().await?;
+            sqlx::query(CONFIG_UPDATE_QUERY)
+                .bind("soft_delete")
+                .bind(if enabled { "1" } else { "0" })
+                .execute(conn.as_mut())
+                .await
+                .map_err(err_map!(Backend, "Error setting soft deletion mode"))?;
+            conn.return_to_pool().await;
+            Ok(())
+        })
+    }
+
+    fn set_audit_log(&self, enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn_pool.acquire().await?;
+            sqlx::query(CONFIG_UPDATE_QUERY)
+                .bind("audit_log")
+                .bind(if enabled { "1" } else { "0" })
+                .execute(conn.as_mut())
+                .await
+                .map_err(err_map!(Backend, "Error setting audit log mode"))?;
+            conn.return_to_pool().await;
+            Ok(())
+        })
+    }
+
+    fn get_meta(&self, name: String) -> BoxFuture<'_, Result<Option<SecretBytes>, Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn_pool.acquire().await?;
+            let enc_value: Option<Vec<u8>> = sqlx::query_scalar(STORE_META_FETCH_QUERY)
+                .bind(&name)
+                .fetch_optional(conn.as_mut())
+                .await
+                .map_err(err_map!(Backend, "Error fetching store metadata"))?;
+            conn.return_to_pool().await;
+            if let Some(enc_value) = enc_value {
+                let store_key = self.key_cache.store_key.clone();
+                let value = unblock(move || store_key.unwrap_data(enc_value))
+                    .await
+                    .map_err(err_map!(Encryption, "Error decrypting store metadata"))?;
+                Ok(Some(value))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    fn set_meta(
+        &self,
+        name: String,
+        value: Option<SecretBytes>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            let enc_value = if let Some(value) = value {
+                let store_key = self.key_cache.store_key.clone();
+                Some(unblock(move || store_key.wrap_data(value)).await?)
+            } else {
+                None
+            };
+            let mut conn = self.conn_pool.acquire().await?;
+            if let Some(enc_value) = enc_value {
+                sqlx::query(STORE_META_UPDATE_QUERY)
+                    .bind(&name)
+                    .bind(enc_value)
+                    .execute(conn.as_mut())
+                    .await
+                    .map_err(err_map!(Backend, "Error updating store metadata"))?;
+            } else {
+                sqlx::query(STORE_META_DELETE_QUERY)
+                    .bind(&name)
+                    .execute(conn.as_mut())
+                    .await
+                    .map_err(err_map!(Backend, "Error removing store metadata"))?;
+            }
+            conn.return_to_pool().await;
+            Ok(())
+        })
+    }
+
     fn remove_profile(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
         Box::pin(async move {
             let mut conn = self.conn_pool.acquire().await?;
@@ -207,90 +579,641 @@ impl Backend for PostgresBackend {
         })
     }
 
+    fn rename_profile(&self, from: String, to: String) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async move {
+            if from == self.active_profile {
+                return Err(err_msg!(Input, "The active profile cannot be renamed"));
+            }
+            let mut conn = self.conn_pool.acquire().await?;
+            let mut txn = conn.begin().await?;
+            let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM profiles WHERE name=$1")
+                .bind(&to)
+                .fetch_optional(txn.as_mut())
+                .await
+                .map_err(err_map!(Backend, "Error checking for existing profile"))?;
+            if existing.is_some() {
+                return Err(err_msg!(Duplicate, "Duplicate profile name"));
+            }
+            let renamed = sqlx::query("UPDATE profiles SET name=$1 WHERE name=$2")
+                .bind(&to)
+                .bind(&from)
+                .execute(txn.as_mut())
+                .await
+                .map_err(err_map!(Backend, "Error renaming profile"))?
+                .rows_affected()
+                != 0;
+            if renamed {
+                sqlx::query("UPDATE config SET value=$1 WHERE name='default_profile' AND value=$2")
+                    .bind(&to)
+                    .bind(&from)
+                    .execute(txn.as_mut())
+                    .await
+                    .map_err(err_map!(Backend, "Error updating default profile name"))?;
+            }
+            txn.commit().await?;
+            conn.return_to_pool().await;
+            if renamed {
+                self.key_cache.remove_profile(&from).await;
+                self.key_cache.remove_profile(&to).await;
+            }
+            Ok(renamed)
+        })
+    }
+
     fn rekey(
         &mut self,
         method: StoreKeyMethod,
         pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.rekey_with_progress(method, pass_key, None)
+    }
+
+    fn rekey_with_progress(
+        &mut self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+        progress: Option<RekeyProgress>,
     ) -> BoxFuture<'_, Result<(), Error>> {
         let pass_key = pass_key.into_owned();
         Box::pin(async move {
-            let (store_key, store_key_ref) = unblock(move || method.resolve(pass_key)).await?;
+            let mut conn = self.conn_pool.acquire().await?;
+            let mut state = RekeyState::default();
+            for row in sqlx::query(STORE_REKEY_STATE_QUERY)
+                .fetch_all(conn.as_mut())
+                .await?
+            {
+                state.load_config(row.try_get(0)?, row.try_get(1)?)?;
+            }
+            let has_master: Option<String> = sqlx::query_scalar(CONFIG_FETCH_QUERY)
+                .bind("master_key")
+                .fetch_optional(conn.as_mut())
+                .await?;
+            if has_master.is_some() && state.key_ref.is_none() {
+                // only the master key is encrypted again, leaving the profile keys
+                // and the key slots unchanged
+                let (store_key_ref, master_key) = unblock({
+                    let store_key = self.key_cache.store_key.clone();
+                    move || rewrap_master_key(method, pass_key, &store_key)
+                })
+                .await?;
+                let mut txn = conn.begin().await?;
+                for (name, value) in [("key", store_key_ref), ("master_key", master_key)] {
+                    sqlx::query(CONFIG_UPDATE_QUERY)
+                        .bind(name)
+                        .bind(value)
+                        .execute(txn.as_mut())
+                        .await?;
+                }
+                txn.commit().await?;
+                conn.return_to_pool().await;
+                return Ok(());
+            }
+            let mut last_id = state.last_id;
+            let (store_key, store_key_ref, wrapped_key, master_key) = unblock({
+                let prev_key = self.key_cache.store_key.clone();
+                move || state.resolve_key(method, pass_key, &prev_key)
+            })
+            .await?;
             let store_key = Arc::new(store_key);
+
+            // record the new key first, so that an interrupted rekey may be resumed
+            let mut txn = conn.begin().await?;
+            for (name, value) in [
+                ("rekey_ref", store_key_ref.clone()),
+                ("rekey_key", wrapped_key),
+                ("rekey_progress", last_id.to_string()),
+            ] {
+                sqlx::query(CONFIG_UPDATE_QUERY)
+                    .bind(name)
+                    .bind(value)
+                    .execute(txn.as_mut())
+                    .await?;
+            }
+            if let Some(master_key) = master_key.as_ref() {
+                sqlx::query(CONFIG_UPDATE_QUERY)
+                    .bind("rekey_master")
+                    .bind(master_key)
+                    .execute(txn.as_mut())
+                    .await?;
+            }
+            txn.commit().await?;
+
+            let row = sqlx::query(STORE_REKEY_COUNT_QUERY)
+                .bind(last_id)
+                .fetch_one(conn.as_mut())
+                .await?;
+            let total: i64 = row.try_get(0)?;
+            let mut processed: i64 = row.try_get(1)?;
+            loop {
+                let mut txn = conn.begin().await?;
+                let rows = sqlx::query(STORE_REKEY_PROFILES_QUERY)
+                    .bind(last_id)
+                    .bind(PAGE_SIZE as i64)
+                    .fetch_all(txn.as_mut())
+                    .await?;
+                if rows.is_empty() {
+                    let mut upd_meta = BTreeMap::<String, Vec<u8>>::new();
+                    for row in sqlx::query("SELECT name, value FROM store_meta")
+                        .fetch_all(txn.as_mut())
+                        .await?
+                    {
+                        let name = row.try_get(0)?;
+                        let enc_value = row.try_get(1)?;
+                        let upd_value = unblock({
+                            let prev_key = self.key_cache.store_key.clone();
+                            let store_key = store_key.clone();
+                            move || store_key.wrap_data(prev_key.unwrap_data(enc_value)?)
+                        })
+                        .await?;
+                        upd_meta.insert(name, upd_value);
+                    }
+                    for (name, value) in upd_meta {
+                        sqlx::query("UPDATE store_meta SET value=$1 WHERE name=$2")
+                            .bind(value)
+                            .bind(name)
+                            .execute(txn.as_mut())
+                            .await?;
+                    }
+                    if sqlx::query("UPDATE config SET value=$1 WHERE name='key'")
+                        .bind(store_key_ref)
+                        .execute(txn.as_mut())
+                        .await?
+                        .rows_affected()
+                        != 1
+                    {
+                        return Err(err_msg!(Backend, "Error updating store key"));
+                    }
+                    if let Some(master_key) = master_key {
+                        sqlx::query(CONFIG_UPDATE_QUERY)
+                            .bind("master_key")
+                            .bind(master_key)
+                            .execute(txn.as_mut())
+                            .await?;
+                    }
+                    sqlx::query(STORE_REKEY_CLEAR_QUERY)
+                        .execute(txn.as_mut())
+                        .await?;
+                    txn.commit().await?;
+                    break;
+                }
+                let count = rows.len();
+                for row in rows {
+                    let pid = row.try_get(0)?;
+                    let enc_key = row.try_get(1)?;
+                    let profile_key = self.key_cache.load_key(enc_key).await?;
+                    let upd_key = unblock({
+                        let store_key = store_key.clone();
+                        move || encode_profile_key(&profile_key, &store_key)
+                    })
+                    .await?;
+                    if sqlx::query("UPDATE profiles SET profile_key=$1 WHERE id=$2")
+                        .bind(upd_key)
+                        .bind(pid)
+                        .execute(txn.as_mut())
+                        .await?
+                        .rows_affected()
+                        != 1
+                    {
+                        return Err(err_msg!(Backend, "Error updating profile key"));
+                    }
+                    last_id = pid;
+                }
+                sqlx::query(CONFIG_UPDATE_QUERY)
+                    .bind("rekey_progress")
+                    .bind(last_id.to_string())
+                    .execute(txn.as_mut())
+                    .await?;
+                txn.commit().await?;
+                processed += count as i64;
+                if let Some(progress) = progress.as_ref() {
+                    // profiles may be created while the rekey is in progress
+                    progress(processed as u64, total.max(processed) as u64);
+                }
+            }
+            conn.return_to_pool().await;
+            self.key_cache = Arc::new(KeyCache::new(store_key));
+            Ok(())
+        })
+    }
+
+    fn rekey_profile(
+        &self,
+        profile: String,
+        progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
             let mut conn = self.conn_pool.acquire().await?;
             let mut txn = conn.begin().await?;
-            let mut rows = sqlx::query("SELECT id, profile_key FROM profiles").fetch(txn.as_mut());
-            let mut upd_keys = BTreeMap::<ProfileId, Vec<u8>>::new();
-            while let Some(row) = rows.next().await {
-                let row = row?;
-                let pid = row.try_get(0)?;
-                let enc_key = row.try_get(1)?;
-                let profile_key = self.key_cache.load_key(enc_key).await?;
-                let upd_key = unblock({
-                    let store_key = store_key.clone();
-                    move || encode_profile_key(&profile_key, &store_key)
-                })
+            let store_rekey: Option<String> = sqlx::query_scalar(CONFIG_FETCH_QUERY)
+                .bind("rekey_progress")
+                .fetch_optional(txn.as_mut())
                 .await?;
-                upd_keys.insert(pid, upd_key);
+            if store_rekey.is_some() {
+                return Err(err_msg!(Busy, "A store rekey is in progress"));
             }
-            drop(rows);
-            for (pid, key) in upd_keys {
-                if sqlx::query("UPDATE profiles SET profile_key=$1 WHERE id=$2")
-                    .bind(key)
+            let row = sqlx::query("SELECT id, profile_key FROM profiles WHERE name=$1 FOR UPDATE")
+                .bind(&profile)
+                .fetch_optional(txn.as_mut())
+                .await?
+                .ok_or_else(|| err_msg!(NotFound, "Profile not found"))?;
+            let pid: ProfileId = row.try_get(0)?;
+            // search tokens cannot be derived again for records indexed before
+            // the search terms were retained
+            let indexed: bool = sqlx::query_scalar(REKEY_SEARCH_CHECK_QUERY)
+                .bind(pid)
+                .fetch_one(txn.as_mut())
+                .await?;
+            if indexed {
+                return Err(err_msg!(
+                    Unsupported,
+                    "Search terms indexed by an earlier store version must be updated \
+                    before rekeying the profile"
+                ));
+            }
+            let prev_key = Arc::new(self.key_cache.load_key(row.try_get(1)?).await?);
+            let (key, enc_key) = unblock({
+                let store_key = self.key_cache.store_key.clone();
+                move || {
+                    let key = ProfileKey::new()?;
+                    let enc_key = encode_profile_key(&key, &store_key)?;
+                    Result::<_, Error>::Ok((Arc::new(key), enc_key))
+                }
+            })
+            .await?;
+
+            let total: i64 = sqlx::query_scalar(REKEY_COUNT_QUERY)
+                .bind(pid)
+                .fetch_one(txn.as_mut())
+                .await?;
+            let mut processed = 0;
+            let mut last_id = 0;
+            loop {
+                let mut entries = BTreeMap::new();
+                for row in sqlx::query(REKEY_ITEMS_QUERY)
                     .bind(pid)
-                    .execute(txn.as_mut())
+                    .bind(last_id)
+                    .bind(PAGE_SIZE as i64)
+                    .fetch_all(txn.as_mut())
+                    .await?
+                {
+                    let id = row.try_get(0)?;
+                    entries.insert(
+                        id,
+                        EncRekeyEntry {
+                            id,
+                            category: row.try_get(1)?,
+                            name: row.try_get(2)?,
+                            value: row.try_get(3)?,
+                            tags: vec![],
+                            history: vec![],
+                            search_terms: None,
+                            search_tokens: vec![],
+                        },
+                    );
+                }
+                let count = entries.len();
+                if count == 0 {
+                    break;
+                }
+                for row in sqlx::query(REKEY_TAGS_QUERY)
+                    .bind(pid)
+                    .bind(last_id)
+                    .bind(PAGE_SIZE as i64)
+                    .fetch_all(txn.as_mut())
+                    .await?
+                {
+                    if let Some(entry) = entries.get_mut(&row.try_get::<i64, _>(0)?) {
+                        entry.tags.push(EncEntryTag {
+                            name: row.try_get(1)?,
+                            value: row.try_get(2)?,
+                            plaintext: row.try_get::<i16, _>(3)? != 0,
+                        });
+                    }
+                }
+                for row in sqlx::query(REKEY_HISTORY_QUERY)
+                    .bind(pid)
+                    .bind(last_id)
+                    .bind(PAGE_SIZE as i64)
+                    .fetch_all(txn.as_mut())
+                    .await?
+                {
+                    if let Some(entry) = entries.get_mut(&row.try_get::<i64, _>(0)?) {
+                        entry
+                            .history
+                            .push((row.try_get(1)?, row.try_get(2)?, row.try_get(3)?));
+                    }
+                }
+                for row in sqlx::query(REKEY_SEARCH_QUERY)
+                    .bind(pid)
+                    .bind(last_id)
+                    .bind(PAGE_SIZE as i64)
+                    .fetch_all(txn.as_mut())
                     .await?
-                    .rows_affected()
-                    != 1
                 {
-                    return Err(err_msg!(Backend, "Error updating profile key"));
+                    if let Some(entry) = entries.get_mut(&row.try_get::<i64, _>(0)?) {
+                        entry.search_terms.replace(row.try_get(1)?);
+                    }
                 }
+                let entries = entries.into_values().collect::<Vec<_>>();
+                last_id = entries[count - 1].id;
+                let entries = unblock({
+                    let prev_key = prev_key.clone();
+                    let key = key.clone();
+                    move || rekey_entry_batch(entries, &prev_key, &key)
+                })
+                .await?;
+                for entry in entries {
+                    sqlx::query(REKEY_ITEM_UPDATE_QUERY)
+                        .bind(entry.category)
+                        .bind(entry.name)
+                        .bind(entry.value)
+                        .bind(entry.id)
+                        .execute(txn.as_mut())
+                        .await?;
+                    sqlx::query(TAG_DELETE_QUERY)
+                        .bind(entry.id)
+                        .execute(txn.as_mut())
+                        .await?;
+                    for tag in entry.tags {
+                        sqlx::query(TAG_INSERT_QUERY)
+                            .bind(entry.id)
+                            .bind(tag.name)
+                            .bind(tag.value)
+                            .bind(tag.plaintext as i16)
+                            .execute(txn.as_mut())
+                            .await?;
+                    }
+                    for (history_id, value, tags) in entry.history {
+                        sqlx::query(REKEY_HISTORY_UPDATE_QUERY)
+                            .bind(value)
+                            .bind(tags)
+                            .bind(history_id)
+                            .execute(txn.as_mut())
+                            .await?;
+                    }
+                    if let Some(search_terms) = entry.search_terms {
+                        sqlx::query(SEARCH_DELETE_QUERY)
+                            .bind(entry.id)
+                            .execute(txn.as_mut())
+                            .await?;
+                        for token in entry.search_tokens {
+                            sqlx::query(SEARCH_INSERT_QUERY)
+                                .bind(entry.id)
+                                .bind(token)
+                                .execute(txn.as_mut())
+                                .await?;
+                        }
+                        sqlx::query(REKEY_SEARCH_UPDATE_QUERY)
+                            .bind(search_terms)
+                            .bind(entry.id)
+                            .execute(txn.as_mut())
+                            .await?;
+                    }
+                }
+                processed += count as u64;
+                if let Some(progress) = progress.as_ref() {
+                    progress(processed, total as u64);
+                }
+            }
+
+            let categories: Vec<Vec<u8>> = sqlx::query_scalar(REKEY_CATEGORIES_QUERY)
+                .bind(pid)
+                .fetch_all(txn.as_mut())
+                .await?;
+            let categories = unblock({
+                let prev_key = prev_key.clone();
+                let key = key.clone();
+                move || {
+                    categories
+                        .into_iter()
+                        .map(|enc_category| {
+                            let category = prev_key.decrypt_entry_category(enc_category.clone())?;
+                            let upd_category = key.encrypt_entry_category(
+                                ProfileKey::prepare_input(category.as_bytes()),
+                            )?;
+                            Result::<_, Error>::Ok((enc_category, upd_category))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                }
+            })
+            .await?;
+            for (enc_category, upd_category) in categories {
+                sqlx::query(REKEY_CATEGORY_UPDATE_QUERY)
+                    .bind(upd_category)
+                    .bind(pid)
+                    .bind(enc_category)
+                    .execute(txn.as_mut())
+                    .await?;
+            }
+
+            let meta = sqlx::query(REKEY_META_QUERY)
+                .bind(pid)
+                .fetch_all(txn.as_mut())
+                .await?
+                .into_iter()
+                .map(|row| {
+                    Result::<_, Error>::Ok((
+                        row.try_get::<Vec<u8>, _>(0)?,
+                        row.try_get::<Vec<u8>, _>(1)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let meta = unblock({
+                let prev_key = prev_key.clone();
+                let key = key.clone();
+                move || {
+                    meta.into_iter()
+                        .map(|(enc_name, enc_value)| {
+                            let name = prev_key.decrypt_entry_name(enc_name.clone())?;
+                            // metadata values are not associated with a record category
+                            let value =
+                                prev_key.decrypt_entry_value(b"", name.as_bytes(), enc_value)?;
+                            let upd_value = key.encrypt_entry_value(b"", name.as_bytes(), value)?;
+                            let upd_name =
+                                key.encrypt_entry_name(ProfileKey::prepare_input(name.as_bytes()))?;
+                            Result::<_, Error>::Ok((enc_name, upd_name, upd_value))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                }
+            })
+            .await?;
+            for (enc_name, upd_name, upd_value) in meta {
+                sqlx::query(REKEY_META_UPDATE_QUERY)
+                    .bind(upd_name)
+                    .bind(upd_value)
+                    .bind(pid)
+                    .bind(enc_name)
+                    .execute(txn.as_mut())
+                    .await?;
             }
-            if sqlx::query("UPDATE config SET value=$1 WHERE name='key'")
-                .bind(store_key_ref.into_uri())
+
+            if sqlx::query("UPDATE profiles SET profile_key=$1 WHERE id=$2")
+                .bind(enc_key)
+                .bind(pid)
                 .execute(txn.as_mut())
                 .await?
                 .rows_affected()
                 != 1
             {
-                return Err(err_msg!(Backend, "Error updating store key"));
+                return Err(err_msg!(Backend, "Error updating profile key"));
             }
             txn.commit().await?;
             conn.return_to_pool().await;
-            self.key_cache = Arc::new(KeyCache::new(store_key));
+            self.key_cache.add_profile(profile, pid, key).await;
             Ok(())
         })
     }
 
-    fn scan(
+    fn add_key_slot(
         &self,
-        profile: Option<String>,
-        kind: Option<EntryKind>,
-        category: Option<String>,
-        tag_filter: Option<TagFilter>,
-        offset: Option<i64>,
-        limit: Option<i64>,
-        order_by: Option<OrderBy>,
-        descending: bool,
-    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        name: String,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        let pass_key = pass_key.into_owned();
         Box::pin(async move {
-            let session = self.session(profile, false)?;
-            let mut active = session.owned_ref();
-            let (profile_id, key) = acquire_key(&mut active).await?;
-            let scan = perform_scan(
-                active,
-                profile_id,
-                key.clone(),
-                kind,
-                category.clone(),
-                tag_filter,
-                offset,
-                limit,
-                order_by,
-                descending,
-                false,
-            );
-            let stream = scan.then(move |enc_rows| {
-                let category = category.clone();
+            let (key_ref, wrapped_key) = unblock({
+                let store_key = self.key_cache.store_key.clone();
+                move || wrap_key_slot(method, pass_key, &store_key)
+            })
+            .await?;
+            let mut conn = self.conn_pool.acquire().await?;
+            let mut txn = conn.begin().await?;
+            let store_rekey: Option<String> = sqlx::query_scalar(CONFIG_FETCH_QUERY)
+                .bind("rekey_progress")
+                .fetch_optional(txn.as_mut())
+                .await?;
+            if store_rekey.is_some() {
+                return Err(err_msg!(Busy, "A store rekey is in progress"));
+            }
+            let has_master: Option<String> = sqlx::query_scalar(CONFIG_FETCH_QUERY)
+                .bind("master_key")
+                .fetch_optional(txn.as_mut())
+                .await?;
+            if has_master.is_none() {
+                return Err(err_msg!(
+                    Unsupported,
+                    "The store must be rekeyed before adding key slots"
+                ));
+            }
+            let inserted = sqlx::query(KEY_SLOT_INSERT_QUERY)
+                .bind(name)
+                .bind(key_ref)
+                .bind(wrapped_key)
+                .execute(txn.as_mut())
+                .await
+                .map_err(err_map!(Backend, "Error adding key slot"))?
+                .rows_affected()
+                != 0;
+            if !inserted {
+                return Err(err_msg!(Duplicate, "Duplicate key slot name"));
+            }
+            txn.commit().await?;
+            conn.return_to_pool().await;
+            Ok(())
+        })
+    }
+
+    fn remove_key_slot(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn_pool.acquire().await?;
+            let ret = sqlx::query("DELETE FROM key_slots WHERE name=$1")
+                .bind(&name)
+                .execute(conn.as_mut())
+                .await
+                .map_err(err_map!(Backend, "Error removing key slot"))?
+                .rows_affected()
+                != 0;
+            conn.return_to_pool().await;
+            Ok(ret)
+        })
+    }
+
+    fn list_key_slots(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn_pool.acquire().await?;
+            let names = sqlx::query_scalar("SELECT name FROM key_slots ORDER BY name")
+                .fetch_all(conn.as_mut())
+                .await
+                .map_err(err_map!(Backend, "Error fetching key slots"))?;
+            conn.return_to_pool().await;
+            Ok(names)
+        })
+    }
+
+    fn scan(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(async move {
+            let session = self.session(profile, false)?;
+            let mut active = session.owned_ref();
+            let (profile_id, key) = acquire_key(&mut active).await?;
+            let scan = perform_scan(
+                active,
+                profile_id,
+                key.clone(),
+                kind,
+                category.clone(),
+                tag_filter,
+                None,
+                offset,
+                limit,
+                order_by,
+                descending,
+                include_deleted,
+                false,
+            );
+            let stream = scan.then(move |enc_rows| {
+                let category = category.clone();
+                let key = key.clone();
+                unblock(move || decrypt_scan_batch(category, enc_rows?, &key))
+            });
+            Ok(Scan::new(stream, PAGE_SIZE))
+        })
+    }
+
+    fn scan_after(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        after: ScanPosition,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(async move {
+            let session = self.session(profile, false)?;
+            let mut active = session.owned_ref();
+            let (profile_id, key) = acquire_key(&mut active).await?;
+            let scan = perform_scan(
+                active,
+                profile_id,
+                key.clone(),
+                kind,
+                category.clone(),
+                tag_filter,
+                Some(after),
+                None,
+                limit,
+                order_by,
+                descending,
+                include_deleted,
+                false,
+            );
+            let stream = scan.then(move |enc_rows| {
+                let category = category.clone();
                 let key = key.clone();
                 unblock(move || decrypt_scan_batch(category, enc_rows?, &key))
             });
@@ -298,6 +1221,103 @@ impl Backend for PostgresBackend {
         })
     }
 
+    fn seal_token(&self, data: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        let store_key = self.key_cache.store_key.clone();
+        Box::pin(async move { unblock(move || store_key.seal_token(&data)).await })
+    }
+
+    fn open_token(&self, token: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        let store_key = self.key_cache.store_key.clone();
+        Box::pin(async move { unblock(move || store_key.open_token(&token)).await })
+    }
+
+    fn subscribe(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+    ) -> BoxFuture<'_, Result<Subscription, Error>> {
+        Box::pin(async move {
+            let mut session = self.session(profile, false)?;
+            let (profile_id, key) = acquire_key(&mut session).await?;
+            drop(session);
+            let enc_category = unblock({
+                let key = key.clone();
+                move || {
+                    category
+                        .map(|c| {
+                            key.encrypt_entry_category(ProfileKey::prepare_input(c.as_bytes()))
+                        })
+                        .transpose()
+                }
+            })
+            .await?;
+            let schema: String = sqlx::query_scalar("SELECT current_schema()")
+                .fetch_one(&self.conn_pool)
+                .await
+                .map_err(err_map!(Backend, "Error fetching current schema"))?;
+            install_changes_trigger(&self.conn_pool).await?;
+            let mut listener = PgListener::connect_with(&self.conn_pool)
+                .await
+                .map_err(err_map!(Backend, "Error connecting change listener"))?;
+            listener
+                .listen(CHANGES_CHANNEL)
+                .await
+                .map_err(err_map!(Backend, "Error listening for changes"))?;
+            let pool = self.conn_pool.clone();
+            let stream = try_stream! {
+                loop {
+                    let notification = listener
+                        .recv()
+                        .await
+                        .map_err(err_map!(Backend, "Error receiving change notification"))?;
+                    let change: ChangeNotification = serde_json::from_str(notification.payload())
+                        .map_err(err_map!(Unexpected, "Invalid change notification"))?;
+                    if change.schema != schema {
+                        continue;
+                    }
+                    let mut conn = pool.acquire().await?;
+                    let row = sqlx::query(CHANGE_FETCH_QUERY)
+                        .bind(change.id)
+                        .fetch_optional(conn.as_mut())
+                        .await
+                        .map_err(err_map!(Backend, "Error fetching change record"))?;
+                    drop(conn);
+                    let row = if let Some(row) = row {
+                        row
+                    } else {
+                        warn!("Change record {} has expired", change.id);
+                        continue;
+                    };
+                    let change_kind: i16 = row.try_get(1)?;
+                    let category: Vec<u8> = row.try_get(2)?;
+                    if row.try_get::<ProfileId, _>(0)? != profile_id
+                        || kind.map(|k| k as i16 != change_kind).unwrap_or(false)
+                        || enc_category.as_ref().map(|c| *c != category).unwrap_or(false)
+                    {
+                        continue;
+                    }
+                    let tags = match row.try_get::<Option<String>, _>(5)? {
+                        Some(tags) => decode_tags(tags.into_bytes())
+                            .map_err(|_| err_msg!(Unexpected, "Error decoding tags"))?,
+                        None => Vec::new(),
+                    };
+                    let change = EncChangeEvent {
+                        operation: change_operation(row.try_get(4)?)?,
+                        profile_id,
+                        kind: EntryKind::try_from(change_kind as usize)?,
+                        category,
+                        name: row.try_get(3)?,
+                        tags,
+                    };
+                    let key = key.clone();
+                    yield unblock(move || decrypt_change_event(change, &key)).await?;
+                }
+            };
+            Ok(Subscription::new(stream))
+        })
+    }
+
     fn session(&self, profile: Option<String>, transaction: bool) -> Result<Self::Session, Error> {
         Ok(DbSession::new(
             self.conn_pool.clone(),
@@ -331,6 +1351,7 @@ impl BackendSession for DbSession<Postgres> {
         kind: Option<EntryKind>,
         category: Option<&'q str>,
         tag_filter: Option<TagFilter>,
+        include_deleted: bool,
     ) -> BoxFuture<'q, Result<i64, Error>> {
         let enc_category = category.map(|c| ProfileKey::prepare_input(c.as_bytes()));
 
@@ -353,7 +1374,11 @@ impl BackendSession for DbSession<Postgres> {
             .await?;
             params.push(enc_category);
             let query = extend_query::<PostgresBackend>(
-                COUNT_QUERY,
+                if include_deleted {
+                    COUNT_ALL_QUERY
+                } else {
+                    COUNT_QUERY
+                },
                 &mut params,
                 tag_filter,
                 None,
@@ -370,6 +1395,115 @@ impl BackendSession for DbSession<Postgres> {
         })
     }
 
+    fn count_by_category<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<BTreeMap<String, i64>, Error>> {
+        Box::pin(async move {
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let mut params = QueryParams::new();
+            params.push(profile_id);
+            params.push(kind.map(|k| k as i16));
+            let tag_filter = unblock({
+                let key = key.clone();
+                let params_len = params.len();
+                move || encode_tag_filter::<PostgresBackend>(tag_filter, &key, params_len)
+            })
+            .await?;
+            let mut query = extend_query::<PostgresBackend>(
+                COUNT_CATEGORY_QUERY,
+                &mut params,
+                tag_filter,
+                None,
+                None,
+                None,
+                false,
+            )?;
+            query.push_str(" GROUP BY category");
+            let mut active = acquire_session(&mut *self).await?;
+            let rows: Vec<(Vec<u8>, i64)> = sqlx::query_as_with(query.as_str(), params)
+                .fetch_all(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error performing count query"))?;
+            unblock(move || {
+                rows.into_iter()
+                    .map(|(enc_category, count)| {
+                        Result::<_, Error>::Ok((key.decrypt_entry_category(enc_category)?, count))
+                    })
+                    .collect::<Result<BTreeMap<_, _>, Error>>()
+            })
+            .await
+        })
+    }
+
+    fn list_tag_values<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        tag_name: &'q str,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<BTreeMap<String, i64>, Error>> {
+        let (name, plaintext) = match tag_name.strip_prefix('~') {
+            Some(name) => (name, true),
+            None => (tag_name, false),
+        };
+        let enc_category = ProfileKey::prepare_input(category.as_bytes());
+        let enc_name = ProfileKey::prepare_input(name.as_bytes());
+
+        Box::pin(async move {
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let mut params = QueryParams::new();
+            params.push(profile_id);
+            params.push(kind as i16);
+            let (enc_category, enc_name, tag_filter) = unblock({
+                let key = key.clone();
+                let params_len = params.len() + 3; // plus category, tag name and plaintext
+                move || {
+                    Result::<_, Error>::Ok((
+                        key.encrypt_entry_category(enc_category)?,
+                        key.encrypt_tag_name(enc_name)?,
+                        encode_tag_filter::<PostgresBackend>(tag_filter, &key, params_len)?,
+                    ))
+                }
+            })
+            .await?;
+            params.push(enc_category);
+            params.push(enc_name);
+            params.push(plaintext as i16);
+            let mut query = extend_query::<PostgresBackend>(
+                TAG_VALUES_QUERY,
+                &mut params,
+                tag_filter,
+                None,
+                None,
+                None,
+                false,
+            )?;
+            query.push_str(" GROUP BY it.value");
+            let mut active = acquire_session(&mut *self).await?;
+            let rows: Vec<(Vec<u8>, i64)> = sqlx::query_as_with(query.as_str(), params)
+                .fetch_all(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error fetching tag values"))?;
+            unblock(move || {
+                rows.into_iter()
+                    .map(|(value, count)| {
+                        let value = if plaintext {
+                            value
+                        } else {
+                            key.decrypt_tag_value(value)?.into_vec()
+                        };
+                        let value = String::from_utf8(value)
+                            .map_err(err_map!(Encryption, "Invalid tag value"))?;
+                        Result::<_, Error>::Ok((value, count))
+                    })
+                    .collect::<Result<BTreeMap<_, _>, Error>>()
+            })
+            .await
+        })
+    }
+
     fn fetch(
         &mut self,
         kind: EntryKind,
@@ -410,6 +1544,10 @@ impl BackendSession for DbSession<Postgres> {
             {
                 let value = row.try_get(1)?;
                 let tags = row.try_get::<Option<String>, _>(2)?.map(String::into_bytes);
+                let version: Option<i64> = row.try_get(3)?;
+                let created_at: Option<i64> = row.try_get(4)?;
+                let updated_at: Option<i64> = row.try_get(5)?;
+                let expires_at: Option<i64> = row.try_get(6)?;
                 let (category, name, value, tags) = unblock(move || {
                     let value = key.decrypt_entry_value(category.as_ref(), name.as_ref(), value)?;
                     let tags = if let Some(enc_tags) = tags {
@@ -423,13 +1561,96 @@ impl BackendSession for DbSession<Postgres> {
                     Result::<_, Error>::Ok((category, name, value, tags))
                 })
                 .await?;
-                Ok(Some(Entry::new(kind, category, name, value, tags)))
+                Ok(Some(
+                    Entry::new(kind, category, name, value, tags)
+                        .with_version(version)
+                        .with_timestamps(created_at, updated_at)
+                        .with_expiry(expires_at),
+                ))
             } else {
                 Ok(None)
             }
         })
     }
 
+    fn fetch_many<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        names: &'q [String],
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Vec<Option<Entry>>, Error>> {
+        let category = category.to_string();
+
+        Box::pin(async move {
+            if names.is_empty() {
+                return Ok(Vec::new());
+            }
+            let for_update = for_update && self.in_transaction();
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let mut params = QueryParams::new();
+            params.push(profile_id);
+            params.push(Some(kind as i16));
+            let (enc_category, name_filter) = unblock({
+                let key = key.clone();
+                let category = ProfileKey::prepare_input(category.as_bytes());
+                let names = names.to_vec();
+                let params_len = params.len() + 1; // plus category
+                move || {
+                    Result::<_, Error>::Ok((
+                        key.encrypt_entry_category(category)?,
+                        encode_name_filter::<PostgresBackend>(&key, &names, params_len)?,
+                    ))
+                }
+            })
+            .await?;
+            params.push(Some(enc_category));
+            let mut query = extend_query::<PostgresBackend>(
+                SCAN_QUERY,
+                &mut params,
+                Some(name_filter),
+                None,
+                None,
+                None,
+                false,
+            )?;
+            if for_update {
+                query.push_str(" FOR NO KEY UPDATE");
+            }
+            let mut active = acquire_session(&mut *self).await?;
+            let rows = sqlx::query_with(query.as_str(), params)
+                .fetch_all(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error performing fetch query"))?;
+            let enc_rows = rows
+                .into_iter()
+                .map(|row| {
+                    Result::<_, Error>::Ok(EncScanEntry {
+                        id: None,
+                        kind,
+                        category: row.try_get(2)?,
+                        name: row.try_get(3)?,
+                        value: row.try_get(4)?,
+                        tags: row
+                            .try_get::<Option<String>, _>(5)?
+                            .map(String::into_bytes)
+                            .unwrap_or_default(),
+                        version: row.try_get(6)?,
+                        created_at: row.try_get(7)?,
+                        updated_at: row.try_get(8)?,
+                        deleted_at: None,
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let found = unblock(move || decrypt_scan_batch(Some(category), enc_rows, &key))
+                .await?
+                .into_iter()
+                .map(|entry| (entry.name.clone(), entry))
+                .collect::<BTreeMap<_, _>>();
+            Ok(names.iter().map(|name| found.get(name).cloned()).collect())
+        })
+    }
+
     fn fetch_all<'q>(
         &'q mut self,
         kind: Option<EntryKind>,
@@ -453,9 +1674,11 @@ impl BackendSession for DbSession<Postgres> {
                 category.clone(),
                 tag_filter,
                 None,
+                None,
                 limit,
                 order_by,
                 descending,
+                false,
                 for_update,
             );
             pin!(scan);
@@ -492,9 +1715,16 @@ impl BackendSession for DbSession<Postgres> {
                 }
             })
             .await?;
-            params.push(enc_category);
+            params.push(enc_category.clone());
+
+            let mut active = acquire_session(&mut *self).await?;
+            let soft_delete = soft_delete_enabled(active.connection_mut()).await?;
             let query = extend_query::<PostgresBackend>(
-                DELETE_ALL_QUERY,
+                if soft_delete {
+                    SOFT_DELETE_ALL_QUERY
+                } else {
+                    DELETE_ALL_QUERY
+                },
                 &mut params,
                 tag_filter,
                 None,
@@ -502,79 +1732,614 @@ impl BackendSession for DbSession<Postgres> {
                 None,
                 false,
             )?;
-
-            let mut active = acquire_session(&mut *self).await?;
             let removed = sqlx::query_with(query.as_str(), params)
                 .execute(active.connection_mut())
                 .await?
                 .rows_affected();
+            if removed > 0 {
+                let audit = active.audit_session();
+                record_audit(
+                    active.connection_mut(),
+                    profile_id,
+                    &audit,
+                    "remove_all",
+                    kind,
+                    enc_category.as_deref(),
+                    None,
+                )
+                .await?;
+            }
             Ok(removed as i64)
         })
     }
 
-    fn update<'q>(
+    fn remove_expired<'q>(
         &'q mut self,
-        kind: EntryKind,
-        operation: EntryOperation,
-        category: &'q str,
-        name: &'q str,
-        value: Option<&'q [u8]>,
-        tags: Option<&'q [EntryTag]>,
-        expiry_ms: Option<i64>,
-    ) -> BoxFuture<'q, Result<(), Error>> {
-        let category = ProfileKey::prepare_input(category.as_bytes());
-        let name = ProfileKey::prepare_input(name.as_bytes());
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        let enc_category = category.map(|c| ProfileKey::prepare_input(c.as_bytes()));
 
-        match operation {
-            op @ EntryOperation::Insert | op @ EntryOperation::Replace => {
-                let value = ProfileKey::prepare_input(value.unwrap_or_default());
-                let tags = tags.map(prepare_tags);
-                Box::pin(async move {
-                    let (_, key) = acquire_key(&mut *self).await?;
-                    let (enc_category, enc_name, enc_value, enc_tags) = unblock(move || {
-                        let enc_value =
-                            key.encrypt_entry_value(category.as_ref(), name.as_ref(), value)?;
-                        Result::<_, Error>::Ok((
-                            key.encrypt_entry_category(category)?,
-                            key.encrypt_entry_name(name)?,
-                            enc_value,
-                            tags.transpose()?
-                                .map(|t| key.encrypt_entry_tags(t))
-                                .transpose()?,
-                        ))
-                    })
-                    .await?;
-                    let mut active = acquire_session(&mut *self).await?;
-                    let mut txn = active.as_transaction().await?;
+        Box::pin(async move {
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let enc_category = unblock(move || {
+                enc_category
+                    .map(|c| key.encrypt_entry_category(c))
+                    .transpose()
+            })
+            .await?;
+            let mut active = acquire_session(&mut *self).await?;
+            let removed = sqlx::query(DELETE_EXPIRED_QUERY)
+                .bind(profile_id)
+                .bind(kind.map(|k| k as i16))
+                .bind(enc_category.as_deref())
+                .execute(active.connection_mut())
+                .await?
+                .rows_affected();
+            if removed > 0 {
+                let audit = active.audit_session();
+                record_audit(
+                    active.connection_mut(),
+                    profile_id,
+                    &audit,
+                    "remove_expired",
+                    kind,
+                    enc_category.as_deref(),
+                    None,
+                )
+                .await?;
+            }
+            Ok(removed as i64)
+        })
+    }
+
+    fn restore<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        let category = ProfileKey::prepare_input(category.as_bytes());
+        let name = ProfileKey::prepare_input(name.as_bytes());
+
+        Box::pin(async move {
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let (enc_category, enc_name) = unblock(move || {
+                Result::<_, Error>::Ok((
+                    key.encrypt_entry_category(category)?,
+                    key.encrypt_entry_name(name)?,
+                ))
+            })
+            .await?;
+            let mut active = acquire_session(&mut *self).await?;
+            let row_id: i64 = sqlx::query_scalar(RESTORE_QUERY)
+                .bind(profile_id)
+                .bind(kind as i16)
+                .bind(enc_category.as_slice())
+                .bind(enc_name.as_slice())
+                .fetch_optional(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error restoring entry"))?
+                .ok_or_else(|| err_msg!(NotFound, "Deleted entry not found"))?;
+            let audit = active.audit_session();
+            record_audit(
+                active.connection_mut(),
+                profile_id,
+                &audit,
+                "restore",
+                Some(kind),
+                Some(&enc_category),
+                Some(row_id),
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn purge<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        older_than_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        Box::pin(async move {
+            let deleted_before = older_than_ms.map(|ms| expiry_timestamp(-ms)).transpose()?;
+            let mut active = acquire_session(&mut *self).await?;
+            let removed = sqlx::query(PURGE_QUERY)
+                .bind(active.profile_id)
+                .bind(kind.map(|k| k as i16))
+                .bind(deleted_before)
+                .execute(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error purging deleted entries"))?
+                .rows_affected();
+            if removed > 0 {
+                let audit = active.audit_session();
+                let profile_id = active.profile_id;
+                record_audit(
+                    active.connection_mut(),
+                    profile_id,
+                    &audit,
+                    "purge",
+                    kind,
+                    None,
+                    None,
+                )
+                .await?;
+            }
+            Ok(removed as i64)
+        })
+    }
+
+    fn set_history<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        enabled: bool,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        let category = ProfileKey::prepare_input(category.as_bytes());
+
+        Box::pin(async move {
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let enc_category = unblock(move || key.encrypt_entry_category(category)).await?;
+            let mut active = acquire_session(&mut *self).await?;
+            sqlx::query(if enabled {
+                HISTORY_ENABLE_QUERY
+            } else {
+                HISTORY_DISABLE_QUERY
+            })
+            .bind(profile_id)
+            .bind(kind as i16)
+            .bind(enc_category.as_slice())
+            .execute(active.connection_mut())
+            .await
+            .map_err(err_map!(Backend, "Error updating record history setting"))?;
+            let audit = active.audit_session();
+            record_audit(
+                active.connection_mut(),
+                profile_id,
+                &audit,
+                if enabled {
+                    "enable_history"
+                } else {
+                    "disable_history"
+                },
+                Some(kind),
+                Some(&enc_category),
+                None,
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn fetch_history<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        limit: Option<i64>,
+    ) -> BoxFuture<'q, Result<Vec<EntryVersion>, Error>> {
+        fetch_entry_history(self, kind, category, name, None, limit)
+    }
+
+    fn fetch_version<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        version: i64,
+    ) -> BoxFuture<'q, Result<Option<EntryVersion>, Error>> {
+        Box::pin(async move {
+            let mut versions =
+                fetch_entry_history(self, kind, category, name, Some(version), Some(1)).await?;
+            Ok(versions.pop())
+        })
+    }
+
+    fn prune_history<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        older_than_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        let enc_category = category.map(|c| ProfileKey::prepare_input(c.as_bytes()));
+
+        Box::pin(async move {
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let enc_category = unblock(move || {
+                enc_category
+                    .map(|c| key.encrypt_entry_category(c))
+                    .transpose()
+            })
+            .await?;
+            let replaced_before =
+                older_than_ms.map(|ms| chrono::Utc::now().timestamp_millis() - ms);
+            let mut active = acquire_session(&mut *self).await?;
+            let removed = sqlx::query(HISTORY_PRUNE_QUERY)
+                .bind(profile_id)
+                .bind(kind.map(|k| k as i16))
+                .bind(enc_category.as_deref())
+                .bind(replaced_before)
+                .execute(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error pruning record history"))?
+                .rows_affected();
+            if removed > 0 {
+                let audit = active.audit_session();
+                record_audit(
+                    active.connection_mut(),
+                    profile_id,
+                    &audit,
+                    "prune_history",
+                    kind,
+                    enc_category.as_deref(),
+                    None,
+                )
+                .await?;
+            }
+            Ok(removed as i64)
+        })
+    }
+
+    fn fetch_audit_log(
+        &mut self,
+        after_id: Option<i64>,
+        limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<AuditRecord>, Error>> {
+        Box::pin(async move {
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let mut active = acquire_session(&mut *self).await?;
+            let audit = active.audit_session();
+            let prev_mac: Option<Vec<u8>> = match after_id {
+                Some(after_id) => sqlx::query_scalar(AUDIT_LATEST_QUERY)
+                    .bind(profile_id)
+                    .bind(after_id)
+                    .fetch_optional(active.connection_mut())
+                    .await
+                    .map_err(err_map!(Backend, "Error fetching audit log"))?,
+                None => None,
+            };
+            let rows = sqlx::query(AUDIT_FETCH_QUERY)
+                .bind(profile_id)
+                .bind(after_id)
+                .bind(limit)
+                .fetch_all(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error fetching audit log"))?;
+            let enc_rows = rows
+                .into_iter()
+                .map(|row| {
+                    let kind: Option<i16> = row.try_get(3)?;
+                    Result::<_, Error>::Ok((
+                        EncAuditRecord {
+                            id: row.try_get(0)?,
+                            profile_id,
+                            session_id: row.try_get(1)?,
+                            operation: row.try_get(2)?,
+                            kind: kind.map(|k| EntryKind::try_from(k as usize)).transpose()?,
+                            category: row.try_get(4)?,
+                            item_id: row.try_get(5)?,
+                            created_at: row.try_get(6)?,
+                        },
+                        row.try_get::<Vec<u8>, _>(7)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            unblock(move || verify_audit_records(enc_rows, prev_mac, &audit.store_key, &key)).await
+        })
+    }
+
+    fn get_profile_meta<'q>(
+        &'q mut self,
+        name: &'q str,
+    ) -> BoxFuture<'q, Result<Option<SecretBytes>, Error>> {
+        let name = ProfileKey::prepare_input(name.as_bytes());
+
+        Box::pin(async move {
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let enc_name = unblock({
+                let key = key.clone();
+                let name = name.clone();
+                move || key.encrypt_entry_name(name)
+            })
+            .await?;
+            let mut active = acquire_session(&mut *self).await?;
+            let enc_value: Option<Vec<u8>> = sqlx::query_scalar(PROFILE_META_FETCH_QUERY)
+                .bind(profile_id)
+                .bind(enc_name)
+                .fetch_optional(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error fetching profile metadata"))?;
+            if let Some(enc_value) = enc_value {
+                // metadata values are not associated with a record category
+                let value =
+                    unblock(move || key.decrypt_entry_value(b"", name.as_ref(), enc_value)).await?;
+                Ok(Some(value))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    fn set_profile_meta<'q>(
+        &'q mut self,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        let name = ProfileKey::prepare_input(name.as_bytes());
+        let value = value.map(ProfileKey::prepare_input);
+
+        Box::pin(async move {
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let (enc_name, enc_value) = unblock(move || {
+                let enc_value = value
+                    .map(|value| key.encrypt_entry_value(b"", name.as_ref(), value))
+                    .transpose()?;
+                Result::<_, Error>::Ok((key.encrypt_entry_name(name)?, enc_value))
+            })
+            .await?;
+            let mut active = acquire_session(&mut *self).await?;
+            if let Some(enc_value) = enc_value {
+                sqlx::query(PROFILE_META_UPDATE_QUERY)
+                    .bind(profile_id)
+                    .bind(enc_name)
+                    .bind(enc_value)
+                    .execute(active.connection_mut())
+                    .await
+                    .map_err(err_map!(Backend, "Error updating profile metadata"))?;
+            } else {
+                sqlx::query(PROFILE_META_DELETE_QUERY)
+                    .bind(profile_id)
+                    .bind(enc_name)
+                    .execute(active.connection_mut())
+                    .await
+                    .map_err(err_map!(Backend, "Error removing profile metadata"))?;
+            }
+            Ok(())
+        })
+    }
+
+    fn update<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        update_entry(
+            self, kind, operation, category, name, value, tags, expiry_ms, None,
+        )
+    }
+
+    fn update_versioned<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+        version: i64,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        if !matches!(operation, EntryOperation::Replace | EntryOperation::Remove) {
+            return Box::pin(std::future::ready(Err(err_msg!(
+                Input,
+                "Versioned updates must replace or remove a record"
+            ))));
+        }
+        update_entry(
+            self,
+            kind,
+            operation,
+            category,
+            name,
+            value,
+            tags,
+            expiry_ms,
+            Some(version),
+        )
+    }
+
+    fn update_many<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        updates: Vec<EntryUpdate>,
+    ) -> BoxFuture<'q, Result<Vec<Result<(), Error>>, Error>> {
+        Box::pin(async move {
+            let (_, key) = acquire_key(&mut *self).await?;
+            let enc_updates = unblock(move || encrypt_entry_updates(updates, &key)).await?;
+            let mut active = acquire_session(&mut *self).await?;
+            let mut txn = active.as_transaction().await?;
+            let mut results = Vec::with_capacity(enc_updates.len());
+            for upd in enc_updates {
+                let result = if upd.operation == EntryOperation::Remove {
+                    let profile_id = txn.profile_id;
+                    let audit = txn.audit_session();
+                    perform_remove(
+                        txn.connection_mut(),
+                        profile_id,
+                        &audit,
+                        kind,
+                        &upd.category,
+                        &upd.name,
+                        None,
+                        false,
+                    )
+                    .await
+                } else {
                     perform_insert(
                         &mut txn,
                         kind,
-                        &enc_category,
-                        &enc_name,
-                        &enc_value,
-                        enc_tags,
-                        expiry_ms,
-                        op == EntryOperation::Insert,
+                        &upd.category,
+                        &upd.name,
+                        &upd.value,
+                        upd.tags,
+                        upd.expiry_ms,
+                        upd.operation,
+                        None,
                     )
-                    .await?;
-                    txn.commit().await?;
-                    Ok(())
-                })
+                    .await
+                };
+                results.push(batch_result(result)?);
             }
+            txn.commit().await?;
+            Ok(results)
+        })
+    }
 
-            EntryOperation::Remove => Box::pin(async move {
-                let (_, key) = acquire_key(&mut *self).await?;
-                let (enc_category, enc_name) = unblock(move || {
+    fn update_search_terms<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        terms: &'q [String],
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        let terms = terms.to_vec();
+
+        Box::pin(async move {
+            let (_, key) = acquire_key(&mut *self).await?;
+            let (enc_category, enc_name, tokens, enc_terms) = unblock({
+                let category = category.to_string();
+                let name = name.to_string();
+                move || {
+                    let enc_terms = if terms.is_empty() {
+                        None
+                    } else {
+                        Some(encrypt_search_terms(&key, &category, &name, &terms)?)
+                    };
                     Result::<_, Error>::Ok((
-                        key.encrypt_entry_category(category)?,
-                        key.encrypt_entry_name(name)?,
+                        key.encrypt_entry_category(ProfileKey::prepare_input(category.as_bytes()))?,
+                        key.encrypt_entry_name(ProfileKey::prepare_input(name.as_bytes()))?,
+                        search_tokens(&key, &category, &terms)?,
+                        enc_terms,
                     ))
+                }
+            })
+            .await?;
+            let mut active = acquire_session(&mut *self).await?;
+            let mut txn = active.as_transaction().await?;
+            let row_id: i64 = sqlx::query_scalar(ITEM_ID_QUERY)
+                .bind(txn.profile_id)
+                .bind(kind as i16)
+                .bind(enc_category.as_slice())
+                .bind(enc_name.as_slice())
+                .fetch_optional(txn.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error fetching entry"))?
+                .ok_or_else(|| err_msg!(NotFound, "Entry not found"))?;
+            sqlx::query(SEARCH_DELETE_QUERY)
+                .bind(row_id)
+                .execute(txn.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error removing existing search terms"))?;
+            for token in tokens {
+                sqlx::query(SEARCH_INSERT_QUERY)
+                    .bind(row_id)
+                    .bind(token)
+                    .execute(txn.connection_mut())
+                    .await
+                    .map_err(err_map!(Backend, "Error inserting search terms"))?;
+            }
+            sqlx::query(SEARCH_TERMS_DELETE_QUERY)
+                .bind(row_id)
+                .execute(txn.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error removing existing search terms"))?;
+            if let Some(enc_terms) = enc_terms {
+                sqlx::query(SEARCH_TERMS_INSERT_QUERY)
+                    .bind(row_id)
+                    .bind(enc_terms)
+                    .execute(txn.connection_mut())
+                    .await
+                    .map_err(err_map!(Backend, "Error inserting search terms"))?;
+            }
+            let audit = txn.audit_session();
+            let profile_id = txn.profile_id;
+            record_audit(
+                txn.connection_mut(),
+                profile_id,
+                &audit,
+                "update_search_terms",
+                Some(kind),
+                Some(&enc_category),
+                Some(row_id),
+            )
+            .await?;
+            txn.commit().await?;
+            Ok(())
+        })
+    }
+
+    fn search_values<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        terms: &'q [String],
+        limit: Option<i64>,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        let category = category.to_string();
+        let terms = terms.to_vec();
+
+        Box::pin(async move {
+            let (profile_id, key) = acquire_key(&mut *self).await?;
+            let mut params = QueryParams::new();
+            params.push(profile_id);
+            params.push(Some(kind as i16));
+            let (enc_category, search_filter) = unblock({
+                let key = key.clone();
+                let category = category.clone();
+                let params_len = params.len() + 1; // plus category
+                move || {
+                    Result::<_, Error>::Ok((
+                        key.encrypt_entry_category(ProfileKey::prepare_input(category.as_bytes()))?,
+                        encode_search_filter::<PostgresBackend>(
+                            &key, &category, &terms, params_len,
+                        )?,
+                    ))
+                }
+            })
+            .await?;
+            params.push(Some(enc_category));
+            let query = extend_query::<PostgresBackend>(
+                SCAN_QUERY,
+                &mut params,
+                Some(search_filter),
+                None,
+                limit,
+                Some(EncOrderBy::Id),
+                false,
+            )?;
+            let mut active = acquire_session(&mut *self).await?;
+            let rows = sqlx::query_with(query.as_str(), params)
+                .fetch_all(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error performing search query"))?;
+            let enc_rows = rows
+                .into_iter()
+                .map(|row| {
+                    Result::<_, Error>::Ok(EncScanEntry {
+                        id: None,
+                        kind,
+                        category: row.try_get(2)?,
+                        name: row.try_get(3)?,
+                        value: row.try_get(4)?,
+                        tags: row
+                            .try_get::<Option<String>, _>(5)?
+                            .map(String::into_bytes)
+                            .unwrap_or_default(),
+                        version: row.try_get(6)?,
+                        created_at: row.try_get(7)?,
+                        updated_at: row.try_get(8)?,
+                        deleted_at: None,
+                    })
                 })
-                .await?;
-                let mut active = acquire_session(&mut *self).await?;
-                perform_remove(&mut active, kind, &enc_category, &enc_name, false).await
-            }),
-        }
+                .collect::<Result<Vec<_>, Error>>()?;
+            unblock(move || decrypt_scan_batch(Some(category), enc_rows, &key)).await
+        })
     }
 
     fn ping(&mut self) -> BoxFuture<'_, Result<(), Error>> {
@@ -604,7 +2369,22 @@ impl BackendSession for DbSession<Postgres> {
     }
 }
 
-impl ExtDatabase for Postgres {}
+impl ExtDatabase for Postgres {
+    fn record_profile_access(
+        conn: &mut Connection<Self>,
+        profile_id: ProfileId,
+        now_ms: i64,
+    ) -> BoxFuture<'_, std::result::Result<(), SqlxError>> {
+        Box::pin(async move {
+            sqlx::query(PROFILE_ACCESS_QUERY)
+                .bind(now_ms)
+                .bind(profile_id)
+                .execute(conn)
+                .await?;
+            Ok(())
+        })
+    }
+}
 
 impl QueryPrepare for PostgresBackend {
     type DB = Postgres;
@@ -652,7 +2432,7 @@ async fn resolve_profile_key(
     profile: String,
     in_txn: bool,
 ) -> Result<(ProfileId, Arc<ProfileKey>), Error> {
-    if let Some((pid, key)) = cache.get_profile(profile.as_str()).await {
+    let (pid, key) = if let Some((pid, key)) = cache.get_profile(profile.as_str()).await {
         if in_txn {
             // lock the profile row to prevent it from being removed
             let check: Option<i64> =
@@ -664,7 +2444,7 @@ async fn resolve_profile_key(
                 return Err(err_msg!(NotFound, "Session profile has been removed"));
             }
         }
-        Ok((pid, key))
+        (pid, key)
     } else if let Some(row) =
         sqlx::query("SELECT id, profile_key FROM profiles WHERE name=$1 FOR NO KEY UPDATE")
             .bind(profile.as_str())
@@ -674,9 +2454,92 @@ async fn resolve_profile_key(
         let pid = row.try_get(0)?;
         let key = Arc::new(cache.load_key(row.try_get(1)?).await?);
         cache.add_profile(profile, pid, key.clone()).await;
-        Ok((pid, key))
+        (pid, key)
     } else {
-        Err(err_msg!(NotFound, "Profile not found"))
+        return Err(err_msg!(NotFound, "Profile not found"));
+    };
+    Ok((pid, key))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_entry<'q>(
+    session: &'q mut DbSession<Postgres>,
+    kind: EntryKind,
+    operation: EntryOperation,
+    category: &'q str,
+    name: &'q str,
+    value: Option<&'q [u8]>,
+    tags: Option<&'q [EntryTag]>,
+    expiry_ms: Option<i64>,
+    version: Option<i64>,
+) -> BoxFuture<'q, Result<(), Error>> {
+    let category = ProfileKey::prepare_input(category.as_bytes());
+    let name = ProfileKey::prepare_input(name.as_bytes());
+
+    match operation {
+        op @ (EntryOperation::Insert
+        | EntryOperation::Replace
+        | EntryOperation::InsertOrReplace) => {
+            let value = ProfileKey::prepare_input(value.unwrap_or_default());
+            let tags = tags.map(prepare_tags);
+            Box::pin(async move {
+                let (_, key) = acquire_key(&mut *session).await?;
+                let (enc_category, enc_name, enc_value, enc_tags) = unblock(move || {
+                    let enc_value =
+                        key.encrypt_entry_value(category.as_ref(), name.as_ref(), value)?;
+                    Result::<_, Error>::Ok((
+                        key.encrypt_entry_category(category)?,
+                        key.encrypt_entry_name(name)?,
+                        enc_value,
+                        tags.transpose()?
+                            .map(|t| key.encrypt_entry_tags(t))
+                            .transpose()?,
+                    ))
+                })
+                .await?;
+                let mut active = acquire_session(&mut *session).await?;
+                let mut txn = active.as_transaction().await?;
+                perform_insert(
+                    &mut txn,
+                    kind,
+                    &enc_category,
+                    &enc_name,
+                    &enc_value,
+                    enc_tags,
+                    expiry_ms,
+                    op,
+                    version,
+                )
+                .await?;
+                txn.commit().await?;
+                Ok(())
+            })
+        }
+
+        EntryOperation::Remove => Box::pin(async move {
+            let (_, key) = acquire_key(&mut *session).await?;
+            let (enc_category, enc_name) = unblock(move || {
+                Result::<_, Error>::Ok((
+                    key.encrypt_entry_category(category)?,
+                    key.encrypt_entry_name(name)?,
+                ))
+            })
+            .await?;
+            let mut active = acquire_session(&mut *session).await?;
+            let profile_id = active.profile_id;
+            let audit = active.audit_session();
+            perform_remove(
+                active.connection_mut(),
+                profile_id,
+                &audit,
+                kind,
+                &enc_category,
+                &enc_name,
+                version,
+                false,
+            )
+            .await
+        }),
     }
 }
 
@@ -689,32 +2552,96 @@ async fn perform_insert(
     enc_value: &[u8],
     enc_tags: Option<Vec<EncEntryTag>>,
     expiry_ms: Option<i64>,
-    new_row: bool,
+    operation: EntryOperation,
+    version: Option<i64>,
 ) -> Result<(), Error> {
-    let row_id = if new_row {
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let row_id = if operation == EntryOperation::Insert {
         trace!("Insert entry");
-        sqlx::query_scalar(INSERT_QUERY)
+        let expiry = expiry_ms.map(expiry_timestamp).transpose()?;
+        let row_id = sqlx::query_scalar(INSERT_QUERY)
             .bind(active.profile_id)
             .bind(kind as i16)
             .bind(enc_category)
             .bind(enc_name)
             .bind(enc_value)
-            .bind(expiry_ms.map(expiry_timestamp).transpose()?)
+            .bind(expiry)
+            .bind(now_ms)
             .fetch_optional(active.connection_mut())
-            .await?
-            .ok_or_else(|| err_msg!(Duplicate, "Duplicate entry"))?
+            .await?;
+        match row_id {
+            Some(row_id) => row_id,
+            None => {
+                // a new entry replaces one which has been soft deleted
+                let purged = sqlx::query(PURGE_ENTRY_QUERY)
+                    .bind(active.profile_id)
+                    .bind(kind as i16)
+                    .bind(enc_category)
+                    .bind(enc_name)
+                    .execute(active.connection_mut())
+                    .await
+                    .map_err(err_map!(Backend, "Error removing deleted entry"))?;
+                if purged.rows_affected() == 0 {
+                    return Err(err_msg!(Duplicate, "Duplicate entry"));
+                }
+                sqlx::query_scalar(INSERT_QUERY)
+                    .bind(active.profile_id)
+                    .bind(kind as i16)
+                    .bind(enc_category)
+                    .bind(enc_name)
+                    .bind(enc_value)
+                    .bind(expiry)
+                    .bind(now_ms)
+                    .fetch_optional(active.connection_mut())
+                    .await?
+                    .ok_or_else(|| err_msg!(Duplicate, "Duplicate entry"))?
+            }
+        }
     } else {
-        trace!("Update entry");
-        let row_id: i64 = sqlx::query_scalar(UPDATE_QUERY)
-            .bind(active.profile_id)
-            .bind(kind as i16)
-            .bind(enc_category)
-            .bind(enc_name)
-            .bind(enc_value)
-            .bind(expiry_ms.map(expiry_timestamp).transpose()?)
-            .fetch_one(active.connection_mut())
-            .await
-            .map_err(|_| err_msg!(NotFound, "Error updating existing entry"))?;
+        record_history(active, kind, enc_category, enc_name, version).await?;
+        let row_id: i64 = if operation == EntryOperation::InsertOrReplace {
+            trace!("Insert or replace entry");
+            sqlx::query_scalar(UPSERT_QUERY)
+                .bind(active.profile_id)
+                .bind(kind as i16)
+                .bind(enc_category)
+                .bind(enc_name)
+                .bind(enc_value)
+                .bind(expiry_ms.map(expiry_timestamp).transpose()?)
+                .bind(now_ms)
+                .fetch_one(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error inserting or replacing entry"))?
+        } else {
+            trace!("Update entry");
+            let row_id = sqlx::query_scalar(UPDATE_QUERY)
+                .bind(active.profile_id)
+                .bind(kind as i16)
+                .bind(enc_category)
+                .bind(enc_name)
+                .bind(enc_value)
+                .bind(expiry_ms.map(expiry_timestamp).transpose()?)
+                .bind(version)
+                .bind(now_ms)
+                .fetch_optional(active.connection_mut())
+                .await
+                .map_err(err_map!(Backend, "Error updating existing entry"))?;
+            match row_id {
+                Some(row_id) => row_id,
+                None => {
+                    let profile_id = active.profile_id;
+                    return Err(missing_entry_error(
+                        active.connection_mut(),
+                        profile_id,
+                        kind,
+                        enc_category,
+                        enc_name,
+                        version,
+                    )
+                    .await);
+                }
+            }
+        };
         sqlx::query(TAG_DELETE_QUERY)
             .bind(row_id)
             .execute(active.connection_mut())
@@ -734,30 +2661,311 @@ async fn perform_insert(
                 .map_err(err_map!(Backend, "Error inserting entry tags"))?;
         }
     }
-    Ok(())
+    let audit = active.audit_session();
+    let profile_id = active.profile_id;
+    record_audit(
+        active.connection_mut(),
+        profile_id,
+        &audit,
+        match operation {
+            EntryOperation::Insert => "insert",
+            EntryOperation::Replace => "replace",
+            EntryOperation::InsertOrReplace => "upsert",
+            EntryOperation::Remove => "remove",
+        },
+        Some(kind),
+        Some(enc_category),
+        Some(row_id),
+    )
+    .await
 }
 
-async fn perform_remove<'q>(
-    active: &mut DbSessionActive<'q, Postgres>,
+#[allow(clippy::too_many_arguments)]
+async fn perform_remove(
+    conn: &mut Connection<Postgres>,
+    profile_id: ProfileId,
+    audit: &AuditSession,
     kind: EntryKind,
     enc_category: &[u8],
     enc_name: &[u8],
+    version: Option<i64>,
     ignore_error: bool,
 ) -> Result<(), Error> {
     trace!("Remove entry");
-    let done = sqlx::query(DELETE_QUERY)
+    let soft_delete = soft_delete_enabled(&mut *conn).await?;
+    let row_id: Option<i64> = sqlx::query_scalar(if soft_delete {
+        SOFT_DELETE_QUERY
+    } else {
+        DELETE_QUERY
+    })
+    .bind(profile_id)
+    .bind(kind as i16)
+    .bind(enc_category)
+    .bind(enc_name)
+    .bind(version)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(err_map!(Backend, "Error removing entry"))?;
+    match row_id {
+        Some(row_id) => {
+            record_audit(
+                conn,
+                profile_id,
+                audit,
+                "remove",
+                Some(kind),
+                Some(enc_category),
+                Some(row_id),
+            )
+            .await
+        }
+        None if ignore_error => Ok(()),
+        None => {
+            Err(missing_entry_error(conn, profile_id, kind, enc_category, enc_name, version).await)
+        }
+    }
+}
+
+fn fetch_entry_history<'q>(
+    session: &'q mut DbSession<Postgres>,
+    kind: EntryKind,
+    category: &'q str,
+    name: &'q str,
+    version: Option<i64>,
+    limit: Option<i64>,
+) -> BoxFuture<'q, Result<Vec<EntryVersion>, Error>> {
+    let category = category.to_string();
+    let name = ProfileKey::prepare_input(name.as_bytes());
+
+    Box::pin(async move {
+        let (profile_id, key) = acquire_key(&mut *session).await?;
+        let (enc_category, enc_name) = unblock({
+            let key = key.clone();
+            let category = ProfileKey::prepare_input(category.as_bytes());
+            move || {
+                Result::<_, Error>::Ok((
+                    key.encrypt_entry_category(category)?,
+                    key.encrypt_entry_name(name)?,
+                ))
+            }
+        })
+        .await?;
+        let mut active = acquire_session(&mut *session).await?;
+        let rows = sqlx::query(HISTORY_FETCH_QUERY)
+            .bind(profile_id)
+            .bind(kind as i16)
+            .bind(enc_category.as_slice())
+            .bind(enc_name)
+            .bind(version)
+            .bind(limit)
+            .fetch_all(active.connection_mut())
+            .await
+            .map_err(err_map!(Backend, "Error fetching record history"))?;
+        let enc_rows = rows
+            .into_iter()
+            .map(|row| {
+                Result::<_, Error>::Ok((
+                    EncScanEntry {
+                        id: None,
+                        kind,
+                        category: enc_category.clone(),
+                        name: row.try_get(1)?,
+                        value: row.try_get(2)?,
+                        tags: row
+                            .try_get::<Option<String>, _>(3)?
+                            .map(String::into_bytes)
+                            .unwrap_or_default(),
+                        version: Some(row.try_get(0)?),
+                        created_at: None,
+                        updated_at: None,
+                        deleted_at: None,
+                    },
+                    row.try_get::<i64, _>(4)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        unblock(move || {
+            enc_rows
+                .into_iter()
+                .map(|(enc_entry, replaced_ms)| {
+                    Ok(EntryVersion {
+                        entry: decrypt_scan_entry(Some(&category), enc_entry, &key)?,
+                        replaced_ms,
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()
+        })
+        .await
+    })
+}
+
+// preserve the current version of a record if history is enabled for its category
+async fn record_history(
+    active: &mut DbSessionTxn<'_, Postgres>,
+    kind: EntryKind,
+    enc_category: &[u8],
+    enc_name: &[u8],
+    version: Option<i64>,
+) -> Result<(), Error> {
+    sqlx::query(HISTORY_INSERT_QUERY)
         .bind(active.profile_id)
         .bind(kind as i16)
         .bind(enc_category)
         .bind(enc_name)
+        .bind(version)
+        .bind(chrono::Utc::now().timestamp_millis())
         .execute(active.connection_mut())
         .await
-        .map_err(err_map!(Backend, "Error removing entry"))?;
-    if done.rows_affected() == 0 && !ignore_error {
-        Err(err_msg!(NotFound, "Entry not found"))
-    } else {
-        Ok(())
+        .map_err(err_map!(Backend, "Error recording entry history"))?;
+    Ok(())
+}
+
+/// A record change published by the `items_notify` trigger
+#[derive(Deserialize)]
+struct ChangeNotification {
+    schema: String,
+    id: i64,
+}
+
+fn change_operation(operation: &str) -> Result<ChangeOperation, Error> {
+    match operation {
+        "insert" => Ok(ChangeOperation::Insert),
+        "update" => Ok(ChangeOperation::Update),
+        "remove" => Ok(ChangeOperation::Remove),
+        _ => Err(err_msg!(
+            Unexpected,
+            "Invalid change notification operation"
+        )),
+    }
+}
+
+// install the trigger publishing item changes, once the store has a subscriber
+async fn install_changes_trigger(pool: &PgPool) -> Result<(), Error> {
+    let mut conn = pool.acquire().await?;
+    let installed: bool = sqlx::query_scalar(CHANGES_TRIGGER_CHECK_QUERY)
+        .fetch_one(conn.as_mut())
+        .await
+        .map_err(err_map!(Backend, "Error checking for change trigger"))?;
+    if installed {
+        return Ok(());
+    }
+    let mut txn = conn.begin().await?;
+    // serialize with any concurrent installation and with item updates
+    sqlx::query("LOCK TABLE items IN SHARE ROW EXCLUSIVE MODE")
+        .execute(txn.as_mut())
+        .await
+        .map_err(err_map!(Backend, "Error locking items table"))?;
+    let installed: bool = sqlx::query_scalar(CHANGES_TRIGGER_CHECK_QUERY)
+        .fetch_one(txn.as_mut())
+        .await
+        .map_err(err_map!(Backend, "Error checking for change trigger"))?;
+    if !installed {
+        txn.as_mut()
+            .execute(CHANGES_TRIGGER_INSTALL)
+            .await
+            .map_err(err_map!(Backend, "Error installing change trigger"))?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+// append an operation to the audit log, when enabled for the store
+async fn record_audit(
+    conn: &mut Connection<Postgres>,
+    profile_id: ProfileId,
+    audit: &AuditSession,
+    operation: &str,
+    kind: Option<EntryKind>,
+    enc_category: Option<&[u8]>,
+    item_id: Option<i64>,
+) -> Result<(), Error> {
+    let enabled: Option<String> = sqlx::query_scalar(CONFIG_FETCH_QUERY)
+        .bind("audit_log")
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(err_map!(Backend, "Error fetching audit log mode"))?;
+    if enabled.as_deref() != Some("1") {
+        return Ok(());
+    }
+    let mut txn = conn
+        .begin()
+        .await
+        .map_err(err_map!(Backend, "Error starting transaction"))?;
+    sqlx::query(AUDIT_LOCK_QUERY)
+        .bind(profile_id as i32)
+        .execute(&mut *txn)
+        .await
+        .map_err(err_map!(Backend, "Error locking audit log"))?;
+    let prev_mac: Option<Vec<u8>> = sqlx::query_scalar(AUDIT_LATEST_QUERY)
+        .bind(profile_id)
+        .bind(Option::<i64>::None)
+        .fetch_optional(&mut *txn)
+        .await
+        .map_err(err_map!(Backend, "Error fetching audit log"))?;
+    let record = EncAuditRecord {
+        id: 0,
+        profile_id,
+        session_id: audit.session_id.clone(),
+        operation: operation.to_string(),
+        kind,
+        category: enc_category.map(<[u8]>::to_vec),
+        item_id,
+        created_at: chrono::Utc::now().timestamp_millis(),
+    };
+    let mac = record.compute_mac(&audit.store_key, prev_mac.as_deref().unwrap_or_default())?;
+    sqlx::query(AUDIT_INSERT_QUERY)
+        .bind(profile_id)
+        .bind(record.session_id)
+        .bind(record.operation)
+        .bind(kind.map(|k| k as i16))
+        .bind(record.category)
+        .bind(item_id)
+        .bind(record.created_at)
+        .bind(mac)
+        .execute(&mut *txn)
+        .await
+        .map_err(err_map!(Backend, "Error recording audit log entry"))?;
+    txn.commit()
+        .await
+        .map_err(err_map!(Backend, "Error committing transaction"))?;
+    Ok(())
+}
+
+async fn soft_delete_enabled(conn: &mut Connection<Postgres>) -> Result<bool, Error> {
+    let value: Option<String> = sqlx::query_scalar(CONFIG_FETCH_QUERY)
+        .bind("soft_delete")
+        .fetch_optional(conn)
+        .await
+        .map_err(err_map!(Backend, "Error fetching soft deletion mode"))?;
+    Ok(value.as_deref() == Some("1"))
+}
+
+// determine whether a conditional update failed due to a modified record
+async fn missing_entry_error(
+    conn: &mut Connection<Postgres>,
+    profile_id: ProfileId,
+    kind: EntryKind,
+    enc_category: &[u8],
+    enc_name: &[u8],
+    version: Option<i64>,
+) -> Error {
+    if version.is_some() {
+        let existing = sqlx::query_scalar::<_, i64>(ITEM_ID_QUERY)
+            .bind(profile_id)
+            .bind(kind as i16)
+            .bind(enc_category)
+            .bind(enc_name)
+            .fetch_optional(conn)
+            .await;
+        match existing {
+            Ok(Some(_)) => return err_msg!(Conflict, "Entry version does not match"),
+            Ok(None) => (),
+            Err(err) => {
+                return err_msg!(Backend, "Error checking for existing entry").with_cause(err)
+            }
+        }
     }
+    err_msg!(NotFound, "Entry not found")
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -768,17 +2976,19 @@ fn perform_scan(
     kind: Option<EntryKind>,
     category: Option<String>,
     tag_filter: Option<TagFilter>,
+    after: Option<ScanPosition>,
     offset: Option<i64>,
     limit: Option<i64>,
     order_by: Option<OrderBy>,
     descending: bool,
+    include_deleted: bool,
     for_update: bool,
 ) -> impl Stream<Item = Result<Vec<EncScanEntry>, Error>> + '_ {
     try_stream! {
         let mut params = QueryParams::new();
         params.push(profile_id);
         params.push(kind.map(|k| k as i16));
-        let (enc_category, tag_filter) = unblock({
+        let (enc_category, tag_filter, order_by) = unblock({
             let key = key.clone();
             let enc_category = category.map(|c| ProfileKey::prepare_input(c.as_bytes()));
             let params_len = params.len() + 1; // plus category
@@ -787,12 +2997,17 @@ fn perform_scan(
                     enc_category
                         .map(|c| key.encrypt_entry_category(c))
                         .transpose()?,
-                    encode_tag_filter::<PostgresBackend>(tag_filter, &key, params_len)?
+                    encode_tag_filter::<PostgresBackend>(tag_filter, &key, params_len)?,
+                    encode_order_by(order_by, &key)?,
                 ))
             }
         }).await?;
         params.push(enc_category);
-        let mut query = extend_query::<PostgresBackend>(SCAN_QUERY, &mut params, tag_filter, offset, limit, order_by, descending)?;
+        let scan_query = if include_deleted { SCAN_ALL_QUERY } else { SCAN_QUERY };
+        let mut query = match after {
+            Some(after) => extend_query_after::<PostgresBackend>(scan_query, &mut params, tag_filter, after, limit, order_by.unwrap_or(EncOrderBy::Id), descending)?,
+            None => extend_query::<PostgresBackend>(scan_query, &mut params, tag_filter, offset, limit, order_by, descending)?,
+        };
         if for_update {
             query.push_str(" FOR NO KEY UPDATE");
         }
@@ -805,7 +3020,11 @@ fn perform_scan(
             let kind: i16 = row.try_get(1)?;
             let kind = EntryKind::try_from(kind as usize)?;
             batch.push(EncScanEntry {
-                kind, category: row.try_get(2)?, name: row.try_get(3)?, value: row.try_get(4)?, tags
+                id: Some(row.try_get(0)?),
+                kind, category: row.try_get(2)?, name: row.try_get(3)?, value: row.try_get(4)?, tags,
+                version: row.try_get(6)?,
+                created_at: row.try_get(7)?, updated_at: row.try_get(8)?,
+                deleted_at: row.try_get(9)?,
             });
             if batch.len() == PAGE_SIZE {
                 yield batch.split_off(0);
@@ -9,13 +9,13 @@ use sqlx::{
 
 use crate::{
     backend::{
-        db_utils::{init_keys, random_profile_name},
+        db_utils::{init_master_keys, random_profile_name, unlock_store_key},
         ManageBackend,
     },
     error::Error,
     future::{unblock, BoxFuture},
     options::IntoOptions,
-    protect::{KeyCache, PassKey, ProfileId, StoreKeyMethod, StoreKeyReference},
+    protect::{KeyCache, PassKey, ProfileId, StoreKeyMethod},
 };
 
 use super::PostgresBackend;
@@ -25,6 +25,114 @@ const DEFAULT_IDLE_TIMEOUT: u64 = 300;
 const DEFAULT_MIN_CONNECTIONS: u32 = 0;
 const DEFAULT_MAX_CONNECTIONS: u32 = 10;
 
+/// Schema upgrade scripts, applied in order to stores created by an earlier
+/// version. The current store version is one more than the number of upgrades.
+const UPGRADES: &[&str] = &[
+    "
+    ALTER TABLE items ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+",
+    "
+    ALTER TABLE items ADD COLUMN deleted_at TIMESTAMP NULL;
+",
+    "
+    CREATE TABLE items_history (
+        id BIGSERIAL,
+        item_id BIGINT NOT NULL,
+        version BIGINT NOT NULL,
+        value BYTEA NOT NULL,
+        tags TEXT NULL,
+        replaced_at BIGINT NOT NULL,
+        PRIMARY KEY(id),
+        FOREIGN KEY(item_id) REFERENCES items(id)
+            ON DELETE CASCADE ON UPDATE CASCADE
+    );
+    CREATE INDEX ix_items_history_item_id ON items_history(item_id, version);
+
+    CREATE TABLE items_history_categories (
+        profile_id BIGINT NOT NULL,
+        kind SMALLINT NOT NULL,
+        category BYTEA NOT NULL,
+        PRIMARY KEY(profile_id, kind, category),
+        FOREIGN KEY(profile_id) REFERENCES profiles(id)
+            ON DELETE CASCADE ON UPDATE CASCADE
+    );
+",
+    "
+    CREATE TABLE audit_log (
+        id BIGSERIAL,
+        profile_id BIGINT NOT NULL,
+        session_id TEXT NOT NULL,
+        operation TEXT NOT NULL,
+        kind SMALLINT NULL,
+        category BYTEA NULL,
+        item_id BIGINT NULL,
+        created_at BIGINT NOT NULL,
+        mac BYTEA NOT NULL,
+        PRIMARY KEY(id)
+    );
+    CREATE INDEX ix_audit_log_profile_id ON audit_log(profile_id, id);
+    CREATE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+    BEGIN
+        RAISE EXCEPTION 'audit log is append-only';
+    END;
+    $$ LANGUAGE plpgsql;
+    CREATE TRIGGER audit_log_no_modify BEFORE UPDATE OR DELETE ON audit_log
+        FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
+",
+    "
+    ALTER TABLE items ADD COLUMN created_at BIGINT NULL;
+    ALTER TABLE items ADD COLUMN updated_at BIGINT NULL;
+",
+    "
+    CREATE TABLE store_meta (
+        name TEXT NOT NULL,
+        value BYTEA NOT NULL,
+        PRIMARY KEY(name)
+    );
+
+    CREATE TABLE profile_meta (
+        profile_id BIGINT NOT NULL,
+        name BYTEA NOT NULL,
+        value BYTEA NOT NULL,
+        PRIMARY KEY(profile_id, name),
+        FOREIGN KEY(profile_id) REFERENCES profiles(id)
+            ON DELETE CASCADE ON UPDATE CASCADE
+    );
+",
+    "
+    ALTER TABLE profiles ADD COLUMN created_at BIGINT NULL;
+    ALTER TABLE profiles ADD COLUMN last_access BIGINT NULL;
+",
+    "
+    CREATE TABLE key_slots (
+        name TEXT NOT NULL,
+        key_ref TEXT NOT NULL,
+        wrapped_key BYTEA NOT NULL,
+        PRIMARY KEY(name)
+    );
+",
+    // the search index may have been created when opening an earlier store
+    "
+    CREATE TABLE IF NOT EXISTS items_search (
+        item_id BIGINT NOT NULL,
+        token BYTEA NOT NULL,
+        PRIMARY KEY(item_id, token),
+        FOREIGN KEY(item_id) REFERENCES items(id)
+            ON DELETE CASCADE ON UPDATE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS ix_items_search_token ON items_search(token);
+",
+    "
+    CREATE TABLE items_search_terms (
+        item_id BIGINT NOT NULL,
+        terms BYTEA NOT NULL,
+        PRIMARY KEY(item_id),
+        FOREIGN KEY(item_id) REFERENCES items(id)
+            ON DELETE CASCADE ON UPDATE CASCADE
+    );
+",
+];
+
 /// Configuration options for PostgreSQL stores
 #[derive(Debug)]
 pub struct PostgresStoreOptions {
@@ -235,9 +343,9 @@ impl PostgresStoreOptions {
 
         // no 'config' table, assume empty database
 
-        let (profile_key, enc_profile_key, store_key, store_key_ref) = unblock({
+        let (profile_key, enc_profile_key, store_key, store_key_ref, master_key) = unblock({
             let pass_key = pass_key.into_owned();
-            move || init_keys(method, pass_key)
+            move || init_master_keys(method, pass_key)
         })
         .await?;
         let default_profile = profile.unwrap_or_else(random_profile_name);
@@ -245,6 +353,7 @@ impl PostgresStoreOptions {
             txn,
             &default_profile,
             store_key_ref,
+            master_key,
             enc_profile_key,
             self.schema.as_ref().unwrap_or(&self.username),
         )
@@ -339,6 +448,7 @@ pub(crate) async fn init_db<'t>(
     mut txn: Transaction<'t, Postgres>,
     profile_name: &str,
     store_key_ref: String,
+    master_key: Option<String>,
     enc_profile_key: Vec<u8>,
     schema: &str,
 ) -> Result<ProfileId, Error> {
@@ -357,6 +467,8 @@ pub(crate) async fn init_db<'t>(
             name TEXT NOT NULL,
             reference TEXT NULL,
             profile_key BYTEA NULL,
+            created_at BIGINT NULL,
+            last_access BIGINT NULL,
             PRIMARY KEY(id)
         );
         CREATE UNIQUE INDEX ix_profile_name ON "{schema}".profiles(name);
@@ -369,6 +481,10 @@ pub(crate) async fn init_db<'t>(
             name BYTEA NOT NULL,
             value BYTEA NOT NULL,
             expiry TIMESTAMP NULL,
+            version BIGINT NOT NULL DEFAULT 1,
+            deleted_at TIMESTAMP NULL,
+            created_at BIGINT NULL,
+            updated_at BIGINT NULL,
             PRIMARY KEY(id),
             FOREIGN KEY(profile_id) REFERENCES "{schema}".profiles(id)
                 ON DELETE CASCADE ON UPDATE CASCADE
@@ -388,6 +504,88 @@ pub(crate) async fn init_db<'t>(
         CREATE INDEX ix_items_tags_item_id ON "{schema}".items_tags(item_id);
         CREATE INDEX ix_items_tags_name_enc ON "{schema}".items_tags(name, SUBSTR(value, 1, 12)) INCLUDE (item_id) WHERE plaintext=0;
         CREATE INDEX ix_items_tags_name_plain ON "{schema}".items_tags(name, value) INCLUDE (item_id) WHERE plaintext=1;
+
+        CREATE TABLE "{schema}".items_search (
+            item_id BIGINT NOT NULL,
+            token BYTEA NOT NULL,
+            PRIMARY KEY(item_id, token),
+            FOREIGN KEY(item_id) REFERENCES "{schema}".items(id)
+                ON DELETE CASCADE ON UPDATE CASCADE
+        );
+        CREATE INDEX ix_items_search_token ON "{schema}".items_search(token);
+
+        CREATE TABLE "{schema}".items_search_terms (
+            item_id BIGINT NOT NULL,
+            terms BYTEA NOT NULL,
+            PRIMARY KEY(item_id),
+            FOREIGN KEY(item_id) REFERENCES "{schema}".items(id)
+                ON DELETE CASCADE ON UPDATE CASCADE
+        );
+
+        CREATE TABLE "{schema}".items_history (
+            id BIGSERIAL,
+            item_id BIGINT NOT NULL,
+            version BIGINT NOT NULL,
+            value BYTEA NOT NULL,
+            tags TEXT NULL,
+            replaced_at BIGINT NOT NULL,
+            PRIMARY KEY(id),
+            FOREIGN KEY(item_id) REFERENCES "{schema}".items(id)
+                ON DELETE CASCADE ON UPDATE CASCADE
+        );
+        CREATE INDEX ix_items_history_item_id ON "{schema}".items_history(item_id, version);
+
+        CREATE TABLE "{schema}".items_history_categories (
+            profile_id BIGINT NOT NULL,
+            kind SMALLINT NOT NULL,
+            category BYTEA NOT NULL,
+            PRIMARY KEY(profile_id, kind, category),
+            FOREIGN KEY(profile_id) REFERENCES "{schema}".profiles(id)
+                ON DELETE CASCADE ON UPDATE CASCADE
+        );
+
+        CREATE TABLE "{schema}".audit_log (
+            id BIGSERIAL,
+            profile_id BIGINT NOT NULL,
+            session_id TEXT NOT NULL,
+            operation TEXT NOT NULL,
+            kind SMALLINT NULL,
+            category BYTEA NULL,
+            item_id BIGINT NULL,
+            created_at BIGINT NOT NULL,
+            mac BYTEA NOT NULL,
+            PRIMARY KEY(id)
+        );
+        CREATE INDEX ix_audit_log_profile_id ON "{schema}".audit_log(profile_id, id);
+        CREATE FUNCTION "{schema}".audit_log_append_only() RETURNS trigger AS $$
+        BEGIN
+            RAISE EXCEPTION 'audit log is append-only';
+        END;
+        $$ LANGUAGE plpgsql;
+        CREATE TRIGGER audit_log_no_modify BEFORE UPDATE OR DELETE ON "{schema}".audit_log
+            FOR EACH ROW EXECUTE FUNCTION "{schema}".audit_log_append_only();
+
+        CREATE TABLE "{schema}".store_meta (
+            name TEXT NOT NULL,
+            value BYTEA NOT NULL,
+            PRIMARY KEY(name)
+        );
+
+        CREATE TABLE "{schema}".profile_meta (
+            profile_id BIGINT NOT NULL,
+            name BYTEA NOT NULL,
+            value BYTEA NOT NULL,
+            PRIMARY KEY(profile_id, name),
+            FOREIGN KEY(profile_id) REFERENCES "{schema}".profiles(id)
+                ON DELETE CASCADE ON UPDATE CASCADE
+        );
+
+        CREATE TABLE "{schema}".key_slots (
+            name TEXT NOT NULL,
+            key_ref TEXT NOT NULL,
+            wrapped_key BYTEA NOT NULL,
+            PRIMARY KEY(name)
+        );
     "#).as_str(),
     )
     .await
@@ -397,22 +595,34 @@ pub(crate) async fn init_db<'t>(
         "INSERT INTO config (name, value) VALUES
             ('default_profile', $1),
             ('key', $2),
-            ('version', '1')",
+            ('version', $3)",
     )
     .persistent(false)
     .bind(profile_name)
     .bind(store_key_ref)
+    .bind((UPGRADES.len() + 1).to_string())
     .execute(txn.as_mut())
     .await
     .map_err(err_map!(Backend, "Error inserting configuration"))?;
 
-    let profile_id =
-        sqlx::query_scalar("INSERT INTO profiles (name, profile_key) VALUES ($1, $2) RETURNING id")
-            .bind(profile_name)
-            .bind(enc_profile_key)
-            .fetch_one(txn.as_mut())
+    if let Some(master_key) = master_key {
+        sqlx::query("INSERT INTO config (name, value) VALUES ('master_key', $1)")
+            .persistent(false)
+            .bind(master_key)
+            .execute(txn.as_mut())
             .await
-            .map_err(err_map!(Backend, "Error inserting default profile"))?;
+            .map_err(err_map!(Backend, "Error inserting configuration"))?;
+    }
+
+    let profile_id = sqlx::query_scalar(
+        "INSERT INTO profiles (name, profile_key, created_at) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(profile_name)
+    .bind(enc_profile_key)
+    .bind(chrono::Utc::now().timestamp_millis())
+    .fetch_one(txn.as_mut())
+    .await
+    .map_err(err_map!(Backend, "Error inserting default profile"))?;
 
     txn.commit().await?;
 
@@ -425,13 +635,44 @@ pub(crate) async fn reset_db(conn: &mut PgConnection) -> Result<(), Error> {
         DROP TABLE IF EXISTS
           config, profiles,
           profile_keys, keys,
-          items, items_tags;
+          items, items_tags, items_search, items_search_terms,
+          items_history, items_history_categories, audit_log,
+          store_meta, profile_meta, key_slots, items_changes;
+        DROP FUNCTION IF EXISTS items_notify();
+        DROP FUNCTION IF EXISTS audit_log_append_only();
         ",
     )
     .await?;
     Ok(())
 }
 
+// apply the schema upgrades following the given store version
+async fn upgrade_db(conn: &mut PgConnection, from_version: usize) -> Result<(), Error> {
+    let mut txn = conn.begin().await?;
+    // lock the store version in case another connection is performing the upgrade
+    let locked_version: String =
+        sqlx::query_scalar("SELECT value FROM config WHERE name = 'version' FOR UPDATE")
+            .fetch_one(txn.as_mut())
+            .await
+            .map_err(err_map!(Backend, "Error fetching store configuration"))?;
+    if locked_version != from_version.to_string() {
+        // already upgraded
+        return Ok(());
+    }
+    for script in &UPGRADES[from_version - 1..] {
+        txn.execute(*script)
+            .await
+            .map_err(err_map!(Backend, "Error upgrading database schema"))?;
+    }
+    sqlx::query("UPDATE config SET value = $1 WHERE name = 'version'")
+        .bind((UPGRADES.len() + 1).to_string())
+        .execute(txn.as_mut())
+        .await
+        .map_err(err_map!(Backend, "Error updating store version"))?;
+    txn.commit().await?;
+    Ok(())
+}
+
 pub(crate) async fn open_db(
     conn_pool: PgPool,
     method: Option<StoreKeyMethod>,
@@ -441,13 +682,15 @@ pub(crate) async fn open_db(
     name: String,
 ) -> Result<PostgresBackend, Error> {
     let mut conn = conn_pool.acquire().await?;
-    let mut ver_ok = false;
+    let mut version: Option<usize> = None;
     let mut default_profile: Option<String> = None;
     let mut store_key_ref: Option<String> = None;
+    let mut master_key: Option<String> = None;
+    let mut rekey_key: Option<String> = None;
 
     let config = sqlx::query(
         r#"SELECT name, value FROM config
-        WHERE name IN ('default_profile', 'key', 'version')"#,
+        WHERE name IN ('default_profile', 'key', 'master_key', 'rekey_key', 'version')"#,
     )
     .fetch_all(conn.as_mut())
     .await
@@ -460,44 +703,71 @@ pub(crate) async fn open_db(
             "key" => {
                 store_key_ref.replace(row.try_get(1)?);
             }
+            "master_key" => {
+                master_key.replace(row.try_get(1)?);
+            }
+            "rekey_key" => {
+                rekey_key.replace(row.try_get(1)?);
+            }
             "version" => {
-                if row.try_get::<&str, _>(1)? != "1" {
-                    return Err(err_msg!(Unsupported, "Unsupported store version"));
-                }
-                ver_ok = true;
+                version.replace(
+                    row.try_get::<&str, _>(1)?
+                        .parse()
+                        .map_err(|_| err_msg!(Unsupported, "Unsupported store version"))?,
+                );
             }
             _ => (),
         }
     }
-    if !ver_ok {
-        return Err(err_msg!(Unsupported, "Store version not found"));
+    let version = version.ok_or_else(|| err_msg!(Unsupported, "Store version not found"))?;
+    if version == 0 || version > UPGRADES.len() + 1 {
+        return Err(err_msg!(Unsupported, "Unsupported store version"));
     }
+    if version <= UPGRADES.len() {
+        upgrade_db(conn.as_mut(), version).await?;
+    }
+
     let profile = profile
         .or(default_profile)
         .ok_or_else(|| err_msg!(Unsupported, "Default store profile not found"))?;
-    let store_key = if let Some(store_key_ref) = store_key_ref {
-        let wrap_ref = StoreKeyReference::parse_uri(&store_key_ref)?;
-        if let Some(method) = method {
-            if !wrap_ref.compare_method(&method) {
-                return Err(err_msg!(Input, "Store key method mismatch"));
-            }
-        }
-        unblock({
-            let pass_key = pass_key.into_owned();
-            move || wrap_ref.resolve(pass_key)
-        })
-        .await?
-    } else {
-        return Err(err_msg!(Unsupported, "Store key not found"));
-    };
-
-    let mut key_cache = KeyCache::new(store_key);
+    let store_key_ref =
+        store_key_ref.ok_or_else(|| err_msg!(Unsupported, "Store key not found"))?;
+    let key_slots = sqlx::query("SELECT key_ref, wrapped_key FROM key_slots")
+        .fetch_all(conn.as_mut())
+        .await
+        .map_err(err_map!(Backend, "Error fetching key slots"))?
+        .into_iter()
+        .map(|row| Result::<_, Error>::Ok((row.try_get(0)?, row.try_get(1)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
     let row = sqlx::query("SELECT id, profile_key FROM profiles WHERE name = $1")
         .bind(&profile)
         .fetch_one(conn.as_mut())
         .await?;
     let profile_id = row.try_get(0)?;
-    let profile_key = key_cache.load_key(row.try_get(1)?).await?;
+    let enc_profile_key: Vec<u8> = row.try_get(1)?;
+    let store_key = unblock({
+        let pass_key = pass_key.into_owned();
+        move || {
+            unlock_store_key(
+                &store_key_ref,
+                master_key.as_deref(),
+                key_slots,
+                method,
+                pass_key,
+            )
+        }
+    })
+    .await?;
+
+    let mut key_cache = KeyCache::new(store_key);
+    if let Some(rekey_key) = rekey_key {
+        // profile keys may be wrapped by the new key of an incomplete rekey
+        let rekey_key =
+            hex::decode(rekey_key).map_err(|_| err_msg!(Unexpected, "Invalid rekey state"))?;
+        let pending_key = key_cache.store_key.unwrap_key(rekey_key)?;
+        key_cache.set_pending_key(pending_key);
+    }
+    let profile_key = key_cache.load_key(enc_profile_key).await?;
     conn.return_to_pool().await;
 
     key_cache.add_profile_mut(profile.clone(), profile_id, profile_key);
@@ -513,7 +783,7 @@ pub(crate) async fn open_db(
 fn _validate_ident(ident: &str, name: &str) -> Result<(), Error> {
     if ident.is_empty() {
         Err(err_msg!(Input, "{name} identifier is empty"))
-    } else if ident.find(|c| c == '"' || c == '\0').is_some() {
+    } else if ident.contains(['"', '\0']) {
         Err(err_msg!(
             Input,
             "Invalid character in {name} identifier: '\"' and '\\0' are disallowed"
@@ -11,7 +11,7 @@ use super::PostgresBackend;
 use crate::{
     any::{into_any_backend, AnyBackend},
     backend::{
-        db_utils::{init_keys, random_profile_name},
+        db_utils::{init_master_keys, random_profile_name},
         Backend,
     },
     error::Error,
@@ -36,8 +36,8 @@ impl TestDB {
     /// This method blocks until the database lock can be acquired.
     pub async fn provision(db_url: &str) -> Result<TestDB, Error> {
         let key = generate_raw_store_key(None)?;
-        let (profile_key, enc_profile_key, store_key, store_key_ref) =
-            unblock(|| init_keys(StoreKeyMethod::RawKey, key)).await?;
+        let (profile_key, enc_profile_key, store_key, store_key_ref, master_key) =
+            unblock(|| init_master_keys(StoreKeyMethod::RawKey, key)).await?;
         let default_profile = random_profile_name();
 
         let opts = PostgresStoreOptions::new(db_url)?;
@@ -70,6 +70,7 @@ impl TestDB {
             init_txn,
             &default_profile,
             store_key_ref,
+            master_key,
             enc_profile_key,
             &opts.username,
         )
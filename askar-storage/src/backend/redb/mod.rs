@@ -0,0 +1,1181 @@
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use futures_lite::stream::{self, StreamExt};
+use redb::{Database, ReadOnlyTable, ReadableTable, Table, TableDefinition, WriteTransaction};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    db_utils::{encode_order_by, encode_profile_key, random_profile_name, EncOrderBy, PAGE_SIZE},
+    Backend, BackendSession, OrderBy,
+};
+use crate::{
+    entry::{
+        EncEntryTag, Entry, EntryKind, EntryOperation, EntryTag, Scan, ScanPosition, SortValue,
+        TagFilter,
+    },
+    error::Error,
+    future::{unblock, BoxFuture},
+    protect::{EntryEncryptor, KeyCache, PassKey, ProfileId, ProfileKey, StoreKeyMethod},
+    wql::{
+        matcher::{TagMatch, TagMatchEncoder},
+        tags::{tag_query, TagQueryEncoder},
+    },
+};
+
+mod provision;
+pub use self::provision::RedbStoreOptions;
+
+const CONFIG_TABLE: TableDefinition<'_, &str, &str> = TableDefinition::new("config");
+const PROFILES_TABLE: TableDefinition<'_, &str, &[u8]> = TableDefinition::new("profiles");
+const ITEMS_TABLE: TableDefinition<'_, &[u8], &[u8]> = TableDefinition::new("items");
+const SEQUENCES_TABLE: TableDefinition<'_, &str, u64> = TableDefinition::new("sequences");
+
+/// An embedded redb database store
+pub struct RedbBackend {
+    db: Arc<Database>,
+    active_profile: String,
+    key_cache: Arc<KeyCache>,
+    path: String,
+}
+
+impl RedbBackend {
+    pub(crate) fn new(
+        db: Database,
+        active_profile: String,
+        key_cache: KeyCache,
+        path: String,
+    ) -> Self {
+        Self {
+            db: Arc::new(db),
+            active_profile,
+            key_cache: Arc::new(key_cache),
+            path,
+        }
+    }
+
+    async fn write<R, F>(&self, f: F) -> Result<R, Error>
+    where
+        R: Send + 'static,
+        F: FnOnce(&WriteTransaction) -> Result<R, Error> + Send + 'static,
+    {
+        let db = self.db.clone();
+        unblock(move || write_txn(&db, f)).await
+    }
+}
+
+impl Debug for RedbBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedbStore")
+            .field("active_profile", &self.active_profile)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl Backend for RedbBackend {
+    type Session = RedbSession;
+
+    fn create_profile(&self, name: Option<String>) -> BoxFuture<'_, Result<String, Error>> {
+        let name = name.unwrap_or_else(random_profile_name);
+        Box::pin(async move {
+            let store_key = self.key_cache.store_key.clone();
+            let (profile_key, enc_key) = unblock(move || {
+                let profile_key = ProfileKey::new()?;
+                let enc_key = encode_profile_key(&profile_key, &store_key)?;
+                Result::<_, Error>::Ok((profile_key, enc_key))
+            })
+            .await?;
+            let pid = self
+                .write({
+                    let name = name.clone();
+                    move |txn| insert_profile(txn, &name, enc_key)
+                })
+                .await?;
+            self.key_cache
+                .add_profile(name.clone(), pid, Arc::new(profile_key))
+                .await;
+            Ok(name)
+        })
+    }
+
+    fn get_active_profile(&self) -> String {
+        self.active_profile.clone()
+    }
+
+    fn get_default_profile(&self) -> BoxFuture<'_, Result<String, Error>> {
+        Box::pin(async move {
+            let db = self.db.clone();
+            Ok(unblock(move || get_config(&db, "default_profile"))
+                .await?
+                .unwrap_or_default())
+        })
+    }
+
+    fn set_default_profile(&self, profile: String) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(self.write(move |txn| set_config(txn, "default_profile", &profile)))
+    }
+
+    fn list_profiles(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        Box::pin(async move {
+            let db = self.db.clone();
+            unblock(move || {
+                let txn = db
+                    .begin_read()
+                    .map_err(redb_err("Error fetching profile list"))?;
+                let table = txn
+                    .open_table(PROFILES_TABLE)
+                    .map_err(redb_err("Error fetching profile list"))?;
+                let mut names = Vec::new();
+                for row in table
+                    .iter()
+                    .map_err(redb_err("Error fetching profile list"))?
+                {
+                    let (name, _) = row.map_err(redb_err("Error fetching profile list"))?;
+                    names.push(name.value().to_string());
+                }
+                Ok(names)
+            })
+            .await
+        })
+    }
+
+    fn remove_profile(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(self.write(move |txn| remove_profile(txn, &name)))
+    }
+
+    fn rekey(
+        &mut self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        let pass_key = pass_key.into_owned();
+        Box::pin(async move {
+            let (store_key, store_key_ref) = unblock(move || method.resolve(pass_key)).await?;
+            let store_key = Arc::new(store_key);
+            // hold the write transaction to prevent concurrent profile changes
+            let db = self.db.clone();
+            let (txn, profiles) = unblock(move || {
+                let txn = db
+                    .begin_write()
+                    .map_err(redb_err("Error starting transaction"))?;
+                let profiles = fetch_profiles(&txn)?;
+                Result::<_, Error>::Ok((txn, profiles))
+            })
+            .await?;
+            let mut upd_profiles = Vec::with_capacity(profiles.len());
+            for (name, mut profile) in profiles {
+                let profile_key = self.key_cache.load_key(profile.profile_key).await?;
+                profile.profile_key = unblock({
+                    let store_key = store_key.clone();
+                    move || encode_profile_key(&profile_key, &store_key)
+                })
+                .await?;
+                upd_profiles.push((name, profile));
+            }
+            unblock(move || {
+                {
+                    let mut table = txn
+                        .open_table(PROFILES_TABLE)
+                        .map_err(redb_err("Error updating profile key"))?;
+                    for (name, profile) in upd_profiles {
+                        table
+                            .insert(name.as_str(), encode_record(&profile)?.as_slice())
+                            .map_err(redb_err("Error updating profile key"))?;
+                    }
+                }
+                set_config(&txn, "key", &store_key_ref.into_uri())?;
+                txn.commit()
+                    .map_err(redb_err("Error committing transaction"))
+            })
+            .await?;
+            self.key_cache = Arc::new(KeyCache::new(store_key));
+            Ok(())
+        })
+    }
+
+    fn scan(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        _include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(async move {
+            let mut session = self.session(profile, false)?;
+            let (profile_id, key) = session.acquire_key().await?;
+            let rows = session
+                .perform_scan(
+                    profile_id,
+                    key.clone(),
+                    kind,
+                    category,
+                    tag_filter,
+                    None,
+                    offset,
+                    limit,
+                    order_by,
+                    descending,
+                )
+                .await?;
+            let mut batches = Vec::with_capacity(rows.len() / PAGE_SIZE + 1);
+            let mut rows = rows.into_iter().peekable();
+            while rows.peek().is_some() {
+                batches.push(rows.by_ref().take(PAGE_SIZE).collect::<Vec<_>>());
+            }
+            let stream = stream::iter(batches).then(move |batch| {
+                let key = key.clone();
+                unblock(move || decrypt_batch(batch, &key))
+            });
+            Ok(Scan::new(stream, PAGE_SIZE))
+        })
+    }
+
+    fn scan_after(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        after: ScanPosition,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        _include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(async move {
+            let mut session = self.session(profile, false)?;
+            let (profile_id, key) = session.acquire_key().await?;
+            let rows = session
+                .perform_scan(
+                    profile_id,
+                    key.clone(),
+                    kind,
+                    category,
+                    tag_filter,
+                    Some(after),
+                    None,
+                    limit,
+                    order_by,
+                    descending,
+                )
+                .await?;
+            let mut batches = Vec::with_capacity(rows.len() / PAGE_SIZE + 1);
+            let mut rows = rows.into_iter().peekable();
+            while rows.peek().is_some() {
+                batches.push(rows.by_ref().take(PAGE_SIZE).collect::<Vec<_>>());
+            }
+            let stream = stream::iter(batches).then(move |batch| {
+                let key = key.clone();
+                unblock(move || decrypt_batch(batch, &key))
+            });
+            Ok(Scan::new(stream, PAGE_SIZE))
+        })
+    }
+
+    fn seal_token(&self, data: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        let store_key = self.key_cache.store_key.clone();
+        Box::pin(async move { unblock(move || store_key.seal_token(&data)).await })
+    }
+
+    fn open_token(&self, token: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        let store_key = self.key_cache.store_key.clone();
+        Box::pin(async move { unblock(move || store_key.open_token(&token)).await })
+    }
+
+    fn session(&self, profile: Option<String>, transaction: bool) -> Result<Self::Session, Error> {
+        Ok(RedbSession {
+            db: self.db.clone(),
+            key_cache: self.key_cache.clone(),
+            profile: profile.unwrap_or_else(|| self.active_profile.clone()),
+            profile_key: None,
+            transaction,
+            txn: None,
+        })
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), Error>> {
+        // the database file is closed when the last reference is dropped
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+/// A session against a redb store
+///
+/// A transactional session holds the single redb write transaction from its
+/// first operation until it is closed, so that other writers are blocked in
+/// the meantime.
+pub struct RedbSession {
+    db: Arc<Database>,
+    key_cache: Arc<KeyCache>,
+    profile: String,
+    profile_key: Option<(ProfileId, Arc<ProfileKey>)>,
+    transaction: bool,
+    txn: Option<WriteTransaction>,
+}
+
+impl Debug for RedbSession {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedbSession")
+            .field("profile", &self.profile)
+            .field("transaction", &self.transaction)
+            .finish()
+    }
+}
+
+impl RedbSession {
+    async fn acquire_key(&mut self) -> Result<(ProfileId, Arc<ProfileKey>), Error> {
+        if let Some((pid, key)) = self.profile_key.as_ref() {
+            return Ok((*pid, key.clone()));
+        }
+        let (pid, key) = if let Some((pid, key)) = self.key_cache.get_profile(&self.profile).await {
+            (pid, key)
+        } else if let Some(profile) = unblock({
+            let db = self.db.clone();
+            let name = self.profile.clone();
+            move || fetch_profile(&db, &name)
+        })
+        .await?
+        {
+            let key = Arc::new(self.key_cache.load_key(profile.profile_key).await?);
+            self.key_cache
+                .add_profile(self.profile.clone(), profile.id, key.clone())
+                .await;
+            (profile.id, key)
+        } else {
+            return Err(err_msg!(NotFound, "Profile not found"));
+        };
+        self.profile_key.replace((pid, key.clone()));
+        Ok((pid, key))
+    }
+
+    // perform a write against the session transaction, or in a new
+    // transaction which is committed immediately
+    async fn write<R, F>(&mut self, f: F) -> Result<R, Error>
+    where
+        R: Send + 'static,
+        F: FnOnce(&WriteTransaction) -> Result<R, Error> + Send + 'static,
+    {
+        let db = self.db.clone();
+        if self.transaction {
+            let txn = self.txn.take();
+            let (txn, res) = unblock(move || {
+                let txn = match txn {
+                    Some(txn) => txn,
+                    None => {
+                        debug!("Start transaction");
+                        db.begin_write()
+                            .map_err(redb_err("Error starting transaction"))?
+                    }
+                };
+                let res = f(&txn);
+                Result::<_, Error>::Ok((txn, res))
+            })
+            .await?;
+            self.txn.replace(txn);
+            res
+        } else {
+            unblock(move || write_txn(&db, f)).await
+        }
+    }
+
+    // perform a read against the session transaction, if any, so that
+    // uncommitted changes are visible
+    async fn read_items<R, F>(&mut self, f: F) -> Result<R, Error>
+    where
+        R: Send + 'static,
+        F: FnOnce(ItemsTable<'_>) -> Result<R, Error> + Send + 'static,
+    {
+        if self.transaction {
+            self.write(move |txn| {
+                f(ItemsTable::Write(
+                    txn.open_table(ITEMS_TABLE)
+                        .map_err(redb_err("Error opening items table"))?,
+                ))
+            })
+            .await
+        } else {
+            let db = self.db.clone();
+            unblock(move || {
+                let txn = db
+                    .begin_read()
+                    .map_err(redb_err("Error starting transaction"))?;
+                f(ItemsTable::Read(
+                    txn.open_table(ITEMS_TABLE)
+                        .map_err(redb_err("Error opening items table"))?,
+                ))
+            })
+            .await
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn perform_scan(
+        &mut self,
+        profile_id: ProfileId,
+        key: Arc<ProfileKey>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        after: Option<ScanPosition>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+    ) -> Result<Vec<ItemRecord>, Error> {
+        let (enc_category, tag_filter, order_by) = unblock(move || {
+            Result::<_, Error>::Ok((
+                category
+                    .map(|c| key.encrypt_entry_category(ProfileKey::prepare_input(c.as_bytes())))
+                    .transpose()?,
+                encode_tag_filter(tag_filter, &key)?,
+                encode_order_by(order_by, &key)?,
+            ))
+        })
+        .await?;
+        let mut rows = self
+            .read_items(move |table| {
+                table.scan(
+                    profile_id,
+                    kind,
+                    enc_category.as_deref(),
+                    tag_filter.as_ref(),
+                )
+            })
+            .await?;
+        let order_by = match (order_by, &after) {
+            (None, Some(_)) => Some(EncOrderBy::Id),
+            (order_by, _) => order_by,
+        };
+        if let Some(order_by) = order_by {
+            let order_tag = match order_by {
+                EncOrderBy::Id => None,
+                EncOrderBy::Tag(name) => Some(name),
+                EncOrderBy::CreatedAt | EncOrderBy::UpdatedAt => {
+                    return Err(err_msg!(
+                        Unsupported,
+                        "Ordering by record timestamps is not supported by this backend"
+                    ))
+                }
+            };
+            let sort_key = |row: &ItemRecord| {
+                let value = order_tag
+                    .as_ref()
+                    .and_then(|name| row.plaintext_tag(name))
+                    .map(<[u8]>::to_vec);
+                (value, row.id)
+            };
+            rows.sort_by_cached_key(sort_key);
+            if descending {
+                rows.reverse();
+            }
+            if let Some(after) = after {
+                let position = (
+                    match after.value {
+                        Some(SortValue::Text(value)) if order_tag.is_some() => {
+                            Some(value.into_bytes())
+                        }
+                        _ => None,
+                    },
+                    after.id as u64,
+                );
+                rows.retain(|row| {
+                    if descending {
+                        sort_key(row) < position
+                    } else {
+                        sort_key(row) > position
+                    }
+                });
+            }
+        }
+        let offset = offset.unwrap_or(0).max(0) as usize;
+        let limit = limit.filter(|l| *l >= 0).map(|l| l as usize);
+        Ok(rows
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+}
+
+impl Drop for RedbSession {
+    fn drop(&mut self) {
+        if let Some(txn) = self.txn.take() {
+            debug!("Dropped transaction: roll-back");
+            if let Err(err) = txn.abort() {
+                warn!("Error rolling back dropped transaction: {}", err);
+            }
+        }
+    }
+}
+
+impl BackendSession for RedbSession {
+    fn count<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        _include_deleted: bool,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        let category = category.map(str::to_string);
+        Box::pin(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let rows = self
+                .perform_scan(
+                    profile_id, key, kind, category, tag_filter, None, None, None, None, false,
+                )
+                .await?;
+            Ok(rows.len() as i64)
+        })
+    }
+
+    fn fetch<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        _for_update: bool,
+    ) -> BoxFuture<'q, Result<Option<Entry>, Error>> {
+        let category = category.to_string();
+        let name = name.to_string();
+        Box::pin(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let (enc_category, enc_name) = unblock({
+                let key = key.clone();
+                let category = ProfileKey::prepare_input(category.as_bytes());
+                let name = ProfileKey::prepare_input(name.as_bytes());
+                move || {
+                    Result::<_, Error>::Ok((
+                        key.encrypt_entry_category(category)?,
+                        key.encrypt_entry_name(name)?,
+                    ))
+                }
+            })
+            .await?;
+            let item_key = item_key(profile_id, kind, &enc_category, &enc_name);
+            let row = self.read_items(move |table| table.get(&item_key)).await?;
+            match row {
+                Some(row) if !row.is_expired(now_millis()) => {
+                    let expires_at = row.expiry;
+                    let (category, name, value, tags) = unblock(move || {
+                        let value = key.decrypt_entry_value(
+                            category.as_bytes(),
+                            name.as_bytes(),
+                            row.value,
+                        )?;
+                        let tags =
+                            key.decrypt_entry_tags(row.tags.into_iter().map(Into::into).collect())?;
+                        Result::<_, Error>::Ok((category, name, value, tags))
+                    })
+                    .await?;
+                    Ok(Some(
+                        Entry::new(kind, category, name, value, tags).with_expiry(expires_at),
+                    ))
+                }
+                _ => Ok(None),
+            }
+        })
+    }
+
+    fn fetch_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        _for_update: bool,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        let category = category.map(str::to_string);
+        Box::pin(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let rows = self
+                .perform_scan(
+                    profile_id,
+                    key.clone(),
+                    kind,
+                    category,
+                    tag_filter,
+                    None,
+                    None,
+                    limit,
+                    order_by,
+                    descending,
+                )
+                .await?;
+            unblock(move || decrypt_batch(rows, &key)).await
+        })
+    }
+
+    fn remove_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        let category = category.map(str::to_string);
+        Box::pin(async move {
+            let (profile_id, key) = self.acquire_key().await?;
+            let (enc_category, tag_filter) = unblock(move || {
+                Result::<_, Error>::Ok((
+                    category
+                        .map(|c| {
+                            key.encrypt_entry_category(ProfileKey::prepare_input(c.as_bytes()))
+                        })
+                        .transpose()?,
+                    encode_tag_filter(tag_filter, &key)?,
+                ))
+            })
+            .await?;
+            self.write(move |txn| {
+                trace!("Remove entries");
+                let mut table = txn
+                    .open_table(ITEMS_TABLE)
+                    .map_err(redb_err("Error removing entries"))?;
+                let rows = scan_items(
+                    &table,
+                    profile_id,
+                    kind,
+                    enc_category.as_deref(),
+                    tag_filter.as_ref(),
+                )?;
+                for row in rows.iter() {
+                    table
+                        .remove(row.key().as_slice())
+                        .map_err(redb_err("Error removing entries"))?;
+                }
+                Ok(rows.len() as i64)
+            })
+            .await
+        })
+    }
+
+    fn update<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        let category = ProfileKey::prepare_input(category.as_bytes());
+        let name = ProfileKey::prepare_input(name.as_bytes());
+
+        match operation {
+            op @ (EntryOperation::Insert
+            | EntryOperation::Replace
+            | EntryOperation::InsertOrReplace) => {
+                let value = ProfileKey::prepare_input(value.unwrap_or_default());
+                let tags = tags.map(|t| t.to_vec());
+                Box::pin(async move {
+                    let (profile_id, key) = self.acquire_key().await?;
+                    let (enc_category, enc_name, enc_value, enc_tags) = unblock(move || {
+                        let enc_value =
+                            key.encrypt_entry_value(category.as_ref(), name.as_ref(), value)?;
+                        Result::<_, Error>::Ok((
+                            key.encrypt_entry_category(category)?,
+                            key.encrypt_entry_name(name)?,
+                            enc_value,
+                            tags.map(|t| key.encrypt_entry_tags(t)).transpose()?,
+                        ))
+                    })
+                    .await?;
+                    let record = ItemRecord {
+                        id: 0,
+                        profile_id,
+                        kind: kind as u8,
+                        category: enc_category,
+                        name: enc_name,
+                        value: enc_value,
+                        tags: enc_tags
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(Into::into)
+                            .collect(),
+                        expiry: expiry_ms.map(|ms| now_millis() + ms),
+                    };
+                    self.write(move |txn| perform_insert(txn, record, op)).await
+                })
+            }
+
+            EntryOperation::Remove => Box::pin(async move {
+                let (profile_id, key) = self.acquire_key().await?;
+                let (enc_category, enc_name) = unblock(move || {
+                    Result::<_, Error>::Ok((
+                        key.encrypt_entry_category(category)?,
+                        key.encrypt_entry_name(name)?,
+                    ))
+                })
+                .await?;
+                let item_key = item_key(profile_id, kind, &enc_category, &enc_name);
+                self.write(move |txn| {
+                    trace!("Remove entry");
+                    let mut table = txn
+                        .open_table(ITEMS_TABLE)
+                        .map_err(redb_err("Error removing entry"))?;
+                    let removed = table
+                        .remove(item_key.as_slice())
+                        .map_err(redb_err("Error removing entry"))?
+                        .map(|row| decode_record::<ItemRecord>(row.value()))
+                        .transpose()?;
+                    match removed {
+                        Some(row) if !row.is_expired(now_millis()) => Ok(()),
+                        _ => Err(err_msg!(NotFound, "Entry not found")),
+                    }
+                })
+                .await
+            }),
+        }
+    }
+
+    fn ping(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            self.acquire_key().await?;
+            let db = self.db.clone();
+            let name = self.profile.clone();
+            if unblock(move || fetch_profile(&db, &name)).await?.is_none() {
+                Err(err_msg!(NotFound, "Session profile has been removed"))
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    fn close(&mut self, commit: bool) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            if let Some(txn) = self.txn.take() {
+                unblock(move || {
+                    if commit {
+                        debug!("Commit transaction on close");
+                        txn.commit()
+                            .map_err(redb_err("Error committing transaction"))
+                    } else {
+                        debug!("Roll-back transaction on close");
+                        txn.abort()
+                            .map_err(redb_err("Error rolling back transaction"))
+                    }
+                })
+                .await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProfileRecord {
+    id: ProfileId,
+    #[serde(with = "serde_bytes")]
+    profile_key: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ItemRecord {
+    id: u64,
+    profile_id: ProfileId,
+    kind: u8,
+    #[serde(with = "serde_bytes")]
+    category: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    name: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    value: Vec<u8>,
+    tags: Vec<TagRecord>,
+    expiry: Option<i64>,
+}
+
+impl ItemRecord {
+    fn key(&self) -> Vec<u8> {
+        let mut key = item_prefix(self.profile_id, Some(self.kind), Some(&self.category));
+        key.extend_from_slice(&self.name);
+        key
+    }
+
+    fn is_expired(&self, now: i64) -> bool {
+        matches!(self.expiry, Some(expiry) if expiry <= now)
+    }
+
+    // the lowest value of a plaintext tag, given the encrypted tag name
+    fn plaintext_tag(&self, name: &[u8]) -> Option<&[u8]> {
+        self.tags
+            .iter()
+            .filter(|tag| tag.plaintext && tag.name == name)
+            .map(|tag| tag.value.as_slice())
+            .min()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct TagRecord {
+    #[serde(with = "serde_bytes")]
+    name: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    value: Vec<u8>,
+    plaintext: bool,
+}
+
+impl From<EncEntryTag> for TagRecord {
+    fn from(tag: EncEntryTag) -> Self {
+        Self {
+            name: tag.name,
+            value: tag.value,
+            plaintext: tag.plaintext,
+        }
+    }
+}
+
+impl From<TagRecord> for EncEntryTag {
+    fn from(tag: TagRecord) -> Self {
+        Self {
+            name: tag.name,
+            value: tag.value,
+            plaintext: tag.plaintext,
+        }
+    }
+}
+
+type ItemsKey = &'static [u8];
+
+// the items table, opened in either a read or a write transaction
+enum ItemsTable<'t> {
+    Read(ReadOnlyTable<ItemsKey, ItemsKey>),
+    Write(Table<'t, ItemsKey, ItemsKey>),
+}
+
+impl ItemsTable<'_> {
+    fn get(&self, item_key: &[u8]) -> Result<Option<ItemRecord>, Error> {
+        match self {
+            Self::Read(table) => get_item(table, item_key),
+            Self::Write(table) => get_item(table, item_key),
+        }
+    }
+
+    fn scan(
+        &self,
+        profile_id: ProfileId,
+        kind: Option<EntryKind>,
+        enc_category: Option<&[u8]>,
+        tag_filter: Option<&TagMatch>,
+    ) -> Result<Vec<ItemRecord>, Error> {
+        match self {
+            Self::Read(table) => scan_items(table, profile_id, kind, enc_category, tag_filter),
+            Self::Write(table) => scan_items(table, profile_id, kind, enc_category, tag_filter),
+        }
+    }
+}
+
+fn redb_err<E: Into<redb::Error>>(message: &'static str) -> impl FnOnce(E) -> Error {
+    move |err| err_msg!(Backend, "{}", message).with_cause(err.into())
+}
+
+fn encode_record<T: Serialize>(record: &T) -> Result<Vec<u8>, Error> {
+    serde_cbor::to_vec(record).map_err(err_map!(Unexpected, "Error encoding record"))
+}
+
+fn decode_record<T: DeserializeOwned>(record: &[u8]) -> Result<T, Error> {
+    serde_cbor::from_slice(record).map_err(err_map!(Unexpected, "Error decoding record"))
+}
+
+#[inline]
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+// the lookup key for a record, such that the records for a profile (and
+// optionally a kind and category) share a common prefix
+fn item_prefix(profile_id: ProfileId, kind: Option<u8>, enc_category: Option<&[u8]>) -> Vec<u8> {
+    let mut key = profile_id.to_be_bytes().to_vec();
+    if let Some(kind) = kind {
+        key.push(kind);
+        if let Some(category) = enc_category {
+            key.extend_from_slice(&(category.len() as u32).to_be_bytes());
+            key.extend_from_slice(category);
+        }
+    }
+    key
+}
+
+fn item_key(
+    profile_id: ProfileId,
+    kind: EntryKind,
+    enc_category: &[u8],
+    enc_name: &[u8],
+) -> Vec<u8> {
+    let mut key = item_prefix(profile_id, Some(kind as u8), Some(enc_category));
+    key.extend_from_slice(enc_name);
+    key
+}
+
+fn write_txn<R>(
+    db: &Database,
+    f: impl FnOnce(&WriteTransaction) -> Result<R, Error>,
+) -> Result<R, Error> {
+    let txn = db
+        .begin_write()
+        .map_err(redb_err("Error starting transaction"))?;
+    let res = f(&txn)?;
+    txn.commit()
+        .map_err(redb_err("Error committing transaction"))?;
+    Ok(res)
+}
+
+fn next_id(txn: &WriteTransaction, sequence: &str) -> Result<u64, Error> {
+    let mut table = txn
+        .open_table(SEQUENCES_TABLE)
+        .map_err(redb_err("Error updating sequence"))?;
+    let id = table
+        .get(sequence)
+        .map_err(redb_err("Error updating sequence"))?
+        .map(|v| v.value())
+        .unwrap_or(0)
+        + 1;
+    table
+        .insert(sequence, id)
+        .map_err(redb_err("Error updating sequence"))?;
+    Ok(id)
+}
+
+fn get_config(db: &Database, name: &str) -> Result<Option<String>, Error> {
+    let txn = db
+        .begin_read()
+        .map_err(redb_err("Error fetching store configuration"))?;
+    let table = match txn.open_table(CONFIG_TABLE) {
+        Ok(table) => table,
+        Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+        Err(err) => return Err(redb_err("Error fetching store configuration")(err)),
+    };
+    let value = table
+        .get(name)
+        .map_err(redb_err("Error fetching store configuration"))?
+        .map(|v| v.value().to_string());
+    Ok(value)
+}
+
+fn set_config(txn: &WriteTransaction, name: &str, value: &str) -> Result<(), Error> {
+    txn.open_table(CONFIG_TABLE)
+        .map_err(redb_err("Error updating store configuration"))?
+        .insert(name, value)
+        .map_err(redb_err("Error updating store configuration"))?;
+    Ok(())
+}
+
+fn fetch_profile(db: &Database, name: &str) -> Result<Option<ProfileRecord>, Error> {
+    let txn = db
+        .begin_read()
+        .map_err(redb_err("Error fetching profile"))?;
+    let table = txn
+        .open_table(PROFILES_TABLE)
+        .map_err(redb_err("Error fetching profile"))?;
+    let row = table
+        .get(name)
+        .map_err(redb_err("Error fetching profile"))?;
+    row.map(|row| decode_record(row.value())).transpose()
+}
+
+fn fetch_profiles(txn: &WriteTransaction) -> Result<Vec<(String, ProfileRecord)>, Error> {
+    let table = txn
+        .open_table(PROFILES_TABLE)
+        .map_err(redb_err("Error fetching profiles"))?;
+    let mut profiles = Vec::new();
+    for row in table.iter().map_err(redb_err("Error fetching profiles"))? {
+        let (name, profile) = row.map_err(redb_err("Error fetching profiles"))?;
+        profiles.push((name.value().to_string(), decode_record(profile.value())?));
+    }
+    Ok(profiles)
+}
+
+fn insert_profile(
+    txn: &WriteTransaction,
+    name: &str,
+    enc_key: Vec<u8>,
+) -> Result<ProfileId, Error> {
+    let id = next_id(txn, "profiles")? as ProfileId;
+    let mut table = txn
+        .open_table(PROFILES_TABLE)
+        .map_err(redb_err("Error creating profile"))?;
+    if table
+        .get(name)
+        .map_err(redb_err("Error creating profile"))?
+        .is_some()
+    {
+        return Err(err_msg!(Duplicate, "Duplicate profile name"));
+    }
+    let record = encode_record(&ProfileRecord {
+        id,
+        profile_key: enc_key,
+    })?;
+    table
+        .insert(name, record.as_slice())
+        .map_err(redb_err("Error creating profile"))?;
+    Ok(id)
+}
+
+fn remove_profile(txn: &WriteTransaction, name: &str) -> Result<bool, Error> {
+    let removed = txn
+        .open_table(PROFILES_TABLE)
+        .map_err(redb_err("Error removing profile"))?
+        .remove(name)
+        .map_err(redb_err("Error removing profile"))?
+        .map(|row| decode_record::<ProfileRecord>(row.value()))
+        .transpose()?;
+    if let Some(profile) = removed {
+        let mut table = txn
+            .open_table(ITEMS_TABLE)
+            .map_err(redb_err("Error removing profile"))?;
+        let prefix = item_prefix(profile.id, None, None);
+        let mut keys = Vec::new();
+        for row in table
+            .range(prefix.as_slice()..)
+            .map_err(redb_err("Error removing profile"))?
+        {
+            let (key, _) = row.map_err(redb_err("Error removing profile"))?;
+            if !key.value().starts_with(&prefix) {
+                break;
+            }
+            keys.push(key.value().to_vec());
+        }
+        for key in keys {
+            table
+                .remove(key.as_slice())
+                .map_err(redb_err("Error removing profile"))?;
+        }
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+fn get_item(
+    table: &impl ReadableTable<ItemsKey, ItemsKey>,
+    item_key: &[u8],
+) -> Result<Option<ItemRecord>, Error> {
+    table
+        .get(item_key)
+        .map_err(redb_err("Error performing fetch query"))?
+        .map(|row| decode_record(row.value()))
+        .transpose()
+}
+
+fn scan_items(
+    table: &impl ReadableTable<ItemsKey, ItemsKey>,
+    profile_id: ProfileId,
+    kind: Option<EntryKind>,
+    enc_category: Option<&[u8]>,
+    tag_filter: Option<&TagMatch>,
+) -> Result<Vec<ItemRecord>, Error> {
+    let prefix = item_prefix(profile_id, kind.map(|k| k as u8), enc_category);
+    let now = now_millis();
+    let mut results = Vec::new();
+    for row in table
+        .range(prefix.as_slice()..)
+        .map_err(redb_err("Error performing scan query"))?
+    {
+        let (key, value) = row.map_err(redb_err("Error performing scan query"))?;
+        if !key.value().starts_with(&prefix) {
+            break;
+        }
+        let row: ItemRecord = decode_record(value.value())?;
+        if row.is_expired(now)
+            || enc_category
+                .map(|c| c != row.category.as_slice())
+                .unwrap_or(false)
+        {
+            continue;
+        }
+        if let Some(filter) = tag_filter {
+            let tags = row.tags.iter().cloned().map(Into::into).collect::<Vec<_>>();
+            if !filter.matches(&tags) {
+                continue;
+            }
+        }
+        results.push(row);
+    }
+    Ok(results)
+}
+
+fn perform_insert(
+    txn: &WriteTransaction,
+    mut record: ItemRecord,
+    operation: EntryOperation,
+) -> Result<(), Error> {
+    let item_key = record.key();
+    let existing = get_item(
+        &txn.open_table(ITEMS_TABLE)
+            .map_err(redb_err("Error updating entry"))?,
+        &item_key,
+    )?;
+    match existing {
+        Some(prev) if operation == EntryOperation::Insert && !prev.is_expired(now_millis()) => {
+            return Err(err_msg!(Duplicate, "Duplicate entry"));
+        }
+        Some(prev) => {
+            trace!("Update entry");
+            record.id = prev.id;
+        }
+        None if operation == EntryOperation::Replace => {
+            return Err(err_msg!(NotFound, "Error updating existing entry"));
+        }
+        None => {
+            trace!("Insert entry");
+            record.id = next_id(txn, "items")?;
+        }
+    }
+    txn.open_table(ITEMS_TABLE)
+        .map_err(redb_err("Error updating entry"))?
+        .insert(item_key.as_slice(), encode_record(&record)?.as_slice())
+        .map_err(redb_err("Error updating entry"))?;
+    Ok(())
+}
+
+fn encode_tag_filter(
+    tag_filter: Option<TagFilter>,
+    key: &ProfileKey,
+) -> Result<Option<TagMatch>, Error> {
+    if let Some(tag_filter) = tag_filter {
+        let tag_query = tag_query(tag_filter.query)?;
+        let mut enc = TagMatchEncoder::new(
+            |name| key.encrypt_tag_name(ProfileKey::prepare_input(name.as_bytes())),
+            |value| key.encrypt_tag_value(ProfileKey::prepare_input(value.as_bytes())),
+        );
+        enc.encode_query(&tag_query)
+    } else {
+        Ok(None)
+    }
+}
+
+fn decrypt_batch(rows: Vec<ItemRecord>, key: &ProfileKey) -> Result<Vec<Entry>, Error> {
+    let mut batch = Vec::with_capacity(rows.len());
+    for row in rows {
+        let kind = EntryKind::try_from(row.kind as usize)?;
+        let category = key.decrypt_entry_category(row.category)?;
+        let name = key.decrypt_entry_name(row.name)?;
+        let value = key.decrypt_entry_value(category.as_bytes(), name.as_bytes(), row.value)?;
+        let tags = key.decrypt_entry_tags(row.tags.into_iter().map(Into::into).collect())?;
+        batch.push(Entry::new(kind, category, name, value, tags).with_row_id(Some(row.id as i64)));
+    }
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redb_item_key_prefix() {
+        let key = item_key(1, EntryKind::Item, b"cat", b"name");
+        assert!(key.starts_with(&item_prefix(1, None, None)));
+        assert!(key.starts_with(&item_prefix(1, Some(EntryKind::Item as u8), None)));
+        assert!(key.starts_with(&item_prefix(1, Some(EntryKind::Item as u8), Some(b"cat"))));
+        // a category which is a prefix of another must not match its records
+        assert!(!key.starts_with(&item_prefix(1, Some(EntryKind::Item as u8), Some(b"ca"))));
+        assert!(!key.starts_with(&item_prefix(2, None, None)));
+    }
+}
@@ -0,0 +1,268 @@
+use std::{borrow::Cow, fs::remove_file, io::ErrorKind as IoErrorKind, path::Path};
+
+use redb::{backends::InMemoryBackend, Database, DatabaseError};
+
+use super::{
+    fetch_profile, get_config, insert_profile, redb_err, set_config, write_txn, RedbBackend,
+    ITEMS_TABLE, SEQUENCES_TABLE,
+};
+use crate::{
+    backend::{
+        db_utils::{init_keys, random_profile_name},
+        ManageBackend,
+    },
+    error::Error,
+    future::{unblock, BoxFuture},
+    options::{IntoOptions, Options},
+    protect::{KeyCache, PassKey, StoreKeyMethod, StoreKeyReference},
+};
+
+/// Configuration options for redb stores
+#[derive(Debug)]
+pub struct RedbStoreOptions {
+    pub(crate) in_memory: bool,
+    pub(crate) path: String,
+}
+
+impl Default for RedbStoreOptions {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl RedbStoreOptions {
+    /// Initialize `RedbStoreOptions` from a generic set of options
+    pub fn new<'a>(options: impl IntoOptions<'a>) -> Result<Self, Error> {
+        let opts = options.into_options()?;
+        let mut path = opts.host.to_string();
+        path.push_str(&opts.path);
+        let in_memory = path == ":memory:";
+        Ok(Self { in_memory, path })
+    }
+
+    /// Default options for an in-memory redb store
+    pub fn in_memory() -> Self {
+        Self::from_path(":memory:")
+    }
+
+    /// Default options for a given redb database path
+    pub fn from_path(path: &str) -> Self {
+        let opts = Options {
+            host: Cow::Borrowed(path),
+            ..Default::default()
+        };
+        Self::new(opts).unwrap()
+    }
+
+    fn create_db(&self) -> Result<Database, Error> {
+        if self.in_memory {
+            Database::builder()
+                .create_with_backend(InMemoryBackend::new())
+                .map_err(redb_err("Error creating database"))
+        } else {
+            Database::create(&self.path).map_err(redb_err("Error creating database"))
+        }
+    }
+
+    /// Provision a new redb store from these configuration options
+    pub async fn provision(
+        self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+        profile: Option<String>,
+        recreate: bool,
+    ) -> Result<RedbBackend, Error> {
+        let (db, path, found) = unblock(move || {
+            if recreate && !self.in_memory {
+                try_remove_file(&self.path)?;
+            }
+            let db = self.create_db()?;
+            let found = !recreate && get_config(&db, "version")?.is_some();
+            Result::<_, Error>::Ok((db, self.path, found))
+        })
+        .await?;
+        if found {
+            return open_db(db, Some(method), pass_key, profile, path).await;
+        }
+        // else: no configuration, assume empty database
+
+        let default_profile = profile.unwrap_or_else(random_profile_name);
+        init_db(db, default_profile, method, pass_key, path).await
+    }
+
+    /// Open an existing redb store from this set of configuration options
+    pub async fn open(
+        self,
+        method: Option<StoreKeyMethod>,
+        pass_key: PassKey<'_>,
+        profile: Option<String>,
+    ) -> Result<RedbBackend, Error> {
+        if self.in_memory {
+            return Err(err_msg!(
+                NotFound,
+                "An in-memory database must be provisioned"
+            ));
+        }
+        let path = self.path;
+        let db = unblock({
+            let path = path.clone();
+            move || {
+                if !Path::new(&path).exists() {
+                    return Err(err_msg!(
+                        NotFound,
+                        "The requested database path was not found"
+                    ));
+                }
+                Database::open(&path).map_err(|err| match err {
+                    DatabaseError::DatabaseAlreadyOpen => {
+                        err_msg!(Busy, "The requested database is in use by another instance")
+                    }
+                    err => redb_err("Error opening database")(err),
+                })
+            }
+        })
+        .await?;
+        open_db(db, method, pass_key, profile, path).await
+    }
+
+    /// Remove the redb store defined by these configuration options
+    pub async fn remove(self) -> Result<bool, Error> {
+        if self.in_memory {
+            Ok(true)
+        } else {
+            unblock(move || try_remove_file(&self.path)).await
+        }
+    }
+}
+
+impl<'a> ManageBackend<'a> for RedbStoreOptions {
+    type Backend = RedbBackend;
+
+    fn open_backend(
+        self,
+        method: Option<StoreKeyMethod>,
+        pass_key: PassKey<'a>,
+        profile: Option<String>,
+    ) -> BoxFuture<'a, Result<RedbBackend, Error>> {
+        Box::pin(self.open(method, pass_key, profile))
+    }
+
+    fn provision_backend(
+        self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'a>,
+        profile: Option<String>,
+        recreate: bool,
+    ) -> BoxFuture<'a, Result<RedbBackend, Error>> {
+        Box::pin(self.provision(method, pass_key, profile, recreate))
+    }
+
+    fn remove_backend(self) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(self.remove())
+    }
+}
+
+async fn init_db(
+    db: Database,
+    profile_name: String,
+    method: StoreKeyMethod,
+    pass_key: PassKey<'_>,
+    path: String,
+) -> Result<RedbBackend, Error> {
+    let pass_key = pass_key.into_owned();
+    let (db, profile_name, key_cache) = unblock(move || {
+        let (profile_key, enc_profile_key, store_key, store_key_ref) = init_keys(method, pass_key)?;
+        let profile_id = write_txn(&db, |txn| {
+            // create all tables up front, so that readers may open them
+            txn.open_table(ITEMS_TABLE)
+                .map_err(redb_err("Error creating database tables"))?;
+            txn.open_table(SEQUENCES_TABLE)
+                .map_err(redb_err("Error creating database tables"))?;
+            set_config(txn, "default_profile", &profile_name)?;
+            set_config(txn, "key", &store_key_ref)?;
+            set_config(txn, "version", "1")?;
+            insert_profile(txn, &profile_name, enc_profile_key)
+        })?;
+        let mut key_cache = KeyCache::new(store_key);
+        key_cache.add_profile_mut(profile_name.clone(), profile_id, profile_key);
+        Result::<_, Error>::Ok((db, profile_name, key_cache))
+    })
+    .await?;
+    Ok(RedbBackend::new(db, profile_name, key_cache, path))
+}
+
+async fn open_db(
+    db: Database,
+    method: Option<StoreKeyMethod>,
+    pass_key: PassKey<'_>,
+    profile: Option<String>,
+    path: String,
+) -> Result<RedbBackend, Error> {
+    let (db, default_profile, store_key_ref) = unblock(move || {
+        match get_config(&db, "version")? {
+            Some(ver) if ver == "1" => (),
+            Some(_) => return Err(err_msg!(Unsupported, "Unsupported store version")),
+            None => return Err(err_msg!(Unsupported, "Store version not found")),
+        }
+        let default_profile = get_config(&db, "default_profile")?;
+        let store_key_ref = get_config(&db, "key")?;
+        Ok((db, default_profile, store_key_ref))
+    })
+    .await?;
+    let profile = profile
+        .or(default_profile)
+        .ok_or_else(|| err_msg!(Unsupported, "Default store profile not found"))?;
+    let store_key = if let Some(store_key_ref) = store_key_ref {
+        let wrap_ref = StoreKeyReference::parse_uri(&store_key_ref)?;
+        if let Some(method) = &method {
+            if !wrap_ref.compare_method(method) {
+                return Err(err_msg!(Input, "Store key method mismatch"));
+            }
+        }
+        unblock({
+            let pass_key = pass_key.into_owned();
+            move || wrap_ref.resolve_with(method.as_ref(), pass_key)
+        })
+        .await?
+    } else {
+        return Err(err_msg!(Unsupported, "Store key not found"));
+    };
+
+    let mut key_cache = KeyCache::new(store_key);
+    let (db, row) = unblock({
+        let profile = profile.clone();
+        move || {
+            let row = fetch_profile(&db, &profile)?;
+            Result::<_, Error>::Ok((db, row))
+        }
+    })
+    .await?;
+    let row = row.ok_or_else(|| err_msg!(NotFound, "Profile not found"))?;
+    let profile_key = key_cache.load_key(row.profile_key).await?;
+    key_cache.add_profile_mut(profile.clone(), row.id, profile_key);
+
+    Ok(RedbBackend::new(db, profile, key_cache, path))
+}
+
+fn try_remove_file(path: &str) -> Result<bool, Error> {
+    match remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == IoErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err_msg!(Backend, "Error removing file").with_cause(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redb_parse_uri() {
+        let opts = RedbStoreOptions::new("redb://:memory:").unwrap();
+        assert!(opts.in_memory);
+
+        let opts = RedbStoreOptions::new("redb://data/store.redb").unwrap();
+        assert!(!opts.in_memory);
+        assert_eq!(opts.path, "data/store.redb");
+    }
+}
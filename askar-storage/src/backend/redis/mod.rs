@@ -0,0 +1,1072 @@
+//! Redis caching of record lookups
+//!
+//! A [`CachedBackend`] wraps another backend, caching the records returned
+//! by lookups of a single record in a Redis server. Cached records are
+//! encrypted using a [`CacheKey`], and are removed from the cache when they
+//! are updated or removed through the cached backend.
+//!
+//! A record is cached for at most the configured lifetime, and never beyond
+//! the expiry time of the stored record. Each cache slot is paired with a
+//! generation value which is replaced whenever the slot is invalidated, so
+//! that a record read from the wrapped backend before a concurrent update is
+//! not served from the cache once that update is complete. The generation of
+//! the profile is replaced when expired records are removed, evicting all
+//! records cached for the profile.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use redis::{aio::ConnectionManager, AsyncCommands};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::{Backend, BackendSession, OrderBy, RekeyProgress};
+use crate::{
+    crypto::{
+        alg::chacha20::{Chacha20Key, C20P},
+        buffer::{ArrayKey, ResizeBuffer, SecretBytes},
+        encrypt::{KeyAeadInPlace, KeyAeadMeta},
+        generic_array::typenum::U32,
+        kdf::FromKeyDerivation,
+        random::{fill_random, RandomDet},
+        repr::KeyGen,
+    },
+    entry::{
+        AuditRecord, Entry, EntryKind, EntryOperation, EntryTag, EntryUpdate, EntryVersion,
+        ProfileDetails, Scan, ScanPosition, Subscription, TagFilter,
+    },
+    error::Error,
+    future::BoxFuture,
+    protect::{
+        hmac_key::{HmacDerive, HmacKey},
+        PassKey, StoreKeyMethod,
+    },
+};
+
+/// The default lifetime of a cached record
+pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+const KEY_PREFIX: &str = "askar:";
+
+const GENERATION_PREFIX: &str = "askar:gen:";
+
+const PROFILE_GENERATION_PREFIX: &str = "askar:profile:";
+
+type CacheEncKey = Chacha20Key<C20P>;
+
+type CacheNonce = ArrayKey<<CacheEncKey as KeyAeadMeta>::NonceSize>;
+
+/// The key used to address and encrypt cached records
+///
+/// Backend instances sharing a Redis server must use the same cache key in
+/// order to observe each other's cache invalidations.
+#[derive(Clone, Debug)]
+pub struct CacheKey {
+    enc_key: CacheEncKey,
+    hmac_key: HmacKey<Sha256, U32>,
+}
+
+impl CacheKey {
+    /// Create a new random cache key
+    pub fn random() -> Result<Self, Error> {
+        Ok(Self {
+            enc_key: CacheEncKey::random()?,
+            hmac_key: HmacKey::random()?,
+        })
+    }
+
+    /// Deterministically create a cache key from a secret seed value
+    pub fn from_seed(seed: &[u8]) -> Result<Self, Error> {
+        let mut rng = RandomDet::new(seed);
+        Ok(Self {
+            enc_key: CacheEncKey::generate(&mut rng)?,
+            hmac_key: HmacKey::generate(&mut rng)?,
+        })
+    }
+
+    fn slot(
+        &self,
+        profile: &str,
+        kind: EntryKind,
+        category: &str,
+        name: &str,
+    ) -> Result<String, Error> {
+        let hash = ArrayKey::<U32>::from_key_derivation(self.hmac_key.hmac_deriver(&[
+            &(profile.len() as u32).to_be_bytes(),
+            profile.as_bytes(),
+            &[kind as u8],
+            &(category.len() as u32).to_be_bytes(),
+            category.as_bytes(),
+            &(name.len() as u32).to_be_bytes(),
+            name.as_bytes(),
+        ]))?;
+        Ok(format!("{}{}", KEY_PREFIX, hex::encode(hash.as_ref())))
+    }
+
+    fn profile_generation_key(&self, profile: &str) -> Result<String, Error> {
+        let hash = ArrayKey::<U32>::from_key_derivation(
+            self.hmac_key
+                .hmac_deriver(&[&(profile.len() as u32).to_be_bytes(), profile.as_bytes()]),
+        )?;
+        Ok(format!(
+            "{}{}",
+            PROFILE_GENERATION_PREFIX,
+            hex::encode(hash.as_ref())
+        ))
+    }
+
+    fn encrypt(&self, slot: &str, entry: &Entry, generation: Generation) -> Result<Vec<u8>, Error> {
+        let record = CachedEntry {
+            value: entry.value.clone(),
+            tags: entry.tags.iter().map(CachedTag::from).collect(),
+            version: entry.version,
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+            expires_at: entry.expires_at,
+            generation: generation.slot,
+            profile_generation: generation.profile,
+        };
+        let mut buffer = SecretBytes::from(
+            serde_cbor::to_vec(&record).map_err(err_map!(Unexpected, "Error encoding record"))?,
+        );
+        let nonce = CacheNonce::random();
+        self.enc_key
+            .encrypt_in_place(&mut buffer, nonce.as_ref(), slot.as_bytes())?;
+        buffer.buffer_insert(0, nonce.as_ref())?;
+        Ok(buffer.into_vec())
+    }
+
+    fn decrypt(
+        &self,
+        slot: &str,
+        kind: EntryKind,
+        category: &str,
+        name: &str,
+        ciphertext: Vec<u8>,
+    ) -> Result<(Entry, Generation), Error> {
+        let nonce_len = CacheNonce::SIZE;
+        if ciphertext.len() < nonce_len {
+            return Err(err_msg!(Encryption, "Invalid cached record"));
+        }
+        let nonce = CacheNonce::from_slice(&ciphertext[..nonce_len]);
+        let mut buffer = SecretBytes::from(ciphertext);
+        buffer.buffer_remove(0..nonce_len)?;
+        self.enc_key
+            .decrypt_in_place(&mut buffer, nonce.as_ref(), slot.as_bytes())?;
+        let record: CachedEntry = serde_cbor::from_slice(buffer.as_ref())
+            .map_err(err_map!(Unexpected, "Error decoding cached record"))?;
+        let entry = Entry::new(
+            kind,
+            category,
+            name,
+            record.value,
+            record.tags.into_iter().map(EntryTag::from).collect(),
+        )
+        .with_version(record.version)
+        .with_timestamps(record.created_at, record.updated_at)
+        .with_expiry(record.expires_at);
+        let generation = Generation {
+            slot: record.generation,
+            profile: record.profile_generation,
+        };
+        Ok((entry, generation))
+    }
+}
+
+/// A backend caching record lookups in a Redis server
+pub struct CachedBackend<B: Backend> {
+    inner: B,
+    cache: Cache,
+}
+
+impl<B: Backend> CachedBackend<B> {
+    /// Wrap a backend, caching records in the Redis server at `url`
+    /// for at most `ttl`
+    pub async fn connect(inner: B, url: &str, key: CacheKey, ttl: Duration) -> Result<Self, Error> {
+        let client =
+            redis::Client::open(url).map_err(err_map!(Input, "Invalid Redis connection URL"))?;
+        let conn = ConnectionManager::new(client)
+            .await
+            .map_err(err_map!(Backend, "Error connecting to Redis"))?;
+        Ok(Self {
+            inner,
+            cache: Cache {
+                conn,
+                key: key.into(),
+                ttl: ttl.as_secs().max(1),
+            },
+        })
+    }
+
+    /// Access the wrapped backend
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Collect the cache slots of the records in a profile
+    async fn profile_slots(&self, name: &str) -> Result<Vec<String>, Error> {
+        // the profile may not be accessible to this backend instance
+        let mut slots = Vec::new();
+        if let Ok(mut scan) = self
+            .inner
+            .scan(
+                Some(name.to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                false,
+            )
+            .await
+        {
+            while let Some(rows) = scan.fetch_next().await? {
+                for row in rows {
+                    slots.push(
+                        self.cache
+                            .key
+                            .slot(name, row.kind, &row.category, &row.name)?,
+                    );
+                }
+            }
+        }
+        Ok(slots)
+    }
+}
+
+impl<B: Backend> Debug for CachedBackend<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedBackend")
+            .field("inner", &self.inner)
+            .field("ttl", &self.cache.ttl)
+            .finish()
+    }
+}
+
+impl<B: Backend> Backend for CachedBackend<B> {
+    type Session = CachedSession<B::Session>;
+
+    fn create_profile(&self, name: Option<String>) -> BoxFuture<'_, Result<String, Error>> {
+        self.inner.create_profile(name)
+    }
+
+    fn get_active_profile(&self) -> String {
+        self.inner.get_active_profile()
+    }
+
+    fn get_default_profile(&self) -> BoxFuture<'_, Result<String, Error>> {
+        self.inner.get_default_profile()
+    }
+
+    fn set_default_profile(&self, profile: String) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.set_default_profile(profile)
+    }
+
+    fn list_profiles(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        self.inner.list_profiles()
+    }
+
+    fn list_profile_details(
+        &self,
+        name_prefix: Option<String>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<ProfileDetails>, Error>> {
+        self.inner.list_profile_details(name_prefix, offset, limit)
+    }
+
+    fn set_soft_delete(&self, enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.set_soft_delete(enabled)
+    }
+
+    fn set_audit_log(&self, enabled: bool) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.set_audit_log(enabled)
+    }
+
+    fn subscribe(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+    ) -> BoxFuture<'_, Result<Subscription, Error>> {
+        self.inner.subscribe(profile, kind, category)
+    }
+
+    fn get_meta(&self, name: String) -> BoxFuture<'_, Result<Option<SecretBytes>, Error>> {
+        self.inner.get_meta(name)
+    }
+
+    fn set_meta(
+        &self,
+        name: String,
+        value: Option<SecretBytes>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.set_meta(name, value)
+    }
+
+    fn remove_profile(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async move {
+            let slots = self.profile_slots(&name).await?;
+            let removed = self.inner.remove_profile(name).await?;
+            if removed {
+                self.cache.clone().invalidate(slots).await;
+            }
+            Ok(removed)
+        })
+    }
+
+    fn rename_profile(&self, from: String, to: String) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async move {
+            // cache slots are derived from the profile name
+            let slots = self.profile_slots(&from).await?;
+            let renamed = self.inner.rename_profile(from, to).await?;
+            if renamed {
+                self.cache.clone().invalidate(slots).await;
+            }
+            Ok(renamed)
+        })
+    }
+
+    fn rekey(
+        &mut self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        // cached records are encrypted using the cache key
+        self.inner.rekey(method, pass_key)
+    }
+
+    fn rekey_with_progress(
+        &mut self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+        progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        // cached records are encrypted using the cache key
+        self.inner.rekey_with_progress(method, pass_key, progress)
+    }
+
+    fn rekey_profile(
+        &self,
+        profile: String,
+        progress: Option<RekeyProgress>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        // cached records are encrypted using the cache key
+        self.inner.rekey_profile(profile, progress)
+    }
+
+    fn add_key_slot(
+        &self,
+        name: String,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.add_key_slot(name, method, pass_key)
+    }
+
+    fn remove_key_slot(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        self.inner.remove_key_slot(name)
+    }
+
+    fn list_key_slots(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        self.inner.list_key_slots()
+    }
+
+    fn scan(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        self.inner.scan(
+            profile,
+            kind,
+            category,
+            tag_filter,
+            offset,
+            limit,
+            order_by,
+            descending,
+            include_deleted,
+        )
+    }
+
+    fn scan_after(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        after: ScanPosition,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        self.inner.scan_after(
+            profile,
+            kind,
+            category,
+            tag_filter,
+            after,
+            limit,
+            order_by,
+            descending,
+            include_deleted,
+        )
+    }
+
+    fn seal_token(&self, data: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        self.inner.seal_token(data)
+    }
+
+    fn open_token(&self, token: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        self.inner.open_token(token)
+    }
+
+    fn session(&self, profile: Option<String>, transaction: bool) -> Result<Self::Session, Error> {
+        let inner = self.inner.session(profile.clone(), transaction)?;
+        let profile = profile.unwrap_or_else(|| self.inner.get_active_profile());
+        Ok(CachedSession {
+            inner,
+            cache: self.cache.clone(),
+            profile_generation: self.cache.key.profile_generation_key(&profile)?,
+            profile,
+            transaction,
+            pending: Vec::new(),
+            pending_expired: false,
+        })
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.close()
+    }
+}
+
+/// A session against a store with cached record lookups
+///
+/// Records are only read from the cache outside of a transaction. Within a
+/// transaction, updated records are removed from the cache once the
+/// transaction is committed.
+pub struct CachedSession<S: BackendSession> {
+    inner: S,
+    cache: Cache,
+    profile: String,
+    profile_generation: String,
+    transaction: bool,
+    pending: Vec<String>,
+    pending_expired: bool,
+}
+
+impl<S: BackendSession> CachedSession<S> {
+    async fn invalidate(&mut self, slots: Vec<String>) {
+        if self.transaction {
+            self.pending.extend(slots);
+        } else {
+            self.cache.invalidate(slots).await;
+        }
+    }
+
+    async fn invalidate_profile(&mut self) {
+        if self.transaction {
+            self.pending_expired = true;
+        } else {
+            self.cache
+                .invalidate_profile(&self.profile_generation)
+                .await;
+        }
+    }
+}
+
+impl<S: BackendSession> Debug for CachedSession<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedSession")
+            .field("inner", &self.inner)
+            .field("profile", &self.profile)
+            .field("transaction", &self.transaction)
+            .finish()
+    }
+}
+
+impl<S: BackendSession> BackendSession for CachedSession<S> {
+    fn count<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        include_deleted: bool,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        self.inner
+            .count(kind, category, tag_filter, include_deleted)
+    }
+
+    fn count_by_category<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<BTreeMap<String, i64>, Error>> {
+        self.inner.count_by_category(kind, tag_filter)
+    }
+
+    fn list_tag_values<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        tag_name: &'q str,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<BTreeMap<String, i64>, Error>> {
+        self.inner
+            .list_tag_values(kind, category, tag_name, tag_filter)
+    }
+
+    fn fetch<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Option<Entry>, Error>> {
+        Box::pin(async move {
+            if self.transaction || for_update {
+                return self.inner.fetch(kind, category, name, for_update).await;
+            }
+            let slot = self.cache.key.slot(&self.profile, kind, category, name)?;
+            let read = match self
+                .cache
+                .get(&slot, &self.profile_generation, kind, category, name)
+                .await
+            {
+                CacheLookup::Found(entry) => return Ok(Some(entry)),
+                CacheLookup::Missing(read) => Some(read),
+                CacheLookup::Unavailable => None,
+            };
+            let entry = self.inner.fetch(kind, category, name, false).await?;
+            if let (Some(entry), Some(read)) = (entry.as_ref(), read) {
+                self.cache.put(&slot, entry, read).await;
+            }
+            Ok(entry)
+        })
+    }
+
+    fn fetch_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        self.inner.fetch_all(
+            kind, category, tag_filter, limit, order_by, descending, for_update,
+        )
+    }
+
+    fn remove_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        Box::pin(async move {
+            let rows = self
+                .inner
+                .fetch_all(kind, category, tag_filter.clone(), None, None, false, true)
+                .await?;
+            let removed = self.inner.remove_all(kind, category, tag_filter).await?;
+            let slots = rows
+                .iter()
+                .map(|row| {
+                    self.cache
+                        .key
+                        .slot(&self.profile, row.kind, &row.category, &row.name)
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            self.invalidate(slots).await;
+            Ok(removed)
+        })
+    }
+
+    fn remove_expired<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        Box::pin(async move {
+            let removed = self.inner.remove_expired(kind, category).await?;
+            if removed > 0 {
+                // the removed records are not known, so all cached records
+                // for the profile are evicted
+                self.invalidate_profile().await;
+            }
+            Ok(removed)
+        })
+    }
+
+    fn restore<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.inner.restore(kind, category, name)
+    }
+
+    fn purge<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        older_than_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        self.inner.purge(kind, older_than_ms)
+    }
+
+    fn set_history<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        enabled: bool,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.inner.set_history(kind, category, enabled)
+    }
+
+    fn fetch_history<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        limit: Option<i64>,
+    ) -> BoxFuture<'q, Result<Vec<EntryVersion>, Error>> {
+        self.inner.fetch_history(kind, category, name, limit)
+    }
+
+    fn fetch_version<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        version: i64,
+    ) -> BoxFuture<'q, Result<Option<EntryVersion>, Error>> {
+        self.inner.fetch_version(kind, category, name, version)
+    }
+
+    fn prune_history<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        older_than_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        self.inner.prune_history(kind, category, older_than_ms)
+    }
+
+    fn fetch_audit_log(
+        &mut self,
+        after_id: Option<i64>,
+        limit: Option<i64>,
+    ) -> BoxFuture<'_, Result<Vec<AuditRecord>, Error>> {
+        self.inner.fetch_audit_log(after_id, limit)
+    }
+
+    fn get_profile_meta<'q>(
+        &'q mut self,
+        name: &'q str,
+    ) -> BoxFuture<'q, Result<Option<SecretBytes>, Error>> {
+        self.inner.get_profile_meta(name)
+    }
+
+    fn set_profile_meta<'q>(
+        &'q mut self,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.inner.set_profile_meta(name, value)
+    }
+
+    fn update<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        Box::pin(async move {
+            self.inner
+                .update(kind, operation, category, name, value, tags, expiry_ms)
+                .await?;
+            let slot = self.cache.key.slot(&self.profile, kind, category, name)?;
+            self.invalidate(vec![slot]).await;
+            Ok(())
+        })
+    }
+
+    fn update_versioned<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+        version: i64,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        Box::pin(async move {
+            self.inner
+                .update_versioned(
+                    kind, operation, category, name, value, tags, expiry_ms, version,
+                )
+                .await?;
+            let slot = self.cache.key.slot(&self.profile, kind, category, name)?;
+            self.invalidate(vec![slot]).await;
+            Ok(())
+        })
+    }
+
+    fn update_many<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        updates: Vec<EntryUpdate>,
+    ) -> BoxFuture<'q, Result<Vec<Result<(), Error>>, Error>> {
+        Box::pin(async move {
+            let slots = updates
+                .iter()
+                .map(|upd| {
+                    self.cache
+                        .key
+                        .slot(&self.profile, kind, &upd.category, &upd.name)
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let results = self.inner.update_many(kind, updates).await?;
+            self.invalidate(slots).await;
+            Ok(results)
+        })
+    }
+
+    fn update_search_terms<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        terms: &'q [String],
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        self.inner.update_search_terms(kind, category, name, terms)
+    }
+
+    fn search_values<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        terms: &'q [String],
+        limit: Option<i64>,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        self.inner.search_values(kind, category, terms, limit)
+    }
+
+    fn ping(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+        self.inner.ping()
+    }
+
+    fn close(&mut self, commit: bool) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            self.inner.close(commit).await?;
+            let pending = std::mem::take(&mut self.pending);
+            let expired = std::mem::take(&mut self.pending_expired);
+            if commit {
+                self.cache.invalidate(pending).await;
+                if expired {
+                    self.cache
+                        .invalidate_profile(&self.profile_generation)
+                        .await;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Clone)]
+struct Cache {
+    conn: ConnectionManager,
+    key: Arc<CacheKey>,
+    ttl: u64,
+}
+
+impl Cache {
+    // errors in accessing the cache are logged rather than returned, as the
+    // wrapped backend remains authoritative
+
+    async fn get(
+        &mut self,
+        slot: &str,
+        profile_generation: &str,
+        kind: EntryKind,
+        category: &str,
+        name: &str,
+    ) -> CacheLookup {
+        let started = Instant::now();
+        let (ciphertext, slot_gen, profile_gen): (Option<Vec<u8>>, Option<u64>, Option<u64>) =
+            match redis::pipe()
+                .get(slot)
+                .get(generation_key(slot))
+                .get(profile_generation)
+                .query_async(&mut self.conn)
+                .await
+            {
+                Ok(found) => found,
+                Err(err) => {
+                    warn!("Error reading from Redis cache: {}", err);
+                    return CacheLookup::Unavailable;
+                }
+            };
+        let read = CacheRead {
+            generation: Generation {
+                slot: slot_gen.unwrap_or(0),
+                profile: profile_gen.unwrap_or(0),
+            },
+            started,
+        };
+        let ciphertext = match ciphertext {
+            Some(ciphertext) => ciphertext,
+            None => return CacheLookup::Missing(read),
+        };
+        match self.key.decrypt(slot, kind, category, name, ciphertext) {
+            // a record cached before the slot or profile was invalidated is ignored
+            Ok((entry, generation)) if generation == read.generation => CacheLookup::Found(entry),
+            Ok(_) => CacheLookup::Missing(read),
+            Err(err) => {
+                warn!("Error decrypting cached record: {}", err);
+                CacheLookup::Missing(read)
+            }
+        }
+    }
+
+    async fn put(&mut self, slot: &str, entry: &Entry, read: CacheRead) {
+        // a record is cached until at most one lifetime after the lookup began,
+        // while the generation set by a concurrent invalidation is retained
+        // for twice the record lifetime
+        let mut ttl_ms = (self.ttl * 1000) as i64 - read.started.elapsed().as_millis() as i64;
+        if let Some(expires_at) = entry.expires_at {
+            ttl_ms = ttl_ms.min(expires_at - chrono::Utc::now().timestamp_millis());
+        }
+        if ttl_ms <= 0 {
+            return;
+        }
+        let ciphertext = match self.key.encrypt(slot, entry, read.generation) {
+            Ok(ciphertext) => ciphertext,
+            Err(err) => {
+                warn!("Error encrypting cached record: {}", err);
+                return;
+            }
+        };
+        if let Err(err) = self
+            .conn
+            .pset_ex::<_, _, ()>(slot, ciphertext, ttl_ms as u64)
+            .await
+        {
+            warn!("Error writing to Redis cache: {}", err);
+        }
+    }
+
+    async fn invalidate_profile(&mut self, profile_generation: &str) {
+        if let Err(err) = self
+            .conn
+            .set_ex::<_, _, ()>(profile_generation, new_generation(), self.ttl * 2)
+            .await
+        {
+            warn!("Error removing records from Redis cache: {}", err);
+        }
+    }
+
+    async fn invalidate(&mut self, slots: Vec<String>) {
+        if slots.is_empty() {
+            return;
+        }
+        let mut pipe = redis::pipe();
+        for slot in slots {
+            pipe.set_ex(generation_key(&slot), new_generation(), self.ttl * 2)
+                .ignore()
+                .del(slot)
+                .ignore();
+        }
+        if let Err(err) = pipe.query_async::<()>(&mut self.conn).await {
+            warn!("Error removing records from Redis cache: {}", err);
+        }
+    }
+}
+
+/// The result of looking up a record in the cache
+enum CacheLookup {
+    Found(Entry),
+    Missing(CacheRead),
+    Unavailable,
+}
+
+/// The state of a cache slot observed when a record was not found
+struct CacheRead {
+    generation: Generation,
+    started: Instant,
+}
+
+/// The generations of a cache slot and its profile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Generation {
+    slot: u64,
+    profile: u64,
+}
+
+fn generation_key(slot: &str) -> String {
+    format!(
+        "{}{}",
+        GENERATION_PREFIX,
+        slot.strip_prefix(KEY_PREFIX).unwrap_or(slot)
+    )
+}
+
+/// Create a random generation, which may not match the generation of a
+/// record cached before the slot or profile was invalidated
+fn new_generation() -> u64 {
+    let mut generation = [0u8; 8];
+    fill_random(&mut generation);
+    u64::from_le_bytes(generation)
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedEntry {
+    value: SecretBytes,
+    tags: Vec<CachedTag>,
+    #[serde(default)]
+    version: Option<i64>,
+    #[serde(default)]
+    created_at: Option<i64>,
+    #[serde(default)]
+    updated_at: Option<i64>,
+    #[serde(default)]
+    expires_at: Option<i64>,
+    #[serde(default)]
+    generation: u64,
+    #[serde(default)]
+    profile_generation: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedTag {
+    name: String,
+    value: String,
+    plaintext: bool,
+}
+
+impl From<&EntryTag> for CachedTag {
+    fn from(tag: &EntryTag) -> Self {
+        match tag {
+            EntryTag::Encrypted(name, value) => Self {
+                name: name.clone(),
+                value: value.clone(),
+                plaintext: false,
+            },
+            EntryTag::Plaintext(name, value) => Self {
+                name: name.clone(),
+                value: value.clone(),
+                plaintext: true,
+            },
+        }
+    }
+}
+
+impl From<CachedTag> for EntryTag {
+    fn from(tag: CachedTag) -> Self {
+        if tag.plaintext {
+            EntryTag::Plaintext(tag.name, tag.value)
+        } else {
+            EntryTag::Encrypted(tag.name, tag.value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_record_round_trip() {
+        let key = CacheKey::random().unwrap();
+        let entry = Entry::new(
+            EntryKind::Item,
+            "category",
+            "name",
+            "value",
+            vec![
+                EntryTag::Encrypted("enc".to_string(), "a".to_string()),
+                EntryTag::Plaintext("plain".to_string(), "b".to_string()),
+            ],
+        );
+        let slot = key
+            .slot("profile", EntryKind::Item, "category", "name")
+            .unwrap();
+        let entry = entry.with_expiry(Some(1_700_000_000_000));
+        let expected = Generation {
+            slot: 5,
+            profile: 7,
+        };
+        let enc = key.encrypt(&slot, &entry, expected).unwrap();
+        let (dec, generation) = key
+            .decrypt(&slot, EntryKind::Item, "category", "name", enc.clone())
+            .unwrap();
+        assert_eq!(dec, entry);
+        assert_eq!(dec.expires_at, entry.expires_at);
+        assert_eq!(generation, expected);
+
+        // a record may not be moved to another slot
+        let other = key
+            .slot("other", EntryKind::Item, "category", "name")
+            .unwrap();
+        assert_ne!(slot, other);
+        assert!(key
+            .decrypt(&other, EntryKind::Item, "category", "name", enc)
+            .is_err());
+    }
+
+    #[test]
+    fn cache_generation_key() {
+        let key = CacheKey::random().unwrap();
+        let slot = key
+            .slot("profile", EntryKind::Item, "category", "name")
+            .unwrap();
+        let gen_key = generation_key(&slot);
+        assert!(gen_key.starts_with(GENERATION_PREFIX));
+        assert_eq!(
+            &gen_key[GENERATION_PREFIX.len()..],
+            &slot[KEY_PREFIX.len()..]
+        );
+
+        let profile_gen = key.profile_generation_key("profile").unwrap();
+        assert!(profile_gen.starts_with(PROFILE_GENERATION_PREFIX));
+        assert_ne!(profile_gen, key.profile_generation_key("other").unwrap());
+    }
+
+    #[test]
+    fn cache_key_from_seed() {
+        let key = CacheKey::from_seed(b"testseed").unwrap();
+        let same = CacheKey::from_seed(b"testseed").unwrap();
+        assert_eq!(
+            key.slot("profile", EntryKind::Item, "category", "name")
+                .unwrap(),
+            same.slot("profile", EntryKind::Item, "category", "name")
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    time::Duration,
+};
+
+use reqwest::{header::CONTENT_TYPE, Client};
+
+use super::{
+    protocol::{decode_response, encode_request, RemoteRequest, RemoteResponse},
+    RemoteTransport,
+};
+use crate::{error::Error, future::BoxFuture};
+
+const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// A transport posting CBOR-encoded requests to an HTTP endpoint
+pub struct HttpTransport {
+    client: Client,
+    url: String,
+    token: Option<String>,
+}
+
+impl HttpTransport {
+    /// Create a new transport for the given endpoint URL
+    pub fn new(url: String, token: Option<String>, timeout: Duration) -> Result<Self, Error> {
+        let client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(err_map!(Backend, "Error creating HTTP client"))?;
+        Ok(Self { client, url, token })
+    }
+}
+
+impl Debug for HttpTransport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpTransport")
+            .field("url", &self.url)
+            .finish()
+    }
+}
+
+impl RemoteTransport for HttpTransport {
+    fn call(&self, request: RemoteRequest) -> BoxFuture<'_, Result<RemoteResponse, Error>> {
+        Box::pin(async move {
+            let body = encode_request(&request)?;
+            let mut req = self
+                .client
+                .post(&self.url)
+                .header(CONTENT_TYPE, CBOR_CONTENT_TYPE)
+                .body(body);
+            if let Some(token) = self.token.as_ref() {
+                req = req.bearer_auth(token);
+            }
+            let response = req.send().await.map_err(|err| {
+                if err.is_timeout() {
+                    err_msg!(Busy, "Timed out waiting for the storage service").with_cause(err)
+                } else {
+                    err_msg!(Backend, "Error sending request to the storage service")
+                        .with_cause(err)
+                }
+            })?;
+            let status = response.status();
+            if status == reqwest::StatusCode::UNAUTHORIZED
+                || status == reqwest::StatusCode::FORBIDDEN
+            {
+                return Err(err_msg!(
+                    Input,
+                    "The storage service rejected the access token"
+                ));
+            }
+            if !status.is_success() {
+                return Err(err_msg!(
+                    Backend,
+                    "Unexpected response status from the storage service: {}",
+                    status
+                ));
+            }
+            let body = response.bytes().await.map_err(err_map!(
+                Backend,
+                "Error receiving storage service response"
+            ))?;
+            decode_response(&body)
+        })
+    }
+}
@@ -0,0 +1,509 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+use futures_lite::stream;
+
+use super::{Backend, BackendSession, OrderBy};
+use crate::{
+    entry::{Entry, EntryKind, EntryOperation, EntryTag, Scan, ScanPosition, TagFilter},
+    error::Error,
+    future::{spawn_ok, BoxFuture},
+    protect::{PassKey, StoreKeyMethod},
+};
+
+mod http;
+pub use self::http::HttpTransport;
+
+pub mod protocol;
+use self::protocol::{
+    encode_operation, encode_order_by, encode_tag_filter, unexpected_response, RemoteRequest,
+    RemoteResponse,
+};
+
+mod provision;
+pub use self::provision::RemoteStoreOptions;
+
+mod service;
+pub use self::service::RemoteService;
+
+// page size reported for remote scans, the batches themselves are
+// determined by the storage service
+const PAGE_SIZE: usize = 32;
+
+/// A transport carrying requests to a remote storage service
+pub trait RemoteTransport: Debug + Send + Sync {
+    /// Perform a single request
+    fn call(&self, request: RemoteRequest) -> BoxFuture<'_, Result<RemoteResponse, Error>>;
+}
+
+/// A store accessed through a remote storage service
+pub struct RemoteBackend {
+    transport: Arc<dyn RemoteTransport>,
+    active_profile: String,
+}
+
+impl RemoteBackend {
+    /// Connect to a remote storage service using the given transport
+    pub async fn connect(
+        transport: impl RemoteTransport + 'static,
+        profile: Option<String>,
+    ) -> Result<Self, Error> {
+        let transport: Arc<dyn RemoteTransport> = Arc::new(transport);
+        match call(&transport, RemoteRequest::Open { profile }).await? {
+            RemoteResponse::Profile(active_profile) => Ok(Self {
+                transport,
+                active_profile,
+            }),
+            other => Err(unexpected_response(other)),
+        }
+    }
+
+    // start a scan on the storage service, fetching each batch on demand
+    async fn open_scan(&self, request: RemoteRequest) -> Result<Scan<'static, Entry>, Error> {
+        let handle = match call(&self.transport, request).await? {
+            RemoteResponse::Handle(handle) => handle,
+            other => return Err(unexpected_response(other)),
+        };
+        let scan = RemoteScan {
+            transport: self.transport.clone(),
+            handle,
+            done: false,
+        };
+        let stream = stream::unfold(scan, |mut scan| async move {
+            if scan.done {
+                return None;
+            }
+            let request = RemoteRequest::ScanNext { scan: scan.handle };
+            match call(&scan.transport, request).await {
+                Ok(RemoteResponse::Batch(Some(rows))) => {
+                    let rows = rows
+                        .into_iter()
+                        .map(Entry::try_from)
+                        .collect::<Result<Vec<_>, _>>();
+                    if rows.is_err() {
+                        scan.done = true;
+                    }
+                    Some((rows, scan))
+                }
+                Ok(RemoteResponse::Batch(None)) => {
+                    // the service releases a finished scan
+                    scan.done = true;
+                    drop(scan);
+                    None
+                }
+                Ok(other) => {
+                    scan.done = true;
+                    Some((Err(unexpected_response(other)), scan))
+                }
+                Err(err) => {
+                    // the service releases a scan after a failure
+                    scan.done = true;
+                    Some((Err(err), scan))
+                }
+            }
+        });
+        Ok(Scan::new(stream, PAGE_SIZE))
+    }
+}
+
+impl Debug for RemoteBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteStore")
+            .field("transport", &self.transport)
+            .field("active_profile", &self.active_profile)
+            .finish()
+    }
+}
+
+impl Backend for RemoteBackend {
+    type Session = RemoteSession;
+
+    fn create_profile(&self, name: Option<String>) -> BoxFuture<'_, Result<String, Error>> {
+        Box::pin(async move {
+            match call(&self.transport, RemoteRequest::CreateProfile { name }).await? {
+                RemoteResponse::Profile(name) => Ok(name),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn get_active_profile(&self) -> String {
+        self.active_profile.clone()
+    }
+
+    fn get_default_profile(&self) -> BoxFuture<'_, Result<String, Error>> {
+        Box::pin(async move {
+            match call(&self.transport, RemoteRequest::GetDefaultProfile).await? {
+                RemoteResponse::Profile(name) => Ok(name),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn set_default_profile(&self, profile: String) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            match call(
+                &self.transport,
+                RemoteRequest::SetDefaultProfile { profile },
+            )
+            .await?
+            {
+                RemoteResponse::Done => Ok(()),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn list_profiles(&self) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        Box::pin(async move {
+            match call(&self.transport, RemoteRequest::ListProfiles).await? {
+                RemoteResponse::Profiles(names) => Ok(names),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn remove_profile(&self, name: String) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async move {
+            match call(&self.transport, RemoteRequest::RemoveProfile { name }).await? {
+                RemoteResponse::Removed(removed) => Ok(removed),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn rekey(
+        &mut self,
+        _method: StoreKeyMethod,
+        _pass_key: PassKey<'_>,
+    ) -> BoxFuture<'_, Result<(), Error>> {
+        // the store key is managed by the storage service, and is never
+        // transferred to clients
+        Box::pin(std::future::ready(Err(err_msg!(
+            Unsupported,
+            "Remote stores must be rekeyed by the storage service"
+        ))))
+    }
+
+    fn scan(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(async move {
+            let request = RemoteRequest::Scan {
+                profile: Some(profile.unwrap_or_else(|| self.active_profile.clone())),
+                kind: kind.map(|k| k as usize),
+                category,
+                tag_filter: encode_tag_filter(tag_filter)?,
+                offset,
+                after: None,
+                limit,
+                order_by: encode_order_by(order_by),
+                descending,
+                include_deleted,
+            };
+            self.open_scan(request).await
+        })
+    }
+
+    fn scan_after(
+        &self,
+        profile: Option<String>,
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<TagFilter>,
+        after: ScanPosition,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+    ) -> BoxFuture<'_, Result<Scan<'static, Entry>, Error>> {
+        Box::pin(async move {
+            let request = RemoteRequest::Scan {
+                profile: Some(profile.unwrap_or_else(|| self.active_profile.clone())),
+                kind: kind.map(|k| k as usize),
+                category,
+                tag_filter: encode_tag_filter(tag_filter)?,
+                offset: None,
+                after: Some(after),
+                limit,
+                order_by: encode_order_by(order_by),
+                descending,
+                include_deleted,
+            };
+            self.open_scan(request).await
+        })
+    }
+
+    fn seal_token(&self, data: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        Box::pin(async move {
+            match call(&self.transport, RemoteRequest::SealToken { data }).await? {
+                RemoteResponse::Token(token) => Ok(token),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn open_token(&self, token: Vec<u8>) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+        Box::pin(async move {
+            match call(&self.transport, RemoteRequest::OpenToken { token }).await? {
+                RemoteResponse::Token(data) => Ok(data),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn session(&self, profile: Option<String>, transaction: bool) -> Result<Self::Session, Error> {
+        Ok(RemoteSession {
+            transport: self.transport.clone(),
+            profile: profile.unwrap_or_else(|| self.active_profile.clone()),
+            transaction,
+            handle: None,
+        })
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+/// A session against a remote store
+///
+/// The session is opened by the storage service when it is first used.
+pub struct RemoteSession {
+    transport: Arc<dyn RemoteTransport>,
+    profile: String,
+    transaction: bool,
+    handle: Option<u64>,
+}
+
+impl RemoteSession {
+    async fn handle(&mut self) -> Result<u64, Error> {
+        if let Some(handle) = self.handle {
+            return Ok(handle);
+        }
+        let request = RemoteRequest::SessionOpen {
+            profile: Some(self.profile.clone()),
+            transaction: self.transaction,
+        };
+        match call(&self.transport, request).await? {
+            RemoteResponse::Handle(handle) => {
+                self.handle.replace(handle);
+                Ok(handle)
+            }
+            other => Err(unexpected_response(other)),
+        }
+    }
+}
+
+impl Debug for RemoteSession {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteSession")
+            .field("profile", &self.profile)
+            .field("transaction", &self.transaction)
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl Drop for RemoteSession {
+    fn drop(&mut self) {
+        if let Some(session) = self.handle.take() {
+            let transport = self.transport.clone();
+            spawn_ok(async move {
+                debug!("Dropped remote session: roll-back");
+                let request = RemoteRequest::SessionClose {
+                    session,
+                    commit: false,
+                };
+                if let Err(err) = call(&transport, request).await {
+                    warn!("Error closing dropped remote session: {}", err);
+                }
+            });
+        }
+    }
+}
+
+impl BackendSession for RemoteSession {
+    fn count<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        include_deleted: bool,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        Box::pin(async move {
+            let request = RemoteRequest::Count {
+                session: self.handle().await?,
+                kind: kind.map(|k| k as usize),
+                category: category.map(str::to_string),
+                tag_filter: encode_tag_filter(tag_filter)?,
+                include_deleted,
+            };
+            match call(&self.transport, request).await? {
+                RemoteResponse::Count(count) => Ok(count),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn fetch<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        category: &'q str,
+        name: &'q str,
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Option<Entry>, Error>> {
+        Box::pin(async move {
+            let request = RemoteRequest::Fetch {
+                session: self.handle().await?,
+                kind: kind as usize,
+                category: category.to_string(),
+                name: name.to_string(),
+                for_update,
+            };
+            match call(&self.transport, request).await? {
+                RemoteResponse::Entry(entry) => entry.map(Entry::try_from).transpose(),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn fetch_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+        limit: Option<i64>,
+        order_by: Option<OrderBy>,
+        descending: bool,
+        for_update: bool,
+    ) -> BoxFuture<'q, Result<Vec<Entry>, Error>> {
+        Box::pin(async move {
+            let request = RemoteRequest::FetchAll {
+                session: self.handle().await?,
+                kind: kind.map(|k| k as usize),
+                category: category.map(str::to_string),
+                tag_filter: encode_tag_filter(tag_filter)?,
+                limit,
+                order_by: encode_order_by(order_by),
+                descending,
+                for_update,
+            };
+            match call(&self.transport, request).await? {
+                RemoteResponse::Entries(rows) => rows.into_iter().map(Entry::try_from).collect(),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn remove_all<'q>(
+        &'q mut self,
+        kind: Option<EntryKind>,
+        category: Option<&'q str>,
+        tag_filter: Option<TagFilter>,
+    ) -> BoxFuture<'q, Result<i64, Error>> {
+        Box::pin(async move {
+            let request = RemoteRequest::RemoveAll {
+                session: self.handle().await?,
+                kind: kind.map(|k| k as usize),
+                category: category.map(str::to_string),
+                tag_filter: encode_tag_filter(tag_filter)?,
+            };
+            match call(&self.transport, request).await? {
+                RemoteResponse::Count(count) => Ok(count),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn update<'q>(
+        &'q mut self,
+        kind: EntryKind,
+        operation: EntryOperation,
+        category: &'q str,
+        name: &'q str,
+        value: Option<&'q [u8]>,
+        tags: Option<&'q [EntryTag]>,
+        expiry_ms: Option<i64>,
+    ) -> BoxFuture<'q, Result<(), Error>> {
+        Box::pin(async move {
+            let request = RemoteRequest::Update {
+                session: self.handle().await?,
+                kind: kind as usize,
+                operation: encode_operation(operation),
+                category: category.to_string(),
+                name: name.to_string(),
+                value: value.map(<[u8]>::to_vec),
+                tags: tags.map(|tags| tags.iter().cloned().map(Into::into).collect()),
+                expiry_ms,
+            };
+            match call(&self.transport, request).await? {
+                RemoteResponse::Done => Ok(()),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn ping(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            let request = RemoteRequest::Ping {
+                session: self.handle().await?,
+            };
+            match call(&self.transport, request).await? {
+                RemoteResponse::Done => Ok(()),
+                other => Err(unexpected_response(other)),
+            }
+        })
+    }
+
+    fn close(&mut self, commit: bool) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            if let Some(session) = self.handle.take() {
+                let request = RemoteRequest::SessionClose { session, commit };
+                match call(&self.transport, request).await? {
+                    RemoteResponse::Done => (),
+                    other => return Err(unexpected_response(other)),
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+struct RemoteScan {
+    transport: Arc<dyn RemoteTransport>,
+    handle: u64,
+    done: bool,
+}
+
+impl Drop for RemoteScan {
+    fn drop(&mut self) {
+        if !self.done {
+            let transport = self.transport.clone();
+            let scan = self.handle;
+            spawn_ok(async move {
+                if let Err(err) = call(&transport, RemoteRequest::ScanClose { scan }).await {
+                    warn!("Error closing dropped remote scan: {}", err);
+                }
+            });
+        }
+    }
+}
+
+async fn call(
+    transport: &Arc<dyn RemoteTransport>,
+    request: RemoteRequest,
+) -> Result<RemoteResponse, Error> {
+    transport.call(request).await?.into_result()
+}
@@ -0,0 +1,427 @@
+//! Wire protocol for remote store access
+//!
+//! Each call is a single CBOR-encoded [`RemoteRequest`], answered by a single
+//! CBOR-encoded [`RemoteResponse`]. Sessions and scans are held by the storage
+//! service and referenced by numeric handles.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::OrderBy,
+    entry::{Entry, EntryKind, EntryOperation, EntryTag, ScanPosition, TagFilter},
+    error::{Error, ErrorKind},
+};
+
+/// A request made to a remote storage service
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum RemoteRequest {
+    /// Resolve the active profile for a new client, defaulting to the
+    /// default profile of the store
+    Open { profile: Option<String> },
+    /// Create a new profile
+    CreateProfile { name: Option<String> },
+    /// Fetch the default profile name
+    GetDefaultProfile,
+    /// Update the default profile name
+    SetDefaultProfile { profile: String },
+    /// List the profile names
+    ListProfiles,
+    /// Remove a profile
+    RemoveProfile { name: String },
+    /// Encrypt data issued by the store as an opaque token
+    SealToken {
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    },
+    /// Decrypt a token issued by the store
+    OpenToken {
+        #[serde(with = "serde_bytes")]
+        token: Vec<u8>,
+    },
+    /// Start a new scan, returning a scan handle
+    Scan {
+        profile: Option<String>,
+        kind: Option<usize>,
+        category: Option<String>,
+        tag_filter: Option<String>,
+        offset: Option<i64>,
+        /// The position to resume the scan from, in place of an offset
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        after: Option<ScanPosition>,
+        limit: Option<i64>,
+        order_by: Option<String>,
+        descending: bool,
+        /// Whether to include records which have been soft deleted
+        #[serde(default)]
+        include_deleted: bool,
+    },
+    /// Fetch the next batch of scan results
+    ScanNext { scan: u64 },
+    /// Release an unfinished scan
+    ScanClose { scan: u64 },
+    /// Start a new session, returning a session handle
+    SessionOpen {
+        profile: Option<String>,
+        transaction: bool,
+    },
+    /// Count the matching records in a session
+    Count {
+        session: u64,
+        kind: Option<usize>,
+        category: Option<String>,
+        tag_filter: Option<String>,
+        /// Whether to include records which have been soft deleted
+        #[serde(default)]
+        include_deleted: bool,
+    },
+    /// Fetch a single record in a session
+    Fetch {
+        session: u64,
+        kind: usize,
+        category: String,
+        name: String,
+        for_update: bool,
+    },
+    /// Fetch all matching records in a session
+    FetchAll {
+        session: u64,
+        kind: Option<usize>,
+        category: Option<String>,
+        tag_filter: Option<String>,
+        limit: Option<i64>,
+        order_by: Option<String>,
+        descending: bool,
+        for_update: bool,
+    },
+    /// Remove all matching records in a session
+    RemoveAll {
+        session: u64,
+        kind: Option<usize>,
+        category: Option<String>,
+        tag_filter: Option<String>,
+    },
+    /// Insert, replace or remove a record in a session
+    Update {
+        session: u64,
+        kind: usize,
+        operation: String,
+        category: String,
+        name: String,
+        #[serde(with = "serde_bytes")]
+        value: Option<Vec<u8>>,
+        tags: Option<Vec<RemoteTag>>,
+        expiry_ms: Option<i64>,
+    },
+    /// Test the session connection
+    Ping { session: u64 },
+    /// Close a session, committing or rolling back any transaction
+    SessionClose { session: u64, commit: bool },
+}
+
+/// A response from a remote storage service
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "result", content = "value", rename_all = "snake_case")]
+pub enum RemoteResponse {
+    /// The operation completed without a result
+    Done,
+    /// A profile name
+    Profile(String),
+    /// A list of profile names
+    Profiles(Vec<String>),
+    /// Whether a record or profile was removed
+    Removed(bool),
+    /// A session or scan handle
+    Handle(u64),
+    /// A record count
+    Count(i64),
+    /// An encrypted token or its decrypted contents
+    Token(#[serde(with = "serde_bytes")] Vec<u8>),
+    /// A single record, if found
+    Entry(Option<RemoteEntry>),
+    /// A list of records
+    Entries(Vec<RemoteEntry>),
+    /// The next batch of scan results, or `None` when the scan is finished
+    Batch(Option<Vec<RemoteEntry>>),
+    /// The operation failed
+    Error(RemoteError),
+}
+
+impl RemoteResponse {
+    /// Convert an error response into an `Error`
+    pub fn into_result(self) -> Result<Self, Error> {
+        match self {
+            Self::Error(err) => Err(err.into()),
+            other => Ok(other),
+        }
+    }
+}
+
+impl From<Error> for RemoteResponse {
+    fn from(err: Error) -> Self {
+        Self::Error(RemoteError {
+            kind: error_code(err.kind()).to_string(),
+            message: err.message().map(str::to_string),
+        })
+    }
+}
+
+/// An error reported by a remote storage service
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteError {
+    /// The error kind code
+    pub kind: String,
+    /// The error message, if any
+    pub message: Option<String>,
+}
+
+impl From<RemoteError> for Error {
+    fn from(err: RemoteError) -> Self {
+        let kind = error_kind(&err.kind);
+        Error::from_msg(
+            kind,
+            err.message.unwrap_or_else(|| kind.as_str().to_string()),
+        )
+    }
+}
+
+/// A record transferred to or from a remote storage service
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteEntry {
+    /// The entry kind discriminator
+    pub kind: usize,
+    /// The category of the entry record
+    pub category: String,
+    /// The name of the entry record
+    pub name: String,
+    /// The value of the entry record
+    #[serde(with = "serde_bytes")]
+    pub value: Vec<u8>,
+    /// Tags associated with the entry record
+    pub tags: Vec<RemoteTag>,
+    /// The creation time of the entry record in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+    /// The last modification time of the entry record in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+    /// The expiry time of the entry record in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// The time the entry record was soft deleted in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<i64>,
+    /// The backend identifier of the entry record, when returned by a scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+}
+
+impl From<Entry> for RemoteEntry {
+    fn from(entry: Entry) -> Self {
+        Self {
+            kind: entry.kind as usize,
+            category: entry.category,
+            name: entry.name,
+            value: entry.value.as_ref().to_vec(),
+            tags: entry.tags.into_iter().map(Into::into).collect(),
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+            expires_at: entry.expires_at,
+            deleted_at: entry.deleted_at,
+            id: entry.row_id,
+        }
+    }
+}
+
+impl TryFrom<RemoteEntry> for Entry {
+    type Error = Error;
+
+    fn try_from(entry: RemoteEntry) -> Result<Self, Error> {
+        Ok(Entry::new(
+            EntryKind::try_from(entry.kind)?,
+            entry.category,
+            entry.name,
+            entry.value,
+            entry.tags.into_iter().map(Into::into).collect(),
+        )
+        .with_timestamps(entry.created_at, entry.updated_at)
+        .with_expiry(entry.expires_at)
+        .with_deleted_at(entry.deleted_at)
+        .with_row_id(entry.id))
+    }
+}
+
+/// A record tag transferred to or from a remote storage service
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteTag {
+    /// The tag name
+    pub name: String,
+    /// The tag value
+    pub value: String,
+    /// Whether the tag is stored unencrypted
+    pub plaintext: bool,
+}
+
+impl From<EntryTag> for RemoteTag {
+    fn from(tag: EntryTag) -> Self {
+        match tag {
+            EntryTag::Encrypted(name, value) => Self {
+                name,
+                value,
+                plaintext: false,
+            },
+            EntryTag::Plaintext(name, value) => Self {
+                name,
+                value,
+                plaintext: true,
+            },
+        }
+    }
+}
+
+impl From<RemoteTag> for EntryTag {
+    fn from(tag: RemoteTag) -> Self {
+        if tag.plaintext {
+            Self::Plaintext(tag.name, tag.value)
+        } else {
+            Self::Encrypted(tag.name, tag.value)
+        }
+    }
+}
+
+/// Encode a request for transmission
+pub fn encode_request(request: &RemoteRequest) -> Result<Vec<u8>, Error> {
+    serde_cbor::to_vec(request).map_err(err_map!(Unexpected, "Error encoding remote request"))
+}
+
+/// Decode a received request
+pub fn decode_request(body: &[u8]) -> Result<RemoteRequest, Error> {
+    serde_cbor::from_slice(body).map_err(err_map!(Input, "Error decoding remote request"))
+}
+
+/// Encode a response for transmission
+pub fn encode_response(response: &RemoteResponse) -> Result<Vec<u8>, Error> {
+    serde_cbor::to_vec(response).map_err(err_map!(Unexpected, "Error encoding remote response"))
+}
+
+/// Decode a received response
+pub fn decode_response(body: &[u8]) -> Result<RemoteResponse, Error> {
+    serde_cbor::from_slice(body).map_err(err_map!(Backend, "Error decoding remote response"))
+}
+
+pub(crate) fn unexpected_response(response: RemoteResponse) -> Error {
+    match response.into_result() {
+        Ok(response) => err_msg!(Backend, "Unexpected remote response: {:?}", response),
+        Err(err) => err,
+    }
+}
+
+pub(crate) fn encode_tag_filter(tag_filter: Option<TagFilter>) -> Result<Option<String>, Error> {
+    tag_filter
+        .map(|filter| {
+            serde_json::to_string(&filter.query)
+                .map_err(err_map!(Unexpected, "Error encoding tag filter"))
+        })
+        .transpose()
+}
+
+pub(crate) fn decode_tag_filter(tag_filter: Option<String>) -> Result<Option<TagFilter>, Error> {
+    tag_filter.as_deref().map(TagFilter::from_str).transpose()
+}
+
+pub(crate) fn encode_order_by(order_by: Option<OrderBy>) -> Option<String> {
+    order_by.map(|order_by| order_by.to_string())
+}
+
+pub(crate) fn decode_order_by(order_by: Option<String>) -> Result<Option<OrderBy>, Error> {
+    order_by.as_deref().map(OrderBy::from_str).transpose()
+}
+
+pub(crate) fn encode_operation(operation: EntryOperation) -> String {
+    match operation {
+        EntryOperation::Insert => "insert",
+        EntryOperation::Replace => "replace",
+        EntryOperation::Remove => "remove",
+        EntryOperation::InsertOrReplace => "upsert",
+    }
+    .to_string()
+}
+
+pub(crate) fn decode_operation(operation: &str) -> Result<EntryOperation, Error> {
+    match operation {
+        "insert" => Ok(EntryOperation::Insert),
+        "replace" => Ok(EntryOperation::Replace),
+        "remove" => Ok(EntryOperation::Remove),
+        "upsert" => Ok(EntryOperation::InsertOrReplace),
+        _ => Err(err_msg!(
+            Unsupported,
+            "Unsupported operation: {}",
+            operation
+        )),
+    }
+}
+
+fn error_code(kind: ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::Backend => "backend",
+        ErrorKind::Busy => "busy",
+        ErrorKind::Conflict => "conflict",
+        ErrorKind::Custom => "custom",
+        ErrorKind::Duplicate => "duplicate",
+        ErrorKind::Encryption => "encryption",
+        ErrorKind::Input => "input",
+        ErrorKind::NotFound => "not_found",
+        ErrorKind::Unexpected => "unexpected",
+        ErrorKind::Unsupported => "unsupported",
+    }
+}
+
+fn error_kind(code: &str) -> ErrorKind {
+    match code {
+        "backend" => ErrorKind::Backend,
+        "busy" => ErrorKind::Busy,
+        "conflict" => ErrorKind::Conflict,
+        "custom" => ErrorKind::Custom,
+        "duplicate" => ErrorKind::Duplicate,
+        "encryption" => ErrorKind::Encryption,
+        "input" => ErrorKind::Input,
+        "not_found" => ErrorKind::NotFound,
+        "unsupported" => ErrorKind::Unsupported,
+        _ => ErrorKind::Unexpected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_request_round_trip() {
+        let request = RemoteRequest::Update {
+            session: 5,
+            kind: EntryKind::Item as usize,
+            operation: encode_operation(EntryOperation::Replace),
+            category: "category".to_string(),
+            name: "name".to_string(),
+            value: Some(b"value".to_vec()),
+            tags: Some(vec![
+                EntryTag::Plaintext("t".to_string(), "v".to_string()).into()
+            ]),
+            expiry_ms: None,
+        };
+        let body = encode_request(&request).unwrap();
+        assert_eq!(decode_request(&body).unwrap(), request);
+    }
+
+    #[test]
+    fn remote_error_round_trip() {
+        let response = RemoteResponse::from(err_msg!(Duplicate, "Duplicate entry"));
+        let body = encode_response(&response).unwrap();
+        let err = decode_response(&body).unwrap().into_result().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Duplicate);
+        assert_eq!(err.message(), Some("Duplicate entry"));
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    time::Duration,
+};
+
+use super::{HttpTransport, RemoteBackend};
+use crate::{
+    backend::ManageBackend,
+    error::Error,
+    future::BoxFuture,
+    options::IntoOptions,
+    protect::{PassKey, StoreKeyMethod},
+};
+
+const DEFAULT_TIMEOUT: u64 = 30;
+
+/// Configuration options for remote stores
+pub struct RemoteStoreOptions {
+    pub(crate) url: String,
+    pub(crate) token: Option<String>,
+    pub(crate) timeout: Duration,
+}
+
+impl RemoteStoreOptions {
+    /// Initialize `RemoteStoreOptions` from a generic set of options
+    ///
+    /// A URI of the form `remote://<host>[:<port>]/<path>` is mapped to an
+    /// HTTPS endpoint, unless the `tls` parameter is set to `false`.
+    pub fn new<'a>(options: impl IntoOptions<'a>) -> Result<Self, Error> {
+        let mut opts = options.into_options()?;
+        if opts.host.is_empty() {
+            return Err(err_msg!(Input, "Storage service host not provided"));
+        }
+        let tls = if let Some(tls) = opts.query.remove("tls") {
+            tls.parse()
+                .map_err(err_map!(Input, "Error parsing 'tls' parameter"))?
+        } else {
+            true
+        };
+        let timeout = if let Some(timeout) = opts.query.remove("timeout") {
+            timeout
+                .parse()
+                .map_err(err_map!(Input, "Error parsing 'timeout' parameter"))?
+        } else {
+            DEFAULT_TIMEOUT
+        };
+        let token = opts.query.remove("token");
+        let url = format!(
+            "{}://{}{}",
+            if tls { "https" } else { "http" },
+            opts.host,
+            opts.path
+        );
+        Ok(Self {
+            url,
+            token,
+            timeout: Duration::from_secs(timeout),
+        })
+    }
+
+    /// Provisioning of remote stores is performed by the storage service
+    pub async fn provision(
+        self,
+        _method: StoreKeyMethod,
+        _pass_key: PassKey<'_>,
+        _profile: Option<String>,
+        _recreate: bool,
+    ) -> Result<RemoteBackend, Error> {
+        Err(err_msg!(
+            Unsupported,
+            "Remote stores must be provisioned by the storage service"
+        ))
+    }
+
+    /// Open a remote store from this set of configuration options
+    ///
+    /// The store key is held by the storage service, so a pass key may not
+    /// be provided.
+    pub async fn open(
+        self,
+        _method: Option<StoreKeyMethod>,
+        pass_key: PassKey<'_>,
+        profile: Option<String>,
+    ) -> Result<RemoteBackend, Error> {
+        if !pass_key.is_empty() {
+            return Err(err_msg!(
+                Input,
+                "Remote stores are unlocked by the storage service"
+            ));
+        }
+        let transport = HttpTransport::new(self.url, self.token, self.timeout)?;
+        RemoteBackend::connect(transport, profile).await
+    }
+
+    /// Removal of remote stores is performed by the storage service
+    pub async fn remove(self) -> Result<bool, Error> {
+        Err(err_msg!(
+            Unsupported,
+            "Remote stores must be removed by the storage service"
+        ))
+    }
+}
+
+impl Debug for RemoteStoreOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteStoreOptions")
+            .field("url", &self.url)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl<'a> ManageBackend<'a> for RemoteStoreOptions {
+    type Backend = RemoteBackend;
+
+    fn open_backend(
+        self,
+        method: Option<StoreKeyMethod>,
+        pass_key: PassKey<'a>,
+        profile: Option<String>,
+    ) -> BoxFuture<'a, Result<RemoteBackend, Error>> {
+        Box::pin(self.open(method, pass_key, profile))
+    }
+
+    fn provision_backend(
+        self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'a>,
+        profile: Option<String>,
+        recreate: bool,
+    ) -> BoxFuture<'a, Result<RemoteBackend, Error>> {
+        Box::pin(self.provision(method, pass_key, profile, recreate))
+    }
+
+    fn remove_backend(self) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(self.remove())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_parse_uri() {
+        let opts = RemoteStoreOptions::new(
+            "remote://storage.example.com:8443/askar/v1?token=abc&timeout=5",
+        )
+        .unwrap();
+        assert_eq!(opts.url, "https://storage.example.com:8443/askar/v1");
+        assert_eq!(opts.token.as_deref(), Some("abc"));
+        assert_eq!(opts.timeout, Duration::from_secs(5));
+
+        let opts = RemoteStoreOptions::new("remote://localhost:8080?tls=false").unwrap();
+        assert_eq!(opts.url, "http://localhost:8080");
+        assert_eq!(opts.token, None);
+    }
+}
@@ -0,0 +1,365 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use async_lock::Mutex;
+
+use super::{
+    protocol::{
+        decode_operation, decode_order_by, decode_request, decode_response, decode_tag_filter,
+        encode_request, encode_response, RemoteEntry, RemoteRequest, RemoteResponse,
+    },
+    RemoteTransport,
+};
+use crate::{
+    backend::{Backend, BackendSession},
+    entry::{Entry, EntryKind, EntryTag, Scan},
+    error::Error,
+    future::BoxFuture,
+};
+
+/// A storage service handling remote requests against a local backend
+///
+/// The service holds the sessions and scans opened by its clients until they
+/// are closed or finished. Authentication of clients is left to the hosting
+/// server.
+pub struct RemoteService<B: Backend> {
+    backend: B,
+    sessions: Mutex<HashMap<u64, Arc<Mutex<B::Session>>>>,
+    scans: Mutex<HashMap<u64, Arc<Mutex<Scan<'static, Entry>>>>>,
+    next_handle: AtomicU64,
+}
+
+impl<B: Backend> RemoteService<B> {
+    /// Create a new service for a backend instance
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            sessions: Mutex::new(HashMap::new()),
+            scans: Mutex::new(HashMap::new()),
+            next_handle: AtomicU64::new(1),
+        }
+    }
+
+    /// Access the backend instance
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Handle an encoded request, producing an encoded response
+    pub async fn handle_bytes(&self, body: &[u8]) -> Result<Vec<u8>, Error> {
+        let response = match decode_request(body) {
+            Ok(request) => self.handle(request).await,
+            Err(err) => err.into(),
+        };
+        encode_response(&response)
+    }
+
+    /// Handle a single request
+    pub async fn handle(&self, request: RemoteRequest) -> RemoteResponse {
+        match self.perform(request).await {
+            Ok(response) => response,
+            Err(err) => err.into(),
+        }
+    }
+
+    /// Close all open sessions and scans, rolling back any transactions
+    pub async fn close(&self) -> Result<(), Error> {
+        self.scans.lock().await.clear();
+        let sessions = self
+            .sessions
+            .lock()
+            .await
+            .drain()
+            .map(|(_, s)| s)
+            .collect::<Vec<_>>();
+        for session in sessions {
+            session.lock().await.close(false).await?;
+        }
+        self.backend.close().await
+    }
+
+    async fn perform(&self, request: RemoteRequest) -> Result<RemoteResponse, Error> {
+        match request {
+            RemoteRequest::Open { profile } => {
+                let profile = match profile {
+                    Some(profile) => profile,
+                    None => self.backend.get_default_profile().await?,
+                };
+                // check that the profile is accessible
+                let mut session = self.backend.session(Some(profile.clone()), false)?;
+                session.ping().await?;
+                session.close(false).await?;
+                Ok(RemoteResponse::Profile(profile))
+            }
+            RemoteRequest::CreateProfile { name } => Ok(RemoteResponse::Profile(
+                self.backend.create_profile(name).await?,
+            )),
+            RemoteRequest::GetDefaultProfile => Ok(RemoteResponse::Profile(
+                self.backend.get_default_profile().await?,
+            )),
+            RemoteRequest::SetDefaultProfile { profile } => {
+                self.backend.set_default_profile(profile).await?;
+                Ok(RemoteResponse::Done)
+            }
+            RemoteRequest::ListProfiles => Ok(RemoteResponse::Profiles(
+                self.backend.list_profiles().await?,
+            )),
+            RemoteRequest::RemoveProfile { name } => Ok(RemoteResponse::Removed(
+                self.backend.remove_profile(name).await?,
+            )),
+            RemoteRequest::SealToken { data } => {
+                Ok(RemoteResponse::Token(self.backend.seal_token(data).await?))
+            }
+            RemoteRequest::OpenToken { token } => {
+                Ok(RemoteResponse::Token(self.backend.open_token(token).await?))
+            }
+            RemoteRequest::Scan {
+                profile,
+                kind,
+                category,
+                tag_filter,
+                offset,
+                after,
+                limit,
+                order_by,
+                descending,
+                include_deleted,
+            } => {
+                let kind = kind.map(EntryKind::try_from).transpose()?;
+                let tag_filter = decode_tag_filter(tag_filter)?;
+                let order_by = decode_order_by(order_by)?;
+                let scan = match after {
+                    Some(after) => {
+                        self.backend
+                            .scan_after(
+                                profile,
+                                kind,
+                                category,
+                                tag_filter,
+                                after,
+                                limit,
+                                order_by,
+                                descending,
+                                include_deleted,
+                            )
+                            .await?
+                    }
+                    None => {
+                        self.backend
+                            .scan(
+                                profile,
+                                kind,
+                                category,
+                                tag_filter,
+                                offset,
+                                limit,
+                                order_by,
+                                descending,
+                                include_deleted,
+                            )
+                            .await?
+                    }
+                };
+                let handle = self.next_handle();
+                self.scans
+                    .lock()
+                    .await
+                    .insert(handle, Arc::new(Mutex::new(scan)));
+                Ok(RemoteResponse::Handle(handle))
+            }
+            RemoteRequest::ScanNext { scan: handle } => {
+                let scan = self.scans.lock().await.get(&handle).cloned();
+                let scan = scan.ok_or_else(|| err_msg!(NotFound, "Unknown scan handle"))?;
+                let batch = scan.lock().await.fetch_next().await;
+                match batch {
+                    Ok(Some(rows)) => Ok(RemoteResponse::Batch(Some(
+                        rows.into_iter().map(RemoteEntry::from).collect(),
+                    ))),
+                    Ok(None) => {
+                        self.scans.lock().await.remove(&handle);
+                        Ok(RemoteResponse::Batch(None))
+                    }
+                    Err(err) => {
+                        self.scans.lock().await.remove(&handle);
+                        Err(err)
+                    }
+                }
+            }
+            RemoteRequest::ScanClose { scan } => {
+                self.scans.lock().await.remove(&scan);
+                Ok(RemoteResponse::Done)
+            }
+            RemoteRequest::SessionOpen {
+                profile,
+                transaction,
+            } => {
+                let session = self.backend.session(profile, transaction)?;
+                let handle = self.next_handle();
+                self.sessions
+                    .lock()
+                    .await
+                    .insert(handle, Arc::new(Mutex::new(session)));
+                Ok(RemoteResponse::Handle(handle))
+            }
+            RemoteRequest::Count {
+                session,
+                kind,
+                category,
+                tag_filter,
+                include_deleted,
+            } => {
+                let session = self.session(session).await?;
+                let count = session
+                    .lock()
+                    .await
+                    .count(
+                        kind.map(EntryKind::try_from).transpose()?,
+                        category.as_deref(),
+                        decode_tag_filter(tag_filter)?,
+                        include_deleted,
+                    )
+                    .await?;
+                Ok(RemoteResponse::Count(count))
+            }
+            RemoteRequest::Fetch {
+                session,
+                kind,
+                category,
+                name,
+                for_update,
+            } => {
+                let session = self.session(session).await?;
+                let entry = session
+                    .lock()
+                    .await
+                    .fetch(EntryKind::try_from(kind)?, &category, &name, for_update)
+                    .await?;
+                Ok(RemoteResponse::Entry(entry.map(RemoteEntry::from)))
+            }
+            RemoteRequest::FetchAll {
+                session,
+                kind,
+                category,
+                tag_filter,
+                limit,
+                order_by,
+                descending,
+                for_update,
+            } => {
+                let session = self.session(session).await?;
+                let rows = session
+                    .lock()
+                    .await
+                    .fetch_all(
+                        kind.map(EntryKind::try_from).transpose()?,
+                        category.as_deref(),
+                        decode_tag_filter(tag_filter)?,
+                        limit,
+                        decode_order_by(order_by)?,
+                        descending,
+                        for_update,
+                    )
+                    .await?;
+                Ok(RemoteResponse::Entries(
+                    rows.into_iter().map(RemoteEntry::from).collect(),
+                ))
+            }
+            RemoteRequest::RemoveAll {
+                session,
+                kind,
+                category,
+                tag_filter,
+            } => {
+                let session = self.session(session).await?;
+                let count = session
+                    .lock()
+                    .await
+                    .remove_all(
+                        kind.map(EntryKind::try_from).transpose()?,
+                        category.as_deref(),
+                        decode_tag_filter(tag_filter)?,
+                    )
+                    .await?;
+                Ok(RemoteResponse::Count(count))
+            }
+            RemoteRequest::Update {
+                session,
+                kind,
+                operation,
+                category,
+                name,
+                value,
+                tags,
+                expiry_ms,
+            } => {
+                let session = self.session(session).await?;
+                let tags =
+                    tags.map(|tags| tags.into_iter().map(EntryTag::from).collect::<Vec<_>>());
+                session
+                    .lock()
+                    .await
+                    .update(
+                        EntryKind::try_from(kind)?,
+                        decode_operation(&operation)?,
+                        &category,
+                        &name,
+                        value.as_deref(),
+                        tags.as_deref(),
+                        expiry_ms,
+                    )
+                    .await?;
+                Ok(RemoteResponse::Done)
+            }
+            RemoteRequest::Ping { session } => {
+                let session = self.session(session).await?;
+                session.lock().await.ping().await?;
+                Ok(RemoteResponse::Done)
+            }
+            RemoteRequest::SessionClose { session, commit } => {
+                let session = self.sessions.lock().await.remove(&session);
+                if let Some(session) = session {
+                    session.lock().await.close(commit).await?;
+                }
+                Ok(RemoteResponse::Done)
+            }
+        }
+    }
+
+    async fn session(&self, handle: u64) -> Result<Arc<Mutex<B::Session>>, Error> {
+        self.sessions
+            .lock()
+            .await
+            .get(&handle)
+            .cloned()
+            .ok_or_else(|| err_msg!(NotFound, "Unknown session handle"))
+    }
+
+    fn next_handle(&self) -> u64 {
+        self.next_handle.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl<B: Backend> Debug for RemoteService<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteService")
+            .field("backend", &self.backend)
+            .finish()
+    }
+}
+
+/// Requests are passed through the wire encoding, so that a service may
+/// stand in for a remote transport
+impl<B: Backend + 'static> RemoteTransport for RemoteService<B> {
+    fn call(&self, request: RemoteRequest) -> BoxFuture<'_, Result<RemoteResponse, Error>> {
+        Box::pin(async move {
+            let body = encode_request(&request)?;
+            decode_response(&self.handle_bytes(&body).await?)
+        })
+    }
+}
@@ -4,23 +4,24 @@ use std::{
 };
 
 use sqlx::{
+    pool::PoolConnection,
     sqlite::{
         SqliteAutoVacuum, SqliteConnectOptions, SqliteJournalMode, SqliteLockingMode, SqlitePool,
         SqlitePoolOptions, SqliteSynchronous,
     },
-    ConnectOptions, Error as SqlxError, Row,
+    ConnectOptions, Connection, Error as SqlxError, Row, Sqlite,
 };
 
 use super::SqliteBackend;
 use crate::{
     backend::{
-        db_utils::{init_keys, random_profile_name},
+        db_utils::{init_master_keys, random_profile_name, unlock_store_key},
         ManageBackend,
     },
     error::Error,
     future::{sleep, unblock, BoxFuture},
     options::{IntoOptions, Options},
-    protect::{KeyCache, PassKey, StoreKeyMethod, StoreKeyReference},
+    protect::{KeyCache, PassKey, StoreKeyMethod},
 };
 
 const DEFAULT_MIN_CONNECTIONS: usize = 1;
@@ -31,6 +32,115 @@ const DEFAULT_JOURNAL_MODE: SqliteJournalMode = SqliteJournalMode::Wal;
 const DEFAULT_LOCKING_MODE: SqliteLockingMode = SqliteLockingMode::Normal;
 const DEFAULT_SYNCHRONOUS: SqliteSynchronous = SqliteSynchronous::Full;
 
+/// Schema upgrade scripts, applied in order to stores created by an earlier
+/// version. The current store version is one more than the number of upgrades.
+const UPGRADES: &[&str] = &[
+    r#"
+    ALTER TABLE items ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+"#,
+    r#"
+    ALTER TABLE items ADD COLUMN deleted_at DATETIME NULL;
+"#,
+    r#"
+    CREATE TABLE items_history (
+        id INTEGER NOT NULL,
+        item_id INTEGER NOT NULL,
+        version INTEGER NOT NULL,
+        value BLOB NOT NULL,
+        tags TEXT NULL,
+        replaced_at INTEGER NOT NULL,
+        PRIMARY KEY (id),
+        FOREIGN KEY (item_id) REFERENCES items (id)
+            ON DELETE CASCADE ON UPDATE CASCADE
+    );
+    CREATE INDEX ix_items_history_item_id ON items_history (item_id, version);
+
+    CREATE TABLE items_history_categories (
+        profile_id INTEGER NOT NULL,
+        kind INTEGER NOT NULL,
+        category BLOB NOT NULL,
+        PRIMARY KEY (profile_id, kind, category),
+        FOREIGN KEY (profile_id) REFERENCES profiles (id)
+            ON DELETE CASCADE ON UPDATE CASCADE
+    );
+"#,
+    r#"
+    CREATE TABLE audit_log (
+        id INTEGER NOT NULL,
+        profile_id INTEGER NOT NULL,
+        session_id TEXT NOT NULL,
+        operation TEXT NOT NULL,
+        kind INTEGER NULL,
+        category BLOB NULL,
+        item_id INTEGER NULL,
+        created_at INTEGER NOT NULL,
+        mac BLOB NOT NULL,
+        PRIMARY KEY (id)
+    );
+    CREATE INDEX ix_audit_log_profile_id ON audit_log (profile_id, id);
+    CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+    BEGIN
+        SELECT RAISE(ABORT, 'audit log is append-only');
+    END;
+    CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+    BEGIN
+        SELECT RAISE(ABORT, 'audit log is append-only');
+    END;
+"#,
+    r#"
+    ALTER TABLE items ADD COLUMN created_at INTEGER NULL;
+    ALTER TABLE items ADD COLUMN updated_at INTEGER NULL;
+"#,
+    r#"
+    CREATE TABLE store_meta (
+        name TEXT NOT NULL,
+        value BLOB NOT NULL,
+        PRIMARY KEY (name)
+    );
+
+    CREATE TABLE profile_meta (
+        profile_id INTEGER NOT NULL,
+        name BLOB NOT NULL,
+        value BLOB NOT NULL,
+        PRIMARY KEY (profile_id, name),
+        FOREIGN KEY (profile_id) REFERENCES profiles (id)
+            ON DELETE CASCADE ON UPDATE CASCADE
+    );
+"#,
+    r#"
+    ALTER TABLE profiles ADD COLUMN created_at INTEGER NULL;
+    ALTER TABLE profiles ADD COLUMN last_access INTEGER NULL;
+"#,
+    r#"
+    CREATE TABLE key_slots (
+        name TEXT NOT NULL,
+        key_ref TEXT NOT NULL,
+        wrapped_key BLOB NOT NULL,
+        PRIMARY KEY (name)
+    );
+"#,
+    // the search index may have been created when opening an earlier store
+    r#"
+    CREATE TABLE IF NOT EXISTS items_search (
+        item_id INTEGER NOT NULL,
+        token BLOB NOT NULL,
+        PRIMARY KEY (item_id, token),
+        FOREIGN KEY (item_id) REFERENCES items (id)
+            ON DELETE CASCADE ON UPDATE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS ix_items_search_token ON items_search (token);
+"#,
+    r#"
+    CREATE TABLE items_search_terms (
+        item_id INTEGER NOT NULL,
+        terms BLOB NOT NULL,
+        PRIMARY KEY (item_id),
+        FOREIGN KEY (item_id) REFERENCES items (id)
+            ON DELETE CASCADE ON UPDATE CASCADE
+    );
+"#,
+];
+
 /// Configuration options for Sqlite stores
 #[derive(Debug)]
 pub struct SqliteStoreOptions {
@@ -79,8 +189,8 @@ impl SqliteStoreOptions {
                     "Error determining available parallelism"
                 ))?
                 .get()
-                .max(DEFAULT_LOWER_MAX_CONNECTIONS)
-                .min(DEFAULT_UPPER_MAX_CONNECTIONS) as u32
+                .clamp(DEFAULT_LOWER_MAX_CONNECTIONS, DEFAULT_UPPER_MAX_CONNECTIONS)
+                as u32
         };
         let min_connections = if let Some(min_conn) = opts.query.remove("min_connections") {
             min_conn
@@ -286,9 +396,9 @@ async fn init_db(
     method: StoreKeyMethod,
     pass_key: PassKey<'_>,
 ) -> Result<KeyCache, Error> {
-    let (profile_key, enc_profile_key, store_key, store_key_ref) = unblock({
+    let (profile_key, enc_profile_key, store_key, store_key_ref, master_key) = unblock({
         let pass_key = pass_key.into_owned();
-        move || init_keys(method, pass_key)
+        move || init_master_keys(method, pass_key)
     })
     .await?;
 
@@ -306,13 +416,17 @@ async fn init_db(
         INSERT INTO config (name, value) VALUES
             ("default_profile", ?1),
             ("key", ?2),
-            ("version", "1");
+            ("master_key", ?6),
+            ("version", ?4);
+        DELETE FROM config WHERE name = "master_key" AND value IS NULL;
 
         CREATE TABLE profiles (
             id INTEGER NOT NULL,
             name TEXT NOT NULL,
             reference TEXT NULL,
             profile_key BLOB NULL,
+            created_at INTEGER NULL,
+            last_access INTEGER NULL,
             PRIMARY KEY(id)
         );
         CREATE UNIQUE INDEX ix_profile_name ON profiles (name);
@@ -325,6 +439,10 @@ async fn init_db(
             name BLOB NOT NULL,
             value BLOB NOT NULL,
             expiry DATETIME NULL,
+            version INTEGER NOT NULL DEFAULT 1,
+            deleted_at DATETIME NULL,
+            created_at INTEGER NULL,
+            updated_at INTEGER NULL,
             PRIMARY KEY (id),
             FOREIGN KEY (profile_id) REFERENCES profiles (id)
                 ON DELETE CASCADE ON UPDATE CASCADE
@@ -345,7 +463,90 @@ async fn init_db(
         CREATE INDEX ix_items_tags_name_enc ON items_tags (name, SUBSTR(value, 1, 12)) WHERE plaintext=0;
         CREATE INDEX ix_items_tags_name_plain ON items_tags (name, value) WHERE plaintext=1;
 
-        INSERT INTO profiles (name, profile_key) VALUES (?1, ?3);
+        CREATE TABLE items_search (
+            item_id INTEGER NOT NULL,
+            token BLOB NOT NULL,
+            PRIMARY KEY (item_id, token),
+            FOREIGN KEY (item_id) REFERENCES items (id)
+                ON DELETE CASCADE ON UPDATE CASCADE
+        );
+        CREATE INDEX ix_items_search_token ON items_search (token);
+
+        CREATE TABLE items_search_terms (
+            item_id INTEGER NOT NULL,
+            terms BLOB NOT NULL,
+            PRIMARY KEY (item_id),
+            FOREIGN KEY (item_id) REFERENCES items (id)
+                ON DELETE CASCADE ON UPDATE CASCADE
+        );
+
+        CREATE TABLE items_history (
+            id INTEGER NOT NULL,
+            item_id INTEGER NOT NULL,
+            version INTEGER NOT NULL,
+            value BLOB NOT NULL,
+            tags TEXT NULL,
+            replaced_at INTEGER NOT NULL,
+            PRIMARY KEY (id),
+            FOREIGN KEY (item_id) REFERENCES items (id)
+                ON DELETE CASCADE ON UPDATE CASCADE
+        );
+        CREATE INDEX ix_items_history_item_id ON items_history (item_id, version);
+
+        CREATE TABLE items_history_categories (
+            profile_id INTEGER NOT NULL,
+            kind INTEGER NOT NULL,
+            category BLOB NOT NULL,
+            PRIMARY KEY (profile_id, kind, category),
+            FOREIGN KEY (profile_id) REFERENCES profiles (id)
+                ON DELETE CASCADE ON UPDATE CASCADE
+        );
+
+        CREATE TABLE audit_log (
+            id INTEGER NOT NULL,
+            profile_id INTEGER NOT NULL,
+            session_id TEXT NOT NULL,
+            operation TEXT NOT NULL,
+            kind INTEGER NULL,
+            category BLOB NULL,
+            item_id INTEGER NULL,
+            created_at INTEGER NOT NULL,
+            mac BLOB NOT NULL,
+            PRIMARY KEY (id)
+        );
+        CREATE INDEX ix_audit_log_profile_id ON audit_log (profile_id, id);
+        CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+        BEGIN
+            SELECT RAISE(ABORT, 'audit log is append-only');
+        END;
+        CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+        BEGIN
+            SELECT RAISE(ABORT, 'audit log is append-only');
+        END;
+
+        CREATE TABLE store_meta (
+            name TEXT NOT NULL,
+            value BLOB NOT NULL,
+            PRIMARY KEY (name)
+        );
+
+        CREATE TABLE profile_meta (
+            profile_id INTEGER NOT NULL,
+            name BLOB NOT NULL,
+            value BLOB NOT NULL,
+            PRIMARY KEY (profile_id, name),
+            FOREIGN KEY (profile_id) REFERENCES profiles (id)
+                ON DELETE CASCADE ON UPDATE CASCADE
+        );
+
+        CREATE TABLE key_slots (
+            name TEXT NOT NULL,
+            key_ref TEXT NOT NULL,
+            wrapped_key BLOB NOT NULL,
+            PRIMARY KEY (name)
+        );
+
+        INSERT INTO profiles (name, profile_key, created_at) VALUES (?1, ?3, ?5);
 
         COMMIT;
     "#,
@@ -354,6 +555,9 @@ async fn init_db(
     .bind(profile_name)
     .bind(store_key_ref)
     .bind(enc_profile_key)
+    .bind((UPGRADES.len() + 1).to_string())
+    .bind(chrono::Utc::now().timestamp_millis())
+    .bind(master_key)
     .execute(conn.as_mut())
     .await.map_err(err_map!(Backend, "Error creating database tables"))?;
 
@@ -371,6 +575,30 @@ async fn init_db(
     Ok(key_cache)
 }
 
+// apply the schema upgrades following the given store version
+async fn upgrade_db(conn: &mut PoolConnection<Sqlite>, from_version: usize) -> Result<(), Error> {
+    let mut txn = conn
+        .begin()
+        .await
+        .map_err(err_map!(Backend, "Error starting transaction"))?;
+    for script in &UPGRADES[from_version - 1..] {
+        sqlx::query(script)
+            .persistent(false)
+            .execute(&mut *txn)
+            .await
+            .map_err(err_map!(Backend, "Error upgrading database schema"))?;
+    }
+    sqlx::query("UPDATE config SET value = ?1 WHERE name = 'version'")
+        .persistent(false)
+        .bind((UPGRADES.len() + 1).to_string())
+        .execute(&mut *txn)
+        .await
+        .map_err(err_map!(Backend, "Error updating store version"))?;
+    txn.commit()
+        .await
+        .map_err(err_map!(Backend, "Error committing transaction"))
+}
+
 async fn open_db(
     conn_pool: SqlitePool,
     method: Option<StoreKeyMethod>,
@@ -379,13 +607,15 @@ async fn open_db(
     path: String,
 ) -> Result<SqliteBackend, Error> {
     let mut conn = conn_pool.acquire().await?;
-    let mut ver_ok = false;
+    let mut version: Option<usize> = None;
     let mut default_profile: Option<String> = None;
     let mut store_key_ref: Option<String> = None;
+    let mut master_key: Option<String> = None;
+    let mut rekey_key: Option<String> = None;
 
     let config = sqlx::query(
         r#"SELECT name, value FROM config
-        WHERE name IN ("default_profile", "key", "version")"#,
+        WHERE name IN ("default_profile", "key", "master_key", "rekey_key", "version")"#,
     )
     .fetch_all(conn.as_mut())
     .await
@@ -398,44 +628,71 @@ async fn open_db(
             "key" => {
                 store_key_ref.replace(row.try_get(1)?);
             }
+            "master_key" => {
+                master_key.replace(row.try_get(1)?);
+            }
+            "rekey_key" => {
+                rekey_key.replace(row.try_get(1)?);
+            }
             "version" => {
-                if row.try_get::<&str, _>(1)? != "1" {
-                    return Err(err_msg!(Unsupported, "Unsupported store version"));
-                }
-                ver_ok = true;
+                version.replace(
+                    row.try_get::<&str, _>(1)?
+                        .parse()
+                        .map_err(|_| err_msg!(Unsupported, "Unsupported store version"))?,
+                );
             }
             _ => (),
         }
     }
-    if !ver_ok {
-        return Err(err_msg!(Unsupported, "Store version not found"));
+    let version = version.ok_or_else(|| err_msg!(Unsupported, "Store version not found"))?;
+    if version == 0 || version > UPGRADES.len() + 1 {
+        return Err(err_msg!(Unsupported, "Unsupported store version"));
+    }
+    if version <= UPGRADES.len() {
+        upgrade_db(&mut conn, version).await?;
     }
+
     let profile = profile
         .or(default_profile)
         .ok_or_else(|| err_msg!(Unsupported, "Default store profile not found"))?;
-    let store_key = if let Some(store_key_ref) = store_key_ref {
-        let wrap_ref = StoreKeyReference::parse_uri(&store_key_ref)?;
-        if let Some(method) = method {
-            if !wrap_ref.compare_method(&method) {
-                return Err(err_msg!(Input, "Store key method mismatch"));
-            }
-        }
-        unblock({
-            let pass_key = pass_key.into_owned();
-            move || wrap_ref.resolve(pass_key)
-        })
-        .await?
-    } else {
-        return Err(err_msg!(Unsupported, "Store key not found"));
-    };
-
-    let mut key_cache = KeyCache::new(store_key);
+    let store_key_ref =
+        store_key_ref.ok_or_else(|| err_msg!(Unsupported, "Store key not found"))?;
+    let key_slots = sqlx::query("SELECT key_ref, wrapped_key FROM key_slots")
+        .fetch_all(conn.as_mut())
+        .await
+        .map_err(err_map!(Backend, "Error fetching key slots"))?
+        .into_iter()
+        .map(|row| Result::<_, Error>::Ok((row.try_get(0)?, row.try_get(1)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
     let row = sqlx::query("SELECT id, profile_key FROM profiles WHERE name = ?1")
         .bind(&profile)
         .fetch_one(conn.as_mut())
         .await?;
     let profile_id = row.try_get(0)?;
-    let profile_key = key_cache.load_key(row.try_get(1)?).await?;
+    let enc_profile_key: Vec<u8> = row.try_get(1)?;
+    let store_key = unblock({
+        let pass_key = pass_key.into_owned();
+        move || {
+            unlock_store_key(
+                &store_key_ref,
+                master_key.as_deref(),
+                key_slots,
+                method,
+                pass_key,
+            )
+        }
+    })
+    .await?;
+
+    let mut key_cache = KeyCache::new(store_key);
+    if let Some(rekey_key) = rekey_key {
+        // profile keys may be wrapped by the new key of an incomplete rekey
+        let rekey_key =
+            hex::decode(rekey_key).map_err(|_| err_msg!(Unexpected, "Invalid rekey state"))?;
+        let pending_key = key_cache.store_key.unwrap_key(rekey_key)?;
+        key_cache.set_pending_key(pending_key);
+    }
+    let profile_key = key_cache.load_key(enc_profile_key).await?;
     conn.return_to_pool().await;
     key_cache.add_profile_mut(profile.clone(), profile_id, profile_key);
 
@@ -6,13 +6,19 @@ use std::{
     str::FromStr,
 };
 
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use futures_lite::stream::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
 use super::wql;
-use crate::{crypto::buffer::SecretBytes, error::Error};
+use crate::{
+    backend::{Backend, OrderBy},
+    crypto::buffer::SecretBytes,
+    error::{Error, ErrorKind},
+};
 
-pub(crate) fn sorted_tags(tags: &Vec<EntryTag>) -> Vec<&EntryTag> {
+pub(crate) fn sorted_tags(tags: &[EntryTag]) -> Vec<&EntryTag> {
     if tags.is_empty() {
         Vec::new()
     } else {
@@ -39,6 +45,30 @@ pub struct Entry {
 
     /// Tags associated with the entry record
     pub tags: Vec<EntryTag>,
+
+    /// The version of the entry record, incremented each time it is replaced,
+    /// when tracked by the backend
+    pub version: Option<i64>,
+
+    /// The time the entry record was created, in milliseconds since the Unix
+    /// epoch, when tracked by the backend
+    pub created_at: Option<i64>,
+
+    /// The time the entry record was last inserted or replaced, in milliseconds
+    /// since the Unix epoch, when tracked by the backend
+    pub updated_at: Option<i64>,
+
+    /// The time the entry record expires, in milliseconds since the Unix epoch,
+    /// when returned by the backend
+    pub expires_at: Option<i64>,
+
+    /// The time the entry record was soft deleted, in milliseconds since the
+    /// Unix epoch, when deleted records are included in a scan
+    pub deleted_at: Option<i64>,
+
+    // the backend identifier of the record, reported by scans in order to
+    // track the position of a scan cursor
+    pub(crate) row_id: Option<i64>,
 }
 
 impl Entry {
@@ -57,9 +87,50 @@ impl Entry {
             name: name.into(),
             value: value.into(),
             tags,
+            version: None,
+            created_at: None,
+            updated_at: None,
+            expires_at: None,
+            deleted_at: None,
+            row_id: None,
         }
     }
 
+    /// Set the version of the entry record
+    #[inline]
+    pub fn with_version(mut self, version: Option<i64>) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Set the creation and modification times of the entry record
+    #[inline]
+    pub fn with_timestamps(mut self, created_at: Option<i64>, updated_at: Option<i64>) -> Self {
+        self.created_at = created_at;
+        self.updated_at = updated_at;
+        self
+    }
+
+    /// Set the expiry time of the entry record
+    #[inline]
+    pub fn with_expiry(mut self, expires_at: Option<i64>) -> Self {
+        self.expires_at = expires_at;
+        self
+    }
+
+    /// Set the time the entry record was soft deleted
+    #[inline]
+    pub fn with_deleted_at(mut self, deleted_at: Option<i64>) -> Self {
+        self.deleted_at = deleted_at;
+        self
+    }
+
+    #[inline]
+    pub(crate) fn with_row_id(mut self, row_id: Option<i64>) -> Self {
+        self.row_id = row_id;
+        self
+    }
+
     pub(crate) fn sorted_tags(&self) -> Vec<&EntryTag> {
         sorted_tags(&self.tags)
     }
@@ -74,6 +145,90 @@ impl PartialEq for Entry {
     }
 }
 
+/// A prior version of a record, preserved when history is enabled for its category
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryVersion {
+    /// The record as it was before being replaced
+    pub entry: Entry,
+
+    /// The time the version was replaced, in milliseconds since the Unix epoch
+    pub replaced_ms: i64,
+}
+
+/// A mutating operation recorded in the audit log of a profile
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// The sequence number of the audit record
+    pub id: i64,
+
+    /// The unique identifier of the session which performed the operation
+    pub session_id: String,
+
+    /// The name of the operation performed
+    pub operation: String,
+
+    /// The kind of the affected records, if limited to one kind
+    pub kind: Option<EntryKind>,
+
+    /// The category of the affected records, if limited to one category
+    pub category: Option<String>,
+
+    /// The unique identifier of the affected record, which is not changed
+    /// when the profile is rekeyed
+    pub item_id: Option<i64>,
+
+    /// The time of the operation, in milliseconds since the Unix epoch
+    pub timestamp_ms: i64,
+}
+
+/// Summary details of a store profile
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileDetails {
+    /// The name of the profile
+    pub name: String,
+
+    /// The time the profile was created, in milliseconds since the Unix epoch,
+    /// when recorded
+    pub created_at: Option<i64>,
+
+    /// The time a session was last started for the profile, in milliseconds
+    /// since the Unix epoch, when recorded
+    pub last_access: Option<i64>,
+
+    /// The number of records in the profile, excluding deleted records
+    pub item_count: i64,
+}
+
+/// The kind of change made to a record
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChangeOperation {
+    /// A new record was inserted, or a deleted record was restored
+    Insert,
+    /// An existing record was replaced
+    Update,
+    /// A record was removed
+    Remove,
+}
+
+/// A committed change to a record, delivered to subscribers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeEvent {
+    /// The kind of change made to the record
+    pub operation: ChangeOperation,
+
+    /// The entry kind discriminator
+    pub kind: EntryKind,
+
+    /// The category of the record
+    pub category: String,
+
+    /// The name of the record
+    pub name: String,
+
+    /// The tags of the record following the change, empty for removals
+    pub tags: Vec<EntryTag>,
+}
+
 /// Set of distinct entry kinds for separating records.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum EntryKind {
@@ -104,6 +259,85 @@ pub enum EntryOperation {
     Replace,
     /// Remove an existing `Entry`
     Remove,
+    /// Insert a new `Entry`, or replace the value and tags of an existing one
+    InsertOrReplace,
+}
+
+/// A single record operation within a batch update
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryUpdate {
+    /// The operation to perform
+    pub operation: EntryOperation,
+
+    /// The category of the entry record
+    pub category: String,
+
+    /// The name of the entry record
+    pub name: String,
+
+    /// The new value of the entry record, if inserting or replacing
+    pub value: Option<SecretBytes>,
+
+    /// The new tags of the entry record, if inserting or replacing
+    pub tags: Option<Vec<EntryTag>>,
+
+    /// The expiry time of the entry record in milliseconds from now
+    pub expiry_ms: Option<i64>,
+}
+
+impl EntryUpdate {
+    /// Create an operation to insert a new record
+    pub fn insert<C: Into<String>, N: Into<String>, V: Into<SecretBytes>>(
+        category: C,
+        name: N,
+        value: V,
+        tags: Option<Vec<EntryTag>>,
+        expiry_ms: Option<i64>,
+    ) -> Self {
+        Self {
+            operation: EntryOperation::Insert,
+            category: category.into(),
+            name: name.into(),
+            value: Some(value.into()),
+            tags,
+            expiry_ms,
+        }
+    }
+
+    /// Create an operation to replace an existing record
+    pub fn replace<C: Into<String>, N: Into<String>, V: Into<SecretBytes>>(
+        category: C,
+        name: N,
+        value: V,
+        tags: Option<Vec<EntryTag>>,
+        expiry_ms: Option<i64>,
+    ) -> Self {
+        Self {
+            operation: EntryOperation::Replace,
+            ..Self::insert(category, name, value, tags, expiry_ms)
+        }
+    }
+
+    /// Create an operation to remove an existing record
+    pub fn remove<C: Into<String>, N: Into<String>>(category: C, name: N) -> Self {
+        Self {
+            operation: EntryOperation::Remove,
+            category: category.into(),
+            name: name.into(),
+            value: None,
+            tags: None,
+            expiry_ms: None,
+        }
+    }
+}
+
+/// Encode an integer as a plaintext tag value which sorts in numeric order
+///
+/// Plaintext tag values are compared as strings, so numeric values used with
+/// ordered comparisons must share a fixed-width representation. Timestamps
+/// may be stored as RFC 3339 strings in UTC, which already sort correctly.
+pub fn sortable_int(value: i64) -> String {
+    format!("{:020}", (value as u64) ^ (1 << 63))
 }
 
 /// A tag on an entry record in the store
@@ -184,6 +418,11 @@ pub(crate) struct EncEntryTag {
 }
 
 /// A WQL filter used to restrict record queries
+///
+/// Ordered comparisons (`$gt`, `$gte`, `$lt`, `$lte` and `$between`) are only
+/// supported for plaintext tags, and compare the tag values as strings. Numeric
+/// values should be encoded with [`sortable_int`] and timestamps as RFC 3339
+/// strings in UTC, otherwise `"10"` will sort before `"9"`.
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct TagFilter {
@@ -195,7 +434,9 @@ impl TagFilter {
     #[inline]
     pub fn all_of(each: Vec<TagFilter>) -> Self {
         Self {
-            query: wql::Query::And(unsafe { std::mem::transmute(each) }),
+            query: wql::Query::And(unsafe {
+                std::mem::transmute::<Vec<TagFilter>, Vec<wql::Query>>(each)
+            }),
         }
     }
 
@@ -203,7 +444,9 @@ impl TagFilter {
     #[inline]
     pub fn any_of(each: Vec<TagFilter>) -> Self {
         Self {
-            query: wql::Query::Or(unsafe { std::mem::transmute(each) }),
+            query: wql::Query::Or(unsafe {
+                std::mem::transmute::<Vec<TagFilter>, Vec<wql::Query>>(each)
+            }),
         }
     }
 
@@ -263,6 +506,24 @@ impl TagFilter {
         }
     }
 
+    /// Create an inclusive range comparison tag filter
+    ///
+    /// The bounds are compared with the tag value as strings.
+    #[inline]
+    pub fn is_between(
+        name: impl Into<String>,
+        low: impl Into<String>,
+        high: impl Into<String>,
+    ) -> Self {
+        let name = name.into();
+        Self {
+            query: wql::Query::And(vec![
+                wql::Query::Gte(name.clone(), low.into()),
+                wql::Query::Lte(name, high.into()),
+            ]),
+        }
+    }
+
     /// Create a LIKE comparison tag filter
     #[inline]
     pub fn is_like(name: impl Into<String>, value: impl Into<String>) -> Self {
@@ -271,6 +532,14 @@ impl TagFilter {
         }
     }
 
+    /// Create a prefix match tag filter
+    #[inline]
+    pub fn is_prefix(name: impl Into<String>, prefix: impl Into<String>) -> Self {
+        Self {
+            query: wql::Query::Prefix(name.into(), prefix.into()),
+        }
+    }
+
     /// Create an IN comparison tag filter for a set of tag values
     #[inline]
     pub fn is_in(name: impl Into<String>, values: Vec<String>) -> Self {
@@ -287,6 +556,14 @@ impl TagFilter {
         }
     }
 
+    /// Create a tag filter matching the number of values for a tag name
+    #[inline]
+    pub fn has_size(name: impl Into<String>, size: usize) -> Self {
+        Self {
+            query: wql::Query::Size(name.into(), size),
+        }
+    }
+
     /// Convert the tag filter to JSON format
     pub fn to_string(&self) -> Result<String, Error> {
         serde_json::to_string(&self.query).map_err(err_map!("Error encoding tag filter"))
@@ -313,11 +590,174 @@ impl FromStr for TagFilter {
     }
 }
 
+/// The position of a record within the ordering of a scan
+///
+/// Records are located by their sort value followed by their backend
+/// identifier, so that a resumed scan is not affected by records added or
+/// removed before the position.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanPosition {
+    #[serde(rename = "v")]
+    pub(crate) value: Option<SortValue>,
+    #[serde(rename = "i")]
+    pub(crate) id: i64,
+}
+
+impl ScanPosition {
+    /// Locate a record returned by a scan with the given ordering
+    pub(crate) fn for_entry(entry: &Entry, order_by: &OrderBy) -> Option<Self> {
+        let id = entry.row_id?;
+        let value = match order_by {
+            OrderBy::Id => None,
+            OrderBy::Tag(name) => entry
+                .tags
+                .iter()
+                .filter_map(|tag| match tag {
+                    EntryTag::Plaintext(tag_name, value) if tag_name == name => Some(value),
+                    _ => None,
+                })
+                .min()
+                .map(|value| SortValue::Text(value.clone())),
+            OrderBy::CreatedAt => entry.created_at.map(SortValue::Int),
+            OrderBy::UpdatedAt => entry.updated_at.map(SortValue::Int),
+        };
+        Some(Self { value, id })
+    }
+}
+
+/// The sort value of a record within a scan
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum SortValue {
+    /// A record timestamp
+    #[serde(rename = "n")]
+    Int(i64),
+    /// A plaintext tag value
+    #[serde(rename = "s")]
+    Text(String),
+}
+
+/// A resumable position within a record scan
+///
+/// The cursor records the parameters of the scan, other than the profile,
+/// along with the position of the last row returned. Backends which do not
+/// report record positions are resumed from the number of rows returned.
+/// The cursor may be encoded as an opaque token, which is encrypted using
+/// a key derived from the store key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanCursor {
+    #[serde(rename = "k")]
+    pub(crate) kind: Option<u8>,
+    #[serde(rename = "c")]
+    pub(crate) category: Option<String>,
+    #[serde(rename = "f")]
+    pub(crate) tag_filter: Option<String>,
+    #[serde(rename = "o")]
+    pub(crate) order_by: Option<String>,
+    #[serde(rename = "d")]
+    pub(crate) descending: bool,
+    #[serde(rename = "p")]
+    pub(crate) offset: i64,
+    #[serde(rename = "a", default)]
+    pub(crate) after: Option<ScanPosition>,
+    #[serde(rename = "x", default)]
+    pub(crate) include_deleted: bool,
+    #[serde(skip)]
+    remaining: Option<i64>,
+}
+
+impl ScanCursor {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        kind: Option<EntryKind>,
+        category: Option<String>,
+        tag_filter: Option<&TagFilter>,
+        order_by: Option<&OrderBy>,
+        descending: bool,
+        include_deleted: bool,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            kind: kind.map(|k| k as u8),
+            category,
+            tag_filter: tag_filter.map(TagFilter::to_string).transpose()?,
+            order_by: order_by.map(OrderBy::to_string),
+            descending,
+            offset: offset.unwrap_or(0).max(0),
+            after: None,
+            include_deleted,
+            remaining: limit.filter(|l| *l >= 0),
+        })
+    }
+
+    pub(crate) fn with_position(mut self, after: ScanPosition) -> Self {
+        self.after.replace(after);
+        self
+    }
+
+    /// Parse a cursor from a token produced by [`ScanCursor::to_token`]
+    ///
+    /// The token must have been produced by the same store, and is rejected
+    /// if it has been modified.
+    pub async fn from_token<B: Backend + ?Sized>(backend: &B, token: &str) -> Result<Self, Error> {
+        let token = URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(err_map!(Input, "Invalid scan cursor"))?;
+        let data = backend.open_token(token).await.map_err(|err| {
+            if err.kind() == ErrorKind::Input {
+                err_msg!(Input, "Invalid scan cursor")
+            } else {
+                err
+            }
+        })?;
+        serde_cbor::from_slice(&data).map_err(err_map!(Input, "Invalid scan cursor"))
+    }
+
+    /// Encode the cursor as an opaque token, encrypted by the store
+    pub async fn to_token<B: Backend + ?Sized>(&self, backend: &B) -> Result<String, Error> {
+        let data =
+            serde_cbor::to_vec(self).map_err(err_map!(Unexpected, "Error encoding scan cursor"))?;
+        let token = backend.seal_token(data).await?;
+        Ok(URL_SAFE_NO_PAD.encode(token))
+    }
+
+    /// Accessor for the position of the last row returned, when reported
+    /// by the backend
+    pub fn position(&self) -> Option<&ScanPosition> {
+        self.after.as_ref()
+    }
+
+    fn advance(&mut self, rows: &[Entry]) {
+        let count = rows.len() as i64;
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining -= count;
+        }
+        // the ordering is validated when the cursor is created
+        let order_by = self
+            .order_by
+            .as_deref()
+            .and_then(|order_by| OrderBy::from_str(order_by).ok())
+            .unwrap_or_default();
+        match rows
+            .last()
+            .and_then(|row| ScanPosition::for_entry(row, &order_by))
+        {
+            Some(position) => {
+                self.after.replace(position);
+                self.offset = 0;
+            }
+            None => self.offset += count,
+        }
+    }
+}
+
 /// An active record scan of a store backend
 pub struct Scan<'s, T> {
     #[allow(clippy::type_complexity)]
     stream: Option<Pin<Box<dyn Stream<Item = Result<Vec<T>, Error>> + Send + 's>>>,
     page_size: usize,
+    cursor: Option<ScanCursor>,
+    advance: fn(&mut ScanCursor, &[T]),
 }
 
 impl<'s, T> Scan<'s, T> {
@@ -328,9 +768,29 @@ impl<'s, T> Scan<'s, T> {
         Self {
             stream: Some(stream.boxed()),
             page_size,
+            cursor: None,
+            advance: |_, _| (),
         }
     }
 
+    /// Get the position following the rows fetched so far, if the scan was
+    /// started with a cursor and further rows may remain
+    pub fn cursor(&self) -> Option<&ScanCursor> {
+        let cursor = self.cursor.as_ref()?;
+        // a scan ending at its limit may be continued
+        if self.stream.is_some() || cursor.remaining == Some(0) {
+            Some(cursor)
+        } else {
+            None
+        }
+    }
+
+    /// Get the maximum number of rows returned by each fetch
+    #[cfg(feature = "blob")]
+    pub(crate) fn page_size(&self) -> usize {
+        self.page_size
+    }
+
     /// Fetch the next set of result rows
     pub async fn fetch_next(&mut self) -> Result<Option<Vec<T>>, Error> {
         if let Some(mut s) = self.stream.take() {
@@ -339,6 +799,9 @@ impl<'s, T> Scan<'s, T> {
                     if val.len() == self.page_size {
                         self.stream.replace(s);
                     }
+                    if let Some(cursor) = self.cursor.as_mut() {
+                        (self.advance)(cursor, &val);
+                    }
                     Ok(Some(val))
                 }
                 None => Ok(None),
@@ -349,6 +812,14 @@ impl<'s, T> Scan<'s, T> {
     }
 }
 
+impl<'s> Scan<'s, Entry> {
+    pub(crate) fn with_cursor(mut self, cursor: ScanCursor) -> Self {
+        self.cursor.replace(cursor);
+        self.advance = ScanCursor::advance;
+        self
+    }
+}
+
 impl<S> Debug for Scan<'_, S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("Scan")
@@ -356,3 +827,32 @@ impl<S> Debug for Scan<'_, S> {
             .finish()
     }
 }
+
+/// An active subscription to record changes in a profile
+pub struct Subscription {
+    stream: Pin<Box<dyn Stream<Item = Result<ChangeEvent, Error>> + Send>>,
+}
+
+impl Subscription {
+    pub(crate) fn new<S>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<ChangeEvent, Error>> + Send + 'static,
+    {
+        Self {
+            stream: stream.boxed(),
+        }
+    }
+
+    /// Wait for the next change event
+    ///
+    /// Returns `None` once no further changes may be delivered.
+    pub async fn next(&mut self) -> Result<Option<ChangeEvent>, Error> {
+        self.stream.try_next().await
+    }
+}
+
+impl Debug for Subscription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription").finish()
+    }
+}
@@ -12,6 +12,9 @@ pub enum ErrorKind {
     /// The store backend was too busy to handle the request
     Busy,
 
+    /// A conditional update failed because the record was modified
+    Conflict,
+
     /// A custom error type for external integrations
     Custom,
 
@@ -40,6 +43,7 @@ impl ErrorKind {
         match self {
             Self::Backend => "Backend error",
             Self::Busy => "Busy",
+            Self::Conflict => "Conflict",
             Self::Custom => "Custom error",
             Self::Duplicate => "Duplicate",
             Self::Encryption => "Encryption error",
@@ -144,13 +148,20 @@ impl From<ErrorKind> for Error {
 
 // FIXME would be preferable to remove this auto-conversion and handle
 // all sqlx errors manually, to ensure there is some context around the error
-#[cfg(any(feature = "indy_compat", feature = "postgres", feature = "sqlite"))]
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
 impl From<sqlx::Error> for Error {
     fn from(err: sqlx::Error) -> Self {
         Error::from(ErrorKind::Backend).with_cause(err)
     }
 }
 
+#[cfg(feature = "mssql")]
+impl From<tiberius::error::Error> for Error {
+    fn from(err: tiberius::error::Error) -> Self {
+        Error::from(ErrorKind::Backend).with_cause(err)
+    }
+}
+
 impl From<CryptoError> for Error {
     fn from(err: CryptoError) -> Self {
         let kind = match err.kind() {
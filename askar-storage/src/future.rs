@@ -1,17 +1,21 @@
+use std::{future::Future, pin::Pin};
+#[cfg(not(target_arch = "wasm32"))]
 use std::{
-    future::Future,
-    pin::Pin,
     sync::Arc,
     thread,
     time::{Duration, Instant},
 };
 
+#[cfg(not(target_arch = "wasm32"))]
 use arc_swap::ArcSwapOption;
+#[cfg(not(target_arch = "wasm32"))]
 use once_cell::sync::Lazy;
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::runtime::Runtime;
 
 pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
+#[cfg(not(target_arch = "wasm32"))]
 static RUNTIME: Lazy<ArcSwapOption<Runtime>> = Lazy::new(|| {
     ArcSwapOption::new(Some(Arc::new(
         Runtime::new().expect("Error creating tokio runtime"),
@@ -19,6 +23,7 @@ static RUNTIME: Lazy<ArcSwapOption<Runtime>> = Lazy::new(|| {
 });
 
 /// Block the current thread on an async task, when not running inside the scheduler.
+#[cfg(not(target_arch = "wasm32"))]
 pub fn block_on<R>(f: impl Future<Output = R>) -> R {
     if let Some(rt) = RUNTIME.load().clone() {
         rt.block_on(f)
@@ -28,6 +33,7 @@ pub fn block_on<R>(f: impl Future<Output = R>) -> R {
 }
 
 /// Run a blocking task without interrupting the async scheduler.
+#[cfg(not(target_arch = "wasm32"))]
 #[inline]
 pub async fn unblock<F, T>(f: F) -> T
 where
@@ -43,7 +49,20 @@ where
     }
 }
 
+/// Run a blocking task on the current thread, as there are no worker
+/// threads available on this target.
+#[cfg(target_arch = "wasm32")]
+#[inline]
+pub async fn unblock<F, T>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    f()
+}
+
 /// Spawn an async task into the runtime.
+#[cfg(not(target_arch = "wasm32"))]
 #[inline]
 pub fn spawn_ok(fut: impl Future<Output = ()> + Send + 'static) {
     if let Some(rt) = RUNTIME.load().clone() {
@@ -51,9 +70,17 @@ pub fn spawn_ok(fut: impl Future<Output = ()> + Send + 'static) {
     }
 }
 
+/// Spawn an async task onto the browser event loop.
+#[cfg(target_arch = "wasm32")]
+#[inline]
+pub fn spawn_ok(fut: impl Future<Output = ()> + Send + 'static) {
+    wasm_bindgen_futures::spawn_local(fut);
+}
+
 /// Wait until a specific duration has passed (used in tests).
 /// This method must be called within `block_on` or a spawned task in order to have
 /// access to the async runtime.
+#[cfg(not(target_arch = "wasm32"))]
 #[doc(hidden)]
 pub async fn sleep(dur: Duration) {
     tokio::time::sleep(dur).await
@@ -62,12 +89,14 @@ pub async fn sleep(dur: Duration) {
 /// Cancel an async task if it does not complete after a timeout (used in tests).
 /// This method must be called within `block_on` or a spawned task in order to have
 /// access to the async runtime.
+#[cfg(not(target_arch = "wasm32"))]
 #[doc(hidden)]
 pub async fn timeout<R>(dur: Duration, f: impl Future<Output = R>) -> Option<R> {
     tokio::time::timeout(dur, f).await.ok()
 }
 
 /// Shut down the async runtime.
+#[cfg(not(target_arch = "wasm32"))]
 #[doc(hidden)]
 pub fn shutdown(max_dur: Duration) {
     let start = Instant::now();
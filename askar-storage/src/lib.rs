@@ -30,9 +30,27 @@ pub use self::backend::{Backend, BackendSession, ManageBackend};
 #[cfg(feature = "any")]
 pub mod any;
 
+#[cfg(feature = "blob")]
+pub use self::backend::blob;
+
+#[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+pub use self::backend::indexeddb;
+
+#[cfg(feature = "mssql")]
+pub use self::backend::mssql;
+
 #[cfg(feature = "postgres")]
 pub use self::backend::postgres;
 
+#[cfg(feature = "redb")]
+pub use self::backend::redb;
+
+#[cfg(feature = "redis")]
+pub use self::backend::redis;
+
+#[cfg(feature = "remote")]
+pub use self::backend::remote;
+
 #[cfg(feature = "sqlite")]
 pub use self::backend::sqlite;
 
@@ -48,10 +66,16 @@ mod options;
 pub use options::{IntoOptions, Options};
 
 mod protect;
+#[cfg(any(
+    feature = "android_keystore",
+    feature = "apple_keychain",
+    feature = "windows_dpapi"
+))]
+pub use protect::keychain;
 pub use protect::{
     generate_raw_store_key,
     kdf::{Argon2Level, KdfMethod},
-    PassKey, StoreKeyMethod,
+    PassKey, StoreKeyMethod, WrapKeyProvider,
 };
 
 mod wql;
@@ -153,7 +153,7 @@ impl IndySdkToAriesAskarMigration {
 
         debug!("Re-opening wallet");
         let db_opts = SqliteStoreOptions::new(self.spec_uri.as_str())?;
-        let key_method = upd_key.key_ref.into();
+        let key_method = upd_key.key_ref.try_into()?;
         let db = db_opts
             .open(Some(key_method), self.wallet_key.as_str().into(), None)
             .await?;
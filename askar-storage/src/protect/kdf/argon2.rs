@@ -3,7 +3,7 @@ use askar_crypto::kdf::KeyDerivation;
 use crate::{
     crypto::{
         buffer::ArrayKey,
-        kdf::argon2::{Argon2, Params, PARAMS_INTERACTIVE, PARAMS_MODERATE},
+        kdf::argon2::{Algorithm, Argon2, Params, Version, PARAMS_INTERACTIVE, PARAMS_MODERATE},
         repr::{KeyMeta, KeySecretBytes},
     },
     error::Error,
@@ -15,19 +15,25 @@ pub use crate::crypto::kdf::argon2::SaltSize;
 pub const LEVEL_INTERACTIVE: &str = "13:int";
 pub const LEVEL_MODERATE: &str = "13:mod";
 
+const VERSION_PREFIX: &str = "13:";
+
 /// Argon2i derivation methods
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub enum Level {
     /// Interactive method
     Interactive,
     /// Stronger Moderate method
+    #[default]
     Moderate,
-}
-
-impl Default for Level {
-    fn default() -> Self {
-        Self::Moderate
-    }
+    /// Explicit derivation parameters
+    Custom {
+        /// The memory cost in KiB
+        mem_cost: u32,
+        /// The number of iterations
+        time_cost: u32,
+        /// The degree of parallelism
+        parallelism: u32,
+    },
 }
 
 impl Level {
@@ -36,14 +42,43 @@ impl Level {
             "int" | LEVEL_INTERACTIVE => Some(Self::Interactive),
             "mod" | LEVEL_MODERATE => Some(Self::Moderate),
             "" => Some(Self::default()),
-            _ => None,
+            custom => Self::parse_custom(custom.strip_prefix(VERSION_PREFIX).unwrap_or(custom)),
         }
     }
 
-    pub(crate) fn as_str(&self) -> &'static str {
+    /// Parse explicit parameters in the form `m=<mem_cost>,t=<time_cost>,p=<parallelism>`
+    fn parse_custom(params: &str) -> Option<Self> {
+        let (mut mem_cost, mut time_cost, mut parallelism) = (None, None, Some(1));
+        for param in params.split(',') {
+            let (name, value) = param.split_once('=')?;
+            let value = value.parse::<u32>().ok()?;
+            match name {
+                "m" => mem_cost.replace(value),
+                "t" => time_cost.replace(value),
+                "p" => parallelism.replace(value),
+                _ => return None,
+            };
+        }
+        let level = Self::Custom {
+            mem_cost: mem_cost?,
+            time_cost: time_cost?,
+            parallelism: parallelism?,
+        };
+        level.params().ok().map(|_| level)
+    }
+
+    pub(crate) fn encode(&self) -> String {
         match self {
-            Self::Interactive => LEVEL_INTERACTIVE,
-            Self::Moderate => LEVEL_MODERATE,
+            Self::Interactive => LEVEL_INTERACTIVE.to_string(),
+            Self::Moderate => LEVEL_MODERATE.to_string(),
+            Self::Custom {
+                mem_cost,
+                time_cost,
+                parallelism,
+            } => format!(
+                "{}m={},t={},p={}",
+                VERSION_PREFIX, mem_cost, time_cost, parallelism
+            ),
         }
     }
 
@@ -51,17 +86,57 @@ impl Level {
         ArrayKey::random()
     }
 
-    fn params(&self) -> &Params {
-        match self {
-            Self::Interactive => &PARAMS_INTERACTIVE,
-            Self::Moderate => &PARAMS_MODERATE,
+    fn params(&self) -> Result<Params, Error> {
+        match *self {
+            Self::Interactive => Ok(PARAMS_INTERACTIVE),
+            Self::Moderate => Ok(PARAMS_MODERATE),
+            Self::Custom {
+                mem_cost,
+                time_cost,
+                parallelism,
+            } => Ok(Params::new(
+                Algorithm::Argon2i,
+                Version::V0x13,
+                mem_cost,
+                time_cost,
+                parallelism,
+            )?),
         }
     }
 
     pub(crate) fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<StoreKey, Error> {
         ArrayKey::<<StoreKeyType as KeyMeta>::KeySize>::temp(|key| {
-            Argon2::new(password, salt, *self.params())?.derive_key_bytes(key)?;
+            Argon2::new(password, salt, self.params()?)?.derive_key_bytes(key)?;
             Ok(StoreKey::from(StoreKeyType::from_secret_bytes(&*key)?))
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_parse_custom() {
+        let level = Level::Custom {
+            mem_cost: 65536,
+            time_cost: 3,
+            parallelism: 2,
+        };
+        assert_eq!(Level::from_str("m=65536,t=3,p=2"), Some(level));
+        assert_eq!(level.encode(), "13:m=65536,t=3,p=2");
+        assert_eq!(Level::from_str(&level.encode()), Some(level));
+        assert_eq!(
+            Level::from_str("m=65536,t=3"),
+            Some(Level::Custom {
+                mem_cost: 65536,
+                time_cost: 3,
+                parallelism: 1
+            })
+        );
+        assert_eq!(Level::from_str("m=65536"), None);
+        assert_eq!(Level::from_str("m=65536,t=0"), None);
+        assert_eq!(Level::from_str("m=65536,t=3,x=1"), None);
+        assert_eq!(Level::from_str("other"), None);
+    }
+}
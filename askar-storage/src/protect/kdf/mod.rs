@@ -51,7 +51,7 @@ impl KdfMethod {
                 "{}:{}:{}{}",
                 PREFIX_KDF,
                 METHOD_ARGON2I,
-                level.as_str(),
+                level.encode(),
                 detail.unwrap_or_default()
             ),
         }
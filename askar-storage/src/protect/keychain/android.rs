@@ -0,0 +1,239 @@
+use jni::{
+    objects::{JByteArray, JObject, JValue},
+    JNIEnv, JavaVM,
+};
+
+use crate::{crypto::buffer::SecretBytes, error::Error, protect::WrapKeyProvider};
+
+const KEYSTORE_PROVIDER: &str = "AndroidKeyStore";
+const CIPHER_TRANSFORMATION: &str = "AES/GCM/NoPadding";
+const SPEC_BUILDER_CLASS: &str = "android/security/keystore/KeyGenParameterSpec$Builder";
+const SPEC_BUILDER_SIG: &str = "Landroid/security/keystore/KeyGenParameterSpec$Builder;";
+
+/// `KeyProperties.PURPOSE_ENCRYPT | KeyProperties.PURPOSE_DECRYPT`
+const PURPOSE_ENCRYPT_DECRYPT: i32 = 3;
+/// `Cipher.ENCRYPT_MODE`
+const ENCRYPT_MODE: i32 = 1;
+/// `Cipher.DECRYPT_MODE`
+const DECRYPT_MODE: i32 = 2;
+
+const GCM_IV_LENGTH: usize = 12;
+const GCM_TAG_BITS: i32 = 128;
+
+/// A wrap key provider using an AES key held in the Android Keystore
+///
+/// The wrapping key is encrypted using AES-GCM with a hardware-backed key
+/// which cannot be exported from the device. The keystore key is created
+/// under the provider alias when it is first needed, and may be required to
+/// reside in a StrongBox secure element.
+#[derive(Debug)]
+pub struct AndroidKeystoreProvider {
+    alias: String,
+    strongbox: bool,
+}
+
+impl AndroidKeystoreProvider {
+    /// Create a new provider for the keystore key with the given alias
+    pub fn new(alias: impl Into<String>) -> Self {
+        Self {
+            alias: alias.into(),
+            strongbox: false,
+        }
+    }
+
+    /// Generate the keystore key within a StrongBox secure element
+    pub fn strongbox(mut self, enabled: bool) -> Self {
+        self.strongbox = enabled;
+        self
+    }
+
+    fn load_key<'local>(&self, env: &mut JNIEnv<'local>) -> jni::errors::Result<JObject<'local>> {
+        let provider = env.new_string(KEYSTORE_PROVIDER)?;
+        let keystore = env
+            .call_static_method(
+                "java/security/KeyStore",
+                "getInstance",
+                "(Ljava/lang/String;)Ljava/security/KeyStore;",
+                &[JValue::Object(&provider)],
+            )?
+            .l()?;
+        env.call_method(
+            &keystore,
+            "load",
+            "(Ljava/security/KeyStore$LoadStoreParameter;)V",
+            &[JValue::Object(&JObject::null())],
+        )?;
+        let alias = env.new_string(&self.alias)?;
+        env.call_method(
+            &keystore,
+            "getKey",
+            "(Ljava/lang/String;[C)Ljava/security/Key;",
+            &[JValue::Object(&alias), JValue::Object(&JObject::null())],
+        )?
+        .l()
+    }
+
+    fn generate_key<'local>(
+        &self,
+        env: &mut JNIEnv<'local>,
+    ) -> jni::errors::Result<JObject<'local>> {
+        let alias = env.new_string(&self.alias)?;
+        let builder = env.new_object(
+            SPEC_BUILDER_CLASS,
+            "(Ljava/lang/String;I)V",
+            &[JValue::Object(&alias), JValue::Int(PURPOSE_ENCRYPT_DECRYPT)],
+        )?;
+        let block_modes = string_array(env, "GCM")?;
+        env.call_method(
+            &builder,
+            "setBlockModes",
+            format!("([Ljava/lang/String;){}", SPEC_BUILDER_SIG),
+            &[JValue::Object(&block_modes)],
+        )?;
+        let paddings = string_array(env, "NoPadding")?;
+        env.call_method(
+            &builder,
+            "setEncryptionPaddings",
+            format!("([Ljava/lang/String;){}", SPEC_BUILDER_SIG),
+            &[JValue::Object(&paddings)],
+        )?;
+        if self.strongbox {
+            env.call_method(
+                &builder,
+                "setIsStrongBoxBacked",
+                format!("(Z){}", SPEC_BUILDER_SIG),
+                &[JValue::Bool(1)],
+            )?;
+        }
+        let spec = env
+            .call_method(
+                &builder,
+                "build",
+                "()Landroid/security/keystore/KeyGenParameterSpec;",
+                &[],
+            )?
+            .l()?;
+
+        let algorithm = env.new_string("AES")?;
+        let provider = env.new_string(KEYSTORE_PROVIDER)?;
+        let generator = env
+            .call_static_method(
+                "javax/crypto/KeyGenerator",
+                "getInstance",
+                "(Ljava/lang/String;Ljava/lang/String;)Ljavax/crypto/KeyGenerator;",
+                &[JValue::Object(&algorithm), JValue::Object(&provider)],
+            )?
+            .l()?;
+        env.call_method(
+            &generator,
+            "init",
+            "(Ljava/security/spec/AlgorithmParameterSpec;)V",
+            &[JValue::Object(&spec)],
+        )?;
+        env.call_method(&generator, "generateKey", "()Ljavax/crypto/SecretKey;", &[])?
+            .l()
+    }
+}
+
+impl WrapKeyProvider for AndroidKeystoreProvider {
+    fn id(&self) -> &str {
+        "android-keystore"
+    }
+
+    fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>, Error> {
+        with_env(|env| {
+            let mut secret = self.load_key(env)?;
+            if secret.is_null() {
+                secret = self.generate_key(env)?;
+            }
+            let cipher = cipher(env)?;
+            env.call_method(
+                &cipher,
+                "init",
+                "(ILjava/security/Key;)V",
+                &[JValue::Int(ENCRYPT_MODE), JValue::Object(&secret)],
+            )?;
+            let input = env.byte_array_from_slice(key)?;
+            let output = JByteArray::from(
+                env.call_method(&cipher, "doFinal", "([B)[B", &[JValue::Object(&input)])?
+                    .l()?,
+            );
+            let iv = JByteArray::from(env.call_method(&cipher, "getIV", "()[B", &[])?.l()?);
+            let mut wrapped = env.convert_byte_array(iv)?;
+            wrapped.extend(env.convert_byte_array(output)?);
+            Ok(wrapped)
+        })
+    }
+
+    fn unwrap_key(&self, wrapped: &[u8]) -> Result<SecretBytes, Error> {
+        if wrapped.len() < GCM_IV_LENGTH {
+            return Err(err_msg!(Encryption, "Invalid wrapped key"));
+        }
+        let (iv, ciphertext) = wrapped.split_at(GCM_IV_LENGTH);
+        let key = with_env(|env| {
+            let secret = self.load_key(env)?;
+            if secret.is_null() {
+                return Ok(None);
+            }
+            let iv = env.byte_array_from_slice(iv)?;
+            let spec = env.new_object(
+                "javax/crypto/spec/GCMParameterSpec",
+                "(I[B)V",
+                &[JValue::Int(GCM_TAG_BITS), JValue::Object(&iv)],
+            )?;
+            let cipher = cipher(env)?;
+            env.call_method(
+                &cipher,
+                "init",
+                "(ILjava/security/Key;Ljava/security/spec/AlgorithmParameterSpec;)V",
+                &[
+                    JValue::Int(DECRYPT_MODE),
+                    JValue::Object(&secret),
+                    JValue::Object(&spec),
+                ],
+            )?;
+            let input = env.byte_array_from_slice(ciphertext)?;
+            let output = JByteArray::from(
+                env.call_method(&cipher, "doFinal", "([B)[B", &[JValue::Object(&input)])?
+                    .l()?,
+            );
+            env.convert_byte_array(output).map(Some)
+        })?;
+        key.map(SecretBytes::from)
+            .ok_or_else(|| err_msg!(NotFound, "Android Keystore key not found"))
+    }
+}
+
+/// Run a function with the Java environment of the current Android application
+fn with_env<R>(f: impl FnOnce(&mut JNIEnv<'_>) -> jni::errors::Result<R>) -> Result<R, Error> {
+    let ctx = ndk_context::android_context();
+    let vm = unsafe { JavaVM::from_raw(ctx.vm().cast()) }
+        .map_err(err_map!(Unexpected, "Error accessing the Java VM"))?;
+    let mut env = vm
+        .attach_current_thread()
+        .map_err(err_map!(Unexpected, "Error attaching to the Java VM"))?;
+    let result = f(&mut env);
+    if result.is_err() && env.exception_check().unwrap_or(false) {
+        let _ = env.exception_clear();
+    }
+    result.map_err(err_map!(Encryption, "Android Keystore error"))
+}
+
+fn cipher<'local>(env: &mut JNIEnv<'local>) -> jni::errors::Result<JObject<'local>> {
+    let transformation = env.new_string(CIPHER_TRANSFORMATION)?;
+    env.call_static_method(
+        "javax/crypto/Cipher",
+        "getInstance",
+        "(Ljava/lang/String;)Ljavax/crypto/Cipher;",
+        &[JValue::Object(&transformation)],
+    )?
+    .l()
+}
+
+fn string_array<'local>(
+    env: &mut JNIEnv<'local>,
+    value: &str,
+) -> jni::errors::Result<JObject<'local>> {
+    let value = env.new_string(value)?;
+    Ok(env.new_object_array(1, "java/lang/String", &value)?.into())
+}
@@ -0,0 +1,98 @@
+use security_framework::{
+    item::{ItemClass, ItemSearchOptions, Location, Reference, SearchResult},
+    key::{Algorithm, GenerateKeyOptions, KeyType, SecKey, Token},
+};
+
+use crate::{crypto::buffer::SecretBytes, error::Error, protect::WrapKeyProvider};
+
+/// The result code returned when a keychain item is not found
+const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+
+const WRAP_ALGORITHM: Algorithm = Algorithm::ECIESEncryptionCofactorVariableIVX963SHA256AESGCM;
+
+/// A wrap key provider using a P-256 key held in the Apple keychain
+///
+/// The wrapping key is encrypted using ECIES with the public key, and may
+/// only be decrypted by the private key stored on this device. The private
+/// key is created under the provider label when it is first needed, and may
+/// be generated within the Secure Enclave.
+#[derive(Debug)]
+pub struct AppleKeychainProvider {
+    label: String,
+    secure_enclave: bool,
+}
+
+impl AppleKeychainProvider {
+    /// Create a new provider for the keychain key with the given label
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            secure_enclave: false,
+        }
+    }
+
+    /// Generate the private key within the Secure Enclave
+    pub fn secure_enclave(mut self, enabled: bool) -> Self {
+        self.secure_enclave = enabled;
+        self
+    }
+
+    fn find_key(&self) -> Result<Option<SecKey>, Error> {
+        match ItemSearchOptions::new()
+            .class(ItemClass::key())
+            .label(&self.label)
+            .load_refs(true)
+            .search()
+        {
+            Ok(results) => Ok(results.into_iter().find_map(|result| match result {
+                SearchResult::Ref(Reference::Key(key)) => Some(key),
+                _ => None,
+            })),
+            Err(err) if err.code() == ERR_SEC_ITEM_NOT_FOUND => Ok(None),
+            Err(err) => Err(err_msg!(Encryption, "Error loading keychain key").with_cause(err)),
+        }
+    }
+
+    fn generate_key(&self) -> Result<SecKey, Error> {
+        let mut options = GenerateKeyOptions::default();
+        options
+            .set_key_type(KeyType::ec())
+            .set_size_in_bits(256)
+            .set_label(self.label.clone())
+            .set_location(Location::DataProtectionKeychain);
+        if self.secure_enclave {
+            options.set_token(Token::SecureEnclave);
+        }
+        SecKey::new(&options)
+            .map_err(|err| err_msg!(Encryption, "Error generating keychain key: {}", err))
+    }
+}
+
+impl WrapKeyProvider for AppleKeychainProvider {
+    fn id(&self) -> &str {
+        "apple-keychain"
+    }
+
+    fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>, Error> {
+        let private_key = match self.find_key()? {
+            Some(private_key) => private_key,
+            None => self.generate_key()?,
+        };
+        let public_key = private_key
+            .public_key()
+            .ok_or_else(|| err_msg!(Encryption, "Error loading keychain public key"))?;
+        public_key
+            .encrypt_data(WRAP_ALGORITHM, key)
+            .map_err(|err| err_msg!(Encryption, "Error wrapping key: {}", err))
+    }
+
+    fn unwrap_key(&self, wrapped: &[u8]) -> Result<SecretBytes, Error> {
+        let private_key = self
+            .find_key()?
+            .ok_or_else(|| err_msg!(NotFound, "Keychain key not found"))?;
+        private_key
+            .decrypt_data(WRAP_ALGORITHM, wrapped)
+            .map(SecretBytes::from)
+            .map_err(|err| err_msg!(Encryption, "Error unwrapping key: {}", err))
+    }
+}
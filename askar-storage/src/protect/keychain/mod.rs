@@ -0,0 +1,19 @@
+//! Wrap key providers backed by platform key stores
+
+#[cfg(all(feature = "android_keystore", target_os = "android"))]
+mod android;
+#[cfg(all(feature = "android_keystore", target_os = "android"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "android_keystore")))]
+pub use self::android::AndroidKeystoreProvider;
+
+#[cfg(all(feature = "apple_keychain", target_vendor = "apple"))]
+mod apple;
+#[cfg(all(feature = "apple_keychain", target_vendor = "apple"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "apple_keychain")))]
+pub use self::apple::AppleKeychainProvider;
+
+#[cfg(all(feature = "windows_dpapi", windows))]
+mod windows;
+#[cfg(all(feature = "windows_dpapi", windows))]
+#[cfg_attr(docsrs, doc(cfg(feature = "windows_dpapi")))]
+pub use self::windows::DpapiProvider;
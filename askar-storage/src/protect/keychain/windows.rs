@@ -0,0 +1,134 @@
+use std::{io, ptr, slice};
+
+use windows_sys::Win32::{
+    Foundation::LocalFree,
+    Security::Cryptography::{
+        CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    },
+};
+use zeroize::Zeroize;
+
+use crate::{crypto::buffer::SecretBytes, error::Error, protect::WrapKeyProvider};
+
+/// A wrap key provider using the Windows Data Protection API
+///
+/// The wrapping key is encrypted using a key derived from the credentials of
+/// the current user, and may only be decrypted by the same user account.
+/// Optional entropy may be provided, which is then required to decrypt the key.
+#[derive(Debug, Default)]
+pub struct DpapiProvider {
+    entropy: Vec<u8>,
+}
+
+impl DpapiProvider {
+    /// Create a new provider for the current user
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the additional entropy used to protect the wrapping key
+    pub fn with_entropy(mut self, entropy: impl Into<Vec<u8>>) -> Self {
+        self.entropy = entropy.into();
+        self
+    }
+
+    fn entropy_blob(&self) -> Option<CRYPT_INTEGER_BLOB> {
+        if self.entropy.is_empty() {
+            None
+        } else {
+            Some(input_blob(&self.entropy))
+        }
+    }
+}
+
+impl WrapKeyProvider for DpapiProvider {
+    fn id(&self) -> &str {
+        "windows-dpapi"
+    }
+
+    fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>, Error> {
+        let input = input_blob(key);
+        let entropy = self.entropy_blob();
+        let mut output = CRYPT_INTEGER_BLOB {
+            cbData: 0,
+            pbData: ptr::null_mut(),
+        };
+        let res = unsafe {
+            CryptProtectData(
+                &input,
+                ptr::null(),
+                entropy.as_ref().map_or(ptr::null(), |e| e as *const _),
+                ptr::null(),
+                ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )
+        };
+        if res == 0 {
+            return Err(
+                err_msg!(Encryption, "Error wrapping key").with_cause(io::Error::last_os_error())
+            );
+        }
+        Ok(unsafe { take_output(output) })
+    }
+
+    fn unwrap_key(&self, wrapped: &[u8]) -> Result<SecretBytes, Error> {
+        let input = input_blob(wrapped);
+        let entropy = self.entropy_blob();
+        let mut output = CRYPT_INTEGER_BLOB {
+            cbData: 0,
+            pbData: ptr::null_mut(),
+        };
+        let res = unsafe {
+            CryptUnprotectData(
+                &input,
+                ptr::null_mut(),
+                entropy.as_ref().map_or(ptr::null(), |e| e as *const _),
+                ptr::null(),
+                ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )
+        };
+        if res == 0 {
+            return Err(
+                err_msg!(Encryption, "Error unwrapping key").with_cause(io::Error::last_os_error())
+            );
+        }
+        Ok(SecretBytes::from(unsafe { take_output(output) }))
+    }
+}
+
+fn input_blob(data: &[u8]) -> CRYPT_INTEGER_BLOB {
+    CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    }
+}
+
+/// Copy the output of a DPAPI call, then clear and release its buffer
+unsafe fn take_output(output: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+    if output.pbData.is_null() {
+        return Vec::new();
+    }
+    let data = slice::from_raw_parts_mut(output.pbData, output.cbData as usize);
+    let result = data.to_vec();
+    data.zeroize();
+    LocalFree(output.pbData as _);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dpapi_wrap_round_trip() {
+        let provider = DpapiProvider::new().with_entropy(&b"entropy"[..]);
+        let wrapped = provider.wrap_key(b"test key").expect("Error wrapping key");
+        assert_ne!(wrapped, b"test key");
+        let key = provider.unwrap_key(&wrapped).expect("Error unwrapping key");
+        assert_eq!(key, &b"test key"[..]);
+        assert!(DpapiProvider::new().unwrap_key(&wrapped).is_err());
+    }
+}
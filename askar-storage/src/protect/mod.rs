@@ -1,6 +1,9 @@
 //! Storage encryption
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 use async_lock::RwLock;
 
@@ -14,6 +17,16 @@ pub use self::pass_key::PassKey;
 mod profile_key;
 pub use self::profile_key::ProfileKey;
 
+#[cfg(any(
+    feature = "android_keystore",
+    feature = "apple_keychain",
+    feature = "windows_dpapi"
+))]
+pub mod keychain;
+
+mod provider;
+pub use self::provider::WrapKeyProvider;
+
 mod store_key;
 pub use self::store_key::{generate_raw_store_key, StoreKey, StoreKeyMethod, StoreKeyReference};
 
@@ -26,27 +39,49 @@ use crate::{
 
 pub type ProfileId = i64;
 
+/// The minimum interval between recorded accesses of a profile
+const PROFILE_ACCESS_INTERVAL_MS: i64 = 60_000;
+
 #[derive(Debug)]
 pub struct KeyCache {
     profile_info: RwLock<HashMap<String, (ProfileId, Arc<ProfileKey>)>>,
+    profile_access: Mutex<HashMap<ProfileId, i64>>,
     pub(crate) store_key: Arc<StoreKey>,
+    pending_key: Option<Arc<StoreKey>>,
 }
 
 impl KeyCache {
     pub fn new(store_key: impl Into<Arc<StoreKey>>) -> Self {
         Self {
             profile_info: RwLock::new(HashMap::new()),
+            profile_access: Mutex::new(HashMap::new()),
             store_key: store_key.into(),
+            pending_key: None,
         }
     }
 
+    /// Set the new store key of an incomplete rekey operation, which may have
+    /// been used to wrap some of the profile keys
+    pub fn set_pending_key(&mut self, key: StoreKey) {
+        self.pending_key.replace(Arc::new(key));
+    }
+
     pub async fn load_key(&self, ciphertext: Vec<u8>) -> Result<ProfileKey, Error> {
         let store_key = self.store_key.clone();
+        let pending_key = self.pending_key.clone();
         unblock(move || {
-            let data = store_key
-                .unwrap_data(ciphertext)
-                .map_err(err_map!(Encryption, "Error decrypting profile key"))?;
-            ProfileKey::from_slice(data.as_ref())
+            let load = |store_key: &StoreKey, ciphertext: Vec<u8>| {
+                let data = store_key
+                    .unwrap_data(ciphertext)
+                    .map_err(err_map!(Encryption, "Error decrypting profile key"))?;
+                ProfileKey::from_slice(data.as_ref())
+            };
+            match pending_key {
+                Some(pending_key) => {
+                    load(&store_key, ciphertext.clone()).or_else(|_| load(&pending_key, ciphertext))
+                }
+                None => load(&store_key, ciphertext),
+            }
         })
         .await
     }
@@ -64,6 +99,24 @@ impl KeyCache {
     pub async fn get_profile(&self, name: &str) -> Option<(ProfileId, Arc<ProfileKey>)> {
         self.profile_info.read().await.get(name).cloned()
     }
+
+    pub async fn remove_profile(&self, name: &str) {
+        self.profile_info.write().await.remove(name);
+    }
+
+    /// Determine whether an access of a profile should be recorded, limiting
+    /// the updates for each profile to one per interval
+    pub fn profile_access_due(&self, pid: ProfileId, now_ms: i64) -> bool {
+        match self.profile_access.lock().unwrap().get(&pid) {
+            Some(prev) => now_ms - prev >= PROFILE_ACCESS_INTERVAL_MS,
+            None => true,
+        }
+    }
+
+    /// Note a successfully recorded access of a profile
+    pub fn set_profile_access(&self, pid: ProfileId, now_ms: i64) {
+        self.profile_access.lock().unwrap().insert(pid, now_ms);
+    }
 }
 
 pub(crate) trait EntryEncryptor {
@@ -91,66 +144,3 @@ pub(crate) trait EntryEncryptor {
     ) -> Result<SecretBytes, Error>;
     fn decrypt_entry_tags(&self, enc_tags: Vec<EncEntryTag>) -> Result<Vec<EntryTag>, Error>;
 }
-
-pub struct NullEncryptor;
-
-impl EntryEncryptor for NullEncryptor {
-    fn encrypt_entry_category(&self, category: SecretBytes) -> Result<Vec<u8>, Error> {
-        Ok(category.into_vec())
-    }
-    fn encrypt_entry_name(&self, name: SecretBytes) -> Result<Vec<u8>, Error> {
-        Ok(name.into_vec())
-    }
-    fn encrypt_entry_value(
-        &self,
-        _category: &[u8],
-        _name: &[u8],
-        value: SecretBytes,
-    ) -> Result<Vec<u8>, Error> {
-        Ok(value.into_vec())
-    }
-    fn encrypt_entry_tags(&self, tags: Vec<EntryTag>) -> Result<Vec<EncEntryTag>, Error> {
-        Ok(tags
-            .into_iter()
-            .map(|tag| match tag {
-                EntryTag::Encrypted(name, value) => EncEntryTag {
-                    name: name.into_bytes(),
-                    value: value.into_bytes(),
-                    plaintext: false,
-                },
-                EntryTag::Plaintext(name, value) => EncEntryTag {
-                    name: name.into_bytes(),
-                    value: value.into_bytes(),
-                    plaintext: true,
-                },
-            })
-            .collect())
-    }
-
-    fn decrypt_entry_category(&self, enc_category: Vec<u8>) -> Result<String, Error> {
-        String::from_utf8(enc_category).map_err(err_map!(Encryption))
-    }
-    fn decrypt_entry_name(&self, enc_name: Vec<u8>) -> Result<String, Error> {
-        String::from_utf8(enc_name).map_err(err_map!(Encryption))
-    }
-    fn decrypt_entry_value(
-        &self,
-        _category: &[u8],
-        _name: &[u8],
-        enc_value: Vec<u8>,
-    ) -> Result<SecretBytes, Error> {
-        Ok(enc_value.into())
-    }
-    fn decrypt_entry_tags(&self, enc_tags: Vec<EncEntryTag>) -> Result<Vec<EntryTag>, Error> {
-        enc_tags.into_iter().try_fold(vec![], |mut acc, tag| {
-            let name = String::from_utf8(tag.name).map_err(err_map!(Encryption))?;
-            let value = String::from_utf8(tag.value).map_err(err_map!(Encryption))?;
-            acc.push(if tag.plaintext {
-                EntryTag::Plaintext(name, value)
-            } else {
-                EntryTag::Encrypted(name, value)
-            });
-            Result::<_, Error>::Ok(acc)
-        })
-    }
-}
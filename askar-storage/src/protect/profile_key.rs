@@ -7,10 +7,13 @@ use crate::{
     crypto::{
         alg::chacha20::{Chacha20Key, C20P},
         buffer::{ArrayKey, ResizeBuffer, SecretBytes, WriteBuffer},
-        encrypt::{KeyAeadInPlace, KeyAeadMeta},
+        encrypt::{
+            committing::{Committing, COMMITMENT_LENGTH},
+            KeyAeadInPlace, KeyAeadMeta,
+        },
         generic_array::typenum::{Unsigned, U32},
         kdf::FromKeyDerivation,
-        repr::KeyGen,
+        repr::{KeyGen, KeySecretBytes},
     },
     entry::{EncEntryTag, EntryTag},
     error::Error,
@@ -38,6 +41,14 @@ pub struct ProfileKeyImpl<Key, HmacKey> {
     pub tag_value_key: Key,
     #[serde(rename = "thk")]
     pub tags_hmac_key: HmacKey,
+    /// Whether entry values are encrypted with a key commitment
+    #[serde(rename = "cmt", default, skip_serializing_if = "is_false")]
+    pub committing: bool,
+}
+
+#[inline]
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl<Key, HmacKey> ProfileKeyImpl<Key, HmacKey>
@@ -53,6 +64,7 @@ where
             tag_name_key: KeyGen::random()?,
             tag_value_key: KeyGen::random()?,
             tags_hmac_key: KeyGen::random()?,
+            committing: true,
         })
     }
 }
@@ -75,11 +87,11 @@ where
 
 impl<Key, HmacKey> ProfileKeyImpl<Key, HmacKey>
 where
-    Key: KeyAeadInPlace + KeyAeadMeta + FromKeyDerivation,
+    Key: KeyAeadInPlace + KeyAeadMeta + KeySecretBytes + FromKeyDerivation,
     HmacKey: HmacDerive,
 {
     fn encrypted_size(len: usize) -> usize {
-        len + Key::NonceSize::USIZE + Key::TagSize::USIZE
+        len + Key::NonceSize::USIZE + Key::TagSize::USIZE + COMMITMENT_LENGTH
     }
 
     /// Encrypt a value with a predictable nonce, making it searchable
@@ -96,14 +108,14 @@ where
         Ok(buffer.into_vec())
     }
 
-    fn encrypt(mut buffer: SecretBytes, enc_key: &Key) -> Result<Vec<u8>, Error> {
+    fn encrypt(mut buffer: SecretBytes, enc_key: &impl KeyAeadInPlace) -> Result<Vec<u8>, Error> {
         let nonce = ArrayKey::<Key::NonceSize>::random();
         enc_key.encrypt_in_place(&mut buffer, nonce.as_ref(), &[])?;
         buffer.buffer_insert(0, nonce.as_ref())?;
         Ok(buffer.into_vec())
     }
 
-    fn decrypt(ciphertext: Vec<u8>, enc_key: &Key) -> Result<SecretBytes, Error> {
+    fn decrypt(ciphertext: Vec<u8>, enc_key: &impl KeyAeadInPlace) -> Result<SecretBytes, Error> {
         let nonce_len = Key::NonceSize::USIZE;
         if ciphertext.len() < nonce_len {
             return Err(err_msg!(Encryption, "invalid encrypted value"));
@@ -142,6 +154,53 @@ where
     pub fn decrypt_tag_value(&self, enc_tag_value: Vec<u8>) -> Result<SecretBytes, Error> {
         Self::decrypt(enc_tag_value, &self.tag_value_key)
     }
+
+    /// Derive the token stored in the search index for a term within a category
+    pub fn search_token(&self, category: &[u8], term: &[u8]) -> Result<Vec<u8>, Error> {
+        let token = ArrayKey::<U32>::from_key_derivation(self.tags_hmac_key.hmac_deriver(&[
+            b"search",
+            &(category.len() as u32).to_be_bytes(),
+            category,
+            term,
+        ]))?;
+        Ok(token.as_ref().to_vec())
+    }
+
+    /// Encrypt the search terms indexed for a record, allowing the search
+    /// tokens to be derived again when the profile key is replaced
+    pub fn encrypt_search_terms(
+        &self,
+        category: &[u8],
+        name: &[u8],
+        terms: SecretBytes,
+    ) -> Result<Vec<u8>, Error> {
+        let terms_key = self.derive_search_terms_key(category, name)?;
+        Self::encrypt(terms, &terms_key)
+    }
+
+    /// Decrypt the search terms indexed for a record
+    pub fn decrypt_search_terms(
+        &self,
+        category: &[u8],
+        name: &[u8],
+        enc_terms: Vec<u8>,
+    ) -> Result<SecretBytes, Error> {
+        let terms_key = self.derive_search_terms_key(category, name)?;
+        Self::decrypt(enc_terms, &terms_key)
+    }
+
+    #[inline]
+    fn derive_search_terms_key(&self, category: &[u8], name: &[u8]) -> Result<Key, Error> {
+        Ok(Key::from_key_derivation(self.item_hmac_key.hmac_deriver(
+            &[
+                b"search",
+                &(category.len() as u32).to_be_bytes(),
+                category,
+                &(name.len() as u32).to_be_bytes(),
+                name,
+            ],
+        ))?)
+    }
 }
 
 impl<Key: PartialEq, HmacKey: PartialEq> PartialEq for ProfileKeyImpl<Key, HmacKey> {
@@ -152,13 +211,14 @@ impl<Key: PartialEq, HmacKey: PartialEq> PartialEq for ProfileKeyImpl<Key, HmacK
             && self.tag_name_key == other.tag_name_key
             && self.tag_value_key == other.tag_value_key
             && self.tags_hmac_key == other.tags_hmac_key
+            && self.committing == other.committing
     }
 }
 impl<Key: PartialEq, HmacKey: PartialEq> Eq for ProfileKeyImpl<Key, HmacKey> {}
 
 impl<Key, HmacKey> EntryEncryptor for ProfileKeyImpl<Key, HmacKey>
 where
-    Key: KeyAeadInPlace + KeyAeadMeta + FromKeyDerivation,
+    Key: KeyAeadInPlace + KeyAeadMeta + KeySecretBytes + FromKeyDerivation,
     HmacKey: HmacDerive,
 {
     fn prepare_input(input: &[u8]) -> SecretBytes {
@@ -182,7 +242,11 @@ where
         value: SecretBytes,
     ) -> Result<Vec<u8>, Error> {
         let value_key = self.derive_value_key(category, name)?;
-        Self::encrypt(value, &value_key)
+        if self.committing {
+            Self::encrypt(value, &Committing(value_key))
+        } else {
+            Self::encrypt(value, &value_key)
+        }
     }
 
     fn decrypt_entry_category(&self, enc_category: Vec<u8>) -> Result<String, Error> {
@@ -200,7 +264,11 @@ where
         enc_value: Vec<u8>,
     ) -> Result<SecretBytes, Error> {
         let value_key = self.derive_value_key(category, name)?;
-        Self::decrypt(enc_value, &value_key)
+        if self.committing {
+            Self::decrypt(enc_value, &Committing(value_key))
+        } else {
+            Self::decrypt(enc_value, &value_key)
+        }
     }
 
     fn encrypt_entry_tags(&self, tags: Vec<EntryTag>) -> Result<Vec<EncEntryTag>, Error> {
@@ -312,6 +380,39 @@ mod tests {
         assert_eq!(dec, input);
     }
 
+    #[test]
+    fn committing_entry_value() {
+        let mut key = ProfileKey::new().unwrap();
+        assert!(key.committing);
+        let enc_value = key
+            .encrypt_entry_value(b"category", b"name", (&b"value"[..]).into())
+            .unwrap();
+        assert_eq!(enc_value.len(), ProfileKey::encrypted_size(5));
+        assert_eq!(
+            key.decrypt_entry_value(b"category", b"name", enc_value.clone())
+                .unwrap(),
+            &b"value"[..]
+        );
+
+        // keys created before commitments were introduced remain readable
+        key.committing = false;
+        assert!(key
+            .decrypt_entry_value(b"category", b"name", enc_value)
+            .is_err());
+        let enc_value = key
+            .encrypt_entry_value(b"category", b"name", (&b"value"[..]).into())
+            .unwrap();
+        let key_cbor = serde_cbor::to_vec(&key).unwrap();
+        let key_cmp: ProfileKey = serde_cbor::from_slice(&key_cbor).unwrap();
+        assert!(!key_cmp.committing);
+        assert_eq!(
+            key_cmp
+                .decrypt_entry_value(b"category", b"name", enc_value)
+                .unwrap(),
+            &b"value"[..]
+        );
+    }
+
     #[test]
     fn serialize_round_trip() {
         let key = ProfileKey::new().unwrap();
@@ -0,0 +1,57 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    hash::{Hash, Hasher},
+};
+
+use crate::{crypto::buffer::SecretBytes, error::Error};
+
+/// An external provider for the wrapping key of a store, such as a key
+/// management service or an operating system keychain
+///
+/// When a store is provisioned or rekeyed, a random wrapping key is generated
+/// and encrypted by the provider, and the result is recorded in the store key
+/// reference. When the store is opened, the provider is asked to decrypt it
+/// again. This keeps the secret protecting the store out of the pass key.
+///
+/// Provider methods are invoked on a blocking thread, so they may perform
+/// network requests or wait for user interaction.
+pub trait WrapKeyProvider: Send + Sync {
+    /// The identifier of the provider, recorded in the store key reference
+    ///
+    /// This value must not contain the characters `:` or `?`.
+    fn id(&self) -> &str;
+
+    /// Encrypt a newly generated wrapping key
+    fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>, Error>;
+
+    /// Decrypt a wrapping key previously encrypted by this provider
+    fn unwrap_key(&self, wrapped: &[u8]) -> Result<SecretBytes, Error>;
+}
+
+impl Debug for dyn WrapKeyProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WrapKeyProvider").field(&self.id()).finish()
+    }
+}
+
+impl PartialEq for dyn WrapKeyProvider {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl Eq for dyn WrapKeyProvider {}
+
+impl Hash for dyn WrapKeyProvider {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state)
+    }
+}
+
+pub(crate) fn validate_provider_id(id: &str) -> Result<(), Error> {
+    if id.is_empty() || id.contains([':', '?']) {
+        Err(err_msg!(Input, "Invalid wrap key provider identifier"))
+    } else {
+        Ok(())
+    }
+}
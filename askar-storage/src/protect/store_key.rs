@@ -1,11 +1,20 @@
+use std::sync::Arc;
+
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+use super::hmac_key::{HmacDerive, HmacKey};
 use super::kdf::KdfMethod;
 
 use super::pass_key::PassKey;
+use super::provider::{validate_provider_id, WrapKeyProvider};
 use crate::{
     crypto::{
         alg::chacha20::{Chacha20Key, C20P},
         buffer::{ArrayKey, ResizeBuffer, SecretBytes},
         encrypt::{KeyAeadInPlace, KeyAeadMeta},
+        generic_array::typenum::U32,
+        kdf::FromKeyDerivation,
         random::RandomDet,
         repr::{KeyGen, KeyMeta, KeySecretBytes},
     },
@@ -15,11 +24,20 @@ use crate::{
 pub const PREFIX_KDF: &str = "kdf";
 pub const PREFIX_RAW: &str = "raw";
 pub const PREFIX_NONE: &str = "none";
+pub const PREFIX_EXT: &str = "ext";
 
 pub type StoreKeyType = Chacha20Key<C20P>;
 
 type StoreKeyNonce = ArrayKey<<StoreKeyType as KeyAeadMeta>::NonceSize>;
 
+type StoreMacKey = HmacKey<Sha256, U32>;
+
+// the label used to derive the key for encrypting tokens issued by the store
+const STORE_TOKEN_LABEL: &[u8] = b"askar:store-token";
+
+// the label used to derive the key for authenticating audit log records
+const AUDIT_MAC_LABEL: &[u8] = b"askar:audit-mac";
+
 /// Create a new raw (non-derived) store key
 pub fn generate_raw_store_key(seed: Option<&[u8]>) -> Result<PassKey<'static>, Error> {
     let key = if let Some(seed) = seed {
@@ -55,7 +73,6 @@ impl StoreKey {
         Ok(Self(Some(StoreKeyType::random()?)))
     }
 
-    #[allow(unused)]
     pub fn is_empty(&self) -> bool {
         self.0.is_none()
     }
@@ -85,6 +102,86 @@ impl StoreKey {
         }
     }
 
+    /// Encrypt another store key using this key
+    pub fn wrap_key(&self, key: &StoreKey) -> Result<Vec<u8>, Error> {
+        let data = match key.0.as_ref() {
+            Some(key) => key.with_secret_bytes(|sk| SecretBytes::from(sk.unwrap_or_default())),
+            None => SecretBytes::from(Vec::new()),
+        };
+        self.wrap_data(data)
+    }
+
+    /// Decrypt a store key encrypted using this key
+    pub fn unwrap_key(&self, ciphertext: Vec<u8>) -> Result<StoreKey, Error> {
+        let data = self.unwrap_data(ciphertext)?;
+        if data.is_empty() {
+            Ok(StoreKey::empty())
+        } else {
+            Ok(StoreKey::from(StoreKeyType::from_secret_bytes(
+                data.as_ref(),
+            )?))
+        }
+    }
+
+    /// Determine whether two store keys are equal
+    pub fn matches(&self, other: &StoreKey) -> bool {
+        match (self.0.as_ref(), other.0.as_ref()) {
+            (Some(key), Some(other)) => key.with_secret_bytes(|sk| {
+                other.with_secret_bytes(|osk| {
+                    sk.unwrap_or_default().ct_eq(osk.unwrap_or_default()).into()
+                })
+            }),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Encrypt data issued by the store as an opaque token
+    ///
+    /// The token is encrypted using a key derived from the store key. For a store
+    /// without a wrapping key, the token is only protected from accidental modification.
+    pub fn seal_token(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let key = StoreKeyType::from_secret_bytes(self.derive_key(STORE_TOKEN_LABEL)?.as_ref())?;
+        let nonce = StoreKeyNonce::random();
+        let mut buffer = SecretBytes::from_slice(data);
+        key.encrypt_in_place(&mut buffer, nonce.as_ref(), &[])?;
+        buffer.buffer_insert(0, nonce.as_ref())?;
+        Ok(buffer.into_vec())
+    }
+
+    /// Decrypt a token produced by [`StoreKey::seal_token`]
+    pub fn open_token(&self, token: &[u8]) -> Result<Vec<u8>, Error> {
+        if token.len() < StoreKeyNonce::SIZE {
+            return Err(err_msg!(Input, "Invalid token"));
+        }
+        let key = StoreKeyType::from_secret_bytes(self.derive_key(STORE_TOKEN_LABEL)?.as_ref())?;
+        let (nonce, ciphertext) = token.split_at(StoreKeyNonce::SIZE);
+        let mut buffer = SecretBytes::from_slice(ciphertext);
+        key.decrypt_in_place(&mut buffer, nonce, &[])
+            .map_err(|_| err_msg!(Input, "Invalid token"))?;
+        Ok(buffer.into_vec())
+    }
+
+    /// Compute a 32-byte authentication code for a record of the audit log
+    pub fn compute_audit_mac(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mac_key = StoreMacKey::from_slice(self.derive_key(AUDIT_MAC_LABEL)?.as_ref())?;
+        let mac = ArrayKey::<U32>::from_key_derivation(mac_key.hmac_deriver(&[data]))?;
+        Ok(mac.as_ref().to_vec())
+    }
+
+    // derive a secondary key for a particular purpose from the store key
+    fn derive_key(&self, label: &[u8]) -> Result<ArrayKey<U32>, Error> {
+        let base_key = match self.0.as_ref() {
+            Some(key) => {
+                key.with_secret_bytes(|sk| StoreMacKey::from_slice(sk.unwrap_or_default()))?
+            }
+            None => StoreMacKey::from_slice(&[0u8; 32])?,
+        };
+        Ok(ArrayKey::<U32>::from_key_derivation(
+            base_key.hmac_deriver(&[label]),
+        )?)
+    }
+
     pub fn to_passkey(&self) -> PassKey<'static> {
         if let Some(key) = self.0.as_ref() {
             PassKey::from(key.with_secret_bytes(|sk| bs58::encode(sk.unwrap()).into_string()))
@@ -111,6 +208,8 @@ pub enum StoreKeyMethod {
     RawKey,
     /// No wrapping key in effect
     Unprotected,
+    /// Wrap using a key protected by an external provider
+    Provider(Arc<dyn WrapKeyProvider>),
 }
 
 impl StoreKeyMethod {
@@ -126,6 +225,10 @@ impl StoreKeyMethod {
                 Ok(Self::DeriveKey(method))
             }
             PREFIX_NONE => Ok(Self::Unprotected),
+            PREFIX_EXT => Err(err_msg!(
+                Unsupported,
+                "A wrap key provider must be supplied directly"
+            )),
             _ => Err(err_msg!(Unsupported, "Invalid store key method")),
         }
     }
@@ -155,6 +258,14 @@ impl StoreKeyMethod {
                 Ok((key, StoreKeyReference::RawKey))
             }
             Self::Unprotected => Ok((StoreKey::empty(), StoreKeyReference::Unprotected)),
+            Self::Provider(provider) => {
+                validate_provider_id(provider.id())?;
+                let key = StoreKeyType::random()?;
+                let wrapped =
+                    key.with_secret_bytes(|sk| provider.wrap_key(sk.unwrap_or_default()))?;
+                let key_ref = StoreKeyReference::Provider(provider.id().to_string(), wrapped);
+                Ok((StoreKey::from(key), key_ref))
+            }
         }
     }
 }
@@ -165,12 +276,17 @@ impl Default for StoreKeyMethod {
     }
 }
 
-impl From<StoreKeyReference> for StoreKeyMethod {
-    fn from(key_ref: StoreKeyReference) -> Self {
+impl TryFrom<StoreKeyReference> for StoreKeyMethod {
+    type Error = Error;
+
+    fn try_from(key_ref: StoreKeyReference) -> Result<Self, Error> {
         match key_ref {
-            StoreKeyReference::DeriveKey(method, _) => Self::DeriveKey(method),
-            StoreKeyReference::RawKey => Self::RawKey,
-            StoreKeyReference::Unprotected => Self::Unprotected,
+            StoreKeyReference::DeriveKey(method, _) => Ok(Self::DeriveKey(method)),
+            StoreKeyReference::RawKey => Ok(Self::RawKey),
+            StoreKeyReference::Unprotected => Ok(Self::Unprotected),
+            StoreKeyReference::Provider(..) => {
+                Err(err_msg!(Input, "Wrap key provider not provided"))
+            }
         }
     }
 }
@@ -181,6 +297,7 @@ pub enum StoreKeyReference {
     DeriveKey(KdfMethod, String),
     RawKey,
     Unprotected,
+    Provider(String, Vec<u8>),
 }
 
 impl StoreKeyReference {
@@ -194,6 +311,16 @@ impl StoreKeyReference {
                 Ok(Self::DeriveKey(method, detail))
             }
             PREFIX_NONE => Ok(Self::Unprotected),
+            PREFIX_EXT => {
+                let detail = prefix_and_detail.next().unwrap_or_default();
+                let (id, wrapped) = detail
+                    .split_once("?wrapped=")
+                    .ok_or_else(|| err_msg!(Input, "Invalid wrap key provider reference"))?;
+                validate_provider_id(id)?;
+                let wrapped = hex::decode(wrapped)
+                    .map_err(err_map!(Input, "Invalid wrap key provider reference"))?;
+                Ok(Self::Provider(id.to_string(), wrapped))
+            }
             _ => Err(err_msg!(
                 Unsupported,
                 "Invalid store key method for reference"
@@ -209,6 +336,9 @@ impl StoreKeyReference {
             }
             Self::RawKey => *method == StoreKeyMethod::RawKey,
             Self::Unprotected => *method == StoreKeyMethod::Unprotected,
+            Self::Provider(id, _wrapped) => {
+                matches!(method, StoreKeyMethod::Provider(p) if p.id() == id)
+            }
         }
     }
 
@@ -218,6 +348,9 @@ impl StoreKeyReference {
             Self::DeriveKey(method, detail) => method.encode(Some(detail.as_str())),
             Self::RawKey => PREFIX_RAW.to_string(),
             Self::Unprotected => PREFIX_NONE.to_string(),
+            Self::Provider(id, wrapped) => {
+                format!("{}:{}?wrapped={}", PREFIX_EXT, id, hex::encode(wrapped))
+            }
         }
     }
 
@@ -239,6 +372,27 @@ impl StoreKeyReference {
                 }
             }
             Self::Unprotected => Ok(StoreKey::empty()),
+            Self::Provider(..) => Err(err_msg!(Input, "Wrap key provider not provided")),
+        }
+    }
+
+    /// Resolve the store key, using the wrap key provider of the store key
+    /// method if one is required by this reference
+    pub fn resolve_with(
+        &self,
+        method: Option<&StoreKeyMethod>,
+        pass_key: PassKey<'_>,
+    ) -> Result<StoreKey, Error> {
+        match (self, method) {
+            (Self::Provider(id, wrapped), Some(StoreKeyMethod::Provider(provider)))
+                if provider.id() == id =>
+            {
+                let key = provider.unwrap_key(wrapped)?;
+                Ok(StoreKey::from(StoreKeyType::from_secret_bytes(
+                    key.as_ref(),
+                )?))
+            }
+            _ => self.resolve(pass_key),
         }
     }
 }
@@ -283,6 +437,27 @@ mod tests {
         assert!(key_uri.starts_with("kdf:argon2i:13:mod?salt="));
     }
 
+    #[test]
+    fn derived_key_custom_params() {
+        let method = StoreKeyMethod::parse_uri("kdf:argon2i:m=16384,t=2,p=2").unwrap();
+        let (key, key_ref) = method
+            .resolve(PassKey::from("pass"))
+            .expect("Error deriving new key");
+        let key_uri = key_ref.into_uri();
+        assert!(key_uri.starts_with("kdf:argon2i:13:m=16384,t=2,p=2?salt="));
+        let key_ref = StoreKeyReference::parse_uri(&key_uri).expect("Error parsing key ref");
+        assert!(key_ref.compare_method(&method));
+        let wrapped = key
+            .wrap_data((&b"test data"[..]).into())
+            .expect("Error wrapping input");
+        let key = key_ref
+            .resolve(PassKey::from("pass"))
+            .expect("Error deriving existing key");
+        let unwrapped = key.unwrap_data(wrapped).expect("Error unwrapping data");
+        assert_eq!(unwrapped, &b"test data"[..]);
+        assert!(StoreKeyMethod::parse_uri("kdf:argon2i:m=16384,t=0").is_err());
+    }
+
     #[test]
     fn derived_key_unwrap_expected() {
         let input = b"test data";
@@ -356,6 +531,52 @@ mod tests {
         assert!(check_bad_key.is_err());
     }
 
+    #[derive(Debug)]
+    struct TestProvider(StoreKey);
+
+    impl WrapKeyProvider for TestProvider {
+        fn id(&self) -> &str {
+            "test"
+        }
+
+        fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>, Error> {
+            self.0.wrap_data(SecretBytes::from_slice(key))
+        }
+
+        fn unwrap_key(&self, wrapped: &[u8]) -> Result<SecretBytes, Error> {
+            self.0.unwrap_data(wrapped.to_vec())
+        }
+    }
+
+    #[test]
+    fn provider_key_wrap() {
+        let input = b"test data";
+        let method = StoreKeyMethod::Provider(Arc::new(TestProvider(StoreKey::random().unwrap())));
+        let (key, key_ref) = method
+            .resolve(None.into())
+            .expect("Error resolving provider key");
+        let wrapped = key
+            .wrap_data((&input[..]).into())
+            .expect("Error wrapping input");
+
+        // round trip the key reference
+        let key_uri = key_ref.into_uri();
+        assert!(key_uri.starts_with("ext:test?wrapped="));
+        let key_ref =
+            StoreKeyReference::parse_uri(&key_uri).expect("Error parsing provider key ref");
+        assert!(key_ref.compare_method(&method));
+        assert!(!key_ref.compare_method(&StoreKeyMethod::RawKey));
+        let key = key_ref
+            .resolve_with(Some(&method), None.into())
+            .expect("Error resolving provider key ref");
+        let unwrapped = key.unwrap_data(wrapped).expect("Error unwrapping data");
+        assert_eq!(unwrapped, &input[..]);
+
+        assert!(key_ref.resolve(None.into()).is_err());
+        let other = StoreKeyMethod::Provider(Arc::new(TestProvider(StoreKey::random().unwrap())));
+        assert!(key_ref.resolve_with(Some(&other), None.into()).is_err());
+    }
+
     #[test]
     fn unprotected_wrap() {
         let input = b"test data";
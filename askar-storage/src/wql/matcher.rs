@@ -0,0 +1,343 @@
+use std::marker::PhantomData;
+
+use super::tags::{CompareOp, ConjunctionOp, TagName, TagQueryEncoder};
+use crate::{entry::EncEntryTag, error::Error};
+
+/// A tag filter evaluated against the encrypted tags of a record, for
+/// backends without a query language of their own
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TagMatch {
+    Compare {
+        op: CompareOp,
+        name: Vec<u8>,
+        value: Vec<u8>,
+        plaintext: bool,
+        negate: bool,
+    },
+    In {
+        name: Vec<u8>,
+        values: Vec<Vec<u8>>,
+        plaintext: bool,
+        negate: bool,
+    },
+    Exist {
+        name: Vec<u8>,
+        plaintext: bool,
+        negate: bool,
+    },
+    Size {
+        name: Vec<u8>,
+        size: usize,
+        plaintext: bool,
+        negate: bool,
+    },
+    Conj(ConjunctionOp, Vec<TagMatch>),
+}
+
+impl TagMatch {
+    /// Check whether a set of encrypted tags satisfies the filter
+    pub fn matches(&self, tags: &[EncEntryTag]) -> bool {
+        match self {
+            Self::Compare {
+                op,
+                name,
+                value,
+                plaintext,
+                negate,
+            } => {
+                let found = tags.iter().any(|tag| {
+                    tag.plaintext == *plaintext
+                        && &tag.name == name
+                        && compare_value(*op, &tag.value, value)
+                });
+                found != *negate
+            }
+            Self::In {
+                name,
+                values,
+                plaintext,
+                negate,
+            } => {
+                let found = tags.iter().any(|tag| {
+                    tag.plaintext == *plaintext && &tag.name == name && values.contains(&tag.value)
+                });
+                found != *negate
+            }
+            Self::Exist {
+                name,
+                plaintext,
+                negate,
+            } => {
+                let found = tags
+                    .iter()
+                    .any(|tag| tag.plaintext == *plaintext && &tag.name == name);
+                found != *negate
+            }
+            Self::Size {
+                name,
+                size,
+                plaintext,
+                negate,
+            } => {
+                let count = tags
+                    .iter()
+                    .filter(|tag| tag.plaintext == *plaintext && &tag.name == name)
+                    .count();
+                (count == *size) != *negate
+            }
+            Self::Conj(ConjunctionOp::And, clauses) => clauses.iter().all(|c| c.matches(tags)),
+            Self::Conj(ConjunctionOp::Or, clauses) => clauses.iter().any(|c| c.matches(tags)),
+        }
+    }
+}
+
+fn compare_value(op: CompareOp, value: &[u8], target: &[u8]) -> bool {
+    match op {
+        CompareOp::Eq => value == target,
+        CompareOp::Neq => value != target,
+        CompareOp::Gt => value > target,
+        CompareOp::Gte => value >= target,
+        CompareOp::Lt => value < target,
+        CompareOp::Lte => value <= target,
+        CompareOp::Like => like_match(value, target),
+        CompareOp::Prefix => value.starts_with(target),
+    }
+}
+
+// match a value against a SQL LIKE pattern, where '%' matches any sequence
+// of bytes and '_' matches a single byte
+fn like_match(value: &[u8], pattern: &[u8]) -> bool {
+    let (mut vi, mut pi) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while vi < value.len() {
+        match pattern.get(pi) {
+            Some(b'%') => {
+                pi += 1;
+                backtrack = Some((vi, pi));
+            }
+            Some(c) if *c == b'_' || *c == value[vi] => {
+                vi += 1;
+                pi += 1;
+            }
+            _ => {
+                if let Some((bv, bp)) = backtrack {
+                    vi = bv + 1;
+                    pi = bp;
+                    backtrack = Some((vi, bp));
+                } else {
+                    return false;
+                }
+            }
+        }
+    }
+    pattern[pi..].iter().all(|c| *c == b'%')
+}
+
+pub struct TagMatchEncoder<'e, EN, EV> {
+    pub enc_name: EN,
+    pub enc_value: EV,
+    _pd: PhantomData<&'e ()>,
+}
+
+impl<'e, EN, EV> TagMatchEncoder<'e, EN, EV>
+where
+    EN: Fn(&str) -> Result<Vec<u8>, Error> + 'e,
+    EV: Fn(&str) -> Result<Vec<u8>, Error> + 'e,
+{
+    pub fn new(enc_name: EN, enc_value: EV) -> Self {
+        Self {
+            enc_name,
+            enc_value,
+            _pd: PhantomData,
+        }
+    }
+}
+
+impl<'e, EN, EV> TagQueryEncoder for TagMatchEncoder<'e, EN, EV>
+where
+    EN: Fn(&str) -> Result<Vec<u8>, Error> + 'e,
+    EV: Fn(&str) -> Result<Vec<u8>, Error> + 'e,
+{
+    type Arg = Vec<u8>;
+    type Clause = TagMatch;
+
+    fn encode_name(&mut self, name: &TagName) -> Result<Self::Arg, Error> {
+        Ok(match name {
+            TagName::Encrypted(name) | TagName::Plaintext(name) => (self.enc_name)(name)?,
+            TagName::Field(field) => {
+                return Err(err_msg!(
+                    Input,
+                    "Record field '{}' may only be used in comparisons",
+                    field.as_str()
+                ))
+            }
+        })
+    }
+
+    fn encode_value(&mut self, value: &str, is_plaintext: bool) -> Result<Self::Arg, Error> {
+        Ok(if is_plaintext {
+            value.as_bytes().to_vec()
+        } else {
+            (self.enc_value)(value)?
+        })
+    }
+
+    fn encode_op_clause(
+        &mut self,
+        op: CompareOp,
+        enc_name: Self::Arg,
+        enc_value: Self::Arg,
+        is_plaintext: bool,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        Ok(Some(TagMatch::Compare {
+            op,
+            name: enc_name,
+            value: enc_value,
+            plaintext: is_plaintext,
+            negate,
+        }))
+    }
+
+    fn encode_in_clause(
+        &mut self,
+        enc_name: Self::Arg,
+        enc_values: Vec<Self::Arg>,
+        is_plaintext: bool,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        Ok(Some(TagMatch::In {
+            name: enc_name,
+            values: enc_values,
+            plaintext: is_plaintext,
+            negate,
+        }))
+    }
+
+    fn encode_exist_clause(
+        &mut self,
+        enc_name: Self::Arg,
+        is_plaintext: bool,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        Ok(Some(TagMatch::Exist {
+            name: enc_name,
+            plaintext: is_plaintext,
+            negate,
+        }))
+    }
+
+    fn encode_size_clause(
+        &mut self,
+        enc_name: Self::Arg,
+        size: usize,
+        is_plaintext: bool,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        Ok(Some(TagMatch::Size {
+            name: enc_name,
+            size,
+            plaintext: is_plaintext,
+            negate,
+        }))
+    }
+
+    fn encode_conj_clause(
+        &mut self,
+        op: ConjunctionOp,
+        clauses: Vec<Self::Clause>,
+    ) -> Result<Option<Self::Clause>, Error> {
+        if clauses.is_empty() && op == ConjunctionOp::And {
+            Ok(None)
+        } else {
+            Ok(Some(TagMatch::Conj(op, clauses)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tags::TagQuery;
+    use super::*;
+
+    fn tag(name: &str, value: &str, plaintext: bool) -> EncEntryTag {
+        EncEntryTag {
+            name: name.as_bytes().to_vec(),
+            value: value.as_bytes().to_vec(),
+            plaintext,
+        }
+    }
+
+    #[test]
+    fn tag_query_match() {
+        let query = TagQuery::Or(vec![
+            TagQuery::And(vec![
+                TagQuery::Eq(TagName::Encrypted("enctag".to_string()), "a".to_string()),
+                TagQuery::Eq(
+                    TagName::Plaintext("plaintag".to_string()),
+                    "plainval".to_string(),
+                ),
+            ]),
+            TagQuery::Not(Box::new(TagQuery::Exist(vec![TagName::Plaintext(
+                "other".to_string(),
+            )]))),
+        ]);
+        let mut enc = TagMatchEncoder::new(
+            |name: &str| Ok(name.as_bytes().to_vec()),
+            |value: &str| Ok(value.to_uppercase().into_bytes()),
+        );
+        let matcher = enc.encode_query(&query).unwrap().unwrap();
+
+        assert!(matcher.matches(&[]));
+        assert!(matcher.matches(&[tag("enctag", "A", false), tag("plaintag", "plainval", true)]));
+        assert!(!matcher.matches(&[tag("enctag", "A", false), tag("other", "", true)]));
+        assert!(matcher.matches(&[
+            tag("enctag", "A", false),
+            tag("plaintag", "plainval", true),
+            tag("other", "", true)
+        ]));
+        assert!(!matcher.matches(&[tag("enctag", "A", true), tag("other", "", true)]));
+    }
+
+    #[test]
+    fn tag_size_match() {
+        let mut enc = TagMatchEncoder::new(
+            |name: &str| Ok(name.as_bytes().to_vec()),
+            |value: &str| Ok(value.to_uppercase().into_bytes()),
+        );
+        let matcher = enc
+            .encode_query(&TagQuery::Size(TagName::Encrypted("role".to_string()), 2))
+            .unwrap()
+            .unwrap();
+        assert!(!matcher.matches(&[tag("role", "A", false)]));
+        assert!(matcher.matches(&[tag("role", "A", false), tag("role", "B", false)]));
+        assert!(!matcher.matches(&[tag("role", "A", false), tag("role", "B", true)]));
+
+        let matcher = enc
+            .encode_query(&TagQuery::Size(TagName::Encrypted("role".to_string()), 0))
+            .unwrap()
+            .unwrap();
+        assert!(matcher.matches(&[]));
+        assert!(!matcher.matches(&[tag("role", "A", false)]));
+    }
+
+    #[test]
+    fn tag_like_match() {
+        assert!(like_match(b"value", b"value"));
+        assert!(like_match(b"value", b"v%"));
+        assert!(like_match(b"value", b"%lu%"));
+        assert!(like_match(b"value", b"_al_e"));
+        assert!(like_match(b"value", b"%"));
+        assert!(!like_match(b"value", b"%x%"));
+        assert!(!like_match(b"value", b"valu"));
+        assert!(!like_match(b"", b"_"));
+    }
+
+    #[test]
+    fn tag_prefix_match() {
+        assert!(compare_value(CompareOp::Prefix, b"50%_off", b"50%_"));
+        assert!(compare_value(CompareOp::Prefix, b"value", b""));
+        assert!(!compare_value(CompareOp::Prefix, b"500 off", b"50%"));
+        assert!(!compare_value(CompareOp::Prefix, b"val", b"value"));
+    }
+}
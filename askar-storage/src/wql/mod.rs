@@ -1,5 +1,11 @@
 mod query;
 pub use query::{AbstractQuery, Query};
 
+#[cfg(any(test, feature = "indexeddb", feature = "redb"))]
+pub mod matcher;
+
+#[cfg(any(test, feature = "mssql"))]
+pub mod mssql;
+
 pub mod sql;
 pub mod tags;
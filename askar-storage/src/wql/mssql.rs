@@ -0,0 +1,247 @@
+use std::marker::PhantomData;
+
+use itertools::Itertools;
+
+use super::tags::{CompareOp, ConjunctionOp, TagName, TagQueryEncoder};
+use crate::error::Error;
+
+/// Encode tag queries as SQL Server (T-SQL) clauses, using `@P{n}` placeholders
+pub struct TagMssqlEncoder<'e, EN, EV> {
+    pub enc_name: EN,
+    pub enc_value: EV,
+    pub arguments: Vec<Vec<u8>>,
+    first_index: usize,
+    _pd: PhantomData<&'e ()>,
+}
+
+impl<'e, EN, EV> TagMssqlEncoder<'e, EN, EV>
+where
+    EN: Fn(&str) -> Result<Vec<u8>, Error> + 'e,
+    EV: Fn(&str) -> Result<Vec<u8>, Error> + 'e,
+{
+    /// Create a new encoder, numbering placeholders starting from `first_index`
+    pub fn new(enc_name: EN, enc_value: EV, first_index: usize) -> Self {
+        Self {
+            enc_name,
+            enc_value,
+            arguments: vec![],
+            first_index,
+            _pd: PhantomData,
+        }
+    }
+
+    fn push_arg(&mut self, arg: Vec<u8>) -> String {
+        let idx = self.first_index + self.arguments.len();
+        self.arguments.push(arg);
+        format!("@P{}", idx)
+    }
+}
+
+impl<'e, EN, EV> TagQueryEncoder for TagMssqlEncoder<'e, EN, EV>
+where
+    EN: Fn(&str) -> Result<Vec<u8>, Error> + 'e,
+    EV: Fn(&str) -> Result<Vec<u8>, Error> + 'e,
+{
+    type Arg = Vec<u8>;
+    type Clause = String;
+
+    fn encode_name(&mut self, name: &TagName) -> Result<Self::Arg, Error> {
+        Ok(match name {
+            TagName::Encrypted(name) | TagName::Plaintext(name) => (self.enc_name)(name)?,
+            TagName::Field(field) => {
+                return Err(err_msg!(
+                    Input,
+                    "Record field '{}' may only be used in comparisons",
+                    field.as_str()
+                ))
+            }
+        })
+    }
+
+    fn encode_value(&mut self, value: &str, is_plaintext: bool) -> Result<Self::Arg, Error> {
+        Ok(if is_plaintext {
+            value.as_bytes().to_vec()
+        } else {
+            (self.enc_value)(value)?
+        })
+    }
+
+    fn encode_op_clause(
+        &mut self,
+        op: CompareOp,
+        enc_name: Self::Arg,
+        enc_value: Self::Arg,
+        is_plaintext: bool,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        let match_prefix = match (is_plaintext, op.as_sql_str_for_prefix()) {
+            // the first 12 bytes of an encrypted tag value are a synthetic nonce,
+            // which serves as an effective index on its own
+            (false, Some(pfx_op)) if enc_value.len() > 12 => {
+                Some((pfx_op, enc_value[..12].to_vec()))
+            }
+            _ => None,
+        };
+        let name_arg = self.push_arg(enc_name);
+        let value_cond = if op == CompareOp::Like {
+            // LIKE is not defined for VARBINARY operands
+            format!(
+                "CAST(value AS VARCHAR(MAX)) LIKE CAST({} AS VARCHAR(MAX))",
+                self.push_arg(enc_value)
+            )
+        } else if op == CompareOp::Prefix {
+            format!(
+                "SUBSTRING(value, 1, {}) = {}",
+                enc_value.len(),
+                self.push_arg(enc_value)
+            )
+        } else {
+            format!("value {} {}", op.as_sql_str(), self.push_arg(enc_value))
+        };
+        let prefix_cond = match match_prefix {
+            Some((pfx_op, prefix)) => format!(
+                " AND SUBSTRING(value, 1, 12) {} {}",
+                pfx_op,
+                self.push_arg(prefix)
+            ),
+            None => String::new(),
+        };
+        Ok(Some(format!(
+            "i.id {} (SELECT item_id FROM items_tags WHERE name = {} AND {}{} AND plaintext = {})",
+            if negate { "NOT IN" } else { "IN" },
+            name_arg,
+            value_cond,
+            prefix_cond,
+            i32::from(is_plaintext)
+        )))
+    }
+
+    fn encode_in_clause(
+        &mut self,
+        enc_name: Self::Arg,
+        enc_values: Vec<Self::Arg>,
+        is_plaintext: bool,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        let name_arg = self.push_arg(enc_name);
+        let args_in = Itertools::intersperse(
+            enc_values.into_iter().map(|v| self.push_arg(v)),
+            ", ".to_string(),
+        )
+        .collect::<String>();
+        Ok(Some(format!(
+            "i.id {} (SELECT item_id FROM items_tags WHERE name = {} AND value IN ({}) AND plaintext = {})",
+            if negate { "NOT IN" } else { "IN" },
+            name_arg,
+            args_in,
+            i32::from(is_plaintext)
+        )))
+    }
+
+    fn encode_exist_clause(
+        &mut self,
+        enc_name: Self::Arg,
+        is_plaintext: bool,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        Ok(Some(format!(
+            "i.id {} (SELECT item_id FROM items_tags WHERE name = {} AND plaintext = {})",
+            if negate { "NOT IN" } else { "IN" },
+            self.push_arg(enc_name),
+            i32::from(is_plaintext)
+        )))
+    }
+
+    fn encode_size_clause(
+        &mut self,
+        enc_name: Self::Arg,
+        size: usize,
+        is_plaintext: bool,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        Ok(Some(format!(
+            "i.id {} (SELECT item_id FROM items_tags WHERE name = {} AND plaintext = {} GROUP BY item_id HAVING COUNT(*) = {})",
+            if negate { "NOT IN" } else { "IN" },
+            self.push_arg(enc_name),
+            i32::from(is_plaintext),
+            size
+        )))
+    }
+
+    fn encode_conj_clause(
+        &mut self,
+        op: ConjunctionOp,
+        clauses: Vec<Self::Clause>,
+    ) -> Result<Option<Self::Clause>, Error> {
+        let qc = clauses.len();
+        if qc == 0 {
+            if op == ConjunctionOp::Or {
+                // T-SQL has no boolean literals
+                return Ok(Some("1=0".to_string()));
+            } else {
+                return Ok(None);
+            }
+        }
+        let mut s = String::new();
+        if qc > 1 {
+            s.push('(');
+        }
+        for (index, clause) in clauses.into_iter().enumerate() {
+            if index > 0 {
+                s.push_str(op.as_sql_str());
+            }
+            s.push_str(&clause);
+        }
+        if qc > 1 {
+            s.push(')');
+        }
+        Ok(Some(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tags::TagQuery;
+    use super::*;
+
+    #[test]
+    fn tag_query_encode_mssql() {
+        let query = TagQuery::Or(vec![
+            TagQuery::And(vec![
+                TagQuery::Eq(
+                    TagName::Encrypted("enctag".to_string()),
+                    "noncenonce12encval".to_string(),
+                ),
+                TagQuery::Like(
+                    TagName::Plaintext("plaintag".to_string()),
+                    "plain%".to_string(),
+                ),
+            ]),
+            TagQuery::Not(Box::new(TagQuery::In(
+                TagName::Plaintext("plaintag".to_string()),
+                vec!["a".to_string(), "b".to_string()],
+            ))),
+            TagQuery::Or(vec![]),
+        ]);
+        let mut enc = TagMssqlEncoder::new(
+            |name: &str| Ok(format!("--{}--", name).into_bytes()),
+            |value: &str| Ok(value.to_uppercase().into_bytes()),
+            4,
+        );
+        let query_str = enc.encode_query(&query).unwrap().unwrap();
+        assert_eq!(query_str, "((i.id IN (SELECT item_id FROM items_tags WHERE name = @P4 AND value = @P5 AND SUBSTRING(value, 1, 12) = @P6 AND plaintext = 0) AND i.id IN (SELECT item_id FROM items_tags WHERE name = @P7 AND CAST(value AS VARCHAR(MAX)) LIKE CAST(@P8 AS VARCHAR(MAX)) AND plaintext = 1)) OR i.id NOT IN (SELECT item_id FROM items_tags WHERE name = @P9 AND value IN (@P10, @P11) AND plaintext = 1) OR 1=0)");
+        assert_eq!(
+            enc.arguments,
+            vec![
+                b"--enctag--".to_vec(),
+                b"NONCENONCE12ENCVAL".to_vec(),
+                b"NONCENONCE12".to_vec(),
+                b"--plaintag--".to_vec(),
+                b"plain%".to_vec(),
+                b"--plaintag--".to_vec(),
+                b"a".to_vec(),
+                b"b".to_vec(),
+            ]
+        );
+    }
+}
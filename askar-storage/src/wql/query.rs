@@ -25,10 +25,14 @@ pub enum AbstractQuery<K, V> {
     Lte(K, V),
     /// SQL 'LIKE'-compatible string comparison for a field value
     Like(K, V),
+    /// Match field values beginning with the given bytes
+    Prefix(K, V),
     /// Match one of multiple field values in a set
     In(K, Vec<V>),
     /// Match any non-null field value of the given field names
     Exist(Vec<K>),
+    /// Match the number of values present for a field name
+    Size(K, usize),
 }
 
 /// A concrete query implementation with String keys and values
@@ -125,6 +129,10 @@ impl<K, V> AbstractQuery<K, V> {
                 let tag_value = vf(&tag_name, tag_value)?;
                 Ok(AbstractQuery::<RK, RV>::Like(kf(tag_name)?, tag_value))
             }
+            Self::Prefix(tag_name, tag_value) => {
+                let tag_value = vf(&tag_name, tag_value)?;
+                Ok(AbstractQuery::<RK, RV>::Prefix(kf(tag_name)?, tag_value))
+            }
             Self::In(tag_name, tag_values) => {
                 let tag_values = tag_values
                     .into_iter()
@@ -138,6 +146,7 @@ impl<K, V> AbstractQuery<K, V> {
                     Result::<_, E>::Ok(v)
                 })?,
             )),
+            Self::Size(tag_name, size) => Ok(AbstractQuery::<RK, RV>::Size(kf(tag_name)?, size)),
             Self::And(subqueries) => {
                 let subqueries = subqueries
                     .into_iter()
@@ -166,7 +175,7 @@ impl<K, V> Default for AbstractQuery<K, V> {
 }
 
 mod serde_support {
-    use std::string;
+    use std::fmt;
 
     use serde::ser::{Serialize, Serializer};
     use serde::{de, Deserialize, Deserializer};
@@ -239,10 +248,14 @@ mod serde_support {
                 Self::Lt(ref tag_name, ref tag_value) => json!({tag_name: {"$lt": tag_value}}),
                 Self::Lte(ref tag_name, ref tag_value) => json!({tag_name: {"$lte": tag_value}}),
                 Self::Like(ref tag_name, ref tag_value) => json!({tag_name: {"$like": tag_value}}),
+                Self::Prefix(ref tag_name, ref tag_value) => {
+                    json!({tag_name: {"$prefix": tag_value}})
+                }
                 Self::In(ref tag_name, ref tag_values) => json!({tag_name: {"$in":tag_values}}),
                 Self::Exist(ref tag_names) => {
                     json!({ "$exist": tag_names.iter().map(Into::into).collect::<Vec<String>>() })
                 }
+                Self::Size(ref tag_name, size) => json!({tag_name: {"$size": size}}),
                 Self::And(ref queries) => {
                     if queries.is_empty() {
                         json!({})
@@ -266,9 +279,9 @@ mod serde_support {
         }
     }
 
-    impl string::ToString for Query {
-        fn to_string(&self) -> String {
-            self.to_value().to_string()
+    impl fmt::Display for Query {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.to_value().fmt(f)
         }
     }
 
@@ -378,6 +391,16 @@ mod serde_support {
             ("$lte", _) => Err("$lte must be used with string"),
             ("$like", JsonValue::String(value_)) => Ok(Query::Like(key, value_)),
             ("$like", _) => Err("$like must be used with string"),
+            ("$prefix", JsonValue::String(value_)) => Ok(Query::Prefix(key, value_)),
+            ("$prefix", _) => Err("$prefix must be used with string"),
+            ("$between", JsonValue::Array(values)) => match <[JsonValue; 2]>::try_from(values) {
+                Ok([JsonValue::String(low), JsonValue::String(high)]) => Ok(Query::And(vec![
+                    Query::Gte(key.clone(), low),
+                    Query::Lte(key, high),
+                ])),
+                _ => Err("$between must be used with array of two strings"),
+            },
+            ("$between", _) => Err("$between must be used with array of two strings"),
             ("$in", JsonValue::Array(values)) => {
                 let mut target_values: Vec<String> = Vec::with_capacity(values.len());
 
@@ -392,6 +415,16 @@ mod serde_support {
                 Ok(Query::In(key, target_values))
             }
             ("$in", _) => Err("$in must be used with array of strings"),
+            ("$exists", JsonValue::Bool(true)) => Ok(Query::Exist(vec![key])),
+            ("$exists", JsonValue::Bool(false)) => {
+                Ok(Query::Not(Box::new(Query::Exist(vec![key]))))
+            }
+            ("$exists", _) => Err("$exists must be used with boolean"),
+            ("$size", JsonValue::Number(size)) => match size.as_u64() {
+                Some(size) => Ok(Query::Size(key, size as usize)),
+                None => Err("$size must be used with non-negative integer"),
+            },
+            ("$size", _) => Err("$size must be used with non-negative integer"),
             (_, _) => Err("Unknown operator"),
         }
     }
@@ -551,6 +584,92 @@ mod tests {
         assert_eq!(query, expected);
     }
 
+    #[test]
+    fn test_simple_operator_exists_parse() {
+        let name1 = _random_string(10);
+
+        let json = format!(r#"{{"{}":{{"$exists":true}}}}"#, name1);
+        let query: Query = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(query, Query::Exist(vec![name1.clone()]));
+
+        let json = format!(r#"{{"{}":{{"$exists":false}}}}"#, name1);
+        let query: Query = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(query, Query::Not(Box::new(Query::Exist(vec![name1]))));
+    }
+
+    #[test]
+    fn test_simple_operator_size_parse() {
+        let name1 = _random_string(10);
+
+        let json = format!(r#"{{"{}":{{"$size":2}}}}"#, name1);
+
+        let query: Query = ::serde_json::from_str(&json).unwrap();
+
+        let expected = Query::Size(name1, 2);
+
+        assert_eq!(query, expected);
+
+        let json = format!(r#"{{"{}":{{"$size":-1}}}}"#, _random_string(10));
+        ::serde_json::from_str::<Query>(&json).unwrap_err();
+    }
+
+    #[test]
+    fn test_simple_operator_size_to_string() {
+        let name1 = _random_string(10);
+
+        let query = Query::Size(name1.clone(), 3);
+
+        let json = query.to_string();
+
+        let expected = format!(r#"{{"{}":{{"$size":3}}}}"#, name1);
+
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn test_simple_operator_prefix_parse() {
+        let name1 = _random_string(10);
+
+        let json = format!(r#"{{"{}":{{"$prefix":"50%_off"}}}}"#, name1);
+
+        let query: Query = ::serde_json::from_str(&json).unwrap();
+
+        let expected = Query::Prefix(name1, "50%_off".to_string());
+
+        assert_eq!(query, expected);
+    }
+
+    #[test]
+    fn test_simple_operator_between_parse() {
+        let name1 = _random_string(10);
+        let value1 = _random_string(10);
+        let value2 = _random_string(10);
+
+        let json = format!(
+            r#"{{"{}":{{"$between":["{}","{}"]}}}}"#,
+            name1, value1, value2
+        );
+
+        let query: Query = ::serde_json::from_str(&json).unwrap();
+
+        let expected = Query::And(vec![
+            Query::Gte(name1.clone(), value1),
+            Query::Lte(name1, value2),
+        ]);
+
+        assert_eq!(query, expected);
+    }
+
+    #[test]
+    fn test_simple_operator_between_invalid() {
+        let name1 = _random_string(10);
+        let value1 = _random_string(10);
+
+        let json = format!(r#"{{"{}":{{"$between":["{}"]}}}}"#, name1, value1);
+
+        ::serde_json::from_str::<Query>(&json).unwrap_err();
+    }
+
     #[test]
     fn test_simple_operator_in_plaintext_parse() {
         let name1 = _random_string(10);
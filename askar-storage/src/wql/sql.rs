@@ -2,7 +2,7 @@ use std::marker::PhantomData;
 
 use itertools::Itertools;
 
-use super::tags::{CompareOp, ConjunctionOp, TagName, TagQueryEncoder};
+use super::tags::{CompareOp, ConjunctionOp, RecordField, TagName, TagQueryEncoder};
 use crate::error::Error;
 
 pub struct TagSqlEncoder<'e, EN, EV> {
@@ -38,6 +38,13 @@ where
     fn encode_name(&mut self, name: &TagName) -> Result<Self::Arg, Error> {
         Ok(match name {
             TagName::Encrypted(name) | TagName::Plaintext(name) => (self.enc_name)(name)?,
+            TagName::Field(field) => {
+                return Err(err_msg!(
+                    Input,
+                    "Record field '{}' may only be used in comparisons",
+                    field.as_str()
+                ))
+            }
         })
     }
 
@@ -71,6 +78,12 @@ where
             }
             _ => (String::new(), None),
         };
+        let value_expr = if op == CompareOp::Prefix {
+            // compare bytes rather than characters, avoiding LIKE wildcards
+            format!("SUBSTR(value, 1, {})", enc_value.len())
+        } else {
+            "value".to_string()
+        };
         self.arguments.push(enc_name);
         self.arguments.push(enc_value);
         if let Some(v) = match_prefix {
@@ -78,9 +91,10 @@ where
         }
 
         let query = format!(
-            "i.id {} (SELECT item_id FROM items_tags WHERE name = ${} AND value {} ${}{} AND plaintext = {})",
+            "i.id {} (SELECT item_id FROM items_tags WHERE name = ${} AND {} {} ${}{} AND plaintext = {})",
             if negate { "NOT IN" } else { "IN" },
             idx + 1,
+            value_expr,
             op.as_sql_str(),
             idx + 2,
             op_prefix.as_str(),
@@ -89,6 +103,38 @@ where
         Ok(Some(query))
     }
 
+    fn encode_field_clause(
+        &mut self,
+        op: CompareOp,
+        field: RecordField,
+        value: &str,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        if matches!(op, CompareOp::Like | CompareOp::Prefix) {
+            return Err(err_msg!(
+                Input,
+                "Pattern matching is not supported for record field '{}'",
+                field.as_str()
+            ));
+        }
+        // timestamps are compared as integers, so may be included in the query directly
+        let value: i64 = value.parse().map_err(|_| {
+            err_msg!(
+                Input,
+                "Invalid timestamp for record field '{}': '{}'",
+                field.as_str(),
+                value
+            )
+        })?;
+        let query = format!("i.{} {} {}", field.as_str(), op.as_sql_str(), value);
+        Ok(Some(if negate {
+            // records without a timestamp are included when negated, as for missing tags
+            format!("({}) IS NOT TRUE", query)
+        } else {
+            query
+        }))
+    }
+
     fn encode_in_clause(
         &mut self,
         enc_name: Self::Arg,
@@ -124,6 +170,23 @@ where
         Ok(Some(query))
     }
 
+    fn encode_size_clause(
+        &mut self,
+        enc_name: Self::Arg,
+        size: usize,
+        is_plaintext: bool,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        let query = format!(
+            "i.id {} (SELECT item_id FROM items_tags WHERE name = $$ AND plaintext = {} GROUP BY item_id HAVING COUNT(*) = {})",
+            if negate { "NOT IN" } else { "IN" },
+            i32::from(is_plaintext),
+            size
+        );
+        self.arguments.push(enc_name);
+        Ok(Some(query))
+    }
+
     fn encode_conj_clause(
         &mut self,
         op: ConjunctionOp,
@@ -205,4 +268,67 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn tag_query_encode_size() {
+        let query = TagQuery::Not(Box::new(TagQuery::Size(
+            TagName::Encrypted("enctag".to_string()),
+            2,
+        )));
+        let mut enc = TagSqlEncoder::new(
+            |name: &str| Ok(format!("--{}--", name).into_bytes()),
+            |value: &str| Ok(value.to_uppercase().into_bytes()),
+        );
+        let query_str = enc.encode_query(&query).unwrap().unwrap();
+        assert_eq!(query_str, "i.id NOT IN (SELECT item_id FROM items_tags WHERE name = $$ AND plaintext = 0 GROUP BY item_id HAVING COUNT(*) = 2)");
+        assert_eq!(enc.arguments, vec![b"--enctag--".to_vec()]);
+    }
+
+    #[test]
+    fn tag_query_encode_prefix() {
+        let query = TagQuery::Prefix(
+            TagName::Plaintext("plaintag".to_string()),
+            "50%_".to_string(),
+        );
+        let mut enc = TagSqlEncoder::new(
+            |name: &str| Ok(format!("--{}--", name).into_bytes()),
+            |value: &str| Ok(value.to_uppercase().into_bytes()),
+        );
+        let query_str = enc.encode_query(&query).unwrap().unwrap();
+        assert_eq!(query_str, "i.id IN (SELECT item_id FROM items_tags WHERE name = $1 AND SUBSTR(value, 1, 4) = $2 AND plaintext = 1)");
+        assert_eq!(
+            enc.arguments,
+            vec![b"--plaintag--".to_vec(), b"50%_".to_vec()]
+        );
+    }
+
+    #[test]
+    fn tag_query_encode_field() {
+        let query = TagQuery::And(vec![
+            TagQuery::Gte(
+                TagName::Field(RecordField::CreatedAt),
+                "1700000000000".to_string(),
+            ),
+            TagQuery::Not(Box::new(TagQuery::Lt(
+                TagName::Field(RecordField::UpdatedAt),
+                "1800000000000".to_string(),
+            ))),
+        ]);
+        let mut enc = TagSqlEncoder::new(
+            |name: &str| Ok(format!("--{}--", name).into_bytes()),
+            |value: &str| Ok(value.to_uppercase().into_bytes()),
+        );
+        let query_str = enc.encode_query(&query).unwrap().unwrap();
+        assert_eq!(
+            query_str,
+            "(i.created_at >= 1700000000000 AND (i.updated_at < 1800000000000) IS NOT TRUE)"
+        );
+        assert!(enc.arguments.is_empty());
+
+        let query = TagQuery::Eq(
+            TagName::Field(RecordField::CreatedAt),
+            "1; DROP TABLE items".to_string(),
+        );
+        assert!(enc.encode_query(&query).is_err());
+    }
 }
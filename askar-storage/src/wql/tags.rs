@@ -1,38 +1,103 @@
+use std::fmt::{self, Display, Formatter};
+
 use super::{AbstractQuery, Query};
 use crate::error::Error;
 
 pub type TagQuery = AbstractQuery<TagName, String>;
 
 pub fn tag_query(query: Query) -> Result<TagQuery, Error> {
-    let result = query
-        .map_names(|k| {
-            if let Some(plain) = k.strip_prefix('~') {
-                Result::<_, ()>::Ok(TagName::Plaintext(plain.to_string()))
-            } else {
-                Ok(TagName::Encrypted(k))
-            }
-        })
-        .unwrap();
+    let result = query.map_names(|k| {
+        if let Some(plain) = k.strip_prefix('~') {
+            Ok(TagName::Plaintext(plain.to_string()))
+        } else if let Some(field) = k.strip_prefix('$') {
+            RecordField::from_name(field).map(TagName::Field)
+        } else {
+            Ok(TagName::Encrypted(k))
+        }
+    })?;
     validate_tag_query(&result)?;
     Ok(result)
 }
 
-pub fn validate_tag_query(_query: &TagQuery) -> Result<(), Error> {
-    // FIXME only equality comparison supported for encrypted keys
-    Ok(())
+pub fn validate_tag_query(query: &TagQuery) -> Result<(), Error> {
+    match query {
+        TagQuery::Gt(TagName::Encrypted(name), _)
+        | TagQuery::Gte(TagName::Encrypted(name), _)
+        | TagQuery::Lt(TagName::Encrypted(name), _)
+        | TagQuery::Lte(TagName::Encrypted(name), _) => Err(err_msg!(
+            Input,
+            "Ordered comparison is only supported for plaintext tags: '{}'",
+            name
+        )),
+        TagQuery::Prefix(TagName::Encrypted(name), _) => Err(err_msg!(
+            Input,
+            "Prefix matching is only supported for plaintext tags: '{}'",
+            name
+        )),
+        TagQuery::Like(TagName::Field(field), _)
+        | TagQuery::Prefix(TagName::Field(field), _)
+        | TagQuery::In(TagName::Field(field), _)
+        | TagQuery::Size(TagName::Field(field), _) => Err(err_msg!(
+            Input,
+            "Record field '{}' may only be used in comparisons",
+            field.as_str()
+        )),
+        TagQuery::Exist(names) => match names.iter().find_map(|name| match name {
+            TagName::Field(field) => Some(field),
+            _ => None,
+        }) {
+            Some(field) => Err(err_msg!(
+                Input,
+                "Record field '{}' may only be used in comparisons",
+                field.as_str()
+            )),
+            None => Ok(()),
+        },
+        TagQuery::And(subqueries) | TagQuery::Or(subqueries) => {
+            subqueries.iter().try_for_each(validate_tag_query)
+        }
+        TagQuery::Not(subquery) => validate_tag_query(subquery),
+        _ => Ok(()),
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum TagName {
     Encrypted(String),
     Plaintext(String),
+    Field(RecordField),
 }
 
-impl ToString for TagName {
-    fn to_string(&self) -> String {
+impl Display for TagName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Encrypted(v) => v.to_string(),
-            Self::Plaintext(v) => format!("~{}", v),
+            Self::Encrypted(v) => f.write_str(v),
+            Self::Plaintext(v) => write!(f, "~{}", v),
+            Self::Field(field) => write!(f, "${}", field.as_str()),
+        }
+    }
+}
+
+/// A record field which may be compared in a tag filter, in place of a tag
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RecordField {
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl RecordField {
+    pub fn from_name(name: &str) -> Result<Self, Error> {
+        match name {
+            "created_at" => Ok(Self::CreatedAt),
+            "updated_at" => Ok(Self::UpdatedAt),
+            _ => Err(err_msg!(Input, "Unknown record field: '{}'", name)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+            Self::UpdatedAt => "updated_at",
         }
     }
 }
@@ -67,6 +132,20 @@ pub trait TagQueryEncoder {
         negate: bool,
     ) -> Result<Option<Self::Clause>, Error>;
 
+    fn encode_field_clause(
+        &mut self,
+        _op: CompareOp,
+        field: RecordField,
+        _value: &str,
+        _negate: bool,
+    ) -> Result<Option<Self::Clause>, Error> {
+        Err(err_msg!(
+            Unsupported,
+            "Filtering on record field '{}' is not supported by this backend",
+            field.as_str()
+        ))
+    }
+
     fn encode_in_clause(
         &mut self,
         enc_name: Self::Arg,
@@ -82,6 +161,14 @@ pub trait TagQueryEncoder {
         negate: bool,
     ) -> Result<Option<Self::Clause>, Error>;
 
+    fn encode_size_clause(
+        &mut self,
+        enc_name: Self::Arg,
+        size: usize,
+        is_plaintext: bool,
+        negate: bool,
+    ) -> Result<Option<Self::Clause>, Error>;
+
     fn encode_conj_clause(
         &mut self,
         op: ConjunctionOp,
@@ -98,6 +185,7 @@ pub enum CompareOp {
     Lt,
     Lte,
     Like,
+    Prefix,
 }
 
 impl CompareOp {
@@ -110,6 +198,8 @@ impl CompareOp {
             Self::Lt => "<",
             Self::Lte => "<=",
             Self::Like => "LIKE",
+            // compared against a substring of the value of the same length
+            Self::Prefix => "=",
         }
     }
 
@@ -172,10 +262,14 @@ where
         TagQuery::Like(tag_name, target_value) => {
             encode_tag_op(CompareOp::Like, tag_name, target_value, enc, negate)
         }
+        TagQuery::Prefix(tag_name, target_value) => {
+            encode_tag_op(CompareOp::Prefix, tag_name, target_value, enc, negate)
+        }
         TagQuery::In(tag_name, target_values) => {
             encode_tag_in(tag_name, target_values, enc, negate)
         }
         TagQuery::Exist(tag_names) => encode_tag_exist(tag_names, enc, negate),
+        TagQuery::Size(tag_name, size) => encode_tag_size(tag_name, *size, enc, negate),
         TagQuery::And(subqueries) => encode_tag_conj(ConjunctionOp::And, subqueries, enc, negate),
         TagQuery::Or(subqueries) => encode_tag_conj(ConjunctionOp::Or, subqueries, enc, negate),
         TagQuery::Not(subquery) => encode_tag_query(subquery, enc, !negate),
@@ -192,6 +286,9 @@ fn encode_tag_op<V, E>(
 where
     E: TagQueryEncoder<Clause = V>,
 {
+    if let TagName::Field(field) = name {
+        return enc.encode_field_clause(op, *field, value, negate);
+    }
     let is_plaintext = matches!(name, TagName::Plaintext(_));
     let enc_name = enc.encode_name(name)?;
     let enc_value = enc.encode_value(value, is_plaintext)?;
@@ -241,6 +338,25 @@ where
     }
 }
 
+fn encode_tag_size<V, E>(
+    name: &TagName,
+    size: usize,
+    enc: &mut E,
+    negate: bool,
+) -> Result<Option<V>, Error>
+where
+    E: TagQueryEncoder<Clause = V>,
+{
+    let is_plaintext = matches!(name, TagName::Plaintext(_));
+    let enc_name = enc.encode_name(name)?;
+    if size == 0 {
+        // records without any values for the tag
+        enc.encode_exist_clause(enc_name, is_plaintext, !negate)
+    } else {
+        enc.encode_size_clause(enc_name, size, is_plaintext, negate)
+    }
+}
+
 fn encode_tag_conj<V, E>(
     op: ConjunctionOp,
     subqueries: &[TagQuery],
@@ -304,6 +420,17 @@ mod tests {
             Ok(Some(format!("{}({})", op, name)))
         }
 
+        fn encode_size_clause(
+            &mut self,
+            name: Self::Arg,
+            size: usize,
+            _is_plaintext: bool,
+            negate: bool,
+        ) -> Result<Option<Self::Clause>, Error> {
+            let op = if negate { "!=" } else { "=" };
+            Ok(Some(format!("SIZE({}) {} {}", name, op, size)))
+        }
+
         fn encode_in_clause(
             &mut self,
             name: Self::Arg,
@@ -355,6 +482,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ordered_comparison_encrypted() {
+        let query = Query::Not(Box::new(Query::And(vec![
+            Query::Gte("enctag".to_string(), "1".to_string()),
+            Query::Lte("enctag".to_string(), "2".to_string()),
+        ])));
+        let err = tag_query(query).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Input);
+
+        let query = Query::Lt("~plaintag".to_string(), "2".to_string());
+        assert!(tag_query(query).is_ok());
+
+        let query = Query::Like("enctag".to_string(), "val%".to_string());
+        assert!(tag_query(query).is_ok());
+
+        let query = Query::Prefix("enctag".to_string(), "val".to_string());
+        let err = tag_query(query).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Input);
+    }
+
     #[test]
     fn test_serialize() {
         let query = TagQuery::And(vec![
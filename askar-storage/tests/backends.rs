@@ -78,6 +78,61 @@ macro_rules! backend_tests {
             $run(super::utils::db_scan)
         }
 
+        #[test]
+        fn fetch_all_ordered() {
+            $run(super::utils::db_fetch_all_ordered)
+        }
+
+        #[test]
+        fn scan_cursor() {
+            $run(super::utils::db_scan_cursor)
+        }
+
+        #[test]
+        fn scan_cursor_tag_order() {
+            $run(super::utils::db_scan_cursor_tag_order)
+        }
+
+        #[test]
+        fn count_by_category() {
+            $run(super::utils::db_count_by_category)
+        }
+
+        #[test]
+        fn update_many() {
+            $run(super::utils::db_update_many)
+        }
+
+        #[test]
+        fn upsert() {
+            $run(super::utils::db_upsert)
+        }
+
+        #[test]
+        fn fetch_many() {
+            $run(super::utils::db_fetch_many)
+        }
+
+        #[test]
+        fn list_tag_values() {
+            $run(super::utils::db_list_tag_values)
+        }
+
+        #[test]
+        fn tag_range() {
+            $run(super::utils::db_tag_range)
+        }
+
+        #[test]
+        fn tag_prefix() {
+            $run(super::utils::db_tag_prefix)
+        }
+
+        #[test]
+        fn tag_exists_size() {
+            $run(super::utils::db_tag_exists_size)
+        }
+
         #[test]
         fn remove_all() {
             $run(super::utils::db_remove_all)
@@ -129,9 +184,19 @@ mod sqlite {
     use askar_storage::any::{into_any_backend, AnyBackend};
     use askar_storage::backend::copy_store;
     use askar_storage::backend::sqlite::SqliteStoreOptions;
+    use askar_storage::entry::{EntryKind, EntryOperation};
     use askar_storage::future::block_on;
-    use askar_storage::{generate_raw_store_key, Backend, ManageBackend, StoreKeyMethod};
-    use std::{future::Future, path::Path};
+    use askar_storage::{
+        crypto::buffer::SecretBytes, generate_raw_store_key, Backend, BackendSession, Error,
+        ErrorKind, ManageBackend, PassKey, StoreKeyMethod, WrapKeyProvider,
+    };
+    use std::{
+        collections::HashMap,
+        future::Future,
+        panic::AssertUnwindSafe,
+        path::Path,
+        sync::{Arc, Mutex},
+    };
 
     use super::*;
 
@@ -224,6 +289,303 @@ mod sqlite {
         })
     }
 
+    #[test]
+    fn rekey_db_resume() {
+        log_init();
+        let fname = format!("sqlite-rekey-resume-{}.db", uuid::Uuid::new_v4());
+        let key2 = generate_raw_store_key(None).expect("Error creating raw key");
+
+        let check_profiles = |store: AnyBackend| async move {
+            for name in ["profile0", "profile39"] {
+                let mut session = store
+                    .session(Some(name.to_string()), false)
+                    .expect("Error starting session");
+                assert!(session
+                    .fetch(EntryKind::Item, "category", "name", false)
+                    .await
+                    .expect("Error fetching row")
+                    .is_some());
+            }
+            store.close().await.expect(ERR_CLOSE);
+        };
+
+        block_on(async {
+            let store = SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .provision_backend(StoreKeyMethod::Unprotected, PassKey::empty(), None, false)
+                .await
+                .expect("Error provisioning sqlite store");
+            for idx in 0..40 {
+                let name = store
+                    .create_profile(Some(format!("profile{}", idx)))
+                    .await
+                    .expect("Error creating profile");
+                let mut session = store
+                    .session(Some(name), false)
+                    .expect("Error starting session");
+                session
+                    .update(
+                        EntryKind::Item,
+                        EntryOperation::Insert,
+                        "category",
+                        "name",
+                        Some(b"value"),
+                        None,
+                        None,
+                    )
+                    .await
+                    .expect("Error inserting row");
+            }
+            store.close().await.expect(ERR_CLOSE);
+        });
+
+        // the profile keys of an unprotected store are encrypted again using a new
+        // master key, interrupt the rekey after the first batch of profiles
+        let interrupted = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            block_on(async {
+                let mut store = SqliteStoreOptions::new(fname.as_str())
+                    .expect("Error initializing sqlite store options")
+                    .open_backend(Some(StoreKeyMethod::Unprotected), PassKey::empty(), None)
+                    .await
+                    .expect("Error opening sqlite store");
+                store
+                    .rekey_with_progress(
+                        StoreKeyMethod::RawKey,
+                        key2.as_ref(),
+                        Some(Arc::new(|_, _| panic!("Rekey interrupted"))),
+                    )
+                    .await
+            })
+        }));
+        assert!(interrupted.is_err());
+
+        block_on(async {
+            // profiles are accessible without a key until the rekey is complete
+            let store = SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .open_backend(Some(StoreKeyMethod::Unprotected), PassKey::empty(), None)
+                .await
+                .expect("Error opening sqlite store");
+            check_profiles(into_any_backend(store)).await;
+
+            let mut store = SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .open_backend(Some(StoreKeyMethod::Unprotected), PassKey::empty(), None)
+                .await
+                .expect("Error opening sqlite store");
+            let other_key = generate_raw_store_key(None).expect("Error creating raw key");
+            store
+                .rekey(StoreKeyMethod::RawKey, other_key.as_ref())
+                .await
+                .expect_err("Expected store key mismatch");
+            let reports = Arc::new(Mutex::new(Vec::new()));
+            store
+                .rekey_with_progress(
+                    StoreKeyMethod::RawKey,
+                    key2.as_ref(),
+                    Some(Arc::new({
+                        let reports = reports.clone();
+                        move |processed, total| reports.lock().unwrap().push((processed, total))
+                    })),
+                )
+                .await
+                .expect("Error resuming rekey");
+            assert_eq!(*reports.lock().unwrap(), [(41, 41)]);
+            store.close().await.expect(ERR_CLOSE);
+
+            let store = SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .open_backend(Some(StoreKeyMethod::RawKey), key2.as_ref(), None)
+                .await
+                .expect("Error opening rekeyed store");
+            check_profiles(into_any_backend(store)).await;
+
+            SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .remove_backend()
+                .await
+                .expect("Error removing sqlite store");
+        })
+    }
+
+    #[test]
+    fn key_slots() {
+        log_init();
+        let fname = format!("sqlite-key-slots-{}.db", uuid::Uuid::new_v4());
+        let key1 = generate_raw_store_key(None).expect("Error creating raw key");
+        let key2 = generate_raw_store_key(None).expect("Error creating raw key");
+        let key3 = generate_raw_store_key(None).expect("Error creating raw key");
+
+        block_on(async move {
+            let open = |key: PassKey<'static>| {
+                let fname = fname.clone();
+                async move {
+                    SqliteStoreOptions::new(fname.as_str())
+                        .expect("Error initializing sqlite store options")
+                        .open_backend(Some(StoreKeyMethod::RawKey), key, None)
+                        .await
+                }
+            };
+
+            let store = SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .provision_backend(StoreKeyMethod::RawKey, key1.as_ref(), None, false)
+                .await
+                .expect("Error provisioning sqlite store");
+            store
+                .add_key_slot(
+                    "recovery".to_string(),
+                    StoreKeyMethod::RawKey,
+                    key2.as_ref(),
+                )
+                .await
+                .expect("Error adding key slot");
+            let err = store
+                .add_key_slot(
+                    "recovery".to_string(),
+                    StoreKeyMethod::RawKey,
+                    key3.as_ref(),
+                )
+                .await
+                .expect_err("Expected duplicate key slot error");
+            assert_eq!(err.kind(), ErrorKind::Duplicate);
+            assert_eq!(
+                store
+                    .list_key_slots()
+                    .await
+                    .expect("Error listing key slots"),
+                ["recovery"]
+            );
+            store.close().await.expect(ERR_CLOSE);
+
+            for key in [&key1, &key2] {
+                open(key.clone())
+                    .await
+                    .expect("Error opening store")
+                    .close()
+                    .await
+                    .expect(ERR_CLOSE);
+            }
+            open(key3.clone())
+                .await
+                .expect_err("Expected error opening store with an unknown key");
+
+            // rekeying replaces the primary key, leaving the key slot unchanged
+            let mut store = open(key2.clone()).await.expect("Error opening store");
+            store
+                .rekey(StoreKeyMethod::RawKey, key3.as_ref())
+                .await
+                .expect("Error rekeying store");
+            store.close().await.expect(ERR_CLOSE);
+            for key in [&key2, &key3] {
+                open(key.clone())
+                    .await
+                    .expect("Error opening store")
+                    .close()
+                    .await
+                    .expect(ERR_CLOSE);
+            }
+            open(key1.clone())
+                .await
+                .expect_err("Expected error opening store with a replaced key");
+
+            let store = open(key2.clone()).await.expect("Error opening store");
+            assert!(store
+                .remove_key_slot("recovery".to_string())
+                .await
+                .expect("Error removing key slot"));
+            assert!(!store
+                .remove_key_slot("recovery".to_string())
+                .await
+                .expect("Error removing key slot"));
+            store.close().await.expect(ERR_CLOSE);
+            open(key2.clone())
+                .await
+                .expect_err("Expected error opening store with a removed key slot");
+
+            SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .remove_backend()
+                .await
+                .expect("Error removing sqlite store");
+        })
+    }
+
+    /// A wrap key provider holding keys in memory, in the manner of a keychain
+    #[derive(Default)]
+    struct TestKeychain(Mutex<HashMap<Vec<u8>, Vec<u8>>>);
+
+    impl WrapKeyProvider for TestKeychain {
+        fn id(&self) -> &str {
+            "test-keychain"
+        }
+
+        fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>, Error> {
+            let handle = uuid::Uuid::new_v4().as_bytes().to_vec();
+            self.0.lock().unwrap().insert(handle.clone(), key.to_vec());
+            Ok(handle)
+        }
+
+        fn unwrap_key(&self, wrapped: &[u8]) -> Result<SecretBytes, Error> {
+            self.0
+                .lock()
+                .unwrap()
+                .get(wrapped)
+                .map(|key| SecretBytes::from_slice(key))
+                .ok_or_else(|| Error::from(ErrorKind::NotFound))
+        }
+    }
+
+    #[test]
+    fn wrap_key_provider() {
+        log_init();
+        let fname = format!("sqlite-provider-{}.db", uuid::Uuid::new_v4());
+        let method = StoreKeyMethod::Provider(Arc::new(TestKeychain::default()));
+
+        block_on(async move {
+            let open = |method: Option<StoreKeyMethod>| {
+                let fname = fname.clone();
+                async move {
+                    SqliteStoreOptions::new(fname.as_str())
+                        .expect("Error initializing sqlite store options")
+                        .open_backend(method, PassKey::empty(), None)
+                        .await
+                }
+            };
+
+            let store = SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .provision_backend(method.clone(), PassKey::empty(), None, false)
+                .await
+                .expect("Error provisioning sqlite store");
+            store.close().await.expect(ERR_CLOSE);
+
+            open(Some(method.clone()))
+                .await
+                .expect("Error opening store with wrap key provider")
+                .close()
+                .await
+                .expect(ERR_CLOSE);
+            open(None)
+                .await
+                .expect_err("Expected error opening store without wrap key provider");
+            open(Some(StoreKeyMethod::Provider(Arc::new(
+                TestKeychain::default(),
+            ))))
+            .await
+            .expect_err("Expected error opening store with an unknown key handle");
+            open(Some(StoreKeyMethod::RawKey))
+                .await
+                .expect_err("Expected error opening store with a raw key");
+
+            SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .remove_backend()
+                .await
+                .expect("Error removing sqlite store");
+        })
+    }
+
     #[test]
     fn copy_db() {
         log_init();
@@ -302,8 +664,195 @@ mod sqlite {
         });
     }
 
-    #[cfg(feature = "stress_test")]
     #[test]
+    fn upgrade_search_index() {
+        log_init();
+        use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection};
+        let fname = format!("sqlite-upgrade-{}.db", uuid::Uuid::new_v4());
+        let key = generate_raw_store_key(None).expect("Error creating raw key");
+
+        block_on(async move {
+            let store = SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .provision_backend(StoreKeyMethod::RawKey, key.as_ref(), None, true)
+                .await
+                .expect("Error provisioning sqlite store");
+            store.close().await.expect("Error closing sqlite store");
+
+            // revert to the store version preceding the search index
+            let mut conn = SqliteConnectOptions::new()
+                .filename(&fname)
+                .connect()
+                .await
+                .expect("Error connecting to sqlite store");
+            let version: String =
+                sqlx::query_scalar("SELECT value FROM config WHERE name = 'version'")
+                    .fetch_one(&mut conn)
+                    .await
+                    .expect("Error fetching store version");
+            let version = version.parse::<usize>().expect("Invalid store version");
+            sqlx::query("DROP TABLE items_search_terms")
+                .execute(&mut conn)
+                .await
+                .expect("Error removing search terms");
+            sqlx::query("DROP TABLE items_search")
+                .execute(&mut conn)
+                .await
+                .expect("Error removing search index");
+            sqlx::query("UPDATE config SET value = ?1 WHERE name = 'version'")
+                .bind((version - 2).to_string())
+                .execute(&mut conn)
+                .await
+                .expect("Error updating store version");
+            conn.close().await.expect("Error closing connection");
+
+            let store = SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .open_backend(Some(StoreKeyMethod::RawKey), key.as_ref(), None)
+                .await
+                .expect("Error opening sqlite store");
+            let terms = ["term".to_string()];
+            let mut session = store.session(None, false).expect("Error starting session");
+            session
+                .update(
+                    EntryKind::Item,
+                    EntryOperation::Insert,
+                    "category",
+                    "name",
+                    Some(b"value"),
+                    None,
+                    None,
+                )
+                .await
+                .expect("Error inserting test row");
+            session
+                .update_search_terms(EntryKind::Item, "category", "name", &terms)
+                .await
+                .expect("Error updating search terms");
+            let rows = session
+                .search_values(EntryKind::Item, "category", &terms, None)
+                .await
+                .expect("Error searching values");
+            assert_eq!(rows.len(), 1);
+            drop(session);
+
+            // records indexed without retaining their search terms cannot be rekeyed
+            let mut conn = SqliteConnectOptions::new()
+                .filename(&fname)
+                .connect()
+                .await
+                .expect("Error connecting to sqlite store");
+            sqlx::query("DELETE FROM items_search_terms")
+                .execute(&mut conn)
+                .await
+                .expect("Error removing search terms");
+            conn.close().await.expect("Error closing connection");
+            let err = store
+                .rekey_profile(store.get_active_profile(), None)
+                .await
+                .expect_err("Expected error rekeying profile");
+            assert_eq!(err.kind(), ErrorKind::Unsupported);
+            store.close().await.expect("Error closing sqlite store");
+
+            SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .remove_backend()
+                .await
+                .expect("Error removing sqlite store");
+        });
+    }
+
+    #[test]
+    fn audit_log_tamper() {
+        log_init();
+        use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection};
+        let fname = format!("sqlite-audit-{}.db", uuid::Uuid::new_v4());
+        let key = generate_raw_store_key(None).expect("Error creating raw key");
+
+        block_on(async move {
+            let store = SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .provision_backend(StoreKeyMethod::RawKey, key.as_ref(), None, true)
+                .await
+                .expect("Error provisioning sqlite store");
+            store
+                .set_audit_log(true)
+                .await
+                .expect("Error enabling audit log");
+            let mut session = store.session(None, false).expect("Error starting session");
+            for name in ["first", "second"] {
+                session
+                    .update(
+                        EntryKind::Item,
+                        EntryOperation::Insert,
+                        "category",
+                        name,
+                        Some(b"value"),
+                        None,
+                        None,
+                    )
+                    .await
+                    .expect("Error inserting test row");
+            }
+            let records = session
+                .fetch_audit_log(None, None)
+                .await
+                .expect("Error fetching audit log");
+            assert_eq!(records.len(), 2);
+            drop(session);
+            store.close().await.expect("Error closing sqlite store");
+
+            let mut conn = SqliteConnectOptions::new()
+                .filename(&fname)
+                .connect()
+                .await
+                .expect("Error connecting to sqlite store");
+            sqlx::query("DELETE FROM audit_log")
+                .execute(&mut conn)
+                .await
+                .expect_err("Audit log records should not be removable");
+            // bypass the trigger and alter the first record
+            sqlx::query("DROP TRIGGER audit_log_no_update")
+                .execute(&mut conn)
+                .await
+                .expect("Error removing trigger");
+            sqlx::query("UPDATE audit_log SET operation = 'replace' WHERE id = ?1")
+                .bind(records[0].id)
+                .execute(&mut conn)
+                .await
+                .expect("Error updating audit log");
+            conn.close().await.expect("Error closing connection");
+
+            let store = SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .open_backend(Some(StoreKeyMethod::RawKey), key.as_ref(), None)
+                .await
+                .expect("Error opening sqlite store");
+            let mut session = store.session(None, false).expect("Error starting session");
+            let err = session
+                .fetch_audit_log(None, None)
+                .await
+                .expect_err("Altered audit log should fail verification");
+            assert_eq!(err.kind(), ErrorKind::Encryption);
+            // later records are verified against the stored code of the altered record
+            let after = session
+                .fetch_audit_log(Some(records[0].id), None)
+                .await
+                .expect("Error fetching audit log");
+            assert_eq!(after, records[1..]);
+            drop(session);
+            store.close().await.expect("Error closing sqlite store");
+
+            SqliteStoreOptions::new(fname.as_str())
+                .expect("Error initializing sqlite store options")
+                .remove_backend()
+                .await
+                .expect("Error removing sqlite store");
+        });
+    }
+
+    #[test]
+    #[ignore = "long-running stress test, run with --ignored"]
     fn stress_test() {
         log_init();
         use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
@@ -326,11 +875,11 @@ mod sqlite {
 
             let mut conn = pool.begin().await.unwrap();
             sqlx::query("CREATE TABLE test (name TEXT)")
-                .execute(&mut conn)
+                .execute(&mut *conn)
                 .await
                 .unwrap();
             sqlx::query("INSERT INTO test (name) VALUES ('test')")
-                .execute(&mut conn)
+                .execute(&mut *conn)
                 .await
                 .unwrap();
             conn.commit().await.unwrap();
@@ -343,15 +892,15 @@ mod sqlite {
                 for _ in 0..COUNT {
                     let mut txn = pool.acquire().await.expect("Acquire error");
                     sqlx::query("BEGIN IMMEDIATE")
-                        .execute(&mut txn)
+                        .execute(&mut *txn)
                         .await
                         .expect("Transaction error");
                     let _ = sqlx::query("SELECT * FROM test")
-                        .fetch_one(&mut txn)
+                        .fetch_one(&mut *txn)
                         .await
                         .expect("Error fetching row");
                     sqlx::query("COMMIT")
-                        .execute(&mut txn)
+                        .execute(&mut *txn)
                         .await
                         .expect("Commit error");
                 }
@@ -392,6 +941,66 @@ mod sqlite {
 
     backend_tests!(with_sqlite_in_memory);
 
+    #[test]
+    fn search_values() {
+        with_sqlite_in_memory(super::utils::db_search_values)
+    }
+
+    #[test]
+    fn update_versioned() {
+        with_sqlite_in_memory(super::utils::db_update_versioned)
+    }
+
+    #[test]
+    fn remove_expired() {
+        with_sqlite_in_memory(super::utils::db_remove_expired)
+    }
+
+    #[test]
+    fn soft_delete() {
+        with_sqlite_in_memory(super::utils::db_soft_delete)
+    }
+
+    #[test]
+    fn record_history() {
+        with_sqlite_in_memory(super::utils::db_record_history)
+    }
+
+    #[test]
+    fn audit_log() {
+        with_sqlite_in_memory(super::utils::db_audit_log)
+    }
+
+    #[test]
+    fn subscribe() {
+        with_sqlite_in_memory(super::utils::db_subscribe)
+    }
+
+    #[test]
+    fn timestamps() {
+        with_sqlite_in_memory(super::utils::db_timestamps)
+    }
+
+    #[test]
+    fn meta() {
+        with_sqlite_in_memory(super::utils::db_meta)
+    }
+
+    #[test]
+    fn list_profile_details() {
+        with_sqlite_in_memory(super::utils::db_list_profile_details)
+    }
+
+    #[test]
+    fn rekey_profile() {
+        with_sqlite_in_memory(super::utils::db_rekey_profile)
+    }
+
+    #[test]
+    fn copy_profile() {
+        with_sqlite_in_memory(super::utils::db_copy_profile)
+    }
+
     #[test]
     fn provision_from_str() {
         let key = generate_raw_store_key(None).expect("Error creating raw key");
@@ -414,6 +1023,164 @@ mod sqlite {
     }
 }
 
+#[cfg(feature = "redb")]
+mod redb {
+    use askar_storage::any::{into_any_backend, AnyBackend};
+    use askar_storage::backend::redb::RedbStoreOptions;
+    use askar_storage::future::block_on;
+    use askar_storage::{generate_raw_store_key, Backend, ManageBackend, StoreKeyMethod};
+    use std::{future::Future, path::Path};
+
+    use super::*;
+
+    #[test]
+    fn create_remove_db() {
+        log_init();
+        let fname = format!("redb-test-{}.redb", uuid::Uuid::new_v4());
+        assert_eq!(
+            Path::new(&fname).exists(),
+            false,
+            "Oops, should be a unique filename"
+        );
+
+        let key = generate_raw_store_key(None).expect("Error creating raw key");
+        block_on(async move {
+            assert_eq!(
+                RedbStoreOptions::new(fname.as_str())
+                    .expect("Error initializing redb store options")
+                    .remove_backend()
+                    .await
+                    .expect("Error removing redb store"),
+                false
+            );
+
+            let store = RedbStoreOptions::new(fname.as_str())
+                .expect("Error initializing redb store options")
+                .provision_backend(StoreKeyMethod::RawKey, key.as_ref(), None, false)
+                .await
+                .expect("Error provisioning redb store");
+            assert_eq!(Path::new(&fname).exists(), true);
+            store.close().await.expect("Error closing redb store");
+            drop(store);
+
+            let store = RedbStoreOptions::new(fname.as_str())
+                .expect("Error initializing redb store options")
+                .open_backend(Some(StoreKeyMethod::RawKey), key.as_ref(), None)
+                .await
+                .expect("Error opening redb store");
+            store.close().await.expect("Error closing redb store");
+            drop(store);
+
+            assert_eq!(
+                RedbStoreOptions::new(fname.as_str())
+                    .expect("Error initializing redb store options")
+                    .remove_backend()
+                    .await
+                    .expect("Error removing redb store"),
+                true
+            );
+            assert_eq!(Path::new(&fname).exists(), false);
+        })
+    }
+
+    fn with_redb_in_memory<F, G>(f: F)
+    where
+        F: FnOnce(AnyBackend) -> G,
+        G: Future<Output = ()>,
+    {
+        log_init();
+        let key = generate_raw_store_key(None).expect("Error generating store key");
+        block_on(async move {
+            let db = into_any_backend(
+                RedbStoreOptions::in_memory()
+                    .provision(StoreKeyMethod::RawKey, key, None, false)
+                    .await
+                    .expect("Error provisioning redb store"),
+            );
+            f(db.clone()).await;
+            db.close().await.expect(ERR_CLOSE);
+        })
+    }
+
+    backend_tests!(with_redb_in_memory);
+}
+
+#[cfg(all(feature = "blob", feature = "sqlite"))]
+mod blob {
+    use askar_storage::any::{into_any_backend, AnyBackend};
+    use askar_storage::backend::blob::{object_store::memory::InMemory, BlobBackend};
+    use askar_storage::backend::sqlite::SqliteStoreOptions;
+    use askar_storage::future::block_on;
+    use askar_storage::{generate_raw_store_key, Backend, StoreKeyMethod};
+    use std::{future::Future, sync::Arc};
+
+    use super::*;
+
+    fn with_blob_sqlite_in_memory<F, G>(f: F)
+    where
+        F: FnOnce(AnyBackend) -> G,
+        G: Future<Output = ()>,
+    {
+        log_init();
+        let key = generate_raw_store_key(None).expect("Error generating store key");
+        block_on(async move {
+            let inner = SqliteStoreOptions::in_memory()
+                .provision(StoreKeyMethod::RawKey, key, None, false)
+                .await
+                .expect("Error provisioning sqlite store");
+            // offload all but the smallest values
+            let db = into_any_backend(BlobBackend::new(
+                inner,
+                Arc::new(InMemory::new()),
+                "askar",
+                4,
+            ));
+            f(db.clone()).await;
+            db.close().await.expect(ERR_CLOSE);
+        })
+    }
+
+    backend_tests!(with_blob_sqlite_in_memory);
+}
+
+#[cfg(all(feature = "remote", feature = "sqlite"))]
+mod remote {
+    use askar_storage::any::{into_any_backend, AnyBackend};
+    use askar_storage::backend::remote::{RemoteBackend, RemoteService};
+    use askar_storage::backend::sqlite::SqliteStoreOptions;
+    use askar_storage::future::block_on;
+    use askar_storage::{generate_raw_store_key, Backend, StoreKeyMethod};
+    use std::future::Future;
+
+    use super::*;
+
+    fn with_remote_sqlite_in_memory<F, G>(f: F)
+    where
+        F: FnOnce(AnyBackend) -> G,
+        G: Future<Output = ()>,
+    {
+        log_init();
+        let key = generate_raw_store_key(None).expect("Error generating store key");
+        block_on(async move {
+            let service = RemoteService::new(
+                SqliteStoreOptions::in_memory()
+                    .provision(StoreKeyMethod::RawKey, key, None, false)
+                    .await
+                    .expect("Error provisioning sqlite store"),
+            );
+            let db = into_any_backend(
+                RemoteBackend::connect(service, None)
+                    .await
+                    .expect("Error connecting to remote store"),
+            );
+            f(db.clone()).await;
+            db.close().await.expect(ERR_CLOSE);
+        })
+    }
+
+    backend_tests!(with_remote_sqlite_in_memory);
+}
+
 #[cfg(feature = "pg_test")]
 mod postgres {
     use askar_storage::any::AnyBackend;
@@ -443,4 +1210,64 @@ mod postgres {
     }
 
     backend_tests!(with_postgres);
+
+    #[test]
+    fn search_values() {
+        with_postgres(super::utils::db_search_values)
+    }
+
+    #[test]
+    fn update_versioned() {
+        with_postgres(super::utils::db_update_versioned)
+    }
+
+    #[test]
+    fn remove_expired() {
+        with_postgres(super::utils::db_remove_expired)
+    }
+
+    #[test]
+    fn soft_delete() {
+        with_postgres(super::utils::db_soft_delete)
+    }
+
+    #[test]
+    fn record_history() {
+        with_postgres(super::utils::db_record_history)
+    }
+
+    #[test]
+    fn audit_log() {
+        with_postgres(super::utils::db_audit_log)
+    }
+
+    #[test]
+    fn subscribe() {
+        with_postgres(super::utils::db_subscribe)
+    }
+
+    #[test]
+    fn timestamps() {
+        with_postgres(super::utils::db_timestamps)
+    }
+
+    #[test]
+    fn meta() {
+        with_postgres(super::utils::db_meta)
+    }
+
+    #[test]
+    fn list_profile_details() {
+        with_postgres(super::utils::db_list_profile_details)
+    }
+
+    #[test]
+    fn rekey_profile() {
+        with_postgres(super::utils::db_rekey_profile)
+    }
+
+    #[test]
+    fn copy_profile() {
+        with_postgres(super::utils::db_copy_profile)
+    }
 }
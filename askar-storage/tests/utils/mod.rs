@@ -1,9 +1,20 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use askar_storage::{
     any::AnyBackend,
-    entry::{Entry, EntryKind, EntryOperation, EntryTag, TagFilter},
+    backend::{copy_profile, resume_scan, scan_with_cursor, OrderBy},
+    crypto::buffer::SecretBytes,
+    entry::{
+        sortable_int, ChangeOperation, Entry, EntryKind, EntryOperation, EntryTag, EntryUpdate,
+        ScanCursor, TagFilter,
+    },
     Backend, BackendSession, ErrorKind,
 };
 
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use tokio::task::spawn;
 
 const ERR_PROFILE: &str = "Error creating profile";
@@ -17,6 +28,7 @@ const ERR_REQ_ROW: &str = "Expected row";
 const ERR_REQ_ERR: &str = "Expected error";
 const ERR_INSERT: &str = "Error inserting test row";
 const ERR_REPLACE: &str = "Error replacing test row";
+const ERR_REMOVE: &str = "Error removing test row";
 const ERR_REMOVE_ALL: &str = "Error removing test rows";
 const ERR_SCAN: &str = "Error starting scan";
 const ERR_SCAN_NEXT: &str = "Error fetching scan rows";
@@ -245,7 +257,7 @@ pub async fn db_replace_missing(db: AnyBackend) {
 
 pub async fn db_count(db: AnyBackend) {
     let category = "category".to_string();
-    let test_rows = vec![Entry::new(
+    let test_rows = [Entry::new(
         EntryKind::Item,
         &category,
         "name",
@@ -271,14 +283,14 @@ pub async fn db_count(db: AnyBackend) {
 
     let tag_filter = None;
     let count = conn
-        .count(Some(EntryKind::Item), Some(&category), tag_filter)
+        .count(Some(EntryKind::Item), Some(&category), tag_filter, false)
         .await
         .expect(ERR_COUNT);
     assert_eq!(count, 1);
 
     let tag_filter = Some(TagFilter::is_eq("sometag", "someval"));
     let count = conn
-        .count(Some(EntryKind::Item), Some(&category), tag_filter)
+        .count(Some(EntryKind::Item), Some(&category), tag_filter, false)
         .await
         .expect(ERR_COUNT);
     assert_eq!(count, 0);
@@ -311,14 +323,14 @@ pub async fn db_count_exist(db: AnyBackend) {
     .expect(ERR_INSERT);
 
     assert_eq!(
-        conn.count(Some(EntryKind::Item), Some(&test_row.category), None)
+        conn.count(Some(EntryKind::Item), Some(&test_row.category), None, false)
             .await
             .expect(ERR_COUNT),
         1
     );
 
     assert_eq!(
-        conn.count(Some(EntryKind::Kms), Some(&test_row.category), None)
+        conn.count(Some(EntryKind::Kms), Some(&test_row.category), None, false)
             .await
             .expect(ERR_COUNT),
         0
@@ -328,7 +340,8 @@ pub async fn db_count_exist(db: AnyBackend) {
         conn.count(
             Some(EntryKind::Item),
             Some(&test_row.category),
-            Some(TagFilter::exist(vec!["enc".to_string()]))
+            Some(TagFilter::exist(vec!["enc".to_string()])),
+            false
         )
         .await
         .expect(ERR_COUNT),
@@ -339,7 +352,8 @@ pub async fn db_count_exist(db: AnyBackend) {
         conn.count(
             Some(EntryKind::Item),
             Some(&test_row.category),
-            Some(TagFilter::exist(vec!["~plain".to_string()]))
+            Some(TagFilter::exist(vec!["~plain".to_string()])),
+            false
         )
         .await
         .expect(ERR_COUNT),
@@ -350,7 +364,8 @@ pub async fn db_count_exist(db: AnyBackend) {
         conn.count(
             Some(EntryKind::Item),
             Some(&test_row.category),
-            Some(TagFilter::exist(vec!["~enc".to_string()]))
+            Some(TagFilter::exist(vec!["~enc".to_string()])),
+            false
         )
         .await
         .expect(ERR_COUNT),
@@ -361,7 +376,8 @@ pub async fn db_count_exist(db: AnyBackend) {
         conn.count(
             Some(EntryKind::Item),
             Some(&test_row.category),
-            Some(TagFilter::exist(vec!["plain".to_string()]))
+            Some(TagFilter::exist(vec!["plain".to_string()])),
+            false
         )
         .await
         .expect(ERR_COUNT),
@@ -372,7 +388,8 @@ pub async fn db_count_exist(db: AnyBackend) {
         conn.count(
             Some(EntryKind::Item),
             Some(&test_row.category),
-            Some(TagFilter::exist(vec!["other".to_string()]))
+            Some(TagFilter::exist(vec!["other".to_string()])),
+            false
         )
         .await
         .expect(ERR_COUNT),
@@ -386,7 +403,8 @@ pub async fn db_count_exist(db: AnyBackend) {
             Some(TagFilter::exist(vec![
                 "enc".to_string(),
                 "other".to_string()
-            ]))
+            ])),
+            false
         )
         .await
         .expect(ERR_COUNT),
@@ -400,7 +418,8 @@ pub async fn db_count_exist(db: AnyBackend) {
             Some(TagFilter::all_of(vec![
                 TagFilter::exist(vec!["enc".to_string()]),
                 TagFilter::exist(vec!["~plain".to_string()])
-            ]))
+            ])),
+            false
         )
         .await
         .expect(ERR_COUNT),
@@ -414,7 +433,8 @@ pub async fn db_count_exist(db: AnyBackend) {
             Some(TagFilter::any_of(vec![
                 TagFilter::exist(vec!["~enc".to_string()]),
                 TagFilter::exist(vec!["~plain".to_string()])
-            ]))
+            ])),
+            false
         )
         .await
         .expect(ERR_COUNT),
@@ -428,7 +448,8 @@ pub async fn db_count_exist(db: AnyBackend) {
             Some(TagFilter::all_of(vec![
                 TagFilter::exist(vec!["~enc".to_string()]),
                 TagFilter::exist(vec!["~plain".to_string()])
-            ]))
+            ])),
+            false
         )
         .await
         .expect(ERR_COUNT),
@@ -442,7 +463,8 @@ pub async fn db_count_exist(db: AnyBackend) {
             Some(TagFilter::negate(TagFilter::exist(vec![
                 "enc".to_string(),
                 "other".to_string()
-            ]),))
+            ]),)),
+            false
         )
         .await
         .expect(ERR_COUNT),
@@ -493,6 +515,7 @@ pub async fn db_scan(db: AnyBackend) {
             limit,
             None,
             false,
+            false,
         )
         .await
         .expect(ERR_SCAN);
@@ -512,6 +535,7 @@ pub async fn db_scan(db: AnyBackend) {
             limit,
             None,
             false,
+            false,
         )
         .await
         .expect(ERR_SCAN);
@@ -519,408 +543,2235 @@ pub async fn db_scan(db: AnyBackend) {
     assert_eq!(rows, None);
 }
 
-pub async fn db_remove_all(db: AnyBackend) {
-    let test_rows = vec![
-        Entry::new(
-            EntryKind::Item,
-            "category",
-            "item1",
-            "value",
-            vec![
-                EntryTag::Encrypted("t1".to_string(), "del".to_string()),
-                EntryTag::Plaintext("t2".to_string(), "del".to_string()),
-            ],
-        ),
-        Entry::new(
-            EntryKind::Item,
-            "category",
-            "item2",
-            "value",
-            vec![
-                EntryTag::Encrypted("t1".to_string(), "del".to_string()),
-                EntryTag::Plaintext("t2".to_string(), "del".to_string()),
-            ],
-        ),
-        Entry::new(
-            EntryKind::Item,
-            "category",
-            "item3",
-            "value",
-            vec![
-                EntryTag::Encrypted("t1".to_string(), "keep".to_string()),
-                EntryTag::Plaintext("t2".to_string(), "keep".to_string()),
-            ],
-        ),
+pub async fn db_fetch_all_ordered(db: AnyBackend) {
+    let category = "category".to_string();
+    let sort_tag = |value: &str| vec![EntryTag::Plaintext("sort".to_string(), value.to_string())];
+    let test_rows = [
+        Entry::new(EntryKind::Item, &category, "a", "value", sort_tag("2")),
+        Entry::new(EntryKind::Item, &category, "b", "value", sort_tag("1")),
+        Entry::new(EntryKind::Item, &category, "c", "value", Vec::new()),
+        Entry::new(EntryKind::Item, &category, "d", "value", sort_tag("1")),
     ];
 
     let mut conn = db.session(None, false).expect(ERR_SESSION);
 
-    for test_row in test_rows.iter() {
+    for upd in test_rows.iter() {
         conn.update(
             EntryKind::Item,
             EntryOperation::Insert,
-            &test_row.category,
-            &test_row.name,
-            Some(&test_row.value),
-            Some(test_row.tags.as_slice()),
+            &upd.category,
+            &upd.name,
+            Some(&upd.value),
+            Some(upd.tags.as_slice()),
             None,
         )
         .await
         .expect(ERR_INSERT);
     }
 
-    // could detect that a second transaction would block here?
-    // depends on the backend. just checking that no SQL errors occur for now.
-    let removed = conn
-        .remove_all(
+    for (order_by, descending, expected) in [
+        (OrderBy::Id, true, ["d", "c", "b", "a"]),
+        (
+            OrderBy::Tag("sort".to_string()),
+            false,
+            ["c", "b", "d", "a"],
+        ),
+        (OrderBy::Tag("sort".to_string()), true, ["a", "d", "b", "c"]),
+    ] {
+        let rows = conn
+            .fetch_all(
+                Some(EntryKind::Item),
+                Some(category.as_str()),
+                None,
+                None,
+                Some(order_by),
+                descending,
+                false,
+            )
+            .await
+            .expect(ERR_FETCH_ALL);
+        let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, expected);
+    }
+
+    let mut scan = db
+        .scan(
+            None,
             Some(EntryKind::Item),
-            Some("category"),
-            Some(TagFilter::all_of(vec![
-                TagFilter::is_eq("t1", "del"),
-                TagFilter::is_eq("~t2", "del"),
-            ])),
+            Some(category.clone()),
+            None,
+            None,
+            Some(2),
+            Some(OrderBy::Tag("sort".to_string())),
+            true,
+            false,
         )
         .await
-        .expect(ERR_REMOVE_ALL);
-    assert_eq!(removed, 2);
+        .expect(ERR_SCAN);
+    let rows = scan
+        .fetch_next()
+        .await
+        .expect(ERR_SCAN_NEXT)
+        .expect(ERR_REQ_ROW);
+    let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["a", "d"]);
 }
 
-pub async fn db_txn_rollback(db: AnyBackend) {
-    let test_row = Entry::new(EntryKind::Item, "category", "name", "value", Vec::new());
+pub async fn db_scan_cursor(db: AnyBackend) {
+    let category = "category".to_string();
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
 
-    let mut conn = db.session(None, true).expect(ERR_TRANSACTION);
+    for name in ["a", "b", "c", "d", "e"] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            &category,
+            name,
+            Some(b"value"),
+            None,
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
 
-    conn.update(
-        EntryKind::Item,
-        EntryOperation::Insert,
-        &test_row.category,
-        &test_row.name,
-        Some(&test_row.value),
-        Some(test_row.tags.as_slice()),
+    let mut scan = scan_with_cursor(
+        &db,
+        None,
+        Some(EntryKind::Item),
+        Some(category.clone()),
         None,
+        Some(1),
+        Some(2),
+        Some(OrderBy::Id),
+        false,
+        false,
     )
     .await
-    .expect(ERR_INSERT);
-
-    conn.close(false)
+    .expect(ERR_SCAN);
+    let rows = scan
+        .fetch_next()
         .await
-        .expect("Error rolling back transaction");
-
-    let mut conn = db.session(None, false).expect("Error starting new session");
-
-    let row = conn
-        .fetch(EntryKind::Item, &test_row.category, &test_row.name, false)
+        .expect(ERR_SCAN_NEXT)
+        .expect(ERR_REQ_ROW);
+    let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["b", "c"]);
+    let token = scan
+        .cursor()
+        .expect("Expected scan cursor")
+        .to_token(&db)
         .await
-        .expect("Error fetching test row");
-    assert_eq!(row, None);
-}
-
-pub async fn db_txn_drop(db: AnyBackend) {
-    let test_row = Entry::new(EntryKind::Item, "category", "name", "value", Vec::new());
-
-    let mut conn = db
-        .session(None, true)
-        .expect("Error starting new transaction");
-
-    conn.update(
-        EntryKind::Item,
-        EntryOperation::Insert,
-        &test_row.category,
-        &test_row.name,
-        Some(&test_row.value),
-        Some(test_row.tags.as_slice()),
-        None,
-    )
-    .await
-    .expect(ERR_INSERT);
+        .expect("Error encoding scan cursor");
+    drop(scan);
 
+    // removing rows before the cursor position must not affect the resumed scan
+    for name in ["a", "c"] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Remove,
+            &category,
+            name,
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect(ERR_REMOVE);
+    }
     drop(conn);
 
-    let mut conn = db.session(None, false).expect("Error starting new session");
+    // the scan parameters are not readable from the token
+    let raw_token = URL_SAFE_NO_PAD
+        .decode(&token)
+        .expect("Error decoding scan cursor");
+    assert!(!raw_token
+        .windows(category.len())
+        .any(|w| w == category.as_bytes()));
+
+    let mut tampered = token.clone().into_bytes();
+    tampered[0] = if tampered[0] == b'A' { b'B' } else { b'A' };
+    let err = ScanCursor::from_token(&db, std::str::from_utf8(&tampered).unwrap())
+        .await
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::Input);
 
-    let row = conn
-        .fetch(EntryKind::Item, &test_row.category, &test_row.name, false)
+    let cursor = ScanCursor::from_token(&db, &token)
         .await
-        .expect("Error fetching test row");
-    assert_eq!(row, None);
+        .expect("Error parsing scan cursor");
+    let mut scan = resume_scan(&db, None, &cursor, None).await.expect(ERR_SCAN);
+    let rows = scan
+        .fetch_next()
+        .await
+        .expect(ERR_SCAN_NEXT)
+        .expect(ERR_REQ_ROW);
+    let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["d", "e"]);
+    assert_eq!(scan.fetch_next().await.expect(ERR_SCAN_NEXT), None);
+    assert!(scan.cursor().is_none());
 }
 
-// test that session does NOT have transaction rollback behaviour
-pub async fn db_session_drop(db: AnyBackend) {
-    let test_row = Entry::new(EntryKind::Item, "category", "name", "value", Vec::new());
-
+pub async fn db_scan_cursor_tag_order(db: AnyBackend) {
+    let category = "category".to_string();
     let mut conn = db.session(None, false).expect(ERR_SESSION);
 
-    conn.update(
-        EntryKind::Item,
-        EntryOperation::Insert,
-        &test_row.category,
-        &test_row.name,
-        Some(&test_row.value),
-        Some(test_row.tags.as_slice()),
-        None,
-    )
-    .await
-    .expect(ERR_INSERT);
+    for (name, rank) in [
+        ("a", Some("2")),
+        ("b", None),
+        ("c", Some("1")),
+        ("d", Some("2")),
+        ("e", Some("3")),
+    ] {
+        let tags = rank.map(|rank| vec![EntryTag::Plaintext("rank".to_string(), rank.to_string())]);
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            &category,
+            name,
+            Some(b"value"),
+            tags.as_deref(),
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
 
+    for (descending, first, rest) in [
+        (false, ["b", "c", "a"], ["d", "e"]),
+        (true, ["e", "d", "a"], ["c", "b"]),
+    ] {
+        let mut scan = scan_with_cursor(
+            &db,
+            None,
+            Some(EntryKind::Item),
+            Some(category.clone()),
+            None,
+            None,
+            Some(3),
+            Some(OrderBy::Tag("rank".to_string())),
+            descending,
+            false,
+        )
+        .await
+        .expect(ERR_SCAN);
+        let rows = scan
+            .fetch_next()
+            .await
+            .expect(ERR_SCAN_NEXT)
+            .expect(ERR_REQ_ROW);
+        let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, first);
+        let cursor = scan.cursor().expect("Expected scan cursor").clone();
+        drop(scan);
+
+        let mut scan = resume_scan(&db, None, &cursor, None).await.expect(ERR_SCAN);
+        let rows = scan
+            .fetch_next()
+            .await
+            .expect(ERR_SCAN_NEXT)
+            .expect(ERR_REQ_ROW);
+        let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, rest);
+    }
     drop(conn);
+}
 
+pub async fn db_count_by_category(db: AnyBackend) {
     let mut conn = db.session(None, false).expect(ERR_SESSION);
 
-    let row = conn
-        .fetch(EntryKind::Item, &test_row.category, &test_row.name, false)
+    for (category, name, flag) in [("alpha", "a", "1"), ("alpha", "b", "0"), ("beta", "c", "1")] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            category,
+            name,
+            Some(b"value"),
+            Some(&[EntryTag::Encrypted("flag".to_string(), flag.to_string())][..]),
+            None,
+        )
         .await
-        .expect(ERR_FETCH);
-    assert_eq!(row, Some(test_row));
-}
+        .expect(ERR_INSERT);
+    }
 
-pub async fn db_txn_commit(db: AnyBackend) {
-    let test_row = Entry::new(EntryKind::Item, "category", "name", "value", Vec::new());
+    let counts = conn
+        .count_by_category(Some(EntryKind::Item), None)
+        .await
+        .expect(ERR_COUNT);
+    assert_eq!(
+        counts.into_iter().collect::<Vec<_>>(),
+        [("alpha".to_string(), 2), ("beta".to_string(), 1)]
+    );
 
-    let mut conn = db.session(None, true).expect(ERR_TRANSACTION);
+    let counts = conn
+        .count_by_category(Some(EntryKind::Item), Some(TagFilter::is_eq("flag", "0")))
+        .await
+        .expect(ERR_COUNT);
+    assert_eq!(
+        counts.into_iter().collect::<Vec<_>>(),
+        [("alpha".to_string(), 1)]
+    );
+}
 
-    conn.update(
-        EntryKind::Item,
-        EntryOperation::Insert,
-        &test_row.category,
-        &test_row.name,
-        Some(&test_row.value),
-        Some(test_row.tags.as_slice()),
-        None,
-    )
-    .await
-    .expect(ERR_INSERT);
+pub async fn db_update_many(db: AnyBackend) {
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
 
-    conn.close(true).await.expect(ERR_COMMIT);
+    let results = conn
+        .update_many(
+            EntryKind::Item,
+            vec![
+                EntryUpdate::insert("category", "a", "value a", None, None),
+                EntryUpdate::insert(
+                    "category",
+                    "b",
+                    "value b",
+                    Some(vec![EntryTag::Encrypted("t".to_string(), "b".to_string())]),
+                    None,
+                ),
+                EntryUpdate::insert("category", "a", "duplicate", None, None),
+            ],
+        )
+        .await
+        .expect(ERR_INSERT);
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok() && results[1].is_ok());
+    assert_eq!(
+        results[2].as_ref().expect_err(ERR_REQ_ERR).kind(),
+        ErrorKind::Duplicate
+    );
 
-    let mut conn = db.session(None, false).expect(ERR_SESSION);
+    let results = conn
+        .update_many(
+            EntryKind::Item,
+            vec![
+                EntryUpdate::replace("category", "a", "new value a", None, None),
+                EntryUpdate::remove("category", "b"),
+                EntryUpdate::remove("category", "missing"),
+            ],
+        )
+        .await
+        .expect(ERR_REPLACE);
+    assert!(results[0].is_ok() && results[1].is_ok());
+    assert_eq!(
+        results[2].as_ref().expect_err(ERR_REQ_ERR).kind(),
+        ErrorKind::NotFound
+    );
 
     let row = conn
-        .fetch(EntryKind::Item, &test_row.category, &test_row.name, false)
+        .fetch(EntryKind::Item, "category", "a", false)
         .await
-        .expect(ERR_FETCH);
-    assert_eq!(row, Some(test_row));
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    assert_eq!(row.value, b"new value a".to_vec());
+    assert!(conn
+        .fetch(EntryKind::Item, "category", "b", false)
+        .await
+        .expect(ERR_FETCH)
+        .is_none());
 }
 
-pub async fn db_txn_fetch_for_update(db: AnyBackend) {
-    let test_row = Entry::new(EntryKind::Item, "category", "name", "value", Vec::new());
-
-    let mut conn = db.session(None, true).expect(ERR_TRANSACTION);
+pub async fn db_upsert(db: AnyBackend) {
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
 
-    conn.update(
-        EntryKind::Item,
-        EntryOperation::Insert,
-        &test_row.category,
-        &test_row.name,
-        Some(&test_row.value),
-        Some(test_row.tags.as_slice()),
-        None,
-    )
-    .await
-    .expect(ERR_INSERT);
-
-    // could detect that a second transaction would block here?
-    // depends on the backend. just checking that no SQL errors occur for now.
-    let row = conn
-        .fetch(EntryKind::Item, &test_row.category, &test_row.name, true)
+    for (value, tag) in [("first", "old"), ("second", "new")] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::InsertOrReplace,
+            "category",
+            "name",
+            Some(value.as_bytes()),
+            Some(&[EntryTag::Encrypted("t".to_string(), tag.to_string())][..]),
+            None,
+        )
         .await
-        .expect(ERR_FETCH)
-        .expect(ERR_REQ_ROW);
-    assert_eq!(row, test_row);
+        .expect(ERR_REPLACE);
+    }
 
     let rows = conn
         .fetch_all(
             Some(EntryKind::Item),
-            Some(&test_row.category),
+            Some("category"),
+            None,
             None,
-            Some(2),
             None,
             false,
-            true,
+            false,
         )
         .await
         .expect(ERR_FETCH_ALL);
-    assert_eq!(rows.len(), 1);
-    assert_eq!(rows[0], test_row);
+    assert_eq!(
+        rows,
+        vec![Entry::new(
+            EntryKind::Item,
+            "category",
+            "name",
+            "second",
+            vec![EntryTag::Encrypted("t".to_string(), "new".to_string())],
+        )]
+    );
 
-    conn.close(true).await.expect(ERR_COMMIT);
+    let count = conn
+        .count(
+            Some(EntryKind::Item),
+            Some("category"),
+            Some(TagFilter::is_eq("t", "old")),
+            false,
+        )
+        .await
+        .expect(ERR_COUNT);
+    assert_eq!(count, 0);
 }
 
-pub async fn db_txn_contention(db: AnyBackend) {
-    let test_row = Entry::new(
-        EntryKind::Item,
-        "category",
-        "count",
-        "0",
+pub async fn db_fetch_many(db: AnyBackend) {
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    for name in ["a", "b", "c"] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            "category",
+            name,
+            Some(name.as_bytes()),
+            Some(&[EntryTag::Plaintext("name".to_string(), name.to_string())][..]),
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+
+    let names = ["c", "missing", "a"].map(String::from);
+    let rows = conn
+        .fetch_many(EntryKind::Item, "category", &names, false)
+        .await
+        .expect(ERR_FETCH);
+    assert_eq!(
+        rows,
         vec![
-            EntryTag::Encrypted("t1".to_string(), "v1".to_string()),
-            EntryTag::Plaintext("t2".to_string(), "v2".to_string()),
-        ],
+            Some(Entry::new(
+                EntryKind::Item,
+                "category",
+                "c",
+                "c",
+                vec![EntryTag::Plaintext("name".to_string(), "c".to_string())],
+            )),
+            None,
+            Some(Entry::new(
+                EntryKind::Item,
+                "category",
+                "a",
+                "a",
+                vec![EntryTag::Plaintext("name".to_string(), "a".to_string())],
+            )),
+        ]
     );
 
-    let mut conn = db.session(None, true).expect(ERR_TRANSACTION);
+    let rows = conn
+        .fetch_many(EntryKind::Item, "other", &names, false)
+        .await
+        .expect(ERR_FETCH);
+    assert_eq!(rows, vec![None, None, None]);
+}
+
+pub async fn db_update_versioned(db: AnyBackend) {
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
 
     conn.update(
         EntryKind::Item,
         EntryOperation::Insert,
-        &test_row.category,
-        &test_row.name,
-        Some(&test_row.value),
-        Some(test_row.tags.as_slice()),
+        "category",
+        "name",
+        Some(b"first"),
+        None,
         None,
     )
     .await
     .expect(ERR_INSERT);
-
-    conn.close(true).await.expect(ERR_COMMIT);
-
-    const TASKS: usize = 10;
-    const INC: usize = 1000;
-
-    async fn inc(db: AnyBackend, category: String, name: String) -> Result<(), &'static str> {
-        // try to avoid panics in this section, as they will be raised on a tokio worker thread
-        for _ in 0..INC {
-            let mut conn = db.session(None, true).expect(ERR_TRANSACTION);
-            let row = conn
-                .fetch(EntryKind::Item, &category, &name, true)
-                .await
-                .map_err(|e| {
-                    log::error!("{:?}", e);
-                    ERR_FETCH
-                })?
-                .ok_or(ERR_REQ_ROW)?;
-            let val: usize = str::parse(row.value.as_opt_str().ok_or("Non-string counter value")?)
-                .map_err(|_| "Error parsing counter value")?;
-            conn.update(
-                EntryKind::Item,
-                EntryOperation::Replace,
-                &category,
-                &name,
-                Some(format!("{}", val + 1).as_bytes()),
-                Some(row.tags.as_slice()),
-                None,
-            )
-            .await
-            .map_err(|e| {
-                log::error!("{:?}", e);
-                ERR_REPLACE
-            })?;
-            conn.close(true).await.map_err(|_| ERR_COMMIT)?;
-        }
-        Ok(())
-    }
-
-    let mut tasks = vec![];
-    for _ in 0..TASKS {
-        tasks.push(spawn(inc(
-            db.clone(),
-            test_row.category.clone(),
-            test_row.name.clone(),
-        )));
-    }
-
-    // JoinSet is not stable yet, just await all the tasks
-    for task in tasks {
-        if let Err(s) = task.await.unwrap() {
-            panic!("Error in concurrent update task: {}", s);
-        }
-    }
-
-    // check the total
-    let mut conn = db.session(None, false).expect(ERR_SESSION);
     let row = conn
-        .fetch(EntryKind::Item, &test_row.category, &test_row.name, false)
+        .fetch(EntryKind::Item, "category", "name", false)
         .await
         .expect(ERR_FETCH)
         .expect(ERR_REQ_ROW);
-    assert_eq!(row.value, format!("{}", TASKS * INC).as_bytes());
-}
-
-pub async fn db_list_profiles(db: AnyBackend) {
-    let p_active = db.get_active_profile();
-    assert_eq!(vec![p_active.clone()], db.list_profiles().await.unwrap());
-
-    let p_new = db.create_profile(None).await.unwrap();
-    let mut profs = vec![p_active, p_new];
-    profs.sort();
-    let mut found = db.list_profiles().await.unwrap();
-    found.sort();
-    assert_eq!(profs, found);
-}
-
-pub async fn db_get_set_default_profile(db: AnyBackend) {
-    let p_default = db.get_default_profile().await.unwrap();
-    let p_new = db.create_profile(None).await.unwrap();
-    assert_ne!(p_new, p_default);
-    db.set_default_profile(p_new.clone()).await.unwrap();
-    assert_eq!(db.get_default_profile().await.unwrap(), p_new);
-}
+    let version = row.version.expect("Expected entry version");
 
-pub async fn db_import_scan(db: AnyBackend) {
-    let test_rows = vec![Entry::new(
+    conn.update_versioned(
         EntryKind::Item,
+        EntryOperation::Replace,
         "category",
         "name",
-        "value",
-        vec![
-            EntryTag::Encrypted("t1".to_string(), "v1".to_string()),
-            EntryTag::Plaintext("t2".to_string(), "v2".to_string()),
-        ],
-    )];
+        Some(b"second"),
+        None,
+        None,
+        version,
+    )
+    .await
+    .expect(ERR_REPLACE);
+    let row = conn
+        .fetch(EntryKind::Item, "category", "name", false)
+        .await
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    assert_eq!(row.value, b"second".to_vec());
+    assert_eq!(row.version, Some(version + 1));
 
-    let mut conn = db.session(None, false).expect(ERR_SESSION);
-    for upd in test_rows.iter() {
-        conn.update(
+    let err = conn
+        .update_versioned(
             EntryKind::Item,
-            EntryOperation::Insert,
-            &upd.category,
-            &upd.name,
-            Some(&upd.value),
-            Some(upd.tags.as_slice()),
+            EntryOperation::Replace,
+            "category",
+            "name",
+            Some(b"stale"),
             None,
+            None,
+            version,
         )
         .await
-        .expect(ERR_INSERT);
-    }
-    drop(conn);
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::Conflict);
 
-    let copy = db.create_profile(None).await.expect(ERR_PROFILE);
-    let mut copy_conn = db.session(Some(copy.clone()), true).expect(ERR_SESSION);
-    let records = db
-        .scan(
-            None,
-            Some(EntryKind::Item),
-            None,
-            None,
+    let err = conn
+        .update_versioned(
+            EntryKind::Item,
+            EntryOperation::Remove,
+            "category",
+            "name",
             None,
             None,
             None,
-            false,
+            version,
         )
         .await
-        .expect(ERR_SCAN);
-    copy_conn
-        .import_scan(records)
-        .await
-        .expect("Error importing records");
-    copy_conn.close(true).await.expect(ERR_COMMIT);
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::Conflict);
 
-    let mut scan = db
-        .scan(
-            Some(copy),
-            Some(EntryKind::Item),
-            None,
-            None,
+    conn.update_versioned(
+        EntryKind::Item,
+        EntryOperation::Remove,
+        "category",
+        "name",
+        None,
+        None,
+        None,
+        version + 1,
+    )
+    .await
+    .expect(ERR_REQ_ROW);
+
+    let err = conn
+        .update_versioned(
+            EntryKind::Item,
+            EntryOperation::Remove,
+            "category",
+            "name",
             None,
             None,
             None,
-            false,
+            version + 1,
         )
         .await
-        .expect(ERR_SCAN);
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
 
-    let rows = scan.fetch_next().await.expect(ERR_SCAN_NEXT);
-    assert_eq!(rows, Some(test_rows));
-    let rows = scan.fetch_next().await.expect(ERR_SCAN_NEXT);
+pub async fn db_remove_expired(db: AnyBackend) {
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    for (name, expiry_ms) in [("expired", Some(-60_000)), ("current", None)] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            "category",
+            name,
+            Some(b"value"),
+            None,
+            expiry_ms,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+
+    let row = conn
+        .fetch(EntryKind::Item, "category", "expired", false)
+        .await
+        .expect(ERR_FETCH);
+    assert!(row.is_none());
+
+    let removed = conn
+        .remove_expired(Some(EntryKind::Item), Some("category"))
+        .await
+        .expect("Error removing expired records");
+    assert_eq!(removed, 1);
+
+    let count = conn
+        .count(Some(EntryKind::Item), Some("category"), None, false)
+        .await
+        .expect(ERR_COUNT);
+    assert_eq!(count, 1);
+
+    // the name of the removed record is available again
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        "category",
+        "expired",
+        Some(b"value"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+
+    // the expiry time of a current record is returned
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        "category",
+        "expiring",
+        Some(b"value"),
+        None,
+        Some(60_000),
+    )
+    .await
+    .expect(ERR_INSERT);
+    let row = conn
+        .fetch(EntryKind::Item, "category", "expiring", false)
+        .await
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    let expires_at = row.expires_at.expect("Expected expiry time");
+    assert!((expires_at - (now_ms + 60_000)).abs() < 5_000);
+}
+
+pub async fn db_soft_delete(db: AnyBackend) {
+    db.set_soft_delete(true)
+        .await
+        .expect("Error enabling soft deletion");
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        "category",
+        "name",
+        Some(b"first"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Remove,
+        "category",
+        "name",
+        None,
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REMOVE);
+    let row = conn
+        .fetch(EntryKind::Item, "category", "name", false)
+        .await
+        .expect(ERR_FETCH);
+    assert!(row.is_none());
+    let count = conn
+        .count(Some(EntryKind::Item), Some("category"), None, false)
+        .await
+        .expect(ERR_COUNT);
+    assert_eq!(count, 0);
+    let count = conn
+        .count(Some(EntryKind::Item), Some("category"), None, true)
+        .await
+        .expect(ERR_COUNT);
+    assert_eq!(count, 1);
+
+    for include_deleted in [false, true] {
+        let rows = db
+            .scan(
+                None,
+                Some(EntryKind::Item),
+                Some("category".to_string()),
+                None,
+                None,
+                None,
+                None,
+                false,
+                include_deleted,
+            )
+            .await
+            .expect(ERR_SCAN)
+            .fetch_next()
+            .await
+            .expect(ERR_SCAN_NEXT)
+            .unwrap_or_default();
+        if include_deleted {
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].name, "name");
+            assert_eq!(rows[0].value, b"first".to_vec());
+            assert!(rows[0].deleted_at.is_some());
+        } else {
+            assert!(rows.is_empty());
+        }
+    }
+
+    conn.restore(EntryKind::Item, "category", "name")
+        .await
+        .expect("Error restoring test row");
+    let row = conn
+        .fetch(EntryKind::Item, "category", "name", false)
+        .await
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    assert_eq!(row.value, b"first".to_vec());
+
+    // inserting a record replaces one which has been deleted
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Remove,
+        "category",
+        "name",
+        None,
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REMOVE);
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        "category",
+        "name",
+        Some(b"second"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+    let row = conn
+        .fetch(EntryKind::Item, "category", "name", false)
+        .await
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    assert_eq!(row.value, b"second".to_vec());
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Remove,
+        "category",
+        "name",
+        None,
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REMOVE);
+    let purged = conn
+        .purge(None, Some(60_000))
+        .await
+        .expect("Error purging deleted rows");
+    assert_eq!(purged, 0);
+    let purged = conn
+        .purge(None, None)
+        .await
+        .expect("Error purging deleted rows");
+    assert_eq!(purged, 1);
+
+    let err = conn
+        .restore(EntryKind::Item, "category", "name")
+        .await
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+pub async fn db_record_history(db: AnyBackend) {
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    conn.set_history(EntryKind::Item, "category", true)
+        .await
+        .expect("Error enabling record history");
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        "category",
+        "name",
+        Some(b"first"),
+        Some(&[EntryTag::Plaintext("state".to_string(), "a".to_string())][..]),
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+    for value in [&b"second"[..], &b"third"[..]] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Replace,
+            "category",
+            "name",
+            Some(value),
+            None,
+            None,
+        )
+        .await
+        .expect(ERR_REPLACE);
+    }
+
+    let history = conn
+        .fetch_history(EntryKind::Item, "category", "name", None)
+        .await
+        .expect("Error fetching record history");
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].entry.value, b"second".to_vec());
+    assert_eq!(history[0].entry.version, Some(2));
+    assert_eq!(history[1].entry.value, b"first".to_vec());
+    assert_eq!(
+        history[1].entry.tags,
+        vec![EntryTag::Plaintext("state".to_string(), "a".to_string())]
+    );
+
+    let first = conn
+        .fetch_version(EntryKind::Item, "category", "name", 1)
+        .await
+        .expect("Error fetching record version")
+        .expect(ERR_REQ_ROW);
+    assert_eq!(first.entry.value, b"first".to_vec());
+    let missing = conn
+        .fetch_version(EntryKind::Item, "category", "name", 3)
+        .await
+        .expect("Error fetching record version");
+    assert!(missing.is_none());
+
+    // versions are not preserved for other categories
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::InsertOrReplace,
+        "other",
+        "name",
+        Some(b"value"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::InsertOrReplace,
+        "other",
+        "name",
+        Some(b"value"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REPLACE);
+    let history = conn
+        .fetch_history(EntryKind::Item, "other", "name", None)
+        .await
+        .expect("Error fetching record history");
+    assert!(history.is_empty());
+
+    let pruned = conn
+        .prune_history(Some(EntryKind::Item), Some("category"), Some(60_000))
+        .await
+        .expect("Error pruning record history");
+    assert_eq!(pruned, 0);
+    let pruned = conn
+        .prune_history(Some(EntryKind::Item), None, None)
+        .await
+        .expect("Error pruning record history");
+    assert_eq!(pruned, 2);
+}
+
+pub async fn db_list_tag_values(db: AnyBackend) {
+    let category = "category".to_string();
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    for (name, state, role) in [
+        ("a", "active", "admin"),
+        ("b", "active", "user"),
+        ("c", "invited", "user"),
+    ] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            &category,
+            name,
+            Some(b"value"),
+            Some(
+                &[
+                    EntryTag::Plaintext("state".to_string(), state.to_string()),
+                    EntryTag::Encrypted("role".to_string(), role.to_string()),
+                ][..],
+            ),
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+
+    let values = conn
+        .list_tag_values(EntryKind::Item, &category, "~state", None)
+        .await
+        .expect("Error listing tag values");
+    assert_eq!(
+        values.into_iter().collect::<Vec<_>>(),
+        [("active".to_string(), 2), ("invited".to_string(), 1)]
+    );
+
+    let values = conn
+        .list_tag_values(
+            EntryKind::Item,
+            &category,
+            "role",
+            Some(TagFilter::is_eq("~state", "active")),
+        )
+        .await
+        .expect("Error listing tag values");
+    assert_eq!(
+        values.into_iter().collect::<Vec<_>>(),
+        [("admin".to_string(), 1), ("user".to_string(), 1)]
+    );
+}
+
+pub async fn db_tag_range(db: AnyBackend) {
+    let category = "category".to_string();
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    for (name, size, created) in [
+        ("a", -5, "2023-11-30T12:00:00Z"),
+        ("b", 3, "2024-02-01T00:00:00Z"),
+        ("c", 10, "2024-10-05T08:30:00Z"),
+        ("d", 200, "2025-01-01T00:00:00Z"),
+    ] {
+        let tags = [
+            EntryTag::Plaintext("size".to_string(), sortable_int(size)),
+            EntryTag::Plaintext("created".to_string(), created.to_string()),
+            EntryTag::Plaintext("raw".to_string(), size.to_string()),
+        ];
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            &category,
+            name,
+            Some(b"value"),
+            Some(&tags[..]),
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+
+    for (filter, expected) in [
+        (
+            TagFilter::is_between("~size", sortable_int(0), sortable_int(100)),
+            vec!["b", "c"],
+        ),
+        (TagFilter::is_lt("~size", sortable_int(3)), vec!["a"]),
+        (TagFilter::is_gte("~size", sortable_int(10)), vec!["c", "d"]),
+        (
+            TagFilter::is_between("~created", "2024-02-01T00:00:00Z", "2024-12-31T23:59:59Z"),
+            vec!["b", "c"],
+        ),
+        // values which are not zero-padded are compared as strings
+        (TagFilter::is_lt("~raw", "3"), vec!["a", "c", "d"]),
+    ] {
+        let rows = conn
+            .fetch_all(
+                Some(EntryKind::Item),
+                Some(category.as_str()),
+                Some(filter),
+                None,
+                Some(OrderBy::Id),
+                false,
+                false,
+            )
+            .await
+            .expect(ERR_FETCH_ALL);
+        let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, expected);
+    }
+
+    let err = conn
+        .fetch_all(
+            Some(EntryKind::Item),
+            Some(category.as_str()),
+            Some(TagFilter::is_gt("size", "0")),
+            None,
+            None,
+            false,
+            false,
+        )
+        .await
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::Input);
+}
+
+pub async fn db_tag_prefix(db: AnyBackend) {
+    let category = "category".to_string();
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    for (name, label) in [("a", "conn-alpha"), ("b", "conn-al_x"), ("c", "other")] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            &category,
+            name,
+            Some(b"value"),
+            Some(&[EntryTag::Plaintext("label".to_string(), label.to_string())][..]),
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+
+    for (filter, expected) in [
+        (TagFilter::is_prefix("~label", "conn-"), vec!["a", "b"]),
+        (TagFilter::is_prefix("~label", "conn-al_"), vec!["b"]),
+        (TagFilter::is_like("~label", "%her"), vec!["c"]),
+    ] {
+        let rows = conn
+            .fetch_all(
+                Some(EntryKind::Item),
+                Some(category.as_str()),
+                Some(filter),
+                None,
+                Some(OrderBy::Id),
+                false,
+                false,
+            )
+            .await
+            .expect(ERR_FETCH_ALL);
+        let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, expected);
+    }
+}
+
+pub async fn db_tag_exists_size(db: AnyBackend) {
+    let category = "category".to_string();
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    let role = |value: &str| EntryTag::Encrypted("role".to_string(), value.to_string());
+    let migrated = EntryTag::Plaintext("migrated".to_string(), "true".to_string());
+    for (name, tags) in [
+        ("a", vec![role("admin"), role("user"), migrated.clone()]),
+        ("b", vec![role("user")]),
+        ("c", vec![]),
+    ] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            &category,
+            name,
+            Some(b"value"),
+            Some(tags.as_slice()),
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+
+    for (filter, expected) in [
+        (
+            r#"{"~migrated": {"$exists": false}}"#
+                .parse::<TagFilter>()
+                .expect("Error parsing tag filter"),
+            vec!["b", "c"],
+        ),
+        (TagFilter::has_size("role", 2), vec!["a"]),
+        (TagFilter::has_size("role", 1), vec!["b"]),
+        (TagFilter::has_size("role", 0), vec!["c"]),
+        (
+            TagFilter::negate(TagFilter::has_size("role", 1)),
+            vec!["a", "c"],
+        ),
+    ] {
+        let rows = conn
+            .fetch_all(
+                Some(EntryKind::Item),
+                Some(category.as_str()),
+                Some(filter),
+                None,
+                Some(OrderBy::Id),
+                false,
+                false,
+            )
+            .await
+            .expect(ERR_FETCH_ALL);
+        let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, expected);
+    }
+}
+
+pub async fn db_search_values(db: AnyBackend) {
+    let terms = |terms: &[&str]| terms.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    for (category, name, index) in [
+        ("messages", "a", terms(&["hello", "world"])),
+        ("messages", "b", terms(&["hello"])),
+        ("other", "c", terms(&["hello", "world"])),
+    ] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            category,
+            name,
+            Some(b"value"),
+            None,
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+        conn.update_search_terms(EntryKind::Item, category, name, &index)
+            .await
+            .expect("Error updating search terms");
+    }
+
+    for (search, expected) in [
+        (terms(&["hello"]), vec!["a", "b"]),
+        (terms(&["hello", "world"]), vec!["a"]),
+        (terms(&["world", "world"]), vec!["a"]),
+        (terms(&["goodbye"]), vec![]),
+    ] {
+        let rows = conn
+            .search_values(EntryKind::Item, "messages", &search, None)
+            .await
+            .expect("Error searching values");
+        let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, expected);
+    }
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Remove,
+        "messages",
+        "a",
+        None,
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REQ_ROW);
+    let rows = conn
+        .search_values(EntryKind::Item, "messages", &terms(&["hello"]), None)
+        .await
+        .expect("Error searching values");
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].name, "b");
+
+    let err = conn
+        .update_search_terms(EntryKind::Item, "messages", "a", &terms(&["hello"]))
+        .await
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    let err = conn
+        .search_values(EntryKind::Item, "messages", &[], None)
+        .await
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::Input);
+}
+
+pub async fn db_remove_all(db: AnyBackend) {
+    let test_rows = [
+        Entry::new(
+            EntryKind::Item,
+            "category",
+            "item1",
+            "value",
+            vec![
+                EntryTag::Encrypted("t1".to_string(), "del".to_string()),
+                EntryTag::Plaintext("t2".to_string(), "del".to_string()),
+            ],
+        ),
+        Entry::new(
+            EntryKind::Item,
+            "category",
+            "item2",
+            "value",
+            vec![
+                EntryTag::Encrypted("t1".to_string(), "del".to_string()),
+                EntryTag::Plaintext("t2".to_string(), "del".to_string()),
+            ],
+        ),
+        Entry::new(
+            EntryKind::Item,
+            "category",
+            "item3",
+            "value",
+            vec![
+                EntryTag::Encrypted("t1".to_string(), "keep".to_string()),
+                EntryTag::Plaintext("t2".to_string(), "keep".to_string()),
+            ],
+        ),
+    ];
+
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    for test_row in test_rows.iter() {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            &test_row.category,
+            &test_row.name,
+            Some(&test_row.value),
+            Some(test_row.tags.as_slice()),
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+
+    // could detect that a second transaction would block here?
+    // depends on the backend. just checking that no SQL errors occur for now.
+    let removed = conn
+        .remove_all(
+            Some(EntryKind::Item),
+            Some("category"),
+            Some(TagFilter::all_of(vec![
+                TagFilter::is_eq("t1", "del"),
+                TagFilter::is_eq("~t2", "del"),
+            ])),
+        )
+        .await
+        .expect(ERR_REMOVE_ALL);
+    assert_eq!(removed, 2);
+}
+
+pub async fn db_txn_rollback(db: AnyBackend) {
+    let test_row = Entry::new(EntryKind::Item, "category", "name", "value", Vec::new());
+
+    let mut conn = db.session(None, true).expect(ERR_TRANSACTION);
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        &test_row.category,
+        &test_row.name,
+        Some(&test_row.value),
+        Some(test_row.tags.as_slice()),
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+
+    conn.close(false)
+        .await
+        .expect("Error rolling back transaction");
+
+    let mut conn = db.session(None, false).expect("Error starting new session");
+
+    let row = conn
+        .fetch(EntryKind::Item, &test_row.category, &test_row.name, false)
+        .await
+        .expect("Error fetching test row");
+    assert_eq!(row, None);
+}
+
+pub async fn db_txn_drop(db: AnyBackend) {
+    let test_row = Entry::new(EntryKind::Item, "category", "name", "value", Vec::new());
+
+    let mut conn = db
+        .session(None, true)
+        .expect("Error starting new transaction");
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        &test_row.category,
+        &test_row.name,
+        Some(&test_row.value),
+        Some(test_row.tags.as_slice()),
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+
+    drop(conn);
+
+    let mut conn = db.session(None, false).expect("Error starting new session");
+
+    let row = conn
+        .fetch(EntryKind::Item, &test_row.category, &test_row.name, false)
+        .await
+        .expect("Error fetching test row");
+    assert_eq!(row, None);
+}
+
+// test that session does NOT have transaction rollback behaviour
+pub async fn db_session_drop(db: AnyBackend) {
+    let test_row = Entry::new(EntryKind::Item, "category", "name", "value", Vec::new());
+
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        &test_row.category,
+        &test_row.name,
+        Some(&test_row.value),
+        Some(test_row.tags.as_slice()),
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+
+    drop(conn);
+
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    let row = conn
+        .fetch(EntryKind::Item, &test_row.category, &test_row.name, false)
+        .await
+        .expect(ERR_FETCH);
+    assert_eq!(row, Some(test_row));
+}
+
+pub async fn db_txn_commit(db: AnyBackend) {
+    let test_row = Entry::new(EntryKind::Item, "category", "name", "value", Vec::new());
+
+    let mut conn = db.session(None, true).expect(ERR_TRANSACTION);
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        &test_row.category,
+        &test_row.name,
+        Some(&test_row.value),
+        Some(test_row.tags.as_slice()),
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+
+    conn.close(true).await.expect(ERR_COMMIT);
+
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    let row = conn
+        .fetch(EntryKind::Item, &test_row.category, &test_row.name, false)
+        .await
+        .expect(ERR_FETCH);
+    assert_eq!(row, Some(test_row));
+}
+
+pub async fn db_txn_fetch_for_update(db: AnyBackend) {
+    let test_row = Entry::new(EntryKind::Item, "category", "name", "value", Vec::new());
+
+    let mut conn = db.session(None, true).expect(ERR_TRANSACTION);
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        &test_row.category,
+        &test_row.name,
+        Some(&test_row.value),
+        Some(test_row.tags.as_slice()),
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+
+    // could detect that a second transaction would block here?
+    // depends on the backend. just checking that no SQL errors occur for now.
+    let row = conn
+        .fetch(EntryKind::Item, &test_row.category, &test_row.name, true)
+        .await
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    assert_eq!(row, test_row);
+
+    let rows = conn
+        .fetch_all(
+            Some(EntryKind::Item),
+            Some(&test_row.category),
+            None,
+            Some(2),
+            None,
+            false,
+            true,
+        )
+        .await
+        .expect(ERR_FETCH_ALL);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0], test_row);
+
+    conn.close(true).await.expect(ERR_COMMIT);
+}
+
+pub async fn db_txn_contention(db: AnyBackend) {
+    let test_row = Entry::new(
+        EntryKind::Item,
+        "category",
+        "count",
+        "0",
+        vec![
+            EntryTag::Encrypted("t1".to_string(), "v1".to_string()),
+            EntryTag::Plaintext("t2".to_string(), "v2".to_string()),
+        ],
+    );
+
+    let mut conn = db.session(None, true).expect(ERR_TRANSACTION);
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        &test_row.category,
+        &test_row.name,
+        Some(&test_row.value),
+        Some(test_row.tags.as_slice()),
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+
+    conn.close(true).await.expect(ERR_COMMIT);
+
+    const TASKS: usize = 10;
+    const INC: usize = 1000;
+
+    async fn inc(db: AnyBackend, category: String, name: String) -> Result<(), &'static str> {
+        // try to avoid panics in this section, as they will be raised on a tokio worker thread
+        for _ in 0..INC {
+            let mut conn = db.session(None, true).expect(ERR_TRANSACTION);
+            let row = conn
+                .fetch(EntryKind::Item, &category, &name, true)
+                .await
+                .map_err(|e| {
+                    log::error!("{:?}", e);
+                    ERR_FETCH
+                })?
+                .ok_or(ERR_REQ_ROW)?;
+            let val: usize = str::parse(row.value.as_opt_str().ok_or("Non-string counter value")?)
+                .map_err(|_| "Error parsing counter value")?;
+            conn.update(
+                EntryKind::Item,
+                EntryOperation::Replace,
+                &category,
+                &name,
+                Some(format!("{}", val + 1).as_bytes()),
+                Some(row.tags.as_slice()),
+                None,
+            )
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                ERR_REPLACE
+            })?;
+            conn.close(true).await.map_err(|_| ERR_COMMIT)?;
+        }
+        Ok(())
+    }
+
+    let mut tasks = vec![];
+    for _ in 0..TASKS {
+        tasks.push(spawn(inc(
+            db.clone(),
+            test_row.category.clone(),
+            test_row.name.clone(),
+        )));
+    }
+
+    // JoinSet is not stable yet, just await all the tasks
+    for task in tasks {
+        if let Err(s) = task.await.unwrap() {
+            panic!("Error in concurrent update task: {}", s);
+        }
+    }
+
+    // check the total
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+    let row = conn
+        .fetch(EntryKind::Item, &test_row.category, &test_row.name, false)
+        .await
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    assert_eq!(row.value, format!("{}", TASKS * INC).as_bytes());
+}
+
+pub async fn db_list_profiles(db: AnyBackend) {
+    let p_active = db.get_active_profile();
+    assert_eq!(vec![p_active.clone()], db.list_profiles().await.unwrap());
+
+    let p_new = db.create_profile(None).await.unwrap();
+    let mut profs = vec![p_active, p_new];
+    profs.sort();
+    let mut found = db.list_profiles().await.unwrap();
+    found.sort();
+    assert_eq!(profs, found);
+}
+
+pub async fn db_get_set_default_profile(db: AnyBackend) {
+    let p_default = db.get_default_profile().await.unwrap();
+    let p_new = db.create_profile(None).await.unwrap();
+    assert_ne!(p_new, p_default);
+    db.set_default_profile(p_new.clone()).await.unwrap();
+    assert_eq!(db.get_default_profile().await.unwrap(), p_new);
+}
+
+pub async fn db_import_scan(db: AnyBackend) {
+    let test_rows = vec![Entry::new(
+        EntryKind::Item,
+        "category",
+        "name",
+        "value",
+        vec![
+            EntryTag::Encrypted("t1".to_string(), "v1".to_string()),
+            EntryTag::Plaintext("t2".to_string(), "v2".to_string()),
+        ],
+    )];
+
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+    for upd in test_rows.iter() {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            &upd.category,
+            &upd.name,
+            Some(&upd.value),
+            Some(upd.tags.as_slice()),
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+    drop(conn);
+
+    let copy = db.create_profile(None).await.expect(ERR_PROFILE);
+    let mut copy_conn = db.session(Some(copy.clone()), true).expect(ERR_SESSION);
+    let records = db
+        .scan(
+            None,
+            Some(EntryKind::Item),
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            false,
+        )
+        .await
+        .expect(ERR_SCAN);
+    copy_conn
+        .import_scan(records)
+        .await
+        .expect("Error importing records");
+    copy_conn.close(true).await.expect(ERR_COMMIT);
+
+    let mut scan = db
+        .scan(
+            Some(copy),
+            Some(EntryKind::Item),
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            false,
+        )
+        .await
+        .expect(ERR_SCAN);
+
+    let rows = scan.fetch_next().await.expect(ERR_SCAN_NEXT);
+    assert_eq!(rows, Some(test_rows));
+    let rows = scan.fetch_next().await.expect(ERR_SCAN_NEXT);
     assert_eq!(rows, None);
 }
+
+pub async fn db_audit_log(db: AnyBackend) {
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    // operations are not logged until enabled
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        "category",
+        "before",
+        Some(b"value"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+    db.set_audit_log(true)
+        .await
+        .expect("Error enabling audit log");
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        "category",
+        "name",
+        Some(b"value"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Replace,
+        "category",
+        "name",
+        Some(b"updated"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REPLACE);
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Remove,
+        "category",
+        "name",
+        None,
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REMOVE);
+
+    let records = conn
+        .fetch_audit_log(None, None)
+        .await
+        .expect("Error fetching audit log");
+    assert_eq!(
+        records
+            .iter()
+            .map(|r| r.operation.as_str())
+            .collect::<Vec<_>>(),
+        vec!["insert", "replace", "remove"]
+    );
+    for record in &records {
+        assert_eq!(record.kind, Some(EntryKind::Item));
+        assert_eq!(record.category.as_deref(), Some("category"));
+        assert_eq!(record.session_id, records[0].session_id);
+        // the item is identified by its row ID rather than its name
+        assert!(record.item_id.is_some());
+        assert_eq!(record.item_id, records[0].item_id);
+    }
+
+    let after = conn
+        .fetch_audit_log(Some(records[0].id), Some(1))
+        .await
+        .expect("Error fetching audit log");
+    assert_eq!(after.len(), 1);
+    assert_eq!(after[0], records[1]);
+    drop(conn);
+
+    // a new session is recorded under a different identifier
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::InsertOrReplace,
+        "category",
+        "name",
+        Some(b"value"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REPLACE);
+    let records = conn
+        .fetch_audit_log(None, None)
+        .await
+        .expect("Error fetching audit log");
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[3].operation, "upsert");
+    assert_ne!(records[3].session_id, records[0].session_id);
+
+    db.set_audit_log(false)
+        .await
+        .expect("Error disabling audit log");
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Remove,
+        "category",
+        "name",
+        None,
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REMOVE);
+    let records = conn
+        .fetch_audit_log(None, None)
+        .await
+        .expect("Error fetching audit log");
+    assert_eq!(records.len(), 4);
+}
+
+pub async fn db_subscribe(db: AnyBackend) {
+    let mut sub = db
+        .subscribe(None, Some(EntryKind::Item), Some("category".to_string()))
+        .await
+        .expect("Error subscribing to changes");
+    let tags = vec![EntryTag::Plaintext("plain".to_string(), "a".to_string())];
+
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        "category",
+        "name",
+        Some(b"value"),
+        Some(tags.as_slice()),
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+    // changes to other categories are not delivered
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Insert,
+        "other",
+        "name",
+        Some(b"value"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_INSERT);
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Replace,
+        "category",
+        "name",
+        Some(b"updated"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REPLACE);
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Remove,
+        "category",
+        "name",
+        None,
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REMOVE);
+    drop(conn);
+
+    // changes are not delivered for a transaction which is rolled back
+    for commit in [false, true] {
+        let mut txn = db.session(None, true).expect(ERR_TRANSACTION);
+        txn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            "category",
+            if commit { "commit" } else { "rollback" },
+            Some(b"value"),
+            None,
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+        txn.close(commit).await.expect("Error closing transaction");
+    }
+
+    let mut events = Vec::new();
+    while events.len() < 4 {
+        events.push(
+            sub.next()
+                .await
+                .expect("Error receiving change event")
+                .expect("Expected change event"),
+        );
+    }
+    assert_eq!(events[0].operation, ChangeOperation::Insert);
+    assert_eq!(events[0].category, "category");
+    assert_eq!(events[0].name, "name");
+    assert_eq!(events[0].tags, tags);
+    assert_eq!(events[1].operation, ChangeOperation::Update);
+    assert!(events[1].tags.is_empty());
+    assert_eq!(events[2].operation, ChangeOperation::Remove);
+    assert_eq!(events[2].name, "name");
+    assert_eq!(events[3].operation, ChangeOperation::Insert);
+    assert_eq!(events[3].name, "commit");
+}
+
+pub async fn db_timestamps(db: AnyBackend) {
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+
+    for name in ["a", "b"] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            "category",
+            name,
+            Some(b"value"),
+            None,
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+
+    let row = conn
+        .fetch(EntryKind::Item, "category", "a", false)
+        .await
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    let created_at = row.created_at.expect("Expected creation time");
+    assert_eq!(row.updated_at, Some(created_at));
+
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Replace,
+        "category",
+        "a",
+        Some(b"updated"),
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REPLACE);
+
+    let row = conn
+        .fetch(EntryKind::Item, "category", "a", false)
+        .await
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    assert_eq!(row.created_at, Some(created_at));
+    let updated_at = row.updated_at.expect("Expected modification time");
+    assert!(updated_at >= created_at);
+
+    let rows = conn
+        .fetch_all(
+            Some(EntryKind::Item),
+            Some("category"),
+            None,
+            None,
+            Some(OrderBy::UpdatedAt),
+            true,
+            false,
+        )
+        .await
+        .expect(ERR_FETCH_ALL);
+    assert_eq!(rows.len(), 2);
+    assert!(rows[0].updated_at >= rows[1].updated_at);
+    assert!(rows.iter().all(|row| row.created_at.is_some()));
+
+    let rows = conn
+        .fetch_all(
+            Some(EntryKind::Item),
+            Some("category"),
+            Some(TagFilter::is_gte("$created_at", created_at.to_string())),
+            None,
+            None,
+            false,
+            false,
+        )
+        .await
+        .expect(ERR_FETCH_ALL);
+    assert_eq!(rows.len(), 2);
+
+    let rows = conn
+        .fetch_all(
+            Some(EntryKind::Item),
+            Some("category"),
+            Some(TagFilter::is_gt("$updated_at", updated_at.to_string())),
+            None,
+            None,
+            false,
+            false,
+        )
+        .await
+        .expect(ERR_FETCH_ALL);
+    assert!(rows.is_empty());
+
+    let err = conn
+        .fetch_all(
+            Some(EntryKind::Item),
+            Some("category"),
+            Some(TagFilter::is_gt("$updated_at", "yesterday")),
+            None,
+            None,
+            false,
+            false,
+        )
+        .await
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::Input);
+}
+
+pub async fn db_meta(db: AnyBackend) {
+    assert_eq!(
+        db.get_meta("app_version".to_string())
+            .await
+            .expect("Error fetching store metadata"),
+        None
+    );
+    db.set_meta(
+        "app_version".to_string(),
+        Some(SecretBytes::from_slice(b"1")),
+    )
+    .await
+    .expect("Error setting store metadata");
+    assert_eq!(
+        db.get_meta("app_version".to_string())
+            .await
+            .expect("Error fetching store metadata"),
+        Some(SecretBytes::from_slice(b"1"))
+    );
+
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+    conn.set_profile_meta("device_id", Some(b"device"))
+        .await
+        .expect("Error setting profile metadata");
+    assert_eq!(
+        conn.get_profile_meta("device_id")
+            .await
+            .expect("Error fetching profile metadata"),
+        Some(SecretBytes::from_slice(b"device"))
+    );
+    // metadata is not included in record counts
+    let count = conn.count(None, None, None, false).await.expect(ERR_COUNT);
+    assert_eq!(count, 0);
+    drop(conn);
+
+    // metadata of other profiles is not visible
+    let profile = db.create_profile(None).await.expect(ERR_PROFILE);
+    let mut conn = db.session(Some(profile), false).expect(ERR_SESSION);
+    assert_eq!(
+        conn.get_profile_meta("device_id")
+            .await
+            .expect("Error fetching profile metadata"),
+        None
+    );
+    drop(conn);
+
+    let mut conn = db.session(None, false).expect(ERR_SESSION);
+    conn.set_profile_meta("device_id", None)
+        .await
+        .expect("Error removing profile metadata");
+    assert_eq!(
+        conn.get_profile_meta("device_id")
+            .await
+            .expect("Error fetching profile metadata"),
+        None
+    );
+    drop(conn);
+
+    db.set_meta("app_version".to_string(), None)
+        .await
+        .expect("Error removing store metadata");
+    assert_eq!(
+        db.get_meta("app_version".to_string())
+            .await
+            .expect("Error fetching store metadata"),
+        None
+    );
+}
+
+pub async fn db_list_profile_details(db: AnyBackend) {
+    for name in ["tenant-b", "tenant-a", "other"] {
+        db.create_profile(Some(name.to_string()))
+            .await
+            .expect(ERR_PROFILE);
+    }
+
+    let mut conn = db
+        .session(Some("tenant-a".to_string()), false)
+        .expect(ERR_SESSION);
+    for name in ["a", "b"] {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            "category",
+            name,
+            Some(b"value"),
+            None,
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+    drop(conn);
+
+    let details = db
+        .list_profile_details(Some("tenant-".to_string()), None, None)
+        .await
+        .expect("Error listing profile details");
+    let names = details
+        .iter()
+        .map(|profile| profile.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["tenant-a", "tenant-b"]);
+    assert_eq!(details[0].item_count, 2);
+    assert!(details[0].created_at.is_some());
+    assert!(details[0].last_access.is_some());
+    assert_eq!(details[1].item_count, 0);
+    assert_eq!(details[1].last_access, None);
+
+    // the access is recorded outside of the rolled back transaction
+    let mut txn = db
+        .session(Some("tenant-b".to_string()), true)
+        .expect(ERR_TRANSACTION);
+    txn.count(Some(EntryKind::Item), Some("category"), None, false)
+        .await
+        .expect(ERR_COUNT);
+    txn.close(false).await.unwrap();
+    let details = db
+        .list_profile_details(Some("tenant-b".to_string()), None, None)
+        .await
+        .expect("Error listing profile details");
+    assert!(details[0].last_access.is_some());
+
+    let details = db
+        .list_profile_details(None, Some(1), Some(2))
+        .await
+        .expect("Error listing profile details");
+    assert_eq!(details.len(), 2);
+    let all = db
+        .list_profile_details(None, None, None)
+        .await
+        .expect("Error listing profile details");
+    assert_eq!(details, all[1..3]);
+}
+
+pub async fn db_rekey_profile(db: AnyBackend) {
+    let profile = db
+        .create_profile(Some("tenant".to_string()))
+        .await
+        .expect(ERR_PROFILE);
+    let mut conn = db.session(Some(profile.clone()), false).expect(ERR_SESSION);
+    conn.set_history(EntryKind::Item, "category", true)
+        .await
+        .expect("Error enabling record history");
+    for idx in 0..40 {
+        conn.update(
+            EntryKind::Item,
+            EntryOperation::Insert,
+            "category",
+            &format!("item{}", idx),
+            Some(b"first"),
+            Some(
+                &[
+                    EntryTag::Encrypted("enc".to_string(), "a".to_string()),
+                    EntryTag::Plaintext("plain".to_string(), idx.to_string()),
+                ][..],
+            ),
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Replace,
+        "category",
+        "item0",
+        Some(b"second"),
+        Some(&[EntryTag::Encrypted("enc".to_string(), "b".to_string())][..]),
+        None,
+    )
+    .await
+    .expect(ERR_REPLACE);
+    conn.update_search_terms(
+        EntryKind::Item,
+        "category",
+        "item0",
+        &["hello".to_string(), "world".to_string()],
+    )
+    .await
+    .expect("Error indexing search terms");
+    conn.update_search_terms(
+        EntryKind::Item,
+        "category",
+        "item39",
+        &["hello".to_string()],
+    )
+    .await
+    .expect("Error indexing search terms");
+    conn.set_profile_meta("device_id", Some(b"device"))
+        .await
+        .expect("Error setting profile metadata");
+    drop(conn);
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    db.rekey_profile(
+        profile.clone(),
+        Some(Arc::new({
+            let reports = reports.clone();
+            move |processed, total| reports.lock().unwrap().push((processed, total))
+        })),
+    )
+    .await
+    .expect("Error rekeying profile");
+    assert_eq!(*reports.lock().unwrap(), [(32, 40), (40, 40)]);
+
+    let mut conn = db.session(Some(profile.clone()), false).expect(ERR_SESSION);
+    let found = conn
+        .fetch(EntryKind::Item, "category", "item0", false)
+        .await
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    assert_eq!(found.value, b"second".to_vec());
+    assert_eq!(
+        found.tags,
+        vec![EntryTag::Encrypted("enc".to_string(), "b".to_string())]
+    );
+    let rows = conn
+        .fetch_all(
+            Some(EntryKind::Item),
+            Some("category"),
+            Some(TagFilter::all_of(vec![
+                TagFilter::is_eq("enc", "a"),
+                TagFilter::is_eq("~plain", "39"),
+            ])),
+            None,
+            None,
+            false,
+            false,
+        )
+        .await
+        .expect(ERR_FETCH_ALL);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].name, "item39");
+    let history = conn
+        .fetch_history(EntryKind::Item, "category", "item0", None)
+        .await
+        .expect("Error fetching record history");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].entry.value, b"first".to_vec());
+    assert_eq!(history[0].entry.tags.len(), 2);
+    assert_eq!(
+        conn.get_profile_meta("device_id")
+            .await
+            .expect("Error fetching profile metadata"),
+        Some(SecretBytes::from_slice(b"device"))
+    );
+    // the search index is rebuilt using the new key
+    let rows = conn
+        .search_values(EntryKind::Item, "category", &["hello".to_string()], None)
+        .await
+        .expect("Error searching values");
+    assert_eq!(rows.len(), 2);
+    let rows = conn
+        .search_values(
+            EntryKind::Item,
+            "category",
+            &["hello".to_string(), "world".to_string()],
+            None,
+        )
+        .await
+        .expect("Error searching values");
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].name, "item0");
+    conn.update_search_terms(EntryKind::Item, "category", "item39", &[])
+        .await
+        .expect("Error clearing search terms");
+    let rows = conn
+        .search_values(EntryKind::Item, "category", &["hello".to_string()], None)
+        .await
+        .expect("Error searching values");
+    assert_eq!(rows.len(), 1);
+    drop(conn);
+
+    let err = db
+        .rekey_profile("missing".to_string(), None)
+        .await
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+pub async fn db_copy_profile(db: AnyBackend) {
+    db.set_soft_delete(true)
+        .await
+        .expect("Error enabling soft deletion");
+    let source = db
+        .create_profile(Some("source".to_string()))
+        .await
+        .expect(ERR_PROFILE);
+    let mut conn = db.session(Some(source.clone()), false).expect(ERR_SESSION);
+    conn.set_history(EntryKind::Item, "category", true)
+        .await
+        .expect("Error enabling record history");
+    for (operation, name, value) in [
+        (EntryOperation::Insert, "current", &b"first"[..]),
+        (EntryOperation::Replace, "current", b"second"),
+        (EntryOperation::Insert, "removed", b"value"),
+    ] {
+        conn.update(
+            EntryKind::Item,
+            operation,
+            "category",
+            name,
+            Some(value),
+            None,
+            None,
+        )
+        .await
+        .expect(ERR_INSERT);
+    }
+    conn.update(
+        EntryKind::Item,
+        EntryOperation::Remove,
+        "category",
+        "removed",
+        None,
+        None,
+        None,
+    )
+    .await
+    .expect(ERR_REMOVE);
+    conn.update_search_terms(
+        EntryKind::Item,
+        "category",
+        "current",
+        &["hello".to_string()],
+    )
+    .await
+    .expect("Error indexing search terms");
+    conn.set_profile_meta("device_id", Some(b"device"))
+        .await
+        .expect("Error setting profile metadata");
+    drop(conn);
+
+    copy_profile(&db, &db, &source, "copy")
+        .await
+        .expect("Error copying profile");
+    let err = copy_profile(&db, &db, &source, "copy")
+        .await
+        .expect_err(ERR_REQ_ERR);
+    assert_eq!(err.kind(), ErrorKind::Duplicate);
+
+    // only the current records are copied
+    let mut conn = db
+        .session(Some("copy".to_string()), false)
+        .expect(ERR_SESSION);
+    let found = conn
+        .fetch(EntryKind::Item, "category", "current", false)
+        .await
+        .expect(ERR_FETCH)
+        .expect(ERR_REQ_ROW);
+    assert_eq!(found.value, b"second".to_vec());
+    let count = conn
+        .count(Some(EntryKind::Item), Some("category"), None, true)
+        .await
+        .expect(ERR_COUNT);
+    assert_eq!(count, 1);
+    let history = conn
+        .fetch_history(EntryKind::Item, "category", "current", None)
+        .await
+        .expect("Error fetching record history");
+    assert!(history.is_empty());
+    let rows = conn
+        .search_values(EntryKind::Item, "category", &["hello".to_string()], None)
+        .await
+        .expect("Error searching values");
+    assert!(rows.is_empty());
+    assert_eq!(
+        conn.get_profile_meta("device_id")
+            .await
+            .expect("Error fetching profile metadata"),
+        None
+    );
+}
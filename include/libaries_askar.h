@@ -27,6 +27,7 @@ enum ErrorCode
   NotFound = 6,
   Unexpected = 7,
   Unsupported = 8,
+  Conflict = 9,
   Custom = 100,
 };
 #ifndef __cplusplus
@@ -326,6 +327,15 @@ ErrorCode askar_key_derive_ecdh_es(FfiStr alg,
                                    int8_t receive,
                                    LocalKeyHandle *out);
 
+ErrorCode askar_key_derive_ecdh_es_bytes(LocalKeyHandle ephem_key,
+                                         LocalKeyHandle recip_key,
+                                         struct ByteBuffer alg_id,
+                                         struct ByteBuffer apu,
+                                         struct ByteBuffer apv,
+                                         int64_t output_len,
+                                         int8_t receive,
+                                         struct SecretBuffer *out);
+
 ErrorCode askar_key_entry_list_count(KeyEntryListHandle handle, int32_t *count);
 
 void askar_key_entry_list_free(KeyEntryListHandle handle);
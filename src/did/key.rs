@@ -0,0 +1,180 @@
+//! `did:key` identifier generation and resolution
+
+use serde_json::{json, Value};
+
+use super::{multikey_method, DidDocument, DID_CONTEXT, MULTIKEY_CONTEXT};
+use crate::{
+    error::Error,
+    kms::{KeyAlg, LocalKey},
+};
+
+const DID_KEY_PREFIX: &str = "did:key:";
+
+/// A `did:key` identifier and its public key
+#[derive(Debug)]
+pub struct DidKey {
+    did: String,
+    key: LocalKey,
+    key_agreement: Option<(String, LocalKey)>,
+}
+
+impl DidKey {
+    /// Create the `did:key` identifier for a public key or keypair
+    ///
+    /// Only the public key is retained.
+    pub fn from_key(key: &LocalKey) -> Result<Self, Error> {
+        Self::from_multikey(&key.to_multikey()?)
+    }
+
+    /// Resolve a `did:key` identifier or DID URL, ignoring any fragment
+    pub fn resolve(did: &str) -> Result<Self, Error> {
+        let did = did.split_once('#').map(|(did, _)| did).unwrap_or(did);
+        let multikey = did
+            .strip_prefix(DID_KEY_PREFIX)
+            .ok_or_else(|| err_msg!(Input, "Invalid did:key identifier"))?;
+        Self::from_multikey(multikey)
+    }
+
+    fn from_multikey(multikey: &str) -> Result<Self, Error> {
+        let key = LocalKey::from_multikey(multikey)?;
+        // an X25519 key agreement key is derived from an Ed25519 verification key
+        let key_agreement = if key.algorithm() == KeyAlg::Ed25519 {
+            let x_key = key.convert_key(KeyAlg::X25519)?;
+            Some((x_key.to_multikey()?, x_key))
+        } else {
+            None
+        };
+        Ok(Self {
+            did: format!("{}{}", DID_KEY_PREFIX, multikey),
+            key,
+            key_agreement,
+        })
+    }
+
+    /// Accessor for the DID
+    pub fn did(&self) -> &str {
+        &self.did
+    }
+
+    /// Accessor for the public key identified by the DID
+    pub fn public_key(&self) -> &LocalKey {
+        &self.key
+    }
+
+    /// Accessor for the key agreement key of the DID
+    ///
+    /// For Ed25519 keys this is the derived X25519 key, and for X25519 and X448 keys
+    /// it is the identified public key.
+    pub fn key_agreement_key(&self) -> Option<&LocalKey> {
+        match &self.key_agreement {
+            Some((_, key)) => Some(key),
+            None if self.is_key_agreement_only() => Some(&self.key),
+            None => None,
+        }
+    }
+
+    /// Get the multibase-encoded public key of the DID
+    pub fn multikey(&self) -> &str {
+        &self.did[DID_KEY_PREFIX.len()..]
+    }
+
+    /// Produce the DID document for the identifier
+    pub fn to_document(&self) -> DidDocument {
+        let did = self.did();
+        let key_id = format!("{}#{}", did, self.multikey());
+        let mut methods = vec![multikey_method(&key_id, did, self.multikey())];
+        let mut doc = DidDocument::new();
+        doc.insert("@context".into(), json!([DID_CONTEXT, MULTIKEY_CONTEXT]));
+        doc.insert("id".into(), did.into());
+
+        let mut agreement = None;
+        if self.is_key_agreement_only() {
+            agreement = Some(key_id);
+        } else {
+            for rel in [
+                "authentication",
+                "assertionMethod",
+                "capabilityDelegation",
+                "capabilityInvocation",
+            ] {
+                doc.insert(rel.into(), json!([key_id]));
+            }
+            if let Some((x_multikey, _)) = &self.key_agreement {
+                let x_id = format!("{}#{}", did, x_multikey);
+                methods.push(multikey_method(&x_id, did, x_multikey));
+                agreement = Some(x_id);
+            }
+        }
+        if let Some(agreement) = agreement {
+            doc.insert("keyAgreement".into(), json!([agreement]));
+        }
+        doc.insert("verificationMethod".into(), Value::Array(methods));
+        doc
+    }
+
+    #[inline]
+    fn is_key_agreement_only(&self) -> bool {
+        matches!(self.key.algorithm(), KeyAlg::X25519 | KeyAlg::X448)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ed25519_did_key() {
+        // from the did:key test vectors
+        let did = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp";
+        let resolved = DidKey::resolve(&format!("{}#key-1", did)).unwrap();
+        assert_eq!(resolved.did(), did);
+        assert_eq!(resolved.public_key().algorithm(), KeyAlg::Ed25519);
+        assert_eq!(
+            resolved.key_agreement_key().unwrap().to_multikey().unwrap(),
+            "z6LShs9GGnqk85isEBzzshkuVWrVKsRp24GnDuHk8QWkARMW"
+        );
+
+        let doc = resolved.to_document();
+        assert_eq!(doc["id"], did);
+        assert_eq!(doc["verificationMethod"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            doc["authentication"],
+            json!([format!(
+                "{}#z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp",
+                did
+            )])
+        );
+        assert_eq!(
+            doc["keyAgreement"],
+            json!([format!(
+                "{}#z6LShs9GGnqk85isEBzzshkuVWrVKsRp24GnDuHk8QWkARMW",
+                did
+            )])
+        );
+
+        let keypair = LocalKey::generate_with_rng(KeyAlg::Ed25519, true).unwrap();
+        let did_key = DidKey::from_key(&keypair).unwrap();
+        assert!(did_key.did().starts_with("did:key:z6Mk"));
+        assert_eq!(
+            DidKey::resolve(did_key.did())
+                .unwrap()
+                .public_key()
+                .to_public_bytes()
+                .unwrap(),
+            keypair.to_public_bytes().unwrap()
+        );
+        assert!(DidKey::resolve("did:web:example.com").is_err());
+    }
+
+    #[test]
+    fn x25519_did_key() {
+        let keypair = LocalKey::generate_with_rng(KeyAlg::X25519, true).unwrap();
+        let did_key = DidKey::from_key(&keypair).unwrap();
+        assert!(did_key.did().starts_with("did:key:z6LS"));
+        assert!(did_key.key_agreement_key().is_some());
+        let doc = did_key.to_document();
+        assert!(doc.get("authentication").is_none());
+        assert_eq!(doc["verificationMethod"].as_array().unwrap().len(), 1);
+        assert_eq!(doc["keyAgreement"].as_array().unwrap().len(), 1);
+    }
+}
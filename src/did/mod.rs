@@ -0,0 +1,26 @@
+//! Support for decentralized identifiers (DIDs) derived from key material
+
+use serde_json::{json, Map, Value};
+
+pub mod key;
+
+pub mod peer;
+
+/// A resolved DID document
+pub type DidDocument = Map<String, Value>;
+
+/// The JSON-LD context of a DID document
+pub(crate) const DID_CONTEXT: &str = "https://www.w3.org/ns/did/v1";
+
+/// The JSON-LD context of the Multikey verification method type
+pub(crate) const MULTIKEY_CONTEXT: &str = "https://w3id.org/security/multikey/v1";
+
+/// Create a Multikey verification method
+pub(crate) fn multikey_method(id: &str, controller: &str, multikey: &str) -> Value {
+    json!({
+        "id": id,
+        "type": "Multikey",
+        "controller": controller,
+        "publicKeyMultibase": multikey,
+    })
+}
@@ -0,0 +1,525 @@
+//! `did:peer` numalgo 2 and 4 identifier encoding and resolution
+
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+use super::{multikey_method, DidDocument, DID_CONTEXT, MULTIKEY_CONTEXT};
+use crate::{
+    crypto::multikey::{decode_multibase, decode_multicodec, encode_multibase, encode_multicodec},
+    error::Error,
+    jose::{b64_decode, b64_encode},
+    kms::LocalKey,
+};
+
+const DID_PEER_2_PREFIX: &str = "did:peer:2";
+const DID_PEER_4_PREFIX: &str = "did:peer:4";
+
+/// Multicodec identifier for JSON
+const MULTICODEC_JSON: u64 = 0x0200;
+
+/// Multihash identifier and digest length for SHA-256
+const MULTIHASH_SHA256: [u8; 2] = [0x12, 0x20];
+
+// abbreviations of service parameter names used by did:peer:2
+const SERVICE_NAMES: &[(&str, &str)] = &[
+    ("type", "t"),
+    ("serviceEndpoint", "s"),
+    ("routingKeys", "r"),
+    ("accept", "a"),
+];
+
+// abbreviations of service types used by did:peer:2
+const SERVICE_TYPES: &[(&str, &str)] = &[("DIDCommMessaging", "dm")];
+
+/// The verification relationship of a key in a `did:peer` identifier
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PeerKeyPurpose {
+    /// The key is used for assertions
+    Assertion,
+    /// The key is used for key agreement
+    Encryption,
+    /// The key is used for authentication
+    Verification,
+    /// The key is used for capability invocation
+    CapabilityInvocation,
+    /// The key is used for capability delegation
+    CapabilityDelegation,
+}
+
+impl PeerKeyPurpose {
+    const ALL: [Self; 5] = [
+        Self::Assertion,
+        Self::Encryption,
+        Self::Verification,
+        Self::CapabilityInvocation,
+        Self::CapabilityDelegation,
+    ];
+
+    /// Get the purpose code used in `did:peer:2` identifiers
+    pub fn as_char(&self) -> char {
+        match self {
+            Self::Assertion => 'A',
+            Self::Encryption => 'E',
+            Self::Verification => 'V',
+            Self::CapabilityInvocation => 'I',
+            Self::CapabilityDelegation => 'D',
+        }
+    }
+
+    /// Look up a purpose by its `did:peer:2` code
+    pub fn from_char(code: char) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_char() == code)
+    }
+
+    /// Get the name of the DID document verification relationship
+    pub fn relationship(&self) -> &'static str {
+        match self {
+            Self::Assertion => "assertionMethod",
+            Self::Encryption => "keyAgreement",
+            Self::Verification => "authentication",
+            Self::CapabilityInvocation => "capabilityInvocation",
+            Self::CapabilityDelegation => "capabilityDelegation",
+        }
+    }
+}
+
+/// A builder for `did:peer` identifiers
+#[derive(Debug, Default)]
+pub struct DidPeerBuilder<'k> {
+    keys: Vec<(PeerKeyPurpose, &'k LocalKey)>,
+    services: Vec<Map<String, Value>>,
+}
+
+impl<'k> DidPeerBuilder<'k> {
+    /// Create a new, empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a public key with the given purpose
+    pub fn key(mut self, purpose: PeerKeyPurpose, key: &'k LocalKey) -> Self {
+        self.keys.push((purpose, key));
+        self
+    }
+
+    /// Add a service, such as a `DIDCommMessaging` service endpoint
+    pub fn service(mut self, service: Map<String, Value>) -> Self {
+        self.services.push(service);
+        self
+    }
+
+    /// Encode the keys and services as a `did:peer:2` identifier
+    pub fn to_did_peer_2(&self) -> Result<String, Error> {
+        let mut did = String::from(DID_PEER_2_PREFIX);
+        for (purpose, key) in &self.keys {
+            did.push('.');
+            did.push(purpose.as_char());
+            did.push_str(&key.to_multikey()?);
+        }
+        for service in &self.services {
+            let service = abbreviate_service(Value::Object(service.clone()), true);
+            let json = serde_json::to_vec(&service)
+                .map_err(err_map!(Unexpected, "Error encoding service"))?;
+            did.push_str(".S");
+            did.push_str(&b64_encode(json));
+        }
+        Ok(did)
+    }
+
+    /// Encode the keys and services as a long form `did:peer:4` identifier
+    pub fn to_did_peer_4(&self) -> Result<String, Error> {
+        let json = serde_json::to_vec(&self.input_document()?)
+            .map_err(err_map!(Unexpected, "Error encoding DID document"))?;
+        let encoded = encode_multibase(&encode_multicodec(MULTICODEC_JSON, &json));
+        Ok(format!("{}:{}", peer_4_short_form(&encoded), encoded))
+    }
+
+    fn input_document(&self) -> Result<DidDocument, Error> {
+        let mut doc = DidDocument::new();
+        doc.insert("@context".into(), json!([DID_CONTEXT, MULTIKEY_CONTEXT]));
+        let mut methods = Vec::with_capacity(self.keys.len());
+        for (idx, (purpose, key)) in self.keys.iter().enumerate() {
+            let id = format!("#key-{}", idx + 1);
+            methods.push(json!({
+                "id": id,
+                "type": "Multikey",
+                "publicKeyMultibase": key.to_multikey()?,
+            }));
+            add_relationship(&mut doc, *purpose, id);
+        }
+        doc.insert("verificationMethod".into(), Value::Array(methods));
+        add_services(&mut doc, self.services.iter().cloned());
+        Ok(doc)
+    }
+}
+
+/// A public key of a resolved `did:peer` identifier
+#[derive(Debug)]
+pub struct DidPeerKey {
+    /// The verification method identifier
+    pub id: String,
+    /// The verification relationships of the key
+    pub purposes: Vec<PeerKeyPurpose>,
+    /// The public key
+    pub key: LocalKey,
+}
+
+/// A resolved `did:peer` identifier
+#[derive(Debug)]
+pub struct DidPeer {
+    did: String,
+    document: DidDocument,
+    keys: Vec<DidPeerKey>,
+}
+
+impl DidPeer {
+    /// Resolve a `did:peer:2` or long form `did:peer:4` identifier
+    pub fn resolve(did: &str) -> Result<Self, Error> {
+        let document = if did.starts_with(DID_PEER_2_PREFIX) {
+            resolve_peer_2(did)?
+        } else if let Some(hash_doc) = did.strip_prefix(DID_PEER_4_PREFIX) {
+            let Some((_, encoded)) = hash_doc.split_once(':') else {
+                return Err(err_msg!(
+                    Unsupported,
+                    "Short form did:peer:4 identifiers cannot be resolved"
+                ));
+            };
+            resolve_peer_4(did, encoded)?
+        } else {
+            return Err(err_msg!(Input, "Unsupported did:peer identifier"));
+        };
+        let keys = load_keys(&document)?;
+        Ok(Self {
+            did: did.to_string(),
+            document,
+            keys,
+        })
+    }
+
+    /// Accessor for the DID
+    pub fn did(&self) -> &str {
+        &self.did
+    }
+
+    /// Get the short form of a `did:peer:4` identifier
+    pub fn short_form(&self) -> Option<&str> {
+        if self.did.starts_with(DID_PEER_4_PREFIX) {
+            self.did.rsplit_once(':').map(|(short, _)| short)
+        } else {
+            None
+        }
+    }
+
+    /// Accessor for the resolved DID document
+    pub fn document(&self) -> &DidDocument {
+        &self.document
+    }
+
+    /// Accessor for the public keys of the DID
+    pub fn keys(&self) -> &[DidPeerKey] {
+        &self.keys
+    }
+
+    /// Iterate the public keys with the given purpose
+    pub fn keys_for(&self, purpose: PeerKeyPurpose) -> impl Iterator<Item = &LocalKey> {
+        self.keys
+            .iter()
+            .filter(move |k| k.purposes.contains(&purpose))
+            .map(|k| &k.key)
+    }
+
+    /// Accessor for the services of the DID document
+    pub fn services(&self) -> &[Value] {
+        match self.document.get("service") {
+            Some(Value::Array(services)) => services,
+            _ => &[],
+        }
+    }
+}
+
+fn resolve_peer_2(did: &str) -> Result<DidDocument, Error> {
+    let mut doc = DidDocument::new();
+    doc.insert("@context".into(), json!([DID_CONTEXT, MULTIKEY_CONTEXT]));
+    doc.insert("id".into(), did.into());
+    let elements = &did[DID_PEER_2_PREFIX.len()..];
+    if !elements.starts_with('.') {
+        return Err(err_msg!(Input, "Invalid did:peer:2 identifier"));
+    }
+    let mut methods = Vec::new();
+    let mut services = Vec::new();
+    for element in elements[1..].split('.') {
+        let mut chars = element.chars();
+        let code = chars
+            .next()
+            .ok_or_else(|| err_msg!(Input, "Invalid did:peer:2 element"))?;
+        let value = chars.as_str();
+        if code == 'S' {
+            let service: Value = serde_json::from_slice(&b64_decode(value)?)
+                .map_err(err_map!(Input, "Invalid did:peer:2 service"))?;
+            match abbreviate_service(service, false) {
+                Value::Object(service) => services.push(service),
+                _ => return Err(err_msg!(Input, "Invalid did:peer:2 service")),
+            }
+        } else {
+            let purpose = PeerKeyPurpose::from_char(code)
+                .ok_or_else(|| err_msg!(Input, "Invalid did:peer:2 purpose code"))?;
+            let id = format!("#key-{}", methods.len() + 1);
+            methods.push(multikey_method(&id, did, value));
+            add_relationship(&mut doc, purpose, id);
+        }
+    }
+    doc.insert("verificationMethod".into(), Value::Array(methods));
+    add_services(&mut doc, services);
+    Ok(doc)
+}
+
+fn resolve_peer_4(did: &str, encoded: &str) -> Result<DidDocument, Error> {
+    let short = peer_4_short_form(encoded);
+    if did != format!("{}:{}", short, encoded) {
+        return Err(err_msg!(Input, "Invalid did:peer:4 hash"));
+    }
+    let decoded = decode_multibase(encoded)?;
+    let (MULTICODEC_JSON, json) = decode_multicodec(&decoded)? else {
+        return Err(err_msg!(Input, "Invalid did:peer:4 document encoding"));
+    };
+    let mut doc: DidDocument =
+        serde_json::from_slice(json).map_err(err_map!(Input, "Invalid did:peer:4 document"))?;
+    doc.insert("id".into(), did.into());
+    doc.insert("alsoKnownAs".into(), json!([short]));
+    if let Some(Value::Array(methods)) = doc.get_mut("verificationMethod") {
+        for method in methods.iter_mut().filter_map(Value::as_object_mut) {
+            method
+                .entry("controller")
+                .or_insert_with(|| did.to_string().into());
+        }
+    }
+    Ok(doc)
+}
+
+fn peer_4_short_form(encoded: &str) -> String {
+    let mut hash = MULTIHASH_SHA256.to_vec();
+    hash.extend_from_slice(&Sha256::digest(encoded.as_bytes()));
+    format!("{}{}", DID_PEER_4_PREFIX, encode_multibase(&hash))
+}
+
+fn add_relationship(doc: &mut DidDocument, purpose: PeerKeyPurpose, id: String) {
+    if let Value::Array(refs) = doc
+        .entry(purpose.relationship())
+        .or_insert_with(|| Value::Array(Vec::new()))
+    {
+        refs.push(id.into());
+    }
+}
+
+fn add_services(doc: &mut DidDocument, services: impl IntoIterator<Item = Map<String, Value>>) {
+    let services: Vec<Value> = services
+        .into_iter()
+        .enumerate()
+        .map(|(idx, mut service)| {
+            service.entry("id").or_insert_with(|| {
+                if idx == 0 {
+                    "#service".into()
+                } else {
+                    format!("#service-{}", idx).into()
+                }
+            });
+            Value::Object(service)
+        })
+        .collect();
+    if !services.is_empty() {
+        doc.insert("service".into(), Value::Array(services));
+    }
+}
+
+/// Abbreviate or expand the parameter names and types of a `did:peer:2` service
+fn abbreviate_service(value: Value, abbreviate: bool) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(name, value)| {
+                    let name = replace_abbreviation(SERVICE_NAMES, name, abbreviate);
+                    let value = match value {
+                        Value::String(ty) if name == "type" || name == "t" => {
+                            Value::String(replace_abbreviation(SERVICE_TYPES, ty, abbreviate))
+                        }
+                        value => abbreviate_service(value, abbreviate),
+                    };
+                    (name, value)
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|v| abbreviate_service(v, abbreviate))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+fn replace_abbreviation(table: &[(&str, &str)], value: String, abbreviate: bool) -> String {
+    table
+        .iter()
+        .find_map(|(full, short)| match abbreviate {
+            true if value == *full => Some(short.to_string()),
+            false if value == *short => Some(full.to_string()),
+            _ => None,
+        })
+        .unwrap_or(value)
+}
+
+fn load_keys(doc: &DidDocument) -> Result<Vec<DidPeerKey>, Error> {
+    let Some(Value::Array(methods)) = doc.get("verificationMethod") else {
+        return Ok(Vec::new());
+    };
+    let mut keys = Vec::with_capacity(methods.len());
+    for method in methods {
+        let id = method
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| err_msg!(Input, "Invalid verification method identifier"))?;
+        let key = if let Some(multikey) = method.get("publicKeyMultibase").and_then(Value::as_str) {
+            LocalKey::from_multikey(multikey)?
+        } else if let Some(jwk) = method.get("publicKeyJwk") {
+            LocalKey::from_jwk(&jwk.to_string())?
+        } else {
+            return Err(err_msg!(Unsupported, "Unsupported verification method"));
+        };
+        let purposes = PeerKeyPurpose::ALL
+            .into_iter()
+            .filter(|purpose| match doc.get(purpose.relationship()) {
+                Some(Value::Array(refs)) => refs.iter().any(|r| r.as_str() == Some(id)),
+                _ => false,
+            })
+            .collect();
+        keys.push(DidPeerKey {
+            id: id.to_string(),
+            purposes,
+            key,
+        });
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kms::KeyAlg;
+
+    fn didcomm_service() -> Map<String, Value> {
+        match json!({
+            "type": "DIDCommMessaging",
+            "serviceEndpoint": {
+                "uri": "https://example.com/endpoint",
+                "routingKeys": ["did:example:somemediator#somekey"],
+                "accept": ["didcomm/v2"],
+            },
+        }) {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn resolve_peer_2_example() {
+        // from the did:peer specification
+        let did = "did:peer:2\
+            .Ez6LSbysY2xFMRpGMhb7tFTLMpeuPRaqaWM1yECx2AtzE3KCc\
+            .Vz6MkqRYqQiSgvZQdnBytw86Qbs2ZWUkGv22od935YF4s8M7V\
+            .SeyJ0IjoiZG0iLCJzIjp7InVyaSI6Imh0dHA6Ly9leGFtcGxlLmNvbS9kaWRjb21tIiwiYSI6WyJkaWRjb21tL3YyIl0sInIiOlsiZGlkOmV4YW1wbGU6MTIzNDU2Nzg5YWJjZGVmZ2hpI2tleS0xIl19fQ";
+        let resolved = DidPeer::resolve(did).unwrap();
+        assert_eq!(resolved.did(), did);
+        assert_eq!(resolved.keys().len(), 2);
+        assert_eq!(resolved.keys()[0].id, "#key-1");
+        assert_eq!(resolved.keys()[0].key.algorithm(), KeyAlg::X25519);
+        assert_eq!(
+            resolved.keys()[0].purposes,
+            vec![PeerKeyPurpose::Encryption]
+        );
+        assert_eq!(resolved.keys()[1].key.algorithm(), KeyAlg::Ed25519);
+        assert_eq!(resolved.keys_for(PeerKeyPurpose::Verification).count(), 1);
+        assert_eq!(
+            resolved.services(),
+            &[json!({
+                "id": "#service",
+                "type": "DIDCommMessaging",
+                "serviceEndpoint": {
+                    "uri": "http://example.com/didcomm",
+                    "accept": ["didcomm/v2"],
+                    "routingKeys": ["did:example:123456789abcdefghi#key-1"],
+                },
+            })]
+        );
+    }
+
+    #[test]
+    fn peer_2_round_trip() {
+        let sign_key = LocalKey::generate_with_rng(KeyAlg::Ed25519, true).unwrap();
+        let enc_key = sign_key.convert_key(KeyAlg::X25519).unwrap();
+        let did = DidPeerBuilder::new()
+            .key(PeerKeyPurpose::Encryption, &enc_key)
+            .key(PeerKeyPurpose::Verification, &sign_key)
+            .service(didcomm_service())
+            .to_did_peer_2()
+            .unwrap();
+        let resolved = DidPeer::resolve(&did).unwrap();
+        let enc = resolved
+            .keys_for(PeerKeyPurpose::Encryption)
+            .next()
+            .unwrap();
+        assert_eq!(
+            enc.to_public_bytes().unwrap(),
+            enc_key.to_public_bytes().unwrap()
+        );
+        assert_eq!(resolved.services().len(), 1);
+        assert_eq!(
+            resolved.services()[0]["serviceEndpoint"]["uri"],
+            "https://example.com/endpoint"
+        );
+
+        assert!(DidPeer::resolve("did:peer:2").is_err());
+        assert!(
+            DidPeer::resolve("did:peer:2.Xz6MkqRYqQiSgvZQdnBytw86Qbs2ZWUkGv22od935YF4s8M7V")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn peer_4_round_trip() {
+        let sign_key = LocalKey::generate_with_rng(KeyAlg::Ed25519, true).unwrap();
+        let enc_key = LocalKey::generate_with_rng(KeyAlg::X25519, true).unwrap();
+        let did = DidPeerBuilder::new()
+            .key(PeerKeyPurpose::Verification, &sign_key)
+            .key(PeerKeyPurpose::Encryption, &enc_key)
+            .service(didcomm_service())
+            .to_did_peer_4()
+            .unwrap();
+        let resolved = DidPeer::resolve(&did).unwrap();
+        let short = resolved.short_form().unwrap();
+        assert!(short.starts_with("did:peer:4zQm"));
+        assert_eq!(resolved.document()["alsoKnownAs"], json!([short]));
+        assert_eq!(resolved.document()["id"], did);
+        assert_eq!(resolved.keys().len(), 2);
+        let sign = resolved
+            .keys_for(PeerKeyPurpose::Verification)
+            .next()
+            .unwrap();
+        assert_eq!(
+            sign.to_public_bytes().unwrap(),
+            sign_key.to_public_bytes().unwrap()
+        );
+        assert_eq!(resolved.services()[0]["id"], "#service");
+
+        // the short form cannot be resolved
+        assert!(DidPeer::resolve(short).is_err());
+        // the hash must match the encoded document
+        let (_, encoded) = did.rsplit_once(':').unwrap();
+        let other = DidPeerBuilder::new()
+            .key(PeerKeyPurpose::Verification, &enc_key)
+            .to_did_peer_4()
+            .unwrap();
+        let (other_short, _) = other.rsplit_once(':').unwrap();
+        assert!(DidPeer::resolve(&format!("{}:{}", other_short, encoded)).is_err());
+    }
+}
@@ -13,6 +13,9 @@ pub enum ErrorKind {
     /// The store backend was too busy to handle the request
     Busy,
 
+    /// A conditional update failed because the record was modified
+    Conflict,
+
     /// A custom error type for external integrations
     Custom,
 
@@ -41,6 +44,7 @@ impl ErrorKind {
         match self {
             Self::Backend => "Backend error",
             Self::Busy => "Busy",
+            Self::Conflict => "Conflict",
             Self::Custom => "Custom error",
             Self::Duplicate => "Duplicate",
             Self::Encryption => "Encryption error",
@@ -155,6 +159,7 @@ impl From<StorageError> for Error {
         let kind = match kind {
             StorageErrorKind::Backend => ErrorKind::Backend,
             StorageErrorKind::Busy => ErrorKind::Busy,
+            StorageErrorKind::Conflict => ErrorKind::Conflict,
             StorageErrorKind::Custom => ErrorKind::Custom,
             StorageErrorKind::Duplicate => ErrorKind::Duplicate,
             StorageErrorKind::Encryption => ErrorKind::Encryption,
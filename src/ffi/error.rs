@@ -21,6 +21,7 @@ pub enum ErrorCode {
     NotFound = 6,
     Unexpected = 7,
     Unsupported = 8,
+    Conflict = 9,
     Custom = 100,
 }
 
@@ -29,6 +30,7 @@ impl From<ErrorKind> for ErrorCode {
         match kind {
             ErrorKind::Backend => ErrorCode::Backend,
             ErrorKind::Busy => ErrorCode::Busy,
+            ErrorKind::Conflict => ErrorCode::Conflict,
             ErrorKind::Custom => ErrorCode::Custom,
             ErrorKind::Duplicate => ErrorCode::Duplicate,
             ErrorKind::Encryption => ErrorCode::Encryption,
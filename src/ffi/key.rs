@@ -6,7 +6,7 @@ use super::{
 };
 use crate::kms::{
     crypto_box, crypto_box_open, crypto_box_random_nonce, crypto_box_seal, crypto_box_seal_open,
-    derive_key_ecdh_1pu, derive_key_ecdh_es, KeyAlg, KeyBackend, LocalKey,
+    derive_bytes_ecdh_es, derive_key_ecdh_1pu, derive_key_ecdh_es, KeyAlg, KeyBackend, LocalKey,
 };
 use ffi_support::{rust_string_to_c, ByteBuffer, FfiStr};
 use std::{os::raw::c_char, str::FromStr};
@@ -541,6 +541,38 @@ pub extern "C" fn askar_key_derive_ecdh_es(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn askar_key_derive_ecdh_es_bytes(
+    ephem_key: LocalKeyHandle,
+    recip_key: LocalKeyHandle,
+    alg_id: ByteBuffer,
+    apu: ByteBuffer,
+    apv: ByteBuffer,
+    output_len: i64,
+    receive: i8,
+    out: *mut SecretBuffer,
+) -> ErrorCode {
+    catch_err! {
+        trace!("ECDH-ES bytes: {}", output_len);
+        check_useful_c_ptr!(out);
+        let output_len = usize::try_from(output_len)
+            .map_err(|_| err_msg!(Input, "Invalid output length"))?;
+        let ephem_key = ephem_key.load()?;
+        let recip_key = recip_key.load()?;
+        let output = derive_bytes_ecdh_es(
+            &ephem_key,
+            &recip_key,
+            alg_id.as_slice(),
+            apu.as_slice(),
+            apv.as_slice(),
+            output_len,
+            receive == 1
+        )?;
+        unsafe { *out = SecretBuffer::from_secret(output) };
+        Ok(ErrorCode::Success)
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn askar_key_derive_ecdh_1pu(
     alg: FfiStr<'_>,
@@ -553,11 +553,9 @@ pub extern "C" fn askar_scan_start(
     cb: Option<extern "C" fn(cb_id: CallbackId, err: ErrorCode, handle: ScanHandle)>,
     cb_id: CallbackId,
 ) -> ErrorCode {
-    let order_by_str = order_by.as_opt_str().map(|s| s.to_lowercase());
-    let order_by = match order_by_str.as_deref() {
-        Some("id") => Some(OrderBy::Id),
-        Some(_) => return ErrorCode::Unsupported,
-        None => None,
+    let order_by = match order_by.as_opt_str().map(OrderBy::from_str).transpose() {
+        Ok(order_by) => order_by,
+        Err(_) => return ErrorCode::Unsupported,
     };
     let descending = descending != 0; // Convert to bool
 
@@ -579,7 +577,7 @@ pub extern "C" fn askar_scan_start(
         spawn_ok(async move {
             let result = async {
                 let store = handle.load().await?;
-                let scan = store.scan(profile, category, tag_filter, Some(offset), if limit < 0 { None }else {Some(limit)}, order_by, descending).await?;
+                let scan = store.scan(profile, category, tag_filter, Some(offset), if limit < 0 { None }else {Some(limit)}, order_by, descending, false).await?;
                 Ok(FFI_SCANS.insert(handle, scan).await)
             }.await;
             cb.resolve(result);
@@ -695,7 +693,7 @@ pub extern "C" fn askar_session_count(
         spawn_ok(async move {
             let result = async {
                 let mut session = FFI_SESSIONS.borrow(handle).await?;
-                session.count(category.as_deref(), tag_filter).await
+                session.count(category.as_deref(), tag_filter, false).await
             }.await;
             cb.resolve(result);
         });
@@ -750,11 +748,9 @@ pub extern "C" fn askar_session_fetch_all(
     cb: Option<extern "C" fn(cb_id: CallbackId, err: ErrorCode, results: EntryListHandle)>,
     cb_id: CallbackId,
 ) -> ErrorCode {
-    let order_by_str = order_by.as_opt_str().map(|s| s.to_lowercase());
-    let order_by = match order_by_str.as_deref() {
-        Some("id") => Some(OrderBy::Id),
-        Some(_) => return ErrorCode::Unsupported,
-        None => None,
+    let order_by = match order_by.as_opt_str().map(OrderBy::from_str).transpose() {
+        Ok(order_by) => order_by,
+        Err(_) => return ErrorCode::Unsupported,
     };
     let descending = descending != 0; // Convert to bool
 
@@ -835,6 +831,7 @@ pub extern "C" fn askar_session_update(
             0 => EntryOperation::Insert,
             1 => EntryOperation::Replace,
             2 => EntryOperation::Remove,
+            3 => EntryOperation::InsertOrReplace,
             _ => return Err(err_msg!("Invalid update operation"))
         };
         let category = category.into_opt_string().ok_or_else(|| err_msg!("Entry category not provided"))?;
@@ -0,0 +1,933 @@
+//! JSON Web Encryption (RFC 7516) using ECDH-ES and ECDH-1PU key agreement
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    str::FromStr,
+};
+
+use serde_json::Value;
+
+use super::{
+    b64_decode, b64_encode, decode_header, encode_header, header_str, merge_headers, Header,
+};
+use crate::{
+    crypto::alg::{AesTypes, Chacha20Types, KeyAlg},
+    error::Error,
+    kms::{derive_key_ecdh_1pu, derive_key_ecdh_es, LocalKey, SecretBytes},
+};
+
+/// Supported JWE key management algorithms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JweAlg {
+    /// Direct key agreement using ECDH-ES
+    EcdhEs,
+    /// ECDH-ES key agreement with AES-128 key wrapping
+    EcdhEsA128Kw,
+    /// ECDH-ES key agreement with AES-192 key wrapping
+    EcdhEsA192Kw,
+    /// ECDH-ES key agreement with AES-256 key wrapping
+    EcdhEsA256Kw,
+    /// ECDH-1PU key agreement with AES-128 key wrapping
+    Ecdh1PuA128Kw,
+    /// ECDH-1PU key agreement with AES-192 key wrapping
+    Ecdh1PuA192Kw,
+    /// ECDH-1PU key agreement with AES-256 key wrapping
+    Ecdh1PuA256Kw,
+}
+
+impl JweAlg {
+    /// Get the JWA identifier for the algorithm
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::EcdhEs => "ECDH-ES",
+            Self::EcdhEsA128Kw => "ECDH-ES+A128KW",
+            Self::EcdhEsA192Kw => "ECDH-ES+A192KW",
+            Self::EcdhEsA256Kw => "ECDH-ES+A256KW",
+            Self::Ecdh1PuA128Kw => "ECDH-1PU+A128KW",
+            Self::Ecdh1PuA192Kw => "ECDH-1PU+A192KW",
+            Self::Ecdh1PuA256Kw => "ECDH-1PU+A256KW",
+        }
+    }
+
+    /// Check whether the algorithm authenticates the sender
+    pub fn is_authenticated(&self) -> bool {
+        matches!(
+            self,
+            Self::Ecdh1PuA128Kw | Self::Ecdh1PuA192Kw | Self::Ecdh1PuA256Kw
+        )
+    }
+
+    fn wrap_alg(&self) -> Option<KeyAlg> {
+        match self {
+            Self::EcdhEs => None,
+            Self::EcdhEsA128Kw | Self::Ecdh1PuA128Kw => Some(KeyAlg::Aes(AesTypes::A128Kw)),
+            Self::EcdhEsA192Kw | Self::Ecdh1PuA192Kw => Some(KeyAlg::Aes(AesTypes::A192Kw)),
+            Self::EcdhEsA256Kw | Self::Ecdh1PuA256Kw => Some(KeyAlg::Aes(AesTypes::A256Kw)),
+        }
+    }
+}
+
+impl FromStr for JweAlg {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "ECDH-ES" => Self::EcdhEs,
+            "ECDH-ES+A128KW" => Self::EcdhEsA128Kw,
+            "ECDH-ES+A192KW" => Self::EcdhEsA192Kw,
+            "ECDH-ES+A256KW" => Self::EcdhEsA256Kw,
+            "ECDH-1PU+A128KW" => Self::Ecdh1PuA128Kw,
+            "ECDH-1PU+A192KW" => Self::Ecdh1PuA192Kw,
+            "ECDH-1PU+A256KW" => Self::Ecdh1PuA256Kw,
+            _ => return Err(err_msg!(Unsupported, "Unsupported JWE algorithm: {}", s)),
+        })
+    }
+}
+
+/// Supported JWE content encryption algorithms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JweEnc {
+    /// AES-128-GCM
+    A128Gcm,
+    /// AES-256-GCM
+    A256Gcm,
+    /// AES-128-CBC with HMAC-SHA256
+    A128CbcHs256,
+    /// AES-256-CBC with HMAC-SHA512
+    A256CbcHs512,
+    /// XChaCha20-Poly1305
+    XC20P,
+}
+
+impl JweEnc {
+    /// Get the JWA identifier for the algorithm
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::A128Gcm => "A128GCM",
+            Self::A256Gcm => "A256GCM",
+            Self::A128CbcHs256 => "A128CBC-HS256",
+            Self::A256CbcHs512 => "A256CBC-HS512",
+            Self::XC20P => "XC20P",
+        }
+    }
+
+    /// Get the content encryption key algorithm
+    pub fn key_alg(&self) -> KeyAlg {
+        match self {
+            Self::A128Gcm => KeyAlg::Aes(AesTypes::A128Gcm),
+            Self::A256Gcm => KeyAlg::Aes(AesTypes::A256Gcm),
+            Self::A128CbcHs256 => KeyAlg::Aes(AesTypes::A128CbcHs256),
+            Self::A256CbcHs512 => KeyAlg::Aes(AesTypes::A256CbcHs512),
+            Self::XC20P => KeyAlg::Chacha20(Chacha20Types::XC20P),
+        }
+    }
+}
+
+impl FromStr for JweEnc {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "A128GCM" => Self::A128Gcm,
+            "A256GCM" => Self::A256Gcm,
+            "A128CBC-HS256" => Self::A128CbcHs256,
+            "A256CBC-HS512" => Self::A256CbcHs512,
+            "XC20P" => Self::XC20P,
+            _ => {
+                return Err(err_msg!(
+                    Unsupported,
+                    "Unsupported JWE content encryption: {}",
+                    s
+                ))
+            }
+        })
+    }
+}
+
+/// The per-recipient values of a JWE
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JweRecipient {
+    /// The unprotected per-recipient header
+    pub header: Option<Header>,
+    /// The encrypted content encryption key
+    pub encrypted_key: Vec<u8>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct JweJson {
+    protected: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unprotected: Option<Header>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recipients: Option<Vec<JweJsonRecipient>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    header: Option<Header>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aad: Option<String>,
+    iv: String,
+    ciphertext: String,
+    tag: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct JweJsonRecipient {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    header: Option<Header>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted_key: Option<String>,
+}
+
+/// An encrypted JWE message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Jwe {
+    protected: String,
+    unprotected: Option<Header>,
+    recipients: Vec<JweRecipient>,
+    aad: Option<String>,
+    iv: Vec<u8>,
+    ciphertext: Vec<u8>,
+    tag: Vec<u8>,
+}
+
+impl Jwe {
+    /// Parse a JWE in the compact serialization
+    pub fn from_compact(jwe: &str) -> Result<Self, Error> {
+        let parts = jwe.split('.').collect::<Vec<_>>();
+        if parts.len() != 5 {
+            return Err(err_msg!(Input, "Invalid compact JWE"));
+        }
+        Ok(Self {
+            protected: parts[0].to_string(),
+            unprotected: None,
+            recipients: vec![JweRecipient {
+                header: None,
+                encrypted_key: b64_decode(parts[1])?,
+            }],
+            aad: None,
+            iv: b64_decode(parts[2])?,
+            ciphertext: b64_decode(parts[3])?,
+            tag: b64_decode(parts[4])?,
+        })
+    }
+
+    /// Parse a JWE in the flattened or general JSON serialization
+    pub fn from_json(jwe: &str) -> Result<Self, Error> {
+        let json: JweJson =
+            serde_json::from_str(jwe).map_err(err_map!(Input, "Invalid JSON JWE"))?;
+        let recipients = match json.recipients {
+            Some(recipients) => {
+                if json.header.is_some() || json.encrypted_key.is_some() {
+                    return Err(err_msg!(Input, "Invalid JSON JWE"));
+                }
+                recipients
+            }
+            None => vec![JweJsonRecipient {
+                header: json.header,
+                encrypted_key: json.encrypted_key,
+            }],
+        };
+        if recipients.is_empty() {
+            return Err(err_msg!(Input, "Invalid JSON JWE: no recipients"));
+        }
+        Ok(Self {
+            protected: json.protected,
+            unprotected: json.unprotected,
+            recipients: recipients
+                .into_iter()
+                .map(|r| {
+                    Ok(JweRecipient {
+                        header: r.header,
+                        encrypted_key: r
+                            .encrypted_key
+                            .as_deref()
+                            .map(b64_decode)
+                            .transpose()?
+                            .unwrap_or_default(),
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+            aad: json.aad,
+            iv: b64_decode(&json.iv)?,
+            ciphertext: b64_decode(&json.ciphertext)?,
+            tag: b64_decode(&json.tag)?,
+        })
+    }
+
+    /// Produce the compact serialization of the JWE
+    pub fn to_compact(&self) -> Result<String, Error> {
+        let recipient = match self.recipients.as_slice() {
+            [recipient] if recipient.header.is_none() => recipient,
+            _ => {
+                return Err(err_msg!(
+                    Input,
+                    "Compact serialization requires a single recipient without unprotected headers"
+                ))
+            }
+        };
+        if self.unprotected.is_some() || self.aad.is_some() {
+            return Err(err_msg!(
+                Input,
+                "Compact serialization does not support unprotected headers or AAD"
+            ));
+        }
+        Ok(format!(
+            "{}.{}.{}.{}.{}",
+            self.protected,
+            b64_encode(&recipient.encrypted_key),
+            b64_encode(&self.iv),
+            b64_encode(&self.ciphertext),
+            b64_encode(&self.tag)
+        ))
+    }
+
+    /// Produce the flattened JSON serialization of the JWE
+    pub fn to_flattened_json(&self) -> Result<String, Error> {
+        let recipient = match self.recipients.as_slice() {
+            [recipient] => recipient,
+            _ => {
+                return Err(err_msg!(
+                    Input,
+                    "Flattened serialization requires a single recipient"
+                ))
+            }
+        };
+        let mut json = self.to_json_parts();
+        json.header = recipient.header.clone();
+        json.encrypted_key = encode_key(&recipient.encrypted_key);
+        serde_json::to_string(&json).map_err(err_map!(Unexpected, "Error encoding JWE"))
+    }
+
+    /// Produce the general JSON serialization of the JWE
+    pub fn to_general_json(&self) -> Result<String, Error> {
+        let mut json = self.to_json_parts();
+        json.recipients = Some(
+            self.recipients
+                .iter()
+                .map(|r| JweJsonRecipient {
+                    header: r.header.clone(),
+                    encrypted_key: encode_key(&r.encrypted_key),
+                })
+                .collect(),
+        );
+        serde_json::to_string(&json).map_err(err_map!(Unexpected, "Error encoding JWE"))
+    }
+
+    fn to_json_parts(&self) -> JweJson {
+        JweJson {
+            protected: self.protected.clone(),
+            unprotected: self.unprotected.clone(),
+            recipients: None,
+            header: None,
+            encrypted_key: None,
+            aad: self.aad.clone(),
+            iv: b64_encode(&self.iv),
+            ciphertext: b64_encode(&self.ciphertext),
+            tag: b64_encode(&self.tag),
+        }
+    }
+
+    /// Decode the protected header
+    pub fn protected_header(&self) -> Result<Header, Error> {
+        decode_header(&self.protected)
+    }
+
+    /// Accessor for the shared unprotected header
+    pub fn unprotected_header(&self) -> Option<&Header> {
+        self.unprotected.as_ref()
+    }
+
+    /// Accessor for the recipients of the JWE
+    pub fn recipients(&self) -> &[JweRecipient] {
+        &self.recipients
+    }
+
+    /// Decode the additional authenticated data, if any
+    pub fn aad(&self) -> Result<Option<Vec<u8>>, Error> {
+        self.aad.as_deref().map(b64_decode).transpose()
+    }
+
+    fn encryption_aad(&self) -> String {
+        encryption_aad(&self.protected, self.aad.as_deref())
+    }
+}
+
+impl FromStr for Jwe {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim_start().starts_with('{') {
+            Self::from_json(s)
+        } else {
+            Self::from_compact(s)
+        }
+    }
+}
+
+fn encode_key(key: &[u8]) -> Option<String> {
+    if key.is_empty() {
+        None
+    } else {
+        Some(b64_encode(key))
+    }
+}
+
+fn encryption_aad(protected: &str, aad: Option<&str>) -> String {
+    match aad {
+        Some(aad) => format!("{}.{}", protected, aad),
+        None => protected.to_string(),
+    }
+}
+
+fn decode_optional(header: &Header, name: &str) -> Result<Vec<u8>, Error> {
+    if header.contains_key(name) {
+        b64_decode(header_str(header, name)?)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// A builder for an encrypted JWE message
+#[derive(Debug)]
+pub struct JweBuilder<'b> {
+    alg: JweAlg,
+    enc: JweEnc,
+    protected: Header,
+    unprotected: Option<Header>,
+    aad: Option<&'b [u8]>,
+    apu: Option<&'b [u8]>,
+    apv: Option<&'b [u8]>,
+    sender: Option<(&'b str, &'b LocalKey)>,
+    recipients: Vec<(&'b str, &'b LocalKey)>,
+}
+
+impl<'b> JweBuilder<'b> {
+    /// Create a new JWE builder
+    pub fn new(alg: JweAlg, enc: JweEnc) -> Self {
+        Self {
+            alg,
+            enc,
+            protected: Header::new(),
+            unprotected: None,
+            aad: None,
+            apu: None,
+            apv: None,
+            sender: None,
+            recipients: Vec::new(),
+        }
+    }
+
+    /// Add a parameter to the protected header
+    pub fn protected_header(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.protected.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Add a parameter to the shared unprotected header
+    pub fn unprotected_header(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.unprotected
+            .get_or_insert_with(Header::new)
+            .insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Set the additional authenticated data
+    pub fn aad(mut self, aad: &'b [u8]) -> Self {
+        self.aad = Some(aad);
+        self
+    }
+
+    /// Set the agreement PartyUInfo value
+    pub fn apu(mut self, apu: &'b [u8]) -> Self {
+        self.apu = Some(apu);
+        self
+    }
+
+    /// Set the agreement PartyVInfo value
+    pub fn apv(mut self, apv: &'b [u8]) -> Self {
+        self.apv = Some(apv);
+        self
+    }
+
+    /// Set the sender key for authenticated encryption
+    pub fn sender(mut self, skid: &'b str, key: &'b LocalKey) -> Self {
+        self.sender = Some((skid, key));
+        self
+    }
+
+    /// Add a recipient key
+    pub fn recipient(mut self, kid: &'b str, key: &'b LocalKey) -> Self {
+        self.recipients.push((kid, key));
+        self
+    }
+
+    /// Encrypt a message for the recipients
+    ///
+    /// When there is a single recipient and no unprotected header or AAD, the
+    /// recipient parameters are placed in the protected header so that the
+    /// result may be represented in the compact serialization.
+    pub fn encrypt(&self, message: &[u8]) -> Result<Jwe, Error> {
+        let alg = self.alg;
+        if self.recipients.is_empty() {
+            return Err(err_msg!(Input, "No recipients provided for JWE"));
+        }
+        if alg.wrap_alg().is_none() && self.recipients.len() != 1 {
+            return Err(err_msg!(
+                Input,
+                "Direct key agreement requires a single recipient"
+            ));
+        }
+        if alg.is_authenticated() != self.sender.is_some() {
+            return Err(err_msg!(
+                Input,
+                "A sender key must be provided for authenticated encryption only"
+            ));
+        }
+        let apu = self.apu.unwrap_or_default();
+        let apv = self.apv.unwrap_or_default();
+
+        let mut protected = self.protected.clone();
+        protected.insert("alg".to_string(), alg.as_str().into());
+        protected.insert("enc".to_string(), self.enc.as_str().into());
+        if let Some(apu) = self.apu {
+            protected.insert("apu".to_string(), b64_encode(apu).into());
+        }
+        if let Some(apv) = self.apv {
+            protected.insert("apv".to_string(), b64_encode(apv).into());
+        }
+        if let Some((skid, _)) = self.sender {
+            protected.insert("skid".to_string(), skid.into());
+        }
+
+        let mut ephemeral = Vec::with_capacity(self.recipients.len());
+        let mut headers = Vec::with_capacity(self.recipients.len());
+        for (kid, key) in self.recipients.iter() {
+            let ephem = LocalKey::generate_with_rng(key.algorithm(), true)?;
+            let epk: Value = serde_json::from_str(&ephem.to_jwk_public(None)?)
+                .map_err(err_map!(Unexpected, "Error encoding ephemeral key"))?;
+            let mut header = Header::new();
+            header.insert("kid".to_string(), (*kid).into());
+            header.insert("epk".to_string(), epk);
+            ephemeral.push(ephem);
+            headers.push(Some(header));
+        }
+        if headers.len() == 1 && self.unprotected.is_none() && self.aad.is_none() {
+            protected.extend(headers[0].take().unwrap());
+        }
+        for header in headers.iter() {
+            merge_headers([Some(&protected), self.unprotected.as_ref(), header.as_ref()])?;
+        }
+
+        let protected = encode_header(&protected)?;
+        let aad = self.aad.map(b64_encode);
+        let enc_aad = encryption_aad(&protected, aad.as_deref());
+
+        let cek = if alg.wrap_alg().is_some() {
+            LocalKey::generate_with_rng(self.enc.key_alg(), true)?
+        } else {
+            derive_key_ecdh_es(
+                self.enc.key_alg(),
+                &ephemeral[0],
+                self.recipients[0].1,
+                self.enc.as_str().as_bytes(),
+                apu,
+                apv,
+                false,
+            )?
+        };
+        let encrypted = cek.aead_encrypt(message, &[], enc_aad.as_bytes())?;
+
+        let mut recipients = Vec::with_capacity(self.recipients.len());
+        for ((ephem, (_, recip_key)), header) in
+            ephemeral.iter().zip(self.recipients.iter()).zip(headers)
+        {
+            let encrypted_key = if let Some(wrap_alg) = alg.wrap_alg() {
+                let kek = if let Some((_, sender_key)) = self.sender {
+                    derive_key_ecdh_1pu(
+                        wrap_alg,
+                        ephem,
+                        sender_key,
+                        recip_key,
+                        alg.as_str().as_bytes(),
+                        apu,
+                        apv,
+                        encrypted.tag(),
+                        false,
+                    )?
+                } else {
+                    derive_key_ecdh_es(
+                        wrap_alg,
+                        ephem,
+                        recip_key,
+                        alg.as_str().as_bytes(),
+                        apu,
+                        apv,
+                        false,
+                    )?
+                };
+                kek.wrap_key(&cek, &[])?.into_vec()
+            } else {
+                Vec::new()
+            };
+            recipients.push(JweRecipient {
+                header,
+                encrypted_key,
+            });
+        }
+
+        Ok(Jwe {
+            protected,
+            unprotected: self.unprotected.clone(),
+            recipients,
+            aad,
+            iv: encrypted.nonce().to_vec(),
+            ciphertext: encrypted.ciphertext().to_vec(),
+            tag: encrypted.tag().to_vec(),
+        })
+    }
+}
+
+/// A request to resolve a key when decrypting a JWE
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JweKeyLookup<'a> {
+    /// Resolve a recipient private key by its key identifier
+    Recipient(&'a str),
+    /// Resolve a sender public key by its key identifier
+    Sender(&'a str),
+}
+
+/// The result of decrypting a JWE
+#[derive(Debug)]
+pub struct DecryptedJwe {
+    /// The decrypted message
+    pub plaintext: SecretBytes,
+    /// The combined protected, unprotected, and recipient header
+    pub header: Header,
+    /// The key identifier of the recipient
+    pub kid: String,
+    /// The key identifier of the sender, for authenticated encryption
+    pub skid: Option<String>,
+}
+
+/// A decryptor for JWE messages, resolving keys using a callback
+pub struct JweDecryptor<F> {
+    resolve: F,
+}
+
+impl<F> JweDecryptor<F>
+where
+    F: FnMut(JweKeyLookup<'_>) -> Result<Option<LocalKey>, Error>,
+{
+    /// Create a new JWE decryptor
+    ///
+    /// The callback is invoked with the key identifier of each recipient until
+    /// a recipient key is found, and with the sender key identifier for
+    /// authenticated encryption.
+    pub fn new(resolve: F) -> Self {
+        Self { resolve }
+    }
+
+    /// Parse and decrypt a JWE in the compact or JSON serialization
+    pub fn decrypt_str(&mut self, jwe: &str) -> Result<DecryptedJwe, Error> {
+        self.decrypt(&Jwe::from_str(jwe)?)
+    }
+
+    /// Decrypt a JWE
+    pub fn decrypt(&mut self, jwe: &Jwe) -> Result<DecryptedJwe, Error> {
+        let protected = jwe.protected_header()?;
+        for recipient in jwe.recipients.iter() {
+            let header = merge_headers([
+                Some(&protected),
+                jwe.unprotected.as_ref(),
+                recipient.header.as_ref(),
+            ])?;
+            let kid = match header.get("kid") {
+                Some(Value::String(kid)) => kid.as_str(),
+                _ => continue,
+            };
+            if let Some(recip_key) = (self.resolve)(JweKeyLookup::Recipient(kid))? {
+                let kid = kid.to_string();
+                return self.decrypt_recipient(jwe, recipient, header, kid, &recip_key);
+            }
+        }
+        Err(err_msg!(NotFound, "No matching JWE recipient key found"))
+    }
+
+    /// Decrypt a JWE using a recipient key already held by the caller
+    ///
+    /// The callback is only invoked to resolve the sender key.
+    pub fn decrypt_with_key(
+        &mut self,
+        jwe: &Jwe,
+        kid: &str,
+        recip_key: &LocalKey,
+    ) -> Result<DecryptedJwe, Error> {
+        let protected = jwe.protected_header()?;
+        for recipient in jwe.recipients.iter() {
+            let header = merge_headers([
+                Some(&protected),
+                jwe.unprotected.as_ref(),
+                recipient.header.as_ref(),
+            ])?;
+            if matches!(header.get("kid"), Some(Value::String(k)) if k == kid) {
+                return self.decrypt_recipient(jwe, recipient, header, kid.to_string(), recip_key);
+            }
+        }
+        Err(err_msg!(NotFound, "No matching JWE recipient found"))
+    }
+
+    fn decrypt_recipient(
+        &mut self,
+        jwe: &Jwe,
+        recipient: &JweRecipient,
+        header: Header,
+        kid: String,
+        recip_key: &LocalKey,
+    ) -> Result<DecryptedJwe, Error> {
+        let alg = JweAlg::from_str(header_str(&header, "alg")?)?;
+        let enc = JweEnc::from_str(header_str(&header, "enc")?)?;
+        let ephem = match header.get("epk") {
+            Some(epk @ Value::Object(_)) => LocalKey::from_jwk(&epk.to_string())?,
+            _ => return Err(err_msg!(Input, "Missing or invalid header parameter: epk")),
+        };
+        let apu = decode_optional(&header, "apu")?;
+        let apv = decode_optional(&header, "apv")?;
+        let sender = if alg.is_authenticated() {
+            let skid = header_str(&header, "skid")?;
+            let sender_key = (self.resolve)(JweKeyLookup::Sender(skid))?
+                .ok_or_else(|| err_msg!(NotFound, "JWE sender key not found"))?;
+            Some((skid.to_string(), sender_key))
+        } else {
+            None
+        };
+
+        let cek = if let Some(wrap_alg) = alg.wrap_alg() {
+            let kek = if let Some((_, sender_key)) = sender.as_ref() {
+                derive_key_ecdh_1pu(
+                    wrap_alg,
+                    &ephem,
+                    sender_key,
+                    recip_key,
+                    alg.as_str().as_bytes(),
+                    &apu,
+                    &apv,
+                    &jwe.tag,
+                    true,
+                )?
+            } else {
+                derive_key_ecdh_es(
+                    wrap_alg,
+                    &ephem,
+                    recip_key,
+                    alg.as_str().as_bytes(),
+                    &apu,
+                    &apv,
+                    true,
+                )?
+            };
+            kek.unwrap_key(enc.key_alg(), recipient.encrypted_key.as_slice(), &[])?
+        } else {
+            if !recipient.encrypted_key.is_empty() {
+                return Err(err_msg!(
+                    Input,
+                    "Encrypted key must be empty for direct key agreement"
+                ));
+            }
+            derive_key_ecdh_es(
+                enc.key_alg(),
+                &ephem,
+                recip_key,
+                enc.as_str().as_bytes(),
+                &apu,
+                &apv,
+                true,
+            )?
+        };
+        let plaintext = cek.aead_decrypt(
+            (jwe.ciphertext.as_slice(), jwe.tag.as_slice()),
+            &jwe.iv,
+            jwe.encryption_aad().as_bytes(),
+        )?;
+
+        Ok(DecryptedJwe {
+            plaintext,
+            header,
+            kid,
+            skid: sender.map(|(skid, _)| skid),
+        })
+    }
+}
+
+impl<F> Debug for JweDecryptor<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JweDecryptor").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::alg::EcCurves;
+
+    fn resolver<'k>(
+        keys: &'k [(&'k str, &'k LocalKey)],
+    ) -> impl FnMut(JweKeyLookup<'_>) -> Result<Option<LocalKey>, Error> + 'k {
+        move |lookup| {
+            let kid = match lookup {
+                JweKeyLookup::Recipient(kid) | JweKeyLookup::Sender(kid) => kid,
+            };
+            keys.iter()
+                .find(|(k, _)| *k == kid)
+                .map(|(_, key)| LocalKey::from_jwk(key.to_jwk_secret()?.as_opt_str().unwrap()))
+                .transpose()
+        }
+    }
+
+    #[test]
+    fn anoncrypt_general_multiple_recipients() {
+        let alice = LocalKey::generate_with_rng(KeyAlg::X25519, false).unwrap();
+        let bob = LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp256r1), false).unwrap();
+        let jwe = JweBuilder::new(JweAlg::EcdhEsA256Kw, JweEnc::XC20P)
+            .protected_header("typ", "application/didcomm-encrypted+json")
+            .recipient("alice", &alice)
+            .recipient("bob", &bob)
+            .apv(b"recipients")
+            .encrypt(b"secret message")
+            .unwrap();
+        assert!(jwe.to_compact().is_err());
+        assert!(jwe.to_flattened_json().is_err());
+        let jwe = Jwe::from_str(&jwe.to_general_json().unwrap()).unwrap();
+        assert_eq!(jwe.recipients().len(), 2);
+
+        for (kid, key) in [("alice", &alice), ("bob", &bob)] {
+            let keys = [(kid, key)];
+            let decrypted = JweDecryptor::new(resolver(&keys)).decrypt(&jwe).unwrap();
+            assert_eq!(&decrypted.plaintext[..], b"secret message");
+            assert_eq!(decrypted.kid, kid);
+            assert_eq!(
+                decrypted.header.get("typ").and_then(Value::as_str),
+                Some("application/didcomm-encrypted+json")
+            );
+        }
+
+        let other = LocalKey::generate_with_rng(KeyAlg::X25519, false).unwrap();
+        let keys = [("carol", &other)];
+        let err = JweDecryptor::new(resolver(&keys))
+            .decrypt(&jwe)
+            .unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn authcrypt_flattened() {
+        let alice = LocalKey::generate_with_rng(KeyAlg::X25519, false).unwrap();
+        let bob = LocalKey::generate_with_rng(KeyAlg::X25519, false).unwrap();
+        assert!(JweBuilder::new(JweAlg::Ecdh1PuA256Kw, JweEnc::A256CbcHs512)
+            .recipient("bob", &bob)
+            .encrypt(b"message")
+            .is_err());
+        let jwe = JweBuilder::new(JweAlg::Ecdh1PuA256Kw, JweEnc::A256CbcHs512)
+            .sender("alice", &alice)
+            .recipient("bob", &bob)
+            .unprotected_header("cty", "text/plain")
+            .aad(b"additional data")
+            .encrypt(b"authenticated message")
+            .unwrap();
+        assert!(jwe.to_compact().is_err());
+        let jwe = Jwe::from_str(&jwe.to_flattened_json().unwrap()).unwrap();
+        assert_eq!(jwe.aad().unwrap().as_deref(), Some(&b"additional data"[..]));
+
+        let keys = [("alice", &alice), ("bob", &bob)];
+        let decrypted = JweDecryptor::new(resolver(&keys)).decrypt(&jwe).unwrap();
+        assert_eq!(&decrypted.plaintext[..], b"authenticated message");
+        assert_eq!(decrypted.skid.as_deref(), Some("alice"));
+
+        // the sender key must match
+        let mallory = LocalKey::generate_with_rng(KeyAlg::X25519, false).unwrap();
+        let keys = [("alice", &mallory), ("bob", &bob)];
+        assert!(JweDecryptor::new(resolver(&keys)).decrypt(&jwe).is_err());
+    }
+
+    #[test]
+    fn direct_compact() {
+        let bob = LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp256r1), false).unwrap();
+        let carol = LocalKey::generate_with_rng(KeyAlg::X25519, false).unwrap();
+        assert!(JweBuilder::new(JweAlg::EcdhEs, JweEnc::A256Gcm)
+            .recipient("bob", &bob)
+            .recipient("carol", &carol)
+            .encrypt(b"message")
+            .is_err());
+        let jwe = JweBuilder::new(JweAlg::EcdhEs, JweEnc::A256Gcm)
+            .recipient("bob", &bob)
+            .apu(b"Alice")
+            .apv(b"Bob")
+            .encrypt(b"direct message")
+            .unwrap();
+        let compact = jwe.to_compact().unwrap();
+        assert_eq!(compact.split('.').nth(1), Some(""));
+        let header = jwe.protected_header().unwrap();
+        assert_eq!(header.get("kid").and_then(Value::as_str), Some("bob"));
+
+        let keys = [("bob", &bob)];
+        let decrypted = JweDecryptor::new(resolver(&keys))
+            .decrypt_str(&compact)
+            .unwrap();
+        assert_eq!(&decrypted.plaintext[..], b"direct message");
+
+        // tampering with the protected header is detected
+        let mut parts = compact.split('.').map(str::to_string).collect::<Vec<_>>();
+        let mut header = decode_header(&parts[0]).unwrap();
+        header.insert("typ".to_string(), "JWE".into());
+        parts[0] = encode_header(&header).unwrap();
+        assert!(JweDecryptor::new(resolver(&keys))
+            .decrypt_str(&parts.join("."))
+            .is_err());
+    }
+
+    #[test]
+    fn p384_recipients() {
+        let alice =
+            LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp384r1), false).unwrap();
+        let bob = LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp384r1), false).unwrap();
+        let keys = [("alice", &alice), ("bob", &bob)];
+
+        for (alg, enc) in [
+            (JweAlg::EcdhEs, JweEnc::A256CbcHs512),
+            (JweAlg::EcdhEsA192Kw, JweEnc::A256Gcm),
+            (JweAlg::Ecdh1PuA192Kw, JweEnc::A256CbcHs512),
+            (JweAlg::Ecdh1PuA256Kw, JweEnc::A128CbcHs256),
+        ] {
+            let mut builder = JweBuilder::new(alg, enc)
+                .recipient("bob", &bob)
+                .apu(b"Alice")
+                .apv(b"Bob");
+            if alg.is_authenticated() {
+                builder = builder.sender("alice", &alice);
+            }
+            let compact = builder
+                .encrypt(b"p-384 message")
+                .unwrap()
+                .to_compact()
+                .unwrap();
+            let jwe = Jwe::from_str(&compact).unwrap();
+            let header = jwe.protected_header().unwrap();
+            assert_eq!(
+                header.get("alg").and_then(Value::as_str),
+                Some(alg.as_str())
+            );
+            assert_eq!(header.get("apu").and_then(Value::as_str), Some("QWxpY2U"));
+            assert_eq!(header.get("apv").and_then(Value::as_str), Some("Qm9i"));
+            assert_eq!(
+                header
+                    .get("epk")
+                    .and_then(|epk| epk.get("crv"))
+                    .and_then(Value::as_str),
+                Some("P-384")
+            );
+
+            let decrypted = JweDecryptor::new(resolver(&keys)).decrypt(&jwe).unwrap();
+            assert_eq!(&decrypted.plaintext[..], b"p-384 message");
+        }
+    }
+}
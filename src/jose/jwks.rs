@@ -0,0 +1,273 @@
+//! JSON Web Key Set (RFC 7517 section 5) parsing and serialization
+
+use serde_json::{Map, Value};
+
+use super::Header;
+use crate::{crypto::jwk::JwkKeyUse, error::Error, kms::LocalKey};
+
+/// A single JSON Web Key in a key set
+pub type Jwk = Map<String, Value>;
+
+/// A JSON Web Key Set
+///
+/// Entries are retained as parsed JSON objects, so that keys with
+/// unsupported types or parameters do not prevent the set from loading.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+impl JwkSet {
+    /// Create a new, empty key set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a JWKS document from a string
+    pub fn from_json(jwks: &str) -> Result<Self, Error> {
+        Self::from_slice(jwks.as_bytes())
+    }
+
+    /// Parse a JWKS document from a byte slice
+    pub fn from_slice(jwks: &[u8]) -> Result<Self, Error> {
+        let mut doc: Map<String, Value> =
+            serde_json::from_slice(jwks).map_err(err_map!(Input, "Invalid JWKS document"))?;
+        let Some(Value::Array(entries)) = doc.remove("keys") else {
+            return Err(err_msg!(Input, "Missing or invalid JWKS keys"));
+        };
+        let mut keys = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match entry {
+                Value::Object(jwk) if jwk_str(&jwk, "kty").is_some() => keys.push(jwk),
+                _ => return Err(err_msg!(Input, "Invalid JWK in JWKS document")),
+            }
+        }
+        Ok(Self { keys })
+    }
+
+    /// Serialize the key set as a JWKS document
+    pub fn to_json(&self) -> Result<String, Error> {
+        let mut doc = Map::new();
+        doc.insert(
+            "keys".into(),
+            Value::Array(self.keys.iter().cloned().map(Value::Object).collect()),
+        );
+        serde_json::to_string(&doc).map_err(err_map!(Unexpected, "Error encoding JWKS document"))
+    }
+
+    /// Add the public key of a `LocalKey` to the set
+    ///
+    /// When no key identifier is provided, the RFC 7638 thumbprint of the key is used.
+    pub fn add_key(
+        &mut self,
+        key: &LocalKey,
+        kid: Option<&str>,
+        alg: Option<&str>,
+        key_use: Option<JwkKeyUse>,
+    ) -> Result<(), Error> {
+        let mut jwk: Jwk = serde_json::from_str(&key.to_jwk_public(None)?)
+            .map_err(err_map!(Unexpected, "Error encoding JWK"))?;
+        let kid = match kid {
+            Some(kid) => kid.to_string(),
+            None => key.to_jwk_thumbprint(None)?,
+        };
+        jwk.insert("kid".into(), kid.into());
+        if let Some(alg) = alg {
+            jwk.insert("alg".into(), alg.into());
+        }
+        if let Some(key_use) = key_use {
+            jwk.insert("use".into(), key_use.as_str().into());
+        }
+        self.keys.push(jwk);
+        Ok(())
+    }
+
+    /// Add a JWK to the set
+    pub fn push(&mut self, jwk: Jwk) -> Result<(), Error> {
+        if jwk_str(&jwk, "kty").is_none() {
+            return Err(err_msg!(Input, "Invalid JWK: missing kty"));
+        }
+        self.keys.push(jwk);
+        Ok(())
+    }
+
+    /// Access the keys of the set
+    pub fn keys(&self) -> &[Jwk] {
+        &self.keys
+    }
+
+    /// Get the number of keys in the set
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Check if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Find the keys matching the given `kid`, `alg` and `use` parameters
+    ///
+    /// Keys which do not declare an `alg` or `use` parameter match any value.
+    pub fn find<'s>(
+        &'s self,
+        kid: Option<&'s str>,
+        alg: Option<&'s str>,
+        key_use: Option<JwkKeyUse>,
+    ) -> impl Iterator<Item = &'s Jwk> + 's {
+        self.keys.iter().filter(move |jwk| {
+            (kid.is_none() || jwk_str(jwk, "kid") == kid)
+                && matches_param(jwk, "alg", alg)
+                && matches_param(jwk, "use", key_use.as_ref().map(JwkKeyUse::as_str))
+        })
+    }
+
+    /// Load the key with the given key identifier
+    pub fn get_key(&self, kid: &str) -> Result<Option<LocalKey>, Error> {
+        self.find(Some(kid), None, None)
+            .next()
+            .map(load_jwk)
+            .transpose()
+    }
+
+    /// Resolve the signature verification key for a JOSE header
+    ///
+    /// This may be used as the key resolver of a `JwsVerifier` or `JwtVerifier`.
+    /// When the header has no `kid`, a key is only returned if exactly one key matches.
+    pub fn resolve(&self, header: &Header) -> Result<Option<LocalKey>, Error> {
+        let kid = header_param(header, "kid")?;
+        let alg = header_param(header, "alg")?;
+        let mut found = self.find(kid, alg, Some(JwkKeyUse::Signature));
+        let Some(jwk) = found.next() else {
+            return Ok(None);
+        };
+        if kid.is_none() && found.next().is_some() {
+            return Err(err_msg!(Input, "Ambiguous JWKS key: no kid provided"));
+        }
+        load_jwk(jwk).map(Some)
+    }
+}
+
+impl FromIterator<Jwk> for JwkSet {
+    fn from_iter<T: IntoIterator<Item = Jwk>>(iter: T) -> Self {
+        Self {
+            keys: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Load a JWK from a key set into a `LocalKey`
+pub fn load_jwk(jwk: &Jwk) -> Result<LocalKey, Error> {
+    let json = serde_json::to_vec(jwk).map_err(err_map!(Unexpected, "Error encoding JWK"))?;
+    LocalKey::from_jwk_slice(&json)
+}
+
+#[inline]
+fn jwk_str<'j>(jwk: &'j Jwk, name: &str) -> Option<&'j str> {
+    jwk.get(name).and_then(Value::as_str)
+}
+
+#[inline]
+fn matches_param(jwk: &Jwk, name: &str, value: Option<&str>) -> bool {
+    match (value, jwk_str(jwk, name)) {
+        (Some(value), Some(param)) => value == param,
+        _ => true,
+    }
+}
+
+fn header_param<'h>(header: &'h Header, name: &str) -> Result<Option<&'h str>, Error> {
+    match header.get(name) {
+        Some(Value::String(value)) => Ok(Some(value.as_str())),
+        Some(_) => Err(err_msg!(Input, "Invalid header parameter: {}", name)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::alg::{EcCurves, KeyAlg},
+        jose::{
+            jws::{JwsAlg, JwsSigner},
+            jwt::{sign_jwt, Claims, JwtVerifier},
+        },
+    };
+
+    #[test]
+    fn parse_find_keys() {
+        // from RFC 7517 appendix A.1
+        let jwks = r#"{"keys":
+            [
+                {"kty":"EC",
+                "crv":"P-256",
+                "x":"MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4",
+                "y":"4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM",
+                "use":"enc",
+                "kid":"1"},
+                {"kty":"RSA",
+                "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+                "e":"AQAB",
+                "alg":"RS256",
+                "kid":"2011-04-29"},
+                {"kty":"unknown"}
+            ]
+        }"#;
+        let set = JwkSet::from_json(jwks).unwrap();
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.find(Some("1"), None, None).count(), 1);
+        assert_eq!(
+            set.find(None, None, Some(JwkKeyUse::Signature))
+                .map(|jwk| jwk_str(jwk, "kid"))
+                .collect::<Vec<_>>(),
+            vec![Some("2011-04-29"), None]
+        );
+        assert_eq!(set.find(None, Some("ES256"), None).count(), 2);
+        let key = set.get_key("1").unwrap().unwrap();
+        assert_eq!(key.algorithm(), KeyAlg::EcCurve(EcCurves::Secp256r1));
+        assert!(set.get_key("missing").unwrap().is_none());
+
+        let reparsed = JwkSet::from_json(&set.to_json().unwrap()).unwrap();
+        assert_eq!(reparsed, set);
+
+        assert!(JwkSet::from_json(r#"{"keys":{}}"#).is_err());
+        assert!(JwkSet::from_json(r#"{"keys":[{"kid":"1"}]}"#).is_err());
+    }
+
+    #[test]
+    fn resolve_jwt_key() {
+        let key = LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp256r1), false).unwrap();
+        let other = LocalKey::generate_with_rng(KeyAlg::Ed25519, false).unwrap();
+        let mut set = JwkSet::new();
+        set.add_key(&key, None, Some("ES256"), Some(JwkKeyUse::Signature))
+            .unwrap();
+        set.add_key(&other, Some("other"), None, None).unwrap();
+        let kid = key.to_jwk_thumbprint(None).unwrap();
+        assert_eq!(set.find(Some(&kid), None, None).count(), 1);
+
+        let set = JwkSet::from_json(&set.to_json().unwrap()).unwrap();
+        let token = sign_jwt(
+            &Claims::new(),
+            JwsSigner::new(JwsAlg::ES256, &key).kid(&kid),
+        )
+        .unwrap();
+        JwtVerifier::new(|header: &Header| set.resolve(header))
+            .verify(&token)
+            .unwrap();
+
+        // without a kid, the key must be uniquely identified
+        let token = sign_jwt(&Claims::new(), JwsSigner::new(JwsAlg::ES256, &key)).unwrap();
+        assert!(JwtVerifier::new(|header: &Header| set.resolve(header))
+            .verify(&token)
+            .is_err());
+        let set = set
+            .keys()
+            .iter()
+            .filter(|jwk| jwk_str(jwk, "kid") == Some(kid.as_str()))
+            .cloned()
+            .collect::<JwkSet>();
+        JwtVerifier::new(|header: &Header| set.resolve(header))
+            .verify(&token)
+            .unwrap();
+    }
+}
@@ -0,0 +1,797 @@
+//! JSON Web Signature (RFC 7515) creation and verification
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    str::FromStr,
+};
+
+use serde_json::Value;
+
+use super::{
+    b64_decode, b64_encode, decode_header, encode_header, header_str, merge_headers, Header,
+};
+use crate::{
+    crypto::sign::{KeySigVerify, KeySign, SignatureType},
+    error::Error,
+    kms::LocalKey,
+};
+
+/// Supported JWS signature algorithms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JwsAlg {
+    /// EdDSA using Ed25519 or Ed448
+    EdDSA,
+    /// ECDSA using P-256 and SHA-256
+    ES256,
+    /// ECDSA using K-256 and SHA-256
+    ES256K,
+    /// ECDSA using P-384 and SHA-384
+    ES384,
+    /// HMAC using SHA-256
+    HS256,
+    /// HMAC using SHA-384
+    HS384,
+    /// HMAC using SHA-512
+    HS512,
+    /// RSASSA-PKCS1-v1_5 using SHA-256
+    RS256,
+    /// RSASSA-PSS using SHA-256 and MGF1 with SHA-256
+    PS256,
+}
+
+impl JwsAlg {
+    /// Get the JWA identifier for the algorithm
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::EdDSA => "EdDSA",
+            Self::ES256 => "ES256",
+            Self::ES256K => "ES256K",
+            Self::ES384 => "ES384",
+            Self::HS256 => "HS256",
+            Self::HS384 => "HS384",
+            Self::HS512 => "HS512",
+            Self::RS256 => "RS256",
+            Self::PS256 => "PS256",
+        }
+    }
+
+    /// Get the corresponding signature type
+    pub fn sig_type(&self) -> SignatureType {
+        match self {
+            Self::EdDSA => SignatureType::EdDSA,
+            Self::ES256 => SignatureType::ES256,
+            Self::ES256K => SignatureType::ES256K,
+            Self::ES384 => SignatureType::ES384,
+            Self::HS256 => SignatureType::HS256,
+            Self::HS384 => SignatureType::HS384,
+            Self::HS512 => SignatureType::HS512,
+            Self::RS256 => SignatureType::RS256,
+            Self::PS256 => SignatureType::PS256,
+        }
+    }
+}
+
+impl FromStr for JwsAlg {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "EdDSA" => Self::EdDSA,
+            "ES256" => Self::ES256,
+            "ES256K" => Self::ES256K,
+            "ES384" => Self::ES384,
+            "HS256" => Self::HS256,
+            "HS384" => Self::HS384,
+            "HS512" => Self::HS512,
+            "RS256" => Self::RS256,
+            "PS256" => Self::PS256,
+            _ => return Err(err_msg!(Unsupported, "Unsupported JWS algorithm: {}", s)),
+        })
+    }
+}
+
+/// A single signature of a JWS
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JwsSignature {
+    protected: String,
+    header: Option<Header>,
+    signature: Vec<u8>,
+}
+
+impl JwsSignature {
+    /// Decode the protected header
+    pub fn protected_header(&self) -> Result<Header, Error> {
+        if self.protected.is_empty() {
+            Ok(Header::new())
+        } else {
+            decode_header(&self.protected)
+        }
+    }
+
+    /// Accessor for the unprotected header
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Accessor for the signature value
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    fn signing_input(&self, payload: &str) -> String {
+        format!("{}.{}", self.protected, payload)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct JwsJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signatures: Option<Vec<JwsJsonSignature>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    protected: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    header: Option<Header>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct JwsJsonSignature {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    protected: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    header: Option<Header>,
+    signature: String,
+}
+
+impl From<&JwsSignature> for JwsJsonSignature {
+    fn from(sig: &JwsSignature) -> Self {
+        Self {
+            protected: Some(sig.protected.clone()).filter(|p| !p.is_empty()),
+            header: sig.header.clone(),
+            signature: b64_encode(&sig.signature),
+        }
+    }
+}
+
+/// A signed JWS message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Jws {
+    payload: Option<String>,
+    signatures: Vec<JwsSignature>,
+}
+
+impl Jws {
+    /// Parse a JWS in the compact serialization
+    ///
+    /// An empty payload segment indicates a detached payload.
+    pub fn from_compact(jws: &str) -> Result<Self, Error> {
+        let parts = jws.split('.').collect::<Vec<_>>();
+        if parts.len() != 3 {
+            return Err(err_msg!(Input, "Invalid compact JWS"));
+        }
+        Ok(Self {
+            payload: Some(parts[1].to_string()).filter(|p| !p.is_empty()),
+            signatures: vec![JwsSignature {
+                protected: parts[0].to_string(),
+                header: None,
+                signature: b64_decode(parts[2])?,
+            }],
+        })
+    }
+
+    /// Parse a JWS in the flattened or general JSON serialization
+    pub fn from_json(jws: &str) -> Result<Self, Error> {
+        let json: JwsJson =
+            serde_json::from_str(jws).map_err(err_map!(Input, "Invalid JSON JWS"))?;
+        let signatures = match (json.signatures, json.signature) {
+            (Some(signatures), None) if json.protected.is_none() && json.header.is_none() => {
+                signatures
+            }
+            (None, Some(signature)) => vec![JwsJsonSignature {
+                protected: json.protected,
+                header: json.header,
+                signature,
+            }],
+            _ => return Err(err_msg!(Input, "Invalid JSON JWS")),
+        };
+        if signatures.is_empty() {
+            return Err(err_msg!(Input, "Invalid JSON JWS: no signatures"));
+        }
+        Ok(Self {
+            payload: json.payload,
+            signatures: signatures
+                .into_iter()
+                .map(|s| {
+                    Ok(JwsSignature {
+                        protected: s.protected.unwrap_or_default(),
+                        header: s.header,
+                        signature: b64_decode(&s.signature)?,
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+        })
+    }
+
+    /// Produce the compact serialization of the JWS
+    pub fn to_compact(&self) -> Result<String, Error> {
+        let sig = match self.signatures.as_slice() {
+            [sig] if sig.header.is_none() => sig,
+            _ => {
+                return Err(err_msg!(
+                    Input,
+                    "Compact serialization requires a single signature without unprotected headers"
+                ))
+            }
+        };
+        Ok(format!(
+            "{}.{}",
+            sig.signing_input(self.payload.as_deref().unwrap_or_default()),
+            b64_encode(&sig.signature)
+        ))
+    }
+
+    /// Produce the flattened JSON serialization of the JWS
+    pub fn to_flattened_json(&self) -> Result<String, Error> {
+        let sig = match self.signatures.as_slice() {
+            [sig] => JwsJsonSignature::from(sig),
+            _ => {
+                return Err(err_msg!(
+                    Input,
+                    "Flattened serialization requires a single signature"
+                ))
+            }
+        };
+        let json = JwsJson {
+            payload: self.payload.clone(),
+            signatures: None,
+            protected: sig.protected,
+            header: sig.header,
+            signature: Some(sig.signature),
+        };
+        serde_json::to_string(&json).map_err(err_map!(Unexpected, "Error encoding JWS"))
+    }
+
+    /// Produce the general JSON serialization of the JWS
+    pub fn to_general_json(&self) -> Result<String, Error> {
+        let json = JwsJson {
+            payload: self.payload.clone(),
+            signatures: Some(self.signatures.iter().map(Into::into).collect()),
+            protected: None,
+            header: None,
+            signature: None,
+        };
+        serde_json::to_string(&json).map_err(err_map!(Unexpected, "Error encoding JWS"))
+    }
+
+    /// Check whether the payload is detached from the JWS
+    pub fn is_detached(&self) -> bool {
+        self.payload.is_none()
+    }
+
+    /// Decode the attached payload, if any
+    pub fn payload(&self) -> Result<Option<Vec<u8>>, Error> {
+        self.payload.as_deref().map(b64_decode).transpose()
+    }
+
+    /// Accessor for the signatures of the JWS
+    pub fn signatures(&self) -> &[JwsSignature] {
+        &self.signatures
+    }
+}
+
+impl FromStr for Jws {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim_start().starts_with('{') {
+            Self::from_json(s)
+        } else {
+            Self::from_compact(s)
+        }
+    }
+}
+
+/// A signing key and its associated headers
+pub struct JwsSigner<'k> {
+    alg: JwsAlg,
+    key: &'k dyn KeySign,
+    protected: Header,
+    unprotected: Option<Header>,
+}
+
+impl<'k> JwsSigner<'k> {
+    /// Create a new signer from a signing key
+    pub fn new(alg: JwsAlg, key: &'k dyn KeySign) -> Self {
+        Self {
+            alg,
+            key,
+            protected: Header::new(),
+            unprotected: None,
+        }
+    }
+
+    /// Set the `kid` protected header parameter
+    pub fn kid(self, kid: &str) -> Self {
+        self.protected_header("kid", kid)
+    }
+
+    /// Set the `typ` protected header parameter
+    pub fn typ(self, typ: &str) -> Self {
+        self.protected_header("typ", typ)
+    }
+
+    /// Set the `crit` protected header parameter
+    ///
+    /// Each critical parameter must also be present in the protected header.
+    pub fn crit<'c>(self, names: impl IntoIterator<Item = &'c str>) -> Self {
+        let names = names.into_iter().map(Value::from).collect::<Vec<_>>();
+        self.protected_header("crit", names)
+    }
+
+    /// Add a parameter to the protected header
+    pub fn protected_header(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.protected.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Add a parameter to the protected header, unless it is already present
+    pub(crate) fn default_protected_header(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.protected
+            .entry(name.to_string())
+            .or_insert_with(|| value.into());
+        self
+    }
+
+    /// Add a parameter to the unprotected header
+    pub fn unprotected_header(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.unprotected
+            .get_or_insert_with(Header::new)
+            .insert(name.to_string(), value.into());
+        self
+    }
+
+    fn sign(&self, payload: &str) -> Result<JwsSignature, Error> {
+        let mut protected = self.protected.clone();
+        protected.insert("alg".to_string(), self.alg.as_str().into());
+        merge_headers([Some(&protected), self.unprotected.as_ref()])?;
+        for name in critical_names(&protected)? {
+            if !protected.contains_key(name) {
+                return Err(err_msg!(
+                    Input,
+                    "Critical header parameter not found: {}",
+                    name
+                ));
+            }
+        }
+        let mut sig = JwsSignature {
+            protected: encode_header(&protected)?,
+            header: self.unprotected.clone(),
+            signature: Vec::new(),
+        };
+        sig.signature = self
+            .key
+            .create_signature(
+                sig.signing_input(payload).as_bytes(),
+                Some(self.alg.sig_type()),
+            )?
+            .into_vec();
+        Ok(sig)
+    }
+}
+
+impl Debug for JwsSigner<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwsSigner")
+            .field("alg", &self.alg)
+            .field("protected", &self.protected)
+            .field("unprotected", &self.unprotected)
+            .finish()
+    }
+}
+
+fn critical_names(protected: &Header) -> Result<Vec<&str>, Error> {
+    match protected.get("crit") {
+        None => Ok(Vec::new()),
+        Some(Value::Array(names)) if !names.is_empty() => names
+            .iter()
+            .map(|name| {
+                name.as_str()
+                    .ok_or_else(|| err_msg!(Input, "Invalid header parameter: crit"))
+            })
+            .collect(),
+        Some(_) => Err(err_msg!(Input, "Invalid header parameter: crit")),
+    }
+}
+
+/// A builder for a signed JWS message
+#[derive(Debug)]
+pub struct JwsBuilder<'b> {
+    payload: &'b [u8],
+    detached: bool,
+    signers: Vec<JwsSigner<'b>>,
+}
+
+impl<'b> JwsBuilder<'b> {
+    /// Create a new JWS builder for a payload
+    pub fn new(payload: &'b [u8]) -> Self {
+        Self {
+            payload,
+            detached: false,
+            signers: Vec::new(),
+        }
+    }
+
+    /// Omit the payload from the serialized JWS
+    pub fn detached(mut self) -> Self {
+        self.detached = true;
+        self
+    }
+
+    /// Add a signer
+    pub fn signer(mut self, signer: JwsSigner<'b>) -> Self {
+        self.signers.push(signer);
+        self
+    }
+
+    /// Sign the payload with each signer
+    pub fn sign(&self) -> Result<Jws, Error> {
+        if self.signers.is_empty() {
+            return Err(err_msg!(Input, "No signers provided for JWS"));
+        }
+        let payload = b64_encode(self.payload);
+        let signatures = self
+            .signers
+            .iter()
+            .map(|signer| signer.sign(&payload))
+            .collect::<Result<_, Error>>()?;
+        Ok(Jws {
+            payload: Some(payload).filter(|_| !self.detached),
+            signatures,
+        })
+    }
+}
+
+/// The result of verifying a JWS
+#[derive(Debug)]
+pub struct VerifiedJws {
+    /// The signed payload
+    pub payload: Vec<u8>,
+    /// The combined protected and unprotected headers of each verified signature
+    pub headers: Vec<Header>,
+}
+
+/// A verifier for JWS messages, resolving keys using a callback
+pub struct JwsVerifier<F> {
+    resolve: F,
+    understood: Vec<String>,
+    require_all: bool,
+}
+
+impl<F> JwsVerifier<F>
+where
+    F: FnMut(&Header) -> Result<Option<LocalKey>, Error>,
+{
+    /// Create a new JWS verifier
+    ///
+    /// The callback is invoked with the combined header of each signature, and
+    /// may return `None` to skip signatures by unknown keys.
+    pub fn new(resolve: F) -> Self {
+        Self {
+            resolve,
+            understood: Vec::new(),
+            require_all: false,
+        }
+    }
+
+    /// Accept a critical header parameter, which is otherwise rejected
+    pub fn understand(mut self, name: &str) -> Self {
+        self.understood.push(name.to_string());
+        self
+    }
+
+    /// Require every signature to be verified, rather than at least one
+    pub fn require_all(mut self) -> Self {
+        self.require_all = true;
+        self
+    }
+
+    /// Verify a JWS with an attached payload
+    pub fn verify(&mut self, jws: &Jws) -> Result<VerifiedJws, Error> {
+        let payload = jws
+            .payload
+            .as_deref()
+            .ok_or_else(|| err_msg!(Input, "JWS payload is detached"))?;
+        self.verify_encoded(jws, payload)
+    }
+
+    /// Verify a JWS with a detached payload
+    pub fn verify_detached(&mut self, jws: &Jws, payload: &[u8]) -> Result<VerifiedJws, Error> {
+        if jws.payload.is_some() {
+            return Err(err_msg!(Input, "JWS payload is not detached"));
+        }
+        self.verify_encoded(jws, &b64_encode(payload))
+    }
+
+    fn verify_encoded(&mut self, jws: &Jws, payload: &str) -> Result<VerifiedJws, Error> {
+        let mut headers = Vec::with_capacity(jws.signatures.len());
+        for sig in jws.signatures.iter() {
+            let protected = sig.protected_header()?;
+            if sig
+                .header
+                .as_ref()
+                .map_or(false, |h| h.contains_key("crit"))
+            {
+                return Err(err_msg!(
+                    Input,
+                    "Critical header parameters must be protected"
+                ));
+            }
+            for name in critical_names(&protected)? {
+                if !protected.contains_key(name) || !self.understood.iter().any(|u| u == name) {
+                    return Err(err_msg!(
+                        Unsupported,
+                        "Unsupported critical header parameter: {}",
+                        name
+                    ));
+                }
+            }
+            // conflicting header parameters are rejected, and the algorithm
+            // is only accepted from the integrity-protected header
+            let header = merge_headers([Some(&protected), sig.header.as_ref()])?;
+            let alg = JwsAlg::from_str(header_str(&protected, "alg")?)?;
+            let Some(key) = (self.resolve)(&header)? else {
+                if self.require_all {
+                    return Err(err_msg!(NotFound, "JWS verification key not found"));
+                }
+                continue;
+            };
+            if !KeySigVerify::verify_signature(
+                &key,
+                sig.signing_input(payload).as_bytes(),
+                &sig.signature,
+                Some(alg.sig_type()),
+            )? {
+                return Err(err_msg!(Input, "Invalid JWS signature"));
+            }
+            headers.push(header);
+        }
+        if headers.is_empty() {
+            return Err(err_msg!(NotFound, "No JWS verification key found"));
+        }
+        Ok(VerifiedJws {
+            payload: b64_decode(payload)?,
+            headers,
+        })
+    }
+}
+
+impl<F> Debug for JwsVerifier<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwsVerifier")
+            .field("understood", &self.understood)
+            .field("require_all", &self.require_all)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::alg::{EcCurves, HmacTypes, KeyAlg},
+        ErrorKind,
+    };
+
+    fn resolver<'k>(
+        keys: &'k [(&'k str, &'k LocalKey)],
+    ) -> impl FnMut(&Header) -> Result<Option<LocalKey>, Error> + 'k {
+        move |header| {
+            let kid = header_str(header, "kid")?;
+            keys.iter()
+                .find(|(k, _)| *k == kid)
+                .map(|(_, key)| LocalKey::from_jwk(&key.to_jwk_public(None)?))
+                .transpose()
+        }
+    }
+
+    #[test]
+    fn compact_sign_verify() {
+        for (alg, key_alg) in [
+            (JwsAlg::EdDSA, KeyAlg::Ed25519),
+            (JwsAlg::ES256, KeyAlg::EcCurve(EcCurves::Secp256r1)),
+            (JwsAlg::ES256K, KeyAlg::EcCurve(EcCurves::Secp256k1)),
+            (JwsAlg::ES384, KeyAlg::EcCurve(EcCurves::Secp384r1)),
+        ] {
+            let key = LocalKey::generate_with_rng(key_alg, false).unwrap();
+            let jws = JwsBuilder::new(b"payload")
+                .signer(JwsSigner::new(alg, &key).kid("key-1").typ("JWT"))
+                .sign()
+                .unwrap();
+            let compact = jws.to_compact().unwrap();
+            let jws = Jws::from_str(&compact).unwrap();
+            let header = jws.signatures()[0].protected_header().unwrap();
+            assert_eq!(header_str(&header, "alg").unwrap(), alg.as_str());
+            assert_eq!(header_str(&header, "typ").unwrap(), "JWT");
+
+            let keys = [("key-1", &key)];
+            let verified = JwsVerifier::new(resolver(&keys)).verify(&jws).unwrap();
+            assert_eq!(verified.payload, b"payload");
+
+            // modifying the payload invalidates the signature
+            let mut parts = compact.split('.').map(str::to_string).collect::<Vec<_>>();
+            parts[1] = b64_encode(b"modified");
+            let jws = Jws::from_compact(&parts.join(".")).unwrap();
+            assert!(JwsVerifier::new(resolver(&keys)).verify(&jws).is_err());
+        }
+    }
+
+    #[test]
+    fn detached_payload() {
+        let key = LocalKey::generate_with_rng(KeyAlg::Ed25519, false).unwrap();
+        let jws = JwsBuilder::new(b"detached payload")
+            .detached()
+            .signer(JwsSigner::new(JwsAlg::EdDSA, &key).kid("key-1"))
+            .sign()
+            .unwrap();
+        let compact = jws.to_compact().unwrap();
+        assert!(compact.contains(".."));
+        let jws = Jws::from_str(&compact).unwrap();
+        assert!(jws.is_detached());
+
+        let keys = [("key-1", &key)];
+        let mut verifier = JwsVerifier::new(resolver(&keys));
+        assert!(verifier.verify(&jws).is_err());
+        let verified = verifier.verify_detached(&jws, b"detached payload").unwrap();
+        assert_eq!(verified.payload, b"detached payload");
+        assert!(verifier.verify_detached(&jws, b"other payload").is_err());
+    }
+
+    #[test]
+    fn critical_headers() {
+        let key = LocalKey::generate_with_rng(KeyAlg::Ed25519, false).unwrap();
+        assert!(JwsBuilder::new(b"payload")
+            .signer(JwsSigner::new(JwsAlg::EdDSA, &key).crit(["exp"]))
+            .sign()
+            .is_err());
+        let jws = JwsBuilder::new(b"payload")
+            .signer(
+                JwsSigner::new(JwsAlg::EdDSA, &key)
+                    .kid("key-1")
+                    .protected_header("exp", 1000)
+                    .crit(["exp"]),
+            )
+            .sign()
+            .unwrap();
+        let keys = [("key-1", &key)];
+        let err = JwsVerifier::new(resolver(&keys)).verify(&jws).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        JwsVerifier::new(resolver(&keys))
+            .understand("exp")
+            .verify(&jws)
+            .unwrap();
+    }
+
+    #[test]
+    fn hmac_sign_verify() {
+        for (alg, key_alg) in [
+            (JwsAlg::HS256, KeyAlg::Hmac(HmacTypes::HS256)),
+            (JwsAlg::HS384, KeyAlg::Hmac(HmacTypes::HS384)),
+            (JwsAlg::HS512, KeyAlg::Hmac(HmacTypes::HS512)),
+        ] {
+            let key = LocalKey::generate_with_rng(key_alg, false).unwrap();
+            let jws = JwsBuilder::new(b"payload")
+                .signer(JwsSigner::new(alg, &key).kid("key-1"))
+                .sign()
+                .unwrap();
+            let jws = Jws::from_str(&jws.to_compact().unwrap()).unwrap();
+            assert_eq!(
+                jws.signatures()[0].signature().len(),
+                alg.sig_type().signature_length()
+            );
+
+            let verified = JwsVerifier::new(|_: &Header| {
+                LocalKey::from_secret_bytes(key_alg, &key.to_secret_bytes()?).map(Some)
+            })
+            .verify(&jws)
+            .unwrap();
+            assert_eq!(verified.payload, b"payload");
+
+            let other = LocalKey::generate_with_rng(key_alg, false).unwrap();
+            assert!(JwsVerifier::new(|_: &Header| {
+                LocalKey::from_secret_bytes(key_alg, &other.to_secret_bytes()?).map(Some)
+            })
+            .verify(&jws)
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn general_multiple_signatures() {
+        let key1 = LocalKey::generate_with_rng(KeyAlg::Ed25519, false).unwrap();
+        let key2 =
+            LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp256r1), false).unwrap();
+        let jws = JwsBuilder::new(b"payload")
+            .signer(JwsSigner::new(JwsAlg::EdDSA, &key1).kid("key-1"))
+            .signer(JwsSigner::new(JwsAlg::ES256, &key2).unprotected_header("kid", "key-2"))
+            .sign()
+            .unwrap();
+        assert!(jws.to_compact().is_err());
+        assert!(jws.to_flattened_json().is_err());
+        let jws = Jws::from_str(&jws.to_general_json().unwrap()).unwrap();
+        assert_eq!(jws.signatures().len(), 2);
+
+        let keys = [("key-1", &key1), ("key-2", &key2)];
+        let verified = JwsVerifier::new(resolver(&keys))
+            .require_all()
+            .verify(&jws)
+            .unwrap();
+        assert_eq!(verified.headers.len(), 2);
+
+        let keys = [("key-2", &key2)];
+        let verified = JwsVerifier::new(resolver(&keys)).verify(&jws).unwrap();
+        assert_eq!(verified.headers.len(), 1);
+        let err = JwsVerifier::new(resolver(&keys))
+            .require_all()
+            .verify(&jws)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        // a signature by a different key is rejected
+        let keys = [("key-1", &key1), ("key-2", &key1)];
+        assert!(JwsVerifier::new(resolver(&keys)).verify(&jws).is_err());
+    }
+
+    #[test]
+    fn flattened_round_trip() {
+        let key = LocalKey::generate_with_rng(KeyAlg::Ed25519, false).unwrap();
+        let jws = JwsBuilder::new(b"payload")
+            .signer(JwsSigner::new(JwsAlg::EdDSA, &key).unprotected_header("kid", "key-1"))
+            .sign()
+            .unwrap();
+        assert!(jws.to_compact().is_err());
+        let parsed = Jws::from_str(&jws.to_flattened_json().unwrap()).unwrap();
+        assert_eq!(parsed, jws);
+        assert_eq!(parsed.payload().unwrap().as_deref(), Some(&b"payload"[..]));
+    }
+
+    #[test]
+    fn unprotected_alg_rejected() {
+        let key = LocalKey::generate_with_rng(KeyAlg::Ed25519, false).unwrap();
+        let keys = [("key-1", &key)];
+
+        // a valid signature with the algorithm only in the unprotected header
+        let mut protected = Header::new();
+        protected.insert("kid".to_string(), "key-1".into());
+        let protected = encode_header(&protected).unwrap();
+        let payload = b64_encode(b"payload");
+        let sig = key
+            .sign_message(format!("{}.{}", protected, payload).as_bytes(), None)
+            .unwrap();
+        let jws = Jws::from_json(
+            &serde_json::json!({
+                "payload": payload,
+                "protected": protected,
+                "header": {"alg": "EdDSA"},
+                "signature": b64_encode(sig),
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let err = JwsVerifier::new(resolver(&keys)).verify(&jws).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Input);
+
+        // an unprotected algorithm conflicting with the protected one
+        let jws = JwsBuilder::new(b"payload")
+            .signer(JwsSigner::new(JwsAlg::EdDSA, &key).kid("key-1"))
+            .sign()
+            .unwrap();
+        let mut json: Value = serde_json::from_str(&jws.to_flattened_json().unwrap()).unwrap();
+        json["header"] = serde_json::json!({"alg": "ES256"});
+        let jws = Jws::from_json(&json.to_string()).unwrap();
+        let err = JwsVerifier::new(resolver(&keys)).verify(&jws).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Input);
+    }
+}
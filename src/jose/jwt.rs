@@ -0,0 +1,244 @@
+//! JSON Web Token (RFC 7519) signing and claim validation
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Map, Value};
+
+use super::{
+    jws::{Jws, JwsBuilder, JwsSigner, JwsVerifier},
+    Header,
+};
+use crate::{error::Error, kms::LocalKey};
+
+/// A set of JWT claims
+pub type Claims = Map<String, Value>;
+
+/// Sign a set of claims, producing a JWT in the compact serialization
+///
+/// The `typ` header parameter is set to `JWT` unless provided by the signer.
+pub fn sign_jwt(claims: &Claims, signer: JwsSigner<'_>) -> Result<String, Error> {
+    let payload =
+        serde_json::to_vec(claims).map_err(err_map!(Unexpected, "Error encoding JWT claims"))?;
+    JwsBuilder::new(&payload)
+        .signer(signer.default_protected_header("typ", "JWT"))
+        .sign()?
+        .to_compact()
+}
+
+/// A verified JWT
+#[derive(Debug)]
+pub struct VerifiedJwt {
+    /// The protected header of the token
+    pub header: Header,
+    /// The validated claims of the token
+    pub claims: Claims,
+}
+
+/// A verifier for JWTs, resolving keys using a callback
+pub struct JwtVerifier<F> {
+    jws: JwsVerifier<F>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    clock_skew: Duration,
+    require_exp: bool,
+    now: Option<SystemTime>,
+}
+
+impl<F> JwtVerifier<F>
+where
+    F: FnMut(&Header) -> Result<Option<LocalKey>, Error>,
+{
+    /// Create a new JWT verifier
+    pub fn new(resolve: F) -> Self {
+        Self {
+            jws: JwsVerifier::new(resolve),
+            issuer: None,
+            audience: None,
+            clock_skew: Duration::ZERO,
+            require_exp: false,
+            now: None,
+        }
+    }
+
+    /// Require the `iss` claim to match
+    pub fn issuer(mut self, issuer: &str) -> Self {
+        self.issuer = Some(issuer.to_string());
+        self
+    }
+
+    /// Require the `aud` claim to contain the audience
+    pub fn audience(mut self, audience: &str) -> Self {
+        self.audience = Some(audience.to_string());
+        self
+    }
+
+    /// Set the allowed clock skew when checking the `exp` and `nbf` claims
+    pub fn clock_skew(mut self, skew: Duration) -> Self {
+        self.clock_skew = skew;
+        self
+    }
+
+    /// Require the `exp` claim to be present
+    pub fn require_exp(mut self) -> Self {
+        self.require_exp = true;
+        self
+    }
+
+    /// Override the current time used to validate the token
+    pub fn current_time(mut self, now: SystemTime) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Verify the signature of a JWT and validate its claims
+    pub fn verify(&mut self, token: &str) -> Result<VerifiedJwt, Error> {
+        let jws = Jws::from_compact(token)?;
+        let verified = self.jws.verify(&jws)?;
+        let claims: Claims = serde_json::from_slice(&verified.payload)
+            .map_err(err_map!(Input, "Invalid JWT claims"))?;
+        self.validate(&claims)?;
+        Ok(VerifiedJwt {
+            header: verified.headers.into_iter().next().unwrap_or_default(),
+            claims,
+        })
+    }
+
+    /// Validate a set of claims
+    pub fn validate(&self, claims: &Claims) -> Result<(), Error> {
+        let now = self
+            .now
+            .unwrap_or_else(SystemTime::now)
+            .duration_since(UNIX_EPOCH)
+            .map_err(err_map!(Unexpected, "Invalid system time"))?
+            .as_secs();
+        let skew = self.clock_skew.as_secs();
+
+        match numeric_claim(claims, "exp")? {
+            Some(exp) if now >= exp.saturating_add(skew) => {
+                return Err(err_msg!(Input, "JWT has expired"))
+            }
+            None if self.require_exp => return Err(err_msg!(Input, "Missing JWT claim: exp")),
+            _ => (),
+        }
+        if let Some(nbf) = numeric_claim(claims, "nbf")? {
+            if now.saturating_add(skew) < nbf {
+                return Err(err_msg!(Input, "JWT is not yet valid"));
+            }
+        }
+        if let Some(issuer) = self.issuer.as_deref() {
+            if claims.get("iss").and_then(Value::as_str) != Some(issuer) {
+                return Err(err_msg!(Input, "JWT issuer does not match"));
+            }
+        }
+        if let Some(audience) = self.audience.as_deref() {
+            let found = match claims.get("aud") {
+                Some(Value::String(aud)) => aud == audience,
+                Some(Value::Array(auds)) => auds.iter().any(|aud| aud.as_str() == Some(audience)),
+                _ => false,
+            };
+            if !found {
+                return Err(err_msg!(Input, "JWT audience does not match"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F> Debug for JwtVerifier<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtVerifier")
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .field("clock_skew", &self.clock_skew)
+            .field("require_exp", &self.require_exp)
+            .finish()
+    }
+}
+
+fn numeric_claim(claims: &Claims, name: &str) -> Result<Option<u64>, Error> {
+    match claims.get(name) {
+        None => Ok(None),
+        Some(Value::Number(num)) => num
+            .as_u64()
+            .or_else(|| num.as_f64().filter(|f| *f >= 0.0).map(|f| f as u64))
+            .map(Some)
+            .ok_or_else(|| err_msg!(Input, "Invalid JWT claim: {}", name)),
+        Some(_) => Err(err_msg!(Input, "Invalid JWT claim: {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        crypto::alg::{EcCurves, KeyAlg},
+        jose::{header_str, jws::JwsAlg},
+    };
+
+    fn claims(value: Value) -> Claims {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn sign_verify_claims() {
+        let key = LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp256r1), false).unwrap();
+        let public = LocalKey::from_jwk(&key.to_jwk_public(None).unwrap()).unwrap();
+        let mut public = Some(public);
+        let token = sign_jwt(
+            &claims(json!({
+                "iss": "issuer",
+                "aud": ["other", "audience"],
+                "nbf": 1000,
+                "exp": 2000,
+            })),
+            JwsSigner::new(JwsAlg::ES256, &key).kid("key-1"),
+        )
+        .unwrap();
+
+        let at = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+        let mut verifier = JwtVerifier::new(|header: &Header| {
+            assert_eq!(header_str(header, "kid")?, "key-1");
+            Ok(public.take())
+        })
+        .issuer("issuer")
+        .audience("audience")
+        .current_time(at(1500));
+        let verified = verifier.verify(&token).unwrap();
+        assert_eq!(header_str(&verified.header, "typ").unwrap(), "JWT");
+        assert_eq!(verified.claims.get("iss"), Some(&json!("issuer")));
+
+        let verifier = verifier.current_time(at(2000));
+        assert!(verifier.validate(&verified.claims).is_err());
+        let verifier = verifier.clock_skew(Duration::from_secs(30));
+        verifier.validate(&verified.claims).unwrap();
+        let verifier = verifier.current_time(at(970));
+        verifier.validate(&verified.claims).unwrap();
+        let verifier = verifier.current_time(at(960));
+        assert!(verifier.validate(&verified.claims).is_err());
+
+        let verifier = verifier.current_time(at(1500)).audience("unknown");
+        assert!(verifier.validate(&verified.claims).is_err());
+        let verifier = verifier.audience("audience").issuer("unknown");
+        assert!(verifier.validate(&verified.claims).is_err());
+    }
+
+    #[test]
+    fn require_expiry() {
+        let verifier = JwtVerifier::new(|_: &Header| Ok(None));
+        let no_exp = claims(json!({"sub": "subject"}));
+        verifier.validate(&no_exp).unwrap();
+        let verifier = verifier.require_exp();
+        assert!(verifier.validate(&no_exp).is_err());
+        assert!(verifier
+            .validate(&claims(json!({"exp": "tomorrow"})))
+            .is_err());
+    }
+}
@@ -0,0 +1,64 @@
+//! Support for JSON Object Signing and Encryption (JOSE) messages
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+
+pub mod jwe;
+
+pub mod jwks;
+
+pub mod jws;
+
+pub mod jwt;
+
+/// A set of JOSE header parameters
+pub type Header = Map<String, Value>;
+
+#[inline]
+pub(crate) fn b64_encode(data: impl AsRef<[u8]>) -> String {
+    URL_SAFE_NO_PAD.encode(data)
+}
+
+#[inline]
+pub(crate) fn b64_decode(data: &str) -> Result<Vec<u8>, Error> {
+    URL_SAFE_NO_PAD
+        .decode(data)
+        .map_err(err_map!(Input, "Invalid base64url encoding"))
+}
+
+/// Decode a base64url-encoded JSON header
+pub(crate) fn decode_header(data: &str) -> Result<Header, Error> {
+    serde_json::from_slice(&b64_decode(data)?).map_err(err_map!(Input, "Invalid JOSE header"))
+}
+
+/// Encode a header as base64url-encoded JSON
+pub(crate) fn encode_header(header: &Header) -> Result<String, Error> {
+    let json = serde_json::to_vec(header).map_err(err_map!(Unexpected, "Error encoding header"))?;
+    Ok(b64_encode(json))
+}
+
+/// Access a required string header parameter
+pub(crate) fn header_str<'h>(header: &'h Header, name: &str) -> Result<&'h str, Error> {
+    match header.get(name) {
+        Some(Value::String(value)) => Ok(value.as_str()),
+        Some(_) => Err(err_msg!(Input, "Invalid header parameter: {}", name)),
+        None => Err(err_msg!(Input, "Missing header parameter: {}", name)),
+    }
+}
+
+/// Combine sets of header parameters, which must not overlap
+pub(crate) fn merge_headers<'h>(
+    headers: impl IntoIterator<Item = Option<&'h Header>>,
+) -> Result<Header, Error> {
+    let mut result = Header::new();
+    for header in headers.into_iter().flatten() {
+        for (name, value) in header {
+            if result.insert(name.clone(), value.clone()).is_some() {
+                return Err(err_msg!(Input, "Duplicate header parameter: {}", name));
+            }
+        }
+    }
+    Ok(result)
+}
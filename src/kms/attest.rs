@@ -0,0 +1,142 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use super::local_key::LocalKey;
+use crate::{
+    crypto::cose::{CoseAlg, CoseSign1},
+    error::Error,
+    jose::{
+        jws::{JwsBuilder, JwsSigner},
+        jwt::Claims,
+    },
+};
+
+/// The `typ` header of a key attestation JWS
+pub const KEY_ATTESTATION_TYP: &str = "key-attestation+jwt";
+
+/// A builder for a signed statement attesting to a key
+///
+/// The attestation payload contains the JWK thumbprint of the attested key as
+/// a confirmation claim (`cnf.jkt`), its algorithm (`key_alg`), its creation
+/// time (`iat`) and any custom claims.
+#[derive(Debug)]
+pub struct KeyAttestationBuilder<'b> {
+    key: &'b LocalKey,
+    created: Option<SystemTime>,
+    claims: Claims,
+}
+
+impl<'b> KeyAttestationBuilder<'b> {
+    /// Create a new attestation builder for a key or keypair
+    pub fn new(key: &'b LocalKey) -> Self {
+        Self {
+            key,
+            created: None,
+            claims: Claims::new(),
+        }
+    }
+
+    /// Set the creation time of the key, defaulting to the current time
+    pub fn created(mut self, created: SystemTime) -> Self {
+        self.created.replace(created);
+        self
+    }
+
+    /// Add a custom claim to the attestation
+    pub fn claim(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.claims.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Produce the claims of the attestation
+    pub fn to_claims(&self) -> Result<Claims, Error> {
+        let created = self
+            .created
+            .unwrap_or_else(SystemTime::now)
+            .duration_since(UNIX_EPOCH)
+            .map_err(err_map!(Input, "Invalid key creation time"))?
+            .as_secs();
+        let mut claims = self.claims.clone();
+        claims.insert(
+            "cnf".to_string(),
+            json!({ "jkt": self.key.to_jwk_thumbprint(None)? }),
+        );
+        claims.insert("key_alg".to_string(), self.key.algorithm().as_str().into());
+        claims.insert("iat".to_string(), created.into());
+        Ok(claims)
+    }
+
+    /// Sign the attestation, producing a JWS in the compact serialization
+    ///
+    /// The `typ` header parameter is set to `key-attestation+jwt` unless
+    /// provided by the signer.
+    pub fn sign_jws(&self, signer: JwsSigner<'_>) -> Result<String, Error> {
+        let payload = serde_json::to_vec(&self.to_claims()?)
+            .map_err(err_map!(Unexpected, "Error encoding attestation claims"))?;
+        JwsBuilder::new(&payload)
+            .signer(signer.default_protected_header("typ", KEY_ATTESTATION_TYP))
+            .sign()?
+            .to_compact()
+    }
+
+    /// Sign the attestation, producing a COSE_Sign1 message with a CBOR payload
+    pub fn sign_cose(
+        &self,
+        signer: &LocalKey,
+        alg: CoseAlg,
+        kid: Option<&[u8]>,
+    ) -> Result<Vec<u8>, Error> {
+        let payload = serde_cbor::to_vec(&self.to_claims()?)
+            .map_err(err_map!(Unexpected, "Error encoding attestation claims"))?;
+        Ok(CoseSign1::sign(signer, alg, kid, &payload, &[])?.to_bytes()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        crypto::alg::KeyAlg,
+        jose::{
+            header_str,
+            jws::{Jws, JwsAlg, JwsVerifier},
+            Header,
+        },
+    };
+
+    #[test]
+    fn attest_round_trip() {
+        let issuer = LocalKey::generate_with_rng(KeyAlg::Ed25519, false).unwrap();
+        let key = LocalKey::generate_with_rng(KeyAlg::X25519, false).unwrap();
+        let builder = KeyAttestationBuilder::new(&key)
+            .created(UNIX_EPOCH + Duration::from_secs(1000))
+            .claim("purpose", "agreement");
+        let thumbprint = key.to_jwk_thumbprint(None).unwrap();
+
+        let token = builder
+            .sign_jws(JwsSigner::new(JwsAlg::EdDSA, &issuer))
+            .unwrap();
+        let mut verifier = JwsVerifier::new(|header: &Header| {
+            assert_eq!(header_str(header, "typ")?, KEY_ATTESTATION_TYP);
+            Ok(Some(LocalKey::from_jwk(&issuer.to_jwk_public(None)?)?))
+        });
+        let verified = verifier
+            .verify(&Jws::from_compact(&token).unwrap())
+            .unwrap();
+        let claims: Claims = serde_json::from_slice(&verified.payload).unwrap();
+        assert_eq!(claims, builder.to_claims().unwrap());
+        assert_eq!(claims["cnf"]["jkt"], thumbprint.as_str());
+        assert_eq!(claims["key_alg"], "x25519");
+        assert_eq!(claims["iat"], 1000);
+        assert_eq!(claims["purpose"], "agreement");
+
+        let message = builder.sign_cose(&issuer, CoseAlg::EdDSA, None).unwrap();
+        let message = CoseSign1::from_bytes(&message).unwrap();
+        assert!(message.verify(&issuer, &[]).unwrap());
+        let claims: Claims = serde_cbor::from_slice(message.payload().unwrap()).unwrap();
+        assert_eq!(claims, builder.to_claims().unwrap());
+    }
+}
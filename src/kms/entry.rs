@@ -1,10 +1,13 @@
-use super::local_key::LocalKey;
+use super::{
+    local_key::LocalKey,
+    policy::{KeyPolicy, PolicyState},
+};
 use crate::{
     crypto::{alg::AnyKey, alg::KeyAlg, buffer::SecretBytes, jwk::FromJwk},
     entry::{Entry, EntryTag},
     error::Error,
 };
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 /// Key reference variant
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -50,6 +53,14 @@ pub struct KeyParams {
     /// - Stored as a key id for hardware-backed keys
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub data: Option<SecretBytes>,
+
+    /// An optional policy restricting the use of the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<KeyPolicy>,
+
+    /// The version of the key, incremented on each rotation
+    #[serde(default, rename = "ver", skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
 }
 
 impl KeyParams {
@@ -101,6 +112,16 @@ impl KeyEntry {
         self.params.metadata.as_ref().map(String::as_ref)
     }
 
+    /// Accessor for the key usage policy
+    pub fn policy(&self) -> Option<&KeyPolicy> {
+        self.params.policy.as_ref()
+    }
+
+    /// Accessor for the key version, starting from 1 for a newly inserted key
+    pub fn version(&self) -> u32 {
+        self.params.version.unwrap_or(1)
+    }
+
     /// Accessor for the key identity
     pub fn name(&self) -> &str {
         self.name.as_str()
@@ -151,6 +172,16 @@ impl KeyEntry {
 
     /// Create a local key instance from this key storage entry
     pub fn load_local_key(&self) -> Result<LocalKey, Error> {
+        let mut key = self.load_key_data()?;
+        key.policy = self
+            .params
+            .policy
+            .clone()
+            .map(|policy| Arc::new(PolicyState::new(policy)));
+        Ok(key)
+    }
+
+    fn load_key_data(&self) -> Result<LocalKey, Error> {
         if let Some(key_data) = self.params.data.as_ref() {
             match &self.params.reference {
                 Some(KeyReference::MobileSecureElement) => {
@@ -165,6 +196,7 @@ impl KeyEntry {
                 _ => Ok(LocalKey {
                     inner: Box::<AnyKey>::from_jwk_slice(key_data.as_ref())?,
                     ephemeral: false,
+                    policy: None,
                 }),
             }
         } else {
@@ -176,6 +208,7 @@ impl KeyEntry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::kms::KeyUsage;
 
     #[test]
     fn key_params_roundtrip() {
@@ -183,6 +216,8 @@ mod tests {
             metadata: Some("meta".to_string()),
             reference: None,
             data: Some(SecretBytes::from(vec![0, 0, 0, 0])),
+            policy: Some(KeyPolicy::new([KeyUsage::Sign]).with_max_uses(10)),
+            version: Some(2),
         };
         let enc_params = params.to_bytes().unwrap();
         let p2 = KeyParams::from_slice(&enc_params).unwrap();
@@ -1,4 +1,8 @@
-use super::local_key::LocalKey;
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+
+use super::{local_key::LocalKey, policy::KeyUsage};
 use crate::{
     crypto::{
         alg::{x25519::X25519KeyPair, KeyAlg},
@@ -8,10 +12,14 @@ use crate::{
             crypto_box_seal as nacl_box_seal, crypto_box_seal_open as nacl_box_seal_open,
             CBOX_NONCE_LENGTH, CBOX_TAG_LENGTH,
         },
-        kdf::{ecdh_1pu::Ecdh1PU, ecdh_es::EcdhEs},
+        kdf::{ecdh_1pu::Ecdh1PU, ecdh_es::EcdhEs, KeyDerivation},
         random::fill_random,
     },
     error::Error,
+    jose::{
+        b64_encode,
+        jwe::{Jwe, JweAlg, JweBuilder, JweDecryptor, JweEnc, JweKeyLookup},
+    },
 };
 
 #[inline]
@@ -37,6 +45,7 @@ pub fn crypto_box(
     message: &[u8],
     nonce: &[u8],
 ) -> Result<Vec<u8>, Error> {
+    sender_x25519.check_policy(KeyUsage::Derive, true)?;
     let recip_pk = cast_x25519(recip_x25519)?;
     let sender_sk = cast_x25519(sender_x25519)?;
     let mut buffer = SecretBytes::from_slice_reserve(message, CBOX_TAG_LENGTH);
@@ -51,6 +60,7 @@ pub fn crypto_box_open(
     message: &[u8],
     nonce: &[u8],
 ) -> Result<SecretBytes, Error> {
+    recip_x25519.check_policy(KeyUsage::Derive, true)?;
     let recip_pk = cast_x25519(recip_x25519)?;
     let sender_sk = cast_x25519(sender_x25519)?;
     let mut buffer = SecretBytes::from_slice(message);
@@ -70,6 +80,7 @@ pub fn crypto_box_seal_open(
     recip_x25519: &LocalKey,
     ciphertext: &[u8],
 ) -> Result<SecretBytes, Error> {
+    recip_x25519.check_policy(KeyUsage::Derive, true)?;
     let kp = cast_x25519(recip_x25519)?;
     Ok(nacl_box_seal_open(kp, ciphertext)?)
 }
@@ -106,3 +117,141 @@ pub fn derive_key_ecdh_es(
     let derive = EcdhEs::new(ephem_key, recip_key, alg_id, apu, apv, receive);
     LocalKey::from_key_derivation(key_alg, derive)
 }
+
+/// Derive ECDH-ES shared key bytes of a given length for direct key agreement
+///
+/// This performs the same derivation as `derive_key_ecdh_es`, using the
+/// ConcatKDF with SHA-256 and the given algorithm identifier, `apu` and `apv`
+/// values, for protocols which require the raw output rather than a key.
+pub fn derive_bytes_ecdh_es(
+    ephem_key: &LocalKey,
+    recip_key: &LocalKey,
+    alg_id: &[u8],
+    apu: &[u8],
+    apv: &[u8],
+    output_len: usize,
+    receive: bool,
+) -> Result<SecretBytes, Error> {
+    if output_len == 0 {
+        return Err(err_msg!(Input, "Invalid output length for key derivation"));
+    }
+    let mut derive = EcdhEs::new(ephem_key, recip_key, alg_id, apu, apv, receive);
+    let mut output = SecretBytes::from(vec![0u8; output_len]);
+    derive.derive_key_bytes(output.as_mut())?;
+    Ok(output)
+}
+
+/// Encrypt a message for one or more recipients without identifying the sender
+///
+/// A random content encryption key is wrapped for each recipient using
+/// ECDH-ES+A256KW, and the message is encrypted with A256GCM. Recipients are
+/// identified by their JWK thumbprints. The result is a JWE in the compact
+/// serialization for a single recipient, or the general JSON serialization otherwise.
+pub fn seal_for(recipients: &[LocalKey], message: &[u8]) -> Result<String, Error> {
+    let kids = recipients
+        .iter()
+        .map(|key| key.to_jwk_thumbprint(None))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut builder = JweBuilder::new(JweAlg::EcdhEsA256Kw, JweEnc::A256Gcm);
+    for (kid, key) in kids.iter().zip(recipients) {
+        builder = builder.recipient(kid, key);
+    }
+    let jwe = builder.encrypt(message)?;
+    if recipients.len() == 1 {
+        jwe.to_compact()
+    } else {
+        jwe.to_general_json()
+    }
+}
+
+/// Decrypt a message produced by `seal_for` using a recipient's private key
+pub fn seal_open(recip_key: &LocalKey, envelope: &str) -> Result<SecretBytes, Error> {
+    recip_key.check_policy(KeyUsage::Derive, true)?;
+    let jwe = Jwe::from_str(envelope)?;
+    if jwe
+        .protected_header()?
+        .get("alg")
+        .and_then(|alg| alg.as_str())
+        != Some(JweAlg::EcdhEsA256Kw.as_str())
+    {
+        return Err(err_msg!(Input, "Unsupported sealed message algorithm"));
+    }
+    let kid = recip_key.to_jwk_thumbprint(None)?;
+    let decrypted = JweDecryptor::new(|_| Ok(None)).decrypt_with_key(&jwe, &kid, recip_key)?;
+    Ok(decrypted.plaintext)
+}
+
+/// Compute the DIDComm `apv` value from the recipient key identifiers
+fn recipients_apv(kids: &[&str]) -> Vec<u8> {
+    let mut kids = kids.to_vec();
+    kids.sort_unstable();
+    Sha256::digest(kids.join(".").as_bytes()).to_vec()
+}
+
+/// Encrypt a message from an identified sender to one or more recipients
+///
+/// Following DIDComm v2 authcrypt, the content encryption key is wrapped for
+/// each recipient using ECDH-1PU+A256KW and the message is encrypted with
+/// A256CBC-HS512. The `apu` value is the sender key identifier and the `apv`
+/// value is the SHA-256 hash of the sorted recipient key identifiers. The
+/// result is a JWE in the general JSON serialization.
+pub fn authcrypt(
+    sender_key: &LocalKey,
+    skid: &str,
+    recipients: &[(&str, &LocalKey)],
+    message: &[u8],
+) -> Result<String, Error> {
+    sender_key.check_policy(KeyUsage::Derive, true)?;
+    let kids = recipients.iter().map(|(kid, _)| *kid).collect::<Vec<_>>();
+    let apv = recipients_apv(&kids);
+    let mut builder = JweBuilder::new(JweAlg::Ecdh1PuA256Kw, JweEnc::A256CbcHs512)
+        .sender(skid, sender_key)
+        .apu(skid.as_bytes())
+        .apv(&apv);
+    for (kid, key) in recipients {
+        builder = builder.recipient(kid, key);
+    }
+    builder.encrypt(message)?.to_general_json()
+}
+
+/// Decrypt a message produced by `authcrypt` using a recipient's private key
+///
+/// The sender key identifier from the message is passed to the callback to
+/// resolve the sender's public key, and is returned with the plaintext.
+pub fn authcrypt_open(
+    recip_key: &LocalKey,
+    kid: &str,
+    envelope: &str,
+    resolve_sender: impl FnOnce(&str) -> Result<LocalKey, Error>,
+) -> Result<(String, SecretBytes), Error> {
+    recip_key.check_policy(KeyUsage::Derive, true)?;
+    let jwe = Jwe::from_str(envelope)?;
+    let protected = jwe.protected_header()?;
+    if protected.get("alg").and_then(|alg| alg.as_str()) != Some(JweAlg::Ecdh1PuA256Kw.as_str())
+        || protected.get("enc").and_then(|enc| enc.as_str()) != Some(JweEnc::A256CbcHs512.as_str())
+    {
+        return Err(err_msg!(Input, "Unsupported authcrypt message algorithm"));
+    }
+    let skid = protected
+        .get("skid")
+        .and_then(|skid| skid.as_str())
+        .ok_or_else(|| err_msg!(Input, "Missing authcrypt sender key identifier"))?;
+    if let Some(apu) = protected.get("apu") {
+        if apu.as_str() != Some(b64_encode(skid).as_str()) {
+            return Err(err_msg!(Input, "Authcrypt apu does not match the sender"));
+        }
+    }
+    let mut resolve_sender = Some(resolve_sender);
+    let decrypted = JweDecryptor::new(|lookup| match lookup {
+        JweKeyLookup::Sender(skid) => match resolve_sender.take() {
+            Some(resolve) => resolve(skid).map(Some),
+            None => Ok(None),
+        },
+        JweKeyLookup::Recipient(_) => Ok(None),
+    })
+    .decrypt_with_key(&jwe, kid, recip_key)?;
+    Ok((
+        decrypted.skid.unwrap_or_else(|| skid.to_string()),
+        decrypted.plaintext,
+    ))
+}
@@ -1,7 +1,11 @@
 use std::borrow::Cow;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use super::enc::{Encrypted, ToDecrypt};
+use super::policy::{KeyPolicy, KeyUsage, PolicyState};
+#[cfg(feature = "rsa")]
+use crate::crypto::alg::rsa::RsaKeyPair;
 pub use crate::crypto::{
     alg::KeyAlg,
     backend::KeyBackend,
@@ -10,13 +14,31 @@ pub use crate::crypto::{
 };
 use crate::{
     crypto::{
-        alg::{bls::BlsKeyGen, AnyKey, AnyKeyCreate, BlsCurves},
-        encrypt::KeyAeadInPlace,
-        jwk::{FromJwk, ToJwk},
-        kdf::{KeyDerivation, KeyExchange},
+        alg::{
+            bls::BlsKeyGen,
+            k256::{K256EcdhOutput, K256KeyPair},
+            p256::P256KeyPair,
+            p384::P384KeyPair,
+            x25519::X25519KeyPair,
+            AnyKey, AnyKeyCreate, BlsCurves, EcCurves, HasKeyAlg, HasKeyBackend,
+        },
+        bip32::{DerivationPath, ExtendedKey, HdCurve},
+        bip39::Mnemonic,
+        cose::{FromCoseKey, ToCoseKey},
+        encrypt::{KeyAeadDeterministic, KeyAeadInPlace},
+        hpke::{self, HpkeAead, HpkeKemKey, HpkePsk},
+        jwk::{FromJwk, JwkThumbprintHash, ToJwk},
+        kdf::{
+            hkdf::HkdfParams,
+            sha3::{Sha3Hash, Sha3KeyGen},
+            KeyDerivation, KeyExchange,
+        },
+        mac::{KeyMac, MacType},
+        multikey::{FromMultikey, ToMultikey},
+        pkcs8::{FromPkcs8, ToPkcs8},
         random::{fill_random, RandomDet},
-        repr::{ToPublicBytes, ToSecretBytes},
-        sign::{KeySigVerify, KeySign, SignatureType},
+        repr::{KeyGen, ToPublicBytes, ToSecretBytes},
+        sign::{KeySigVerify, KeySign, SignatureFormat, SignatureType, Signer, Verifier},
         Error as CryptoError,
     },
     error::Error,
@@ -25,24 +47,36 @@ use crate::{
 #[cfg(feature = "mobile_secure_element")]
 use crate::crypto::alg::p256_hardware::P256HardwareKeyPair;
 
+#[cfg(feature = "unauthenticated")]
+use crate::crypto::unauthenticated::KeyUnauthenticatedCipher;
+
 /// A stored key entry
 #[derive(Debug)]
 pub struct LocalKey {
     pub(crate) inner: Box<AnyKey>,
     pub(crate) ephemeral: bool,
+    pub(crate) policy: Option<Arc<PolicyState>>,
 }
 
 impl LocalKey {
     /// Create a new random key or keypair
     pub fn generate_with_rng(alg: KeyAlg, ephemeral: bool) -> Result<Self, Error> {
         let inner = Box::<AnyKey>::random(alg)?;
-        Ok(Self { inner, ephemeral })
+        Ok(Self {
+            inner,
+            ephemeral,
+            policy: None,
+        })
     }
 
     /// Create a new random keypair backed by hardware
     pub fn generate_for_hardware(alg: KeyAlg, ephemeral: bool) -> Result<Self, Error> {
         let inner = Box::<AnyKey>::generate_for_hardware(alg)?;
-        Ok(Self { inner, ephemeral })
+        Ok(Self {
+            inner,
+            ephemeral,
+            policy: None,
+        })
     }
 
     /// Get a local key by id
@@ -51,6 +85,7 @@ impl LocalKey {
         Ok(Self {
             inner,
             ephemeral: false,
+            policy: None,
         })
     }
 
@@ -59,25 +94,44 @@ impl LocalKey {
         let inner = match method {
             Some("bls_keygen") => Box::<AnyKey>::generate_with_rng(alg, BlsKeyGen::new(seed)?)?,
             None | Some("") => Box::<AnyKey>::generate_with_rng(alg, RandomDet::new(seed))?,
-            _ => {
-                return Err(err_msg!(
-                    Unsupported,
-                    "Unknown seed method for key generation"
-                ))
-            }
+            Some(method) => match Sha3Hash::from_str(method) {
+                Ok(hash) => Box::<AnyKey>::generate_with_rng(alg, Sha3KeyGen::new(hash, seed))?,
+                Err(_) => {
+                    return Err(err_msg!(
+                        Unsupported,
+                        "Unknown seed method for key generation"
+                    ))
+                }
+            },
         };
         Ok(Self {
             inner,
             ephemeral: false,
+            policy: None,
         })
     }
 
+    /// Create a new deterministic key or keypair from a BIP-39 mnemonic phrase
+    ///
+    /// The seed derived from the phrase and passphrase is passed to `from_seed`
+    /// with the given seed method.
+    pub fn from_mnemonic(
+        alg: KeyAlg,
+        phrase: &str,
+        passphrase: &str,
+        method: Option<&str>,
+    ) -> Result<Self, Error> {
+        let seed = Mnemonic::from_phrase(phrase)?.to_seed(passphrase);
+        Self::from_seed(alg, seed.as_ref(), method)
+    }
+
     /// Import a key or keypair from a JWK in binary format
     pub fn from_jwk_slice(jwk: &[u8]) -> Result<Self, Error> {
         let inner = Box::<AnyKey>::from_jwk_slice(jwk)?;
         Ok(Self {
             inner,
             ephemeral: false,
+            policy: None,
         })
     }
 
@@ -87,15 +141,76 @@ impl LocalKey {
         Ok(Self {
             inner,
             ephemeral: false,
+            policy: None,
+        })
+    }
+
+    /// Import a key or keypair from a COSE_Key
+    pub fn from_cose_key(cose_key: &[u8]) -> Result<Self, Error> {
+        let inner = Box::<AnyKey>::from_cose_key(cose_key)?;
+        Ok(Self {
+            inner,
+            ephemeral: false,
+            policy: None,
+        })
+    }
+
+    /// Import a public key from a multibase-encoded multikey
+    pub fn from_multikey(multikey: &str) -> Result<Self, Error> {
+        let inner = Box::<AnyKey>::from_multikey(multikey)?;
+        Ok(Self {
+            inner,
+            ephemeral: false,
+            policy: None,
+        })
+    }
+
+    /// Import a keypair from a DER PKCS#8 document, optionally encrypted with a passphrase
+    pub fn from_pkcs8_der(der: &[u8], passphrase: Option<&[u8]>) -> Result<Self, Error> {
+        let inner = match passphrase {
+            Some(passphrase) => Box::<AnyKey>::from_pkcs8_encrypted_der(der, passphrase)?,
+            None => Box::<AnyKey>::from_pkcs8_der(der)?,
+        };
+        Ok(Self {
+            inner,
+            ephemeral: false,
+            policy: None,
+        })
+    }
+
+    /// Import a keypair from a PEM PKCS#8 document, optionally encrypted with a passphrase
+    pub fn from_pkcs8_pem(pem: &str, passphrase: Option<&[u8]>) -> Result<Self, Error> {
+        let inner = match passphrase {
+            Some(passphrase) => Box::<AnyKey>::from_pkcs8_encrypted_pem(pem, passphrase)?,
+            None => Box::<AnyKey>::from_pkcs8_pem(pem)?,
+        };
+        Ok(Self {
+            inner,
+            ephemeral: false,
+            policy: None,
+        })
+    }
+
+    /// Import a public key from a PEM SubjectPublicKeyInfo document
+    pub fn from_public_key_pem(pem: &str) -> Result<Self, Error> {
+        let inner = Box::<AnyKey>::from_public_key_pem(pem)?;
+        Ok(Self {
+            inner,
+            ephemeral: false,
+            policy: None,
         })
     }
 
     /// Import a public key from its compact representation
+    ///
+    /// Elliptic curve public keys may be provided in the compressed or
+    /// uncompressed SEC1 encoding, or as the concatenated x and y coordinates.
     pub fn from_public_bytes(alg: KeyAlg, public: &[u8]) -> Result<Self, Error> {
         let inner = Box::<AnyKey>::from_public_bytes(alg, public)?;
         Ok(Self {
             inner,
             ephemeral: false,
+            policy: None,
         })
     }
 
@@ -110,23 +225,144 @@ impl LocalKey {
         Ok(Self {
             inner,
             ephemeral: false,
+            policy: None,
         })
     }
 
+    /// Export the public key of an elliptic curve keypair in the uncompressed SEC1 encoding
+    pub fn to_public_bytes_uncompressed(&self) -> Result<SecretBytes, Error> {
+        let public = if let Some(kp) = self.inner.downcast_ref::<P256KeyPair>() {
+            kp.with_uncompressed_public_bytes(SecretBytes::from_slice)
+        } else if let Some(kp) = self.inner.downcast_ref::<K256KeyPair>() {
+            kp.with_uncompressed_public_bytes(SecretBytes::from_slice)
+        } else if let Some(kp) = self.inner.downcast_ref::<P384KeyPair>() {
+            kp.with_uncompressed_public_bytes(SecretBytes::from_slice)
+        } else {
+            return Err(err_msg!(
+                Unsupported,
+                "Uncompressed encoding requires an elliptic curve key"
+            ));
+        };
+        Ok(public)
+    }
+
     /// Export the raw bytes of the private key
     pub fn to_secret_bytes(&self) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Export, false)?;
         Ok(self.inner.to_secret_bytes()?)
     }
 
     /// Derive a new key from a Diffie-Hellman exchange between this keypair and a public key
     pub fn to_key_exchange(&self, alg: KeyAlg, pk: &LocalKey) -> Result<Self, Error> {
+        self.check_policy(KeyUsage::Derive, true)?;
         let inner = Box::<AnyKey>::from_key_exchange(alg, &*self.inner, &*pk.inner)?;
         Ok(Self {
             inner,
             ephemeral: self.ephemeral || pk.ephemeral,
+            policy: None,
         })
     }
 
+    /// Perform a secp256k1 key exchange with a public key, returning the shared
+    /// secret in the requested format
+    pub fn to_k256_key_exchange_bytes(
+        &self,
+        pk: &LocalKey,
+        output: K256EcdhOutput,
+    ) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Derive, true)?;
+        match (
+            self.inner.downcast_ref::<K256KeyPair>(),
+            pk.inner.downcast_ref::<K256KeyPair>(),
+        ) {
+            (Some(sk), Some(pk)) => {
+                let mut buf = SecretBytes::with_capacity(65);
+                sk.write_key_exchange_output(pk, output, &mut buf)?;
+                Ok(buf)
+            }
+            _ => Err(err_msg!(Input, "secp256k1 keypairs required")),
+        }
+    }
+
+    /// Derive a new symmetric key from this symmetric key using HKDF
+    ///
+    /// The raw key bytes are used as the input key material, and are never
+    /// exposed to the caller.
+    pub fn derive_key(&self, alg: KeyAlg, params: HkdfParams<'_>) -> Result<Self, Error> {
+        if !is_symmetric(self.algorithm()) {
+            return Err(err_msg!(
+                Unsupported,
+                "HKDF derivation requires a symmetric key"
+            ));
+        }
+        if !is_symmetric(alg) {
+            return Err(err_msg!(
+                Unsupported,
+                "HKDF may only derive a symmetric key"
+            ));
+        }
+        self.check_policy(KeyUsage::Derive, true)?;
+        let ikm = self.inner.to_secret_bytes()?;
+        let inner = Box::<AnyKey>::from_key_derivation(
+            alg,
+            HkdfDerive {
+                ikm: ikm.as_ref(),
+                params,
+            },
+        )?;
+        Ok(Self {
+            inner,
+            ephemeral: self.ephemeral,
+            policy: None,
+        })
+    }
+
+    /// Derive a child key along a BIP-32 or SLIP-0010 path such as `m/44'/0'/0'/0/1`
+    ///
+    /// The secret key of this secp256k1 or Ed25519 keypair is used as the seed
+    /// for the master extended key, so that a single stored key may produce any
+    /// number of deterministic child keys. Ed25519 only supports hardened paths.
+    pub fn derive_child(&self, path: &str) -> Result<Self, Error> {
+        self.check_policy(KeyUsage::Derive, true)?;
+        let key = self.extended_key(path)?;
+        let secret = key
+            .secret_bytes()
+            .ok_or_else(|| err_msg!(Input, "Missing secret key"))?;
+        let inner = Box::<AnyKey>::from_secret_bytes(self.algorithm(), secret)?;
+        Ok(Self {
+            inner,
+            ephemeral: self.ephemeral,
+            policy: None,
+        })
+    }
+
+    /// Serialize the extended secp256k1 key at a derivation path in the `xprv`
+    /// or `xpub` format, following the same derivation as `derive_child`
+    pub fn to_extended_key(&self, path: &str, private: bool) -> Result<String, Error> {
+        if private {
+            self.check_policy(KeyUsage::Export, false)?;
+        } else {
+            self.check_policy(KeyUsage::Derive, false)?;
+        }
+        Ok(self.extended_key(path)?.to_extended_string(private)?)
+    }
+
+    fn extended_key(&self, path: &str) -> Result<ExtendedKey, Error> {
+        let curve = match self.algorithm() {
+            KeyAlg::EcCurve(EcCurves::Secp256k1) => HdCurve::Secp256k1,
+            KeyAlg::Ed25519 => HdCurve::Ed25519,
+            _ => {
+                return Err(err_msg!(
+                    Unsupported,
+                    "Unsupported key algorithm for hierarchical derivation"
+                ))
+            }
+        };
+        let path = DerivationPath::from_str(path)?;
+        let seed = self.inner.to_secret_bytes()?;
+        Ok(ExtendedKey::from_seed(curve, seed.as_ref())?.derive_path(&path)?)
+    }
+
     pub(crate) fn from_key_derivation(
         alg: KeyAlg,
         derive: impl KeyDerivation,
@@ -135,6 +371,7 @@ impl LocalKey {
         Ok(Self {
             inner,
             ephemeral: false,
+            policy: None,
         })
     }
 
@@ -154,14 +391,59 @@ impl LocalKey {
 
     /// Get the JWK representation for this private key or keypair
     pub fn to_jwk_secret(&self) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Export, false)?;
         Ok(self.inner.to_jwk_secret(None)?)
     }
 
+    /// Get the COSE_Key representation for this public key or keypair
+    pub fn to_cose_key_public(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.inner.to_cose_key_public()?)
+    }
+
+    /// Get the COSE_Key representation for this private key or keypair
+    pub fn to_cose_key_secret(&self) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Export, false)?;
+        Ok(self.inner.to_cose_key_secret()?)
+    }
+
+    /// Get the multibase-encoded multikey representation of this public key or keypair
+    pub fn to_multikey(&self) -> Result<String, Error> {
+        Ok(self.inner.to_multikey()?)
+    }
+
+    /// Get the DER PKCS#8 representation of this keypair, optionally encrypted with a passphrase
+    pub fn to_pkcs8_der(&self, passphrase: Option<&[u8]>) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Export, false)?;
+        Ok(match passphrase {
+            Some(passphrase) => self.inner.to_pkcs8_encrypted_der(passphrase)?.into(),
+            None => self.inner.to_pkcs8_der()?,
+        })
+    }
+
+    /// Get the PEM PKCS#8 representation of this keypair, optionally encrypted with a passphrase
+    pub fn to_pkcs8_pem(&self, passphrase: Option<&[u8]>) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Export, false)?;
+        Ok(match passphrase {
+            Some(passphrase) => self.inner.to_pkcs8_encrypted_pem(passphrase)?.into(),
+            None => self.inner.to_pkcs8_pem()?,
+        })
+    }
+
+    /// Get the PEM SubjectPublicKeyInfo representation of this public key or keypair
+    pub fn to_public_key_pem(&self) -> Result<String, Error> {
+        Ok(self.inner.to_public_key_pem()?)
+    }
+
     /// Get the JWK thumbprint for this key or keypair
     pub fn to_jwk_thumbprint(&self, alg: Option<KeyAlg>) -> Result<String, Error> {
         Ok(self.inner.to_jwk_thumbprint(alg)?)
     }
 
+    /// Get the JWK thumbprint for this key or keypair using a specific hash function
+    pub fn to_jwk_thumbprint_hash(&self, hash: JwkThumbprintHash) -> Result<String, Error> {
+        Ok(self.inner.jwk_thumbprint(hash)?)
+    }
+
     /// Get the set of indexed JWK thumbprints for this key or keypair
     pub fn to_jwk_thumbprints(&self) -> Result<Vec<String>, Error> {
         if self.inner.algorithm() == KeyAlg::Bls12_381(BlsCurves::G1G2) {
@@ -182,6 +464,7 @@ impl LocalKey {
         Ok(Self {
             inner,
             ephemeral: self.ephemeral,
+            policy: self.policy.clone(),
         })
     }
 
@@ -220,6 +503,7 @@ impl LocalKey {
         nonce: &[u8],
         aad: &[u8],
     ) -> Result<Encrypted, Error> {
+        self.check_policy(KeyUsage::Encrypt, true)?;
         let params = self.inner.aead_params();
         let mut nonce = Cow::Borrowed(nonce);
         if nonce.is_empty() && params.nonce_length > 0 {
@@ -243,13 +527,138 @@ impl LocalKey {
         nonce: &[u8],
         aad: &[u8],
     ) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Encrypt, true)?;
         let mut buf = ciphertext.into().into_secret();
         self.inner.decrypt_in_place(&mut buf, nonce, aad)?;
         Ok(buf)
     }
 
+    /// Perform deterministic message encryption with this AES-SIV key
+    ///
+    /// The same message and associated data always produce the same output,
+    /// allowing exact-match search on encrypted values at the cost of revealing
+    /// which values are equal. The synthetic IV is prepended to the ciphertext.
+    pub fn encrypt_deterministic(&self, message: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+        self.check_policy(KeyUsage::Encrypt, true)?;
+        let mut buf = message.to_vec();
+        self.inner.encrypt_deterministic(&mut buf, &[aad])?;
+        Ok(buf)
+    }
+
+    /// Perform deterministic message decryption with this AES-SIV key
+    pub fn decrypt_deterministic(
+        &self,
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Encrypt, true)?;
+        let mut buf = SecretBytes::from_slice(ciphertext);
+        self.inner.decrypt_deterministic(&mut buf, &[aad])?;
+        Ok(buf)
+    }
+
+    /// Encrypt or decrypt a message with this symmetric key using AES-CTR
+    ///
+    /// The output is not authenticated, and this method should only be used
+    /// for interoperability with legacy formats.
+    #[cfg(feature = "unauthenticated")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "unauthenticated")))]
+    pub fn aes_ctr_unauthenticated(&self, message: &[u8], iv: &[u8]) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Encrypt, true)?;
+        let mut buf = SecretBytes::from_slice(message);
+        self.inner.apply_ctr_unauthenticated(buf.as_mut(), iv)?;
+        Ok(buf)
+    }
+
+    /// Encrypt a message with this symmetric key using AES-CBC with PKCS#7 padding
+    ///
+    /// The output is not authenticated, and this method should only be used
+    /// for interoperability with legacy formats.
+    #[cfg(feature = "unauthenticated")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "unauthenticated")))]
+    pub fn aes_cbc_encrypt_unauthenticated(
+        &self,
+        message: &[u8],
+        iv: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        self.check_policy(KeyUsage::Encrypt, true)?;
+        let mut buf = message.to_vec();
+        self.inner.encrypt_cbc_unauthenticated(&mut buf, iv)?;
+        Ok(buf)
+    }
+
+    /// Decrypt a message with this symmetric key using AES-CBC with PKCS#7 padding
+    ///
+    /// The plaintext is not authenticated, and this method should only be used
+    /// for interoperability with legacy formats.
+    #[cfg(feature = "unauthenticated")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "unauthenticated")))]
+    pub fn aes_cbc_decrypt_unauthenticated(
+        &self,
+        ciphertext: &[u8],
+        iv: &[u8],
+    ) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Encrypt, true)?;
+        let mut buf = SecretBytes::from_slice(ciphertext);
+        self.inner.decrypt_cbc_unauthenticated(&mut buf, iv)?;
+        Ok(buf)
+    }
+
+    /// Encrypt or decrypt a message with the raw keystream of this ChaCha20 key
+    ///
+    /// The keystream begins at the given 64-byte block counter. The output is
+    /// not authenticated, and this method should only be used for
+    /// interoperability with legacy formats.
+    #[cfg(feature = "unauthenticated")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "unauthenticated")))]
+    pub fn chacha20_keystream_unauthenticated(
+        &self,
+        message: &[u8],
+        nonce: &[u8],
+        counter: u32,
+    ) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Encrypt, true)?;
+        let mut buf = SecretBytes::from_slice(message);
+        self.inner
+            .apply_chacha20_unauthenticated(buf.as_mut(), nonce, counter)?;
+        Ok(buf)
+    }
+
+    /// Compute a message authentication code with this symmetric key
+    pub fn create_mac(
+        &self,
+        message: &[u8],
+        mac_type: &str,
+        customization: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        self.check_policy(KeyUsage::Sign, true)?;
+        let mut tag = Vec::new();
+        self.inner.write_mac(
+            message,
+            MacType::from_str(mac_type)?,
+            customization,
+            &mut tag,
+        )?;
+        Ok(tag)
+    }
+
+    /// Verify a message authentication code with this symmetric key
+    pub fn verify_mac(
+        &self,
+        message: &[u8],
+        tag: &[u8],
+        mac_type: &str,
+        customization: &[u8],
+    ) -> Result<bool, Error> {
+        self.check_policy(KeyUsage::Sign, false)?;
+        Ok(self
+            .inner
+            .verify_mac(message, tag, MacType::from_str(mac_type)?, customization)?)
+    }
+
     /// Sign a message with this private signing key
     pub fn sign_message(&self, message: &[u8], sig_type: Option<&str>) -> Result<Vec<u8>, Error> {
+        self.check_policy(KeyUsage::Sign, true)?;
         let mut sig = Vec::new();
         self.inner.write_signature(
             message,
@@ -266,6 +675,7 @@ impl LocalKey {
         signature: &[u8],
         sig_type: Option<&str>,
     ) -> Result<bool, Error> {
+        self.check_policy(KeyUsage::Sign, false)?;
         Ok(self.inner.verify_signature(
             message,
             signature,
@@ -273,8 +683,67 @@ impl LocalKey {
         )?)
     }
 
+    /// Sign a message digest computed by the caller with this private signing key
+    pub fn sign_prehashed(&self, digest: &[u8], sig_type: Option<&str>) -> Result<Vec<u8>, Error> {
+        self.check_policy(KeyUsage::Sign, true)?;
+        let mut sig = Vec::new();
+        self.inner.write_signature_prehashed(
+            digest,
+            sig_type.map(SignatureType::from_str).transpose()?,
+            &mut sig,
+        )?;
+        Ok(sig)
+    }
+
+    /// Verify a signature over a message digest computed by the caller
+    pub fn verify_signature_prehashed(
+        &self,
+        digest: &[u8],
+        signature: &[u8],
+        sig_type: Option<&str>,
+    ) -> Result<bool, Error> {
+        self.check_policy(KeyUsage::Sign, false)?;
+        Ok(self.inner.verify_signature_prehashed(
+            digest,
+            signature,
+            sig_type.map(SignatureType::from_str).transpose()?,
+        )?)
+    }
+
+    /// Start an incremental signature over a message provided in parts
+    pub fn signer(&self, sig_type: Option<&str>) -> Result<Signer<'_, AnyKey>, Error> {
+        self.check_policy(KeyUsage::Sign, true)?;
+        Ok(Signer::new(
+            &*self.inner,
+            sig_type.map(SignatureType::from_str).transpose()?,
+        )?)
+    }
+
+    /// Start an incremental signature verification over a message provided in parts
+    pub fn verifier(&self, sig_type: Option<&str>) -> Result<Verifier<'_, AnyKey>, Error> {
+        self.check_policy(KeyUsage::Sign, false)?;
+        Ok(Verifier::new(
+            &*self.inner,
+            sig_type.map(SignatureType::from_str).transpose()?,
+        )?)
+    }
+
     /// Wrap another key using this key
+    ///
+    /// RSA keys perform RSA-OAEP encryption, in which case the nonce must be empty
     pub fn wrap_key(&self, key: &LocalKey, nonce: &[u8]) -> Result<Encrypted, Error> {
+        self.check_policy(KeyUsage::Wrap, true)?;
+        key.check_policy(KeyUsage::Export, false)?;
+        #[cfg(feature = "rsa")]
+        if let Some(rsa) = self.inner.downcast_ref::<RsaKeyPair>() {
+            if !nonce.is_empty() {
+                return Err(err_msg!(Input, "Nonce not supported for RSA key wrapping"));
+            }
+            let secret = key.inner.to_secret_bytes()?;
+            let buf = SecretBytes::from(rsa.encrypt_oaep(secret.as_ref())?);
+            let len = buf.len();
+            return Ok(Encrypted::new(buf, len, len));
+        }
         let params = self.inner.aead_params();
         let mut buf = SecretBytes::with_capacity(
             key.inner.secret_bytes_length()? + params.tag_length + params.nonce_length,
@@ -293,11 +762,130 @@ impl LocalKey {
         ciphertext: impl Into<ToDecrypt<'d>>,
         nonce: &[u8],
     ) -> Result<LocalKey, Error> {
+        self.check_policy(KeyUsage::Wrap, true)?;
         let mut buf = ciphertext.into().into_secret();
+        #[cfg(feature = "rsa")]
+        if let Some(rsa) = self.inner.downcast_ref::<RsaKeyPair>() {
+            if !nonce.is_empty() {
+                return Err(err_msg!(Input, "Nonce not supported for RSA key wrapping"));
+            }
+            let secret = rsa.decrypt_oaep(buf.as_ref())?;
+            return Self::from_secret_bytes(alg, secret.as_ref());
+        }
         self.inner.decrypt_in_place(&mut buf, nonce, &[])?;
         Self::from_secret_bytes(alg, buf.as_ref())
     }
 
+    /// Encrypt a message to this recipient key using HPKE (RFC 9180)
+    ///
+    /// Returns the encapsulated key and the ciphertext. When a sender key is
+    /// provided, the message is encrypted in the authenticated mode.
+    pub fn hpke_seal(
+        &self,
+        aead: HpkeAead,
+        info: &[u8],
+        aad: &[u8],
+        message: &[u8],
+        psk: Option<HpkePsk<'_>>,
+        sender: Option<&LocalKey>,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        fn seal<K: HpkeKemKey + HasKeyAlg + HasKeyBackend + KeyGen + 'static>(
+            recip: &K,
+            aead: HpkeAead,
+            info: &[u8],
+            aad: &[u8],
+            message: &[u8],
+            psk: Option<HpkePsk<'_>>,
+            sender: Option<&LocalKey>,
+        ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+            if let Some(sender) = sender {
+                sender.check_policy(KeyUsage::Derive, true)?;
+            }
+            let sender = sender.map(hpke_key::<K>).transpose()?;
+            let mut enc = Vec::with_capacity(K::ENC_LENGTH);
+            let mut ctx = hpke::setup_sender(aead, recip, info, psk, sender, &mut enc)?;
+            let mut buf = SecretBytes::from_slice_reserve(message, 16);
+            ctx.seal_in_place(&mut buf, aad)?;
+            Ok((enc, buf.into_vec()))
+        }
+
+        if let Some(recip) = self.inner.downcast_ref::<X25519KeyPair>() {
+            seal(recip, aead, info, aad, message, psk, sender)
+        } else if let Some(recip) = self.inner.downcast_ref::<P256KeyPair>() {
+            seal(recip, aead, info, aad, message, psk, sender)
+        } else {
+            Err(err_msg!(Unsupported, "Unsupported key type for HPKE"))
+        }
+    }
+
+    /// Decrypt a message encrypted to this recipient key using HPKE (RFC 9180)
+    #[allow(clippy::too_many_arguments)]
+    pub fn hpke_open(
+        &self,
+        aead: HpkeAead,
+        enc: &[u8],
+        info: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+        psk: Option<HpkePsk<'_>>,
+        sender: Option<&LocalKey>,
+    ) -> Result<SecretBytes, Error> {
+        self.check_policy(KeyUsage::Derive, true)?;
+        #[allow(clippy::too_many_arguments)]
+        fn open<K: HpkeKemKey + HasKeyAlg + HasKeyBackend + 'static>(
+            recip: &K,
+            aead: HpkeAead,
+            enc: &[u8],
+            info: &[u8],
+            aad: &[u8],
+            ciphertext: &[u8],
+            psk: Option<HpkePsk<'_>>,
+            sender: Option<&LocalKey>,
+        ) -> Result<SecretBytes, Error> {
+            if let Some(sender) = sender {
+                sender.check_policy(KeyUsage::Derive, true)?;
+            }
+            let sender = sender.map(hpke_key::<K>).transpose()?;
+            let mut ctx = hpke::setup_recipient(aead, enc, recip, info, psk, sender)?;
+            let mut buf = SecretBytes::from_slice(ciphertext);
+            ctx.open_in_place(&mut buf, aad)?;
+            Ok(buf)
+        }
+
+        if let Some(recip) = self.inner.downcast_ref::<X25519KeyPair>() {
+            open(recip, aead, enc, info, aad, ciphertext, psk, sender)
+        } else if let Some(recip) = self.inner.downcast_ref::<P256KeyPair>() {
+            open(recip, aead, enc, info, aad, ciphertext, psk, sender)
+        } else {
+            Err(err_msg!(Unsupported, "Unsupported key type for HPKE"))
+        }
+    }
+
+    /// Attach a usage policy restricting the operations performed with this key
+    ///
+    /// The policy is stored with the key when it is inserted into a store. An
+    /// existing policy may not be replaced.
+    pub fn with_policy(mut self, policy: KeyPolicy) -> Result<Self, Error> {
+        if self.policy.is_some() {
+            return Err(err_msg!(Input, "Key policy is already assigned"));
+        }
+        self.policy.replace(Arc::new(PolicyState::new(policy)));
+        Ok(self)
+    }
+
+    /// Accessor for the key usage policy
+    pub fn policy(&self) -> Option<&KeyPolicy> {
+        self.policy.as_deref().map(|state| &state.policy)
+    }
+
+    pub(crate) fn check_policy(&self, usage: KeyUsage, consume: bool) -> Result<(), CryptoError> {
+        if let Some(state) = self.policy.as_deref() {
+            state.check(usage, consume)
+        } else {
+            Ok(())
+        }
+    }
+
     /// Check whether the key is hardware backed by checking the type id of the underlying
     /// structure
     pub fn is_hardware_backed(&self) -> bool {
@@ -309,12 +897,115 @@ impl LocalKey {
     }
 }
 
+fn is_symmetric(alg: KeyAlg) -> bool {
+    matches!(alg, KeyAlg::Aes(_) | KeyAlg::Chacha20(_) | KeyAlg::Hmac(_))
+}
+
+struct HkdfDerive<'d> {
+    ikm: &'d [u8],
+    params: HkdfParams<'d>,
+}
+
+impl KeyDerivation for HkdfDerive<'_> {
+    fn derive_key_bytes(&mut self, key_output: &mut [u8]) -> Result<(), CryptoError> {
+        self.params.derive_key_bytes(self.ikm, key_output)
+    }
+}
+
+fn hpke_key<K: HpkeKemKey + HasKeyAlg + HasKeyBackend + 'static>(
+    key: &LocalKey,
+) -> Result<&K, Error> {
+    key.inner
+        .downcast_ref::<K>()
+        .ok_or_else(|| err_msg!(Input, "Mismatched key types for HPKE"))
+}
+
 impl KeyExchange for LocalKey {
     fn write_key_exchange(
         &self,
         other: &LocalKey,
         out: &mut dyn WriteBuffer,
     ) -> Result<(), CryptoError> {
+        self.check_policy(KeyUsage::Derive, true)?;
         self.inner.write_key_exchange(&other.inner, out)
     }
 }
+
+impl KeySign for LocalKey {
+    fn write_signature(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), CryptoError> {
+        self.check_policy(KeyUsage::Sign, true)?;
+        self.inner.write_signature(message, sig_type, out)
+    }
+
+    fn write_signature_format(
+        &self,
+        message: &[u8],
+        sig_type: Option<SignatureType>,
+        format: SignatureFormat,
+        out: &mut dyn WriteBuffer,
+    ) -> Result<(), CryptoError> {
+        self.check_policy(KeyUsage::Sign, true)?;
+        self.inner
+            .write_signature_format(message, sig_type, format, out)
+    }
+}
+
+impl ToPkcs8 for LocalKey {
+    fn to_pkcs8_der(&self) -> Result<SecretBytes, CryptoError> {
+        self.check_policy(KeyUsage::Export, false)?;
+        self.inner.to_pkcs8_der()
+    }
+
+    fn to_public_key_der(&self) -> Result<Vec<u8>, CryptoError> {
+        self.inner.to_public_key_der()
+    }
+}
+
+impl FromPkcs8 for LocalKey {
+    fn from_pkcs8_der(der: &[u8]) -> Result<Self, CryptoError> {
+        let inner = Box::<AnyKey>::from_pkcs8_der(der)?;
+        Ok(Self {
+            inner,
+            ephemeral: false,
+            policy: None,
+        })
+    }
+
+    fn from_public_key_der(der: &[u8]) -> Result<Self, CryptoError> {
+        let inner = Box::<AnyKey>::from_public_key_der(der)?;
+        Ok(Self {
+            inner,
+            ephemeral: false,
+            policy: None,
+        })
+    }
+}
+
+impl KeySigVerify for LocalKey {
+    fn verify_signature(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+    ) -> Result<bool, CryptoError> {
+        self.check_policy(KeyUsage::Sign, false)?;
+        self.inner.verify_signature(message, signature, sig_type)
+    }
+
+    fn verify_signature_format(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        sig_type: Option<SignatureType>,
+        format: SignatureFormat,
+    ) -> Result<bool, CryptoError> {
+        self.check_policy(KeyUsage::Sign, false)?;
+        self.inner
+            .verify_signature_format(message, signature, sig_type, format)
+    }
+}
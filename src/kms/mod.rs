@@ -9,13 +9,17 @@ use zeroize::Zeroize;
 
 use crate::error::Error;
 
+mod attest;
+pub use self::attest::{KeyAttestationBuilder, KEY_ATTESTATION_TYP};
+
 mod enc;
 pub use self::enc::{Encrypted, SecretBytes, ToDecrypt};
 
 mod envelope;
 pub use self::envelope::{
-    crypto_box, crypto_box_open, crypto_box_random_nonce, crypto_box_seal, crypto_box_seal_open,
-    derive_key_ecdh_1pu, derive_key_ecdh_es,
+    authcrypt, authcrypt_open, crypto_box, crypto_box_open, crypto_box_random_nonce,
+    crypto_box_seal, crypto_box_seal_open, derive_bytes_ecdh_es, derive_key_ecdh_1pu,
+    derive_key_ecdh_es, seal_for, seal_open,
 };
 
 mod entry;
@@ -24,11 +28,16 @@ pub use self::entry::{KeyEntry, KeyParams, KeyReference};
 mod local_key;
 pub use self::local_key::{KeyAlg, KeyBackend, LocalKey};
 
+mod policy;
+pub use self::policy::{KeyPolicy, KeyUsage};
+
 /// Supported categories of KMS entries
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Zeroize)]
 pub(crate) enum KmsCategory {
     /// A stored key or keypair
     CryptoKey,
+    /// A prior version of a rotated key or keypair
+    CryptoKeyVersion,
     // future options: Mnemonic, Entropy
 }
 
@@ -37,6 +46,7 @@ impl KmsCategory {
     pub fn as_str(&self) -> &str {
         match self {
             Self::CryptoKey => "cryptokey",
+            Self::CryptoKeyVersion => "cryptokey_version",
         }
     }
 }
@@ -53,6 +63,7 @@ impl FromStr for KmsCategory {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s {
             "cryptokey" => Self::CryptoKey,
+            "cryptokey_version" => Self::CryptoKeyVersion,
             _ => return Err(err_msg!("Unknown KMS category: {}", s)),
         })
     }
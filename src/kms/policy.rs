@@ -0,0 +1,133 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::crypto::{Error as CryptoError, ErrorKind as CryptoErrorKind};
+
+/// Categories of key operations which may be permitted by a key policy
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyUsage {
+    /// Message signing and MAC generation, and their verification
+    Sign,
+    /// AEAD message encryption and decryption
+    Encrypt,
+    /// Key agreement and the derivation of new keys
+    Derive,
+    /// Wrapping and unwrapping other keys
+    Wrap,
+    /// Export of the secret key material
+    Export,
+}
+
+/// A policy restricting the operations which may be performed with a key
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct KeyPolicy {
+    /// The permitted operations, or an empty list to permit any operation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub usages: Vec<KeyUsage>,
+
+    /// The maximum number of operations using the secret key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_uses: Option<u64>,
+
+    /// The time after which the key may not be used, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<i64>,
+}
+
+impl KeyPolicy {
+    /// Create a policy permitting only the given operations
+    pub fn new(usages: impl IntoIterator<Item = KeyUsage>) -> Self {
+        Self {
+            usages: usages.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Limit the number of operations using the secret key
+    pub fn with_max_uses(mut self, max_uses: u64) -> Self {
+        self.max_uses.replace(max_uses);
+        self
+    }
+
+    /// Set the time after which the key may not be used
+    pub fn with_not_after(mut self, not_after: i64) -> Self {
+        self.not_after.replace(not_after);
+        self
+    }
+
+    /// Determine whether a category of operations is permitted
+    pub fn permits(&self, usage: KeyUsage) -> bool {
+        self.usages.is_empty() || self.usages.contains(&usage)
+    }
+}
+
+/// A key policy together with the running count of key uses
+#[derive(Debug)]
+pub(crate) struct PolicyState {
+    pub(crate) policy: KeyPolicy,
+    uses: AtomicU64,
+}
+
+impl PolicyState {
+    pub fn new(policy: KeyPolicy) -> Self {
+        Self {
+            policy,
+            uses: AtomicU64::new(0),
+        }
+    }
+
+    /// Check that an operation is permitted, counting it as a use of the
+    /// secret key when `consume` is set
+    pub fn check(&self, usage: KeyUsage, consume: bool) -> Result<(), CryptoError> {
+        if !self.policy.permits(usage) {
+            return Err(CryptoError::from_msg(
+                CryptoErrorKind::Usage,
+                "Key usage not permitted by policy",
+            ));
+        }
+        if let Some(not_after) = self.policy.not_after {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or_default();
+            if now > not_after {
+                return Err(CryptoError::from_msg(
+                    CryptoErrorKind::Usage,
+                    "Key policy has expired",
+                ));
+            }
+        }
+        if let (Some(max_uses), true) = (self.policy.max_uses, consume) {
+            self.uses
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |uses| {
+                    (uses < max_uses).then_some(uses + 1)
+                })
+                .map_err(|_| {
+                    CryptoError::from_msg(CryptoErrorKind::Usage, "Key policy use limit exceeded")
+                })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_check() {
+        let state = PolicyState::new(KeyPolicy::new([KeyUsage::Sign]).with_max_uses(2));
+        assert!(state.check(KeyUsage::Derive, true).is_err());
+        state.check(KeyUsage::Sign, true).unwrap();
+        state.check(KeyUsage::Sign, false).unwrap();
+        state.check(KeyUsage::Sign, true).unwrap();
+        assert!(state.check(KeyUsage::Sign, true).is_err());
+        assert!(state.check(KeyUsage::Sign, false).is_ok());
+
+        let state = PolicyState::new(KeyPolicy::default().with_not_after(0));
+        assert!(state.check(KeyUsage::Encrypt, false).is_err());
+    }
+}
@@ -24,7 +24,13 @@ pub use askar_storage::future;
 #[cfg(feature = "ffi")]
 mod ffi;
 
+pub mod did;
+
+pub mod jose;
+
 pub mod kms;
 
+pub use askar_crypto::secret;
+
 mod store;
-pub use store::{entry, PassKey, Session, Store, StoreKeyMethod};
+pub use store::{entry, PassKey, Session, Store, StoreKeyMethod, WrapKeyProvider};
@@ -1,17 +1,25 @@
-use askar_storage::backend::{copy_profile, OrderBy};
+use std::{collections::BTreeMap, str::FromStr};
+
+use askar_storage::backend::{
+    copy_profile, import_profile, resume_scan, scan_with_cursor, OrderBy, RekeyProgress,
+};
 
 use crate::{
+    crypto::buffer::SecretBytes,
     error::Error,
-    kms::{KeyEntry, KeyParams, KeyReference, KmsCategory, LocalKey},
+    kms::{KeyAlg, KeyEntry, KeyParams, KeyReference, KmsCategory, LocalKey},
     storage::{
         any::{AnyBackend, AnyBackendSession},
         backend::{Backend, BackendSession, ManageBackend},
-        entry::{Entry, EntryKind, EntryOperation, EntryTag, Scan, TagFilter},
+        entry::{
+            AuditRecord, Entry, EntryKind, EntryOperation, EntryTag, EntryUpdate, EntryVersion,
+            ProfileDetails, Scan, ScanCursor, Subscription, TagFilter,
+        },
         generate_raw_store_key,
     },
 };
 
-pub use crate::storage::{entry, PassKey, StoreKeyMethod};
+pub use crate::storage::{entry, PassKey, StoreKeyMethod, WrapKeyProvider};
 
 #[derive(Debug, Clone)]
 /// An instance of an opened store
@@ -72,6 +80,63 @@ impl Store {
         Ok(self.0.set_default_profile(profile).await?)
     }
 
+    /// Enable or disable soft deletion of records
+    ///
+    /// While enabled, removed records are retained as deleted and may be
+    /// restored until they are purged.
+    pub async fn set_soft_delete(&self, enabled: bool) -> Result<(), Error> {
+        Ok(self.0.set_soft_delete(enabled).await?)
+    }
+
+    /// Enable or disable the audit log
+    ///
+    /// While enabled, each record insert, replace and removal is appended to
+    /// the audit log of the affected profile. Record values are not logged.
+    pub async fn set_audit_log(&self, enabled: bool) -> Result<(), Error> {
+        Ok(self.0.set_audit_log(enabled).await?)
+    }
+
+    /// Fetch a store-level metadata value
+    pub async fn get_meta(&self, name: &str) -> Result<Option<SecretBytes>, Error> {
+        Ok(self.0.get_meta(name.to_string()).await?)
+    }
+
+    /// Set a store-level metadata value, or remove it when `value` is `None`
+    ///
+    /// Store metadata is encrypted using the store key and is kept separate
+    /// from the records of all profiles.
+    pub async fn set_meta(&self, name: &str, value: Option<&[u8]>) -> Result<(), Error> {
+        Ok(self
+            .0
+            .set_meta(name.to_string(), value.map(SecretBytes::from_slice))
+            .await?)
+    }
+
+    /// Fetch a metadata value of a profile
+    pub async fn get_profile_meta(
+        &self,
+        profile: Option<String>,
+        name: &str,
+    ) -> Result<Option<SecretBytes>, Error> {
+        let mut session = self.session(profile).await?;
+        let value = session.get_profile_meta(name).await?;
+        session.0.close(false).await?;
+        Ok(value)
+    }
+
+    /// Set a metadata value of a profile, or remove it when `value` is `None`
+    pub async fn set_profile_meta(
+        &self,
+        profile: Option<String>,
+        name: &str,
+        value: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        let mut session = self.session(profile).await?;
+        session.set_profile_meta(name, value).await?;
+        session.0.close(true).await?;
+        Ok(())
+    }
+
     /// Replace the wrapping key on a store
     pub async fn rekey(
         &mut self,
@@ -81,6 +146,63 @@ impl Store {
         Ok(self.0.rekey(method, pass_key).await?)
     }
 
+    /// Replace the wrapping key on a store, reporting progress
+    ///
+    /// When the store has a master key, only the master key is encrypted again.
+    /// Otherwise the profile keys are re-encrypted in batches, each in a separate
+    /// transaction, and the `progress` callback receives the number of profiles
+    /// processed and the total number of profiles after each batch. An
+    /// interrupted rekey may be resumed by opening the store with the previous
+    /// pass key and repeating the rekey with the same key method and pass key.
+    pub async fn rekey_with_progress(
+        &mut self,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+        progress: Option<RekeyProgress>,
+    ) -> Result<(), Error> {
+        Ok(self
+            .0
+            .rekey_with_progress(method, pass_key, progress)
+            .await?)
+    }
+
+    /// Replace the key of a single profile, re-encrypting the records of the profile
+    ///
+    /// The `progress` callback receives the number of records processed and the
+    /// total number of records after each batch. The search index of the profile
+    /// is rebuilt using the new profile key.
+    pub async fn rekey_profile(
+        &self,
+        profile: String,
+        progress: Option<RekeyProgress>,
+    ) -> Result<(), Error> {
+        Ok(self.0.rekey_profile(profile, progress).await?)
+    }
+
+    /// Add a named key slot, allowing the store to be opened using another pass key
+    ///
+    /// The store must have a master key, which is the case for protected stores
+    /// created by this version or rekeyed since. Rekeying the store leaves its
+    /// key slots unchanged.
+    pub async fn add_key_slot(
+        &self,
+        name: String,
+        method: StoreKeyMethod,
+        pass_key: PassKey<'_>,
+    ) -> Result<(), Error> {
+        Ok(self.0.add_key_slot(name, method, pass_key).await?)
+    }
+
+    /// Remove a key slot, returning `false` if it was not found
+    pub async fn remove_key_slot(&self, name: String) -> Result<bool, Error> {
+        Ok(self.0.remove_key_slot(name).await?)
+    }
+
+    /// Get the names of the key slots of the store
+    pub async fn list_key_slots(&self) -> Result<Vec<String>, Error> {
+        Ok(self.0.list_key_slots().await?)
+    }
+
     /// Copy to a new store instance using a database URL
     pub async fn copy_to(
         &self,
@@ -92,10 +214,20 @@ impl Store {
         let default_profile = self.get_default_profile().await?;
         let profile_ids = self.list_profiles().await?;
         let target = target_url
-            .provision_backend(key_method, pass_key, Some(default_profile), recreate)
+            .provision_backend(
+                key_method,
+                pass_key,
+                Some(default_profile.clone()),
+                recreate,
+            )
             .await?;
         for profile in profile_ids {
-            copy_profile(&self.0, &target, &profile, &profile).await?;
+            if profile == default_profile {
+                // the default profile is created when the target is provisioned
+                import_profile(&self.0, &target, &profile, &profile).await?;
+            } else {
+                copy_profile(&self.0, &target, &profile, &profile).await?;
+            }
         }
         Ok(Self::new(target))
     }
@@ -110,14 +242,53 @@ impl Store {
         Ok(self.0.list_profiles().await?)
     }
 
+    /// Get the details of store profiles in order of profile name
+    ///
+    /// The results include the creation and last access times of each profile
+    /// along with the number of records it contains. When `name_prefix` is
+    /// provided, only profiles with names starting with the prefix are returned.
+    pub async fn list_profile_details(
+        &self,
+        name_prefix: Option<&str>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> Result<Vec<ProfileDetails>, Error> {
+        Ok(self
+            .0
+            .list_profile_details(name_prefix.map(str::to_string), offset, limit)
+            .await?)
+    }
+
     /// Remove an existing profile with the given profile namestore.r
     pub async fn remove_profile(&self, name: String) -> Result<bool, Error> {
         Ok(self.0.remove_profile(name).await?)
     }
 
+    /// Rename an existing profile, returning `false` if it was not found
+    ///
+    /// The default profile name is updated when it refers to the renamed
+    /// profile. The active profile of the store may not be renamed.
+    pub async fn rename_profile(&self, from: String, to: String) -> Result<bool, Error> {
+        Ok(self.0.rename_profile(from, to).await?)
+    }
+
+    /// Copy all records of an existing profile into a new profile
+    ///
+    /// The new profile is created with its own profile key, and the records
+    /// are re-encrypted under it. The new profile is removed if the copy fails.
+    /// Soft-deleted records, record history, indexed search terms and profile
+    /// metadata are not copied.
+    pub async fn copy_profile(&self, from: String, to: String) -> Result<(), Error> {
+        Ok(copy_profile(&self.0, &self.0, &from, &to).await?)
+    }
+
     /// Create a new scan instance against the store
     ///
-    /// The result will keep an open connection to the backend until it is consumed
+    /// The result will keep an open connection to the backend until it is consumed.
+    /// The position of the scan may be recorded using `Scan::cursor` and encoded
+    /// using `Store::scan_cursor_token`, in order to continue it later using
+    /// `Store::resume_scan`. Records which have been soft deleted are only
+    /// returned when `include_deleted` is set, and report a `deleted_at` time.
     #[allow(clippy::too_many_arguments)]
     pub async fn scan(
         &self,
@@ -128,22 +299,72 @@ impl Store {
         limit: Option<i64>,
         order_by: Option<OrderBy>,
         descending: bool,
+        include_deleted: bool,
+    ) -> Result<Scan<'static, Entry>, Error> {
+        Ok(scan_with_cursor(
+            &self.0,
+            profile,
+            Some(EntryKind::Item),
+            category,
+            tag_filter,
+            offset,
+            limit,
+            order_by,
+            descending,
+            include_deleted,
+        )
+        .await?)
+    }
+
+    /// Encode the position of a scan as a cursor token
+    ///
+    /// The token is encrypted using a key derived from the store key, and may
+    /// be passed to `Store::resume_scan` in order to continue the scan.
+    pub async fn scan_cursor_token(&self, cursor: &ScanCursor) -> Result<String, Error> {
+        Ok(cursor.to_token(&self.0).await?)
+    }
+
+    /// Continue a scan from a cursor token, returning at most `limit` further rows
+    ///
+    /// The profile is not recorded in the cursor token, and must match the profile
+    /// of the original scan. Tokens which were not issued by this store are rejected.
+    pub async fn resume_scan(
+        &self,
+        profile: Option<String>,
+        cursor: &str,
+        limit: Option<i64>,
     ) -> Result<Scan<'static, Entry>, Error> {
+        let cursor = ScanCursor::from_token(&self.0, cursor).await?;
+        Ok(resume_scan(&self.0, profile, &cursor, limit).await?)
+    }
+
+    /// Subscribe to committed changes to the records of a profile
+    ///
+    /// Change events are delivered once the modifying transaction has been
+    /// committed, optionally limited to records in a single `category`.
+    pub async fn subscribe(
+        &self,
+        profile: Option<String>,
+        category: Option<String>,
+    ) -> Result<Subscription, Error> {
         Ok(self
             .0
-            .scan(
-                profile,
-                Some(EntryKind::Item),
-                category,
-                tag_filter,
-                offset,
-                limit,
-                order_by,
-                descending,
-            )
+            .subscribe(profile, Some(EntryKind::Item), category)
             .await?)
     }
 
+    /// Count the number of entries in a profile for each record category
+    pub async fn count_by_category(
+        &self,
+        profile: Option<String>,
+        tag_filter: Option<TagFilter>,
+    ) -> Result<BTreeMap<String, i64>, Error> {
+        let mut session = self.session(profile).await?;
+        let counts = session.count_by_category(tag_filter).await?;
+        session.0.close(false).await?;
+        Ok(counts)
+    }
+
     /// Create a new session against the store
     pub async fn session(&self, profile: Option<String>) -> Result<Session, Error> {
         let mut sess = Session::new(self.0.session(profile, false)?);
@@ -187,15 +408,44 @@ impl Session {
         Self(inner)
     }
 
-    /// Count the number of entries for a given record category
+    /// Count the number of entries for a given record category, including
+    /// those which have been soft deleted when `include_deleted` is set
     pub async fn count(
         &mut self,
         category: Option<&str>,
         tag_filter: Option<TagFilter>,
+        include_deleted: bool,
     ) -> Result<i64, Error> {
         Ok(self
             .0
-            .count(Some(EntryKind::Item), category, tag_filter)
+            .count(Some(EntryKind::Item), category, tag_filter, include_deleted)
+            .await?)
+    }
+
+    /// Count the number of entries matching a `tag_filter` for each record category
+    pub async fn count_by_category(
+        &mut self,
+        tag_filter: Option<TagFilter>,
+    ) -> Result<BTreeMap<String, i64>, Error> {
+        Ok(self
+            .0
+            .count_by_category(Some(EntryKind::Item), tag_filter)
+            .await?)
+    }
+
+    /// Count the entries in a category matching a `tag_filter` for each distinct value
+    /// of a tag
+    ///
+    /// Plaintext tag names are indicated by a `~` prefix, as in tag filters.
+    pub async fn list_tag_values(
+        &mut self,
+        category: &str,
+        tag_name: &str,
+        tag_filter: Option<TagFilter>,
+    ) -> Result<BTreeMap<String, i64>, Error> {
+        Ok(self
+            .0
+            .list_tag_values(EntryKind::Item, category, tag_name, tag_filter)
             .await?)
     }
 
@@ -215,6 +465,22 @@ impl Session {
             .await?)
     }
 
+    /// Fetch a set of records from the store by category and name
+    ///
+    /// The result contains an entry for each requested name, in the same
+    /// order, which is `None` when the record was not found.
+    pub async fn fetch_many(
+        &mut self,
+        category: &str,
+        names: &[String],
+        for_update: bool,
+    ) -> Result<Vec<Option<Entry>>, Error> {
+        Ok(self
+            .0
+            .fetch_many(EntryKind::Item, category, names, for_update)
+            .await?)
+    }
+
     /// Retrieve all records matching the given `category` and `tag_filter`.
     ///
     /// Unlike `Store::scan`, this method may be used within a transaction. It should
@@ -305,6 +571,89 @@ impl Session {
             .await?)
     }
 
+    /// Insert a new record into the store, or replace the value and tags of
+    /// an existing record with the same category and name
+    pub async fn upsert(
+        &mut self,
+        category: &str,
+        name: &str,
+        value: &[u8],
+        tags: Option<&[EntryTag]>,
+        expiry_ms: Option<i64>,
+    ) -> Result<(), Error> {
+        Ok(self
+            .0
+            .update(
+                EntryKind::Item,
+                EntryOperation::InsertOrReplace,
+                category,
+                name,
+                Some(value),
+                tags,
+                expiry_ms,
+            )
+            .await?)
+    }
+
+    /// Insert a batch of new records into the store in a single transaction
+    ///
+    /// The result for each record indicates whether it was inserted, or
+    /// was rejected as a duplicate.
+    pub async fn insert_many(
+        &mut self,
+        entries: Vec<Entry>,
+        expiry_ms: Option<i64>,
+    ) -> Result<Vec<Result<(), Error>>, Error> {
+        self.update_many(
+            entries
+                .into_iter()
+                .map(|entry| {
+                    EntryUpdate::insert(
+                        entry.category,
+                        entry.name,
+                        entry.value,
+                        Some(entry.tags),
+                        expiry_ms,
+                    )
+                })
+                .collect(),
+        )
+        .await
+    }
+
+    /// Insert, replace or remove a batch of records in a single transaction
+    ///
+    /// The result for each update indicates whether it was applied, or was
+    /// rejected because the record was a duplicate or not found.
+    pub async fn update_many(
+        &mut self,
+        updates: Vec<EntryUpdate>,
+    ) -> Result<Vec<Result<(), Error>>, Error> {
+        let results = self.0.update_many(EntryKind::Item, updates).await?;
+        Ok(results
+            .into_iter()
+            .map(|result| result.map_err(Error::from))
+            .collect())
+    }
+
+    /// Remove a batch of records in a category in a single transaction
+    ///
+    /// The result for each name indicates whether the record was removed,
+    /// or was not found.
+    pub async fn remove_many(
+        &mut self,
+        category: &str,
+        names: &[String],
+    ) -> Result<Vec<Result<(), Error>>, Error> {
+        self.update_many(
+            names
+                .iter()
+                .map(|name| EntryUpdate::remove(category, name.as_str()))
+                .collect(),
+        )
+        .await
+    }
+
     /// Remove all records in the store matching a given `category` and `tag_filter`
     pub async fn remove_all(
         &mut self,
@@ -317,6 +666,134 @@ impl Session {
             .await?)
     }
 
+    /// Permanently remove all records which have passed their expiry time,
+    /// optionally limited to a single `category`
+    pub async fn remove_expired(&mut self, category: Option<&str>) -> Result<i64, Error> {
+        Ok(self
+            .0
+            .remove_expired(Some(EntryKind::Item), category)
+            .await?)
+    }
+
+    /// Restore a record which has been soft deleted
+    pub async fn restore(&mut self, category: &str, name: &str) -> Result<(), Error> {
+        Ok(self.0.restore(EntryKind::Item, category, name).await?)
+    }
+
+    /// Permanently remove soft deleted records, including keys, returning
+    /// the number removed
+    ///
+    /// When `older_than_ms` is provided, only records deleted at least that
+    /// many milliseconds ago are removed.
+    pub async fn purge(&mut self, older_than_ms: Option<i64>) -> Result<i64, Error> {
+        Ok(self.0.purge(None, older_than_ms).await?)
+    }
+
+    /// Enable or disable the preservation of prior record versions for a category
+    ///
+    /// While enabled, the value and tags of a record are preserved each time
+    /// it is replaced.
+    pub async fn set_history(&mut self, category: &str, enabled: bool) -> Result<(), Error> {
+        Ok(self
+            .0
+            .set_history(EntryKind::Item, category, enabled)
+            .await?)
+    }
+
+    /// Fetch the prior versions of a record, most recent first
+    pub async fn fetch_history(
+        &mut self,
+        category: &str,
+        name: &str,
+        limit: Option<i64>,
+    ) -> Result<Vec<EntryVersion>, Error> {
+        Ok(self
+            .0
+            .fetch_history(EntryKind::Item, category, name, limit)
+            .await?)
+    }
+
+    /// Fetch a single prior version of a record
+    pub async fn fetch_version(
+        &mut self,
+        category: &str,
+        name: &str,
+        version: i64,
+    ) -> Result<Option<EntryVersion>, Error> {
+        Ok(self
+            .0
+            .fetch_version(EntryKind::Item, category, name, version)
+            .await?)
+    }
+
+    /// Remove preserved record versions, optionally limited to a single
+    /// `category` or to versions replaced at least `older_than_ms` ago
+    pub async fn prune_history(
+        &mut self,
+        category: Option<&str>,
+        older_than_ms: Option<i64>,
+    ) -> Result<i64, Error> {
+        Ok(self
+            .0
+            .prune_history(Some(EntryKind::Item), category, older_than_ms)
+            .await?)
+    }
+
+    /// Fetch audit log records for the current profile, oldest first
+    ///
+    /// When `after_id` is provided, only records with a greater identifier
+    /// are returned.
+    pub async fn fetch_audit_log(
+        &mut self,
+        after_id: Option<i64>,
+        limit: Option<i64>,
+    ) -> Result<Vec<AuditRecord>, Error> {
+        Ok(self.0.fetch_audit_log(after_id, limit).await?)
+    }
+
+    /// Fetch a metadata value of the current profile
+    pub async fn get_profile_meta(&mut self, name: &str) -> Result<Option<SecretBytes>, Error> {
+        Ok(self.0.get_profile_meta(name).await?)
+    }
+
+    /// Set a metadata value of the current profile, or remove it when `value` is `None`
+    ///
+    /// Profile metadata is encrypted using the profile key and is not included
+    /// in record counts, scans or exports.
+    pub async fn set_profile_meta(
+        &mut self,
+        name: &str,
+        value: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        Ok(self.0.set_profile_meta(name, value).await?)
+    }
+
+    /// Export audit log records for the current profile as a JSON array
+    ///
+    /// The chain of authentication codes for the records is verified, and
+    /// an error is returned if any record has been altered or removed.
+    pub async fn export_audit_log(&mut self, after_id: Option<i64>) -> Result<String, Error> {
+        let records = self.0.fetch_audit_log(after_id, None).await?;
+        let json = records
+            .into_iter()
+            .map(|record| {
+                serde_json::json!({
+                    "id": record.id,
+                    "session_id": record.session_id,
+                    "operation": record.operation,
+                    "kind": record.kind.map(|kind| match kind {
+                        EntryKind::Kms => "kms",
+                        EntryKind::Item => "item",
+                    }),
+                    "category": record.category,
+                    "item_id": record.item_id,
+                    "timestamp_ms": record.timestamp_ms,
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_string(&json).map_err(err_map!(Unexpected, "Error encoding audit log"))
+    }
+
     /// Perform a record update
     ///
     /// This may correspond to an record insert, replace, or remove depending on
@@ -344,6 +821,66 @@ impl Session {
             .await?)
     }
 
+    /// Replace or remove a record only if its version matches `version`
+    ///
+    /// The current version of a record is available from `Entry::version`
+    /// when it is fetched. If the record has been modified since then, an
+    /// error of kind `Conflict` is returned.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_versioned(
+        &mut self,
+        operation: EntryOperation,
+        category: &str,
+        name: &str,
+        value: Option<&[u8]>,
+        tags: Option<&[EntryTag]>,
+        expiry_ms: Option<i64>,
+        version: i64,
+    ) -> Result<(), Error> {
+        Ok(self
+            .0
+            .update_versioned(
+                EntryKind::Item,
+                operation,
+                category,
+                name,
+                value,
+                tags,
+                expiry_ms,
+                version,
+            )
+            .await?)
+    }
+
+    /// Replace the search index terms for an existing record
+    ///
+    /// Each term is stored as a keyed hash scoped to the record category, so
+    /// only exact term matches may be found using `search_values`.
+    pub async fn update_search_terms(
+        &mut self,
+        category: &str,
+        name: &str,
+        terms: &[String],
+    ) -> Result<(), Error> {
+        Ok(self
+            .0
+            .update_search_terms(EntryKind::Item, category, name, terms)
+            .await?)
+    }
+
+    /// Fetch the records in a category which were indexed with all of the given terms
+    pub async fn search_values(
+        &mut self,
+        category: &str,
+        terms: &[String],
+        limit: Option<i64>,
+    ) -> Result<Vec<Entry>, Error> {
+        Ok(self
+            .0
+            .search_values(EntryKind::Item, category, terms, limit)
+            .await?)
+    }
+
     /// Insert a local key instance into the store
     pub async fn insert_key(
         &mut self,
@@ -363,17 +900,11 @@ impl Session {
             metadata: metadata.map(str::to_string),
             reference,
             data: Some(data),
+            policy: key.policy().cloned(),
+            version: None,
         };
         let value = params.to_bytes()?;
-        let mut ins_tags = Vec::with_capacity(10);
-        let alg = key.algorithm().as_str();
-        if !alg.is_empty() {
-            ins_tags.push(EntryTag::Encrypted("alg".to_string(), alg.to_string()));
-        }
-        let thumbs = key.to_jwk_thumbprints()?;
-        for thumb in thumbs {
-            ins_tags.push(EntryTag::Encrypted("thumb".to_string(), thumb));
-        }
+        let mut ins_tags = key_tags(key)?;
         if let Some(tags) = tags {
             for t in tags {
                 ins_tags.push(t.map_ref(|k, v| (format!("user:{}", k), v.to_string())));
@@ -470,10 +1001,104 @@ impl Session {
         Ok(entries)
     }
 
-    /// Remove an existing key from the store
-    pub async fn remove_key(&mut self, name: &str) -> Result<(), Error> {
+    /// Fetch a specific version of a key, including prior versions of a rotated key
+    pub async fn fetch_key_version(
+        &mut self,
+        name: &str,
+        version: u32,
+        for_update: bool,
+    ) -> Result<Option<KeyEntry>, Error> {
+        if let Some(row) = self
+            .0
+            .fetch(
+                EntryKind::Kms,
+                KmsCategory::CryptoKeyVersion.as_str(),
+                &versioned_key_name(name, version),
+                for_update,
+            )
+            .await?
+        {
+            let mut entry = KeyEntry::from_entry(row)?;
+            entry.name = name.to_string();
+            return Ok(Some(entry));
+        }
         Ok(self
+            .fetch_key(name, for_update)
+            .await?
+            .filter(|entry| entry.version() == version))
+    }
+
+    /// Replace an existing key with a new random key of the same algorithm
+    ///
+    /// The metadata, tags and policy of the key are retained and the version is
+    /// incremented. The previous version remains available from `fetch_key_version`
+    /// for verification or decryption, while `fetch_key` returns the latest version.
+    /// This should be performed within a transaction.
+    pub async fn rotate_key(&mut self, name: &str) -> Result<KeyEntry, Error> {
+        let row = self
             .0
+            .fetch(EntryKind::Kms, KmsCategory::CryptoKey.as_str(), name, true)
+            .await?
+            .ok_or_else(|| err_msg!(NotFound, "Key entry not found"))?;
+        let prev = KeyEntry::from_entry(row.clone())?;
+        if !prev.is_local() {
+            return Err(err_msg!(Unsupported, "Only local keys may be rotated"));
+        }
+        let alg = KeyAlg::from_str(
+            prev.algorithm()
+                .ok_or_else(|| err_msg!(Input, "Missing algorithm for key entry"))?,
+        )?;
+        let version = prev.version();
+
+        let mut arch_tags = row.tags.clone();
+        arch_tags.push(EntryTag::Encrypted("name".to_string(), name.to_string()));
+        self.0
+            .update(
+                EntryKind::Kms,
+                EntryOperation::Insert,
+                KmsCategory::CryptoKeyVersion.as_str(),
+                &versioned_key_name(name, version),
+                Some(row.value.as_ref()),
+                Some(arch_tags.as_slice()),
+                None,
+            )
+            .await?;
+
+        let key = LocalKey::generate_with_rng(alg, false)?;
+        let params = KeyParams {
+            metadata: prev.params.metadata.clone(),
+            reference: None,
+            data: Some(key.encode()?),
+            policy: prev.params.policy.clone(),
+            version: Some(version + 1),
+        };
+        let value = params.to_bytes()?;
+        let mut upd_tags = key_tags(&key)?;
+        for t in row.tags {
+            if t.name().starts_with("user:") {
+                upd_tags.push(t);
+            }
+        }
+        self.0
+            .update(
+                EntryKind::Kms,
+                EntryOperation::Replace,
+                KmsCategory::CryptoKey.as_str(),
+                name,
+                Some(value.as_ref()),
+                Some(upd_tags.as_slice()),
+                None,
+            )
+            .await?;
+
+        self.fetch_key(name, false)
+            .await?
+            .ok_or_else(|| err_msg!(Unexpected, "Rotated key entry not found"))
+    }
+
+    /// Remove an existing key from the store, along with any prior versions
+    pub async fn remove_key(&mut self, name: &str) -> Result<(), Error> {
+        self.0
             .update(
                 EntryKind::Kms,
                 EntryOperation::Remove,
@@ -483,7 +1108,15 @@ impl Session {
                 None,
                 None,
             )
-            .await?)
+            .await?;
+        self.0
+            .remove_all(
+                Some(EntryKind::Kms),
+                Some(KmsCategory::CryptoKeyVersion.as_str()),
+                Some(TagFilter::is_eq("name", name)),
+            )
+            .await?;
+        Ok(())
     }
 
     /// Replace the metadata and tags on an existing key in the store
@@ -546,3 +1179,19 @@ impl Session {
         Ok(self.0.close(false).await?)
     }
 }
+
+fn key_tags(key: &LocalKey) -> Result<Vec<EntryTag>, Error> {
+    let mut tags = Vec::with_capacity(10);
+    let alg = key.algorithm().as_str();
+    if !alg.is_empty() {
+        tags.push(EntryTag::Encrypted("alg".to_string(), alg.to_string()));
+    }
+    for thumb in key.to_jwk_thumbprints()? {
+        tags.push(EntryTag::Encrypted("thumb".to_string(), thumb));
+    }
+    Ok(tags)
+}
+
+fn versioned_key_name(name: &str, version: u32) -> String {
+    format!("{}@{}", name, version)
+}
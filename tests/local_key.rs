@@ -1,6 +1,21 @@
 #![allow(clippy::bool_assert_comparison)]
 
-use aries_askar::kms::{KeyAlg, LocalKey};
+use aries_askar::{
+    crypto::{
+        alg::{k256::K256EcdhOutput, AesTypes, Chacha20Types, EcCurves},
+        cose::{
+            cwt::{CwtClaims, CwtValidator},
+            CoseAlg,
+        },
+        hpke::{HpkeAead, HpkePsk},
+        kdf::hkdf::{HkdfHash, HkdfParams},
+        x509::{CertificateBuilder, SubjectAltName, X509Certificate},
+    },
+    kms::{
+        authcrypt, authcrypt_open, derive_bytes_ecdh_es, seal_for, seal_open, KeyAlg, KeyPolicy,
+        KeyUsage, LocalKey,
+    },
+};
 
 const ERR_CREATE_KEYPAIR: &str = "Error creating keypair";
 const ERR_SIGN: &str = "Error signing message";
@@ -50,3 +65,562 @@ pub fn localkey_sign_verify() {
         true
     );
 }
+
+#[cfg(feature = "rsa")]
+#[test]
+pub fn localkey_rsa_wrap_unwrap() {
+    use aries_askar::crypto::alg::RsaTypes;
+
+    let wrap_key = LocalKey::generate_with_rng(KeyAlg::Rsa(RsaTypes::Rsa2048), true)
+        .expect(ERR_CREATE_KEYPAIR);
+    let key = LocalKey::generate_with_rng(KeyAlg::Aes(AesTypes::A256Gcm), true)
+        .expect(ERR_CREATE_KEYPAIR);
+
+    let wrapped = wrap_key.wrap_key(&key, &[]).expect("Error wrapping key");
+    assert!(wrapped.nonce().is_empty());
+    let unwrapped = wrap_key
+        .unwrap_key(KeyAlg::Aes(AesTypes::A256Gcm), &wrapped, &[])
+        .expect("Error unwrapping key");
+    assert_eq!(
+        unwrapped.to_secret_bytes().unwrap(),
+        key.to_secret_bytes().unwrap()
+    );
+}
+
+#[test]
+pub fn localkey_hpke_seal_open() {
+    for alg in [KeyAlg::X25519, KeyAlg::EcCurve(EcCurves::Secp256r1)] {
+        let recip = LocalKey::generate_with_rng(alg, true).expect(ERR_CREATE_KEYPAIR);
+        let sender = LocalKey::generate_with_rng(alg, true).expect(ERR_CREATE_KEYPAIR);
+        let psk = HpkePsk {
+            psk: b"pre-shared key",
+            psk_id: b"psk id",
+        };
+
+        let (enc, ciphertext) = recip
+            .hpke_seal(
+                HpkeAead::Aes256Gcm,
+                b"info",
+                b"aad",
+                b"message",
+                Some(psk),
+                Some(&sender),
+            )
+            .expect("Error sealing message");
+        let message = recip
+            .hpke_open(
+                HpkeAead::Aes256Gcm,
+                &enc,
+                b"info",
+                b"aad",
+                &ciphertext,
+                Some(psk),
+                Some(&sender),
+            )
+            .expect("Error opening message");
+        assert_eq!(&message[..], b"message");
+        assert!(recip
+            .hpke_open(
+                HpkeAead::Aes256Gcm,
+                &enc,
+                b"info",
+                b"aad",
+                &ciphertext,
+                Some(psk),
+                None,
+            )
+            .is_err());
+    }
+}
+
+#[test]
+pub fn localkey_cwt_issue_verify() {
+    let keypair = LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp256r1), true)
+        .expect(ERR_CREATE_KEYPAIR);
+    let public = LocalKey::from_cose_key(&keypair.to_cose_key_public().unwrap())
+        .expect("Error importing COSE_Key");
+    let claims = CwtClaims {
+        iss: Some("issuer".into()),
+        exp: Some(2000),
+        ..Default::default()
+    };
+    let token = claims
+        .sign(&keypair, CoseAlg::ES256, Some(&b"key-1"[..]))
+        .expect("Error issuing CWT");
+    let verified = CwtValidator::new(1000)
+        .issuer("issuer")
+        .require_exp()
+        .verify(&token, &public)
+        .expect("Error verifying CWT");
+    assert_eq!(verified, claims);
+    assert!(CwtValidator::new(2000).verify(&token, &public).is_err());
+}
+
+#[test]
+pub fn localkey_pkcs8_round_trip() {
+    let keypair = LocalKey::generate_with_rng(KeyAlg::Ed25519, true).expect(ERR_CREATE_KEYPAIR);
+    let pem = keypair.to_pkcs8_pem(None).expect("Error exporting PKCS#8");
+    let loaded =
+        LocalKey::from_pkcs8_pem(pem.as_opt_str().unwrap(), None).expect("Error importing PKCS#8");
+    assert_eq!(loaded.algorithm(), KeyAlg::Ed25519);
+    assert_eq!(
+        loaded.to_secret_bytes().unwrap(),
+        keypair.to_secret_bytes().unwrap()
+    );
+
+    let der = keypair
+        .to_pkcs8_der(Some(b"passphrase"))
+        .expect("Error exporting encrypted PKCS#8");
+    let loaded =
+        LocalKey::from_pkcs8_der(&der, Some(b"passphrase")).expect("Error importing PKCS#8");
+    assert_eq!(
+        loaded.to_secret_bytes().unwrap(),
+        keypair.to_secret_bytes().unwrap()
+    );
+    assert!(LocalKey::from_pkcs8_der(&der, Some(b"other")).is_err());
+
+    let public = LocalKey::from_public_key_pem(&keypair.to_public_key_pem().unwrap())
+        .expect("Error importing public key");
+    let sig = keypair.sign_message(b"message", None).expect(ERR_SIGN);
+    assert!(public
+        .verify_signature(b"message", &sig, None)
+        .expect(ERR_VERIFY));
+}
+
+#[test]
+pub fn localkey_multikey_round_trip() {
+    let keypair = LocalKey::generate_with_rng(KeyAlg::Ed25519, true).expect(ERR_CREATE_KEYPAIR);
+    let multikey = keypair.to_multikey().expect("Error encoding multikey");
+    assert!(multikey.starts_with("z6Mk"));
+    let public = LocalKey::from_multikey(&multikey).expect("Error decoding multikey");
+    assert_eq!(public.algorithm(), KeyAlg::Ed25519);
+    assert_eq!(
+        public.to_public_bytes().unwrap(),
+        keypair.to_public_bytes().unwrap()
+    );
+}
+
+#[test]
+pub fn localkey_x509_self_signed() {
+    let keypair = LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp256r1), true)
+        .expect(ERR_CREATE_KEYPAIR);
+    let pem = CertificateBuilder::new("CN=wallet.example")
+        .expect("Error creating certificate builder")
+        .alt_name(SubjectAltName::DnsName("wallet.example".into()))
+        .validity(1700000000, 1800000000)
+        .self_signed(&keypair)
+        .expect("Error creating certificate")
+        .to_pem()
+        .unwrap();
+
+    let cert = X509Certificate::from_pem(&pem).expect("Error parsing certificate");
+    assert_eq!(cert.subject(), "CN=wallet.example");
+    let public = cert
+        .public_key::<LocalKey>()
+        .expect("Error loading certificate public key");
+    assert_eq!(
+        public.to_public_bytes().unwrap(),
+        keypair.to_public_bytes().unwrap()
+    );
+    assert!(cert.verify_signature(&public).unwrap());
+
+    let csr = CertificateBuilder::new("CN=wallet.example")
+        .unwrap()
+        .certificate_request_pem(&keypair)
+        .expect("Error creating certificate request");
+    assert!(csr.starts_with("-----BEGIN CERTIFICATE REQUEST-----"));
+}
+
+#[test]
+pub fn localkey_derive_hkdf() {
+    let key = LocalKey::generate_with_rng(KeyAlg::Aes(AesTypes::A256Gcm), false)
+        .expect("Error creating key");
+    let params = HkdfParams::new(Some(b"salt"), b"encryption");
+    let derived = key
+        .derive_key(KeyAlg::Chacha20(Chacha20Types::C20P), params)
+        .expect("Error deriving key");
+    assert_eq!(derived.algorithm(), KeyAlg::Chacha20(Chacha20Types::C20P));
+    let again = key
+        .derive_key(KeyAlg::Chacha20(Chacha20Types::C20P), params)
+        .expect("Error deriving key");
+    assert_eq!(
+        derived.to_secret_bytes().unwrap(),
+        again.to_secret_bytes().unwrap()
+    );
+
+    let other = key
+        .derive_key(
+            KeyAlg::Chacha20(Chacha20Types::C20P),
+            params.with_hash(HkdfHash::Sha512),
+        )
+        .expect("Error deriving key");
+    assert_ne!(
+        derived.to_secret_bytes().unwrap(),
+        other.to_secret_bytes().unwrap()
+    );
+
+    let keypair = LocalKey::generate_with_rng(KeyAlg::Ed25519, false).expect(ERR_CREATE_KEYPAIR);
+    assert!(keypair
+        .derive_key(KeyAlg::Aes(AesTypes::A256Gcm), params)
+        .is_err());
+}
+
+#[test]
+pub fn localkey_mac() {
+    let key = LocalKey::generate_with_rng(KeyAlg::Aes(AesTypes::A256Kw), false)
+        .expect("Error creating key");
+    for mac_type in ["aes-cmac", "kmac128", "kmac256"] {
+        let tag = key
+            .create_mac(b"message", mac_type, b"")
+            .expect("Error computing MAC");
+        assert!(key.verify_mac(b"message", &tag, mac_type, b"").unwrap());
+        assert!(!key.verify_mac(b"other", &tag, mac_type, b"").unwrap());
+    }
+
+    let key = LocalKey::generate_with_rng(KeyAlg::Chacha20(Chacha20Types::C20P), false)
+        .expect("Error creating key");
+    let tag = key
+        .create_mac(b"message", "kmac256", b"KDF")
+        .expect("Error computing MAC");
+    assert_eq!(tag.len(), 64);
+    assert!(!key.verify_mac(b"message", &tag, "kmac256", b"").unwrap());
+    assert!(key.create_mac(b"message", "aes-cmac", b"").is_err());
+}
+
+#[test]
+pub fn localkey_from_seed_sha3() {
+    let seed = b"testseed000000000000000000000001";
+    for method in ["sha3-256", "sha3-512", "shake128", "shake256"] {
+        let key =
+            LocalKey::from_seed(KeyAlg::Ed25519, seed, Some(method)).expect(ERR_CREATE_KEYPAIR);
+        let check =
+            LocalKey::from_seed(KeyAlg::Ed25519, seed, Some(method)).expect(ERR_CREATE_KEYPAIR);
+        assert_eq!(
+            key.to_public_bytes().unwrap(),
+            check.to_public_bytes().unwrap()
+        );
+        let default = LocalKey::from_seed(KeyAlg::Ed25519, seed, None).expect(ERR_CREATE_KEYPAIR);
+        assert_ne!(
+            key.to_public_bytes().unwrap(),
+            default.to_public_bytes().unwrap()
+        );
+    }
+    assert!(LocalKey::from_seed(KeyAlg::Ed25519, seed, Some("keccak")).is_err());
+
+    let key = LocalKey::generate_with_rng(KeyAlg::Aes(AesTypes::A256Gcm), false)
+        .expect("Error creating key");
+    let params = HkdfParams::new(None, b"info").with_hash(HkdfHash::Sha3_256);
+    let derived = key
+        .derive_key(KeyAlg::Aes(AesTypes::A256Gcm), params)
+        .expect("Error deriving key");
+    let check = key
+        .derive_key(KeyAlg::Aes(AesTypes::A256Gcm), params)
+        .expect("Error deriving key");
+    assert_eq!(
+        derived.to_secret_bytes().unwrap(),
+        check.to_secret_bytes().unwrap()
+    );
+}
+
+#[test]
+pub fn localkey_from_mnemonic() {
+    let phrase = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+    let key =
+        LocalKey::from_mnemonic(KeyAlg::Ed25519, phrase, "TREZOR", None).expect(ERR_CREATE_KEYPAIR);
+    let check =
+        LocalKey::from_mnemonic(KeyAlg::Ed25519, phrase, "TREZOR", None).expect(ERR_CREATE_KEYPAIR);
+    assert_eq!(
+        key.to_public_bytes().unwrap(),
+        check.to_public_bytes().unwrap()
+    );
+    let other =
+        LocalKey::from_mnemonic(KeyAlg::Ed25519, phrase, "", None).expect(ERR_CREATE_KEYPAIR);
+    assert_ne!(
+        key.to_public_bytes().unwrap(),
+        other.to_public_bytes().unwrap()
+    );
+    assert!(LocalKey::from_mnemonic(KeyAlg::Ed25519, "legal winner", "", None).is_err());
+}
+
+#[test]
+pub fn localkey_derive_child() {
+    let master = LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp256k1), false)
+        .expect(ERR_CREATE_KEYPAIR);
+    let child = master
+        .derive_child("m/44'/0'/0'/0/1")
+        .expect("Error deriving key");
+    let check = master
+        .derive_child("m/44'/0'/0'/0/1")
+        .expect("Error deriving key");
+    assert_eq!(child.algorithm(), KeyAlg::EcCurve(EcCurves::Secp256k1));
+    assert_eq!(
+        child.to_public_bytes().unwrap(),
+        check.to_public_bytes().unwrap()
+    );
+    let other = master
+        .derive_child("m/44'/0'/0'/0/2")
+        .expect("Error deriving key");
+    assert_ne!(
+        child.to_public_bytes().unwrap(),
+        other.to_public_bytes().unwrap()
+    );
+    let xprv = master.to_extended_key("m/44'/0'/0'/0/1", true).unwrap();
+    assert!(xprv.starts_with("xprv"));
+    let xpub = master.to_extended_key("m/44'/0'/0'", false).unwrap();
+    assert!(xpub.starts_with("xpub"));
+
+    let master = LocalKey::generate_with_rng(KeyAlg::Ed25519, false).expect(ERR_CREATE_KEYPAIR);
+    let child = master.derive_child("m/0'/1'").expect("Error deriving key");
+    assert_eq!(child.algorithm(), KeyAlg::Ed25519);
+    assert!(master.derive_child("m/0'/1").is_err());
+    assert!(master.to_extended_key("m/0'", true).is_err());
+}
+
+#[test]
+pub fn localkey_policy() {
+    let issuer = LocalKey::generate_with_rng(KeyAlg::Ed25519, false)
+        .expect(ERR_CREATE_KEYPAIR)
+        .with_policy(KeyPolicy::new([KeyUsage::Sign]).with_max_uses(1))
+        .unwrap();
+    let sig = issuer.sign_message(b"message", None).expect(ERR_SIGN);
+    assert!(issuer.sign_message(b"message", None).is_err());
+    assert!(issuer
+        .verify_signature(b"message", &sig, None)
+        .expect(ERR_VERIFY));
+    assert!(issuer.to_secret_bytes().is_err());
+    assert!(issuer.with_policy(KeyPolicy::default()).is_err());
+
+    // the policy follows the key through conversion
+    let issuer = LocalKey::generate_with_rng(KeyAlg::Ed25519, false)
+        .expect(ERR_CREATE_KEYPAIR)
+        .with_policy(KeyPolicy::new([KeyUsage::Sign]))
+        .unwrap();
+    let converted = issuer.convert_key(KeyAlg::X25519).unwrap();
+    let peer = LocalKey::generate_with_rng(KeyAlg::X25519, true).expect(ERR_CREATE_KEYPAIR);
+    assert!(converted
+        .to_key_exchange(KeyAlg::Chacha20(Chacha20Types::C20P), &peer)
+        .is_err());
+    peer.to_key_exchange(KeyAlg::Chacha20(Chacha20Types::C20P), &converted)
+        .unwrap();
+
+    let expired = LocalKey::generate_with_rng(KeyAlg::Ed25519, false)
+        .expect(ERR_CREATE_KEYPAIR)
+        .with_policy(KeyPolicy::default().with_not_after(0))
+        .unwrap();
+    assert!(expired.sign_message(b"message", None).is_err());
+}
+
+#[test]
+pub fn localkey_public_bytes_uncompressed() {
+    for (curve, length) in [
+        (EcCurves::Secp256r1, 65),
+        (EcCurves::Secp256k1, 65),
+        (EcCurves::Secp384r1, 97),
+    ] {
+        let alg = KeyAlg::EcCurve(curve);
+        let keypair = LocalKey::generate_with_rng(alg, true).expect(ERR_CREATE_KEYPAIR);
+        let compressed = keypair.to_public_bytes().unwrap();
+        let uncompressed = keypair.to_public_bytes_uncompressed().unwrap();
+        assert_eq!(uncompressed.len(), length);
+        for public in [&compressed[..], &uncompressed[..], &uncompressed[1..]] {
+            let load = LocalKey::from_public_bytes(alg, public).unwrap();
+            assert_eq!(load.to_public_bytes().unwrap(), compressed);
+        }
+    }
+    let keypair = LocalKey::generate_with_rng(KeyAlg::Ed25519, true).expect(ERR_CREATE_KEYPAIR);
+    assert!(keypair.to_public_bytes_uncompressed().is_err());
+}
+
+#[test]
+pub fn localkey_k256_key_exchange_output() {
+    let alg = KeyAlg::EcCurve(EcCurves::Secp256k1);
+    let kp1 = LocalKey::generate_with_rng(alg, true).expect(ERR_CREATE_KEYPAIR);
+    let kp2 = LocalKey::generate_with_rng(alg, true).expect(ERR_CREATE_KEYPAIR);
+    let point = kp1
+        .to_k256_key_exchange_bytes(&kp2, K256EcdhOutput::Uncompressed)
+        .unwrap();
+    assert_eq!(point.len(), 65);
+    let x = kp2
+        .to_k256_key_exchange_bytes(&kp1, K256EcdhOutput::XCoordinate)
+        .unwrap();
+    assert_eq!(&point[1..33], &x[..]);
+    let hashed = kp1
+        .to_k256_key_exchange_bytes(&kp2, K256EcdhOutput::Sha256Compressed)
+        .unwrap();
+    assert_eq!(
+        hashed,
+        kp2.to_k256_key_exchange_bytes(&kp1, K256EcdhOutput::Sha256Compressed)
+            .unwrap()
+    );
+
+    let other = LocalKey::generate_with_rng(KeyAlg::X25519, true).expect(ERR_CREATE_KEYPAIR);
+    assert!(kp1
+        .to_k256_key_exchange_bytes(&other, K256EcdhOutput::Uncompressed)
+        .is_err());
+}
+
+#[cfg(feature = "unauthenticated")]
+#[test]
+pub fn localkey_aes_unauthenticated() {
+    let key = LocalKey::generate_with_rng(KeyAlg::Aes(AesTypes::A256Gcm), true)
+        .expect(ERR_CREATE_KEYPAIR);
+    let message = b"legacy message";
+    let iv = [1u8; 16];
+
+    let ctext = key.aes_ctr_unauthenticated(message, &iv).unwrap();
+    assert_eq!(ctext.len(), message.len());
+    let ptext = key.aes_ctr_unauthenticated(&ctext, &iv).unwrap();
+    assert_eq!(ptext, &message[..]);
+
+    let ctext = key.aes_cbc_encrypt_unauthenticated(message, &iv).unwrap();
+    assert_eq!(ctext.len(), 16);
+    let ptext = key.aes_cbc_decrypt_unauthenticated(&ctext, &iv).unwrap();
+    assert_eq!(ptext, &message[..]);
+
+    let key = LocalKey::generate_with_rng(KeyAlg::Aes(AesTypes::A128CbcHs256), true)
+        .expect(ERR_CREATE_KEYPAIR);
+    assert!(key.aes_ctr_unauthenticated(message, &iv).is_err());
+}
+
+#[cfg(feature = "unauthenticated")]
+#[test]
+pub fn localkey_chacha20_keystream_unauthenticated() {
+    let key = LocalKey::generate_with_rng(KeyAlg::Chacha20(Chacha20Types::C20P), true)
+        .expect(ERR_CREATE_KEYPAIR);
+    let message = b"legacy record";
+    let nonce = key.aead_random_nonce().unwrap();
+
+    // the keystream at block 1 matches the ChaCha20-Poly1305 ciphertext
+    let enc = key.aead_encrypt(message, &nonce, b"").unwrap();
+    let ctext = key
+        .chacha20_keystream_unauthenticated(message, &nonce, 1)
+        .unwrap();
+    assert_eq!(&ctext[..], enc.ciphertext());
+    let ptext = key
+        .chacha20_keystream_unauthenticated(&ctext, &nonce, 1)
+        .unwrap();
+    assert_eq!(ptext, &message[..]);
+
+    let key = LocalKey::generate_with_rng(KeyAlg::Aes(AesTypes::A256Gcm), true)
+        .expect(ERR_CREATE_KEYPAIR);
+    assert!(key
+        .chacha20_keystream_unauthenticated(message, &nonce, 1)
+        .is_err());
+}
+
+#[test]
+pub fn localkey_seal_for_recipients() {
+    let alice = LocalKey::generate_with_rng(KeyAlg::X25519, false).expect(ERR_CREATE_KEYPAIR);
+    let bob = LocalKey::generate_with_rng(KeyAlg::EcCurve(EcCurves::Secp256r1), false)
+        .expect(ERR_CREATE_KEYPAIR);
+    let carol = LocalKey::generate_with_rng(KeyAlg::X25519, false).expect(ERR_CREATE_KEYPAIR);
+    let message = b"sealed message";
+
+    let alice_pub = LocalKey::from_jwk(&alice.to_jwk_public(None).unwrap()).unwrap();
+    let bob_pub = LocalKey::from_jwk(&bob.to_jwk_public(None).unwrap()).unwrap();
+    let envelope = seal_for(&[alice_pub, bob_pub], message).unwrap();
+    assert_eq!(seal_open(&alice, &envelope).unwrap(), &message[..]);
+    assert_eq!(seal_open(&bob, &envelope).unwrap(), &message[..]);
+    assert!(seal_open(&carol, &envelope).is_err());
+
+    // a single recipient produces a compact envelope
+    let envelope = seal_for(std::slice::from_ref(&carol), message).unwrap();
+    assert_eq!(envelope.split('.').count(), 5);
+    assert_eq!(seal_open(&carol, &envelope).unwrap(), &message[..]);
+    assert!(seal_for(&[], message).is_err());
+}
+
+#[test]
+pub fn localkey_authcrypt() {
+    let alice = LocalKey::generate_with_rng(KeyAlg::X25519, false).expect(ERR_CREATE_KEYPAIR);
+    let bob = LocalKey::generate_with_rng(KeyAlg::X25519, false).expect(ERR_CREATE_KEYPAIR);
+    let carol = LocalKey::generate_with_rng(KeyAlg::X25519, false).expect(ERR_CREATE_KEYPAIR);
+    let message = b"authenticated message";
+    let public = |key: &LocalKey| LocalKey::from_jwk(&key.to_jwk_public(None).unwrap()).unwrap();
+
+    let (bob_pub, carol_pub) = (public(&bob), public(&carol));
+    let envelope = authcrypt(
+        &alice,
+        "did:example:alice#key-1",
+        &[
+            ("did:example:bob#key-1", &bob_pub),
+            ("did:example:carol#key-1", &carol_pub),
+        ],
+        message,
+    )
+    .unwrap();
+    for (kid, key) in [
+        ("did:example:bob#key-1", &bob),
+        ("did:example:carol#key-1", &carol),
+    ] {
+        let (skid, plaintext) = authcrypt_open(key, kid, &envelope, |skid| {
+            assert_eq!(skid, "did:example:alice#key-1");
+            Ok(public(&alice))
+        })
+        .unwrap();
+        assert_eq!(skid, "did:example:alice#key-1");
+        assert_eq!(plaintext, &message[..]);
+    }
+
+    // the wrong sender key fails to decrypt
+    assert!(
+        authcrypt_open(&bob, "did:example:bob#key-1", &envelope, |_| Ok(public(
+            &carol
+        )))
+        .is_err()
+    );
+    // anonymous envelopes are rejected
+    let sealed = seal_for(std::slice::from_ref(&bob_pub), message).unwrap();
+    assert!(
+        authcrypt_open(&bob, "did:example:bob#key-1", &sealed, |_| Ok(public(
+            &alice
+        )))
+        .is_err()
+    );
+}
+
+#[test]
+pub fn localkey_deterministic_encrypt() {
+    let key = LocalKey::generate_with_rng(KeyAlg::Aes(AesTypes::A128Siv), false)
+        .expect(ERR_CREATE_KEYPAIR);
+    let enc = key.encrypt_deterministic(b"value", b"field").unwrap();
+    assert_eq!(enc.len(), 5 + 16);
+    assert_eq!(key.encrypt_deterministic(b"value", b"field").unwrap(), enc);
+    assert_ne!(key.encrypt_deterministic(b"value", b"other").unwrap(), enc);
+    assert_eq!(
+        key.decrypt_deterministic(&enc, b"field").unwrap(),
+        &b"value"[..]
+    );
+    assert!(key.decrypt_deterministic(&enc, b"other").is_err());
+
+    let key = LocalKey::generate_with_rng(KeyAlg::Aes(AesTypes::A256Gcm), false)
+        .expect(ERR_CREATE_KEYPAIR);
+    assert!(key.encrypt_deterministic(b"value", b"field").is_err());
+}
+
+#[test]
+pub fn localkey_derive_bytes_ecdh_es() {
+    // from RFC 8037 Appendix A.6
+    let bob_pk = LocalKey::from_jwk(
+        r#"{"kty":"OKP","crv":"X25519","kid":"Bob",
+        "x":"3p7bfXt9wbTTW2HC7OQ1Nz-DQ8hbeGdNrfx-FG-IK08"}"#,
+    )
+    .unwrap();
+    let ephem_sk = LocalKey::from_jwk(
+        r#"{"kty":"OKP","crv":"X25519",
+        "d":"dwdtCnMYpX08FsFyUbJmRd9ML4frwJkqsXf7pR25LCo",
+        "x":"hSDwCYkwp1R0i33ctD73Wg2_Og0mOBr066SpjqqbTmo"}"#,
+    )
+    .unwrap();
+    let expected: [u8; 32] = [
+        47, 54, 54, 145, 141, 219, 87, 254, 11, 53, 105, 17, 63, 25, 196, 182, 197, 24, 194, 132,
+        63, 137, 48, 240, 93, 178, 92, 213, 93, 238, 83, 193,
+    ];
+    let output =
+        derive_bytes_ecdh_es(&ephem_sk, &bob_pk, b"A256GCM", b"Alice", b"Bob", 32, false).unwrap();
+    assert_eq!(output.as_ref(), &expected[..]);
+    let longer =
+        derive_bytes_ecdh_es(&ephem_sk, &bob_pk, b"A256GCM", b"Alice", b"Bob", 48, false).unwrap();
+    assert_eq!(longer.len(), 48);
+    assert!(derive_bytes_ecdh_es(&ephem_sk, &bob_pk, b"A256GCM", b"", b"", 0, false).is_err());
+}
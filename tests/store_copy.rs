@@ -1,7 +1,7 @@
 use aries_askar::{
     future::block_on,
     kms::{KeyAlg, LocalKey},
-    Store, StoreKeyMethod,
+    ErrorKind, Store, StoreKeyMethod,
 };
 
 const ERR_RAW_KEY: &str = "Error creating raw store key";
@@ -76,3 +76,68 @@ fn store_copy() {
         db.close().await.expect(ERR_CLOSE);
     })
 }
+
+#[test]
+fn profile_copy_rename() {
+    block_on(async {
+        let pass_key = Store::new_raw_key(None).expect(ERR_RAW_KEY);
+        let db = Store::provision(
+            "sqlite://:memory:",
+            StoreKeyMethod::RawKey,
+            pass_key,
+            None,
+            true,
+        )
+        .await
+        .expect(ERR_OPEN);
+
+        let source = db
+            .create_profile(Some("source".to_string()))
+            .await
+            .expect("Error creating profile");
+        let mut conn = db.session(Some(source.clone())).await.expect(ERR_SESSION);
+        conn.insert("testcat", "testrow", b"testval", None, None)
+            .await
+            .expect("Error inserting row");
+        drop(conn);
+
+        db.copy_profile(source.clone(), "copy".to_string())
+            .await
+            .expect("Error copying profile");
+        let err = db
+            .copy_profile(source.clone(), "copy".to_string())
+            .await
+            .expect_err("Expected duplicate profile error");
+        assert_eq!(err.kind(), ErrorKind::Duplicate);
+
+        let renamed = db
+            .rename_profile("copy".to_string(), "renamed".to_string())
+            .await
+            .expect("Error renaming profile");
+        assert!(renamed);
+        let err = db
+            .rename_profile("renamed".to_string(), source.clone())
+            .await
+            .expect_err("Expected duplicate profile error");
+        assert_eq!(err.kind(), ErrorKind::Duplicate);
+        assert!(!db
+            .rename_profile("copy".to_string(), "other".to_string())
+            .await
+            .expect("Error renaming profile"));
+
+        let mut conn = db
+            .session(Some("renamed".to_string()))
+            .await
+            .expect(ERR_SESSION);
+        let found = conn
+            .fetch("testcat", "testrow", false)
+            .await
+            .expect("Error loading row")
+            .expect(ERR_REQ_ROW);
+        assert_eq!(found.value, &b"testval"[..]);
+        drop(conn);
+        assert!(db.session(Some("copy".to_string())).await.is_err());
+
+        db.close().await.expect(ERR_CLOSE);
+    })
+}
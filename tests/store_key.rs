@@ -1,6 +1,6 @@
 use aries_askar::{
     future::block_on,
-    kms::{KeyAlg, LocalKey},
+    kms::{KeyAlg, KeyPolicy, KeyUsage, LocalKey},
     Store, StoreKeyMethod,
 };
 
@@ -50,3 +50,120 @@ fn keypair_create_fetch() {
         db.close().await.expect(ERR_CLOSE);
     })
 }
+
+#[test]
+fn keypair_policy() {
+    block_on(async {
+        let pass_key = Store::new_raw_key(None).expect(ERR_RAW_KEY);
+        let db = Store::provision(
+            "sqlite://:memory:",
+            StoreKeyMethod::RawKey,
+            pass_key,
+            None,
+            true,
+        )
+        .await
+        .expect(ERR_OPEN);
+
+        let policy = KeyPolicy::new([KeyUsage::Sign]);
+        let keypair = LocalKey::generate_with_rng(KeyAlg::Ed25519, false)
+            .expect("Error creating keypair")
+            .with_policy(policy.clone())
+            .unwrap();
+
+        let mut conn = db.session(None).await.expect(ERR_SESSION);
+
+        let key_name = "testkey";
+        conn.insert_key(key_name, &keypair, None, None, None, None)
+            .await
+            .expect("Error inserting key");
+
+        let found = conn
+            .fetch_key(key_name, false)
+            .await
+            .expect("Error fetching key")
+            .expect(ERR_REQ_ROW);
+        assert_eq!(found.policy(), Some(&policy));
+        let loaded = found.load_local_key().expect("Error loading key");
+        loaded
+            .sign_message(b"message", None)
+            .expect("Error signing message");
+        assert!(loaded.to_key_exchange(KeyAlg::X25519, &keypair).is_err());
+
+        drop(conn);
+        db.close().await.expect(ERR_CLOSE);
+    })
+}
+
+#[test]
+fn keypair_rotate() {
+    block_on(async {
+        let pass_key = Store::new_raw_key(None).expect(ERR_RAW_KEY);
+        let db = Store::provision(
+            "sqlite://:memory:",
+            StoreKeyMethod::RawKey,
+            pass_key,
+            None,
+            true,
+        )
+        .await
+        .expect(ERR_OPEN);
+
+        let keypair =
+            LocalKey::generate_with_rng(KeyAlg::Ed25519, false).expect("Error creating keypair");
+        let sig = keypair
+            .sign_message(b"message", None)
+            .expect("Error signing message");
+
+        let mut conn = db.transaction(None).await.expect(ERR_SESSION);
+
+        let key_name = "testkey";
+        conn.insert_key(key_name, &keypair, Some("meta"), None, None, None)
+            .await
+            .expect("Error inserting key");
+
+        let rotated = conn.rotate_key(key_name).await.expect("Error rotating key");
+        assert_eq!(rotated.version(), 2);
+        assert_eq!(rotated.metadata(), Some("meta"));
+
+        let found = conn
+            .fetch_key(key_name, false)
+            .await
+            .expect("Error fetching key")
+            .expect(ERR_REQ_ROW);
+        assert_eq!(found, rotated);
+        assert!(!found
+            .load_local_key()
+            .expect("Error loading key")
+            .verify_signature(b"message", &sig, None)
+            .expect("Error verifying signature"));
+
+        let prev = conn
+            .fetch_key_version(key_name, 1, false)
+            .await
+            .expect("Error fetching key")
+            .expect(ERR_REQ_ROW);
+        assert_eq!(prev.name(), key_name);
+        assert_eq!(prev.version(), 1);
+        assert!(prev
+            .load_local_key()
+            .expect("Error loading key")
+            .verify_signature(b"message", &sig, None)
+            .expect("Error verifying signature"));
+        assert!(conn
+            .fetch_key_version(key_name, 2, false)
+            .await
+            .expect("Error fetching key")
+            .is_some());
+
+        conn.remove_key(key_name).await.expect("Error removing key");
+        assert!(conn
+            .fetch_key_version(key_name, 1, false)
+            .await
+            .expect("Error fetching key")
+            .is_none());
+
+        conn.commit().await.expect("Error committing transaction");
+        db.close().await.expect(ERR_CLOSE);
+    })
+}
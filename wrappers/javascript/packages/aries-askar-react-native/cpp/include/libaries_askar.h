@@ -27,6 +27,7 @@ enum ErrorCode
   NotFound = 6,
   Unexpected = 7,
   Unsupported = 8,
+  Conflict = 9,
   Custom = 100,
 };
 #ifndef __cplusplus
@@ -2,4 +2,5 @@ export enum EntryOperation {
   Insert,
   Replace,
   Remove,
+  InsertOrReplace,
 }
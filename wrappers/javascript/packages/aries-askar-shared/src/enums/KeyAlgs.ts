@@ -6,17 +6,36 @@ export enum KeyAlgs {
   AesA128CbcHs256 = 'a128cbchs256',
   AesA256CbcHs512 = 'a256cbchs512',
   AesA128Kw = 'a128kw',
+  AesA192Kw = 'a192kw',
   AesA256Kw = 'a256kw',
+  AesA128GcmSiv = 'a128gcmsiv',
+  AesA256GcmSiv = 'a256gcmsiv',
+  AesA128Siv = 'a128siv',
+  AesA256Siv = 'a256siv',
   Bls12381G1 = 'bls12381g1',
   Bls12381G2 = 'bls12381g2',
   Bls12381G1G2 = 'bls12381g1g2',
   Chacha20C20P = 'c20p',
   Chacha20XC20P = 'xc20p',
   Ed25519 = 'ed25519',
+  HmacHs256 = 'hs256',
+  HmacHs384 = 'hs384',
+  HmacHs512 = 'hs512',
   X25519 = 'x25519',
+  X25519MlKem768 = 'x25519mlkem768',
+  Ed448 = 'ed448',
+  X448 = 'x448',
   EcSecp256k1 = 'k256',
   EcSecp256r1 = 'p256',
   EcSecp384r1 = 'p384',
+  Rsa2048 = 'rsa2048',
+  Rsa3072 = 'rsa3072',
+  Rsa4096 = 'rsa4096',
+  MlDsa44 = 'mldsa44',
+  MlDsa65 = 'mldsa65',
+  MlDsa87 = 'mldsa87',
+  SlhDsaSha2_128s = 'slhdsasha2128s',
+  SlhDsaSha2_128f = 'slhdsasha2128f',
 }
 
 export const keyAlgFromString = (alg: string): KeyAlgs => {
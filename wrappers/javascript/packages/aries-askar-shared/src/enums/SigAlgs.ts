@@ -1,6 +1,17 @@
 export enum SigAlgs {
   EdDSA = 'eddsa',
+  Ed25519ph = 'ed25519ph',
   ES256 = 'es256',
   ES256K = 'es256k',
+  ES256KR = 'es256kr',
+  BlsBasic = 'blsbasic',
+  BlsAug = 'blsaug',
+  BlsPop = 'blspop',
   ES384 = 'es384',
+  Ed448 = 'ed448',
+  HS256 = 'hs256',
+  HS384 = 'hs384',
+  HS512 = 'hs512',
+  RS256 = 'rs256',
+  PS256 = 'ps256',
 }
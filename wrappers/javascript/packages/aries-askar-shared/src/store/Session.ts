@@ -148,6 +148,33 @@ export class Session {
     })
   }
 
+  public async upsert({
+    category,
+    name,
+    expiryMs,
+    tags,
+    value,
+  }: {
+    category: string
+    name: string
+    value: string | Record<string, unknown>
+    tags?: Record<string, unknown>
+    expiryMs?: number
+  }) {
+    if (!this.handle) throw AriesAskarError.customError({ message: 'Cannot upsert with a closed session' })
+    const serializedValue = typeof value === 'string' ? value : JSON.stringify(value)
+
+    await ariesAskar.sessionUpdate({
+      value: Uint8Array.from(Buffer.from(serializedValue)),
+      expiryMs,
+      tags,
+      name,
+      category,
+      sessionHandle: this.handle,
+      operation: EntryOperation.InsertOrReplace,
+    })
+  }
+
   public async remove({ category, name }: { category: string; name: string }) {
     if (!this.handle) throw AriesAskarError.customError({ message: 'Cannot remove with a closed session' })
 
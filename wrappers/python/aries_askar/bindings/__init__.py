@@ -1027,6 +1027,40 @@ def key_derive_ecdh_es(
     return key
 
 
+def key_derive_ecdh_es_bytes(
+    ephem_key: LocalKeyHandle,
+    receiver_key: LocalKeyHandle,
+    alg_id: Union[bytes, str, ByteBuffer],
+    apu: Union[bytes, str, ByteBuffer],
+    apv: Union[bytes, str, ByteBuffer],
+    output_len: int,
+    receive: bool,
+) -> ByteBuffer:
+    buf = ByteBuffer()
+    invoke(
+        "askar_key_derive_ecdh_es_bytes",
+        (
+            LocalKeyHandle,
+            LocalKeyHandle,
+            FfiByteBuffer,
+            FfiByteBuffer,
+            FfiByteBuffer,
+            c_int64,
+            c_int8,
+            POINTER(ByteBuffer),
+        ),
+        ephem_key,
+        receiver_key,
+        alg_id,
+        apu,
+        apv,
+        output_len,
+        receive,
+        byref(buf),
+    )
+    return buf
+
+
 def key_derive_ecdh_1pu(
     key_alg: Union[str, KeyAlg],
     ephem_key: LocalKeyHandle,
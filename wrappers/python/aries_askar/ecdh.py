@@ -1,6 +1,11 @@
 from typing import Optional, Union
 
-from .bindings import Encrypted, key_derive_ecdh_es, key_derive_ecdh_1pu
+from .bindings import (
+    Encrypted,
+    key_derive_ecdh_es,
+    key_derive_ecdh_es_bytes,
+    key_derive_ecdh_1pu,
+)
 from .key import Key
 from .types import KeyAlg
 
@@ -37,6 +42,24 @@ class EcdhEs:
             )
         )
 
+    def derive_bytes(
+        self,
+        ephemeral_key: Union[dict, str, Key],
+        receiver_key: Union[dict, str, Key],
+        output_len: int,
+        *,
+        receive: bool = False,
+    ) -> bytes:
+        return bytes(
+            key_derive_ecdh_es_bytes(
+                _load_key(ephemeral_key)._handle,
+                _load_key(receiver_key)._handle,
+                *self._params,
+                output_len,
+                receive,
+            )
+        )
+
     def encrypt_direct(
         self,
         enc_alg: Union[str, KeyAlg],
@@ -13,6 +13,7 @@ class AskarErrorCode(IntEnum):
     NOT_FOUND = 6
     UNEXPECTED = 7
     UNSUPPORTED = 8
+    CONFLICT = 9
     WRAPPER = 99
     CUSTOM = 100
 
@@ -603,6 +603,30 @@ class Session:
             self._handle, EntryOperation.REPLACE, category, name, value, tags, expiry_ms
         )
 
+    async def upsert(
+        self,
+        category: str,
+        name: str,
+        value: Union[str, bytes] = None,
+        tags: dict = None,
+        expiry_ms: int = None,
+        value_json=None,
+    ):
+        """Insert a new record, or replace an existing record with the same name."""
+        if not self._handle:
+            raise AskarError(AskarErrorCode.WRAPPER, "Cannot update closed session")
+        if value is None and value_json is not None:
+            value = json.dumps(value_json)
+        await bindings.session_update(
+            self._handle,
+            EntryOperation.INSERT_OR_REPLACE,
+            category,
+            name,
+            value,
+            tags,
+            expiry_ms,
+        )
+
     async def remove(
         self,
         category: str,
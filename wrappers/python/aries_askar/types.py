@@ -8,17 +8,36 @@ class KeyAlg(Enum):
     A128CBC_HS256 = "a128cbchs256"
     A256CBC_HS512 = "a256cbchs512"
     A128KW = "a128kw"
+    A192KW = "a192kw"
     A256KW = "a256kw"
+    A128GCMSIV = "a128gcmsiv"
+    A256GCMSIV = "a256gcmsiv"
+    A128SIV = "a128siv"
+    A256SIV = "a256siv"
     BLS12_381_G1 = "bls12381g1"
     BLS12_381_G2 = "bls12381g2"
     BLS12_381_G1G2 = "bls12381g1g2"
     C20P = "c20p"
     XC20P = "xc20p"
     ED25519 = "ed25519"
+    HS256 = "hs256"
+    HS384 = "hs384"
+    HS512 = "hs512"
     X25519 = "x25519"
+    X25519_ML_KEM_768 = "x25519mlkem768"
+    ED448 = "ed448"
+    X448 = "x448"
     K256 = "k256"
     P256 = "p256"
     P384 = "p384"
+    RSA2048 = "rsa2048"
+    RSA3072 = "rsa3072"
+    RSA4096 = "rsa4096"
+    ML_DSA_44 = "mldsa44"
+    ML_DSA_65 = "mldsa65"
+    ML_DSA_87 = "mldsa87"
+    SLH_DSA_SHA2_128S = "slhdsasha2128s"
+    SLH_DSA_SHA2_128F = "slhdsasha2128f"
 
     @classmethod
     def from_key_alg(cls, alg: str) -> Optional["KeyAlg"]:
@@ -63,3 +82,4 @@ class EntryOperation(Enum):
     INSERT = 0
     REPLACE = 1
     REMOVE = 2
+    INSERT_OR_REPLACE = 3
@@ -50,6 +50,18 @@ def test_ecdh_es_direct():
     assert message_recv == message
 
 
+def test_ecdh_es_derive_bytes():
+    bob_key = Key.generate(KeyAlg.X25519)
+    ephem_key = Key.generate(KeyAlg.X25519)
+    derive = EcdhEs("A256GCM", "Alice", "Bob")
+    sent = derive.derive_bytes(ephem_key, bob_key.get_jwk_public(), 32)
+    recv = derive.derive_bytes(ephem_key.get_jwk_public(), bob_key, 32, receive=True)
+    assert len(sent) == 32
+    assert sent == recv
+    direct = derive._derive_key(KeyAlg.A256GCM, ephem_key, bob_key, False)
+    assert direct.get_secret_bytes() == sent
+
+
 def test_ecdh_es_wrapped():
     bob_key = Key.generate(KeyAlg.X25519)
     bob_jwk = bob_key.get_jwk_public()